@@ -15,52 +15,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let db_path = workspace_root.join("scraper.db");
     let db_url = format!("sqlite:{}", db_path.display());
     
-    // Create the database if it doesn't exist
-    if !Sqlite::database_exists(&db_url).await.unwrap_or(false) {
-        println!("Creating database at: {}", db_url);
-        Sqlite::create_database(&db_url).await?;
-    } else {
+    // Existing databases, including ones that predate migrations, are upgraded by the
+    // server on startup
+    if Sqlite::database_exists(&db_url).await.unwrap_or(false) {
         println!("Database already exists at: {}", db_url);
+        println!("Pending migrations are applied when the server starts.");
+        return Ok(());
     }
+    println!("Creating database at: {}", db_url);
+    Sqlite::create_database(&db_url).await?;
     
     // Connect to the database
     let pool = SqlitePool::connect(&db_url).await?;
     
-    // Create tables
-    println!("Creating tables...");
-    
-    // Create sites table
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS sites(
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            url TEXT NOT NULL UNIQUE,
-            interval_secs INTEGER NOT NULL,
-            style TEXT NOT NULL,
-            last_checked TEXT,
-            last_updated TEXT,
-            status TEXT
-         );"
-    ).execute(&pool).await?;
-    
-    // Create updates table
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS updates(
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            site_id INTEGER,
-            timestamp TEXT,
-            diff_hash TEXT,
-            content TEXT,
-            FOREIGN KEY(site_id) REFERENCES sites(id)
-        );"
-    ).execute(&pool).await?;
+    // Same migration files the server embeds, so the two can't drift
+    println!("Applying migrations...");
+    sqlx::migrate!("../scraper_backend/migrations/sqlite").run(&pool).await?;
     
     // Close the connection
     pool.close().await;
     
     println!("Database initialized successfully at: {}", db_url);
-    println!("You can now build and run the application with:");
-    println!("set DATABASE_URL=sqlite:scraper.db");
-    println!("cargo run");
+    println!("You can now run the application with:");
+    println!("cargo run -p scraper_backend");
     
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,91 @@
+-- Initial schema with native types; the indexes keep the per-site "newest N" queries
+-- cheap once many sites share one server.
+
+CREATE TABLE IF NOT EXISTS sites(
+    id BIGSERIAL PRIMARY KEY,
+    url TEXT NOT NULL UNIQUE,
+    interval_secs BIGINT NOT NULL,
+    style TEXT NOT NULL,
+    last_checked TIMESTAMPTZ,
+    last_updated TIMESTAMPTZ,
+    status TEXT,
+    snoozed_until TIMESTAMPTZ,
+    last_success TIMESTAMPTZ,
+    selector TEXT,
+    priority BIGINT NOT NULL DEFAULT 0,
+    retention_count BIGINT,
+    probe_mode TEXT,
+    probe_tail_kb BIGINT,
+    probe_signature TEXT
+);
+
+CREATE TABLE IF NOT EXISTS updates(
+    id BIGSERIAL PRIMARY KEY,
+    site_id BIGINT REFERENCES sites(id) ON DELETE CASCADE,
+    timestamp TIMESTAMPTZ,
+    diff_hash TEXT,
+    content TEXT
+);
+
+CREATE INDEX IF NOT EXISTS updates_site_id_idx ON updates(site_id, id);
+
+CREATE TABLE IF NOT EXISTS feed_entries(
+    id BIGSERIAL PRIMARY KEY,
+    site_id BIGINT NOT NULL REFERENCES sites(id) ON DELETE CASCADE,
+    guid TEXT NOT NULL,
+    title TEXT,
+    link TEXT,
+    published TIMESTAMPTZ,
+    first_seen TIMESTAMPTZ NOT NULL,
+    UNIQUE(site_id, guid)
+);
+
+CREATE TABLE IF NOT EXISTS site_tags(
+    site_id BIGINT NOT NULL REFERENCES sites(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY(site_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS site_tags_tag_idx ON site_tags(tag);
+
+CREATE TABLE IF NOT EXISTS webhooks(
+    id BIGSERIAL PRIMARY KEY,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    site_id BIGINT REFERENCES sites(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    canary_checked_at TIMESTAMPTZ,
+    canary_ok BOOLEAN,
+    canary_error TEXT
+);
+
+CREATE TABLE IF NOT EXISTS alert_rules(
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    is_regex BOOLEAN NOT NULL DEFAULT FALSE,
+    site_id BIGINT REFERENCES sites(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS fetch_log(
+    id BIGSERIAL PRIMARY KEY,
+    site_id BIGINT NOT NULL REFERENCES sites(id) ON DELETE CASCADE,
+    fetched_at TIMESTAMPTZ NOT NULL,
+    status_code BIGINT,
+    duration_ms BIGINT NOT NULL,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS fetch_log_site_id_idx ON fetch_log(site_id, id);
+
+CREATE TABLE IF NOT EXISTS detection_latency(
+    id BIGSERIAL PRIMARY KEY,
+    site_id BIGINT NOT NULL REFERENCES sites(id) ON DELETE CASCADE,
+    update_id BIGINT,
+    published TIMESTAMPTZ NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL,
+    latency_secs BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS detection_latency_site_id_idx ON detection_latency(site_id, id);
@@ -0,0 +1,2 @@
+-- Unified diff of the visible text against the previous snapshot, stored for changes
+ALTER TABLE updates ADD COLUMN diff TEXT;
@@ -0,0 +1,2 @@
+-- Age limit on stored history; NULL falls back to update_retention_days
+ALTER TABLE sites ADD COLUMN retention_days BIGINT;
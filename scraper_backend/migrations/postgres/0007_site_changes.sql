@@ -0,0 +1,9 @@
+-- When each site's content changed, for interval recommendations. Kept separately
+-- because retention prunes the snapshots themselves.
+CREATE TABLE IF NOT EXISTS site_changes(
+    id BIGSERIAL PRIMARY KEY,
+    site_id BIGINT NOT NULL REFERENCES sites(id) ON DELETE CASCADE,
+    changed_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS site_changes_site_id_idx ON site_changes(site_id, id);
@@ -0,0 +1,9 @@
+-- Visible text of each stored update for GET /api/search. Rows are written by the
+-- scraper and disappear with their update.
+CREATE TABLE IF NOT EXISTS update_text(
+    update_id BIGINT PRIMARY KEY REFERENCES updates(id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    document TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', text)) STORED
+);
+
+CREATE INDEX IF NOT EXISTS update_text_document_idx ON update_text USING GIN (document);
@@ -0,0 +1,4 @@
+-- updates.content now holds zstd-compressed bytes behind a format marker. Existing rows
+-- keep their text as plain UTF-8 bytes, which still reads back, and the server
+-- compresses them in the background after startup.
+ALTER TABLE updates ALTER COLUMN content TYPE BYTEA USING convert_to(content, 'UTF8');
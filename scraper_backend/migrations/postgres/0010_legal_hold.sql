@@ -0,0 +1,3 @@
+-- Legal hold: held sites and updates are exempt from retention, deletion and resets
+ALTER TABLE sites ADD COLUMN legal_hold BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE updates ADD COLUMN legal_hold BOOLEAN NOT NULL DEFAULT FALSE;
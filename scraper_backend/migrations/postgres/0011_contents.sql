@@ -0,0 +1,10 @@
+-- Fetched bodies stored once per distinct body, keyed by its SHA-256. Updates reference
+-- their body by hash; rows from before keep theirs in updates.content until the server
+-- moves them over after startup.
+CREATE TABLE IF NOT EXISTS contents (
+    hash TEXT PRIMARY KEY,
+    body BYTEA NOT NULL
+);
+
+ALTER TABLE updates ADD COLUMN content_hash TEXT REFERENCES contents(hash);
+CREATE INDEX IF NOT EXISTS idx_updates_content_hash ON updates(content_hash);
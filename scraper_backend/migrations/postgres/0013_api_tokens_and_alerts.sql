@@ -0,0 +1,24 @@
+-- Expiring tokens minted through /api/admin/tokens. Only the SHA-256 of each token is
+-- stored; scope is "read", "alerts_ack" or "admin".
+CREATE TABLE IF NOT EXISTS api_tokens(
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    scope TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+-- Updates that matched alert rules, until someone acknowledges them. update_id isn't a
+-- foreign key: an alert outlives the snapshot when retention prunes it.
+CREATE TABLE IF NOT EXISTS alerts(
+    id BIGSERIAL PRIMARY KEY,
+    update_id BIGINT NOT NULL,
+    site_id BIGINT NOT NULL REFERENCES sites(id) ON DELETE CASCADE,
+    rules TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    acknowledged_at TIMESTAMPTZ,
+    acknowledged_by TEXT
+);
+
+CREATE INDEX IF NOT EXISTS alerts_unacknowledged_idx ON alerts(acknowledged_at, id);
@@ -0,0 +1,17 @@
+-- Telegram chats that changes are pushed to, each with the bot that posts there
+CREATE TABLE IF NOT EXISTS telegram_chats(
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    bot_token TEXT NOT NULL,
+    chat_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+-- Sites opted in to each chat; a chat gets nothing until a site is added
+CREATE TABLE IF NOT EXISTS telegram_sites(
+    telegram_chat_id BIGINT NOT NULL REFERENCES telegram_chats(id) ON DELETE CASCADE,
+    site_id BIGINT NOT NULL REFERENCES sites(id) ON DELETE CASCADE,
+    PRIMARY KEY(telegram_chat_id, site_id)
+);
+
+CREATE INDEX IF NOT EXISTS telegram_sites_site_id_idx ON telegram_sites(site_id);
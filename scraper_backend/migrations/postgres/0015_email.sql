@@ -0,0 +1,21 @@
+-- Matches of high-priority rules are emailed right away
+ALTER TABLE alert_rules ADD COLUMN high_priority BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Email recipients: `alerts` opts in to immediate alert emails, `digest` is "none",
+-- "hourly" or "daily" (sent at digest_hour, UTC)
+CREATE TABLE IF NOT EXISTS email_recipients(
+    id BIGSERIAL PRIMARY KEY,
+    address TEXT NOT NULL,
+    alerts BOOLEAN NOT NULL,
+    digest TEXT NOT NULL,
+    digest_hour BIGINT NOT NULL,
+    last_digest_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+-- Overrides of the built-in "alert" and "digest" templates
+CREATE TABLE IF NOT EXISTS email_templates(
+    kind TEXT PRIMARY KEY,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL
+);
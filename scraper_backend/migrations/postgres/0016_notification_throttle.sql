@@ -0,0 +1,12 @@
+-- Per-site notification throttle; NULL falls back to notify_burst_limit and notify_window_secs
+ALTER TABLE sites ADD COLUMN notify_burst_limit BIGINT;
+ALTER TABLE sites ADD COLUMN notify_window_secs BIGINT;
+
+-- Content hashes each site has been notified about, so a page flipping back to an
+-- earlier version doesn't notify again
+CREATE TABLE IF NOT EXISTS notified_changes(
+    site_id BIGINT NOT NULL REFERENCES sites(id) ON DELETE CASCADE,
+    diff_hash TEXT NOT NULL,
+    notified_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY(site_id, diff_hash)
+);
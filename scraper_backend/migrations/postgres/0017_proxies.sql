@@ -0,0 +1,5 @@
+-- Proxies a site is fetched through; empty uses the global pool, ["direct"] none
+ALTER TABLE sites ADD COLUMN proxies JSONB NOT NULL DEFAULT '[]';
+
+-- Proxy that served each fetch, NULL for direct fetches
+ALTER TABLE fetch_log ADD COLUMN proxy TEXT;
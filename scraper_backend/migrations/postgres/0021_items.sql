@@ -0,0 +1,14 @@
+-- News items extracted from feeds and pages, kept for GET /api/items. A site's item is
+-- stored once, when it first appears.
+CREATE TABLE IF NOT EXISTS items(
+    id BIGSERIAL PRIMARY KEY,
+    site_id BIGINT NOT NULL REFERENCES sites(id) ON DELETE CASCADE,
+    guid TEXT NOT NULL,
+    title TEXT,
+    link TEXT,
+    summary TEXT,
+    published_at TIMESTAMPTZ,
+    first_seen TIMESTAMPTZ NOT NULL,
+    UNIQUE(site_id, guid)
+);
+CREATE INDEX IF NOT EXISTS idx_items_first_seen ON items(first_seen);
@@ -0,0 +1,2 @@
+-- Per-site active hours as JSON; null uses the global active_window
+ALTER TABLE sites ADD COLUMN active_window JSONB NOT NULL DEFAULT 'null';
@@ -0,0 +1,6 @@
+-- What each fetch's response said about itself, and the update it was stored as
+ALTER TABLE fetch_log ADD COLUMN final_url TEXT;
+ALTER TABLE fetch_log ADD COLUMN content_type TEXT;
+ALTER TABLE fetch_log ADD COLUMN content_length BIGINT;
+ALTER TABLE fetch_log ADD COLUMN changed BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE fetch_log ADD COLUMN update_id BIGINT;
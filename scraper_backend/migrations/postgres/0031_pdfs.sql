@@ -0,0 +1,8 @@
+-- Fetched PDFs, stored once per distinct file and keyed by its SHA-256. Their extracted
+-- text is the update's content; pdf_hash points back at the original.
+CREATE TABLE IF NOT EXISTS pdfs (
+    hash TEXT PRIMARY KEY,
+    data BYTEA NOT NULL
+);
+
+ALTER TABLE updates ADD COLUMN pdf_hash TEXT REFERENCES pdfs(hash);
@@ -0,0 +1,17 @@
+-- Copies of documents newly linked from "links" mode sites with archive_links set, kept
+-- after the source takes them down. update_id has no foreign key so pruning updates
+-- leaves the archive alone.
+CREATE TABLE IF NOT EXISTS documents (
+    id BIGSERIAL PRIMARY KEY,
+    site_id BIGINT NOT NULL REFERENCES sites(id),
+    update_id BIGINT,
+    url TEXT NOT NULL,
+    content_type TEXT,
+    size BIGINT NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL,
+    data BYTEA NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS documents_site_id_idx ON documents(site_id, id);
+
+ALTER TABLE sites ADD COLUMN archive_links BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,7 @@
+-- PNG screenshots captured in browser mode for updates that changed, keyed by the
+-- update's id. Rows disappear with their update.
+CREATE TABLE IF NOT EXISTS screenshots (
+    update_id BIGINT PRIMARY KEY REFERENCES updates(id) ON DELETE CASCADE,
+    captured_at TIMESTAMPTZ NOT NULL,
+    data BYTEA NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Title, dates, summary and the like a fetched page declared in JSON-LD, microdata or
+-- OpenGraph tags, as JSON; null when it declared none
+ALTER TABLE updates ADD COLUMN metadata JSONB NOT NULL DEFAULT 'null';
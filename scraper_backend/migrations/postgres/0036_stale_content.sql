@@ -0,0 +1,6 @@
+-- Publication date of an update's content, from its structured data or feed entries
+ALTER TABLE updates ADD COLUMN published_at TIMESTAMPTZ;
+
+-- Changes to content published longer ago than this aren't notified; null uses the
+-- global max_content_age_hours
+ALTER TABLE sites ADD COLUMN max_content_age_hours BIGINT;
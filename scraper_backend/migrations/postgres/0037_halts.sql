@@ -0,0 +1,17 @@
+-- Trading halts listed by "halts" mode sites, one row per symbol and halt time. update_id
+-- has no foreign key so pruning updates leaves the halt history alone.
+CREATE TABLE IF NOT EXISTS halts (
+    id BIGSERIAL PRIMARY KEY,
+    site_id BIGINT NOT NULL REFERENCES sites(id),
+    update_id BIGINT,
+    symbol TEXT NOT NULL,
+    name TEXT,
+    market TEXT,
+    reason_code TEXT,
+    halted_at TIMESTAMPTZ NOT NULL,
+    resumed_at TIMESTAMPTZ,
+    first_seen TIMESTAMPTZ NOT NULL,
+    UNIQUE (site_id, symbol, halted_at)
+);
+
+CREATE INDEX IF NOT EXISTS halts_halted_at_idx ON halts(halted_at);
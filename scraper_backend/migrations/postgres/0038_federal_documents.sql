@@ -0,0 +1,20 @@
+-- Documents listed by "federal_register" mode sites, one row per site and document number.
+-- agencies is a JSON array of agency names. update_id has no foreign key so pruning updates
+-- leaves the documents alone.
+CREATE TABLE IF NOT EXISTS federal_documents (
+    id BIGSERIAL PRIMARY KEY,
+    site_id BIGINT NOT NULL REFERENCES sites(id),
+    update_id BIGINT,
+    document_number TEXT NOT NULL,
+    title TEXT,
+    document_type TEXT,
+    agencies JSONB NOT NULL DEFAULT '[]',
+    publication_date DATE,
+    html_url TEXT,
+    pdf_url TEXT,
+    summary TEXT,
+    first_seen TIMESTAMPTZ NOT NULL,
+    UNIQUE (site_id, document_number)
+);
+
+CREATE INDEX IF NOT EXISTS federal_documents_publication_date_idx ON federal_documents(publication_date);
@@ -0,0 +1,5 @@
+-- Listing pages followed from a site's page: links matching crawl_pattern, up to
+-- crawl_depth links away and crawl_max_pages pages in all; null pattern crawls nothing
+ALTER TABLE sites ADD COLUMN crawl_pattern TEXT;
+ALTER TABLE sites ADD COLUMN crawl_depth BIGINT;
+ALTER TABLE sites ADD COLUMN crawl_max_pages BIGINT;
@@ -0,0 +1,4 @@
+-- Checks in a row that failed, and the health state they put the site in: OK, DEGRADED
+-- or DOWN; null until the site's first check
+ALTER TABLE sites ADD COLUMN consecutive_failures BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE sites ADD COLUMN health TEXT;
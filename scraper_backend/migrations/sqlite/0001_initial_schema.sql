@@ -0,0 +1,96 @@
+-- Initial schema. Tables use IF NOT EXISTS so databases created before migrations
+-- were introduced are baselined without losing data.
+
+CREATE TABLE IF NOT EXISTS sites(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL UNIQUE,
+    interval_secs INTEGER NOT NULL,
+    style TEXT NOT NULL,
+    last_checked TEXT,
+    last_updated TEXT,
+    status TEXT,
+    snoozed_until TEXT,
+    last_success TEXT,
+    selector TEXT,
+    priority INTEGER NOT NULL DEFAULT 0,
+    retention_count INTEGER,
+    probe_mode TEXT,
+    probe_tail_kb INTEGER,
+    probe_signature TEXT
+);
+
+CREATE TABLE IF NOT EXISTS updates(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id INTEGER,
+    timestamp TEXT,
+    diff_hash TEXT,
+    content TEXT,
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+-- Seen entry GUIDs per feed site
+CREATE TABLE IF NOT EXISTS feed_entries(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id INTEGER NOT NULL,
+    guid TEXT NOT NULL,
+    title TEXT,
+    link TEXT,
+    published TEXT,
+    first_seen TEXT NOT NULL,
+    UNIQUE(site_id, guid),
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+-- Free-form labels used for filtering and bulk edits
+CREATE TABLE IF NOT EXISTS site_tags(
+    site_id INTEGER NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY(site_id, tag),
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+-- Change notification endpoints; site_id NULL means every site
+CREATE TABLE IF NOT EXISTS webhooks(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    site_id INTEGER,
+    created_at TEXT NOT NULL,
+    canary_checked_at TEXT,
+    canary_ok INTEGER,
+    canary_error TEXT,
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+-- Keyword/regex rules that flag matching changes as alerts
+CREATE TABLE IF NOT EXISTS alert_rules(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    is_regex INTEGER NOT NULL DEFAULT 0,
+    site_id INTEGER,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+-- Rolling log of fetch attempts, trimmed to fetch_log_size per site
+CREATE TABLE IF NOT EXISTS fetch_log(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id INTEGER NOT NULL,
+    fetched_at TEXT NOT NULL,
+    status_code INTEGER,
+    duration_ms INTEGER NOT NULL,
+    error TEXT,
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+-- Published → detected delay per change, for GET /api/stats/latency
+CREATE TABLE IF NOT EXISTS detection_latency(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id INTEGER NOT NULL,
+    update_id INTEGER,
+    published TEXT NOT NULL,
+    detected_at TEXT NOT NULL,
+    latency_secs INTEGER NOT NULL,
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+-- Version of the extraction/hash pipeline that produced each stored diff_hash.
+-- NULL for snapshots stored before versioning.
+ALTER TABLE updates ADD COLUMN pipeline_version INTEGER;
@@ -0,0 +1,4 @@
+-- Expected-content checks; a fetch failing any of them is not hashed or stored
+ALTER TABLE sites ADD COLUMN assert_selector TEXT;
+ALTER TABLE sites ADD COLUMN assert_pattern TEXT;
+ALTER TABLE sites ADD COLUMN assert_min_length INTEGER;
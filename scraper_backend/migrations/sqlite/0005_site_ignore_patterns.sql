@@ -0,0 +1,2 @@
+-- Per-site regexes and `css:` selectors stripped before hashing, as a JSON array
+ALTER TABLE sites ADD COLUMN ignore_patterns TEXT NOT NULL DEFAULT '[]';
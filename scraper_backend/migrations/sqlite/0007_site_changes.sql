@@ -0,0 +1,10 @@
+-- When each site's content changed, for interval recommendations. Kept separately
+-- because retention prunes the snapshots themselves.
+CREATE TABLE IF NOT EXISTS site_changes(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id INTEGER NOT NULL,
+    changed_at TEXT NOT NULL,
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS site_changes_site_id_idx ON site_changes(site_id, id);
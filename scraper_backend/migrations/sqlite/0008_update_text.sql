@@ -0,0 +1,7 @@
+-- Visible text of each stored update for GET /api/search, keyed by the update's id.
+-- Rows are written by the scraper and disappear with their update.
+CREATE VIRTUAL TABLE IF NOT EXISTS update_text USING fts5(text);
+
+CREATE TRIGGER IF NOT EXISTS update_text_delete AFTER DELETE ON updates BEGIN
+    DELETE FROM update_text WHERE rowid = old.id;
+END;
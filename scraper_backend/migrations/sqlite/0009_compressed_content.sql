@@ -0,0 +1,4 @@
+-- updates.content now holds zstd-compressed bytes behind a format marker. SQLite keeps
+-- each value's own type, so existing TEXT rows stay readable as they are; the server
+-- compresses them in the background after startup. No schema change is needed.
+SELECT 1;
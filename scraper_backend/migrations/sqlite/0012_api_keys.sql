@@ -0,0 +1,9 @@
+-- API keys created through /api/admin/keys. Only the SHA-256 of each key is stored;
+-- role is "write" or "admin".
+CREATE TABLE IF NOT EXISTS api_keys(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,25 @@
+-- Expiring tokens minted through /api/admin/tokens. Only the SHA-256 of each token is
+-- stored; scope is "read", "alerts_ack" or "admin".
+CREATE TABLE IF NOT EXISTS api_tokens(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    scope TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+-- Updates that matched alert rules, until someone acknowledges them. update_id isn't a
+-- foreign key: an alert outlives the snapshot when retention prunes it.
+CREATE TABLE IF NOT EXISTS alerts(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    update_id INTEGER NOT NULL,
+    site_id INTEGER NOT NULL,
+    rules TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    acknowledged_at TEXT,
+    acknowledged_by TEXT,
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS alerts_unacknowledged_idx ON alerts(acknowledged_at, id);
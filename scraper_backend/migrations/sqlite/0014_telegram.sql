@@ -0,0 +1,19 @@
+-- Telegram chats that changes are pushed to, each with the bot that posts there
+CREATE TABLE IF NOT EXISTS telegram_chats(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    bot_token TEXT NOT NULL,
+    chat_id TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+-- Sites opted in to each chat; a chat gets nothing until a site is added
+CREATE TABLE IF NOT EXISTS telegram_sites(
+    telegram_chat_id INTEGER NOT NULL,
+    site_id INTEGER NOT NULL,
+    PRIMARY KEY(telegram_chat_id, site_id),
+    FOREIGN KEY(telegram_chat_id) REFERENCES telegram_chats(id) ON DELETE CASCADE,
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS telegram_sites_site_id_idx ON telegram_sites(site_id);
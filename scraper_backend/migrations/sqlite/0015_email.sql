@@ -0,0 +1,21 @@
+-- Matches of high-priority rules are emailed right away
+ALTER TABLE alert_rules ADD COLUMN high_priority INTEGER NOT NULL DEFAULT 0;
+
+-- Email recipients: `alerts` opts in to immediate alert emails, `digest` is "none",
+-- "hourly" or "daily" (sent at digest_hour, UTC)
+CREATE TABLE IF NOT EXISTS email_recipients(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    address TEXT NOT NULL,
+    alerts INTEGER NOT NULL,
+    digest TEXT NOT NULL,
+    digest_hour INTEGER NOT NULL,
+    last_digest_at TEXT,
+    created_at TEXT NOT NULL
+);
+
+-- Overrides of the built-in "alert" and "digest" templates
+CREATE TABLE IF NOT EXISTS email_templates(
+    kind TEXT PRIMARY KEY,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL
+);
@@ -0,0 +1,13 @@
+-- Per-site notification throttle; NULL falls back to notify_burst_limit and notify_window_secs
+ALTER TABLE sites ADD COLUMN notify_burst_limit INTEGER;
+ALTER TABLE sites ADD COLUMN notify_window_secs INTEGER;
+
+-- Content hashes each site has been notified about, so a page flipping back to an
+-- earlier version doesn't notify again
+CREATE TABLE IF NOT EXISTS notified_changes(
+    site_id INTEGER NOT NULL,
+    diff_hash TEXT NOT NULL,
+    notified_at TEXT NOT NULL,
+    PRIMARY KEY(site_id, diff_hash),
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- Backoff after failed or rate-limited fetches, kept so a restart still honors it
+ALTER TABLE sites ADD COLUMN backoff_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sites ADD COLUMN backoff_until TEXT;
+ALTER TABLE sites ADD COLUMN backoff_reason TEXT;
@@ -0,0 +1,10 @@
+-- Tags as groups of their own: a description and defaults for sites added with the tag.
+-- Every tag in use gets a row; site_tags keeps referring to it by name.
+CREATE TABLE IF NOT EXISTS tags(
+    name TEXT PRIMARY KEY,
+    description TEXT,
+    interval_secs INTEGER,
+    style TEXT
+);
+
+INSERT OR IGNORE INTO tags (name) SELECT DISTINCT tag FROM site_tags;
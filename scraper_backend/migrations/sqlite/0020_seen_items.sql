@@ -0,0 +1,19 @@
+-- Feed items seen per site, each under every key that identifies it: its GUID, its link
+-- without tracking parameters and a hash of its text. An item is new when none of its
+-- keys were seen within the lookback window. Replaces feed_entries, which only had GUIDs.
+CREATE TABLE IF NOT EXISTS seen_items(
+    site_id INTEGER NOT NULL,
+    item_key TEXT NOT NULL,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL,
+    PRIMARY KEY(site_id, item_key),
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_seen_items_last_seen ON seen_items(site_id, last_seen);
+
+INSERT OR IGNORE INTO seen_items (site_id, item_key, first_seen, last_seen)
+    SELECT site_id, 'guid:' || guid, first_seen, first_seen FROM feed_entries;
+INSERT OR IGNORE INTO seen_items (site_id, item_key, first_seen, last_seen)
+    SELECT site_id, 'link:' || link, first_seen, first_seen FROM feed_entries WHERE link IS NOT NULL;
+
+DROP TABLE feed_entries;
@@ -0,0 +1,15 @@
+-- News items extracted from feeds and pages, kept for GET /api/items. A site's item is
+-- stored once, when it first appears.
+CREATE TABLE IF NOT EXISTS items(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id INTEGER NOT NULL,
+    guid TEXT NOT NULL,
+    title TEXT,
+    link TEXT,
+    summary TEXT,
+    published_at TEXT,
+    first_seen TEXT NOT NULL,
+    UNIQUE(site_id, guid),
+    FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_items_first_seen ON items(first_seen);
@@ -0,0 +1,2 @@
+-- Interval learned for sites with the adaptive style; NULL starts from interval_secs
+ALTER TABLE sites ADD COLUMN adaptive_interval_secs INTEGER;
@@ -0,0 +1,3 @@
+-- Burst mode: checked every burst_interval_secs until burst_until
+ALTER TABLE sites ADD COLUMN burst_interval_secs INTEGER;
+ALTER TABLE sites ADD COLUMN burst_until TEXT;
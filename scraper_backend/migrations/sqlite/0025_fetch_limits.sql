@@ -0,0 +1,4 @@
+-- Per-site fetch limits; NULL uses the defaults
+ALTER TABLE sites ADD COLUMN timeout_secs INTEGER;
+ALTER TABLE sites ADD COLUMN max_redirects INTEGER;
+ALTER TABLE sites ADD COLUMN max_body_bytes INTEGER;
@@ -0,0 +1,2 @@
+-- Per-site extraction mode; NULL uses the generic content heuristics
+ALTER TABLE sites ADD COLUMN extraction_mode TEXT;
@@ -0,0 +1,2 @@
+-- Per-site live preview length; NULL uses the default
+ALTER TABLE sites ADD COLUMN preview_length INTEGER;
@@ -0,0 +1,2 @@
+-- Per-site rhai extraction script; null uses the built-in extraction
+ALTER TABLE sites ADD COLUMN extraction_script TEXT;
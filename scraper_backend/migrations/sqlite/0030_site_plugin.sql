@@ -0,0 +1,2 @@
+-- WASM plugin in plugin_dir fetching and extracting the site; null uses the built-in ones
+ALTER TABLE sites ADD COLUMN plugin TEXT;
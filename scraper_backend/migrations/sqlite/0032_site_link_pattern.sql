@@ -0,0 +1,2 @@
+-- Regex a link URL must match to count in the "links" extraction mode
+ALTER TABLE sites ADD COLUMN link_pattern TEXT;
@@ -0,0 +1,11 @@
+-- PNG screenshots captured in browser mode for updates that changed, keyed by the
+-- update's id. Rows disappear with their update.
+CREATE TABLE IF NOT EXISTS screenshots (
+    update_id INTEGER PRIMARY KEY,
+    captured_at TEXT NOT NULL,
+    data BLOB NOT NULL
+);
+
+CREATE TRIGGER IF NOT EXISTS screenshots_delete AFTER DELETE ON updates BEGIN
+    DELETE FROM screenshots WHERE update_id = old.id;
+END;
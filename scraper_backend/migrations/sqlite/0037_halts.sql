@@ -0,0 +1,17 @@
+-- Trading halts listed by "halts" mode sites, one row per symbol and halt time. update_id
+-- has no foreign key so pruning updates leaves the halt history alone.
+CREATE TABLE IF NOT EXISTS halts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id INTEGER NOT NULL REFERENCES sites(id),
+    update_id INTEGER,
+    symbol TEXT NOT NULL,
+    name TEXT,
+    market TEXT,
+    reason_code TEXT,
+    halted_at TEXT NOT NULL,
+    resumed_at TEXT,
+    first_seen TEXT NOT NULL,
+    UNIQUE (site_id, symbol, halted_at)
+);
+
+CREATE INDEX IF NOT EXISTS halts_halted_at_idx ON halts(halted_at);
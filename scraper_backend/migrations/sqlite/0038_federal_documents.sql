@@ -0,0 +1,20 @@
+-- Documents listed by "federal_register" mode sites, one row per site and document number.
+-- agencies is a JSON array of agency names. update_id has no foreign key so pruning updates
+-- leaves the documents alone.
+CREATE TABLE IF NOT EXISTS federal_documents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id INTEGER NOT NULL REFERENCES sites(id),
+    update_id INTEGER,
+    document_number TEXT NOT NULL,
+    title TEXT,
+    document_type TEXT,
+    agencies TEXT NOT NULL DEFAULT '[]',
+    publication_date TEXT,
+    html_url TEXT,
+    pdf_url TEXT,
+    summary TEXT,
+    first_seen TEXT NOT NULL,
+    UNIQUE (site_id, document_number)
+);
+
+CREATE INDEX IF NOT EXISTS federal_documents_publication_date_idx ON federal_documents(publication_date);
@@ -0,0 +1,4 @@
+-- Wire a stored update's press release came from ("businesswire", "prnewswire",
+-- "globenewswire"), and alert rules that only fire on press releases
+ALTER TABLE updates ADD COLUMN wire_source TEXT;
+ALTER TABLE alert_rules ADD COLUMN press_release INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Sites without a change for longer than this are flagged STALE by the watchdog; null
+-- never flags them
+ALTER TABLE sites ADD COLUMN max_quiet_hours INTEGER;
@@ -0,0 +1,226 @@
+use chrono::{DateTime, Utc};
+use jiff::civil::{Date, Time};
+use jiff::tz::TimeZone;
+use jiff::Timestamp;
+use serde::{Deserialize, Deserializer, Serialize};
+
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+// Beyond this a multiplier is more likely a typo than a wish to park the site for days
+const MAX_MULTIPLIER: f64 = 100.0;
+
+// Hours during which a site is checked at its normal interval, e.g. 08:00-20:00
+// America/New_York on mon-fri. Outside them the interval is stretched by
+// off_hours_multiplier, but never past the start of the next window.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ActiveWindow {
+    // "mon-fri", "sat,sun", "mon,wed-fri"; empty means every day
+    #[serde(default)]
+    pub days: String,
+    // HH:MM local time; an end before the start runs past midnight, and an end equal
+    // to the start covers the whole day
+    pub start: String,
+    pub end: String,
+    // IANA zone name from the system time zone database
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    #[serde(default = "default_multiplier")]
+    pub off_hours_multiplier: f64,
+}
+
+fn default_timezone() -> String {
+    "UTC".into()
+}
+
+fn default_multiplier() -> f64 {
+    4.0
+}
+
+// config.yaml's active_window, applied to every site without a window of its own
+pub fn from_yaml(value: &serde_yaml::Value) -> Result<Option<ActiveWindow>, String> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let window: ActiveWindow = serde_yaml::from_value(value.clone()).map_err(|e| format!("Invalid active_window: {}", e))?;
+    window.compile()?;
+    Ok(Some(window))
+}
+
+// PATCH field: absent leaves the window alone, null removes it
+pub fn deserialize_patch<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<ActiveWindow>>, D::Error> {
+    Option::<ActiveWindow>::deserialize(deserializer).map(Some)
+}
+
+// An ActiveWindow checked and ready to evaluate
+pub struct Window {
+    // Indexed from Monday; a window belongs to the day it starts on
+    days: [bool; 7],
+    start: Time,
+    end: Time,
+    tz: TimeZone,
+    multiplier: f64,
+}
+
+impl ActiveWindow {
+    pub fn compile(&self) -> Result<Window, String> {
+        let tz = TimeZone::get(self.timezone.trim()).map_err(|e| format!("Invalid timezone '{}': {}", self.timezone, e))?;
+        if !self.off_hours_multiplier.is_finite() || !(1.0..=MAX_MULTIPLIER).contains(&self.off_hours_multiplier) {
+            return Err(format!("off_hours_multiplier must be between 1 and {}", MAX_MULTIPLIER));
+        }
+        Ok(Window {
+            days: parse_days(&self.days)?,
+            start: parse_time(&self.start)?,
+            end: parse_time(&self.end)?,
+            tz,
+            multiplier: self.off_hours_multiplier,
+        })
+    }
+}
+
+// Checks a window sent to the API, so a bad one is refused up front
+pub fn validate(window: Option<&ActiveWindow>) -> Result<(), String> {
+    window.map_or(Ok(()), |w| w.compile().map(|_| ()))
+}
+
+fn parse_days(spec: &str) -> Result<[bool; 7], String> {
+    let spec = spec.trim().to_lowercase();
+    if spec.is_empty() {
+        return Ok([true; 7]);
+    }
+    let day = |name: &str| DAYS.iter().position(|d| *d == name.trim())
+        .ok_or_else(|| format!("Invalid day '{}': expected one of {}", name.trim(), DAYS.join(", ")));
+    let mut days = [false; 7];
+    for part in spec.split(',') {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (day(first)?, day(last)?),
+            None => (day(part)?, day(part)?),
+        };
+        // sat-mon wraps around the week
+        let mut i = first;
+        loop {
+            days[i] = true;
+            if i == last {
+                break;
+            }
+            i = (i + 1) % 7;
+        }
+    }
+    Ok(days)
+}
+
+fn parse_time(value: &str) -> Result<Time, String> {
+    let invalid = || format!("Invalid time '{}': expected HH:MM", value);
+    let (hour, minute) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let hour: i8 = hour.parse().map_err(|_| invalid())?;
+    let minute: i8 = minute.parse().map_err(|_| invalid())?;
+    Time::new(hour, minute, 0, 0).map_err(|_| invalid())
+}
+
+fn timestamp(at: DateTime<Utc>) -> Timestamp {
+    Timestamp::new(at.timestamp(), at.timestamp_subsec_nanos() as i32).unwrap_or(Timestamp::UNIX_EPOCH)
+}
+
+impl Window {
+    fn runs_on(&self, date: Date) -> bool {
+        self.days[date.weekday().to_monday_zero_offset() as usize]
+    }
+
+    pub fn is_active(&self, at: DateTime<Utc>) -> bool {
+        let local = timestamp(at).to_zoned(self.tz.clone());
+        let (date, time) = (local.date(), local.time());
+        match self.start.cmp(&self.end) {
+            std::cmp::Ordering::Equal => self.runs_on(date),
+            std::cmp::Ordering::Less => self.runs_on(date) && time >= self.start && time < self.end,
+            std::cmp::Ordering::Greater => {
+                (self.runs_on(date) && time >= self.start)
+                    || (time < self.end && date.yesterday().is_ok_and(|d| self.runs_on(d)))
+            },
+        }
+    }
+
+    // The next time a window opens after `at`; None if no day is enabled
+    pub fn next_start(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let after = timestamp(at);
+        let mut date = after.to_zoned(self.tz.clone()).date();
+        for _ in 0..8 {
+            if self.runs_on(date) {
+                // A start inside a DST gap resolves to the moment after it
+                if let Ok(start) = date.to_datetime(self.start).to_zoned(self.tz.clone()) {
+                    let start = start.timestamp();
+                    if start > after {
+                        return DateTime::from_timestamp(start.as_second(), start.subsec_nanosecond() as u32);
+                    }
+                }
+            }
+            date = date.tomorrow().ok()?;
+        }
+        None
+    }
+
+    // The next check after a fetch at `fetched_at` that would normally come at `next`
+    pub fn apply(&self, fetched_at: DateTime<Utc>, next: DateTime<Utc>) -> DateTime<Utc> {
+        if self.multiplier <= 1.0 || next <= fetched_at || self.is_active(fetched_at) {
+            return next;
+        }
+        let stretched = (next - fetched_at).num_milliseconds() as f64 * self.multiplier;
+        let slowed = fetched_at + chrono::Duration::milliseconds(stretched as i64);
+        match self.next_start(fetched_at) {
+            Some(start) => slowed.min(start.max(next)),
+            None => slowed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn slows_checks_outside_the_window_until_it_opens() {
+        let market = ActiveWindow {
+            days: "mon-fri".into(),
+            start: "09:30".into(),
+            end: "16:00".into(),
+            timezone: "America/New_York".into(),
+            off_hours_multiplier: 4.0,
+        }.compile().unwrap();
+        // Wednesday 2024-01-10, New York is UTC-5
+        assert!(market.is_active(at("2024-01-10T15:00:00Z")));
+        assert!(!market.is_active(at("2024-01-10T21:00:00Z")));
+        assert!(!market.is_active(at("2024-01-13T15:00:00Z")));
+        assert_eq!(market.next_start(at("2024-01-12T22:00:00Z")), Some(at("2024-01-15T14:30:00Z")));
+
+        let fetched = at("2024-01-10T21:00:00Z");
+        let hour = chrono::Duration::hours(1);
+        assert_eq!(market.apply(at("2024-01-10T15:00:00Z"), at("2024-01-10T16:00:00Z")), at("2024-01-10T16:00:00Z"));
+        assert_eq!(market.apply(fetched, fetched + hour), fetched + hour * 4);
+        // Stretched past the next open: back at the open instead
+        assert_eq!(market.apply(fetched, fetched + hour * 5), at("2024-01-11T14:30:00Z"));
+
+        let overnight = ActiveWindow {
+            days: "fri".into(),
+            start: "22:00".into(),
+            end: "02:00".into(),
+            timezone: "UTC".into(),
+            off_hours_multiplier: 2.0,
+        }.compile().unwrap();
+        assert!(overnight.is_active(at("2024-01-13T01:00:00Z")));
+        assert!(!overnight.is_active(at("2024-01-14T01:00:00Z")));
+
+        let bad = |days: &str, start: &str, timezone: &str, multiplier: f64| ActiveWindow {
+            days: days.into(),
+            start: start.into(),
+            end: "20:00".into(),
+            timezone: timezone.into(),
+            off_hours_multiplier: multiplier,
+        }.compile().is_err();
+        assert!(bad("mon-fry", "08:00", "UTC", 4.0));
+        assert!(bad("", "8am", "UTC", 4.0));
+        assert!(bad("", "08:00", "Mars/Olympus", 4.0));
+        assert!(bad("", "08:00", "UTC", 0.5));
+        assert_eq!(parse_days("sat-mon").unwrap(), [true, false, false, false, false, true, true]);
+    }
+}
@@ -0,0 +1,239 @@
+use super::scraper::{normalize_unicode, select_region};
+use super::storage::Storage;
+use super::auth::Caller;
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashSet;
+
+#[derive(Serialize, FromRow, Clone)]
+pub struct AlertRule {
+    pub id: i64,
+    pub name: String,
+    pub pattern: String,
+    // Plain keywords match case-insensitively as a substring
+    pub is_regex: bool,
+    // None applies the rule to every site
+    pub site_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    // Matches are emailed immediately instead of waiting for a digest
+    pub high_priority: bool,
+    // Only fires on press releases from a wire; the pattern may then be empty to match every
+    // new release
+    pub press_release: bool,
+}
+
+#[derive(Deserialize)]
+pub struct NewAlertRule {
+    name: Option<String>,
+    pattern: String,
+    #[serde(default)]
+    is_regex: bool,
+    site_id: Option<i64>,
+    #[serde(default)]
+    high_priority: bool,
+    #[serde(default)]
+    press_release: bool,
+}
+
+// An update that matched rules; `rules` holds their names, comma-separated
+#[derive(Serialize, FromRow, Clone)]
+pub struct Alert {
+    pub id: i64,
+    pub update_id: i64,
+    pub site_id: i64,
+    pub rules: String,
+    pub created_at: DateTime<Utc>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    // Name of the key or token that acknowledged it; None when auth was open
+    pub acknowledged_by: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AlertQuery {
+    #[serde(default)]
+    unacknowledged: bool,
+    limit: Option<i64>,
+}
+
+// Rule reference attached to UpdateMessages and alert events
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MatchedRule {
+    pub id: i64,
+    pub name: String,
+}
+
+pub struct CompiledRule {
+    rule: MatchedRule,
+    regex: Regex,
+    press_release: bool,
+}
+
+fn compile(pattern: &str, is_regex: bool) -> Result<Regex, regex::Error> {
+    let source = if is_regex { pattern.to_string() } else { regex::escape(pattern) };
+    RegexBuilder::new(&source).case_insensitive(!is_regex).build()
+}
+
+// Rules that apply to a site; rules whose pattern no longer compiles are skipped
+pub async fn rules_for_site(storage: &dyn Storage, site_id: i64) -> Vec<CompiledRule> {
+    let rules = storage.site_alert_rules(site_id).await.unwrap_or_default();
+    rules.into_iter()
+        .filter_map(|r| {
+            let regex = compile(&r.pattern, r.is_regex).ok()?;
+            Some(CompiledRule { rule: MatchedRule { id: r.id, name: r.name }, regex, press_release: r.press_release })
+        })
+        .collect()
+}
+
+// Press release rules are only tried on text from one
+pub fn match_rules(rules: &[CompiledRule], text: &str, press_release: bool) -> Vec<MatchedRule> {
+    rules.iter()
+        .filter(|r| press_release || !r.press_release)
+        .filter(|r| r.regex.is_match(text))
+        .map(|r| r.rule.clone())
+        .collect()
+}
+
+// Visible text of a page (or its selected region), one text node per line
+pub fn page_text(html: &str, selector: Option<&str>) -> String {
+    let region = selector.and_then(|sel| select_region(html, sel));
+    let document = Html::parse_document(region.as_deref().unwrap_or(html));
+    let mut lines = Vec::new();
+    for node in document.tree.nodes() {
+        let Some(text) = node.value().as_text() else {
+            continue;
+        };
+        let in_code = node.parent()
+            .and_then(|p| p.value().as_element().map(|e| e.name().to_string()))
+            .is_some_and(|name| matches!(name.as_str(), "script" | "style" | "noscript"));
+        let text = text.trim();
+        if !in_code && !text.is_empty() {
+            lines.push(normalize_unicode(text));
+        }
+    }
+    lines.join("\n")
+}
+
+// Lines of `new` that don't appear anywhere in `old`, so a rule only fires on text
+// that was actually added rather than on every change to a page that mentions it
+pub fn added_text(old: &str, new: &str) -> String {
+    let seen: HashSet<&str> = old.lines().map(str::trim).collect();
+    new.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !seen.contains(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub async fn list_rules(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.list_alert_rules().await {
+        Ok(rules) => HttpResponse::Ok().json(rules),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn add_rule(data: web::Data<AppState>, payload: web::Json<NewAlertRule>) -> impl Responder {
+    let pattern = payload.pattern.trim();
+    if pattern.is_empty() && !payload.press_release {
+        return HttpResponse::BadRequest().body("pattern must not be empty");
+    }
+    if let Err(e) = compile(pattern, payload.is_regex) {
+        return HttpResponse::BadRequest().body(format!("Invalid pattern '{}': {}", pattern, e));
+    }
+    let name = payload.name.as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .or((!pattern.is_empty()).then_some(pattern))
+        .unwrap_or("New press release")
+        .to_string();
+    let mut rule = AlertRule {
+        id: 0,
+        name,
+        pattern: pattern.to_string(),
+        is_regex: payload.is_regex,
+        site_id: payload.site_id,
+        created_at: Utc::now(),
+        high_priority: payload.high_priority,
+        press_release: payload.press_release,
+    };
+
+    match data.storage.insert_alert_rule(&rule).await {
+        Ok(id) => {
+            rule.id = id;
+            HttpResponse::Ok().json(rule)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn delete_rule(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    match data.storage.delete_alert_rule(id).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("Alert rule with ID {} not found", id)),
+        Ok(true) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// GET /api/alerts?unacknowledged=true&limit=: newest first
+pub async fn list_alerts(data: web::Data<AppState>, query: web::Query<AlertQuery>) -> impl Responder {
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    match data.storage.list_alerts(query.unacknowledged, limit).await {
+        Ok(alerts) => HttpResponse::Ok().json(alerts),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// POST /api/alerts/{id}/ack: acknowledging twice keeps the first acknowledgement
+pub async fn acknowledge(data: web::Data<AppState>, path: web::Path<i64>, caller: Option<web::ReqData<Caller>>) -> impl Responder {
+    let id = path.into_inner();
+    let by = caller.map(|c| c.into_inner().0);
+    match data.storage.acknowledge_alert(id, Utc::now(), by.as_deref()).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("Alert with ID {} not found", id)),
+        Ok(true) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: i64, pattern: &str, is_regex: bool) -> CompiledRule {
+        CompiledRule {
+            rule: MatchedRule { id, name: pattern.to_string() },
+            regex: compile(pattern, is_regex).unwrap(),
+            press_release: false,
+        }
+    }
+
+    #[test]
+    fn matches_keywords_and_regexes() {
+        let rules = vec![rule(1, "FDA approval", false), rule(2, r"\bNVDA\b", true), rule(3, "halt", false)];
+        let matched = match_rules(&rules, "Company receives fda Approval for NVDAX", false);
+        assert_eq!(matched, vec![MatchedRule { id: 1, name: "FDA approval".into() }]);
+        assert_eq!(match_rules(&rules, "Trading HALTED in NVDA", false).len(), 2);
+
+        // An empty press release rule matches every release and nothing else
+        let rules = vec![CompiledRule { press_release: true, ..rule(4, "", false) }];
+        assert!(match_rules(&rules, "Trading HALTED in NVDA", false).is_empty());
+        assert_eq!(match_rules(&rules, "Acme reports results", true).len(), 1);
+    }
+
+    #[test]
+    fn page_text_skips_scripts() {
+        let html = "<html><body><p>Trading halt</p><script>var halt = 1;</script><p>Resumed</p></body></html>";
+        assert_eq!(page_text(html, None), "Trading halt\nResumed");
+    }
+
+    #[test]
+    fn only_added_lines_count() {
+        let old = "FDA news\nOld item";
+        let new = "FDA news\nOld item\nNew trading halt";
+        assert_eq!(added_text(old, new), "New trading halt");
+    }
+}
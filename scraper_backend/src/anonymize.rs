@@ -0,0 +1,51 @@
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+// Replaces source URLs and internal identifiers with stable aliases so digests
+// can be shared without revealing the watch list
+#[derive(Clone)]
+pub struct Anonymizer {
+    salt: String,
+    url_pattern: Regex,
+}
+
+impl Anonymizer {
+    pub fn new(salt: &str) -> Self {
+        Anonymizer {
+            salt: salt.to_string(),
+            url_pattern: Regex::new(r#"(?i)\b(?:https?|ftp)://[^\s<>"']+"#).unwrap(),
+        }
+    }
+
+    // Same URL and salt always map to the same alias; without the salt the
+    // alias can't be reversed by hashing candidate URLs
+    pub fn alias(&self, url: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update(url.as_bytes());
+        let digest = format!("{:x}", hasher.finalize());
+        format!("source-{}", &digest[..8])
+    }
+
+    // Remove any URLs (and the credentials or tokens they may carry) from free text
+    pub fn scrub(&self, text: &str) -> String {
+        self.url_pattern.replace_all(text, "[link removed]").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_are_stable_and_salted() {
+        let a = Anonymizer::new("one");
+        let b = Anonymizer::new("two");
+        assert_eq!(a.alias("https://www.sec.gov/news"), a.alias("https://www.sec.gov/news"));
+        assert_ne!(a.alias("https://www.sec.gov/news"), b.alias("https://www.sec.gov/news"));
+        assert_eq!(
+            a.scrub("see https://user:pw@example.com/x?token=1 now"),
+            "see [link removed] now"
+        );
+    }
+}
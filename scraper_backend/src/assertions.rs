@@ -0,0 +1,72 @@
+use super::alerts::page_text;
+use super::scraper::parse_selector;
+use super::Site;
+use regex::Regex;
+use scraper::Html;
+
+// Site status after a fetch that failed one of the site's assertions
+pub const EXTRACTION_FAILED: &str = "EXTRACTION_FAILED";
+
+// Empty means "no pattern"; invalid patterns are rejected up front
+pub fn validate_pattern(pattern: Option<&str>) -> Result<Option<String>, String> {
+    match pattern.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(pattern) => Regex::new(pattern)
+            .map(|_| Some(pattern.to_string()))
+            .map_err(|e| format!("Invalid assert_pattern '{}': {}", pattern, e)),
+    }
+}
+
+// Describes the first assertion the fetched body fails. The selector is looked up in the
+// whole page; pattern and length apply to the visible text of the site's selected region.
+pub fn check(site: &Site, body: &str) -> Result<(), String> {
+    if let Some(sel) = &site.assert_selector {
+        let selector = parse_selector(sel)?;
+        if Html::parse_document(body).select(&selector).next().is_none() {
+            return Err(format!("no element matches '{}'", sel));
+        }
+    }
+    if site.assert_pattern.is_none() && site.assert_min_length.is_none() {
+        return Ok(());
+    }
+
+    let text = page_text(body, site.selector.as_deref());
+    if let Some(pattern) = &site.assert_pattern {
+        let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+        if !regex.is_match(&text) {
+            return Err(format!("text does not match /{}/", pattern));
+        }
+    }
+    if let Some(min_length) = site.assert_min_length {
+        let length = text.chars().count() as i64;
+        if length < min_length {
+            return Err(format!("only {} characters of text, expected at least {}", length, min_length));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_first_failed_assertion() {
+        let page = r#"<html><body><table class="prices"><tr><td>AAPL 190.2</td></tr></table></body></html>"#;
+        let block_page = "<html><body><h1>Access denied</h1></body></html>";
+        let site = Site {
+            assert_selector: Some("table.prices".into()),
+            assert_pattern: Some(r"\d+\.\d".into()),
+            assert_min_length: Some(5),
+            ..Site::new("https://a.example", 60, "random")
+        };
+        assert_eq!(check(&site, page), Ok(()));
+        assert_eq!(check(&site, block_page), Err("no element matches 'table.prices'".into()));
+
+        let site = Site { assert_selector: None, assert_min_length: Some(50), ..site };
+        assert_eq!(check(&site, page), Err("only 10 characters of text, expected at least 50".into()));
+        assert_eq!(check(&site, block_page), Err(r"text does not match /\d+\.\d/".into()));
+        assert!(validate_pattern(Some("(")).is_err());
+        assert_eq!(validate_pattern(Some(" ")), Ok(None));
+    }
+}
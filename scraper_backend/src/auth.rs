@@ -0,0 +1,471 @@
+use super::storage::Storage;
+use super::AppState;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpResponse, Responder};
+use chrono::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use std::fmt;
+
+// Keys and tokens are sent as `X-API-Key: <key>` or `Authorization: Bearer <key>`;
+// GET requests may also pass `?token=<key>`, since EventSource can't set headers
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+const DEFAULT_TOKEN_TTL_HOURS: i64 = 12;
+const MAX_TOKEN_TTL_HOURS: i64 = 24 * 30;
+
+// What a request needs, weakest first. A key or token grants one level and everything
+// below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Access {
+    // The dashboard page and probes outside /api
+    Public,
+    Read,
+    AcknowledgeAlerts,
+    Write,
+    Admin,
+}
+
+// Admin keys can do everything write keys can
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Write,
+    Admin,
+}
+
+impl Role {
+    fn parse(role: &str) -> Option<Role> {
+        match role {
+            "write" => Some(Role::Write),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Role::Write => "write",
+            Role::Admin => "admin",
+        }
+    }
+
+    fn access(self) -> Access {
+        match self {
+            Role::Write => Access::Write,
+            Role::Admin => Access::Admin,
+        }
+    }
+}
+
+// What a token minted through /api/admin/tokens may do
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Read,
+    // Read, plus acknowledging alerts
+    AlertsAck,
+    Admin,
+}
+
+impl Scope {
+    fn parse(scope: &str) -> Option<Scope> {
+        match scope {
+            "read" => Some(Scope::Read),
+            "alerts_ack" => Some(Scope::AlertsAck),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::AlertsAck => "alerts_ack",
+            Scope::Admin => "admin",
+        }
+    }
+
+    fn access(self) -> Access {
+        match self {
+            Scope::Read => Access::Read,
+            Scope::AlertsAck => Access::AcknowledgeAlerts,
+            Scope::Admin => Access::Admin,
+        }
+    }
+}
+
+// A key created through /api/admin/keys
+#[derive(Serialize, FromRow, Clone)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// A short-lived token, typically handed to a colleague's dashboard session
+#[derive(Serialize, FromRow, Clone)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// Name of the key or token a request was made with, for handlers that record who acted
+#[derive(Clone)]
+pub struct Caller(pub String);
+
+// Keys from the api_keys setting, kept as hashes. Debug output names them without
+// the hash, since the loaded config is logged at startup.
+#[derive(Clone, Default)]
+pub struct ConfigKeys(Vec<(String, String, Role)>);
+
+impl fmt::Debug for ConfigKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.iter().map(|(name, _, role)| format!("{} ({})", name, role.as_str()))).finish()
+    }
+}
+
+impl ConfigKeys {
+    // A sequence of { name, key, role } maps; a missing setting means no keys
+    pub fn from_yaml(value: &serde_yaml::Value) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        for (i, entry) in value.as_sequence().into_iter().flatten().enumerate() {
+            let name = entry["name"].as_str().map(String::from).unwrap_or_else(|| format!("key {}", i + 1));
+            let key = entry["key"].as_str().map(str::trim).filter(|k| !k.is_empty());
+            let Some(key) = key else {
+                return Err(format!("api_keys entry '{}' has no key", name));
+            };
+            let role = entry["role"].as_str().unwrap_or("write");
+            let Some(role) = Role::parse(role) else {
+                return Err(format!("api_keys entry '{}' has unknown role '{}'", name, role));
+            };
+            keys.push((name, key_hash(key), role));
+        }
+        Ok(ConfigKeys(keys))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn find(&self, hash: &str) -> Option<(String, Role)> {
+        self.0.iter().find(|(_, h, _)| h == hash).map(|(name, _, role)| (name.clone(), *role))
+    }
+}
+
+pub fn key_hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+// Reading is open unless protect_reads is set; acknowledging an alert needs at least an
+// alerts_ack token, anything else that changes state needs a key, and wiping or rewriting
+// stored data needs admin. reset-db is a GET, so it is matched by path.
+fn required_access(method: &Method, path: &str) -> Access {
+    if path == "/api/reset-db" || path.starts_with("/api/admin/") {
+        Access::Admin
+    } else if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        if path.starts_with("/api/") { Access::Read } else { Access::Public }
+    } else if *method == Method::POST && path.starts_with("/api/alerts/") && path.ends_with("/ack") {
+        Access::AcknowledgeAlerts
+    } else {
+        Access::Write
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenParam {
+    token: Option<String>,
+}
+
+fn presented_key(req: &ServiceRequest) -> Option<String> {
+    let headers = req.headers();
+    let key = match headers.get(API_KEY_HEADER) {
+        Some(value) => value.to_str().ok().map(String::from),
+        None => headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok()).and_then(|v| v.strip_prefix("Bearer ")).map(String::from),
+    };
+    let key = key.or_else(|| {
+        let query = (req.method() == Method::GET).then(|| web::Query::<TokenParam>::from_query(req.query_string()).ok()).flatten();
+        query.and_then(|q| q.into_inner().token)
+    });
+    key.map(|k| k.trim().to_string()).filter(|k| !k.is_empty())
+}
+
+fn database_error(e: impl fmt::Display) -> HttpResponse {
+    HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+}
+
+// Without a credential, writes are refused unless allow_open_writes is set; admin routes
+// always are. Returns who made the request.
+async fn authorize(
+    storage: &dyn Storage,
+    config_keys: &ConfigKeys,
+    protect_reads: bool,
+    allow_open_writes: bool,
+    key: Option<&str>,
+    required: Access,
+    now: DateTime<Utc>,
+) -> Result<Option<Caller>, HttpResponse> {
+    let Some(key) = key else {
+        let open = if allow_open_writes {
+            Access::Write
+        } else if protect_reads {
+            Access::Public
+        } else {
+            Access::Read
+        };
+        return match required {
+            _ if required <= open => Ok(None),
+            Access::Admin => Err(HttpResponse::Unauthorized().body("This endpoint requires an admin API key; configure one in api_keys")),
+            _ => Err(HttpResponse::Unauthorized().body(format!("API key required: send it in the {} header", API_KEY_HEADER))),
+        };
+    };
+
+    let stored = storage.list_api_keys().await.map_err(database_error)?;
+    let hash = key_hash(key);
+    let found = config_keys.find(&hash)
+        .or_else(|| stored.iter().find(|k| k.key_hash == hash).and_then(|k| Some((k.name.clone(), Role::parse(&k.role)?))))
+        .map(|(name, role)| (name, role.access()));
+    let (name, granted) = match found {
+        Some(found) => found,
+        None => {
+            let tokens = storage.list_api_tokens().await.map_err(database_error)?;
+            match tokens.iter().find(|t| t.token_hash == hash) {
+                Some(token) if token.expires_at <= now => return Err(HttpResponse::Unauthorized().body("API token has expired")),
+                Some(token) => match Scope::parse(&token.scope) {
+                    Some(scope) => (token.name.clone(), scope.access()),
+                    None => return Err(HttpResponse::Unauthorized().body("Invalid API key")),
+                },
+                None => return Err(HttpResponse::Unauthorized().body("Invalid API key")),
+            }
+        },
+    };
+    match required {
+        _ if granted >= required => Ok(Some(Caller(name))),
+        Access::Admin => Err(HttpResponse::Forbidden().body("This endpoint requires an admin API key")),
+        Access::Write => Err(HttpResponse::Forbidden().body("This token can't make changes")),
+        _ => Err(HttpResponse::Forbidden().body("This token is read-only")),
+    }
+}
+
+// The path as the router matches it, percent-encoding decoded. req.path() is still encoded,
+// so /api/%72eset-db would reach reset-db without the admin check.
+fn routed_path(req: &ServiceRequest) -> &str {
+    req.match_info().as_str()
+}
+
+pub async fn require_api_key<B: MessageBody>(req: ServiceRequest, next: Next<B>) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let required = required_access(req.method(), routed_path(&req));
+    let Some(data) = req.app_data::<web::Data<AppState>>().cloned() else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+    // Open reads skip the key lookup
+    if required == Access::Public || (required == Access::Read && !data.config.protect_reads) {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+    let key = presented_key(&req);
+    match authorize(data.storage.as_ref(), &data.config.api_keys, data.config.protect_reads, data.config.allow_open_writes, key.as_deref(), required, Utc::now()).await {
+        Ok(caller) => {
+            if let Some(caller) = caller {
+                req.extensions_mut().insert(caller);
+            }
+            next.call(req).await.map(ServiceResponse::map_into_left_body)
+        },
+        Err(response) => Ok(req.into_response(response).map_into_right_body()),
+    }
+}
+
+fn generate_secret() -> String {
+    thread_rng().sample_iter(&Alphanumeric).take(40).map(char::from).collect()
+}
+
+#[derive(Deserialize)]
+pub struct NewApiKey {
+    name: String,
+    role: Option<Role>,
+}
+
+// GET /api/admin/keys: stored keys without their hashes; keys from config aren't listed
+pub async fn list_keys(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.list_api_keys().await {
+        Ok(keys) => HttpResponse::Ok().json(keys),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// POST /api/admin/keys: the generated key is only returned here
+pub async fn add_key(data: web::Data<AppState>, payload: web::Json<NewApiKey>) -> impl Responder {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return HttpResponse::BadRequest().body("API key name must not be empty");
+    }
+    let key = generate_secret();
+    let api_key = ApiKey {
+        id: 0,
+        name: name.to_string(),
+        key_hash: key_hash(&key),
+        role: payload.role.unwrap_or(Role::Write).as_str().to_string(),
+        created_at: Utc::now(),
+    };
+
+    match data.storage.insert_api_key(&api_key).await {
+        Ok(id) => HttpResponse::Ok().json(serde_json::json!({
+            "id": id,
+            "name": api_key.name,
+            "role": api_key.role,
+            "created_at": api_key.created_at,
+            "key": key,
+        })),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn delete_key(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    match data.storage.delete_api_key(id).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("API key with ID {} not found", id)),
+        Ok(true) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewApiToken {
+    name: String,
+    scope: Scope,
+    ttl_hours: Option<i64>,
+}
+
+// GET /api/admin/tokens: minted tokens without their hashes, expired ones included
+pub async fn list_tokens(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.list_api_tokens().await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// POST /api/admin/tokens: like keys, the token itself is only returned here
+pub async fn add_token(data: web::Data<AppState>, payload: web::Json<NewApiToken>) -> impl Responder {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return HttpResponse::BadRequest().body("API token name must not be empty");
+    }
+    let ttl_hours = payload.ttl_hours.unwrap_or(DEFAULT_TOKEN_TTL_HOURS);
+    if !(1..=MAX_TOKEN_TTL_HOURS).contains(&ttl_hours) {
+        return HttpResponse::BadRequest().body(format!("ttl_hours must be between 1 and {}", MAX_TOKEN_TTL_HOURS));
+    }
+    let token = generate_secret();
+    let created_at = Utc::now();
+    let api_token = ApiToken {
+        id: 0,
+        name: name.to_string(),
+        token_hash: key_hash(&token),
+        scope: payload.scope.as_str().to_string(),
+        created_at,
+        expires_at: created_at + Duration::hours(ttl_hours),
+    };
+
+    match data.storage.insert_api_token(&api_token).await {
+        Ok(id) => HttpResponse::Ok().json(serde_json::json!({
+            "id": id,
+            "name": api_token.name,
+            "scope": api_token.scope,
+            "created_at": api_token.created_at,
+            "expires_at": api_token.expires_at,
+            "token": token,
+        })),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn delete_token(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    match data.storage.delete_api_token(id).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("API token with ID {} not found", id)),
+        Ok(true) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use actix_web::http::StatusCode;
+
+    #[tokio::test]
+    async fn keys_tokens_and_roles_gate_routes() {
+        assert_eq!(required_access(&Method::GET, "/api/sites"), Access::Read);
+        assert_eq!(required_access(&Method::GET, "/"), Access::Public);
+        assert_eq!(required_access(&Method::DELETE, "/api/sites/3"), Access::Write);
+        assert_eq!(required_access(&Method::POST, "/api/alerts/7/ack"), Access::AcknowledgeAlerts);
+        assert_eq!(required_access(&Method::GET, "/api/reset-db"), Access::Admin);
+        assert_eq!(required_access(&Method::POST, "/api/admin/reprocess"), Access::Admin);
+        // Decided on the decoded path the router sees
+        let routed = |method: Method, uri: &str| {
+            let req = actix_web::test::TestRequest::default().method(method).uri(uri).to_srv_request();
+            required_access(req.method(), routed_path(&req))
+        };
+        assert_eq!(routed(Method::GET, "/api/%72eset-db"), Access::Admin);
+        assert_eq!(routed(Method::POST, "/api/%61dmin/reprocess"), Access::Admin);
+        assert_eq!(routed(Method::POST, "/%61pi/alerts/7/%61ck"), Access::AcknowledgeAlerts);
+        assert_eq!(routed(Method::GET, "/%61pi/sites"), Access::Read);
+
+        let storage = MemoryStorage::new();
+        let now = Utc::now();
+        let status = |result: Result<Option<Caller>, HttpResponse>| result.err().map(|r| r.status());
+        let none = ConfigKeys::default();
+        // No keys anywhere: writes need one all the same, unless open writes are allowed;
+        // admin routes always do
+        assert_eq!(status(authorize(&storage, &none, false, false, None, Access::Write, now).await), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(authorize(&storage, &none, false, false, None, Access::Read, now).await), None);
+        assert_eq!(status(authorize(&storage, &none, false, true, None, Access::Write, now).await), None);
+        assert_eq!(status(authorize(&storage, &none, false, true, None, Access::Admin, now).await), Some(StatusCode::UNAUTHORIZED));
+
+        let yaml: serde_yaml::Value = serde_yaml::from_str("[{name: ops, key: secret-admin, role: admin}]").unwrap();
+        let config = ConfigKeys::from_yaml(&yaml).unwrap();
+        assert_eq!(format!("{:?}", config), r#"["ops (admin)"]"#);
+        assert_eq!(status(authorize(&storage, &config, false, false, None, Access::Write, now).await), Some(StatusCode::UNAUTHORIZED));
+        let caller = authorize(&storage, &config, false, false, Some("secret-admin"), Access::Admin, now).await.ok().flatten();
+        assert_eq!(caller.map(|c| c.0).as_deref(), Some("ops"));
+        assert_eq!(status(authorize(&storage, &config, false, false, Some("guess"), Access::Write, now).await), Some(StatusCode::UNAUTHORIZED));
+
+        // Stored keys work alongside config keys and on their own
+        let writer = ApiKey { id: 0, name: "ci".into(), key_hash: key_hash("ci-key"), role: "write".into(), created_at: now };
+        storage.insert_api_key(&writer).await.unwrap();
+        assert_eq!(status(authorize(&storage, &config, false, false, Some("ci-key"), Access::Write, now).await), None);
+        assert_eq!(status(authorize(&storage, &none, false, false, Some("ci-key"), Access::Admin, now).await), Some(StatusCode::FORBIDDEN));
+        assert_eq!(status(authorize(&storage, &none, false, false, None, Access::Write, now).await), Some(StatusCode::UNAUTHORIZED));
+
+        // Tokens are limited to their scope until they expire; protect_reads closes reads to everyone else
+        let token = |name: &str, scope: Scope, expires_at| ApiToken {
+            id: 0, name: name.into(), token_hash: key_hash(name), scope: scope.as_str().into(), created_at: now, expires_at,
+        };
+        storage.insert_api_token(&token("viewer", Scope::Read, now + Duration::hours(1))).await.unwrap();
+        storage.insert_api_token(&token("on-call", Scope::AlertsAck, now + Duration::hours(1))).await.unwrap();
+        storage.insert_api_token(&token("old", Scope::Admin, now)).await.unwrap();
+        assert_eq!(status(authorize(&storage, &none, true, false, None, Access::Read, now).await), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(authorize(&storage, &none, true, false, Some("viewer"), Access::Read, now).await), None);
+        assert_eq!(status(authorize(&storage, &none, true, false, Some("viewer"), Access::AcknowledgeAlerts, now).await), Some(StatusCode::FORBIDDEN));
+        assert_eq!(status(authorize(&storage, &none, true, false, Some("on-call"), Access::AcknowledgeAlerts, now).await), None);
+        assert_eq!(status(authorize(&storage, &none, true, false, Some("on-call"), Access::Write, now).await), Some(StatusCode::FORBIDDEN));
+        assert_eq!(status(authorize(&storage, &none, true, false, Some("old"), Access::Read, now).await), Some(StatusCode::UNAUTHORIZED));
+
+        assert!(ConfigKeys::from_yaml(&serde_yaml::from_str("[{name: x, role: admin}]").unwrap()).is_err());
+        assert!(ConfigKeys::from_yaml(&serde_yaml::from_str("[{key: k, role: root}]").unwrap()).is_err());
+        assert!(ConfigKeys::from_yaml(&serde_yaml::Value::Null).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use super::{scraper::SchedulerEvent, AppState};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+
+// Slack posts slash commands as form data, other integrations send JSON
+#[derive(Deserialize)]
+pub struct CommandRequest {
+    text: String,
+}
+
+pub async fn handle_command(
+    data: web::Data<AppState>,
+    payload: web::Either<web::Json<CommandRequest>, web::Form<CommandRequest>>,
+) -> impl Responder {
+    let text = match payload {
+        web::Either::Left(json) => json.into_inner().text,
+        web::Either::Right(form) => form.into_inner().text,
+    };
+
+    let reply = match run_command(&data, text.trim()).await {
+        Ok(reply) => reply,
+        Err(e) => format!("Error: {}", e),
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "response_type": "ephemeral",
+        "text": reply,
+    }))
+}
+
+async fn run_command(data: &AppState, text: &str) -> Result<String, String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    match words.as_slice() {
+        [] | ["help"] => Ok(help_text()),
+        ["status"] => status(data).await,
+        // "last 5 changes for NVDA" / "last changes for nvidia"
+        ["last", rest @ ..] => {
+            let (count, rest) = match rest.first().and_then(|w| w.parse::<i64>().ok()) {
+                Some(n) => (n.clamp(1, 50), &rest[1..]),
+                None => (5, rest),
+            };
+            let rest: Vec<&str> = rest.iter()
+                .copied()
+                .skip_while(|w| *w == "changes" || *w == "change")
+                .collect();
+            let term = match rest.as_slice() {
+                ["for", term @ ..] if !term.is_empty() => Some(term.join(" ")),
+                [] => None,
+                _ => return Err("usage: last [N] changes [for <term>]".into()),
+            };
+            last_changes(data, term.as_deref(), count).await
+        },
+        ["snooze", term, duration] => {
+            let duration = parse_duration(duration).ok_or("duration must look like 30m, 2h or 1d")?;
+            snooze(data, term, Some(duration)).await
+        },
+        ["unsnooze", term] => snooze(data, term, None).await,
+        _ => Err(format!("unknown command '{}'. {}", text, help_text())),
+    }
+}
+
+fn help_text() -> String {
+    "Commands: status | last [N] changes [for <term>] | snooze <term> <30m|2h|1d> | unsnooze <term>".into()
+}
+
+async fn status(data: &AppState) -> Result<String, String> {
+    let stats = data.storage.site_stats(Utc::now())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut reply = format!(
+        "{} sites: {} OK, {} ERROR, {} pending, {} snoozed",
+        stats.total, stats.ok, stats.errors, stats.pending, stats.snoozed
+    );
+    if let Some(ts) = stats.last_change {
+        reply.push_str(&format!("\nLast change detected {}", ts.format("%Y-%m-%d %H:%M:%S UTC")));
+    }
+    Ok(reply)
+}
+
+async fn last_changes(data: &AppState, term: Option<&str>, count: i64) -> Result<String, String> {
+    let changes = data.storage.recent_changes(term, count)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if changes.is_empty() {
+        return Ok(match term {
+            Some(term) => format!("No recent changes matching '{}'", term),
+            None => "No recent changes".into(),
+        });
+    }
+
+    let lines: Vec<String> = changes.iter()
+        .map(|c| format!("[{}] {}", c.timestamp.format("%Y-%m-%d %H:%M UTC"), c.url))
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+// Snooze (or unsnooze when duration is None) every site whose URL contains `term`
+async fn snooze(data: &AppState, term: &str, duration: Option<Duration>) -> Result<String, String> {
+    let until = match duration {
+        Some(d) => Some(Utc::now().checked_add_signed(d).ok_or("duration is too long")?),
+        None => None,
+    };
+    let term_lower = term.to_lowercase();
+
+    let ids: Vec<i64> = data.storage.list_sites()
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|s| s.url.to_lowercase().contains(&term_lower))
+        .map(|s| s.id)
+        .collect();
+
+    if ids.is_empty() {
+        return Err(format!("no sites match '{}'", term));
+    }
+
+    for id in &ids {
+        data.storage.set_snoozed_until(*id, until).await.map_err(|e| e.to_string())?;
+        let _ = data.scheduler.send(SchedulerEvent::SiteUpdated(*id));
+    }
+
+    Ok(match until {
+        Some(until) => format!("Snoozed {} site(s) matching '{}' until {}", ids.len(), term, until.format("%Y-%m-%d %H:%M UTC")),
+        None => format!("Unsnoozed {} site(s) matching '{}'", ids.len(), term),
+    })
+}
+
+// Parse durations like "90s", "30m", "2h", "1d"; None past what a Duration holds
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let (value, unit) = text.split_at(split);
+    let value: i64 = value.parse().ok()?;
+    match unit {
+        "s" => Duration::try_seconds(value),
+        "m" => Duration::try_minutes(value),
+        "h" => Duration::try_hours(value),
+        "d" => Duration::try_days(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_snooze_durations() {
+        assert_eq!(parse_duration("90s"), Some(Duration::seconds(90)));
+        assert_eq!(parse_duration("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_duration("1d"), Some(Duration::days(1)));
+        assert_eq!(parse_duration("2w"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("999999999999d"), None);
+    }
+}
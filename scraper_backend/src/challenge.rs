@@ -0,0 +1,143 @@
+use super::alerts::page_text;
+use super::scraper;
+use reqwest::header::HeaderMap;
+use serde_json::{json, Value};
+use std::fmt;
+use std::time::Duration;
+use tracing::info;
+
+// Site status after a fetch that got an anti-bot challenge instead of the page
+pub const CHALLENGE: &str = "CHALLENGE";
+
+// Markers only challenge and block pages carry, lowercased, with who serves them
+const MARKERS: &[(&str, &str)] = &[
+    ("window._cf_chl_opt", "Cloudflare"),
+    ("<title>just a moment...</title>", "Cloudflare"),
+    ("<title>attention required! | cloudflare</title>", "Cloudflare"),
+    ("id=\"challenge-error-text\"", "Cloudflare"),
+    ("captcha-delivery.com", "DataDome"),
+    ("id=\"px-captcha\"", "PerimeterX"),
+    ("check.ddos-guard.net", "DDoS-Guard"),
+    ("<title>sucuri website firewall", "Sucuri"),
+];
+// Captcha widgets, which ordinary pages embed in forms too: only a page with little else
+// on it is taken for a challenge
+const CAPTCHAS: &[&str] = &["g-recaptcha", "h-captcha", "cf-turnstile", "hcaptcha.com/1/api.js", "challenges.cloudflare.com/turnstile"];
+const MAX_CAPTCHA_PAGE_TEXT: usize = 500;
+
+// Cloudflare's header on responses it answered with a challenge
+pub fn is_mitigated(headers: &HeaderMap) -> bool {
+    headers.get("cf-mitigated").and_then(|v| v.to_str().ok()).is_some_and(|v| v.eq_ignore_ascii_case("challenge"))
+}
+
+// What kind of challenge the page is, such as "Cloudflare challenge"; None for the site's
+// own content
+pub fn detect(mitigated: bool, body: &str) -> Option<String> {
+    if mitigated {
+        return Some("Cloudflare challenge".to_string());
+    }
+    let lower = body.to_lowercase();
+    if let Some((_, vendor)) = MARKERS.iter().find(|(marker, _)| lower.contains(marker)) {
+        return Some(format!("{} challenge", vendor));
+    }
+    let captcha = CAPTCHAS.iter().any(|marker| lower.contains(marker));
+    (captcha && page_text(body, None).chars().count() <= MAX_CAPTCHA_PAGE_TEXT).then(|| "captcha page".to_string())
+}
+
+// A FlareSolverr endpoint, or another browser service speaking its API, that challenged
+// pages are fetched again through. It is the operator's own service, usually on this
+// machine, so like webhooks it is reached directly and past the network guard; the
+// solver's browser goes through the site's proxy instead.
+#[derive(Clone)]
+pub struct Solver {
+    // Such as http://localhost:8191/v1
+    url: String,
+    timeout: Duration,
+    client: reqwest::Client,
+}
+
+// A page as a check reads it: what the site sent, or what the solver got past its challenge
+pub struct Page {
+    pub status: u16,
+    pub body: String,
+    // Set while a challenge stands, describing it
+    pub challenge: Option<String>,
+}
+
+impl fmt::Debug for Solver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Solver").field("url", &self.url).field("timeout", &self.timeout).finish_non_exhaustive()
+    }
+}
+
+impl Solver {
+    pub fn new(url: String, timeout: Duration) -> Self {
+        Solver { url, timeout, client: scraper::build_http_client() }
+    }
+
+    // The page behind the challenge, as the solver's browser loaded it through the same
+    // proxy as the site
+    async fn solve(&self, url: &str, proxy: Option<&str>) -> Result<(u16, String), String> {
+        let mut request = json!({"cmd": "request.get", "url": url, "maxTimeout": self.timeout.as_millis() as u64});
+        if let Some(proxy) = proxy {
+            request["proxy"] = json!({"url": proxy});
+        }
+        let resp = self.client
+            .post(&self.url)
+            .json(&request)
+            .timeout(self.timeout + Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let reply: Value = resp.json().await.map_err(|e| format!("unreadable reply: {}", e))?;
+        if reply["status"] != "ok" {
+            return Err(reply["message"].as_str().unwrap_or("no solution").to_string());
+        }
+        let solution = &reply["solution"];
+        let body = solution["response"].as_str().ok_or("reply without a page")?;
+        Ok((solution["status"].as_u64().unwrap_or(200) as u16, body.to_string()))
+    }
+}
+
+// Passes a fetched page through challenge detection: a challenge is fetched again through
+// the solver when there is one, and stands when there isn't or it fails
+pub async fn pass(solver: Option<&Solver>, url: &str, proxy: Option<&str>, status: u16, mitigated: bool, body: String) -> Page {
+    let Some(challenge) = detect(mitigated, &body) else {
+        return Page { status, body, challenge: None };
+    };
+    let Some(solver) = solver else {
+        return Page { status, body, challenge: Some(challenge) };
+    };
+    match solver.solve(url, proxy).await {
+        Ok((status, solved)) => match detect(false, &solved) {
+            None => {
+                info!(url, "Got past a {} through the challenge solver", challenge);
+                Page { status, body: solved, challenge: None }
+            },
+            Some(_) => Page { status, body, challenge: Some(format!("{}, also through the challenge solver", challenge)) },
+        },
+        Err(e) => Page { status, body, challenge: Some(format!("{}; challenge solver failed: {}", challenge, e)) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_interstitials_but_not_pages_with_a_captcha_form() {
+        let cloudflare = r#"<!DOCTYPE html><html><head><title>Just a moment...</title></head><body>
+            <noscript>Enable JavaScript and cookies to continue</noscript>
+            <script>(function(){window._cf_chl_opt={cvId: '3'};})();</script></body></html>"#;
+        assert_eq!(detect(false, cloudflare).as_deref(), Some("Cloudflare challenge"));
+        assert_eq!(detect(true, "<html></html>").as_deref(), Some("Cloudflare challenge"));
+        assert_eq!(detect(false, r#"<html><body><script src="https://ct.captcha-delivery.com/c.js"></script></body></html>"#).as_deref(), Some("DataDome challenge"));
+
+        let captcha = r#"<html><body><p>Please verify you are a human</p><div class="g-recaptcha" data-sitekey="x"></div></body></html>"#;
+        assert_eq!(detect(false, captcha).as_deref(), Some("captcha page"));
+        let contact = format!("<html><body><article>{}</article><form><div class=\"g-recaptcha\"></div></form></body></html>", "Quarterly results are out. ".repeat(40));
+        assert_eq!(detect(false, &contact), None);
+        // Cloudflare's bot script is on many ordinary pages
+        assert_eq!(detect(false, r#"<html><body><p>News</p><script src="/cdn-cgi/challenge-platform/scripts/jsd/main.js"></script></body></html>"#), None);
+    }
+}
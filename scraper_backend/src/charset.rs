@@ -0,0 +1,88 @@
+use encoding_rs::{Encoding, UTF_8};
+use regex::bytes::Regex;
+use std::sync::OnceLock;
+
+// How far into a document its <meta charset> or XML declaration is looked for
+const PRESCAN_BYTES: usize = 4096;
+
+fn encoding_for(label: &[u8]) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim_ascii())
+}
+
+// charset parameter of a Content-Type header
+fn header_charset(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';')
+        .filter_map(|param| param.trim().split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, value)| encoding_for(value.trim().trim_matches(['"', '\'']).as_bytes()))
+}
+
+// Encoding named by the document itself: <meta charset>, <meta http-equiv content> or
+// <?xml encoding>. Pages in GBK, Shift_JIS and the like keep these ASCII, so they can be
+// read before knowing the encoding.
+fn declared_charset(body: &[u8]) -> Option<&'static Encoding> {
+    static PATTERNS: OnceLock<[Regex; 2]> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| [
+        Regex::new(r#"(?i)^\s*<\?xml[^>]*\sencoding\s*=\s*["']([\w.:-]+)"#).unwrap(),
+        Regex::new(r#"(?i)<meta\s[^>]*charset\s*=\s*["']?\s*([\w.:-]+)"#).unwrap(),
+    ]);
+    let head = &body[..body.len().min(PRESCAN_BYTES)];
+    patterns.iter()
+        .find_map(|pattern| pattern.captures(head))
+        .and_then(|captures| encoding_for(&captures[1]))
+        // A page that was saved as UTF-16 can't contain its own ASCII declaration
+        .map(|encoding| encoding.output_encoding())
+}
+
+fn decode_with(encoding: &'static Encoding, body: &[u8]) -> Option<String> {
+    let (text, malformed) = encoding.decode_without_bom_handling(body);
+    (!malformed).then(|| text.into_owned())
+}
+
+// Decodes a response body the way browsers roughly do: a byte order mark wins, then the
+// Content-Type charset unless the body isn't valid in it (servers often send a default
+// charset whatever the page is in), then the charset the document declares. Without any
+// of these, UTF-8 is assumed and invalid bytes are replaced.
+pub fn decode(body: &[u8], content_type: Option<&str>) -> String {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(body) {
+        return encoding.decode_without_bom_handling(&body[bom_length..]).0.into_owned();
+    }
+    let from_header = content_type.and_then(header_charset);
+    let declared = declared_charset(body);
+    for encoding in [from_header, declared].into_iter().flatten() {
+        if let Some(text) = decode_with(encoding, body) {
+            return text;
+        }
+    }
+    from_header.or(declared).unwrap_or(UTF_8).decode_without_bom_handling(body).0.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{GBK, SHIFT_JIS};
+
+    fn page(charset_meta: &str, text: &str, encoding: &'static Encoding) -> Vec<u8> {
+        let html = format!("<html><head>{}<title>{}</title></head><body><p>{}</p></body></html>", charset_meta, text, text);
+        encoding.encode(&html).0.into_owned()
+    }
+
+    #[test]
+    fn decodes_gbk_and_shift_jis_pages() {
+        let mofcom = page(r#"<meta http-equiv="Content-Type" content="text/html; charset=gb2312">"#, "商务部新闻发布", GBK);
+        let nikkei = page(r#"<meta charset="Shift_JIS">"#, "日本経済新聞", SHIFT_JIS);
+
+        // Declared by the page only, and by the header only
+        assert!(decode(&mofcom, Some("text/html")).contains("<p>商务部新闻发布</p>"));
+        assert!(decode(&page("", "日本経済新聞", SHIFT_JIS), Some("text/html; charset=\"shift_jis\"")).contains("日本経済新聞"));
+        // A header claiming UTF-8 for a Shift_JIS page loses to the page's own declaration
+        assert!(decode(&nikkei, Some("text/html; charset=UTF-8")).contains("<title>日本経済新聞</title>"));
+
+        let feed = GBK.encode("<?xml version=\"1.0\" encoding=\"GBK\"?><rss><channel><title>公告</title></channel></rss>").0;
+        assert!(decode(&feed, Some("application/xml")).contains("<title>公告</title>"));
+
+        assert_eq!(decode(b"\xEF\xBB\xBFcaf\xC3\xA9", Some("text/html; charset=iso-8859-1")), "café");
+        assert_eq!(decode("plain UTF-8 – ok".as_bytes(), None), "plain UTF-8 – ok");
+        assert_eq!(decode(b"caf\xE9", None), "caf\u{FFFD}");
+    }
+}
@@ -0,0 +1,83 @@
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgTypeInfo, PgValueRef};
+use sqlx::sqlite::{SqliteTypeInfo, SqliteValueRef};
+use sqlx::{Decode, Postgres, Sqlite, Type};
+
+// Stored content starts with a NUL byte and a format version. Plain text written before
+// compression never starts with NUL, so old rows stay readable until they are rewritten.
+const MARKER: u8 = 0;
+const FORMAT_ZSTD: u8 = 1;
+const ZSTD_LEVEL: i32 = 3;
+
+pub fn compress(content: &str) -> Vec<u8> {
+    let mut stored = vec![MARKER, FORMAT_ZSTD];
+    // Encoding from and into memory can't fail
+    stored.extend(zstd::encode_all(content.as_bytes(), ZSTD_LEVEL).unwrap());
+    stored
+}
+
+pub fn decompress(stored: &[u8]) -> Result<String, String> {
+    let bytes = match stored {
+        [MARKER, FORMAT_ZSTD, frame @ ..] => zstd::decode_all(frame).map_err(|e| format!("corrupt compressed content: {}", e))?,
+        [MARKER, format, ..] => return Err(format!("unknown content format {}", format)),
+        plain => plain.to_vec(),
+    };
+    String::from_utf8(bytes).map_err(|e| format!("stored content is not UTF-8: {}", e))
+}
+
+// A stored body, decompressed as it is read. Convert with String::from.
+pub struct StoredContent(String);
+
+impl From<StoredContent> for String {
+    fn from(content: StoredContent) -> String {
+        content.0
+    }
+}
+
+impl Type<Sqlite> for StoredContent {
+    fn type_info() -> SqliteTypeInfo {
+        <Vec<u8> as Type<Sqlite>>::type_info()
+    }
+
+    // SQLite keeps each value's own type, so uncompressed rows are still TEXT
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <Vec<u8> as Type<Sqlite>>::compatible(ty) || <String as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for StoredContent {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(StoredContent(decompress(<&[u8] as Decode<Sqlite>>::decode(value)?)?))
+    }
+}
+
+impl Type<Postgres> for StoredContent {
+    fn type_info() -> PgTypeInfo {
+        <Vec<u8> as Type<Postgres>>::type_info()
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for StoredContent {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(StoredContent(decompress(<&[u8] as Decode<Postgres>>::decode(value)?)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_reads_plain_text() {
+        let page = format!("<html><body>{}</body></html>", "<p>Same row again</p>".repeat(500));
+        let stored = compress(&page);
+        assert_eq!(&stored[..2], &[MARKER, FORMAT_ZSTD]);
+        assert!(stored.len() * 20 < page.len());
+        assert_eq!(decompress(&stored).unwrap(), page);
+
+        assert_eq!(decompress(b"<p>before compression</p>").unwrap(), "<p>before compression</p>");
+        assert_eq!(decompress(&compress("")).unwrap(), "");
+        assert!(decompress(&[MARKER, 9, 1, 2]).is_err());
+        assert!(decompress(&[MARKER, FORMAT_ZSTD, 1, 2]).is_err());
+    }
+}
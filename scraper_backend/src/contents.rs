@@ -0,0 +1,49 @@
+use super::storage::SharedStorage;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+// Inline updates moved per batch after startup
+const BACKLOG_BATCH: i64 = 100;
+
+// Key of a fetched body in the contents table: the SHA-256 of the raw body, not the
+// site's diff hash, so bodies that only differ outside the selector are kept apart
+pub fn content_address(body: &str) -> String {
+    format!("{:x}", Sha256::digest(body.as_bytes()))
+}
+
+// Key of a fetched PDF in the pdfs table, the SHA-256 of the file
+pub fn pdf_address(pdf: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(pdf))
+}
+
+// Moves updates stored before the contents table existed, a batch at a time
+pub async fn move_inline_backlog(storage: SharedStorage) {
+    let mut moved = 0;
+    loop {
+        match storage.move_inline_content(BACKLOG_BATCH).await {
+            Ok(0) => break,
+            Ok(count) => moved += count,
+            Err(e) => {
+                warn!("Moving stored updates to the contents table failed: {}", e);
+                return;
+            },
+        }
+    }
+    if moved > 0 {
+        info!("Moved {} stored update(s) to the contents table", moved);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_are_stable_hex_digests() {
+        let address = content_address("<p>page</p>");
+        assert_eq!(address.len(), 64);
+        assert_eq!(address, content_address("<p>page</p>"));
+        assert_ne!(address, content_address("<p>page</p> "));
+        assert_eq!(content_address(""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+}
@@ -0,0 +1,123 @@
+use super::scraper::fetch_text;
+use super::{AppConfig, Site};
+use regex::{Captures, Regex};
+use reqwest::Url;
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use tracing::debug;
+
+// Sites with a crawl_pattern walk the pages of a paginated listing: links on the site's
+// page whose URL matches it are fetched too, breadth first, and their pages appended to the
+// body, so the hash, links and items cover the whole window
+pub const DEFAULT_DEPTH: i64 = 1;
+pub const DEFAULT_MAX_PAGES: i64 = 3;
+pub const MAX_DEPTH: i64 = 5;
+// The site's own page included
+pub const MAX_PAGES: i64 = 20;
+
+// Empty means no crawl; depth and page limits must be within the caps, 0 the defaults
+pub fn validate(pattern: Option<&str>, depth: Option<i64>, max_pages: Option<i64>) -> Result<Option<String>, String> {
+    if depth.is_some_and(|n| !(0..=MAX_DEPTH).contains(&n)) {
+        return Err(format!("crawl_depth must be between 0 and {}", MAX_DEPTH));
+    }
+    if max_pages.is_some_and(|n| !(0..=MAX_PAGES).contains(&n)) {
+        return Err(format!("crawl_max_pages must be between 0 and {}", MAX_PAGES));
+    }
+    match pattern.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(pattern) => Regex::new(pattern)
+            .map(|_| Some(pattern.to_string()))
+            .map_err(|e| format!("Invalid crawl_pattern '{}': {}", pattern, e)),
+    }
+}
+
+fn without_fragment(mut url: Url) -> String {
+    url.set_fragment(None);
+    url.to_string()
+}
+
+// The http(s) links of a page matching the pattern, absolute and without fragments, once
+// each in page order
+fn next_pages(html: &str, page_url: &Url, pattern: &Regex) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let anchors = Selector::parse("a[href]").unwrap();
+    let mut seen = HashSet::new();
+    document.select(&anchors)
+        .filter_map(|anchor| page_url.join(anchor.value().attr("href")?.trim()).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(without_fragment)
+        .filter(|url| pattern.is_match(url) && seen.insert(url.clone()))
+        .collect()
+}
+
+// Links and images of a crawled page made absolute, since its body is read as part of the
+// site's page and resolved against the site's URL
+fn absolute_links(html: &str, page_url: &Url) -> String {
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    let attribute = ATTRIBUTE.get_or_init(|| Regex::new(r#"(?i)(\s(?:href|src)\s*=\s*)(["'])([^"']*)(["'])"#).unwrap());
+    attribute.replace_all(html, |caps: &Captures| {
+        let url = page_url.join(caps[3].trim()).map_or_else(|_| caps[3].to_string(), |url| url.to_string());
+        format!("{}{}{}{}", &caps[1], &caps[2], url, &caps[4])
+    }).into_owned()
+}
+
+// The site's page followed by the listing pages its crawl reaches, within crawl_depth links
+// and crawl_max_pages pages. A page that fails fails the crawl, since a window missing a
+// page would look like its entries were removed.
+pub async fn crawl(client: &reqwest::Client, config: &AppConfig, site: &Site, body: String) -> Result<String, String> {
+    let Some(pattern) = site.crawl_pattern.as_deref().and_then(|pattern| Regex::new(pattern).ok()) else {
+        return Ok(body);
+    };
+    let Ok(start) = Url::parse(&site.url) else {
+        return Ok(body);
+    };
+    let depth = site.crawl_depth.unwrap_or(DEFAULT_DEPTH).clamp(0, MAX_DEPTH);
+    let max_pages = site.crawl_max_pages.unwrap_or(DEFAULT_MAX_PAGES).clamp(1, MAX_PAGES) as usize;
+    let mut visited = HashSet::from([without_fragment(start.clone())]);
+    let mut frontier = vec![(start, body.clone())];
+    let mut window = body;
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for (page_url, html) in &frontier {
+            for url in next_pages(html, page_url, &pattern) {
+                if visited.len() >= max_pages {
+                    break;
+                }
+                if !visited.insert(url.clone()) {
+                    continue;
+                }
+                let page = fetch_text(client, config, site, &url).await.map_err(|e| format!("crawling {} failed: {}", url, e))?;
+                let page_url = Url::parse(&url).map_err(|e| format!("crawling {} failed: {}", url, e))?;
+                window.push('\n');
+                window.push_str(&absolute_links(&page, &page_url));
+                next.push((page_url, page));
+            }
+        }
+        frontier = next;
+    }
+    debug!(pages = visited.len(), "Crawled listing");
+    Ok(window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_matching_links_once_and_absolutizes_crawled_pages() {
+        let page = Url::parse("https://court.example/opinions/?page=1").unwrap();
+        let html = r##"<a href="?page=2">2</a> <a href="?page=3#top">3</a> <a href="/opinions/?page=2">Next</a>
+            <a href="/opinions/2026-1.pdf">Opinion</a> <a href="mailto:clerk@court.example?page=4">Clerk</a>"##;
+        let pattern = Regex::new(r"[?&]page=\d+").unwrap();
+        assert_eq!(next_pages(html, &page, &pattern), vec!["https://court.example/opinions/?page=2", "https://court.example/opinions/?page=3"]);
+
+        let page2 = Url::parse("https://court.example/opinions/page/2/").unwrap();
+        let crawled = absolute_links(r#"<a href="../../2026-2.pdf">Opinion</a><img src='/seal.png'>"#, &page2);
+        assert_eq!(crawled, r#"<a href="https://court.example/opinions/2026-2.pdf">Opinion</a><img src='https://court.example/seal.png'>"#);
+
+        assert!(validate(Some("page=("), None, None).is_err());
+        assert!(validate(Some("page="), Some(MAX_DEPTH + 1), None).is_err());
+        assert_eq!(validate(Some(" "), Some(2), Some(5)).unwrap(), None);
+    }
+}
@@ -0,0 +1,81 @@
+use super::storage::{Activity, ChangeCount, StorageResult};
+use super::{AppState, Site};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Sites listed under top_changing_sites
+const TOP_SITES: i64 = 10;
+// Sites that haven't finished a check yet
+const PENDING: &str = "PENDING";
+
+// System health at a glance, for the landing page
+#[derive(Serialize, Debug)]
+pub struct Dashboard {
+    pub sites: i64,
+    pub sites_by_status: BTreeMap<String, i64>,
+    pub last_hour: Activity,
+    pub last_day: Activity,
+    // Over the last day
+    pub top_changing_sites: Vec<ChangeCount>,
+    pub db_size_bytes: Option<i64>,
+    pub queue: Queue,
+    // Clients on the live stream and other listeners of its broadcast
+    pub subscribers: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Queue {
+    // Due checks waiting for their domain's turn or a fetch permit
+    pub waiting: i64,
+    pub in_flight: i64,
+}
+
+fn sites_by_status(sites: &[Site]) -> BTreeMap<String, i64> {
+    let mut counts = BTreeMap::new();
+    for site in sites {
+        *counts.entry(site.status.clone().unwrap_or_else(|| PENDING.to_string())).or_default() += 1;
+    }
+    counts
+}
+
+async fn summary(data: &AppState) -> StorageResult<Dashboard> {
+    let now = Utc::now();
+    let sites = data.storage.list_sites().await?;
+    Ok(Dashboard {
+        sites: sites.len() as i64,
+        sites_by_status: sites_by_status(&sites),
+        last_hour: data.storage.activity(now - Duration::hours(1)).await?,
+        last_day: data.storage.activity(now - Duration::days(1)).await?,
+        top_changing_sites: data.storage.top_changing_sites(now - Duration::days(1), TOP_SITES).await?,
+        db_size_bytes: data.storage.database_size().await?,
+        queue: Queue {
+            waiting: data.metrics.checks_waiting.get(),
+            in_flight: data.metrics.checks_in_flight.get(),
+        },
+        subscribers: data.tx_updates.receiver_count(),
+    })
+}
+
+// GET /api/stats: site statuses, recent fetches and changes, the busiest sites, database
+// size, the check queue and stream subscribers
+pub async fn get_dashboard(data: web::Data<AppState>) -> impl Responder {
+    match summary(&data).await {
+        Ok(dashboard) => HttpResponse::Ok().json(dashboard),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_sites_by_status() {
+        let site = |status: Option<&str>| Site { status: status.map(String::from), ..Site::new("https://a.example", 60, "random") };
+        let sites = [site(Some("OK")), site(None), site(Some("OK")), site(Some("CHALLENGE"))];
+        let counts = sites_by_status(&sites);
+        assert_eq!(counts.into_iter().collect::<Vec<_>>(), vec![("CHALLENGE".to_string(), 1), ("OK".to_string(), 2), ("PENDING".to_string(), 1)]);
+    }
+}
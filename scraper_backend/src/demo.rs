@@ -0,0 +1,262 @@
+use super::alerts::AlertRule;
+use super::storage::Storage;
+use super::Site;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+// `--demo` runs against its own database, recreated on every start, with a fixed site list
+// pointing at pages this binary serves under /demo/. A timer changes one page at a time,
+// in the same order every run, so the UI can be shown and developed offline.
+pub const FLAG: &str = "--demo";
+pub const DATABASE_FILE: &str = "demo.db";
+pub const DATABASE_URL: &str = "sqlite:demo.db?mode=rwc";
+// The HTTP server always listens on 8080
+const BASE_URL: &str = "http://127.0.0.1:8080/demo";
+pub const LOOPBACK: &str = "127.0.0.1";
+
+const SEED: u64 = 0x5eed;
+const CHECK_INTERVAL_SECS: i64 = 10;
+// Entries every page starts with, and how many the newest-first lists show
+const INITIAL_ITEMS: u64 = 3;
+const LISTED_ITEMS: u64 = 10;
+
+const COMPANIES: &[(&str, &str)] = &[
+    ("Acme Biotech", "ACMB"),
+    ("Northwind Semiconductors", "NWSC"),
+    ("Globex Energy", "GLXE"),
+    ("Initech Software", "INTC"),
+    ("Umbrella Pharma", "UMBP"),
+    ("Stark Aerospace", "STKA"),
+    ("Tyrell Robotics", "TYRL"),
+    ("Wayne Holdings", "WAYN"),
+];
+const EVENTS: &[&str] = &[
+    "receives FDA approval for",
+    "announces a $2B buyback alongside",
+    "is subpoenaed over",
+    "wins a federal contract for",
+    "recalls",
+    "raises guidance on",
+    "delays",
+    "files an 8-K on",
+];
+const SUBJECTS: &[&str] = &[
+    "its lead oncology drug",
+    "next-generation GPUs",
+    "offshore wind assets",
+    "third-quarter revenue",
+    "its accounting practices",
+    "satellite launch services",
+    "a pediatric vaccine",
+    "a pending merger",
+];
+const HALT_REASONS: &[&str] = &["News pending", "Volatility pause", "Additional information requested", "Regulatory concern"];
+
+#[derive(Clone, Copy)]
+enum Kind {
+    PressReleases,
+    Filings,
+    Halts,
+}
+
+struct Fixture {
+    name: &'static str,
+    kind: Kind,
+    content_type: &'static str,
+    // Limits the check to the part of the page that changes
+    selector: Option<&'static str>,
+}
+
+const FIXTURES: &[Fixture] = &[
+    Fixture { name: "press-releases.html", kind: Kind::PressReleases, content_type: "text/html; charset=utf-8", selector: Some("#releases") },
+    Fixture { name: "filings.xml", kind: Kind::Filings, content_type: "application/rss+xml; charset=utf-8", selector: None },
+    Fixture { name: "halts.html", kind: Kind::Halts, content_type: "text/html; charset=utf-8", selector: None },
+];
+
+// One generated entry; the same fixture and index always give the same entry
+struct Item {
+    index: u64,
+    company: &'static str,
+    ticker: &'static str,
+    headline: String,
+    halt_reason: &'static str,
+    published: DateTime<Utc>,
+}
+
+fn item(fixture: usize, index: u64) -> Item {
+    let mut rng = StdRng::seed_from_u64(SEED ^ ((fixture as u64) << 32) ^ index);
+    let (company, ticker) = *COMPANIES.choose(&mut rng).unwrap();
+    let event = EVENTS.choose(&mut rng).unwrap();
+    let subject = SUBJECTS.choose(&mut rng).unwrap();
+    let epoch = Utc.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap();
+    Item {
+        index,
+        company,
+        ticker,
+        headline: format!("{} {} {}", company, event, subject),
+        halt_reason: HALT_REASONS.choose(&mut rng).unwrap(),
+        published: epoch + Duration::minutes(37 * index as i64),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// How far each fixture has been changed
+pub struct DemoPages {
+    revisions: Vec<AtomicU64>,
+}
+
+impl Default for DemoPages {
+    fn default() -> Self {
+        DemoPages { revisions: FIXTURES.iter().map(|_| AtomicU64::new(0)).collect() }
+    }
+}
+
+impl DemoPages {
+    fn advance(&self, fixture: usize) -> u64 {
+        self.revisions[fixture].fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    // Newest first
+    fn items(&self, fixture: usize) -> Vec<Item> {
+        let count = INITIAL_ITEMS + self.revisions[fixture].load(Ordering::Relaxed);
+        (count.saturating_sub(LISTED_ITEMS)..count).rev().map(|i| item(fixture, i)).collect()
+    }
+
+    fn render(&self, fixture: usize) -> String {
+        let items = self.items(fixture);
+        match FIXTURES[fixture].kind {
+            Kind::PressReleases => {
+                let list: String = items.iter()
+                    .map(|i| format!(
+                        "<li><a href=\"{}/releases/{}\">{}</a> <time>{}</time></li>\n",
+                        BASE_URL, i.index, escape(&i.headline), i.published.format("%b %d, %Y %H:%M"),
+                    ))
+                    .collect();
+                format!(
+                    "<!DOCTYPE html>\n<html><head><title>Demo Newsroom</title></head><body>\n\
+                     <nav><a href=\"/\">Home</a> | <a href=\"/investors\">Investors</a></nav>\n\
+                     <main id=\"releases\"><h1>Press Releases</h1>\n<ul>\n{}</ul></main>\n\
+                     <footer>Demo fixture; entries are generated.</footer>\n</body></html>\n",
+                    list,
+                )
+            },
+            Kind::Filings => {
+                let entries: String = items.iter()
+                    .map(|i| format!(
+                        "<item><title>{}</title><link>{}/filings/{}</link><guid>demo-filing-{}</guid><pubDate>{}</pubDate>\
+                         <description>{} ({}) filed a current report.</description></item>\n",
+                        escape(&i.headline), BASE_URL, i.index, i.index, i.published.to_rfc2822(), escape(i.company), i.ticker,
+                    ))
+                    .collect();
+                format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\"><channel>\n\
+                     <title>Demo Filings</title><link>{}/filings.xml</link><description>Generated filings</description>\n{}</channel></rss>\n",
+                    BASE_URL, entries,
+                )
+            },
+            Kind::Halts => {
+                let rows: String = items.iter()
+                    .map(|i| format!(
+                        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                        i.published.format("%Y-%m-%d %H:%M"), i.ticker, escape(i.company), i.halt_reason,
+                    ))
+                    .collect();
+                format!(
+                    "<!DOCTYPE html>\n<html><head><title>Demo Trading Halts</title></head><body>\n<h1>Current Trading Halts</h1>\n\
+                     <table><tr><th>Halted</th><th>Ticker</th><th>Company</th><th>Reason</th></tr>\n{}</table>\n</body></html>\n",
+                    rows,
+                )
+            },
+        }
+    }
+}
+
+pub fn fixture_urls() -> Vec<String> {
+    FIXTURES.iter().map(|f| format!("{}/{}", BASE_URL, f.name)).collect()
+}
+
+// The demo site list, plus an alert rule so the alert flow shows up too
+pub async fn seed(storage: &dyn Storage) {
+    for (fixture, url) in FIXTURES.iter().zip(fixture_urls()) {
+        let mut site = Site::new(&url, CHECK_INTERVAL_SECS, "none");
+        site.selector = fixture.selector.map(String::from);
+        let Ok(site_id) = storage.insert_site(&site).await else {
+            continue;
+        };
+        if matches!(fixture.kind, Kind::PressReleases) {
+            let rule = AlertRule {
+                id: 0,
+                name: "FDA approvals".to_string(),
+                pattern: "FDA approval".to_string(),
+                is_regex: false,
+                site_id: Some(site_id),
+                created_at: Utc::now(),
+                high_priority: true,
+                press_release: false,
+            };
+            let _ = storage.insert_alert_rule(&rule).await;
+        }
+    }
+}
+
+// Changes the fixtures in turn, one every `interval_secs`
+pub async fn run_changes(pages: Arc<DemoPages>, interval_secs: i64) {
+    let mut ticks = tokio::time::interval(std::time::Duration::from_secs(interval_secs.max(1) as u64));
+    ticks.tick().await;
+    info!("Demo fixtures change every {}s", interval_secs.max(1));
+    for n in 0.. {
+        ticks.tick().await;
+        let fixture = n % FIXTURES.len();
+        let revision = pages.advance(fixture);
+        debug!(fixture = FIXTURES[fixture].name, revision, "Demo fixture changed");
+    }
+}
+
+// GET /demo/{name}
+pub async fn serve_fixture(pages: web::Data<DemoPages>, path: web::Path<String>) -> impl Responder {
+    let name = path.into_inner();
+    match FIXTURES.iter().position(|f| f.name == name) {
+        Some(i) => HttpResponse::Ok().content_type(FIXTURES[i].content_type).body(pages.render(i)),
+        None => HttpResponse::NotFound().body(format!("No demo fixture named '{}'", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_are_deterministic_and_grow_one_entry_per_change() {
+        let (a, b) = (DemoPages::default(), DemoPages::default());
+        for i in 0..FIXTURES.len() {
+            assert_eq!(a.render(i), b.render(i));
+        }
+        let before = a.render(0);
+        assert_eq!(a.advance(0), 1);
+        let after = a.render(0);
+        assert_ne!(before, after);
+        assert_eq!(after.matches("<li>").count(), before.matches("<li>").count() + 1);
+        // The other pages didn't change
+        assert_eq!(a.render(1), b.render(1));
+        b.advance(0);
+        assert_eq!(b.render(0), after);
+
+        // Lists stop growing at LISTED_ITEMS, newest first
+        for _ in 0..20 {
+            a.advance(2);
+        }
+        let items = a.items(2);
+        assert_eq!(items.len() as u64, LISTED_ITEMS);
+        assert!(items.windows(2).all(|w| w[0].index == w[1].index + 1));
+        assert!(feed_rs::parser::parse(a.render(1).as_bytes()).is_ok());
+    }
+}
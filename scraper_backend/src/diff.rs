@@ -0,0 +1,288 @@
+use super::alerts::page_text;
+use super::formats::{self, Format};
+use super::scraper::{normalize_unicode, select_region};
+use super::AppState;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use scraper::{ElementRef, Html};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+// Lines of unchanged text kept around each hunk
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    // "line" (default) or "semantic"
+    mode: Option<String>,
+    // "text" or "markdown" instead of the Accept header; "text" is the bare diff
+    format: Option<String>,
+}
+
+#[derive(Serialize)]
+struct UpdateDiff {
+    update_id: i64,
+    site_id: i64,
+    timestamp: DateTime<Utc>,
+    added: usize,
+    removed: usize,
+    diff: String,
+}
+
+// Line-based unified diff of the visible text of two snapshots. Raw HTML would bury the
+// change in markup, so both sides go through the same text extraction as alert rules.
+pub fn text_diff(old_html: &str, new_html: &str, selector: Option<&str>) -> String {
+    unified_diff(&page_text(old_html, selector), &page_text(new_html, selector))
+}
+
+fn unified_diff(old: &str, new: &str) -> String {
+    // Terminate both sides so the last line doesn't get a "no newline" marker
+    let (old, new) = (format!("{}\n", old), format!("{}\n", new));
+    TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header("previous", "current")
+        .to_string()
+}
+
+// Elements whose text reads as one unit; inline markup inside them is flattened
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "caption", "dd", "details", "div", "dl", "dt",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "html", "li",
+    "main", "nav", "ol", "p", "pre", "section", "summary", "table", "td", "th", "title", "tr", "ul",
+];
+const SKIPPED_TAGS: &[&str] = &["script", "style", "noscript", "template", "svg"];
+
+#[derive(Serialize)]
+struct SemanticUpdateDiff {
+    update_id: i64,
+    site_id: i64,
+    timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    diff: SemanticDiff,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TextBlock {
+    // Tag of the block element the text belongs to
+    pub element: String,
+    pub text: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct SemanticDiff {
+    added: Vec<TextBlock>,
+    removed: Vec<TextBlock>,
+}
+
+// Readable text blocks of a page in document order. Markup, attribute and whitespace
+// changes leave these untouched, so they don't show up in a semantic diff.
+pub fn text_blocks(html: &str, selector: Option<&str>) -> Vec<TextBlock> {
+    let region = selector.and_then(|sel| select_region(html, sel));
+    let document = Html::parse_document(region.as_deref().unwrap_or(html));
+    let mut blocks = Vec::new();
+    let mut text = String::new();
+    collect_blocks(document.root_element(), "html", &mut blocks, &mut text);
+    flush_block("html", &mut text, &mut blocks);
+    blocks
+}
+
+// Walks `element`, appending inline text to `text` for the enclosing `block`
+fn collect_blocks(element: ElementRef, block: &str, blocks: &mut Vec<TextBlock>, text: &mut String) {
+    for child in element.children() {
+        if let Some(t) = child.value().as_text() {
+            text.push(' ');
+            text.push_str(t);
+            continue;
+        }
+        let Some(child) = ElementRef::wrap(child) else {
+            continue;
+        };
+        let name = child.value().name();
+        if SKIPPED_TAGS.contains(&name) {
+            continue;
+        }
+        if BLOCK_TAGS.contains(&name) {
+            // Text before a nested block is a block of its own
+            flush_block(block, text, blocks);
+            let mut inner = String::new();
+            collect_blocks(child, name, blocks, &mut inner);
+            flush_block(name, &mut inner, blocks);
+        } else {
+            collect_blocks(child, block, blocks, text);
+        }
+    }
+}
+
+fn flush_block(element: &str, text: &mut String, blocks: &mut Vec<TextBlock>) {
+    let normalized = normalize_unicode(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+    text.clear();
+    if !normalized.is_empty() {
+        blocks.push(TextBlock { element: element.to_string(), text: normalized });
+    }
+}
+
+// Aligns the block sequences of both snapshots and keeps only blocks whose text was
+// added or removed. A block that moved shows up as both.
+pub fn semantic_diff(old_html: &str, new_html: &str, selector: Option<&str>) -> SemanticDiff {
+    let old = text_blocks(old_html, selector);
+    let new = text_blocks(new_html, selector);
+    let old_text: Vec<&str> = old.iter().map(|b| b.text.as_str()).collect();
+    let new_text: Vec<&str> = new.iter().map(|b| b.text.as_str()).collect();
+
+    let mut result = SemanticDiff::default();
+    for change in TextDiff::from_slices(&old_text, &new_text).iter_all_changes() {
+        match (change.tag(), change.old_index(), change.new_index()) {
+            (ChangeTag::Insert, _, Some(i)) => result.added.push(new[i].clone()),
+            (ChangeTag::Delete, Some(i), _) => result.removed.push(old[i].clone()),
+            _ => {},
+        }
+    }
+    result
+}
+
+// Added and removed lines, skipping the two file header lines
+fn count_changes(diff: &str) -> (usize, usize) {
+    let body = diff.lines().skip_while(|l| l.starts_with("---") || l.starts_with("+++"));
+    body.fold((0, 0), |(added, removed), line| match line.as_bytes().first() {
+        Some(b'+') => (added + 1, removed),
+        Some(b'-') => (added, removed + 1),
+        _ => (added, removed),
+    })
+}
+
+fn markdown_heading(update_id: i64, site_id: i64, timestamp: DateTime<Utc>) -> String {
+    format!("## Update {} of site {} ({})", update_id, site_id, timestamp.to_rfc3339())
+}
+
+fn semantic_markdown(update: &SemanticUpdateDiff) -> String {
+    let mut sections = vec![markdown_heading(update.update_id, update.site_id, update.timestamp)];
+    for (title, blocks) in [("Added", &update.diff.added), ("Removed", &update.diff.removed)] {
+        if !blocks.is_empty() {
+            let items: Vec<String> = blocks.iter().map(|b| format!("- {}", b.text)).collect();
+            sections.push(format!("### {}\n\n{}", title, items.join("\n")));
+        }
+    }
+    if sections.len() == 1 {
+        sections.push("No text changes.".to_string());
+    }
+    sections.join("\n\n")
+}
+
+fn line_markdown(update: &UpdateDiff) -> String {
+    let heading = markdown_heading(update.update_id, update.site_id, update.timestamp);
+    if update.diff.is_empty() {
+        return format!("{}\n\nNo text changes.", heading);
+    }
+    format!("{}\n\n{} line(s) added, {} removed\n\n```diff\n{}```", heading, update.added, update.removed, update.diff)
+}
+
+// GET /api/updates/{id}/diff: what changed relative to the snapshot stored before it,
+// as JSON, plain text or Markdown depending on Accept
+pub async fn get_diff(data: web::Data<AppState>, req: HttpRequest, path: web::Path<i64>, query: web::Query<DiffQuery>) -> impl Responder {
+    let update_id = path.into_inner();
+    let semantic = match query.mode.as_deref() {
+        None | Some("line") => false,
+        Some("semantic") => true,
+        Some(mode) => return HttpResponse::BadRequest().body(format!("Invalid mode '{}': expected line or semantic", mode)),
+    };
+    let format = match query.format.as_deref() {
+        Some(format) => match formats::from_query(format) {
+            Some(format) if format != Format::Html => format,
+            _ => return HttpResponse::BadRequest().body(format!("Invalid format '{}': expected json, text or markdown", format)),
+        },
+        None => match formats::negotiate(&req) {
+            Some(format) => format,
+            None => return formats::not_acceptable(),
+        },
+    };
+    let record = match data.storage.update_content(update_id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return HttpResponse::NotFound().body(format!("Update with ID {} not found", update_id)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+
+    // Only line diffs are stored; everything else needs the previous snapshot
+    let previous = match (&record.diff, semantic) {
+        (Some(_), false) => None,
+        _ => match data.storage.previous_content(record.site_id, update_id).await {
+            Ok(Some(previous)) => Some(previous),
+            Ok(None) => return HttpResponse::NotFound().body(format!("Update {} has no earlier snapshot to compare against", update_id)),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+        },
+    };
+    let selector = record.selector.as_deref();
+
+    if semantic {
+        let update = SemanticUpdateDiff {
+            update_id,
+            site_id: record.site_id,
+            timestamp: record.timestamp,
+            diff: semantic_diff(previous.as_deref().unwrap_or_default(), &record.content, selector),
+        };
+        return match format {
+            Format::Json | Format::Html => formats::json(&update),
+            Format::Markdown => formats::text(format, semantic_markdown(&update)),
+            Format::Text => {
+                let lines: Vec<String> = update.diff.removed.iter().map(|b| format!("- {}", b.text))
+                    .chain(update.diff.added.iter().map(|b| format!("+ {}", b.text)))
+                    .collect();
+                formats::text(format, lines.join("\n"))
+            },
+        };
+    }
+
+    // Unchanged fetches and ones stored before diffs were recorded are compared on the fly
+    let diff = match (record.diff, previous) {
+        (Some(diff), _) => diff,
+        (None, previous) => text_diff(&previous.unwrap_or_default(), &record.content, selector),
+    };
+    if format == Format::Text {
+        return formats::text(format, diff);
+    }
+    let (added, removed) = count_changes(&diff);
+    let update = UpdateDiff {
+        update_id,
+        site_id: record.site_id,
+        timestamp: record.timestamp,
+        added,
+        removed,
+        diff,
+    };
+    match format {
+        Format::Markdown => formats::text(format, line_markdown(&update)),
+        _ => formats::json(&update),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_visible_text_only() {
+        let old = "<html><head><script>var t = 1;</script></head><body><p>Intro</p><p>Old price</p></body></html>";
+        let new = "<html><head><script>var t = 2;</script></head><body><p>Intro</p><p>New price</p><p>+ Extra</p></body></html>";
+        let diff = text_diff(old, new, None);
+        assert!(diff.starts_with("--- previous\n+++ current\n"));
+        assert!(diff.contains("\n Intro\n-Old price\n+New price\n++ Extra\n"));
+        assert_eq!(count_changes(&diff), (2, 1));
+        assert_eq!(text_diff(old, old, None), "");
+        }
+
+    #[test]
+    fn semantic_diff_reports_text_blocks_only() {
+        let old = r#"<html><body><div class="a"><p>Intro <b>bold</b></p><ul><li>One</li><li>Two</li></ul>Footer</div>
+            <script>track()</script></body></html>"#;
+        let new = r#"<html><body><div class="b">
+            <p>Intro   <i>bold</i></p><ul><li>One</li><li>Three</li></ul>Footer</div></body></html>"#;
+        let diff = semantic_diff(old, new, None);
+        assert_eq!(diff.added, vec![TextBlock { element: "li".into(), text: "Three".into() }]);
+        assert_eq!(diff.removed, vec![TextBlock { element: "li".into(), text: "Two".into() }]);
+
+        let blocks = text_blocks(old, None);
+        assert_eq!(blocks.iter().map(|b| b.text.as_str()).collect::<Vec<_>>(), vec!["Intro bold", "One", "Two", "Footer"]);
+        assert_eq!(blocks[3].element, "div");
+        assert!(semantic_diff(old, old, Some("ul")).added.is_empty());
+    }
+}
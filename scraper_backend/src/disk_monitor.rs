@@ -0,0 +1,101 @@
+use super::storage::SharedStorage;
+use super::{AppConfig, StatusEvent, StreamEvent};
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use tokio::sync::broadcast::Sender;
+use tokio::time::{sleep, Duration};
+use tracing::{error, warn};
+
+const MB: u64 = 1024 * 1024;
+
+// Periodically check the DB file size and free disk space against the configured thresholds.
+// Events are edge-triggered: one when a threshold is crossed and one when it recovers.
+pub async fn run_disk_monitor(storage: SharedStorage, tx: Sender<StreamEvent>, db_url: String, config: AppConfig) {
+    if config.db_size_warn_mb <= 0 && config.disk_free_warn_mb <= 0 {
+        return;
+    }
+    let Some(db_path) = sqlite_file_path(&db_url) else {
+        warn!("Disk monitor disabled: database_url is not a SQLite file");
+        return;
+    };
+    let interval = Duration::from_secs(config.storage_check_interval_secs.max(1) as u64);
+
+    let mut db_size_breached = false;
+    let mut disk_free_breached = false;
+
+    loop {
+        let db_size = database_size(&db_path);
+        let free = db_path.parent()
+            .map(|dir| if dir.as_os_str().is_empty() { Path::new(".") } else { dir })
+            .and_then(|dir| fs2::available_space(dir).ok());
+
+        if config.db_size_warn_mb > 0 {
+            let limit = config.db_size_warn_mb as u64 * MB;
+            let breached = db_size >= limit;
+            if breached != db_size_breached {
+                db_size_breached = breached;
+                let message = if breached {
+                    format!("Database size {} MB has reached the {} MB threshold", db_size / MB, config.db_size_warn_mb)
+                } else {
+                    format!("Database size {} MB is back under the {} MB threshold", db_size / MB, config.db_size_warn_mb)
+                };
+                notify(&tx, "db_size", breached, message);
+            }
+        }
+
+        if let (true, Some(free)) = (config.disk_free_warn_mb > 0, free) {
+            let limit = config.disk_free_warn_mb as u64 * MB;
+            let breached = free <= limit;
+            if breached != disk_free_breached {
+                disk_free_breached = breached;
+                let message = if breached {
+                    format!("Free disk space {} MB is below the {} MB threshold", free / MB, config.disk_free_warn_mb)
+                } else {
+                    format!("Free disk space {} MB is back above the {} MB threshold", free / MB, config.disk_free_warn_mb)
+                };
+                notify(&tx, "disk_free", breached, message);
+            }
+        }
+
+        // While any threshold is breached, keep history trimmed to the emergency size
+        if (db_size_breached || disk_free_breached) && config.emergency_retention_size > 0 {
+            match storage.prune_all_updates(config.emergency_retention_size).await {
+                Ok(0) => {},
+                Ok(deleted) => warn!("Emergency retention removed {} stored update(s)", deleted),
+                Err(e) => error!("Emergency retention failed: {}", e),
+            }
+        }
+
+        sleep(interval).await;
+    }
+}
+
+fn notify(tx: &Sender<StreamEvent>, kind: &str, breached: bool, message: String) {
+    warn!("Storage alert: {}", message);
+    let _ = tx.send(StreamEvent::Status(StatusEvent {
+        kind: kind.to_string(),
+        site_id: None,
+        level: if breached { "warning" } else { "info" }.to_string(),
+        message,
+        timestamp: Utc::now(),
+    }));
+}
+
+// Size of the SQLite file plus its WAL, if any
+fn database_size(path: &Path) -> u64 {
+    let file_len = |p: &Path| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    file_len(path) + file_len(Path::new(&wal))
+}
+
+// "sqlite:../scraper.db", "sqlite://data/scraper.db?mode=rwc" -> file path
+fn sqlite_file_path(db_url: &str) -> Option<PathBuf> {
+    let path = db_url.strip_prefix("sqlite:")?;
+    let path = path.strip_prefix("//").unwrap_or(path);
+    let path = path.split('?').next().unwrap_or(path);
+    if path.is_empty() || path == ":memory:" {
+        return None;
+    }
+    Some(PathBuf::from(path))
+}
@@ -0,0 +1,140 @@
+use super::feeds::FeedEntry;
+use super::{formats, pdf};
+use super::scraper::{self, DEFAULT_TIMEOUT_SECS};
+use super::storage::SharedStorage;
+use super::{AppConfig, AppState, Site};
+use actix_web::http::header::CONTENT_SECURITY_POLICY;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use sqlx::FromRow;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// New links archived per check; a page gaining more at once was redesigned rather than
+// given new filings
+const MAX_PER_CHECK: usize = 20;
+
+// A copy of a document a site newly linked to, kept even if the source removes it.
+// Documents outlive the updates that found them.
+#[derive(Serialize, FromRow, Clone, Debug)]
+pub struct Document {
+    pub id: i64,
+    pub site_id: i64,
+    // Update of the check that found the link
+    pub update_id: Option<i64>,
+    pub url: String,
+    pub content_type: Option<String>,
+    pub size: i64,
+    pub fetched_at: DateTime<Utc>,
+}
+
+pub struct NewDocument<'a> {
+    pub site_id: i64,
+    pub update_id: i64,
+    pub url: &'a str,
+    pub content_type: Option<&'a str>,
+    pub data: &'a [u8],
+    pub fetched_at: DateTime<Utc>,
+}
+
+// How an UpdateMessage points at an archived copy
+#[derive(Serialize, Clone, Debug)]
+pub struct ArchivedDocument {
+    pub url: String,
+    pub archive_url: String,
+}
+
+fn archive_url(id: i64) -> String {
+    format!("/api/documents/{}", id)
+}
+
+// Fetches the new links of a check through the same client, and so the same proxy, and
+// stores the PDF and HTML documents among them. Links that fail or are something else are
+// skipped.
+pub async fn archive(storage: &SharedStorage, client: &reqwest::Client, config: &AppConfig, site: &Site, update_id: i64, links: &[FeedEntry]) -> Vec<ArchivedDocument> {
+    let mut archived = Vec::new();
+    for link in links.iter().take(MAX_PER_CHECK) {
+        let (content_type, data) = match fetch(client, config, site, &link.guid).await {
+            Ok(document) => document,
+            Err(e) => {
+                warn!(url = %link.guid, "Archiving a new link failed: {}", e);
+                continue;
+            },
+        };
+        let document = NewDocument {
+            site_id: site.id,
+            update_id,
+            url: &link.guid,
+            content_type: content_type.as_deref(),
+            data: &data,
+            fetched_at: Utc::now(),
+        };
+        match storage.insert_document(&document).await {
+            Ok(id) => archived.push(ArchivedDocument { url: link.guid.clone(), archive_url: archive_url(id) }),
+            Err(e) => warn!(url = %link.guid, "Storing an archived document failed: {}", e),
+        }
+    }
+    if !archived.is_empty() {
+        info!("Archived {} newly linked document(s)", archived.len());
+    }
+    archived
+}
+
+async fn fetch(client: &reqwest::Client, config: &AppConfig, site: &Site, url: &str) -> Result<(Option<String>, Vec<u8>), String> {
+    config.network_guard.check_url(url)?;
+    let timeout = Duration::from_secs(site.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(1) as u64);
+    let resp = scraper::site_get(client, url, None, timeout).send().await.map_err(|e| scraper::error_chain(&e))?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status().as_u16()));
+    }
+    let content_type = resp.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(String::from);
+    let data = scraper::read_bytes(resp, site.max_body_bytes.unwrap_or(config.max_body_bytes).max(1) as u64).await?;
+    match (pdf::is_pdf(content_type.as_deref(), None, &data), is_html(content_type.as_deref())) {
+        (true, _) => Ok((Some(pdf::MIME_TYPE.to_string()), data)),
+        (false, true) => Ok((content_type, data)),
+        (false, false) => Err(format!("{} is neither a PDF nor HTML", content_type.as_deref().unwrap_or("an untyped body"))),
+    }
+}
+
+fn is_html(content_type: Option<&str>) -> bool {
+    let mime = content_type.and_then(|t| t.split(';').next()).map(str::trim);
+    mime.is_some_and(|mime| mime.eq_ignore_ascii_case("text/html") || mime.eq_ignore_ascii_case("application/xhtml+xml"))
+}
+
+// GET /api/documents/{id}: the archived copy as it was fetched. HTML is sandboxed like
+// raw page content, so scripts in it can't run on this server's origin.
+pub async fn get_document(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    match data.storage.document(id).await {
+        Ok(Some((document, body))) => {
+            let mut response = HttpResponse::Ok();
+            response.content_type(document.content_type.as_deref().unwrap_or("application/octet-stream"));
+            if is_html(document.content_type.as_deref()) {
+                response.insert_header((CONTENT_SECURITY_POLICY, formats::SANDBOX_POLICY));
+            }
+            response.body(body)
+        },
+        Ok(None) => HttpResponse::NotFound().body(format!("Document with ID {} not found", id)),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[derive(Serialize)]
+struct ListedDocument {
+    #[serde(flatten)]
+    document: Document,
+    archive_url: String,
+}
+
+// GET /api/sites/{id}/documents: the site's archived documents, newest first
+pub async fn list_documents(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let site_id = path.into_inner();
+    match data.storage.list_documents(site_id).await {
+        Ok(documents) => HttpResponse::Ok().json(documents.into_iter()
+            .map(|document| ListedDocument { archive_url: archive_url(document.id), document })
+            .collect::<Vec<_>>()),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
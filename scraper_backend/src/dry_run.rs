@@ -0,0 +1,116 @@
+use super::scraper;
+use super::{crawl, ignore, links, scripts, validate_assertions, validate_extraction_mode, validate_plugin, validate_preview_length, validate_selector, AppState, Site};
+use actix_web::{web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+// A page and the extraction settings to try on it. With site_id the site's own settings
+// apply wherever the request leaves a field out; an empty value clears the site's setting.
+#[derive(Deserialize)]
+pub struct DryRunRequest {
+    url: Option<String>,
+    site_id: Option<i64>,
+    selector: Option<String>,
+    ignore_patterns: Option<Vec<String>>,
+    extraction_mode: Option<String>,
+    extraction_script: Option<String>,
+    plugin: Option<String>,
+    link_pattern: Option<String>,
+    crawl_pattern: Option<String>,
+    crawl_depth: Option<i64>,
+    crawl_max_pages: Option<i64>,
+    preview_length: Option<i64>,
+    assert_selector: Option<String>,
+    assert_pattern: Option<String>,
+    assert_min_length: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct DryRunResponse<'a> {
+    url: &'a str,
+    site_id: Option<i64>,
+    #[serde(flatten)]
+    result: scraper::DryRun,
+}
+
+// Applies the candidate settings to the site, validated as on create
+async fn candidate_site(data: &AppState, payload: &DryRunRequest, mut site: Site) -> Result<Site, String> {
+    if let Some(selector) = &payload.selector {
+        site.selector = validate_selector(Some(selector))?;
+    }
+    if let Some(patterns) = &payload.ignore_patterns {
+        site.ignore_patterns = ignore::validate_ignore_patterns(patterns)?;
+    }
+    if let Some(mode) = &payload.extraction_mode {
+        site.extraction_mode = validate_extraction_mode(Some(mode))?;
+    }
+    if let Some(script) = &payload.extraction_script {
+        site.extraction_script = match script.trim() {
+            "" => None,
+            script => {
+                scripts::validate(script)?;
+                Some(script.to_string())
+            },
+        };
+    }
+    if let Some(plugin) = &payload.plugin {
+        site.plugin = validate_plugin(&data.config.plugins, Some(plugin)).await?;
+    }
+    if let Some(pattern) = &payload.link_pattern {
+        site.link_pattern = links::validate_pattern(Some(pattern))?;
+    }
+    let crawl_pattern = crawl::validate(payload.crawl_pattern.as_deref(), payload.crawl_depth, payload.crawl_max_pages)?;
+    if payload.crawl_pattern.is_some() {
+        site.crawl_pattern = crawl_pattern;
+    }
+    if let Some(depth) = payload.crawl_depth {
+        site.crawl_depth = Some(depth).filter(|n| *n > 0);
+    }
+    if let Some(pages) = payload.crawl_max_pages {
+        site.crawl_max_pages = Some(pages).filter(|n| *n > 0);
+    }
+    if let Some(length) = payload.preview_length {
+        validate_preview_length(Some(length))?;
+        site.preview_length = Some(length).filter(|n| *n > 0);
+    }
+    if let Some(selector) = &payload.assert_selector {
+        site.assert_selector = validate_assertions(Some(selector), None, None)?.selector;
+    }
+    if let Some(pattern) = &payload.assert_pattern {
+        site.assert_pattern = validate_assertions(None, Some(pattern), None)?.pattern;
+    }
+    if let Some(min_length) = payload.assert_min_length {
+        validate_assertions(None, None, Some(min_length))?;
+        site.assert_min_length = Some(min_length).filter(|n| *n > 0);
+    }
+    Ok(site)
+}
+
+// POST /api/preview: fetch a page once with candidate extraction settings and return the
+// text a check would compare, its hash and the preview, storing nothing
+pub async fn preview(data: web::Data<AppState>, payload: web::Json<DryRunRequest>) -> impl Responder {
+    let site = match (&payload.url, payload.site_id) {
+        (Some(url), None) => match data.config.network_guard.validate_site_url(url).await {
+            Ok(url) => Site::new(&url, data.config.default_interval_secs, "random"),
+            Err(e) => return HttpResponse::BadRequest().body(e),
+        },
+        (None, Some(id)) => match data.storage.get_site(id).await {
+            Ok(Some(site)) => site,
+            Ok(None) => return HttpResponse::NotFound().body(format!("Site with ID {} not found", id)),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+        },
+        _ => return HttpResponse::BadRequest().body("Give either url or site_id"),
+    };
+    let site = match candidate_site(&data, &payload, site).await {
+        Ok(site) => site,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+
+    match scraper::dry_run(&site, &data.config).await {
+        Ok(result) => {
+            info!(url = %site.url, extraction = result.extraction, "Dry run fetched {}", result.http_status);
+            HttpResponse::Ok().json(DryRunResponse { url: &site.url, site_id: payload.site_id, result })
+        },
+        Err(e) => HttpResponse::BadGateway().body(format!("Fetching {} failed: {}", site.url, e)),
+    }
+}
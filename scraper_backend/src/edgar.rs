@@ -0,0 +1,205 @@
+use super::feeds::FeedEntry;
+use super::items::parse_date;
+use super::plugins::FetchRequest;
+use super::truncate::{self, WORD_BREAKS};
+use super::Site;
+use reqwest::header::USER_AGENT;
+use reqwest::Url;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// Sites with this extraction_mode poll SEC EDGAR's JSON APIs and report each new filing
+pub const EXTRACTION_MODE: &str = "edgar";
+
+// SEC allows ten requests a second across www.sec.gov, data.sec.gov and efts.sec.gov, and
+// answers more with a 403 that blocks the client for ten minutes
+pub const DOMAIN: &str = "sec.gov";
+pub const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(100);
+pub const BLOCK_SECS: i64 = 600;
+
+// The submissions API lists up to 1000 recent filings; older ones were seen long ago
+const MAX_FILINGS: usize = 100;
+const ARCHIVES_URL: &str = "https://www.sec.gov/Archives/edgar/data";
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Where EDGAR is reached, and the User-Agent SEC requires of automated clients: a company
+// or person and a contact address, such as "Acme Research ops@acme.example"
+#[derive(Clone, Debug)]
+pub struct Edgar {
+    pub user_agent: Option<String>,
+    pub submissions_url: String,
+    pub tickers_url: String,
+}
+
+impl Edgar {
+    fn user_agent(&self) -> Result<&str, String> {
+        self.user_agent.as_deref().ok_or_else(|| "EDGAR sites need edgar_user_agent configured, as SEC requires".to_string())
+    }
+
+    fn submissions(&self, cik: u64) -> String {
+        format!("{}/CIK{:010}.json", self.submissions_url.trim_end_matches('/'), cik)
+    }
+
+    // The URL an EDGAR site polls: a submissions or full-text search URL as given, else the
+    // submissions of a CIK ("320193", "CIK0000320193") or ticker ("AAPL")
+    pub async fn resolve(&self, source: &str, client: &reqwest::Client) -> Result<String, String> {
+        let user_agent = self.user_agent()?;
+        let source = source.trim();
+        if Url::parse(source).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+            return Ok(source.to_string());
+        }
+        let digits = source.strip_prefix("CIK").or_else(|| source.strip_prefix("cik")).unwrap_or(source);
+        if let Ok(cik) = digits.parse::<u64>() {
+            return Ok(self.submissions(cik));
+        }
+        let tickers: Value = client
+            .get(&self.tickers_url)
+            .header(USER_AGENT, user_agent)
+            .timeout(LOOKUP_TIMEOUT)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| format!("Looking up ticker '{}' failed: {}", source, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Looking up ticker '{}' failed: {}", source, e))?;
+        tickers_cik(&tickers, source)
+            .map(|cik| self.submissions(cik))
+            .ok_or_else(|| format!("'{}' is neither a URL, a CIK nor a ticker known to EDGAR", source))
+    }
+}
+
+// company_tickers.json: {"0": {"cik_str": 320193, "ticker": "AAPL", "title": "Apple Inc."}, ...}
+fn tickers_cik(tickers: &Value, ticker: &str) -> Option<u64> {
+    tickers.as_object()?.values()
+        .find(|company| company["ticker"].as_str().is_some_and(|t| t.eq_ignore_ascii_case(ticker)))
+        .and_then(|company| company["cik_str"].as_u64())
+}
+
+// EDGAR sites are fetched with the configured User-Agent instead of a browser's
+pub fn request(site: &Site, config: &Edgar) -> Result<Option<FetchRequest>, String> {
+    if site.extraction_mode.as_deref() != Some(EXTRACTION_MODE) {
+        return Ok(None);
+    }
+    let headers = BTreeMap::from([("User-Agent".to_string(), config.user_agent()?.to_string())]);
+    Ok(Some(FetchRequest { url: site.url.clone(), headers }))
+}
+
+fn filing_url(cik: &str, accession: &str, document: Option<&str>) -> String {
+    let cik = cik.trim_start_matches('0');
+    let folder = format!("{}/{}/{}", ARCHIVES_URL, cik, accession.replace('-', ""));
+    match document.filter(|d| !d.is_empty()) {
+        Some(document) => format!("{}/{}", folder, document),
+        None => format!("{}/{}-index.htm", folder, accession),
+    }
+}
+
+// One filing as a feed entry: its accession number is the GUID, its form and filer the title
+fn filing(accession: &str, form: &str, filer: &str, description: Option<&str>, filed: &str, accepted: Option<&str>, link: String) -> FeedEntry {
+    let title = match description.filter(|d| !d.is_empty() && !d.eq_ignore_ascii_case(form)) {
+        Some(description) => format!("{}: {} – {}", form, filer, description),
+        None => format!("{}: {}", form, filer),
+    };
+    FeedEntry {
+        guid: accession.to_string(),
+        title: Some(title),
+        link: Some(link),
+        summary: Some(format!("Form {} filed {}, accession number {}", form, filed, accession)),
+        published: accepted.and_then(parse_date).or_else(|| parse_date(filed)),
+    }
+}
+
+// The filings in a submissions or full-text search response, newest first as EDGAR lists
+// them; None if the body is neither
+pub fn parse(body: &str) -> Option<Vec<FeedEntry>> {
+    let json: Value = serde_json::from_str(body).ok()?;
+    if let Some(recent) = json.pointer("/filings/recent") {
+        return Some(submissions(&json, recent));
+    }
+    json.pointer("/hits/hits").and_then(Value::as_array).map(|hits| search_hits(hits))
+}
+
+// Parallel arrays under filings.recent, one index per filing
+fn submissions(json: &Value, recent: &Value) -> Vec<FeedEntry> {
+    let column = |name: &str, i: usize| recent[name].get(i).and_then(Value::as_str);
+    let cik = json["cik"].as_str().unwrap_or_default();
+    let filer = json["name"].as_str().unwrap_or(cik);
+    let count = recent["accessionNumber"].as_array().map_or(0, Vec::len).min(MAX_FILINGS);
+    (0..count)
+        .filter_map(|i| {
+            let accession = column("accessionNumber", i)?;
+            let form = column("form", i).unwrap_or("Filing");
+            let link = filing_url(cik, accession, column("primaryDocument", i));
+            Some(filing(accession, form, filer, column("primaryDocDescription", i), column("filingDate", i).unwrap_or_default(), column("acceptanceDateTime", i), link))
+        })
+        .collect()
+}
+
+// efts.sec.gov/LATEST/search-index hits: _id is "<accession>:<document>"
+fn search_hits(hits: &[Value]) -> Vec<FeedEntry> {
+    let mut entries: Vec<FeedEntry> = Vec::new();
+    for hit in hits.iter().take(MAX_FILINGS) {
+        let source = &hit["_source"];
+        let (Some(accession), Some(cik)) = (source["adsh"].as_str(), source["ciks"].get(0).and_then(Value::as_str)) else {
+            continue;
+        };
+        // A filing matching in several of its documents is one filing
+        if entries.iter().any(|e| e.guid == accession) {
+            continue;
+        }
+        let document = hit["_id"].as_str().and_then(|id| id.split_once(':')).map(|(_, document)| document);
+        let filer = source["display_names"].get(0).and_then(Value::as_str).unwrap_or(cik);
+        let form = source["form"].as_str().or_else(|| source["file_type"].as_str()).unwrap_or("Filing");
+        let description = source["file_description"].as_str();
+        entries.push(filing(accession, form, filer, description, source["file_date"].as_str().unwrap_or_default(), None, filing_url(cik, accession, document)));
+    }
+    entries
+}
+
+// Headed "🏛 2 filings", one filing per line
+pub fn preview(filings: &[FeedEntry], max_length: usize) -> String {
+    let lines = filings.iter().map(|filing| filing.title.as_deref().unwrap_or(&filing.guid)).collect::<Vec<_>>().join("\n");
+    let heading = format!("🏛 {} filing{}", filings.len(), if filings.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&lines, max_length, &[WORD_BREAKS]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_filings_from_submissions_and_search_results() {
+        let submissions = r#"{"cik": "320193", "name": "Apple Inc.", "tickers": ["AAPL"], "filings": {"recent": {
+            "accessionNumber": ["0000320193-26-000104", "0001140361-26-038121"],
+            "filingDate": ["2026-10-13", "2026-10-09"],
+            "acceptanceDateTime": ["2026-10-13T16:30:12.000Z", ""],
+            "form": ["8-K", "4"],
+            "primaryDocument": ["aapl-20261013.htm", ""],
+            "primaryDocDescription": ["8-K", "Statement of changes in beneficial ownership"]
+        }, "files": []}}"#;
+        let filings = parse(submissions).unwrap();
+        assert_eq!(filings.len(), 2);
+        assert_eq!(filings[0].guid, "0000320193-26-000104");
+        assert_eq!(filings[0].title.as_deref(), Some("8-K: Apple Inc."));
+        assert_eq!(filings[0].link.as_deref(), Some("https://www.sec.gov/Archives/edgar/data/320193/000032019326000104/aapl-20261013.htm"));
+        assert_eq!(filings[0].published, parse_date("2026-10-13T16:30:12Z"));
+        assert_eq!(filings[1].title.as_deref(), Some("4: Apple Inc. – Statement of changes in beneficial ownership"));
+        assert_eq!(filings[1].link.as_deref(), Some("https://www.sec.gov/Archives/edgar/data/320193/000114036126038121/0001140361-26-038121-index.htm"));
+        assert_eq!(filings[1].published, parse_date("2026-10-09"));
+
+        let search = r#"{"hits": {"hits": [
+            {"_id": "0001193125-26-201234:d81234d8k.htm", "_source": {"adsh": "0001193125-26-201234", "ciks": ["0000320193"], "display_names": ["Apple Inc.  (AAPL)  (CIK 0000320193)"], "form": "8-K", "file_date": "2026-10-12"}},
+            {"_id": "0001193125-26-201234:d81234dex991.htm", "_source": {"adsh": "0001193125-26-201234", "ciks": ["0000320193"], "form": "8-K", "file_date": "2026-10-12"}}
+        ]}}"#;
+        let hits = parse(search).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].link.as_deref(), Some("https://www.sec.gov/Archives/edgar/data/320193/000119312526201234/d81234d8k.htm"));
+        assert!(parse("<rss></rss>").is_none());
+
+        let tickers = serde_json::json!({"0": {"cik_str": 320193, "ticker": "AAPL"}, "1": {"cik_str": 789019, "ticker": "MSFT"}});
+        assert_eq!(tickers_cik(&tickers, "msft"), Some(789019));
+        let config = Edgar { user_agent: None, submissions_url: "https://data.sec.gov/submissions/".into(), tickers_url: String::new() };
+        assert_eq!(config.submissions(320193), "https://data.sec.gov/submissions/CIK0000320193.json");
+    }
+}
@@ -0,0 +1,531 @@
+use super::storage::{ChangeEntry, SharedStorage, Storage};
+use super::{AppConfig, AppState, StreamEvent, UpdateMessage};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Duration, Timelike, Utc};
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+use std::fmt;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tracing::{error, info, warn};
+
+const DIGESTS: &[&str] = &["none", "hourly", "daily"];
+const PREVIEW_CHARS: usize = 1000;
+// Changes listed in one digest; the rest are only counted
+const DIGEST_MAX_CHANGES: i64 = 1000;
+const DIGEST_CHECK_SECS: u64 = 60;
+
+// The smtp_url setting. Debug output hides the password, since the loaded config is
+// logged at startup.
+#[derive(Clone)]
+pub struct SmtpUrl(pub String);
+
+impl fmt::Debug for SmtpUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match reqwest::Url::parse(&self.0) {
+            Ok(mut url) if url.password().is_some() => {
+                let _ = url.set_password(Some("***"));
+                write!(f, "{:?}", url.as_str())
+            },
+            _ => write!(f, "{:?}", self.0),
+        }
+    }
+}
+
+#[derive(Serialize, FromRow, Clone, Debug)]
+pub struct EmailRecipient {
+    pub id: i64,
+    pub address: String,
+    // Immediate emails for matches of high-priority alert rules
+    pub alerts: bool,
+    // "none", "hourly" or "daily"
+    pub digest: String,
+    // UTC hour daily digests go out at
+    pub digest_hour: i64,
+    pub last_digest_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct NewEmailRecipient {
+    address: String,
+    alerts: Option<bool>,
+    digest: Option<String>,
+    digest_hour: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct EmailRecipientUpdate {
+    address: Option<String>,
+    alerts: Option<bool>,
+    digest: Option<String>,
+    digest_hour: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, FromRow, Clone, Debug)]
+pub struct EmailTemplate {
+    pub kind: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+pub struct TemplatePayload {
+    subject: String,
+    body: String,
+}
+
+// Built-in templates and the {{placeholders}} each kind can use
+struct Kind {
+    name: &'static str,
+    subject: &'static str,
+    body: &'static str,
+    placeholders: &'static [&'static str],
+}
+
+const KINDS: &[Kind] = &[
+    Kind {
+        name: "alert",
+        subject: "[Alert] {{rules}}: {{title}}",
+        body: "Alert rule(s) {{rules}} matched a change on {{site_url}}.\n\n{{title}}\n{{link}}\nDetected {{timestamp}}\n\n{{preview}}\n",
+        placeholders: &["site_url", "title", "link", "timestamp", "rules", "preview"],
+    },
+    Kind {
+        name: "digest",
+        subject: "{{period}} digest: {{change_count}} change(s) on {{site_count}} site(s)",
+        body: "Changes detected from {{from}} to {{until}}:\n\n{{sites}}\n",
+        placeholders: &["period", "from", "until", "change_count", "site_count", "sites"],
+    },
+];
+
+fn kind(name: &str) -> Option<&'static Kind> {
+    KINDS.iter().find(|k| k.name == name)
+}
+
+// Replaces each {{name}}; unknown placeholders are left as they are
+fn render(template: &str, values: &[(&str, String)]) -> String {
+    values.iter().fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{{{}}}}}", name), value))
+}
+
+// Placeholders used in `text` that `kind` doesn't fill in
+fn unknown_placeholders(kind: &Kind, text: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else { break };
+        let name = rest[start + 2..start + end].trim();
+        if !kind.placeholders.contains(&name) && !unknown.iter().any(|u| u == name) {
+            unknown.push(name.to_string());
+        }
+        rest = &rest[start + end + 2..];
+    }
+    unknown
+}
+
+// The stored override, or the built-in template
+async fn template(storage: &dyn Storage, kind: &Kind) -> EmailTemplate {
+    let stored = match storage.list_email_templates().await {
+        Ok(templates) => templates.into_iter().find(|t| t.kind == kind.name),
+        Err(e) => {
+            warn!("Could not load email templates, using the built-in ones: {}", e);
+            None
+        },
+    };
+    stored.unwrap_or_else(|| EmailTemplate { kind: kind.name.to_string(), subject: kind.subject.to_string(), body: kind.body.to_string() })
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    match super::truncate::cut(text, max_chars, &[]) {
+        Some(start) => format!("{}…", start.trim_end()),
+        None => text.to_string(),
+    }
+}
+
+fn alert_values(msg: &UpdateMessage) -> Vec<(&'static str, String)> {
+    let names: Vec<&str> = msg.matched_rules.iter().map(|r| r.name.as_str()).collect();
+    vec![
+        ("site_url", msg.url.clone()),
+        ("title", msg.title.clone().unwrap_or_else(|| msg.url.clone())),
+        ("link", msg.link.clone().unwrap_or_else(|| msg.url.clone())),
+        ("timestamp", msg.timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+        ("rules", names.join(", ")),
+        ("preview", truncate(&msg.content_preview, PREVIEW_CHARS)),
+    ]
+}
+
+// One section per site, oldest change first
+fn digest_values(period: &str, from: DateTime<Utc>, until: DateTime<Utc>, changes: &[ChangeEntry]) -> Vec<(&'static str, String)> {
+    let mut sites: BTreeMap<i64, (&str, Vec<DateTime<Utc>>)> = BTreeMap::new();
+    for change in changes {
+        sites.entry(change.site_id).or_insert((&change.url, Vec::new())).1.push(change.timestamp);
+    }
+    let sections: Vec<String> = sites.values()
+        .map(|(url, times)| {
+            let lines: String = times.iter().map(|t| format!("\n  - {}", t.format("%Y-%m-%d %H:%M UTC"))).collect();
+            format!("{} ({} change(s)){}", url, times.len(), lines)
+        })
+        .collect();
+    vec![
+        ("period", period.to_string()),
+        ("from", from.format("%Y-%m-%d %H:%M UTC").to_string()),
+        ("until", until.format("%Y-%m-%d %H:%M UTC").to_string()),
+        ("change_count", changes.len().to_string()),
+        ("site_count", sites.len().to_string()),
+        ("sites", sections.join("\n\n")),
+    ]
+}
+
+// Start of the digest period `now` falls in; None for recipients without a digest
+fn period_start(digest: &str, digest_hour: i64, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let hour = match digest {
+        "hourly" => now.hour(),
+        "daily" => digest_hour as u32,
+        _ => return None,
+    };
+    let start = now.date_naive().and_hms_opt(hour, 0, 0)?.and_utc();
+    Some(if start > now { start - Duration::days(1) } else { start })
+}
+
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    // None when smtp_url isn't set
+    pub fn from_config(config: &AppConfig) -> Result<Option<Mailer>, String> {
+        let Some(url) = &config.smtp_url else {
+            return Ok(None);
+        };
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(&url.0)
+            .map_err(|e| format!("Invalid smtp_url: {}", e))?
+            .build();
+        let from = config.email_from.parse().map_err(|e| format!("Invalid email_from '{}': {}", config.email_from, e))?;
+        Ok(Some(Mailer { transport, from }))
+    }
+
+    async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), String> {
+        let to: Mailbox = to.parse().map_err(|e| format!("Invalid address '{}': {}", to, e))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(|e| e.to_string())?;
+        self.transport.send(message).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+fn mailer_or_log(config: &AppConfig) -> Option<Mailer> {
+    match Mailer::from_config(config) {
+        Ok(mailer) => mailer,
+        Err(e) => {
+            error!("Email disabled: {}", e);
+            None
+        },
+    }
+}
+
+// Email alerts of high-priority rules to every recipient that opted in
+pub async fn run_alert_mailer(storage: SharedStorage, mut rx: Receiver<StreamEvent>, config: AppConfig) {
+    let Some(mailer) = mailer_or_log(&config) else {
+        return;
+    };
+
+    loop {
+        let msg = match rx.recv().await {
+            Ok(StreamEvent::Alert(msg)) => msg,
+            Ok(_) => continue,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Alert mailer fell behind, {} event(s) skipped", skipped);
+                continue;
+            },
+            Err(RecvError::Closed) => return,
+        };
+
+        let rules = match storage.list_alert_rules().await {
+            Ok(rules) => rules,
+            Err(e) => {
+                warn!("Could not load alert rules: {}", e);
+                continue;
+            }
+        };
+        if !msg.matched_rules.iter().any(|m| rules.iter().any(|r| r.id == m.id && r.high_priority)) {
+            continue;
+        }
+        let recipients = match storage.list_email_recipients().await {
+            Ok(recipients) => recipients,
+            Err(e) => {
+                warn!("Could not load email recipients: {}", e);
+                continue;
+            }
+        };
+
+        let kind = kind("alert").unwrap();
+        let template = template(storage.as_ref(), kind).await;
+        let values = alert_values(&msg);
+        let (subject, body) = (render(&template.subject, &values), render(&template.body, &values));
+        for recipient in recipients.iter().filter(|r| r.alerts) {
+            if let Err(e) = mailer.send(&recipient.address, &subject, body.clone()).await {
+                error!("Alert email for update {} to {} failed: {}", msg.update_id, recipient.address, e);
+            }
+        }
+    }
+}
+
+// Sends each recipient's digest once its period has ended. A digest covers everything since
+// the previous one, so periods missed while the server was down are folded into the next.
+pub async fn run_digests(storage: SharedStorage, config: AppConfig) {
+    let Some(mailer) = mailer_or_log(&config) else {
+        return;
+    };
+    info!("Email enabled, sending from {}", config.email_from);
+
+    let mut ticks = tokio::time::interval(std::time::Duration::from_secs(DIGEST_CHECK_SECS));
+    loop {
+        ticks.tick().await;
+        let recipients = match storage.list_email_recipients().await {
+            Ok(recipients) => recipients,
+            Err(e) => {
+                warn!("Could not load email recipients: {}", e);
+                continue;
+            }
+        };
+        let now = Utc::now();
+        for recipient in recipients {
+            let Some(until) = period_start(&recipient.digest, recipient.digest_hour, now) else {
+                continue;
+            };
+            let from = recipient.last_digest_at.unwrap_or(recipient.created_at);
+            if from >= until {
+                continue;
+            }
+            if let Err(e) = send_digest(storage.as_ref(), &mailer, &recipient, from, until).await {
+                error!("Digest to {} failed: {}", recipient.address, e);
+                continue;
+            }
+            if let Err(e) = storage.mark_digest_sent(recipient.id, until).await {
+                warn!("Could not record digest to {}: {}", recipient.address, e);
+            }
+        }
+    }
+}
+
+// Nothing is sent for a period without changes
+async fn send_digest(storage: &dyn Storage, mailer: &Mailer, recipient: &EmailRecipient, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<(), String> {
+    let changes = storage.changes_between(from, until, DIGEST_MAX_CHANGES).await.map_err(|e| e.to_string())?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let period = if recipient.digest == "hourly" { "Hourly" } else { "Daily" };
+    let template = template(storage, kind("digest").unwrap()).await;
+    let values = digest_values(period, from, until, &changes);
+    mailer.send(&recipient.address, &render(&template.subject, &values), render(&template.body, &values)).await
+}
+
+fn validate_recipient(recipient: &EmailRecipient) -> Result<(), String> {
+    if let Err(e) = recipient.address.parse::<Mailbox>() {
+        return Err(format!("Invalid address '{}': {}", recipient.address, e));
+    }
+    if !DIGESTS.contains(&recipient.digest.as_str()) {
+        return Err(format!("digest must be one of: {}", DIGESTS.join(", ")));
+    }
+    if !(0..24).contains(&recipient.digest_hour) {
+        return Err("digest_hour must be between 0 and 23 (UTC)".to_string());
+    }
+    Ok(())
+}
+
+pub async fn list_recipients(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.list_email_recipients().await {
+        Ok(recipients) => HttpResponse::Ok().json(recipients),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// Alerts on and a daily digest at 08:00 UTC unless the payload says otherwise
+pub async fn add_recipient(data: web::Data<AppState>, payload: web::Json<NewEmailRecipient>) -> impl Responder {
+    let mut recipient = EmailRecipient {
+        id: 0,
+        address: payload.address.trim().to_string(),
+        alerts: payload.alerts.unwrap_or(true),
+        digest: payload.digest.clone().unwrap_or_else(|| "daily".to_string()),
+        digest_hour: payload.digest_hour.unwrap_or(8),
+        last_digest_at: None,
+        created_at: Utc::now(),
+    };
+    if let Err(e) = validate_recipient(&recipient) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    match data.storage.insert_email_recipient(&recipient).await {
+        Ok(id) => {
+            recipient.id = id;
+            HttpResponse::Ok().json(recipient)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// PATCH /api/email/recipients/{id}: change the address, alert opt-in or digest schedule
+pub async fn update_recipient(data: web::Data<AppState>, path: web::Path<i64>, payload: web::Json<EmailRecipientUpdate>) -> impl Responder {
+    let id = path.into_inner();
+    let recipients = match data.storage.list_email_recipients().await {
+        Ok(recipients) => recipients,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let Some(mut recipient) = recipients.into_iter().find(|r| r.id == id) else {
+        return HttpResponse::NotFound().body(format!("Email recipient with ID {} not found", id));
+    };
+    if let Some(address) = &payload.address {
+        recipient.address = address.trim().to_string();
+    }
+    recipient.alerts = payload.alerts.unwrap_or(recipient.alerts);
+    recipient.digest = payload.digest.clone().unwrap_or(recipient.digest);
+    recipient.digest_hour = payload.digest_hour.unwrap_or(recipient.digest_hour);
+    if let Err(e) = validate_recipient(&recipient) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    match data.storage.update_email_recipient(&recipient).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("Email recipient with ID {} not found", id)),
+        Ok(true) => HttpResponse::Ok().json(recipient),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn delete_recipient(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    match data.storage.delete_email_recipient(id).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("Email recipient with ID {} not found", id)),
+        Ok(true) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// POST /api/email/recipients/{id}/test: send a test email now and report whether the relay took it
+pub async fn test_recipient(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    let mailer = match Mailer::from_config(&data.config) {
+        Ok(Some(mailer)) => mailer,
+        Ok(None) => return HttpResponse::ServiceUnavailable().body("Email is not configured; set smtp_url"),
+        Err(e) => return HttpResponse::ServiceUnavailable().body(e),
+    };
+    let recipients = match data.storage.list_email_recipients().await {
+        Ok(recipients) => recipients,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let Some(recipient) = recipients.into_iter().find(|r| r.id == id) else {
+        return HttpResponse::NotFound().body(format!("Email recipient with ID {} not found", id));
+    };
+
+    let body = "Email notifications for this address are working. No action needed.\n".to_string();
+    let result = mailer.send(&recipient.address, "[TEST] Website watcher notifications", body).await;
+    HttpResponse::Ok().json(serde_json::json!({
+        "id": id,
+        "ok": result.is_ok(),
+        "error": result.err(),
+    }))
+}
+
+// Templates in use, whether each is the built-in one, and the placeholders it can use
+pub async fn list_templates(data: web::Data<AppState>) -> impl Responder {
+    let stored = match data.storage.list_email_templates().await {
+        Ok(templates) => templates,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let templates: Vec<serde_json::Value> = KINDS.iter()
+        .map(|kind| {
+            let custom = stored.iter().find(|t| t.kind == kind.name);
+            serde_json::json!({
+                "kind": kind.name,
+                "subject": custom.map_or(kind.subject, |t| t.subject.as_str()),
+                "body": custom.map_or(kind.body, |t| t.body.as_str()),
+                "custom": custom.is_some(),
+                "placeholders": kind.placeholders,
+            })
+        })
+        .collect();
+    HttpResponse::Ok().json(templates)
+}
+
+// PUT /api/email/templates/{kind}
+pub async fn set_template(data: web::Data<AppState>, path: web::Path<String>, payload: web::Json<TemplatePayload>) -> impl Responder {
+    let name = path.into_inner();
+    let Some(kind) = kind(&name) else {
+        return HttpResponse::NotFound().body(format!("No email template named '{}'", name));
+    };
+    if payload.subject.trim().is_empty() {
+        return HttpResponse::BadRequest().body("subject must not be empty");
+    }
+    let unknown = unknown_placeholders(kind, &format!("{}\n{}", payload.subject, payload.body));
+    if !unknown.is_empty() {
+        return HttpResponse::BadRequest().body(format!(
+            "Unknown placeholder(s) {}; {} templates can use: {}", unknown.join(", "), kind.name, kind.placeholders.join(", ")
+        ));
+    }
+
+    let template = EmailTemplate { kind: kind.name.to_string(), subject: payload.subject.trim().to_string(), body: payload.body.clone() };
+    match data.storage.set_email_template(&template).await {
+        Ok(()) => HttpResponse::Ok().json(template),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// DELETE /api/email/templates/{kind}: back to the built-in template
+pub async fn reset_template(data: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
+    let name = path.into_inner();
+    if kind(&name).is_none() {
+        return HttpResponse::NotFound().body(format!("No email template named '{}'", name));
+    }
+    match data.storage.delete_email_template(&name).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_templates_and_digest_periods() {
+        let alert = kind("alert").unwrap();
+        assert_eq!(render("{{rules}}: {{title}} {{other}}", &[("rules", "SEC".into()), ("title", "X".into())]), "SEC: X {{other}}");
+        assert_eq!(unknown_placeholders(alert, "{{ title }} {{nope}} {{nope}} {{unclosed"), vec!["nope"]);
+        assert!(unknown_placeholders(kind("digest").unwrap(), alert.body).contains(&"rules".to_string()));
+
+        let now: DateTime<Utc> = "2024-05-01T07:30:00Z".parse().unwrap();
+        assert_eq!(period_start("hourly", 8, now), Some("2024-05-01T07:00:00Z".parse().unwrap()));
+        assert_eq!(period_start("daily", 8, now), Some("2024-04-30T08:00:00Z".parse().unwrap()));
+        assert_eq!(period_start("daily", 7, now), Some("2024-05-01T07:00:00Z".parse().unwrap()));
+        assert_eq!(period_start("none", 8, now), None);
+
+        let change = |site_id: i64, url: &str, minute: u32| ChangeEntry {
+            id: minute as i64,
+            site_id,
+            url: url.into(),
+            timestamp: now.with_minute(minute).unwrap(),
+            diff_hash: String::new(),
+            content: String::new(),
+            selector: None,
+            metadata: None,
+            published_at: None,
+            wire_source: None,
+        };
+        let changes = [change(2, "https://b.example", 1), change(1, "https://a.example", 2), change(2, "https://b.example", 3)];
+        let values = digest_values("Daily", now, now, &changes);
+        let value = |name: &str| values.iter().find(|(n, _)| *n == name).unwrap().1.clone();
+        assert_eq!(value("change_count"), "3");
+        assert_eq!(value("site_count"), "2");
+        assert_eq!(
+            value("sites"),
+            "https://a.example (1 change(s))\n  - 2024-05-01 07:02 UTC\n\n\
+             https://b.example (2 change(s))\n  - 2024-05-01 07:01 UTC\n  - 2024-05-01 07:03 UTC"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use super::anonymize::Anonymizer;
+use super::storage::{ChangeEntry, SharedStorage, Storage};
+use super::{AppConfig, AppState, Site};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::time::{sleep, Duration};
+use tracing::{debug, info, warn};
+
+// Periodically render the recent update timeline into a static HTML bundle
+pub async fn run_static_export(storage: SharedStorage, config: AppConfig) {
+    let Some(dir) = config.static_export_dir.clone() else {
+        return;
+    };
+    let out_dir = PathBuf::from(dir);
+    let interval = Duration::from_secs(config.static_export_interval_secs.max(1) as u64);
+
+    info!("Static export enabled, writing to {} every {}s", out_dir.display(), interval.as_secs());
+
+    // Sources are replaced by stable aliases when the bundle is shared externally
+    let anonymizer = config.static_export_anonymize.then(|| Anonymizer::new(&config.export_alias_salt));
+
+    // Last change timestamp rendered per site, so unchanged pages aren't rewritten
+    let mut rendered: HashMap<i64, (String, Option<DateTime<Utc>>)> = HashMap::new();
+
+    loop {
+        if let Err(e) = export_once(storage.as_ref(), &out_dir, config.static_export_limit, anonymizer.as_ref(), &mut rendered).await {
+            warn!("Static export failed: {}", e);
+        }
+        sleep(interval).await;
+    }
+}
+
+async fn export_once(
+    storage: &dyn Storage,
+    out_dir: &Path,
+    limit: i64,
+    anon: Option<&Anonymizer>,
+    rendered: &mut HashMap<i64, (String, Option<DateTime<Utc>>)>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let sites = storage.list_sites().await?;
+
+    // Only sites whose last change moved since the previous run need their page rebuilt
+    let dirty: Vec<&Site> = sites.iter()
+        .filter(|s| rendered.get(&s.id).map(|(_, ts)| ts) != Some(&s.last_updated))
+        .collect();
+    let removed: Vec<i64> = rendered.keys()
+        .filter(|id| !sites.iter().any(|s| s.id == **id))
+        .copied()
+        .collect();
+
+    if dirty.is_empty() && removed.is_empty() {
+        return Ok(());
+    }
+
+    let timeline = storage.recent_changes(None, limit).await?;
+
+    let sites_dir = out_dir.join("sites");
+    tokio::fs::create_dir_all(&sites_dir).await?;
+
+    for site in &dirty {
+        let entries: Vec<&ChangeEntry> = timeline.iter().filter(|e| e.site_id == site.id).collect();
+        let html = render_site_page(site, &entries, anon);
+        let page = page_name(site.id, &site.url, anon);
+        write_atomic(&sites_dir.join(&page), &html).await?;
+        rendered.insert(site.id, (page, site.last_updated));
+    }
+
+    for id in removed {
+        if let Some((page, _)) = rendered.remove(&id) {
+            let _ = tokio::fs::remove_file(sites_dir.join(page)).await;
+        }
+    }
+
+    write_atomic(&out_dir.join("index.html"), &render_index(&sites, &timeline, anon)).await?;
+
+    debug!("Static export refreshed {} site page(s) in {}", dirty.len(), out_dir.display());
+    Ok(())
+}
+
+// Site pages are named by alias in anonymized mode so the file list doesn't leak IDs
+fn page_name(site_id: i64, url: &str, anon: Option<&Anonymizer>) -> String {
+    match anon {
+        Some(anon) => format!("{}.html", anon.alias(url)),
+        None => format!("{}.html", site_id),
+    }
+}
+
+fn site_label(url: &str, anon: Option<&Anonymizer>) -> String {
+    match anon {
+        Some(anon) => anon.alias(url),
+        None => url.to_string(),
+    }
+}
+
+fn render_index(sites: &[Site], timeline: &[ChangeEntry], anon: Option<&Anonymizer>) -> String {
+    let mut body = String::new();
+
+    body.push_str("<h2>Recent Changes</h2>\n");
+    if timeline.is_empty() {
+        body.push_str("<p>No changes detected yet.</p>\n");
+    } else {
+        body.push_str("<ul class=\"timeline\">\n");
+        for entry in timeline {
+            let href = format!("sites/{}", page_name(entry.site_id, &entry.url, anon));
+            body.push_str(&render_entry(entry, Some(&href), anon));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    body.push_str("<h2>Monitored Sites</h2>\n<table>\n<tr><th>URL</th><th>Status</th><th>Last Check</th><th>Last Update</th></tr>\n");
+    for site in sites {
+        body.push_str(&format!(
+            "<tr><td><a href=\"sites/{}\">{}</a></td><td class=\"status-{}\">{}</td><td>{}</td><td>{}</td></tr>\n",
+            page_name(site.id, &site.url, anon),
+            escape_html(&site_label(&site.url, anon)),
+            escape_html(site.status.as_deref().unwrap_or("Pending")),
+            escape_html(site.status.as_deref().unwrap_or("Pending")),
+            format_time(site.last_checked),
+            format_time(site.last_updated),
+        ));
+    }
+    body.push_str("</table>\n");
+
+    render_page("Rust Website Watcher - Snapshot", &body)
+}
+
+fn render_site_page(site: &Site, entries: &[&ChangeEntry], anon: Option<&Anonymizer>) -> String {
+    let heading = match anon {
+        Some(anon) => escape_html(&anon.alias(&site.url)),
+        None => format!("<a href=\"{0}\">{0}</a>", escape_html(&site.url)),
+    };
+    let mut body = format!(
+        "<p><a href=\"../index.html\">&larr; All sites</a></p>\n<h2>{}</h2>\n<p>Status: {} &middot; Last update: {}</p>\n",
+        heading,
+        escape_html(site.status.as_deref().unwrap_or("Pending")),
+        format_time(site.last_updated),
+    );
+
+    if entries.is_empty() {
+        body.push_str("<p>No changes in the exported window.</p>\n");
+    } else {
+        body.push_str("<ul class=\"timeline\">\n");
+        for entry in entries {
+            body.push_str(&render_entry(entry, None, anon));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    render_page(&format!("{} - Snapshot", site_label(&site.url, anon)), &body)
+}
+
+fn render_entry(entry: &ChangeEntry, link: Option<&str>, anon: Option<&Anonymizer>) -> String {
+    let mut preview = super::extractors::preview(&entry.content, super::extractors::DEFAULT_PREVIEW_LENGTH, entry.selector.as_deref(), None);
+    let label = escape_html(&site_label(&entry.url, anon));
+    let url = match link {
+        Some(href) => format!("<a href=\"{}\">{}</a>", href, label),
+        None => label,
+    };
+    // Internal update IDs and content hashes are left out of anonymized bundles
+    let (anchor, hash) = match anon {
+        Some(anon) => {
+            preview = anon.scrub(&preview);
+            (String::new(), String::new())
+        },
+        None => (
+            format!(" id=\"update-{}\"", entry.id),
+            format!(" <code>{}</code>", &entry.diff_hash[..entry.diff_hash.len().min(12)]),
+        ),
+    };
+    format!(
+        "<li{}><div><strong>[{}]</strong> {}{}</div><pre>{}</pre></li>\n",
+        anchor,
+        entry.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+        url,
+        hash,
+        escape_html(&preview),
+    )
+}
+
+fn render_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+    <meta charset=\"utf-8\"/>
+    <title>{}</title>
+    <style>
+        body {{ font-family: Arial, sans-serif; margin: 0 auto; padding: 1rem; max-width: 1100px; }}
+        table {{ border-collapse: collapse; width: 100%; }}
+        th, td {{ border: 1px solid #ddd; padding: 8px; text-align: left; }}
+        th {{ background-color: #f4f4f4; }}
+        .status-OK {{ color: green; font-weight: bold; }}
+        .status-ERROR {{ color: red; font-weight: bold; }}
+        .status-OVERDUE {{ color: #ff9800; font-weight: bold; }}
+        .status-STALE {{ color: #ff9800; font-weight: bold; }}
+        .timeline {{ list-style: none; padding-left: 0; }}
+        .timeline li {{ margin-bottom: 18px; padding: 15px; border-left: 4px solid #4CAF50; background: #f9f9f9; }}
+        pre {{ white-space: pre-wrap; word-break: break-word; }}
+    </style>
+</head>
+<body>
+<h1>{}</h1>
+<p style=\"color:#666;\">Generated {}</p>
+{}</body>
+</html>
+",
+        escape_html(title),
+        escape_html(title),
+        Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+        body,
+    )
+}
+
+fn format_time(ts: Option<DateTime<Utc>>) -> String {
+    ts.map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#039;")
+}
+
+// Write to a temp file first so a sync job never uploads a half-written page
+async fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let tmp = path.with_extension("html.tmp");
+    tokio::fs::write(&tmp, contents).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+#[derive(Deserialize)]
+pub struct DigestQuery {
+    limit: Option<i64>,
+    anonymize: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct DigestEntry {
+    source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diff_hash: Option<String>,
+    timestamp: DateTime<Utc>,
+    preview: String,
+}
+
+// JSON digest of recent changes; ?anonymize=true swaps sources for aliases and drops internal IDs
+pub async fn export_digest(data: web::Data<AppState>, query: web::Query<DigestQuery>) -> impl Responder {
+    let limit = query.limit.unwrap_or(data.config.static_export_limit).clamp(1, 1000);
+    let anon = query.anonymize
+        .unwrap_or(data.config.static_export_anonymize)
+        .then(|| Anonymizer::new(&data.config.export_alias_salt));
+
+    let changes = match data.storage.recent_changes(None, limit).await {
+        Ok(changes) => changes,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+
+    let digest: Vec<DigestEntry> = changes.into_iter()
+        .map(|c| {
+            let preview = super::extractors::preview(&c.content, super::extractors::DEFAULT_PREVIEW_LENGTH, c.selector.as_deref(), None);
+            match &anon {
+                Some(anon) => DigestEntry {
+                    source: anon.alias(&c.url),
+                    site_id: None,
+                    diff_hash: None,
+                    timestamp: c.timestamp,
+                    preview: anon.scrub(&preview),
+                },
+                None => DigestEntry {
+                    source: c.url,
+                    site_id: Some(c.site_id),
+                    diff_hash: Some(c.diff_hash),
+                    timestamp: c.timestamp,
+                    preview,
+                },
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(digest)
+}
@@ -0,0 +1,582 @@
+use super::{edgar, fda, federal_register, halts, links, news_search, pdf, reddit, sitemap, social, structured, substack};
+use super::readability::{self, Article};
+use super::scraper::{clean_html_content, clean_script_content, clean_xml_entities, normalize_whitespace};
+use super::truncate::{self, SENTENCE_BREAKS, WORD_BREAKS};
+use regex::Regex;
+use scraper::{Html, Selector};
+
+// Characters in a preview, unless a site sets its own preview_length
+pub const DEFAULT_PREVIEW_LENGTH: usize = 400;
+
+// What a preview is built for
+pub struct PreviewRequest<'a> {
+    pub max_length: usize,
+    // The site's selector; extractors working on HTML preview only that region
+    pub selector: Option<&'a str>,
+}
+
+// One kind of source a preview can be made of. A site names the one it wants in its
+// extraction_mode; otherwise the first registered extractor that recognizes the body is
+// used. Adding a source type means implementing this and listing it in EXTRACTORS.
+pub trait ContentExtractor: Sync {
+    // Name used in extraction_mode
+    fn name(&self) -> &'static str;
+    // Whether a body looks like this extractor's kind of content
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool;
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String;
+}
+
+// In the order bodies are sniffed; Html takes whatever the others don't
+static EXTRACTORS: [&dyn ContentExtractor; 16] = [&ReadabilityExtractor, &PdfExtractor, &LinksExtractor, &EdgarExtractor, &FdaExtractor, &FederalRegisterExtractor, &HaltsExtractor, &RedditExtractor, &SocialExtractor, &SubstackExtractor, &NewsSearchExtractor, &SitemapExtractor, &FeedExtractor, &JsonExtractor, &ScriptExtractor, &HtmlExtractor];
+
+pub fn by_name(name: &str) -> Option<&'static dyn ContentExtractor> {
+    EXTRACTORS.iter().copied().find(|extractor| extractor.name() == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    EXTRACTORS.iter().map(|extractor| extractor.name()).collect()
+}
+
+// The site's extractor when it names one, else the first that recognizes the body
+pub fn select(body: &str, extraction_mode: Option<&str>, request: &PreviewRequest) -> &'static dyn ContentExtractor {
+    extraction_mode.and_then(by_name)
+        .or_else(|| EXTRACTORS.iter().copied().find(|extractor| extractor.sniff(body, request)))
+        .unwrap_or(&HtmlExtractor)
+}
+
+// Extract and format a preview of the content
+// With a site selector configured, only the selected region is previewed
+pub fn preview(body: &str, max_length: usize, selector: Option<&str>, extraction_mode: Option<&str>) -> String {
+    let request = PreviewRequest { max_length, selector };
+    select(body, extraction_mode, &request).preview(body, &request)
+}
+
+// The article's title and as much of its text as fits, cut at a sentence if possible
+pub fn article_preview(article: &Article, max_length: usize) -> String {
+    let mut preview = article.title.as_ref().map(|title| format!("📰 {}\n\n", title)).unwrap_or_default();
+    preview.push_str(&truncate::preview(&article.text, max_length, &[SENTENCE_BREAKS, WORD_BREAKS]));
+    preview
+}
+
+
+// Main article of the page, or the generic HTML preview when it has none. Only used when
+// a site asks for it.
+struct ReadabilityExtractor;
+
+impl ContentExtractor for ReadabilityExtractor {
+    fn name(&self) -> &'static str {
+        "readability"
+    }
+
+    fn sniff(&self, _body: &str, _request: &PreviewRequest) -> bool {
+        false
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match readability::extract(body) {
+            Some(article) => article_preview(&article, request.max_length),
+            None => HtmlExtractor.preview(body, request),
+        }
+    }
+}
+
+// Text extracted from a PDF, which is what a PDF site stores. Picked through the site's
+// extraction_mode; a PDF served as one is converted whatever the mode.
+struct PdfExtractor;
+
+impl ContentExtractor for PdfExtractor {
+    fn name(&self) -> &'static str {
+        pdf::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, _body: &str, _request: &PreviewRequest) -> bool {
+        false
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        article_preview(&Article { title: None, text: body.to_string() }, request.max_length)
+    }
+}
+
+// The links of the page; checks preview only those new since the previous fetch. Without a
+// page URL to resolve them against, only absolute links are listed.
+struct LinksExtractor;
+
+impl ContentExtractor for LinksExtractor {
+    fn name(&self) -> &'static str {
+        links::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, _body: &str, _request: &PreviewRequest) -> bool {
+        false
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        links::preview(&links::extract(body, request.selector, None, None), false, request.max_length)
+    }
+}
+
+// Filings in an EDGAR submissions or full-text search response; checks report each new
+// one like a feed entry
+struct EdgarExtractor;
+
+impl ContentExtractor for EdgarExtractor {
+    fn name(&self) -> &'static str {
+        edgar::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, _body: &str, _request: &PreviewRequest) -> bool {
+        false
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match edgar::parse(body) {
+            Some(filings) => edgar::preview(&filings, request.max_length),
+            None => JsonExtractor.preview(body, request),
+        }
+    }
+}
+
+// Drug approvals and shortages from openFDA or an accessdata.fda.gov result table; checks
+// report each new record like a feed entry
+struct FdaExtractor;
+
+impl ContentExtractor for FdaExtractor {
+    fn name(&self) -> &'static str {
+        fda::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none() && fda::is_openfda(body)
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match fda::parse(body) {
+            Some(records) => fda::preview(&records, request.max_length),
+            None => HtmlExtractor.preview(body, request),
+        }
+    }
+}
+
+// Documents from the Federal Register's API; checks report each new document like a feed
+// entry and store its metadata
+struct FederalRegisterExtractor;
+
+impl ContentExtractor for FederalRegisterExtractor {
+    fn name(&self) -> &'static str {
+        federal_register::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none() && federal_register::read(body, None).is_some()
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match federal_register::read(body, Some(federal_register::EXTRACTION_MODE)) {
+            Some(documents) => federal_register::preview(&documents, request.max_length),
+            None => JsonExtractor.preview(body, request),
+        }
+    }
+}
+
+// Trading halts, like Nasdaq's halts feed and table; checks report each new halt
+struct HaltsExtractor;
+
+impl ContentExtractor for HaltsExtractor {
+    fn name(&self) -> &'static str {
+        halts::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none() && halts::read(body, None).is_some()
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match halts::parse(body) {
+            Some(halts) => halts::preview(&halts, request.max_length),
+            None => HtmlExtractor.preview(body, request),
+        }
+    }
+}
+
+// Posts and comments from Reddit's JSON listings; checks report each new one like a feed
+// entry
+struct RedditExtractor;
+
+impl ContentExtractor for RedditExtractor {
+    fn name(&self) -> &'static str {
+        reddit::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none() && reddit::is_listing_response(body)
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match reddit::parse(body) {
+            Some(entries) => reddit::preview(&entries, request.max_length),
+            None => extract_rss_preview(body, request.max_length),
+        }
+    }
+}
+
+// An account's posts from a Mastodon server, Bluesky or X; checks report each new post
+struct SocialExtractor;
+
+impl ContentExtractor for SocialExtractor {
+    fn name(&self) -> &'static str {
+        social::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none() && social::read(body, None).is_some()
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match social::read(body, Some(social::EXTRACTION_MODE)) {
+            Some(posts) => social::preview(&posts, request.max_length),
+            None => JsonExtractor.preview(body, request),
+        }
+    }
+}
+
+// A Substack publication's posts, free ones in full; checks report each new post
+struct SubstackExtractor;
+
+impl ContentExtractor for SubstackExtractor {
+    fn name(&self) -> &'static str {
+        substack::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none() && substack::read(body, None).is_some()
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match substack::read(body, Some(substack::EXTRACTION_MODE)) {
+            Some(posts) => substack::preview(&posts, request.max_length),
+            None => extract_rss_preview(body, request.max_length),
+        }
+    }
+}
+
+// News hits for a search site's query; checks report each new article
+struct NewsSearchExtractor;
+
+impl ContentExtractor for NewsSearchExtractor {
+    fn name(&self) -> &'static str {
+        news_search::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none() && news_search::read(body, None).is_some()
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match news_search::read(body, Some(news_search::EXTRACTION_MODE)) {
+            Some(hits) => news_search::preview(&hits, request.max_length),
+            None => extract_rss_preview(body, request.max_length),
+        }
+    }
+}
+
+// A sitemap.xml's URLs; checks report each page newly listed or modified
+struct SitemapExtractor;
+
+impl ContentExtractor for SitemapExtractor {
+    fn name(&self) -> &'static str {
+        sitemap::EXTRACTION_MODE
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none() && sitemap::is_sitemap(body)
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match sitemap::read(body, Some(sitemap::EXTRACTION_MODE)) {
+            Some(pages) => sitemap::preview(&pages, request.max_length),
+            None => HtmlExtractor.preview(body, request),
+        }
+    }
+}
+
+// RSS and Atom
+struct FeedExtractor;
+
+impl ContentExtractor for FeedExtractor {
+    fn name(&self) -> &'static str {
+        "feed"
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none()
+            && (body.contains("<?xml") || body.contains("<rss") || body.contains("<feed")
+                || body.contains("<item>") || body.contains("<entry>"))
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        extract_rss_preview(body, request.max_length)
+    }
+}
+
+struct JsonExtractor;
+
+impl ContentExtractor for JsonExtractor {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none()
+            && ((body.starts_with('{') && body.ends_with('}')) || (body.starts_with('[') && body.ends_with(']')))
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        extract_json_preview(body, request.max_length)
+    }
+}
+
+// Pages that are mostly JavaScript, cleaned of it first
+struct ScriptExtractor;
+
+impl ContentExtractor for ScriptExtractor {
+    fn name(&self) -> &'static str {
+        "script"
+    }
+
+    fn sniff(&self, body: &str, request: &PreviewRequest) -> bool {
+        request.selector.is_none() && (body.contains("function(") || body.contains("var ") || body.contains("const "))
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        let cleaned = clean_script_content(body);
+        format!("📰 Content Preview\n\n{}", truncate::preview(&cleaned, request.max_length, &[WORD_BREAKS]))
+    }
+}
+
+// Title and main content of an HTML page, or of the site's selected region. For the whole
+// page the title, date and summary the page declares in structured data come first.
+struct HtmlExtractor;
+
+impl ContentExtractor for HtmlExtractor {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn sniff(&self, _body: &str, _request: &PreviewRequest) -> bool {
+        true
+    }
+
+    fn preview(&self, body: &str, request: &PreviewRequest) -> String {
+        match request.selector {
+            Some(selector) => extract_html_preview(body, request.max_length, &[selector], None),
+            None => extract_html_preview(body, request.max_length, &DEFAULT_CONTENT_SELECTORS, structured::extract(body).as_ref()),
+        }
+    }
+}
+
+// Generic main-content selectors tried by priority when a site has no selector of its own
+const DEFAULT_CONTENT_SELECTORS: [&str; 13] = [
+    "article", "main", ".content", "#content", ".post-content", 
+    ".entry-content", ".article-content", ".post", "p",
+    ".news-article", ".article__content", ".story-body", ".story__content"
+];
+
+// Extract preview from HTML content using the HTML parser
+fn extract_html_preview(html: &str, max_length: usize, content_selectors: &[&str], metadata: Option<&structured::Metadata>) -> String {
+    // Create a new HTML document for parsing
+    let document = Html::parse_document(html);
+    
+    // Try to find the title, preferring the one the page declares
+    let mut title = metadata.and_then(|m| m.title.clone()).unwrap_or_default();
+    
+    // Else check for title tag
+    if title.is_empty() {
+        if let Ok(title_selector) = Selector::parse("title") {
+            if let Some(title_element) = document.select(&title_selector).next() {
+                title = title_element.text().collect::<Vec<_>>().join(" ").trim().to_string();
+            }
+        }
+    }
+    
+    // If no title, try h1
+    if title.is_empty() {
+        if let Ok(h1_selector) = Selector::parse("h1") {
+            if let Some(h1_element) = document.select(&h1_selector).next() {
+                title = h1_element.text().collect::<Vec<_>>().join(" ").trim().to_string();
+            }
+        }
+    }
+    
+    // Start building the preview
+    let mut preview = String::new();
+    
+    // Add the title with formatting if found
+    if !title.is_empty() {
+        preview.push_str(&format!("📰 {}\n\n", title));
+    }
+    if let Some(published) = metadata.and_then(|m| m.published) {
+        preview.push_str(&format!("🗓 {}\n\n", published.format("%Y-%m-%d")));
+    }
+    
+    // Try to extract meaningful content, starting with the page's own summary
+    let mut content_text = metadata.and_then(|m| m.summary.clone()).unwrap_or_default();
+    
+    // Otherwise try the content selectors by priority
+    let content_selectors = if content_text.is_empty() { content_selectors } else { &[] };
+    for selector_str in content_selectors {
+        if let Ok(selector) = Selector::parse(selector_str) {
+            let elements: Vec<_> = document.select(&selector).collect();
+            if !elements.is_empty() {
+                // Join text from all matching elements
+                content_text = elements.iter()
+                    .flat_map(|el| el.text())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .trim()
+                    .to_string();
+                
+                // Remove excessive whitespace
+                content_text = normalize_whitespace(&content_text);
+                
+                if !content_text.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+    
+    // If we couldn't extract content with selectors, fall back to general text extraction
+    if content_text.is_empty() {
+        // Extract all text from body
+        if let Ok(body_selector) = Selector::parse("body") {
+            if let Some(body) = document.select(&body_selector).next() {
+                content_text = body.text()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .trim()
+                    .to_string();
+                
+                content_text = normalize_whitespace(&content_text);
+                content_text = clean_script_content(&content_text);
+            }
+        }
+    }
+    
+    // Add content preview with length limit
+    if !content_text.is_empty() {
+        // Try to cut at a sentence boundary if possible
+        preview.push_str(&truncate::preview(&content_text, max_length, &[SENTENCE_BREAKS, WORD_BREAKS]));
+    } else if !preview.is_empty() {
+        // If we only have a title, add a placeholder for content
+        preview.push_str("[Content not available]");
+    } else {
+        // Complete fallback if we couldn't extract anything meaningful
+        preview = "Unable to extract readable content from this page.".to_string();
+    }
+    
+    preview
+}
+
+// Extract preview from RSS/XML content
+fn extract_rss_preview(xml: &str, max_length: usize) -> String {
+    let mut preview = String::new();
+    
+    // Very basic XML tag extraction
+    // Look for common RSS/feed elements
+    let title_pattern = Regex::new(r"<title[^>]*>(.*?)</title>").unwrap_or_else(|_| Regex::new(r"").unwrap());
+    let desc_pattern = Regex::new(r"<description[^>]*>(.*?)</description>").unwrap_or_else(|_| Regex::new(r"").unwrap());
+    let content_pattern = Regex::new(r"<content[^>]*>(.*?)</content>").unwrap_or_else(|_| Regex::new(r"").unwrap());
+    
+    // Extract title 
+    if let Some(captures) = title_pattern.captures(xml) {
+        if let Some(title_match) = captures.get(1) {
+            let title = clean_xml_entities(title_match.as_str());
+            if !title.is_empty() {
+                preview.push_str(&format!("📰 {}\n\n", title));
+            }
+        }
+    }
+    
+    // Try to extract content (prioritize content over description)
+    let mut content_text = String::new();
+    
+    if let Some(captures) = content_pattern.captures(xml) {
+        if let Some(content_match) = captures.get(1) {
+            content_text = clean_xml_entities(content_match.as_str());
+        }
+    }
+    
+    // If no content, try description
+    if content_text.is_empty() {
+        if let Some(captures) = desc_pattern.captures(xml) {
+            if let Some(desc_match) = captures.get(1) {
+                content_text = clean_xml_entities(desc_match.as_str());
+            }
+        }
+    }
+    
+    // If still no content, try extracting from CDATA sections
+    if content_text.is_empty() {
+        let cdata_pattern = Regex::new(r"<!\[CDATA\[(.*?)\]\]>").unwrap_or_else(|_| Regex::new(r"").unwrap());
+        if let Some(captures) = cdata_pattern.captures(xml) {
+            if let Some(cdata_match) = captures.get(1) {
+                content_text = clean_html_content(cdata_match.as_str());
+            }
+        }
+    }
+    
+    // Add content with length limit
+    if !content_text.is_empty() {
+        preview.push_str(&truncate::preview(&content_text, max_length, &[WORD_BREAKS]));
+    } else if !preview.is_empty() {
+        // If we only have a title, add a placeholder
+        preview.push_str("[RSS feed detected - content not available]");
+    } else {
+        // Complete fallback
+        preview = "RSS/XML content detected, but couldn't extract readable content.".to_string();
+    }
+    
+    preview
+}
+
+// Extract preview from JSON content
+fn extract_json_preview(json: &str, max_length: usize) -> String {
+    // Simple JSON preview - for now just indicate it's JSON and show a sample
+    let mut preview = String::new();
+    
+    preview.push_str("📊 JSON Data\n\n");
+    
+    // Get a short sample of the JSON
+    preview.push_str(&truncate::preview(json, max_length, &[WORD_BREAKS]));
+    
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_named_extractor_or_sniffs_the_body() {
+        let request = PreviewRequest { max_length: 100, selector: None };
+        let feed = r#"<?xml version="1.0"?><rss><channel><title>Filings</title><description>New 8-K</description></channel></rss>"#;
+        assert_eq!(select(feed, None, &request).name(), "feed");
+        assert_eq!(select(r#"{"halted": ["XYZ"]}"#, None, &request).name(), "json");
+        assert_eq!(select("<html><body><p>Hello</p></body></html>", None, &request).name(), "html");
+        // A named extractor wins over sniffing, and a selector means the page is HTML
+        assert_eq!(select(feed, Some("html"), &request).name(), "html");
+        assert_eq!(select(feed, None, &PreviewRequest { max_length: 100, selector: Some("title") }).name(), "html");
+        assert_eq!(select("<p>x</p>", Some("nonexistent"), &request).name(), "html");
+
+        assert_eq!(preview(feed, 100, None, None), "📰 Filings\n\nNew 8-K");
+        // Structured data beats the title tag and the page text, but not a site's selector
+        let release = r#"<html><head><title>Press | Acme</title><meta property="og:title" content="Acme reports Q3">
+            <meta property="article:published_time" content="2026-10-14"><meta name="description" content="Revenue rose."></head>
+            <body><p>Accept cookies</p></body></html>"#;
+        assert_eq!(preview(release, 100, None, None), "📰 Acme reports Q3\n\n🗓 2026-10-14\n\nRevenue rose.");
+        assert_eq!(preview(release, 100, Some("p"), None), "📰 Press | Acme\n\nAccept cookies");
+        assert!(by_name("readability").is_some());
+        assert_eq!(names(), ["readability", "pdf", "links", "edgar", "fda", "federal_register", "halts", "reddit", "social", "substack", "search", "sitemap", "feed", "json", "script", "html"]);
+    }
+}
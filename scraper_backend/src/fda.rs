@@ -0,0 +1,225 @@
+use super::feeds::FeedEntry;
+use super::truncate::{self, WORD_BREAKS};
+use chrono::NaiveDate;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+
+// Sites with this extraction_mode are read as FDA records: openFDA drug approval and
+// shortage results, or the result table of an accessdata.fda.gov query. openFDA responses
+// are recognized without it.
+pub const EXTRACTION_MODE: &str = "fda";
+
+// Marks every openFDA response, in meta.terms
+const OPENFDA_TERMS: &str = "https://open.fda.gov/terms/";
+const MAX_RECORDS: usize = 200;
+const DRUGS_FDA_URL: &str = "https://www.accessdata.fda.gov/scripts/cder/daf/index.cfm?event=overview.process&ApplNo=";
+
+// Approval submission statuses as openFDA abbreviates them
+const STATUSES: &[(&str, &str)] = &[("AP", "Approved"), ("TA", "Tentatively approved")];
+
+pub fn is_openfda(body: &str) -> bool {
+    body.trim_start().starts_with('{') && body.contains(OPENFDA_TERMS)
+}
+
+// One drug, its sponsor and what happened to it on its action date. The key identifies the
+// record across fetches; it changes when the record gets a new action.
+struct Record {
+    key: String,
+    drug: String,
+    sponsor: Option<String>,
+    action: String,
+    action_date: Option<NaiveDate>,
+    details: Vec<String>,
+    link: Option<String>,
+}
+
+impl Record {
+    fn entry(self) -> FeedEntry {
+        let title = match &self.sponsor {
+            Some(sponsor) => format!("{} ({}): {}", self.drug, sponsor, self.action),
+            None => format!("{}: {}", self.drug, self.action),
+        };
+        let mut details = self.details;
+        if let Some(date) = self.action_date {
+            details.push(format!("action date {}", date));
+        }
+        FeedEntry {
+            guid: self.key,
+            title: Some(title),
+            link: self.link,
+            summary: Some(details.join(", ")).filter(|s| !s.is_empty()),
+            published: self.action_date.map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc()),
+        }
+    }
+}
+
+fn date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    ["%Y%m%d", "%m/%d/%Y", "%Y-%m-%d", "%b %d, %Y", "%B %d, %Y"].iter().find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.split_whitespace().collect::<Vec<_>>().join(" ")).filter(|s| !s.is_empty()),
+        Value::Array(values) => values.iter().find_map(text),
+        _ => None,
+    }
+}
+
+// The records of an openFDA response or of the first result table, newest first as the
+// query sorts them; None if the body is neither
+pub fn parse(body: &str) -> Option<Vec<FeedEntry>> {
+    let records = match serde_json::from_str::<Value>(body) {
+        Ok(json) => json["results"].as_array()?.iter().filter_map(|result| approval(result).or_else(|| shortage(result))).collect(),
+        Err(_) => table(body)?,
+    };
+    Some(records.into_iter().take(MAX_RECORDS).map(Record::entry).collect())
+}
+
+// drug/drugsfda.json: an application and its submissions; the latest decided one is the
+// application's current action
+fn approval(result: &Value) -> Option<Record> {
+    let application = text(&result["application_number"])?;
+    let submission = result["submissions"].as_array()?.iter()
+        .filter(|s| text(&s["submission_status_date"]).is_some())
+        .max_by_key(|s| text(&s["submission_status_date"]))?;
+    let number = format!("{}{}", text(&submission["submission_type"]).unwrap_or_default(), text(&submission["submission_number"]).unwrap_or_default());
+    let status = text(&submission["submission_status"]).unwrap_or_default();
+    let action = STATUSES.iter().find(|(code, _)| *code == status).map_or(status.clone(), |(_, action)| action.to_string());
+    let action = match text(&submission["submission_type"]).as_deref() {
+        Some("SUPPL") => format!("{} supplement", action),
+        _ => action,
+    };
+    let drug = text(&result["products"][0]["brand_name"]).or_else(|| text(&result["openfda"]["brand_name"])).or_else(|| text(&result["openfda"]["generic_name"]))?;
+    let mut details = vec![format!("application {}", application)];
+    details.extend(text(&submission["submission_class_code_description"]).or_else(|| text(&submission["submission_class_code"])));
+    let digits: String = application.chars().filter(char::is_ascii_digit).collect();
+    Some(Record {
+        key: format!("fda:{}:{}", application, number),
+        drug,
+        sponsor: text(&result["sponsor_name"]),
+        action,
+        action_date: text(&submission["submission_status_date"]).as_deref().and_then(date),
+        details,
+        link: Some(format!("{}{}", DRUGS_FDA_URL, digits)),
+    })
+}
+
+// drug/shortages.json: a presentation of a drug and its shortage status, a new record
+// whenever the status changes
+fn shortage(result: &Value) -> Option<Record> {
+    let drug = text(&result["generic_name"]).or_else(|| text(&result["proprietary_name"]))?;
+    let status = text(&result["status"])?;
+    let sponsor = text(&result["company_name"]);
+    let presentation = text(&result["presentation"]);
+    let mut details: Vec<String> = presentation.iter().cloned().collect();
+    details.extend(text(&result["shortage_reason"]).map(|reason| format!("reason: {}", reason)));
+    Some(Record {
+        key: format!("shortage:{}:{}:{}:{}", drug, sponsor.as_deref().unwrap_or_default(), presentation.as_deref().unwrap_or_default(), status).to_lowercase(),
+        drug,
+        sponsor,
+        action: format!("Shortage {}", status.to_lowercase()),
+        action_date: text(&result["update_date"]).or_else(|| text(&result["initial_posting_date"])).as_deref().and_then(date),
+        details,
+        link: None,
+    })
+}
+
+fn cell_text(cell: ElementRef) -> String {
+    cell.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn column(headers: &[String], names: &[&str]) -> Option<usize> {
+    headers.iter().position(|h| names.iter().any(|name| h.contains(name)))
+}
+
+// The first table whose headers name a drug and a date, as accessdata.fda.gov lists query
+// results. Links in a row point at the record's page.
+fn table(body: &str) -> Option<Vec<Record>> {
+    let document = Html::parse_document(body);
+    let tables = Selector::parse("table").unwrap();
+    let rows = Selector::parse("tr").unwrap();
+    let cells = Selector::parse("th, td").unwrap();
+    let anchors = Selector::parse("a[href]").unwrap();
+    let base = Url::parse("https://www.accessdata.fda.gov/").unwrap();
+    document.select(&tables).find_map(|table| {
+        let mut table_rows = table.select(&rows);
+        let headers: Vec<String> = table_rows.next()?.select(&cells).map(|c| cell_text(c).to_lowercase()).collect();
+        let drug = column(&headers, &["drug", "brand", "proprietary", "product", "generic"])?;
+        let date = column(&headers, &["date"])?;
+        let sponsor = column(&headers, &["sponsor", "company", "applicant", "manufacturer"]);
+        let action = column(&headers, &["action", "status", "submission"]).filter(|i| *i != date);
+        let application = column(&headers, &["appl. no", "appl no", "application no", "application number"]);
+        // Without an action column the date's header names it, as in "Approval Date"
+        let dated = headers[date].trim_end_matches("date").trim();
+        let listed = match dated.chars().next() {
+            Some(first) => first.to_uppercase().chain(dated.chars().skip(1)).collect(),
+            None => "Listed".to_string(),
+        };
+        Some(table_rows
+            .filter_map(|row| {
+                let values: Vec<ElementRef> = row.select(&cells).collect();
+                let value = |i: Option<usize>| i.and_then(|i| values.get(i)).map(|c| cell_text(*c)).filter(|v| !v.is_empty());
+                let drug = value(Some(drug))?;
+                let (sponsor, action_date, application) = (value(sponsor), value(Some(date)), value(application));
+                let key = format!("fda:{}|{}|{}|{}", drug, sponsor.as_deref().unwrap_or_default(), application.as_deref().unwrap_or_default(), action_date.as_deref().unwrap_or_default());
+                Some(Record {
+                    key: key.to_lowercase(),
+                    link: row.select(&anchors).next().and_then(|a| base.join(a.value().attr("href")?).ok()).map(String::from),
+                    drug,
+                    sponsor,
+                    action: value(action).unwrap_or_else(|| listed.clone()),
+                    action_date: action_date.as_deref().and_then(self::date),
+                    details: application.map(|a| format!("application {}", a)).into_iter().collect(),
+                })
+            })
+            .collect())
+    })
+}
+
+// Headed "💊 2 FDA records", one record per line
+pub fn preview(records: &[FeedEntry], max_length: usize) -> String {
+    let lines = records.iter().map(|record| record.title.as_deref().unwrap_or(&record.guid)).collect::<Vec<_>>().join("\n");
+    let heading = format!("💊 {} FDA record{}", records.len(), if records.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&lines, max_length, &[WORD_BREAKS]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_approvals_shortages_and_result_tables() {
+        let approvals = r#"{"meta": {"disclaimer": "...", "terms": "https://open.fda.gov/terms/"}, "results": [
+            {"application_number": "NDA217806", "sponsor_name": "ELI LILLY AND CO",
+             "products": [{"brand_name": "ZEPBOUND"}],
+             "submissions": [
+                {"submission_type": "ORIG", "submission_number": "1", "submission_status": "AP", "submission_status_date": "20231108", "submission_class_code_description": "Type 5 - New Formulation or New Manufacturer"},
+                {"submission_type": "SUPPL", "submission_number": "12", "submission_status": "AP", "submission_status_date": "20261009"}
+             ]}
+        ]}"#;
+        assert!(is_openfda(approvals));
+        let entries = parse(approvals).unwrap();
+        assert_eq!(entries[0].guid, "fda:NDA217806:SUPPL12");
+        assert_eq!(entries[0].title.as_deref(), Some("ZEPBOUND (ELI LILLY AND CO): Approved supplement"));
+        assert_eq!(entries[0].summary.as_deref(), Some("application NDA217806, action date 2026-10-09"));
+        assert_eq!(entries[0].link.as_deref(), Some("https://www.accessdata.fda.gov/scripts/cder/daf/index.cfm?event=overview.process&ApplNo=217806"));
+        assert_eq!(entries[0].published.map(|d| d.date_naive()), NaiveDate::from_ymd_opt(2026, 10, 9));
+
+        let shortages = r#"{"meta": {"terms": "https://open.fda.gov/terms/"}, "results": [
+            {"generic_name": "Amoxicillin", "company_name": "Teva", "presentation": "Capsule, 500 mg", "status": "Current", "update_date": "10/01/2026", "shortage_reason": "Demand increase"}
+        ]}"#;
+        let current = parse(shortages).unwrap();
+        assert_eq!(current[0].title.as_deref(), Some("Amoxicillin (Teva): Shortage current"));
+        let resolved = parse(&shortages.replace("Current", "Resolved")).unwrap();
+        assert_ne!(current[0].guid, resolved[0].guid);
+
+        let table = r#"<table><tr><th>Drug Name</th><th>Active Ingredients</th><th>Company</th><th>Approval Date</th><th>Appl. No.</th></tr>
+            <tr><td><a href="/scripts/cder/daf/index.cfm?ApplNo=219876">NEWDRUG</a></td><td>newdrugamab</td><td>Acme Pharma</td><td>10/13/2026</td><td>BLA219876</td></tr></table>"#;
+        let rows = parse(table).unwrap();
+        assert_eq!(rows[0].title.as_deref(), Some("NEWDRUG (Acme Pharma): Approval"));
+        assert_eq!(rows[0].link.as_deref(), Some("https://www.accessdata.fda.gov/scripts/cder/daf/index.cfm?ApplNo=219876"));
+        assert!(parse("<p>No matching records</p>").is_none());
+    }
+}
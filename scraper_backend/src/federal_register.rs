@@ -0,0 +1,286 @@
+use super::feeds::FeedEntry;
+use super::storage::SharedStorage;
+use super::truncate::{self, WORD_BREAKS};
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use tracing::warn;
+
+// Sites with this extraction_mode poll the Federal Register's documents API and report each
+// new document. federalregister.gov search pages and feeds are switched to it when added.
+pub const EXTRACTION_MODE: &str = "federal_register";
+
+const HOST: &str = "federalregister.gov";
+const API_PATH: &str = "/api/v1/documents.json";
+// The API pages 20 documents by default; a busy day publishes more for broad queries
+const PER_PAGE: &str = "100";
+const MAX_LISTED: i64 = 500;
+
+// Document types as the API names them, and the names sites may give them by
+const DOCUMENT_TYPES: &[(&str, &str)] = &[
+    ("RULE", "rule"),
+    ("PRORULE", "proposed_rule"),
+    ("NOTICE", "notice"),
+    ("PRESDOCU", "presidential_document"),
+];
+
+// Per-site query of POST /api/sites: agency slugs as in the site's URLs, such as
+// "environmental-protection-agency", document types and full-text search terms
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct Query {
+    #[serde(default)]
+    pub agencies: Vec<String>,
+    #[serde(default)]
+    pub document_types: Vec<String>,
+    pub terms: Option<String>,
+}
+
+// One document as the API describes it
+#[derive(Serialize, FromRow, Clone, Debug, PartialEq)]
+pub struct FederalDocument {
+    pub document_number: String,
+    pub title: Option<String>,
+    // "Rule", "Proposed Rule", "Notice" or "Presidential Document"
+    pub document_type: Option<String>,
+    #[sqlx(json)]
+    pub agencies: Vec<String>,
+    pub publication_date: Option<NaiveDate>,
+    pub html_url: Option<String>,
+    pub pdf_url: Option<String>,
+    // The abstract
+    pub summary: Option<String>,
+}
+
+// A document as stored, one row per site and document number
+#[derive(Serialize, FromRow, Clone, Debug)]
+pub struct StoredFederalDocument {
+    pub id: i64,
+    pub site_id: i64,
+    // Update of the check that first listed it
+    pub update_id: Option<i64>,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub document: FederalDocument,
+    pub first_seen: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct FederalDocumentQuery {
+    pub site_id: Option<i64>,
+    // An API type such as "RULE", or a name such as "proposed_rule"
+    #[serde(rename = "type")]
+    pub document_type: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl FederalDocumentQuery {
+    // The type's display name, as stored
+    pub fn document_type(&self) -> Option<String> {
+        let code = document_type_code(self.document_type.as_deref()?.trim()).ok()?;
+        Some(display_type(code).to_string())
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(50).clamp(1, MAX_LISTED)
+    }
+}
+
+fn document_type_code(value: &str) -> Result<&'static str, String> {
+    DOCUMENT_TYPES.iter()
+        .find(|(code, name)| code.eq_ignore_ascii_case(value) || name.eq_ignore_ascii_case(&value.replace([' ', '-'], "_")))
+        .map(|(code, _)| *code)
+        .ok_or_else(|| format!("Invalid document type '{}': expected rule, proposed_rule, notice or presidential_document", value))
+}
+
+fn display_type(code: &str) -> &'static str {
+    match code {
+        "RULE" => "Rule",
+        "PRORULE" => "Proposed Rule",
+        "NOTICE" => "Notice",
+        _ => "Presidential Document",
+    }
+}
+
+fn is_federal_register(url: &Url) -> bool {
+    url.host_str().is_some_and(|host| host == HOST || host.ends_with(".federalregister.gov"))
+}
+
+// Document searches and feeds on federalregister.gov, which are better read through the API
+pub fn is_listing(source: &str) -> bool {
+    Url::parse(source.trim()).is_ok_and(|url| is_federal_register(&url) && (url.path().starts_with("/documents/search") || url.path().starts_with("/api/v1/documents")))
+}
+
+// The API URL a Federal Register site polls. A federalregister.gov search page or feed keeps
+// its conditions, which the API shares; the site's query adds to them. Other URLs, such as a
+// mirror of the API, are only added to.
+pub fn resolve(source: &str, query: Option<&Query>) -> Result<String, String> {
+    let mut url = Url::parse(source.trim()).map_err(|e| format!("Invalid URL '{}': {}", source, e))?;
+    if is_federal_register(&url) {
+        url.set_path(API_PATH);
+    }
+    let existing: Vec<String> = url.query_pairs().map(|(key, _)| key.into_owned()).collect();
+    {
+        let mut pairs = url.query_pairs_mut();
+        if let Some(query) = query {
+            for agency in query.agencies.iter().map(|a| a.trim().to_lowercase()).filter(|a| !a.is_empty()) {
+                pairs.append_pair("conditions[agencies][]", &agency);
+            }
+            for kind in query.document_types.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+                pairs.append_pair("conditions[type][]", document_type_code(kind)?);
+            }
+            if let Some(terms) = query.terms.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+                pairs.append_pair("conditions[term]", terms);
+            }
+        }
+        // Newest first, so the documents that matter are on the one page fetched
+        if !existing.iter().any(|key| key == "order") {
+            pairs.append_pair("order", "newest");
+        }
+        if !existing.iter().any(|key| key == "per_page") {
+            pairs.append_pair("per_page", PER_PAGE);
+        }
+    }
+    Ok(url.to_string())
+}
+
+fn text(value: &Value) -> Option<String> {
+    value.as_str().map(|s| s.split_whitespace().collect::<Vec<_>>().join(" ")).filter(|s| !s.is_empty())
+}
+
+// The documents of an API response, newest first as the query orders them. Responses are
+// recognized by their federalregister.gov links without the extraction mode; None if the
+// body is not one.
+pub fn read(body: &str, extraction_mode: Option<&str>) -> Option<Vec<FederalDocument>> {
+    let sniffed = body.trim_start().starts_with('{') && body.contains("\"document_number\"") && body.contains(HOST);
+    if extraction_mode != Some(EXTRACTION_MODE) && !sniffed {
+        return None;
+    }
+    let json: Value = serde_json::from_str(body).ok()?;
+    // A query matching nothing has a count and no results
+    let results = match (json["results"].as_array(), json["count"].as_u64()) {
+        (Some(results), _) => results,
+        (None, Some(_)) => return Some(Vec::new()),
+        (None, None) => return None,
+    };
+    Some(results.iter().filter_map(document).collect())
+}
+
+fn document(result: &Value) -> Option<FederalDocument> {
+    let agencies = result["agencies"].as_array().map(|agencies| {
+        agencies.iter().filter_map(|agency| text(&agency["name"]).or_else(|| text(&agency["raw_name"]))).collect()
+    });
+    Some(FederalDocument {
+        document_number: text(&result["document_number"])?,
+        title: text(&result["title"]),
+        document_type: text(&result["type"]),
+        agencies: agencies.unwrap_or_default(),
+        publication_date: text(&result["publication_date"]).and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+        html_url: text(&result["html_url"]),
+        pdf_url: text(&result["pdf_url"]).or_else(|| text(&result["public_inspection_pdf_url"])),
+        summary: text(&result["abstract"]),
+    })
+}
+
+// A document as a feed entry: its document number is the GUID, its type and agencies lead
+// the summary
+pub fn entry(document: &FederalDocument) -> FeedEntry {
+    let source = match (&document.document_type, document.agencies.is_empty()) {
+        (Some(kind), false) => Some(format!("{} from {}", kind, document.agencies.join(", "))),
+        (Some(kind), true) => Some(kind.clone()),
+        (None, false) => Some(document.agencies.join(", ")),
+        (None, true) => None,
+    };
+    let summary = match (source, &document.summary) {
+        (Some(source), Some(summary)) => Some(format!("{}. {}", source, summary)),
+        (source, summary) => source.or_else(|| summary.clone()),
+    };
+    FeedEntry {
+        guid: document.document_number.clone(),
+        title: document.title.clone(),
+        link: document.html_url.clone().or_else(|| document.pdf_url.clone()),
+        summary,
+        published: document.publication_date.map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc()),
+    }
+}
+
+// Stores the documents a check listed; the ones already stored are left as first seen
+pub async fn record(storage: &SharedStorage, site_id: i64, update_id: i64, documents: &[FederalDocument], now: DateTime<Utc>) {
+    for document in documents {
+        if let Err(e) = storage.record_federal_document(site_id, update_id, document, now).await {
+            warn!(document_number = %document.document_number, "Failed to store Federal Register document: {}", e);
+        }
+    }
+}
+
+// Headed "📜 2 documents", one document per line
+pub fn preview(documents: &[FederalDocument], max_length: usize) -> String {
+    let lines = documents.iter()
+        .map(|d| format!("{}: {}", d.document_type.as_deref().unwrap_or("Document"), d.title.as_deref().unwrap_or(&d.document_number)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let heading = format!("📜 {} document{}", documents.len(), if documents.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&lines, max_length, &[WORD_BREAKS]))
+}
+
+// GET /api/federal-documents: stored Federal Register documents, latest publication first,
+// filtered by ?site_id= and ?type=
+pub async fn list_documents(data: web::Data<AppState>, query: web::Query<FederalDocumentQuery>) -> impl Responder {
+    match data.storage.list_federal_documents(&query).await {
+        Ok(documents) => HttpResponse::Ok().json(documents),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_queries_and_reads_documents() {
+        let query = Query {
+            agencies: vec!["Environmental-Protection-Agency".into()],
+            document_types: vec!["proposed rule".into()],
+            terms: Some("PFAS".into()),
+        };
+        let url = resolve("https://www.federalregister.gov/documents/search?conditions%5Bterm%5D=water", Some(&query)).unwrap();
+        let url = Url::parse(&url).unwrap();
+        assert_eq!(url.path(), API_PATH);
+        let pairs: Vec<(String, String)> = url.query_pairs().map(|(k, v)| (k.into_owned(), v.into_owned())).collect();
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+        assert_eq!(pairs, vec![
+            pair("conditions[term]", "water"),
+            pair("conditions[agencies][]", "environmental-protection-agency"),
+            pair("conditions[type][]", "PRORULE"),
+            pair("conditions[term]", "PFAS"),
+            pair("order", "newest"),
+            pair("per_page", "100"),
+        ]);
+        assert!(resolve("https://www.federalregister.gov/", Some(&Query { document_types: vec!["memo".into()], ..Query::default() })).is_err());
+        assert!(is_listing("https://www.federalregister.gov/api/v1/documents.rss?conditions[agencies][]=treasury-department"));
+        assert!(!is_listing("https://www.federalregister.gov/documents/2026/10/14/2026-20555/some-rule"));
+
+        let response = r#"{"count": 2, "description": "Documents", "total_pages": 1, "results": [
+            {"title": "Air Plan Approval; Ohio", "type": "Rule", "abstract": "The EPA is approving a revision.",
+             "document_number": "2026-20555", "html_url": "https://www.federalregister.gov/documents/2026/10/14/2026-20555/air-plan-approval-ohio",
+             "pdf_url": "https://www.govinfo.gov/content/pkg/FR-2026-10-14/pdf/2026-20555.pdf", "publication_date": "2026-10-14",
+             "agencies": [{"raw_name": "ENVIRONMENTAL PROTECTION AGENCY", "name": "Environmental Protection Agency", "slug": "environmental-protection-agency"}]},
+            {"title": "Sunshine Act Meetings", "type": "Notice", "document_number": "2026-20501", "publication_date": "2026-10-13",
+             "agencies": [{"raw_name": "NATIONAL TRANSPORTATION SAFETY BOARD"}]}
+        ]}"#;
+        let documents = read(response, None).unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].agencies, vec!["Environmental Protection Agency"]);
+        assert_eq!(documents[0].publication_date, NaiveDate::from_ymd_opt(2026, 10, 14));
+        assert_eq!(documents[0].pdf_url.as_deref(), Some("https://www.govinfo.gov/content/pkg/FR-2026-10-14/pdf/2026-20555.pdf"));
+        let entry = entry(&documents[0]);
+        assert_eq!(entry.guid, "2026-20555");
+        assert_eq!(entry.summary.as_deref(), Some("Rule from Environmental Protection Agency. The EPA is approving a revision."));
+        assert_eq!(super::entry(&documents[1]).summary.as_deref(), Some("Notice from NATIONAL TRANSPORTATION SAFETY BOARD"));
+        assert_eq!(read(r#"{"count": 0, "description": "Documents", "total_pages": 0}"#, Some(EXTRACTION_MODE)), Some(Vec::new()));
+        assert!(read(r#"{"count": 0}"#, None).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+use super::scraper::clean_html_content;
+use super::truncate;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use serde::Serialize;
+use std::collections::HashSet;
+use reqwest::Url;
+
+// Item keys kept per site in seen_items, about three per entry; the least recently seen go first
+pub const MAX_SEEN_ITEMS: i64 = 3000;
+
+// Query parameters that ad and newsletter links rotate without pointing anywhere new
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "mc_cid", "mc_eid", "ref", "cmpid"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedEntry {
+    pub guid: String,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub summary: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+}
+
+// Result of comparing a fetched feed against the entries already seen for a site
+pub struct FeedDiff {
+    pub new_entries: Vec<FeedEntry>,
+    // True when the site had no tracked entries yet (first fetch, or after a reset)
+    pub first_fetch: bool,
+}
+
+// Compares entries against the item keys seen for a site. Entries sharing a key with an
+// earlier one in the same fetch are only reported once.
+pub fn diff_against(mut seen: HashSet<String>, entries: &[FeedEntry]) -> FeedDiff {
+    let first_fetch = seen.is_empty();
+    let mut new_entries = Vec::new();
+    for entry in entries {
+        let keys = item_keys(entry);
+        if !keys.iter().any(|key| seen.contains(key)) {
+            new_entries.push(entry.clone());
+        }
+        seen.extend(keys);
+    }
+    FeedDiff { new_entries, first_fetch }
+}
+
+// Parse RSS/Atom/JSON Feed content; None if the body isn't a feed
+pub fn parse_feed(body: &str) -> Option<Vec<FeedEntry>> {
+    let feed = feed_rs::parser::parse(body.as_bytes()).ok()?;
+    Some(feed.entries.into_iter()
+        .map(|entry| {
+            let summary = entry.summary.map(|t| t.content)
+                .or_else(|| entry.content.and_then(|c| c.body))
+                .map(|html| clean_html_content(&html))
+                .filter(|text| !text.is_empty());
+            FeedEntry {
+                guid: entry.id,
+                title: entry.title.map(|t| clean_html_content(&t.content)).filter(|t| !t.is_empty()),
+                link: entry.links.first().map(|l| l.href.clone()),
+                summary,
+                published: entry.published.or(entry.updated),
+            }
+        })
+        .collect())
+}
+
+// Every key an entry can be recognised by: its GUID, its link without tracking parameters,
+// and a hash of its title and summary. Feeds that hand out fresh GUIDs or ad links for the
+// same item on each fetch still repeat at least one of them.
+pub fn item_keys(entry: &FeedEntry) -> Vec<String> {
+    let mut keys = vec![format!("guid:{}", entry.guid)];
+    if let Some(link) = entry.link.as_deref().and_then(canonical_link) {
+        keys.push(format!("link:{}", link));
+    }
+    if entry.title.is_some() || entry.summary.is_some() {
+        let text = format!("{}\n{}", entry.title.as_deref().unwrap_or(""), entry.summary.as_deref().unwrap_or(""));
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        keys.push(format!("text:{:x}", Sha256::digest(text.as_bytes())));
+    }
+    keys
+}
+
+fn canonical_link(link: &str) -> Option<String> {
+    let mut url = Url::parse(link.trim()).ok()?;
+    url.set_fragment(None);
+    let kept: Vec<(String, String)> = url.query_pairs()
+        .filter(|(name, _)| !name.starts_with("utm_") && !TRACKING_PARAMS.contains(&name.as_ref()))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    match kept.is_empty() {
+        true => url.set_query(None),
+        false => {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        },
+    }
+    Some(url.into())
+}
+
+// Hash of the entry set, independent of item order and channel metadata like lastBuildDate
+pub fn entries_hash(entries: &[FeedEntry]) -> String {
+    let mut guids: Vec<&str> = entries.iter().map(|e| e.guid.as_str()).collect();
+    guids.sort_unstable();
+    guids.dedup();
+    let mut hasher = Sha256::new();
+    for guid in guids {
+        hasher.update(guid.as_bytes());
+        hasher.update(b"\n");
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+// Preview text for a single entry, in the same style as the other previews
+pub fn entry_preview(entry: &FeedEntry, max_length: usize) -> String {
+    let mut preview = String::new();
+    if let Some(title) = &entry.title {
+        preview.push_str(&format!("📰 {}\n\n", title));
+    }
+    match &entry.summary {
+        Some(summary) => preview.push_str(&truncate::preview(summary, max_length, &[])),
+        None if preview.is_empty() => preview.push_str("[New feed entry]"),
+        None => {},
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED_A: &str = r#"<?xml version="1.0"?><rss version="2.0"><channel><title>T</title>
+        <lastBuildDate>Mon, 01 Jan 2024 00:00:00 GMT</lastBuildDate>
+        <item><guid>a</guid><title>First</title><description>One</description></item>
+        <item><guid>b</guid><title>Second</title></item>
+        </channel></rss>"#;
+    const FEED_B: &str = r#"<?xml version="1.0"?><rss version="2.0"><channel><title>T</title>
+        <lastBuildDate>Tue, 02 Jan 2024 00:00:00 GMT</lastBuildDate>
+        <item><guid>b</guid><title>Second</title></item>
+        <item><guid>a</guid><title>First</title><description>One</description></item>
+        </channel></rss>"#;
+
+    #[test]
+    fn reordering_and_build_date_do_not_change_hash() {
+        let a = parse_feed(FEED_A).unwrap();
+        let b = parse_feed(FEED_B).unwrap();
+        assert_eq!(a.len(), 2);
+        assert_eq!(a[0].title.as_deref(), Some("First"));
+        assert_eq!(entries_hash(&a), entries_hash(&b));
+    }
+
+    #[test]
+    fn rotated_links_keep_their_keys() {
+        let entry = |guid: &str, link: &str, title: &str| FeedEntry {
+            guid: guid.into(),
+            title: Some(title.into()),
+            link: Some(link.into()),
+            summary: None,
+            published: None,
+        };
+        let a = item_keys(&entry("ad-1", "https://x.example/p?id=7&utm_source=rss#top", "Sponsored:  Buy now"));
+        let b = item_keys(&entry("ad-2", "https://x.example/p?utm_source=feed&id=7", "sponsored: buy now"));
+        assert_eq!(a.len(), 3);
+        assert_eq!(a[1], "link:https://x.example/p?id=7");
+        assert_eq!(a[1..], b[1..]);
+        let c = item_keys(&entry("ad-3", "https://x.example/p?id=8", "Other"));
+        assert!(c.iter().all(|key| !a.contains(key)));
+    }
+
+    #[test]
+    fn html_is_not_a_feed() {
+        assert!(parse_feed("<html><body><p>Hello</p></body></html>").is_none());
+    }
+}
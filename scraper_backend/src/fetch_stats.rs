@@ -0,0 +1,149 @@
+use super::bot::parse_duration;
+use super::storage::FetchLogEntry;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+// Windows GET /api/sites/{id}/stats covers without ?windows=
+pub const DEFAULT_WINDOWS: &str = "24h,7d,30d";
+const MAX_WINDOWS: usize = 10;
+const MAX_WINDOW_DAYS: i64 = 366;
+
+// A site's fetches summed up from its fetch log, which keeps its newest fetch_log_size
+// fetches: windows reaching back further only cover those
+#[derive(Serialize, Debug)]
+pub struct FetchStats {
+    pub site_id: i64,
+    // Oldest fetch in the log
+    pub since: Option<DateTime<Utc>>,
+    pub last_error: Option<LastError>,
+    pub windows: Vec<WindowStats>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct LastError {
+    pub fetched_at: DateTime<Utc>,
+    pub status_code: Option<i64>,
+    pub error: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct WindowStats {
+    // As asked for, such as "24h"
+    pub window: String,
+    pub fetches: i64,
+    pub successes: i64,
+    // Share of fetches without an error; None without fetches
+    pub success_rate: Option<f64>,
+    pub avg_ms: Option<i64>,
+    pub p50_ms: Option<i64>,
+    pub p95_ms: Option<i64>,
+    pub max_ms: Option<i64>,
+    pub changes: i64,
+    // Over the part of the window the log covers
+    pub changes_per_day: Option<f64>,
+}
+
+// "1h,24h,7d" as durations, each kept with its name
+pub fn parse_windows(windows: &str) -> Result<Vec<(String, Duration)>, String> {
+    let windows: Vec<(String, Duration)> = windows.split(',')
+        .map(str::trim)
+        .filter(|window| !window.is_empty())
+        .map(|window| match parse_duration(window) {
+            Some(duration) if duration > Duration::days(MAX_WINDOW_DAYS) => Err(format!("Window '{}' is longer than {} days", window, MAX_WINDOW_DAYS)),
+            Some(duration) if duration > Duration::zero() => Ok((window.to_string(), duration)),
+            _ => Err(format!("Invalid window '{}'; use durations like 1h, 24h or 7d", window)),
+        })
+        .collect::<Result<_, _>>()?;
+    match windows.len() {
+        0 => Err("windows must name at least one window".to_string()),
+        n if n > MAX_WINDOWS => Err(format!("At most {} windows", MAX_WINDOWS)),
+        _ => Ok(windows),
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+// Nearest-rank percentile of sorted durations
+fn percentile(sorted: &[i64], percent: usize) -> Option<i64> {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+// `entries` newest first, as the fetch log returns them
+pub fn summarize(site_id: i64, entries: &[FetchLogEntry], windows: &[(String, Duration)], now: DateTime<Utc>) -> FetchStats {
+    let since = entries.iter().map(|entry| entry.fetched_at).min();
+    let last_error = entries.iter().find_map(|entry| {
+        let error = entry.error.clone()?;
+        Some(LastError { fetched_at: entry.fetched_at, status_code: entry.status_code, error })
+    });
+    let windows = windows.iter().map(|(name, duration)| {
+        let start = now.checked_sub_signed(*duration).unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let fetches: Vec<&FetchLogEntry> = entries.iter().filter(|entry| entry.fetched_at > start).collect();
+        let successes = fetches.iter().filter(|entry| entry.error.is_none()).count() as i64;
+        let changes = fetches.iter().filter(|entry| entry.changed).count() as i64;
+        let mut durations: Vec<i64> = fetches.iter().map(|entry| entry.duration_ms).collect();
+        durations.sort_unstable();
+        let covered_days = since.map(|since| (now - since.max(start)).num_seconds() as f64 / 86400.0).filter(|days| *days > 0.0);
+        WindowStats {
+            window: name.clone(),
+            fetches: fetches.len() as i64,
+            successes,
+            success_rate: (!fetches.is_empty()).then(|| round(successes as f64 / fetches.len() as f64)),
+            avg_ms: (!durations.is_empty()).then(|| durations.iter().sum::<i64>() / durations.len() as i64),
+            p50_ms: percentile(&durations, 50),
+            p95_ms: percentile(&durations, 95),
+            max_ms: durations.last().copied(),
+            changes,
+            changes_per_day: covered_days.map(|days| round(changes as f64 / days)),
+        }
+    }).collect();
+    FetchStats { site_id, since, last_error, windows }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetch(now: DateTime<Utc>, hours_ago: i64, duration_ms: i64, error: Option<&str>, changed: bool) -> FetchLogEntry {
+        FetchLogEntry {
+            site_id: 1,
+            fetched_at: now - Duration::hours(hours_ago),
+            status_code: Some(if error.is_some() { 503 } else { 200 }),
+            duration_ms,
+            error: error.map(String::from),
+            proxy: None,
+            final_url: None,
+            content_type: None,
+            content_length: None,
+            changed,
+            update_id: None,
+        }
+    }
+
+    #[test]
+    fn sums_up_windows_of_the_fetch_log() {
+        let now = Utc::now();
+        let entries = [
+            fetch(now, 1, 100, None, true),
+            fetch(now, 2, 300, Some("HTTP 503"), false),
+            fetch(now, 3, 200, None, false),
+            fetch(now, 48, 400, None, true),
+        ];
+        let windows = parse_windows("24h, 7d").unwrap();
+        let stats = summarize(1, &entries, &windows, now);
+        assert_eq!(stats.last_error.as_ref().map(|e| e.error.as_str()), Some("HTTP 503"));
+        let day = &stats.windows[0];
+        assert_eq!((day.fetches, day.successes, day.success_rate), (3, 2, Some(0.667)));
+        assert_eq!((day.avg_ms, day.p50_ms, day.p95_ms, day.max_ms), (Some(200), Some(200), Some(300), Some(300)));
+        assert_eq!((day.changes, day.changes_per_day), (1, Some(1.0)));
+        // The log reaches back two days, not the week
+        assert_eq!((stats.windows[1].changes, stats.windows[1].changes_per_day), (2, Some(1.0)));
+
+        assert!(parse_windows("24x").is_err());
+        assert!(parse_windows(" ").is_err());
+        assert!(parse_windows("367d").is_err());
+        assert!(parse_windows("100000000d").is_err());
+    }
+}
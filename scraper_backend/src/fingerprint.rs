@@ -0,0 +1,130 @@
+use super::scraper::{clean_content_for_comparison, clean_html_content, content_hash};
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+#[derive(Serialize)]
+struct Fingerprint {
+    update_id: i64,
+    site_id: i64,
+    timestamp: DateTime<Utc>,
+    // Same hash the scraper uses for change detection
+    hash: String,
+    simhash: String,
+    entities: Vec<String>,
+}
+
+pub async fn get_fingerprint(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let update_id = path.into_inner();
+
+    match data.storage.update_content(update_id).await {
+        Ok(Some(record)) => {
+            let selector = record.selector.as_deref();
+            let text = clean_html_content(&clean_content_for_comparison(&record.content, selector, &record.ignore_patterns));
+            HttpResponse::Ok().json(Fingerprint {
+                update_id,
+                site_id: record.site_id,
+                timestamp: record.timestamp,
+                hash: content_hash(&record.content, selector, &record.ignore_patterns, data.config.hash_fold_case),
+                simhash: format!("{:016x}", simhash(&text)),
+                entities: extract_entities(&text, 25),
+            })
+        },
+        Ok(None) => HttpResponse::NotFound().body(format!("Update with ID {} not found", update_id)),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// 64-bit simhash over 3-word shingles; near-duplicate texts differ in few bits
+pub fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text.split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.is_empty() {
+        return 0;
+    }
+
+    let shingles: Vec<String> = if words.len() < 3 {
+        vec![words.join(" ")]
+    } else {
+        words.windows(3).map(|w| w.join(" ")).collect()
+    };
+
+    let mut weights = [0i64; 64];
+    for shingle in &shingles {
+        let digest = Sha256::digest(shingle.as_bytes());
+        let hash = u64::from_be_bytes(digest[..8].try_into().unwrap());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights.iter()
+        .enumerate()
+        .filter(|(_, w)| **w > 0)
+        .fold(0u64, |acc, (bit, _)| acc | (1 << bit))
+}
+
+// Heuristic entity list: ticker symbols ($NVDA, "NASDAQ: NVDA") and runs of capitalized
+// words ("Food and Drug Administration"), ordered by frequency
+pub fn extract_entities(text: &str, limit: usize) -> Vec<String> {
+    let ticker = Regex::new(r"\$([A-Z]{1,5})\b|\((?:NASDAQ|NYSE|NYSE American|OTC|TSX|LSE)\s*:\s*([A-Z.]{1,6})\)").unwrap();
+    let proper = Regex::new(r"\b[A-Z][a-zA-Z&'.-]+(?:\s+(?:of|and|for|the|de)?\s*[A-Z][a-zA-Z&'.-]+)+").unwrap();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut first_seen: Vec<String> = Vec::new();
+    let mut add = |entity: String| {
+        let count = counts.entry(entity.clone()).or_insert(0);
+        if *count == 0 {
+            first_seen.push(entity);
+        }
+        *count += 1;
+    };
+
+    for caps in ticker.captures_iter(text) {
+        if let Some(symbol) = caps.get(1).or_else(|| caps.get(2)) {
+            add(format!("${}", symbol.as_str()));
+        }
+    }
+    for m in proper.find_iter(text) {
+        let entity = m.as_str().split_whitespace().collect::<Vec<_>>().join(" ");
+        if entity.len() <= 80 {
+            add(entity);
+        }
+    }
+
+    // Stable order: most frequent first, ties by first appearance
+    let mut entities = first_seen;
+    entities.sort_by(|a, b| counts[b].cmp(&counts[a]));
+    entities.truncate(limit);
+    entities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simhash_is_close_for_near_duplicates() {
+        let a = simhash("The FDA approved the new drug for adults with chronic kidney disease on Monday");
+        let b = simhash("The FDA approved the new drug for adults with chronic kidney disease on Tuesday");
+        let c = simhash("Shares of the chipmaker fell sharply after the earnings call disappointed investors");
+        assert!((a ^ b).count_ones() < (a ^ c).count_ones());
+    }
+
+    #[test]
+    fn extracts_tickers_and_names() {
+        let entities = extract_entities("NVIDIA Corp (NASDAQ: NVDA) said $NVDA rose. Food and Drug Administration", 10);
+        assert!(entities.contains(&"$NVDA".to_string()));
+        assert!(entities.contains(&"Food and Drug Administration".to_string()));
+    }
+}
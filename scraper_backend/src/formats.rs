@@ -0,0 +1,110 @@
+use actix_web::http::header::{ACCEPT, CONTENT_SECURITY_POLICY, VARY};
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+
+// Representations the content and diff endpoints can answer with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Text,
+    Markdown,
+    // Sanitized HTML, only on ?format=html: browsers put text/html first in every Accept
+    Html,
+}
+
+// Keeps a browser opening stored HTML directly from running anything in it
+pub const SANDBOX_POLICY: &str = "sandbox; default-src 'none'; img-src http: https:";
+
+const SUPPORTED: &str = "application/json, text/plain, text/markdown";
+
+fn media_format(media: &str) -> Option<Format> {
+    match media {
+        "application/json" | "application/*" | "*/*" => Some(Format::Json),
+        "text/plain" | "text/*" => Some(Format::Text),
+        "text/markdown" | "text/x-markdown" => Some(Format::Markdown),
+        _ => None,
+    }
+}
+
+// Best supported type in an Accept header by q-value, earlier entries winning ties.
+// None when nothing listed is supported.
+fn preferred(accept: &str) -> Option<Format> {
+    let mut ranked: Vec<(f32, Format)> = accept
+        .split(',')
+        .filter_map(|entry| {
+            let mut params = entry.split(';').map(str::trim);
+            let format = media_format(&params.next()?.to_ascii_lowercase())?;
+            let quality = params
+                .filter_map(|p| p.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (quality > 0.0).then_some((quality, format))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    ranked.first().map(|(_, format)| *format)
+}
+
+// JSON when the client doesn't say; None when it only accepts types we can't produce
+pub fn negotiate(req: &HttpRequest) -> Option<Format> {
+    let Some(accept) = req.headers().get(ACCEPT).and_then(|v| v.to_str().ok()) else {
+        return Some(Format::Json);
+    };
+    if accept.trim().is_empty() {
+        return Some(Format::Json);
+    }
+    preferred(accept)
+}
+
+// A ?format= query value
+pub fn from_query(value: &str) -> Option<Format> {
+    match value {
+        "json" => Some(Format::Json),
+        "text" => Some(Format::Text),
+        "markdown" => Some(Format::Markdown),
+        "html" => Some(Format::Html),
+        _ => None,
+    }
+}
+
+pub fn not_acceptable() -> HttpResponse {
+    HttpResponse::NotAcceptable().body(format!("Supported formats: {}", SUPPORTED))
+}
+
+// Responses vary with Accept, so caches must not hand one format to another client
+pub fn json<T: Serialize>(value: &T) -> HttpResponse {
+    HttpResponse::Ok().insert_header((VARY, "Accept")).json(value)
+}
+
+// Plain text, or Markdown when that was asked for
+pub fn text(format: Format, body: String) -> HttpResponse {
+    let content_type = match format {
+        Format::Markdown => "text/markdown; charset=utf-8",
+        _ => "text/plain; charset=utf-8",
+    };
+    HttpResponse::Ok().content_type(content_type).insert_header((VARY, "Accept")).body(body)
+}
+
+// HTML page; the sandbox keeps a browser opening it directly from running anything in it,
+// which matters for raw pages and anything the sanitizer missed
+pub fn html(body: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header((CONTENT_SECURITY_POLICY, SANDBOX_POLICY))
+        .body(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_best_supported_type() {
+        assert_eq!(preferred("text/markdown"), Some(Format::Markdown));
+        assert_eq!(preferred("text/html,application/xhtml+xml,*/*;q=0.8"), Some(Format::Json));
+        assert_eq!(preferred("application/json;q=0.5, text/plain"), Some(Format::Text));
+        assert_eq!(preferred("text/plain;q=0, text/markdown;q=0.2"), Some(Format::Markdown));
+        assert_eq!(preferred("image/png"), None);
+        assert_eq!(preferred("TEXT/PLAIN; charset=utf-8"), Some(Format::Text));
+    }
+}
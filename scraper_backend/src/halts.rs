@@ -0,0 +1,289 @@
+use super::feeds::FeedEntry;
+use super::storage::SharedStorage;
+use super::truncate::{self, WORD_BREAKS};
+use super::{AppState, StreamEvent};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Timelike, Utc};
+use jiff::tz::{self, TimeZone};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use tokio::sync::broadcast::Sender;
+use tracing::warn;
+
+// Sites with this extraction_mode are read as a list of trading halts. Nasdaq's halts RSS
+// feed and halts table are recognized without it.
+pub const EXTRACTION_MODE: &str = "halts";
+
+// Halt and resumption times are given in exchange time
+const EXCHANGE_TIMEZONE: &str = "America/New_York";
+const MAX_HALTS: usize = 500;
+const MAX_LISTED: i64 = 500;
+
+// Nasdaq's halt reason codes
+const REASONS: &[(&str, &str)] = &[
+    ("T1", "News pending"),
+    ("T2", "News released"),
+    ("T3", "News and resumption times"),
+    ("T5", "Single stock trading pause"),
+    ("T6", "Extraordinary market activity"),
+    ("T7", "Quotation only period"),
+    ("T8", "Exchange-traded fund"),
+    ("T12", "Additional information requested"),
+    ("H4", "Non-compliance"),
+    ("H9", "Not current in required filings"),
+    ("H10", "SEC trading suspension"),
+    ("H11", "Regulatory concern"),
+    ("O1", "Operations halt"),
+    ("IPO1", "IPO not yet trading"),
+    ("M1", "Corporate action"),
+    ("M2", "Quotation not available"),
+    ("LUDP", "Volatility trading pause"),
+    ("LUDS", "Volatility trading pause, straddle condition"),
+    ("MWC1", "Market-wide circuit breaker level 1"),
+    ("MWC2", "Market-wide circuit breaker level 2"),
+    ("MWC3", "Market-wide circuit breaker level 3"),
+    ("MWC0", "Market-wide circuit breaker carried over"),
+];
+
+#[derive(Serialize, FromRow, Clone, Debug, PartialEq)]
+pub struct Halt {
+    pub symbol: String,
+    pub name: Option<String>,
+    pub market: Option<String>,
+    pub reason_code: Option<String>,
+    pub halted_at: DateTime<Utc>,
+    // When trading resumes; halts are often listed before it is known
+    pub resumed_at: Option<DateTime<Utc>>,
+}
+
+impl Halt {
+    fn reason(&self) -> Option<&'static str> {
+        let code = self.reason_code.as_deref()?;
+        REASONS.iter().find(|(c, _)| c.eq_ignore_ascii_case(code)).map(|(_, reason)| *reason)
+    }
+}
+
+// A halt as stored, one row per symbol and halt time
+#[derive(Serialize, FromRow, Clone, Debug)]
+pub struct StoredHalt {
+    pub id: i64,
+    pub site_id: i64,
+    // Update of the check that first listed it
+    pub update_id: Option<i64>,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub halt: Halt,
+    pub first_seen: DateTime<Utc>,
+}
+
+// What storing a listed halt changed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HaltChange {
+    New,
+    Resumed,
+    Unchanged,
+}
+
+// Sent on the live stream as `event: halt`
+#[derive(Serialize, Clone, Debug)]
+pub struct HaltEvent {
+    pub site_id: i64,
+    pub update_id: i64,
+    // "halted" for a new halt, "resumed" once a listed one gets a resumption time
+    pub status: &'static str,
+    #[serde(flatten)]
+    pub halt: Halt,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct HaltQuery {
+    pub site_id: Option<i64>,
+    pub symbol: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl HaltQuery {
+    pub fn symbol(&self) -> Option<String> {
+        self.symbol.as_deref().map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty())
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(50).clamp(1, MAX_LISTED)
+    }
+}
+
+// Nasdaq's feed tags and table headers, lowercased without punctuation or namespace
+fn key(name: &str) -> String {
+    let name = name.rsplit(':').next().unwrap_or(name);
+    name.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+fn text(element: ElementRef) -> String {
+    element.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// The halts a site lists, when it is in halts mode or looks like Nasdaq's halts feed or
+// table; None otherwise
+pub fn read(body: &str, extraction_mode: Option<&str>) -> Option<Vec<Halt>> {
+    let nasdaq = body.contains("ndaq:IssueSymbol") || (body.contains("Issue Symbol") && body.contains("Halt Time"));
+    match extraction_mode {
+        Some(EXTRACTION_MODE) => parse(body),
+        None if nasdaq => parse(body),
+        _ => None,
+    }
+}
+
+// RSS items with a field per halt detail, or the rows of a table whose headers name a
+// symbol and a halt time. A table without rows is a list without halts.
+pub fn parse(body: &str) -> Option<Vec<Halt>> {
+    let document = Html::parse_document(body);
+    let items = Selector::parse("item").unwrap();
+    let rss: Vec<HashMap<String, String>> = document.select(&items)
+        .map(|item| item.descendants().filter_map(ElementRef::wrap).map(|field| (key(field.value().name()), text(field))).collect())
+        .collect();
+    let rows = match rss.is_empty() {
+        true => table_rows(&document)?,
+        false => rss,
+    };
+    Some(rows.iter().filter_map(halt).take(MAX_HALTS).collect())
+}
+
+fn table_rows(document: &Html) -> Option<Vec<HashMap<String, String>>> {
+    let tables = Selector::parse("table").unwrap();
+    let rows = Selector::parse("tr").unwrap();
+    let cells = Selector::parse("th, td").unwrap();
+    document.select(&tables).find_map(|table| {
+        let mut table_rows = table.select(&rows).map(|row| row.select(&cells).map(text).collect::<Vec<_>>());
+        let headers: Vec<String> = table_rows.next()?.iter().map(|h| key(h)).collect();
+        let has = |names: &[&str]| headers.iter().any(|h| names.contains(&h.as_str()));
+        if !has(&["issuesymbol", "symbol", "ticker"]) || !has(&["halttime", "halted"]) {
+            return None;
+        }
+        Some(table_rows.map(|row| headers.iter().cloned().zip(row).collect()).collect())
+    })
+}
+
+fn halt(fields: &HashMap<String, String>) -> Option<Halt> {
+    let field = |names: &[&str]| names.iter().find_map(|n| fields.get(*n)).filter(|v| !v.is_empty()).cloned();
+    let halted_at = exchange_time(field(&["haltdate"]).as_deref(), field(&["halttime", "halted"])?.as_str())?;
+    let resumed_at = field(&["resumptiontradetime", "resumptiontime", "resumed"])
+        .and_then(|time| exchange_time(field(&["resumptiondate"]).or_else(|| field(&["haltdate"])).as_deref(), &time));
+    Some(Halt {
+        symbol: field(&["issuesymbol", "symbol", "ticker"])?.to_uppercase(),
+        name: field(&["issuename", "company", "name"]),
+        market: field(&["market"]),
+        reason_code: field(&["reasoncodes", "reasoncode", "reason"]),
+        halted_at,
+        resumed_at,
+    })
+}
+
+// A date such as 10/14/2026 or 2026-10-14 and a time such as 09:45:12, in exchange time.
+// The time may carry the date itself ("2026-10-14 09:45").
+fn exchange_time(date: Option<&str>, time: &str) -> Option<DateTime<Utc>> {
+    let (date, time) = match time.split_once(' ') {
+        Some((date, time)) => (date, time),
+        None => (date?, time),
+    };
+    let date = NaiveDate::parse_from_str(date.trim(), "%m/%d/%Y").or_else(|_| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")).ok()?;
+    let time = NaiveTime::parse_from_str(time.trim(), "%H:%M:%S%.f").or_else(|_| NaiveTime::parse_from_str(time.trim(), "%H:%M")).ok()?;
+    // Without the system time zone database, Eastern standard time
+    let zone = TimeZone::get(EXCHANGE_TIMEZONE).unwrap_or_else(|_| TimeZone::fixed(tz::offset(-5)));
+    let local = jiff::civil::date(date.year() as i16, date.month() as i8, date.day() as i8)
+        .at(time.hour() as i8, time.minute() as i8, time.second() as i8, 0)
+        .to_zoned(zone)
+        .ok()?;
+    DateTime::from_timestamp(local.timestamp().as_second(), 0)
+}
+
+// A halt as a feed entry, so it becomes an item and a notification of its own. Its
+// resumption is left out, so it doesn't make the halt look new.
+pub fn entry(halt: &Halt) -> FeedEntry {
+    let reason = match (&halt.reason_code, halt.reason()) {
+        (Some(code), Some(reason)) => format!(" ({} {})", code, reason.to_lowercase()),
+        (Some(code), None) => format!(" ({})", code),
+        (None, _) => String::new(),
+    };
+    let listing = [halt.name.as_deref(), halt.market.as_deref()].into_iter().flatten().collect::<Vec<_>>().join(", ");
+    FeedEntry {
+        guid: format!("halt:{}:{}", halt.symbol, halt.halted_at.to_rfc3339()),
+        title: Some(format!("{} halted{}", halt.symbol, reason)),
+        link: None,
+        summary: Some(listing).filter(|l| !l.is_empty()),
+        published: Some(halt.halted_at),
+    }
+}
+
+// Headed "⏸ 2 halts", one halt per line
+pub fn preview(halts: &[Halt], max_length: usize) -> String {
+    let lines = halts.iter().map(|halt| entry(halt).title.unwrap_or_default()).collect::<Vec<_>>().join("\n");
+    let heading = format!("⏸ {} halt{}", halts.len(), if halts.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&lines, max_length, &[WORD_BREAKS]))
+}
+
+// Stores the halts of a check and, unless it is the site's first, announces new halts and
+// resumptions on the live stream
+pub async fn record(storage: &SharedStorage, tx: &Sender<StreamEvent>, site_id: i64, update_id: i64, halts: &[Halt], announce: bool, now: DateTime<Utc>) {
+    for halt in halts {
+        let status = match storage.record_halt(site_id, update_id, halt, now).await {
+            Ok(HaltChange::New) => "halted",
+            Ok(HaltChange::Resumed) => "resumed",
+            Ok(HaltChange::Unchanged) => continue,
+            Err(e) => {
+                warn!(symbol = %halt.symbol, "Failed to store halt: {}", e);
+                continue;
+            },
+        };
+        if announce {
+            let _ = tx.send(StreamEvent::Halt(HaltEvent { site_id, update_id, status, halt: halt.clone(), reason: halt.reason(), timestamp: now }));
+        }
+    }
+}
+
+// GET /api/halts: stored halts, latest halt time first, filtered by ?site_id= and ?symbol=
+pub async fn list_halts(data: web::Data<AppState>, query: web::Query<HaltQuery>) -> impl Responder {
+    match data.storage.list_halts(&query).await {
+        Ok(halts) => HttpResponse::Ok().json(halts),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_halts_from_the_feed_and_the_table() {
+        let feed = r#"<?xml version="1.0" encoding="utf-8"?><rss version="2.0" xmlns:ndaq="http://www.nasdaqtrader.com/"><channel>
+            <title>NASDAQTrader.com Trade Halts</title>
+            <item><title>ABCD</title><ndaq:HaltDate>10/14/2026</ndaq:HaltDate><ndaq:HaltTime>09:45:12</ndaq:HaltTime>
+              <ndaq:IssueSymbol>ABCD</ndaq:IssueSymbol><ndaq:IssueName>Abcd Therapeutics Inc</ndaq:IssueName><ndaq:Market>NASDAQ</ndaq:Market>
+              <ndaq:ReasonCode>T1</ndaq:ReasonCode><ndaq:ResumptionDate></ndaq:ResumptionDate><ndaq:ResumptionTradeTime></ndaq:ResumptionTradeTime></item>
+            <item><title>WXYZ</title><ndaq:HaltDate>01/14/2026</ndaq:HaltDate><ndaq:HaltTime>11:02:00</ndaq:HaltTime>
+              <ndaq:IssueSymbol>WXYZ</ndaq:IssueSymbol><ndaq:ReasonCode>LUDP</ndaq:ReasonCode>
+              <ndaq:ResumptionDate>01/14/2026</ndaq:ResumptionDate><ndaq:ResumptionTradeTime>11:07:00</ndaq:ResumptionTradeTime></item>
+            </channel></rss>"#;
+        let halts = read(feed, None).unwrap();
+        assert_eq!(halts.len(), 2);
+        // 09:45 in New York is 13:45 UTC in October and 16:02 UTC in January
+        assert_eq!(halts[0].halted_at.to_rfc3339(), "2026-10-14T13:45:12+00:00");
+        assert_eq!(halts[0].resumed_at, None);
+        assert_eq!(halts[1].resumed_at.map(|t| t.to_rfc3339()).as_deref(), Some("2026-01-14T16:07:00+00:00"));
+        let entry = entry(&halts[0]);
+        assert_eq!(entry.title.as_deref(), Some("ABCD halted (T1 news pending)"));
+        assert_eq!(entry.summary.as_deref(), Some("Abcd Therapeutics Inc, NASDAQ"));
+
+        let table = r#"<table><tr><th>Halted</th><th>Ticker</th><th>Company</th><th>Reason</th></tr>
+            <tr><td>2026-10-14 10:30</td><td>efgh</td><td>Efgh Corp</td><td>H10</td></tr></table>"#;
+        assert!(read(table, None).is_none());
+        let halts = read(table, Some(EXTRACTION_MODE)).unwrap();
+        assert_eq!((halts[0].symbol.as_str(), halts[0].reason()), ("EFGH", Some("SEC trading suspension")));
+        assert_eq!(read("<table><tr><th>Issue Symbol</th><th>Halt Time</th></tr></table>", None), Some(Vec::new()));
+        assert_eq!(read("<p>No halts</p>", Some(EXTRACTION_MODE)), None);
+    }
+}
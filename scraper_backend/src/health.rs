@@ -0,0 +1,85 @@
+use super::storage::SharedStorage;
+use super::{StatusEvent, StreamEvent};
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::broadcast::Sender;
+use tracing::{info, warn};
+
+// A site's health, from how many checks in a row have failed: OK, then DEGRADED after
+// health_degraded_after failures and DOWN after health_down_after. One success makes it OK.
+pub const OK: &str = "OK";
+pub const DEGRADED: &str = "DEGRADED";
+pub const DOWN: &str = "DOWN";
+
+#[derive(Clone, Copy, Debug)]
+pub struct Thresholds {
+    pub degraded_after: i64,
+    pub down_after: i64,
+}
+
+impl Thresholds {
+    // DOWN never comes before DEGRADED
+    pub fn new(degraded_after: i64, down_after: i64) -> Self {
+        let degraded_after = degraded_after.max(1);
+        Thresholds { degraded_after, down_after: down_after.max(degraded_after) }
+    }
+
+    pub fn state(&self, failures: i64) -> &'static str {
+        match failures {
+            n if n >= self.down_after => DOWN,
+            n if n >= self.degraded_after => DEGRADED,
+            _ => OK,
+        }
+    }
+}
+
+// A site moving from one health state to another
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Transition {
+    pub from: Option<String>,
+    pub to: &'static str,
+    pub failures: i64,
+}
+
+// Counts a finished check into the site's failure streak and reports a change of state on
+// the stream. The first check's OK isn't worth an event.
+pub async fn record(storage: &SharedStorage, tx: &Sender<StreamEvent>, site_id: i64, url: &str, success: bool, thresholds: &Thresholds) {
+    let transition = match storage.record_health(site_id, success, thresholds).await {
+        Ok(Some(transition)) => transition,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Failed to record site health: {}", e);
+            return;
+        },
+    };
+    let (level, message) = match transition.to {
+        DOWN => ("error", format!("{} is DOWN after {} failed checks in a row", url, transition.failures)),
+        DEGRADED => ("warning", format!("{} is DEGRADED after {} failed checks in a row", url, transition.failures)),
+        _ if transition.from.is_none() => return,
+        _ => ("info", format!("{} is OK again", url)),
+    };
+    match transition.to {
+        OK => info!("{}", message),
+        _ => warn!("{}", message),
+    }
+    let _ = tx.send(StreamEvent::Status(StatusEvent {
+        kind: "health".into(),
+        site_id: Some(site_id),
+        level: level.into(),
+        message,
+        timestamp: Utc::now(),
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escalates_with_the_failure_streak() {
+        let thresholds = Thresholds::new(3, 10);
+        assert_eq!([0, 2, 3, 9, 10, 50].map(|n| thresholds.state(n)), [OK, OK, DEGRADED, DEGRADED, DOWN, DOWN]);
+        let inverted = Thresholds::new(5, 2);
+        assert_eq!((inverted.state(4), inverted.state(5)), (OK, DOWN));
+    }
+}
@@ -0,0 +1,32 @@
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use tracing::info;
+
+// GET /api/holds: every held site and update, for export before a hold is released
+pub async fn list_holds(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.legal_holds().await {
+        Ok(holds) => HttpResponse::Ok().json(holds),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+async fn set_hold(data: web::Data<AppState>, update_id: i64, held: bool) -> HttpResponse {
+    match data.storage.set_update_hold(update_id, held).await {
+        Ok(true) => {
+            info!(update_id, held, "Legal hold changed");
+            HttpResponse::Ok().finish()
+        },
+        Ok(false) => HttpResponse::NotFound().body(format!("Update with ID {} not found", update_id)),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// PUT /api/updates/{id}/hold: keep this update whatever retention says
+pub async fn hold_update(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    set_hold(data, path.into_inner(), true).await
+}
+
+// DELETE /api/updates/{id}/hold: hand the update back to retention
+pub async fn release_update(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    set_hold(data, path.into_inner(), false).await
+}
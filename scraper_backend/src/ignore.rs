@@ -0,0 +1,79 @@
+use super::scraper::parse_selector;
+use regex::Regex;
+use scraper::Html;
+
+// Entries with this prefix are CSS selectors; everything else is a regex
+const CSS_PREFIX: &str = "css:";
+
+// Trims entries and drops empty ones; invalid selectors and regexes are rejected up front
+pub fn validate_ignore_patterns(patterns: &[String]) -> Result<Vec<String>, String> {
+    let mut valid = Vec::new();
+    for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        match pattern.strip_prefix(CSS_PREFIX) {
+            Some(selector) => {
+                parse_selector(selector.trim()).map_err(|e| format!("Invalid ignore selector '{}': {}", selector.trim(), e))?;
+            },
+            None => {
+                Regex::new(pattern).map_err(|e| format!("Invalid ignore pattern '{}': {}", pattern, e))?;
+            },
+        }
+        valid.push(pattern.to_string());
+    }
+    Ok(valid)
+}
+
+// Strips a site's own volatile parts (nonces, CSRF tokens, "5 minutes ago") before
+// hashing: elements matching a `css:` selector are dropped, then regex matches removed.
+pub fn strip_ignored(html: &str, patterns: &[String]) -> String {
+    let (selectors, regexes): (Vec<&str>, Vec<&str>) = patterns.iter()
+        .map(String::as_str)
+        .partition(|p| p.starts_with(CSS_PREFIX));
+
+    // Re-serializing changes the markup, so pages without selectors are left as they are
+    let mut stripped = if selectors.is_empty() {
+        html.to_string()
+    } else {
+        let mut document = Html::parse_document(html);
+        let ignored: Vec<_> = selectors.iter()
+            .filter_map(|s| parse_selector(s[CSS_PREFIX.len()..].trim()).ok())
+            .flat_map(|selector| document.select(&selector).map(|e| e.id()).collect::<Vec<_>>())
+            .collect();
+        for id in ignored {
+            if let Some(mut node) = document.tree.get_mut(id) {
+                node.detach();
+            }
+        }
+        document.html()
+    };
+    for pattern in regexes {
+        if let Ok(re) = Regex::new(pattern) {
+            stripped = re.replace_all(&stripped, "").into_owned();
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraper::content_hash;
+
+    #[test]
+    fn ignored_parts_do_not_change_the_hash() {
+        let patterns = validate_ignore_patterns(&[
+            " css:input[name=csrf] ".into(),
+            "".into(),
+            r"\d+ minutes ago".into(),
+        ]).unwrap();
+        assert_eq!(patterns, vec!["css:input[name=csrf]", r"\d+ minutes ago"]);
+
+        let old = r#"<html><body><form><input name="csrf" value="a1"></form><p>Posted 5 minutes ago</p><p>News</p></body></html>"#;
+        let new = r#"<html><body><form><input name="csrf" value="b2"></form><p>Posted 7 minutes ago</p><p>News</p></body></html>"#;
+        assert_ne!(content_hash(old, None, &[], false), content_hash(new, None, &[], false));
+        assert_eq!(content_hash(old, None, &patterns, false), content_hash(new, None, &patterns, false));
+        assert!(!strip_ignored(new, &patterns).contains("b2"));
+
+        assert!(validate_ignore_patterns(&["css:<<".into()]).is_err());
+        assert!(validate_ignore_patterns(&["(".into()]).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use super::feeds::FeedEntry;
+use super::scraper::select_region;
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+const MAX_ITEMS: i64 = 500;
+// Links taken from one page, so a site index full of links doesn't flood the table
+const MAX_PAGE_ITEMS: usize = 100;
+// Shorter link texts are navigation ("Home", "Read more") rather than headlines
+const MIN_TITLE_WORDS: usize = 4;
+const MAX_SUMMARY_CHARS: usize = 300;
+
+// A news item as stored: a feed entry, or a headline link found on a page
+#[derive(Serialize, FromRow, Clone, Debug)]
+pub struct Item {
+    pub id: i64,
+    pub site_id: i64,
+    pub site_url: String,
+    pub guid: String,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub summary: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub first_seen: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ItemQuery {
+    pub site_id: Option<i64>,
+    pub tag: Option<String>,
+    // Case-insensitive text the title or summary must contain
+    pub q: Option<String>,
+    // Time range on published_at, or first_seen for items without a date
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+impl ItemQuery {
+    pub fn tag(&self) -> Option<String> {
+        self.tag.as_deref().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty())
+    }
+
+    pub fn keyword(&self) -> Option<String> {
+        self.q.as_deref().map(|q| q.trim().to_lowercase()).filter(|q| !q.is_empty())
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(50).clamp(1, MAX_ITEMS)
+    }
+}
+
+// Headline links of a page, within the site's selector when it has one: links of at least
+// MIN_TITLE_WORDS words outside nav, header, footer and aside. The article, list item or
+// table row around a link gives its summary and, through a <time> element, its date.
+pub fn html_items(html: &str, selector: Option<&str>, page_url: &str) -> Vec<FeedEntry> {
+    let region = selector.and_then(|s| select_region(html, s));
+    let document = Html::parse_document(region.as_deref().unwrap_or(html));
+    let Ok(page_url) = Url::parse(page_url) else {
+        return Vec::new();
+    };
+    let links = Selector::parse("a[href]").unwrap();
+    let times = Selector::parse("time[datetime]").unwrap();
+
+    let mut items: Vec<FeedEntry> = Vec::new();
+    for link in document.select(&links) {
+        let ancestors: Vec<ElementRef> = link.ancestors().filter_map(ElementRef::wrap).collect();
+        if ancestors.iter().any(|a| matches!(a.value().name(), "nav" | "header" | "footer" | "aside")) {
+            continue;
+        }
+        let title = collapse(link.text());
+        if title.split_whitespace().count() < MIN_TITLE_WORDS {
+            continue;
+        }
+        let Some(mut href) = link.value().attr("href").and_then(|href| page_url.join(href).ok()) else {
+            continue;
+        };
+        href.set_fragment(None);
+        if !matches!(href.scheme(), "http" | "https") || items.iter().any(|i| i.guid == href.as_str()) {
+            continue;
+        }
+        let container = ancestors.iter().find(|a| matches!(a.value().name(), "article" | "li" | "tr"));
+        let summary = container
+            .map(|c| collapse(c.text()).replacen(&title, "", 1).trim().to_string())
+            .filter(|s| !s.is_empty())
+            .map(|s| match s.chars().count() > MAX_SUMMARY_CHARS {
+                true => format!("{}...", s.chars().take(MAX_SUMMARY_CHARS).collect::<String>()),
+                false => s,
+            });
+        let published = container
+            .and_then(|c| c.select(&times).next())
+            .and_then(|time| time.value().attr("datetime"))
+            .and_then(parse_date);
+        items.push(FeedEntry { guid: href.to_string(), title: Some(title), link: Some(href.into()), summary, published });
+        if items.len() == MAX_PAGE_ITEMS {
+            break;
+        }
+    }
+    items
+}
+
+fn collapse<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// RFC 3339 or RFC 2822; dates and times without a zone are taken as UTC
+pub fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value).or_else(|_| DateTime::parse_from_rfc2822(value)).map(|d| d.with_timezone(&Utc)).ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").ok().map(|d| d.and_utc()))
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc()))
+}
+
+// GET /api/items: stored news items, newest first, filtered by ?site_id=, ?tag=, ?q= and
+// ?since=/?until=
+pub async fn list_items(data: web::Data<AppState>, query: web::Query<ItemQuery>) -> impl Responder {
+    match data.storage.list_items(&query).await {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_headline_links_outside_navigation() {
+        let html = r#"<html><body>
+            <nav><a href="/press">All press releases from the agency</a></nav>
+            <ul class="news">
+              <li><a href="/press/1#top">FDA approves first gene therapy for disease</a>
+                  <time datetime="2024-05-01">May 1</time> Approval follows phase 3 results.</li>
+              <li><a href="https://other.example/2">Agency issues warning letters to sellers</a></li>
+              <li><a href="/about">About us</a></li>
+              <li><a href="mailto:press@fda.example">Write to the press office team here</a></li>
+            </ul></body></html>"#;
+        let items = html_items(html, None, "https://fda.example/news/");
+        assert_eq!(items.iter().map(|i| i.guid.as_str()).collect::<Vec<_>>(), ["https://fda.example/press/1", "https://other.example/2"]);
+        assert_eq!(items[0].title.as_deref(), Some("FDA approves first gene therapy for disease"));
+        assert_eq!(items[0].summary.as_deref(), Some("May 1 Approval follows phase 3 results."));
+        assert_eq!(items[0].published, Some("2024-05-01T00:00:00Z".parse().unwrap()));
+        assert_eq!(items[1].summary, None);
+
+        assert_eq!(html_items(html, Some("li:nth-child(2)"), "https://fda.example/").len(), 1);
+    }
+}
@@ -0,0 +1,125 @@
+use super::feeds::{self, FeedEntry};
+use super::scraper::select_region;
+use super::truncate::{self, WORD_BREAKS};
+use regex::Regex;
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+// Sites with this extraction_mode report the links that appeared since the previous fetch
+pub const EXTRACTION_MODE: &str = "links";
+
+// Links of one fetch compared with those of the previous stored one
+pub struct LinkDiff {
+    pub first_fetch: bool,
+    // Of the whole link set, independent of link order
+    pub hash: String,
+    pub new_links: Vec<FeedEntry>,
+}
+
+// Empty means every link counts; invalid patterns are rejected up front
+pub fn validate_pattern(pattern: Option<&str>) -> Result<Option<String>, String> {
+    match pattern.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(pattern) => Regex::new(pattern)
+            .map(|_| Some(pattern.to_string()))
+            .map_err(|e| format!("Invalid link_pattern '{}': {}", pattern, e)),
+    }
+}
+
+// The http(s) links of a page, or of the site's selected region, whose absolute URL matches
+// the pattern: once each, without fragments, in page order. The link text is the title.
+// Without a page URL only absolute links can be resolved.
+pub fn extract(html: &str, selector: Option<&str>, page_url: Option<&Url>, pattern: Option<&str>) -> Vec<FeedEntry> {
+    let region = selector.and_then(|s| select_region(html, s));
+    let document = Html::parse_document(region.as_deref().unwrap_or(html));
+    let anchors = Selector::parse("a[href]").unwrap();
+    let pattern = pattern.and_then(|p| Regex::new(p).ok());
+
+    let mut links: Vec<FeedEntry> = Vec::new();
+    for anchor in document.select(&anchors) {
+        let href = anchor.value().attr("href").unwrap_or_default();
+        let Some(mut url) = page_url.map_or_else(|| Url::parse(href), |base| base.join(href)).ok() else {
+            continue;
+        };
+        url.set_fragment(None);
+        if !matches!(url.scheme(), "http" | "https")
+            || pattern.as_ref().is_some_and(|p| !p.is_match(url.as_str()))
+            || links.iter().any(|l| l.guid == url.as_str())
+        {
+            continue;
+        }
+        let text = anchor.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ");
+        links.push(FeedEntry {
+            guid: url.to_string(),
+            title: Some(text).filter(|t| !t.is_empty()),
+            link: Some(url.into()),
+            summary: None,
+            published: None,
+        });
+    }
+    links
+}
+
+// Compares the page's links with those of the previous stored body, if there is one
+pub fn diff(body: &str, previous: Option<&str>, selector: Option<&str>, page_url: &str, pattern: Option<&str>) -> LinkDiff {
+    let page_url = Url::parse(page_url).ok();
+    let current = extract(body, selector, page_url.as_ref(), pattern);
+    let new_links = match previous {
+        Some(previous) => {
+            let known = extract(previous, selector, page_url.as_ref(), pattern);
+            current.iter().filter(|link| known.iter().all(|k| k.guid != link.guid)).cloned().collect()
+        },
+        None => current.clone(),
+    };
+    LinkDiff { first_fetch: previous.is_none(), hash: feeds::entries_hash(&current), new_links }
+}
+
+// One link per line, its text before its URL
+pub fn describe(links: &[FeedEntry]) -> String {
+    links.iter()
+        .map(|link| match &link.title {
+            Some(title) => format!("{} {}", title, link.guid),
+            None => link.guid.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Headed "🔗 3 new links" for the new links of a check, "🔗 3 links" for all of a page's
+pub fn preview(links: &[FeedEntry], new: bool, max_length: usize) -> String {
+    let heading = format!("🔗 {} {}link{}", links.len(), if new { "new " } else { "" }, if links.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&describe(links), max_length, &[WORD_BREAKS]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_links_missing_from_the_previous_fetch() {
+        let before = r#"<nav><a href="/">Home</a></nav><main>
+            <a href="/opinions/23-411.pdf">Smith v. Jones</a>
+            <a href="https://cdn.example/opinions/23-388.pdf#page=2">Doe v. Roe</a></main>"#;
+        let after = r#"<nav><a href="/">Home</a></nav><main>
+            <a href="/opinions/23-412.pdf">  Acme v.
+               United States </a>
+            <a href="/opinions/23-411.pdf">Smith v. Jones</a>
+            <a href="/opinions/23-412.pdf">again</a>
+            <a href="mailto:clerk@court.example">Clerk</a>
+            <a href="https://cdn.example/opinions/23-388.pdf">Doe v. Roe</a></main>"#;
+        let url = "https://court.example/opinions/";
+
+        let changed = diff(after, Some(before), None, url, Some(r"\.pdf$"));
+        assert!(!changed.first_fetch);
+        assert_eq!(describe(&changed.new_links), "Acme v. United States https://court.example/opinions/23-412.pdf");
+        // Reordering or dropping links outside the pattern leaves the set, and its hash, alone
+        let unchanged = diff(before, Some(after), Some("main"), url, Some(r"\.pdf$"));
+        assert!(unchanged.new_links.is_empty());
+        assert_eq!(unchanged.hash, diff(&before.replace("Doe", "Doe et al."), None, None, url, Some(r"\.pdf$")).hash);
+
+        let first = diff(before, None, None, url, None);
+        assert!(first.first_fetch);
+        assert_eq!(first.new_links.len(), 3);
+        assert!(validate_pattern(Some("(")).is_err());
+    }
+}
@@ -1,26 +1,188 @@
 use actix_web::{web, App, HttpServer, HttpResponse, Responder};
 use actix_files::Files;
-use actix_web::middleware::Logger;
+use actix_web::dev::Service;
+use actix_web::http::StatusCode;
+use actix_web::middleware::{from_fn, Logger};
 use serde::{Deserialize, Serialize};
-use sqlx::{SqlitePool, FromRow};
+use sqlx::FromRow;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
 use chrono::{DateTime, Utc};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
 
+mod active_hours;
+mod alerts;
+mod anonymize;
+mod assertions;
+mod auth;
+mod bot;
+mod challenge;
+mod charset;
+mod compression;
+mod contents;
+mod crawl;
+mod dashboard;
+mod demo;
+mod diff;
+mod disk_monitor;
+mod documents;
+mod dry_run;
+mod edgar;
+mod email;
+mod export;
+mod extractors;
+mod fda;
+mod federal_register;
+mod feeds;
+mod fetch_stats;
+mod fingerprint;
+mod formats;
+mod halts;
+mod health;
+mod holds;
+mod ignore;
+mod items;
+mod links;
+mod markdown;
+mod metrics;
+mod netguard;
+mod news_search;
+mod notify;
+mod opml;
+mod pdf;
+mod plugins;
+mod proxies;
+mod readability;
+mod recommendations;
+mod reddit;
+mod reprocess;
+mod retention;
+mod sanitize;
+mod schema;
+mod screenshots;
+mod scripts;
 mod scraper;
+mod search;
+mod seeds;
+mod selftest;
+mod sitemap;
+mod social;
+mod sql_storage;
+mod storage;
+mod structured;
+mod substack;
+mod tags;
+mod telegram;
+mod timeline;
+mod truncate;
+mod watchdog;
+mod webhooks;
+mod wire;
 
 #[derive(Clone)]
 struct AppState {
-    pool: SqlitePool,
-    tx_updates: broadcast::Sender<UpdateMessage>,
+    storage: storage::SharedStorage,
+    tx_updates: broadcast::Sender<StreamEvent>,
+    scheduler: scraper::SchedulerHandle,
     config: AppConfig,
+    self_test: Arc<selftest::SelfTestReport>,
+    metrics: Arc<metrics::Metrics>,
+    timeline: Arc<timeline::Timeline>,
 }
 
 #[derive(Clone, Debug)]
 struct AppConfig {
     update_cache_size: i64,
+    // Days of history kept per site; 0 keeps updates regardless of age
+    update_retention_days: i64,
+    // How often the retention task prunes stored history
+    retention_interval_secs: i64,
     default_interval_secs: i64,
+    // Sitemaps change slowly and are large, so sitemap sites default to this interval instead
+    sitemap_interval_secs: i64,
     interval_jitter_max_ms: i64,
+    max_concurrent_fetches: i64,
+    per_domain_min_delay_ms: i64,
+    coalesce_window_ms: i64,
+    hash_fold_case: bool,
+    // Feed items not seen for this many days count as new when they come back; 0 never forgets
+    feed_lookback_days: i64,
+    static_export_dir: Option<String>,
+    static_export_interval_secs: i64,
+    static_export_limit: i64,
+    static_export_anonymize: bool,
+    export_alias_salt: String,
+    db_size_warn_mb: i64,
+    disk_free_warn_mb: i64,
+    storage_check_interval_secs: i64,
+    emergency_retention_size: i64,
+    watchdog_interval_secs: i64,
+    watchdog_max_age_multiple: i64,
+    watchdog_min_age_secs: i64,
+    // Failed checks in a row that make a site DEGRADED and DOWN
+    health: health::Thresholds,
+    self_test_canary_url: Option<String>,
+    self_test_exit_on_failure: bool,
+    // /readyz fails once the scheduler loop has been silent this long
+    readiness_max_heartbeat_age_secs: i64,
+    webhook_max_attempts: i64,
+    webhook_retry_base_ms: i64,
+    // Notifications per site within notify_window_secs before further changes are
+    // collapsed into one; a 0 in either turns the throttle off. Sites can override both.
+    notify_burst_limit: i64,
+    notify_window_secs: i64,
+    // Changes to content published longer ago than this many hours are stored but not
+    // notified; 0 notifies them all. Sites can override it.
+    max_content_age_hours: i64,
+    // Bot API base URL, overridable for a local stub
+    telegram_api_url: String,
+    // smtp:// or smtps:// URL of the relay; email is off without one
+    smtp_url: Option<email::SmtpUrl>,
+    email_from: String,
+    fetch_log_size: i64,
+    // Largest response body read from a site; sites can override it
+    max_body_bytes: i64,
+    // Scheduler events kept in memory for GET /api/admin/events
+    event_log_size: i64,
+    tuning: recommendations::Tuning,
+    adaptive: recommendations::AdaptiveBounds,
+    // Hours at which sites are checked at their normal interval; sites can override it
+    active_window: Option<active_hours::ActiveWindow>,
+    // How often recommended intervals are applied automatically; 0 disables
+    tune_auto_apply_interval_secs: i64,
+    webhook_canary_interval_secs: i64,
+    // Refuses site fetches to internal addresses outside private_network_allowlist
+    network_guard: netguard::NetworkGuard,
+    // Proxy pool site fetches rotate through, shared by every clone; sites can override it
+    proxies: Arc<proxies::Proxies>,
+    // WASM plugins in plugin_dir, reloaded when their files change
+    plugins: Arc<plugins::Plugins>,
+    // Browser mode: changed pages are screenshotted with the browser at browser_path
+    browser: Option<screenshots::Browser>,
+    // FlareSolverr endpoint that pages answered with an anti-bot challenge are fetched
+    // again through; without one they fail as CHALLENGE
+    challenge_solver: Option<challenge::Solver>,
+    // SEC EDGAR endpoints and the User-Agent EDGAR sites identify themselves with
+    edgar: edgar::Edgar,
+    // The User-Agent Reddit sites send and the app credentials they use the OAuth API with
+    reddit: reddit::Reddit,
+    // The Bluesky and X APIs social sites read, and the token X sites need
+    social: social::Social,
+    // The news searches search sites poll for their query
+    news_search: news_search::NewsSearch,
+    // Keys from api_keys; more can be created at /api/admin/keys
+    api_keys: auth::ConfigKeys,
+    // Require a key or token for GET routes under /api too
+    protect_reads: bool,
+    // Let requests without a key use write routes; admin routes still need one
+    allow_open_writes: bool,
+    // Sites added on a fresh install, after a reset and by --seed; None adds none
+    seed_file: Option<String>,
+    // Started with --demo: fixture sites instead of the seed file
+    demo: bool,
+    demo_change_interval_secs: i64,
 }
 
 #[derive(Serialize, Deserialize, FromRow, Clone)]
@@ -32,49 +194,887 @@ struct Site {
     last_checked: Option<DateTime<Utc>>,
     last_updated: Option<DateTime<Utc>>,
     status: Option<String>,
+    snoozed_until: Option<DateTime<Utc>>,
+    last_success: Option<DateTime<Utc>>,
+    selector: Option<String>,
+    // Higher priority sites are dispatched first when several checks are due together
+    priority: i64,
+    // Updates kept for this site; None uses update_cache_size
+    retention_count: Option<i64>,
+    // Days of history kept for this site; None uses update_retention_days
+    retention_days: Option<i64>,
+    // Large files: "head" compares Content-Length/ETag, "tail" hashes the last
+    // probe_tail_kb KB via a Range request; the full body is only fetched on a difference
+    probe_mode: Option<String>,
+    probe_tail_kb: Option<i64>,
+    probe_signature: Option<String>,
+    // Expected content: a fetch missing the selector, not matching the pattern, or with
+    // less visible text than the minimum is marked EXTRACTION_FAILED instead of stored
+    assert_selector: Option<String>,
+    assert_pattern: Option<String>,
+    assert_min_length: Option<i64>,
+    // Regexes and `css:` selectors stripped before hashing, stored as a JSON array
+    #[sqlx(json)]
+    #[serde(default)]
+    ignore_patterns: Vec<String>,
+    // Legal hold: the site and its stored history are never pruned or deleted
+    #[serde(default)]
+    legal_hold: bool,
+    // Notification throttle; None uses notify_burst_limit and notify_window_secs
+    notify_burst_limit: Option<i64>,
+    notify_window_secs: Option<i64>,
+    // Proxies this site is fetched through, in turn; empty uses the global pool and
+    // ["direct"] none. Passwords are masked when the site is serialized.
+    #[sqlx(json)]
+    #[serde(default, serialize_with = "proxies::serialize_redacted")]
+    proxies: Vec<String>,
+    // Backoff of the scheduler after failed or rate-limited fetches: how many in a row,
+    // when the next check is due and what the last one got back
+    #[serde(default)]
+    backoff_count: i64,
+    backoff_until: Option<DateTime<Utc>>,
+    backoff_reason: Option<String>,
+    // Checks in a row that failed and the health they put the site in; see health.rs
+    #[serde(default)]
+    consecutive_failures: i64,
+    health: Option<String>,
+    // What the adaptive style has learned from the site's change rate; None until its
+    // first check, and again after interval_secs is changed
+    adaptive_interval_secs: Option<i64>,
+    // Overrides the global active_window; stored as JSON, null uses the global one
+    #[sqlx(json)]
+    #[serde(default)]
+    active_window: Option<active_hours::ActiveWindow>,
+    // Burst mode from POST /api/sites/{id}/burst: checked every burst_interval_secs until
+    // burst_until, whatever the style and active hours
+    burst_interval_secs: Option<i64>,
+    burst_until: Option<DateTime<Utc>>,
+    // Fetch limits; None uses a 10 second timeout, 10 redirects and max_body_bytes
+    timeout_secs: Option<i64>,
+    max_redirects: Option<i64>,
+    max_body_bytes: Option<i64>,
+    // Extractor building the site's previews instead of guessing from the body: "html",
+    // "feed", "json", "reddit" (Reddit's JSON listings), "social" (an account's posts on
+    // Mastodon, Bluesky or X), "substack" (a publication's posts API), "search" (news hits
+    // for a query), "sitemap" (pages new to a sitemap), "script", "readability", which also
+    // hashes only the page's main article, "pdf", which reads the URL as a PDF
+    // whatever its Content-Type, or "links", which reports the links that appeared since
+    // the previous fetch
+    extraction_mode: Option<String>,
+    // Length of live previews in characters; None uses 400
+    preview_length: Option<i64>,
+    // Rhai script from PUT /api/sites/{id}/script giving the title, text and items used for
+    // the hash, previews, alerts and items instead of the built-in extraction
+    extraction_script: Option<String>,
+    // WASM plugin in plugin_dir fetching and extracting the site instead; see plugins.rs
+    plugin: Option<String>,
+    // With extraction_mode "links", only links whose URL matches this regex count
+    link_pattern: Option<String>,
+    // With extraction_mode "links", fetch the new PDF and HTML documents a check finds and
+    // keep a copy in documents; see documents.rs
+    #[serde(default)]
+    archive_links: bool,
+    // Overrides max_content_age_hours; None uses it
+    max_content_age_hours: Option<i64>,
+    // Listing pages linked from the site's page whose URL matches this regex are fetched
+    // and checked with it, up to crawl_depth links away (None is 1) and crawl_max_pages
+    // pages in all (None is 3); see crawl.rs
+    crawl_pattern: Option<String>,
+    crawl_depth: Option<i64>,
+    crawl_max_pages: Option<i64>,
+    // The watchdog flags the site STALE once it has gone this long without a change; None
+    // never does
+    max_quiet_hours: Option<i64>,
+    // Seconds of burst left, filled in for GET /api/sites
+    #[sqlx(skip)]
+    #[serde(default, skip_deserializing)]
+    burst_remaining_secs: Option<i64>,
+    // Kept in site_tags; filled in by the storage backend
+    #[sqlx(skip)]
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl Site {
+    // A site that hasn't been checked yet, with default settings
+    fn new(url: &str, interval_secs: i64, style: &str) -> Self {
+        Site {
+            id: 0,
+            url: url.to_string(),
+            interval_secs,
+            style: style.to_string(),
+            last_checked: None,
+            last_updated: None,
+            status: None,
+            snoozed_until: None,
+            last_success: None,
+            selector: None,
+            priority: 0,
+            retention_count: None,
+            retention_days: None,
+            probe_mode: None,
+            probe_tail_kb: None,
+            probe_signature: None,
+            assert_selector: None,
+            assert_pattern: None,
+            assert_min_length: None,
+            ignore_patterns: Vec::new(),
+            legal_hold: false,
+            notify_burst_limit: None,
+            notify_window_secs: None,
+            proxies: Vec::new(),
+            backoff_count: 0,
+            backoff_until: None,
+            backoff_reason: None,
+            consecutive_failures: 0,
+            health: None,
+            adaptive_interval_secs: None,
+            active_window: None,
+            burst_interval_secs: None,
+            burst_until: None,
+            burst_remaining_secs: None,
+            timeout_secs: None,
+            max_redirects: None,
+            max_body_bytes: None,
+            extraction_mode: None,
+            preview_length: None,
+            extraction_script: None,
+            plugin: None,
+            link_pattern: None,
+            archive_links: false,
+            max_content_age_hours: None,
+            crawl_pattern: None,
+            crawl_depth: None,
+            crawl_max_pages: None,
+            max_quiet_hours: None,
+            tags: Vec::new(),
+        }
+    }
 }
 
 #[derive(Serialize, Clone)]
 struct UpdateMessage {
+    update_id: i64,
     site_id: i64,
     url: String,
     timestamp: DateTime<Utc>,
     diff_hash: String,
     content_preview: String,
     has_full_content: bool,
+    // Set for messages describing a single new feed entry, and for press releases: the
+    // release's headline and the wire's copy of it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link: Option<String>,
+    // Alert rules matched by text added in this update
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    matched_rules: Vec<alerts::MatchedRule>,
+    // Source's own timestamp (feed entry date or Last-Modified) and how long after
+    // it the change was detected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detection_latency_secs: Option<i64>,
+    // Set on notifications the throttle collapsed a burst into: how many changes this one,
+    // the latest of them, stands for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collapsed_updates: Option<i64>,
+    // Title, dates and summary the page declared in structured data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<structured::Metadata>,
+    // Press-release wire the release came from, such as "businesswire"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wire_source: Option<String>,
+    // A screenshot of the changed page is at GET /api/updates/{id}/screenshot
+    has_screenshot: bool,
+    // Archived copies of the documents this change newly linked to, served by this server
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    documents: Vec<documents::ArchivedDocument>,
+}
+
+impl UpdateMessage {
+    // Rebuilt from a stored change for replays; per-entry feed details, alert matches,
+    // latencies, screenshots and archived documents only exist on the live message
+    fn from_change(change: &storage::ChangeEntry) -> Self {
+        UpdateMessage {
+            update_id: change.id,
+            site_id: change.site_id,
+            url: change.url.clone(),
+            timestamp: change.timestamp,
+            diff_hash: change.diff_hash.clone(),
+            content_preview: extractors::preview(&change.content, extractors::DEFAULT_PREVIEW_LENGTH, change.selector.as_deref(), None),
+            has_full_content: true,
+            title: None,
+            link: None,
+            matched_rules: Vec::new(),
+            published: change.published_at,
+            detection_latency_secs: None,
+            collapsed_updates: None,
+            metadata: change.metadata.clone(),
+            wire_source: change.wire_source.clone(),
+            has_screenshot: false,
+            documents: Vec::new(),
+        }
+    }
+}
+
+// System-level notice pushed to stream subscribers (storage alerts and the like)
+#[derive(Serialize, Clone)]
+struct StatusEvent {
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site_id: Option<i64>,
+    level: String,
+    message: String,
+    timestamp: DateTime<Utc>,
+}
+
+// Everything broadcast to SSE subscribers. Updates go out as plain `message` events
+// so existing clients keep working; other variants use a named SSE event.
+#[derive(Clone)]
+enum StreamEvent {
+    Update(UpdateMessage),
+    Status(StatusEvent),
+    // Sent alongside the regular update when it matched alert rules
+    Alert(UpdateMessage),
+    // A halts site listed a new trading halt or a resumption
+    Halt(halts::HaltEvent),
+}
+
+impl StreamEvent {
+    // System-wide status events have no site and pass every stream filter
+    fn site_id(&self) -> Option<i64> {
+        match self {
+            StreamEvent::Update(msg) | StreamEvent::Alert(msg) => Some(msg.site_id),
+            StreamEvent::Status(status) => status.site_id,
+            StreamEvent::Halt(halt) => Some(halt.site_id),
+        }
+    }
+
+    fn to_sse(&self) -> String {
+        match self {
+            // The update ID doubles as the SSE event ID for Last-Event-ID replay
+            StreamEvent::Update(msg) => format!("id: {}\ndata: {}\n\n", msg.update_id, serde_json::to_string(msg).unwrap()),
+            StreamEvent::Status(status) => format!("event: status\ndata: {}\n\n", serde_json::to_string(status).unwrap()),
+            StreamEvent::Alert(msg) => format!("event: alert\ndata: {}\n\n", serde_json::to_string(msg).unwrap()),
+            StreamEvent::Halt(halt) => format!("event: halt\ndata: {}\n\n", serde_json::to_string(halt).unwrap()),
+        }
+    }
 }
 
 #[derive(Deserialize)]
 struct NewSite {
+    // May be left out for search sites given a query
+    #[serde(default)]
     url: String,
     interval_secs: Option<i64>,
     style: Option<String>,
+    selector: Option<String>,
+    priority: Option<i64>,
+    retention_count: Option<i64>,
+    retention_days: Option<i64>,
+    probe_mode: Option<String>,
+    probe_tail_kb: Option<i64>,
+    assert_selector: Option<String>,
+    assert_pattern: Option<String>,
+    assert_min_length: Option<i64>,
+    #[serde(default)]
+    ignore_patterns: Vec<String>,
+    #[serde(default)]
+    legal_hold: bool,
+    notify_burst_limit: Option<i64>,
+    notify_window_secs: Option<i64>,
+    #[serde(default)]
+    proxies: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    active_window: Option<active_hours::ActiveWindow>,
+    timeout_secs: Option<i64>,
+    max_redirects: Option<i64>,
+    max_body_bytes: Option<i64>,
+    extraction_mode: Option<String>,
+    preview_length: Option<i64>,
+    plugin: Option<String>,
+    link_pattern: Option<String>,
+    #[serde(default)]
+    archive_links: bool,
+    max_content_age_hours: Option<i64>,
+    crawl_pattern: Option<String>,
+    crawl_depth: Option<i64>,
+    crawl_max_pages: Option<i64>,
+    max_quiet_hours: Option<i64>,
+    // Agencies, document types and search terms a Federal Register site's API query adds
+    federal_register: Option<federal_register::Query>,
+    // What a search site watches the news for, instead of a URL
+    query: Option<String>,
+}
+
+// Partial update for PATCH /api/sites/{id}. Empty string clears: selector, probe_mode,
+// extraction_mode, plugin, link_pattern, crawl_pattern and the assertions. 0 falls back to the
+// default: retention_count, retention_days, notify_burst_limit, notify_window_secs,
+// timeout_secs, max_redirects, max_body_bytes, preview_length, max_content_age_hours,
+// crawl_depth and crawl_max_pages. 0 switches off: max_quiet_hours stops flagging the site,
+// assert_min_length removes the length check. Lists replace: ignore_patterns, proxies and tags.
+// legal_hold places or releases a hold on the whole site; archive_links turns archiving on or
+// off. null active_window uses the global one.
+#[derive(Deserialize)]
+struct SiteUpdate {
+    interval_secs: Option<i64>,
+    style: Option<String>,
+    selector: Option<String>,
+    priority: Option<i64>,
+    retention_count: Option<i64>,
+    retention_days: Option<i64>,
+    probe_mode: Option<String>,
+    probe_tail_kb: Option<i64>,
+    assert_selector: Option<String>,
+    assert_pattern: Option<String>,
+    assert_min_length: Option<i64>,
+    ignore_patterns: Option<Vec<String>>,
+    legal_hold: Option<bool>,
+    notify_burst_limit: Option<i64>,
+    notify_window_secs: Option<i64>,
+    proxies: Option<Vec<String>>,
+    tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "active_hours::deserialize_patch")]
+    active_window: Option<Option<active_hours::ActiveWindow>>,
+    timeout_secs: Option<i64>,
+    max_redirects: Option<i64>,
+    max_body_bytes: Option<i64>,
+    extraction_mode: Option<String>,
+    preview_length: Option<i64>,
+    plugin: Option<String>,
+    link_pattern: Option<String>,
+    archive_links: Option<bool>,
+    max_content_age_hours: Option<i64>,
+    crawl_pattern: Option<String>,
+    crawl_depth: Option<i64>,
+    crawl_max_pages: Option<i64>,
+    max_quiet_hours: Option<i64>,
+}
+
+// Validated assertion settings of a new site or PATCH request
+struct Assertions {
+    selector: Option<String>,
+    pattern: Option<String>,
+}
+
+fn validate_assertions(selector: Option<&str>, pattern: Option<&str>, min_length: Option<i64>) -> Result<Assertions, String> {
+    if min_length.is_some_and(|n| n < 0) {
+        return Err("assert_min_length must not be negative".into());
+    }
+    Ok(Assertions {
+        selector: validate_selector(selector)?,
+        pattern: assertions::validate_pattern(pattern)?,
+    })
+}
+
+// Limits of a site's fetches; 0 means the default
+const MAX_TIMEOUT_SECS: i64 = 300;
+const MAX_SITE_REDIRECTS: i64 = 20;
+// Longest live preview a site can ask for
+const MAX_PREVIEW_LENGTH: i64 = 10_000;
+
+fn validate_preview_length(length: Option<i64>) -> Result<(), String> {
+    match length {
+        Some(n) if !(0..=MAX_PREVIEW_LENGTH).contains(&n) => Err(format!("preview_length must be between 0 and {}", MAX_PREVIEW_LENGTH)),
+        _ => Ok(()),
+    }
+}
+
+fn validate_fetch_limits(timeout_secs: Option<i64>, max_redirects: Option<i64>, max_body_bytes: Option<i64>) -> Result<(), String> {
+    if timeout_secs.is_some_and(|secs| !(0..=MAX_TIMEOUT_SECS).contains(&secs)) {
+        return Err(format!("timeout_secs must be between 0 and {}", MAX_TIMEOUT_SECS));
+    }
+    if max_redirects.is_some_and(|n| !(0..=MAX_SITE_REDIRECTS).contains(&n)) {
+        return Err(format!("max_redirects must be between 0 and {}", MAX_SITE_REDIRECTS));
+    }
+    if max_body_bytes.is_some_and(|n| n < 0) {
+        return Err("max_body_bytes must not be negative".into());
+    }
+    Ok(())
+}
+
+// CSS selector(s) restricting hashing and previews to part of the page.
+// Empty means "no selector"; invalid selectors are rejected up front.
+fn validate_selector(selector: Option<&str>) -> Result<Option<String>, String> {
+    match selector.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(sel) => scraper::parse_selector(sel)
+            .map(|_| Some(sel.to_string()))
+            .map_err(|e| format!("Invalid selector '{}': {}", sel, e)),
+    }
 }
 
-async fn list_sites(data: web::Data<AppState>) -> impl Responder {
-    let sites: Vec<Site> = sqlx::query_as::<_, Site>("SELECT * FROM sites")
-        .fetch_all(&data.pool)
-        .await
-        .unwrap_or_default();
+fn validate_extraction_mode(mode: Option<&str>) -> Result<Option<String>, String> {
+    match mode.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(mode) if extractors::by_name(mode).is_some() => Ok(Some(mode.to_string())),
+        Some(mode) => Err(format!("Invalid extraction_mode '{}': expected one of {}", mode, extractors::names().join(", "))),
+    }
+}
+
+// A plugin must load before a site can use it; empty means none
+async fn validate_plugin(plugins: &Arc<plugins::Plugins>, name: Option<&str>) -> Result<Option<String>, String> {
+    let name = match name.map(str::trim) {
+        None | Some("") => return Ok(None),
+        Some(name) => name.to_string(),
+    };
+    let plugins = plugins.clone();
+    web::block(move || plugins.check(&name).map(|_| name)).await
+        .unwrap_or_else(|e| Err(format!("Loading the plugin failed: {}", e)))
+        .map(Some)
+}
+
+fn validate_probe_mode(mode: Option<&str>) -> Result<Option<String>, String> {
+    match mode.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(mode @ ("head" | "tail")) => Ok(Some(mode.to_string())),
+        Some(mode) => Err(format!("Invalid probe_mode '{}': expected head or tail", mode)),
+    }
+}
+
+#[derive(Deserialize)]
+struct SitesQuery {
+    tag: Option<String>,
+}
+
+// GET /api/sites, optionally only the sites carrying ?tag=
+async fn list_sites(data: web::Data<AppState>, query: web::Query<SitesQuery>) -> impl Responder {
+    let mut sites = data.storage.list_sites().await.unwrap_or_default();
+    if let Some(tag) = &query.tag {
+        let tag = tag.trim().to_lowercase();
+        sites.retain(|site| site.tags.contains(&tag));
+    }
+    let now = Utc::now();
+    for site in &mut sites {
+        site.burst_remaining_secs = site.burst_until.map(|until| (until - now).num_seconds()).filter(|secs| *secs > 0);
+    }
     HttpResponse::Ok().json(sites)
 }
 
+// Watched sites by normalized URL, for the duplicate check of create_site. Sites stored
+// before URLs were normalized are compared in normalized form too.
+async fn watched_urls(data: &AppState) -> Result<HashMap<String, i64>, (StatusCode, String)> {
+    match data.storage.list_sites().await {
+        Ok(sites) => Ok(sites.iter().filter_map(|s| netguard::normalize_site_url(&s.url).ok().map(|url| (url.to_string(), s.id))).collect()),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))),
+    }
+}
+
+// Validates and stores a new site, adding it to `watched`; the error carries the status to
+// answer with
+async fn create_site(data: &AppState, payload: &NewSite, watched: &mut HashMap<String, i64>) -> Result<i64, (StatusCode, String)> {
+    // Whatever the payload leaves out comes from the site's tags, then the global defaults
+    let (tag_interval, tag_style) = match payload.tags.is_empty() {
+        true => (None, None),
+        false => match data.storage.list_tags().await {
+            Ok(defined) => tags::defaults_for(&payload.tags, &defined),
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))),
+        },
+    };
+    let interval = payload.interval_secs.or(tag_interval);
+    let style = payload.style.clone().or(tag_style).unwrap_or_else(|| "random".into());
+    let selector = match validate_selector(payload.selector.as_deref()) {
+        Ok(selector) => selector,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+
+    let probe_mode = match validate_probe_mode(payload.probe_mode.as_deref()) {
+        Ok(mode) => mode,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+    let asserts = match validate_assertions(payload.assert_selector.as_deref(), payload.assert_pattern.as_deref(), payload.assert_min_length) {
+        Ok(asserts) => asserts,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+    let ignore_patterns = match ignore::validate_ignore_patterns(&payload.ignore_patterns) {
+        Ok(patterns) => patterns,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+    let site_proxies = match proxies::validate_site_proxies(&payload.proxies) {
+        Ok(list) => list,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+    if let Err(e) = active_hours::validate(payload.active_window.as_ref()) {
+        return Err((StatusCode::BAD_REQUEST, e));
+    }
+    if let Err(e) = validate_fetch_limits(payload.timeout_secs, payload.max_redirects, payload.max_body_bytes) {
+        return Err((StatusCode::BAD_REQUEST, e));
+    }
+    let extraction_mode = match validate_extraction_mode(payload.extraction_mode.as_deref()) {
+        // Federal Register searches and feeds are read through its API instead
+        Ok(None) if payload.federal_register.is_some() || federal_register::is_listing(&payload.url) => Some(federal_register::EXTRACTION_MODE.to_string()),
+        // So are Reddit users and subreddits, through its JSON listings
+        Ok(None) if reddit::is_listing(&payload.url) => Some(reddit::EXTRACTION_MODE.to_string()),
+        // And Truth Social, Bluesky and X profiles, which render nothing without JavaScript
+        Ok(None) if social::is_profile(&payload.url) => Some(social::EXTRACTION_MODE.to_string()),
+        // Substack's feeds cut posts short; its posts API doesn't
+        Ok(None) if substack::is_publication(&payload.url) => Some(substack::EXTRACTION_MODE.to_string()),
+        // A query instead of a URL makes a news search
+        Ok(None) if payload.query.is_some() => Some(news_search::EXTRACTION_MODE.to_string()),
+        Ok(None) if sitemap::is_sitemap_url(&payload.url) => Some(sitemap::EXTRACTION_MODE.to_string()),
+        Ok(mode) => mode,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+    if let Err(e) = validate_preview_length(payload.preview_length) {
+        return Err((StatusCode::BAD_REQUEST, e));
+    }
+    let plugin = match validate_plugin(&data.config.plugins, payload.plugin.as_deref()).await {
+        Ok(plugin) => plugin,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+    let link_pattern = match links::validate_pattern(payload.link_pattern.as_deref()) {
+        Ok(pattern) => pattern,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+    let crawl_pattern = match crawl::validate(payload.crawl_pattern.as_deref(), payload.crawl_depth, payload.crawl_max_pages) {
+        Ok(pattern) => pattern,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+    // EDGAR sites may be given as a CIK or ticker instead of a URL, Reddit sites as "u/name"
+    // or "r/name"; Federal Register, Reddit, social and Substack sites poll the API for their
+    // listing. Lookups on the way go out through the guarded client of the proxy pool.
+    let lookup = || data.config.proxies.lookup(Utc::now()).map(|route| route.client).map_err(|e| (StatusCode::BAD_REQUEST, e));
+    let source = match extraction_mode.as_deref() {
+        Some(edgar::EXTRACTION_MODE) => match data.config.edgar.resolve(&payload.url, &lookup()?).await {
+            Ok(url) => url,
+            Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+        },
+        Some(federal_register::EXTRACTION_MODE) => match federal_register::resolve(&payload.url, payload.federal_register.as_ref()) {
+            Ok(url) => url,
+            Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+        },
+        Some(reddit::EXTRACTION_MODE) => match reddit::resolve(&payload.url) {
+            Ok(url) => url,
+            Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+        },
+        Some(social::EXTRACTION_MODE) => match data.config.social.resolve(&payload.url, &data.config.network_guard, &lookup()?).await {
+            Ok(url) => url,
+            Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+        },
+        Some(substack::EXTRACTION_MODE) => match substack::resolve(&payload.url) {
+            Ok(url) => url,
+            Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+        },
+        Some(news_search::EXTRACTION_MODE) => match data.config.news_search.resolve(payload.query.as_deref(), &payload.url) {
+            Ok(url) => url,
+            Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+        },
+        // A site's home page finds its sitemap through robots.txt
+        Some(sitemap::EXTRACTION_MODE) => match sitemap::resolve(&payload.url, &data.config.network_guard, &lookup()?).await {
+            Ok(url) => url,
+            Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+        },
+        _ => payload.url.clone(),
+    };
+    let interval = match extraction_mode.as_deref() {
+        Some(sitemap::EXTRACTION_MODE) => interval.unwrap_or(data.config.sitemap_interval_secs),
+        _ => interval.unwrap_or(data.config.default_interval_secs),
+    };
+    let url = match data.config.network_guard.validate_site_url(&source).await {
+        Ok(url) => url,
+        Err(e) => return Err((StatusCode::BAD_REQUEST, e)),
+    };
+    if let Some(existing) = watched.get(&url) {
+        return Err((StatusCode::CONFLICT, format!("Site {} is already watched, id={}", url, existing)));
+    }
+
+    let site = Site {
+        selector,
+        priority: payload.priority.unwrap_or(0),
+        retention_count: payload.retention_count.filter(|n| *n > 0),
+        retention_days: payload.retention_days.filter(|n| *n > 0),
+        probe_mode,
+        probe_tail_kb: payload.probe_tail_kb.filter(|kb| *kb > 0),
+        assert_selector: asserts.selector,
+        assert_pattern: asserts.pattern,
+        assert_min_length: payload.assert_min_length.filter(|n| *n > 0),
+        ignore_patterns,
+        legal_hold: payload.legal_hold,
+        notify_burst_limit: payload.notify_burst_limit.filter(|n| *n > 0),
+        notify_window_secs: payload.notify_window_secs.filter(|n| *n > 0),
+        proxies: site_proxies,
+        active_window: payload.active_window.clone(),
+        timeout_secs: payload.timeout_secs.filter(|n| *n > 0),
+        max_redirects: payload.max_redirects.filter(|n| *n > 0),
+        max_body_bytes: payload.max_body_bytes.filter(|n| *n > 0),
+        extraction_mode,
+        preview_length: payload.preview_length.filter(|n| *n > 0),
+        plugin,
+        link_pattern,
+        archive_links: payload.archive_links,
+        max_content_age_hours: payload.max_content_age_hours.filter(|n| *n > 0),
+        crawl_pattern,
+        crawl_depth: payload.crawl_depth.filter(|n| *n > 0),
+        crawl_max_pages: payload.crawl_max_pages.filter(|n| *n > 0),
+        max_quiet_hours: payload.max_quiet_hours.filter(|n| *n > 0),
+        tags: payload.tags.clone(),
+        ..Site::new(&url, interval, &style)
+    };
+
+    let id = data.storage.insert_site(&site).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let _ = data.scheduler.send(scraper::SchedulerEvent::SiteAdded(id));
+    watched.insert(url, id);
+    Ok(id)
+}
+
 async fn add_site(data: web::Data<AppState>, payload: web::Json<NewSite>) -> impl Responder {
-    let interval = payload.interval_secs.unwrap_or(data.config.default_interval_secs);
-    let style = payload.style.clone().unwrap_or_else(|| "random".into());
+    let result = match watched_urls(&data).await {
+        Ok(mut watched) => create_site(&data, &payload, &mut watched).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(id) => HttpResponse::Ok().body(format!("Site added, id={:?}", id)),
+        Err((status, message)) => HttpResponse::build(status).body(message),
+    }
+}
+
+// Sites accepted by one POST /api/sites/bulk
+const MAX_BULK_SITES: usize = 1000;
+
+#[derive(Serialize)]
+struct BulkAddResult {
+    index: usize,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// POST /api/sites/bulk: an array of POST /api/sites payloads, added one by one. A failed
+// item doesn't stop the rest; each gets its site id or the status and error a single add
+// would have answered with.
+async fn add_sites(data: web::Data<AppState>, payload: web::Json<Vec<NewSite>>) -> impl Responder {
+    if payload.len() > MAX_BULK_SITES {
+        return HttpResponse::PayloadTooLarge().body(format!("At most {} sites per request", MAX_BULK_SITES));
+    }
+    // Loaded once, so a large import doesn't list every site per item
+    let mut watched = match watched_urls(&data).await {
+        Ok(watched) => watched,
+        Err((status, message)) => return HttpResponse::build(status).body(message),
+    };
+    let mut results = Vec::new();
+    for (index, site) in payload.iter().enumerate() {
+        let result = create_site(&data, site, &mut watched).await;
+        results.push(BulkAddResult {
+            index,
+            url: site.url.clone(),
+            id: result.as_ref().ok().copied(),
+            status: result.as_ref().err().map(|(status, _)| status.as_u16()),
+            error: result.err().map(|(_, message)| message),
+        });
+    }
+    let added = results.iter().filter(|r| r.id.is_some()).count();
+    info!("Bulk add: {} of {} site(s) added", added, results.len());
+    HttpResponse::Ok().json(serde_json::json!({
+        "added": added,
+        "failed": results.len() - added,
+        "results": results,
+    }))
+}
 
-    let rec = sqlx::query!(
-        "INSERT INTO sites (url, interval_secs, style) VALUES (?1, ?2, ?3)",
-        payload.url,
-        interval,
-        style
-    )
-    .execute(&data.pool)
-    .await;
+async fn update_site(data: web::Data<AppState>, path: web::Path<i64>, payload: web::Json<SiteUpdate>) -> impl Responder {
+    let id = path.into_inner();
+    let mut payload = payload.into_inner();
+    let selector = match validate_selector(payload.selector.as_deref()) {
+        Ok(selector) => selector,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let probe_mode = match validate_probe_mode(payload.probe_mode.as_deref()) {
+        Ok(mode) => mode,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let asserts = match validate_assertions(payload.assert_selector.as_deref(), payload.assert_pattern.as_deref(), payload.assert_min_length) {
+        Ok(asserts) => asserts,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    if let Some(patterns) = &payload.ignore_patterns {
+        match ignore::validate_ignore_patterns(patterns) {
+            Ok(patterns) => payload.ignore_patterns = Some(patterns),
+            Err(e) => return HttpResponse::BadRequest().body(e),
+        }
+    }
+    if let Some(list) = &payload.proxies {
+        match proxies::validate_site_proxies(list) {
+            Ok(list) => payload.proxies = Some(list),
+            Err(e) => return HttpResponse::BadRequest().body(e),
+        }
+    }
+    if let Err(e) = active_hours::validate(payload.active_window.as_ref().and_then(Option::as_ref)) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    if let Err(e) = validate_fetch_limits(payload.timeout_secs, payload.max_redirects, payload.max_body_bytes) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    if let Some(mode) = &payload.extraction_mode {
+        match validate_extraction_mode(Some(mode)) {
+            Ok(mode) => payload.extraction_mode = Some(mode.unwrap_or_default()),
+            Err(e) => return HttpResponse::BadRequest().body(e),
+        }
+    }
+    if let Err(e) = validate_preview_length(payload.preview_length) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    if let Some(name) = &payload.plugin {
+        match validate_plugin(&data.config.plugins, Some(name)).await {
+            Ok(plugin) => payload.plugin = Some(plugin.unwrap_or_default()),
+            Err(e) => return HttpResponse::BadRequest().body(e),
+        }
+    }
+    if let Some(pattern) = &payload.link_pattern {
+        match links::validate_pattern(Some(pattern)) {
+            Ok(pattern) => payload.link_pattern = Some(pattern.unwrap_or_default()),
+            Err(e) => return HttpResponse::BadRequest().body(e),
+        }
+    }
+    match crawl::validate(payload.crawl_pattern.as_deref(), payload.crawl_depth, payload.crawl_max_pages) {
+        Ok(pattern) if payload.crawl_pattern.is_some() => payload.crawl_pattern = Some(pattern.unwrap_or_default()),
+        Ok(_) => {},
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    }
 
-    match rec {
-        Ok(result) => HttpResponse::Ok().body(format!("Site added, id={:?}", result.last_insert_rowid())),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    match data.storage.update_site(id, &payload, selector, probe_mode, asserts).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("Site with ID {} not found", id)),
+        Ok(true) => {
+            let _ = data.scheduler.send(scraper::SchedulerEvent::SiteUpdated(id));
+            HttpResponse::Ok().finish()
+        },
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// Fetch a site now, or join a fetch that is already running or due within the
+// coalescing window. Waits for the result so the caller sees what it produced.
+async fn check_site_now(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let site_id = path.into_inner();
+    let (reply, outcome) = tokio::sync::oneshot::channel();
+    if data.scheduler.send(scraper::SchedulerEvent::CheckNow { site_id, reply }).is_err() {
+        return HttpResponse::ServiceUnavailable().body("Scraper is not running");
+    }
+
+    match tokio::time::timeout(std::time::Duration::from_secs(60), outcome).await {
+        Ok(Ok(outcome)) => HttpResponse::Ok().json(outcome),
+        Ok(Err(_)) => HttpResponse::NotFound().body(format!("Site with ID {} not found", site_id)),
+        Err(_) => HttpResponse::Accepted().body("Check queued; results will appear in the update stream"),
+    }
+}
+
+// Longest burst one request can start, so a forgotten one doesn't hammer a site for days
+const MAX_BURST_SECS: i64 = 86400;
+
+#[derive(Deserialize)]
+struct BurstRequest {
+    // How long the burst lasts (default: an hour)
+    duration_secs: Option<i64>,
+    // Interval during the burst (default: 10 seconds)
+    interval_secs: Option<i64>,
+}
+
+// POST /api/sites/{id}/burst: check the site every interval_secs for duration_secs, starting
+// now. The regular schedule takes over again when the burst runs out; a new burst replaces
+// the running one.
+async fn start_burst(data: web::Data<AppState>, path: web::Path<i64>, payload: web::Json<BurstRequest>) -> impl Responder {
+    let id = path.into_inner();
+    let duration = payload.duration_secs.unwrap_or(3600);
+    let interval = payload.interval_secs.unwrap_or(10);
+    if !(1..=MAX_BURST_SECS).contains(&duration) {
+        return HttpResponse::BadRequest().body(format!("duration_secs must be between 1 and {}", MAX_BURST_SECS));
+    }
+    if interval < 1 || interval > duration {
+        return HttpResponse::BadRequest().body("interval_secs must be at least 1 and at most duration_secs");
+    }
+    match data.storage.get_site(id).await {
+        Ok(Some(_)) => {},
+        Ok(None) => return HttpResponse::NotFound().body(format!("Site with ID {} not found", id)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+
+    let until = Utc::now() + chrono::Duration::seconds(duration);
+    if let Err(e) = data.storage.set_burst(id, Some(interval), Some(until)).await {
+        return HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+    }
+    info!(site_id = id, interval, "Burst until {}", until.to_rfc3339());
+    let _ = data.scheduler.send(scraper::SchedulerEvent::SiteUpdated(id));
+    HttpResponse::Ok().json(serde_json::json!({
+        "site_id": id,
+        "burst_interval_secs": interval,
+        "burst_until": until,
+    }))
+}
+
+// DELETE /api/sites/{id}/burst: end a burst early
+async fn stop_burst(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    match data.storage.get_site(id).await {
+        Ok(Some(_)) => {},
+        Ok(None) => return HttpResponse::NotFound().body(format!("Site with ID {} not found", id)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+    match data.storage.set_burst(id, None, None).await {
+        Ok(()) => {
+            let _ = data.scheduler.send(scraper::SchedulerEvent::SiteUpdated(id));
+            HttpResponse::Ok().finish()
+        },
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[derive(Deserialize)]
+struct FetchLogQuery {
+    limit: Option<i64>,
+}
+
+// Recent fetch attempts of a site, newest first
+async fn site_fetch_log(data: web::Data<AppState>, path: web::Path<i64>, query: web::Query<FetchLogQuery>) -> impl Responder {
+    let id = path.into_inner();
+    match data.storage.get_site(id).await {
+        Ok(Some(_)) => {},
+        Ok(None) => return HttpResponse::NotFound().body(format!("Site with ID {} not found", id)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, data.config.fetch_log_size.max(1));
+    match data.storage.fetch_log(id, limit).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[derive(Deserialize)]
+struct FetchStatsQuery {
+    windows: Option<String>,
+}
+
+// GET /api/sites/{id}/stats?windows=24h,7d: success rate, fetch latency and changes of a
+// site over each window, from its fetch log
+async fn site_fetch_stats(data: web::Data<AppState>, path: web::Path<i64>, query: web::Query<FetchStatsQuery>) -> impl Responder {
+    let id = path.into_inner();
+    let windows = match fetch_stats::parse_windows(query.windows.as_deref().unwrap_or(fetch_stats::DEFAULT_WINDOWS)) {
+        Ok(windows) => windows,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    match data.storage.get_site(id).await {
+        Ok(Some(_)) => {},
+        Ok(None) => return HttpResponse::NotFound().body(format!("Site with ID {} not found", id)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+
+    match data.storage.fetch_log(id, data.config.fetch_log_size.max(1)).await {
+        Ok(entries) => HttpResponse::Ok().json(fetch_stats::summarize(id, &entries, &windows, Utc::now())),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// GET /api/stats/latency: published → detected delay per site, slowest first
+async fn latency_stats(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.latency_stats().await {
+        Ok(stats) => HttpResponse::Ok().json(stats),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
     }
 }
 
@@ -82,57 +1082,229 @@ async fn delete_site(data: web::Data<AppState>, path: web::Path<i64>) -> impl Re
     let id = path.into_inner();
     
     // Log the deletion attempt for debugging
-    println!("Attempting to delete site with ID: {}", id);
-    
-    // Make sure foreign keys are enabled for this connection
-    let _ = sqlx::query("PRAGMA foreign_keys = ON;").execute(&data.pool).await;
-    
-    // First, manually delete any updates for this site
-    println!("Deleting any updates for site {}", id);
-    let _ = sqlx::query!("DELETE FROM updates WHERE site_id = ?1", id)
-        .execute(&data.pool)
-        .await;
-    
-    // Check if the site exists before trying to delete
-    let site_exists = sqlx::query!("SELECT id FROM sites WHERE id = ?1", id)
-        .fetch_optional(&data.pool)
-        .await;
+    info!(site_id = id, "Deleting site");
+
+    match data.storage.legal_holds().await {
+        Ok(holds) if holds.covers_site(id) => {
+            warn!(site_id = id, "Refusing to delete site under legal hold");
+            return HttpResponse::Conflict().body(format!(
+                "Site {} has data under legal hold; release its holds before deleting it", id
+            ));
+        },
+        Ok(_) => {},
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
     
-    match site_exists {
-        Ok(Some(_)) => {
-            // Now delete the site itself
-            let result = sqlx::query!("DELETE FROM sites WHERE id = ?1", id)
-                .execute(&data.pool)
-                .await;
-            
-            match result {
-                Ok(result) => {
-                    println!("Successfully deleted site {}, rows affected: {}", id, result.rows_affected());
-                    HttpResponse::Ok().finish()
-                },
-                Err(e) => {
-                    println!("Error deleting site {}: {}", id, e);
-                    HttpResponse::InternalServerError().body(format!("Database error: {}", e))
-                }
-            }
+    match data.storage.delete_site(id).await {
+        Ok(true) => {
+            info!(site_id = id, "Site deleted");
+            let _ = data.scheduler.send(scraper::SchedulerEvent::SiteRemoved(id));
+            HttpResponse::Ok().finish()
         },
-        Ok(None) => {
-            println!("Site {} not found for deletion", id);
+        Ok(false) => {
+            warn!(site_id = id, "Site not found for deletion");
             HttpResponse::NotFound().body(format!("Site with ID {} not found", id))
         },
         Err(e) => {
-            println!("Error checking if site {} exists: {}", id, e);
+            error!(site_id = id, "Error deleting site: {}", e);
+            HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        },
+    }
+}
+
+#[derive(Deserialize)]
+struct BulkDelete {
+    ids: Vec<i64>,
+}
+
+// DELETE /api/sites: removes the sites listed in {"ids": [...]}, or those carrying ?tag=,
+// with everything stored for them in one transaction. Nothing is deleted if any of them
+// has data under legal hold.
+async fn delete_sites(data: web::Data<AppState>, query: web::Query<SitesQuery>, payload: Option<web::Json<BulkDelete>>) -> impl Responder {
+    let ids = match (&query.tag, payload) {
+        (Some(tag), None) => match data.storage.site_ids_with_tag(&tag.trim().to_lowercase()).await {
+            Ok(ids) => ids,
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+        },
+        (None, Some(payload)) => payload.into_inner().ids,
+        _ => return HttpResponse::BadRequest().body("Pass either a body of {\"ids\": [...]} or ?tag=, not both"),
+    };
+
+    match data.storage.legal_holds().await {
+        Ok(holds) => {
+            let held: Vec<i64> = ids.iter().copied().filter(|&id| holds.covers_site(id)).collect();
+            if !held.is_empty() {
+                warn!(?held, "Refusing bulk delete of sites under legal hold");
+                return HttpResponse::Conflict().json(serde_json::json!({
+                    "error": "Some sites have data under legal hold; release their holds or leave them out",
+                    "held": held,
+                }));
+            }
+        },
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+
+    match data.storage.delete_sites(&ids).await {
+        Ok(deleted) => {
+            for &id in &deleted {
+                let _ = data.scheduler.send(scraper::SchedulerEvent::SiteRemoved(id));
+            }
+            info!("Bulk delete removed {} site(s)", deleted.len());
+            let not_found: Vec<i64> = ids.into_iter().filter(|id| !deleted.contains(id)).collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "deleted": deleted,
+                "not_found": not_found,
+            }))
+        },
+        Err(e) => {
+            error!("Error deleting sites: {}", e);
             HttpResponse::InternalServerError().body(format!("Database error: {}", e))
         },
     }
 }
 
-async fn sse_updates(data: web::Data<AppState>, _req: actix_web::HttpRequest) -> impl Responder {
+// ?site_ids=1,5,9 and/or ?tag=fda narrow the stream to those sites; ?batch=true groups
+// updates arriving close together into one event (the poll endpoint ignores it)
+#[derive(Deserialize)]
+struct StreamQuery {
+    site_ids: Option<String>,
+    tag: Option<String>,
+    batch: Option<bool>,
+}
+
+// Tag membership is re-read periodically so retagging applies to open streams
+const TAG_FILTER_REFRESH: std::time::Duration = std::time::Duration::from_secs(30);
+
+struct StreamFilter {
+    site_ids: Option<HashSet<i64>>,
+    tag: Option<String>,
+    tag_sites: HashSet<i64>,
+    refreshed: Option<std::time::Instant>,
+}
+
+impl StreamFilter {
+    fn from_query(query: &StreamQuery) -> Result<Self, String> {
+        let site_ids = match query.site_ids.as_deref() {
+            Some(ids) => Some(
+                ids.split(',')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(|id| id.parse::<i64>().map_err(|_| format!("Invalid site id '{}'", id)))
+                    .collect::<Result<HashSet<i64>, String>>()?,
+            ),
+            None => None,
+        };
+        let tag = query.tag.as_deref().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty());
+        Ok(StreamFilter { site_ids, tag, tag_sites: HashSet::new(), refreshed: None })
+    }
+
+    async fn matches(&mut self, storage: &dyn storage::Storage, event: &StreamEvent) -> bool {
+        let Some(site_id) = event.site_id() else {
+            return true;
+        };
+        if self.site_ids.as_ref().is_some_and(|ids| !ids.contains(&site_id)) {
+            return false;
+        }
+        let Some(tag) = &self.tag else {
+            return true;
+        };
+        if self.refreshed.is_none_or(|t| t.elapsed() >= TAG_FILTER_REFRESH) {
+            self.tag_sites = storage.site_ids_with_tag(tag).await.unwrap_or_default().into_iter().collect();
+            self.refreshed = Some(std::time::Instant::now());
+        }
+        self.tag_sites.contains(&site_id)
+    }
+}
+
+// Most changes replayed to a reconnecting client before it switches to live events
+const MAX_REPLAY: i64 = 500;
+
+// With ?batch=true, updates arriving within this window of the first one go out together
+const BATCH_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+const MAX_BATCH: usize = 200;
+
+// A lone update stays a plain message. Several become one `batch` event carrying the
+// last update ID, so Last-Event-ID replay resumes after the whole batch.
+fn batch_to_sse(mut updates: Vec<UpdateMessage>) -> String {
+    if updates.len() == 1 {
+        return StreamEvent::Update(updates.remove(0)).to_sse();
+    }
+    let last_id = updates.last().map(|u| u.update_id).unwrap_or_default();
+    let body = serde_json::json!({ "count": updates.len(), "updates": updates });
+    format!("id: {}\nevent: batch\ndata: {}\n\n", last_id, body)
+}
+
+// Live updates that were already sent as part of the replay
+fn already_replayed(event: &StreamEvent, replayed_up_to: Option<i64>) -> bool {
+    matches!((event, replayed_up_to), (StreamEvent::Update(msg), Some(up_to)) if msg.update_id <= up_to)
+}
+
+async fn sse_updates(data: web::Data<AppState>, query: web::Query<StreamQuery>, req: actix_web::HttpRequest) -> impl Responder {
+    let mut filter = match StreamFilter::from_query(&query) {
+        Ok(filter) => filter,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    // Browsers resend the last seen event ID when EventSource reconnects
+    let last_event_id = req.headers()
+        .get("Last-Event-ID")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok());
+
+    let batch = query.batch.unwrap_or(false);
+    let storage = data.storage.clone();
+    // Subscribe before reading the backlog so nothing falls between the two
     let mut rx = data.tx_updates.subscribe();
     let stream = async_stream::stream! {
-        while let Ok(msg) = rx.recv().await {
-            let json = serde_json::to_string(&msg).unwrap();
-            yield Ok::<_, actix_web::Error>(actix_web::web::Bytes::from(format!("data: {}\n\n", json)));
+        let mut replayed_up_to = None;
+        if let Some(after_id) = last_event_id {
+            let missed = storage.changes_since(after_id, MAX_REPLAY).await.unwrap_or_default();
+            for change in missed {
+                replayed_up_to = Some(change.id);
+                let event = StreamEvent::Update(UpdateMessage::from_change(&change));
+                if filter.matches(storage.as_ref(), &event).await {
+                    yield Ok::<_, actix_web::Error>(actix_web::web::Bytes::from(event.to_sse()));
+                }
+            }
+        }
+
+        while let Ok(event) = rx.recv().await {
+            if already_replayed(&event, replayed_up_to) || !filter.matches(storage.as_ref(), &event).await {
+                continue;
+            }
+            let first = match event {
+                StreamEvent::Update(msg) if batch => msg,
+                event => {
+                    yield Ok::<_, actix_web::Error>(actix_web::web::Bytes::from(event.to_sse()));
+                    continue;
+                },
+            };
+
+            // Collect further updates until the window closes. Status and alert events end
+            // the batch early and follow it, so the order of events is kept.
+            let deadline = tokio::time::Instant::now() + BATCH_WINDOW;
+            let mut updates = vec![first];
+            let mut trailing = None;
+            while updates.len() < MAX_BATCH {
+                let event = match tokio::time::timeout_at(deadline, rx.recv()).await {
+                    Ok(Ok(event)) => event,
+                    Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                    Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break,
+                };
+                if already_replayed(&event, replayed_up_to) || !filter.matches(storage.as_ref(), &event).await {
+                    continue;
+                }
+                match event {
+                    StreamEvent::Update(msg) => updates.push(msg),
+                    event => {
+                        trailing = Some(event);
+                        break;
+                    },
+                }
+            }
+            yield Ok::<_, actix_web::Error>(actix_web::web::Bytes::from(batch_to_sse(updates)));
+            if let Some(event) = trailing {
+                yield Ok::<_, actix_web::Error>(actix_web::web::Bytes::from(event.to_sse()));
+            }
         }
     };
     HttpResponse::Ok()
@@ -140,113 +1312,124 @@ async fn sse_updates(data: web::Data<AppState>, _req: actix_web::HttpRequest) ->
         .streaming(stream)
 }
 
-// List of default sites to add when the database is initialized
-async fn add_default_sites(pool: &SqlitePool) {
-    // Define the default sites - this replaces the hardcoded example sites from the frontend
-    let default_sites = vec![
-        ("https://ag.ny.gov/press-releases", 1100, "random"),
-        ("https://apnews.com/article/feed", 1100, "random"),
-        ("https://apnews.com/index.rss", 1100, "random"),
-        ("https://asia.nikkei.com/rss/feed/nar", 1100, "random"),
-        ("https://citronresearch.com/feed/", 1100, "random"),
-        ("https://defence-blog.com/feed/", 1100, "random"),
-        ("https://endpts.com/feed/", 1100, "random"),
-        ("https://feeds.feedburner.com/nvidiablog", 1100, "random"),
-        ("https://fuzzypandaresearch.com/feed/", 1100, "random"),
-        ("https://grizzlyreports.com/?feed=rss2", 1100, "random"),
-        ("https://hindenburgresearch.com/feed/", 1100, "random"),
-        ("https://home.treasury.gov/news/press-releases", 1800, "random"),
-        ("https://iceberg-research.com/2023/feed/", 1100, "random"),
-        ("https://investor.nvidia.com/rss/SECFiling.aspx?Exchange=CIK&Symbol=0001045810", 1800, "random"),
-        ("https://investor.regeneron.com/rss/news-releases.xml?items=15", 1800, "random"),
-        ("https://investors.arm.com/financials/quarterly-annual-results", 1800, "random"),
-        ("https://investors.block.xyz/financials/quarterly-earnings-reports/default.aspx", 1800, "random"),
-        ("https://investors.pfizer.com/Investors/News/default.aspx", 1800, "random"),
-        ("https://ir.amd.com/news-events/press-releases/rss", 1800, "random"),
-        ("https://ir.cytokinetics.com/press-releases", 1800, "random"),
-        ("https://ir.netflix.net/financials/quarterly-earnings/default.aspx", 1800, "random"),
-        ("https://ir.netflix.net/rss/SECFiling.aspx?Exchange=CIK&Symbol=0001065280", 1800, "random"),
-        ("https://ir.purecycle.com/news-events/press-releases/rss", 1800, "random"),
-        ("https://ir.supermicro.com/financials/sec-filings/default.aspx", 1800, "random"),
-        ("https://ir.supermicro.com/news/default.aspx", 1800, "random"),
-        ("https://ir.tesla.com/#quarterly-disclosure/", 1800, "random"),
-        ("https://ir.tesla.com/press", 1800, "random"),
-        ("https://ir.tmtgcorp.com/financials/sec-filings/", 1800, "random"),
-        ("https://listingcenter.nasdaq.com/IssuersPendingSuspensionDelisting.aspx", 1800, "random"),
-        ("https://newsroom.thecignagroup.com/latest-press-releases?pagetemplate=rss", 1800, "random"),
-        ("https://nvidianews.nvidia.com/cats/press_release.xml", 1100, "random"),
-        ("https://punchbowl.news/feed/", 1100, "random"),
-        ("https://scorpioncapital.com/", 1100, "random"),
-        ("https://search.cnbc.com/rs/search/combinedcms/view.xml?partnerId=wrss01&id=100003114", 1100, "random"),
-        ("https://techcrunch.com/feed/", 1100, "random"),
-        ("https://theaircurrent.com/author/jonostrower/feed/", 1100, "random"),
-        ("https://thebearcave.substack.com/feed", 1100, "random"),
-        ("https://truthsocial.com/@realDonaldTrump", 1100, "random"),
-        ("https://www.accessdata.fda.gov/scripts/cder/daf/index.cfm?event=report.page", 1800, "random"),
-        ("https://www.accessdata.fda.gov/scripts/cder/daf/index.cfm?event=reportsSearch.process", 1800, "random"),
-        ("https://www.accessdata.fda.gov/scripts/drugshortages/dsp_ActiveIngredientDetails.cfm?AI=Semaglutide%20Injection&st=c&tab=tabs-1", 1800, "random"),
-        ("https://www.axios.com/feeds/feed.rss", 1100, "random"),
-        ("https://www.axios.com/pro", 1100, "random"),
-        ("https://www.axios.com/pro/energy-policy/2025/05", 1100, "random"),
-        ("https://www.betaville.co.uk/", 1100, "random"),
-        ("https://www.biopharmadive.com/feeds/news/", 1100, "random"),
-        ("https://www.cadc.uscourts.gov/internet/judgments.nsf/uscadcjudgments.xml", 1800, "random"),
-        ("https://www.ded.uscourts.gov/judges-info/opinions?field_opinion_date_value%5Bvalue%5D%5Byear%5D=2024&field_judge_nid=All", 1800, "random"),
-        ("https://www.digitimes.com/rss/daily.xml", 1100, "random"),
-        ("https://www.dtcc.com/products/cs/exchange_traded_funds_plain_new.php", 1800, "random"),
-        ("https://www.fda.gov/about-fda/contact-fda/stay-informed/rss-feeds/medwatch/rss.xml", 1800, "random"),
-        ("https://www.fda.gov/about-fda/contact-fda/stay-informed/rss-feeds/oci-press-releases/rss.xml", 1800, "random"),
-        ("https://www.fda.gov/about-fda/contact-fda/stay-informed/rss-feeds/press-releases/rss.xml", 1800, "random"),
-        ("https://www.federalregister.gov/api/v1/documents.rss", 1800, "random"),
-        ("https://www.ft.com/myft/following/b013133b-aba9-4ba5-8f97-e3b6c46f6665.rss", 1100, "random"),
-        ("https://www.ftc.gov/feeds/press-release.xml", 1800, "random"),
-        ("https://www.gothamcityresearch.com/main/", 1100, "random"),
-        ("https://www.jcapitalresearch.com/company-reports.html", 1100, "random"),
-        ("https://www.mofcom.gov.cn/xwfb/xwfyrth/index.html", 1800, "random"),
-        ("https://www.morpheus-research.com/rss/", 1100, "random"),
-        ("https://www.politico.com/rss/politicopicks.xml", 1100, "random"),
-        ("https://www.reddit.com/user/AVOCADO-IN-MY-ANUS/.rss", 1100, "random"),
-        ("https://www.reddit.com/user/DeepFuckingValue/comments.rss", 1100, "random"),
-        ("https://www.reddit.com/user/DeepFuckingValue/submitted.rss", 1100, "random"),
-        ("https://www.rockstargames.com/newswire", 1800, "random"),
-        ("https://www.sec.gov/Archives/edgar/usgaap.rss.xml", 1800, "random"),
-        ("https://www.sec.gov/news/pressreleases.rss", 1800, "random"),
-        ("https://www.sec.gov/rules/sro/national-securities-exchanges?aId=&sro_organization=All&title=&release_number=&file_number=&year=All&page=0", 1800, "random"),
-        ("https://www.semafor.com/newsletters/business/latest", 1100, "random"),
-        ("https://www.semafor.com/rss.xml", 1100, "random"),
-        ("https://www.sprucepointcap.com/research/feed/", 1100, "random"),
-        ("https://www.statnews.com/feed", 1100, "random"),
-        ("https://www.statnews.com/staff/adam-feuerstein/feed", 1100, "random"),
-        ("https://www.take2games.com/ir/press-releases", 1800, "random"),
-        ("https://www.theinformation.com/feed", 1100, "random"),
-        ("https://www.wolfpackresearch.com/items", 1100, "random"),
-        ("https://www.youtube.com/feeds/videos.xml?channel_id=UC6VcWc1rAoWdBCM0JxrRQ3A", 1100, "random"),
-        ("https://nasdaqtrader.com/Trader.aspx?id=archiveheadlines&cat_id=105", 1800, "random"),
-        ("https://origin.kerrisdalecap.com/feed/", 1100, "random"),
-        ("https://whitediamondresearch.com/", 1100, "random"),
-        ("https://www.betaville.co.uk/exclusives", 1100, "random"),
-        ("https://www.cadc.uscourts.gov/internet/home.nsf/uscadcnews.xml", 1800, "random"),
-        ("https://www.nyc.gov/office-of-the-mayor/news.page", 1800, "random"),
-        ("https://www.youtube.com/feeds/videos.xml?channel_id=UC0patpmwYbhcEUap0bTX3JQ", 1100, "random")
-    ];
-    
-    // Insert each default site
-    for (url, interval_secs, style) in default_sites {
-        // We ignore errors - sites might already exist in DB
-        let _ = sqlx::query!(
-            "INSERT OR IGNORE INTO sites (url, interval_secs, style) VALUES (?1, ?2, ?3)",
-            url,
-            interval_secs,
-            style
-        )
-        .execute(pool)
-        .await;
+#[derive(Deserialize)]
+struct RecentUpdatesQuery {
+    since: Option<DateTime<Utc>>,
+    limit: Option<i64>,
+}
+
+// GET /api/updates: the latest stored changes, newest first, for filling the feed on page
+// load or after a reconnect. Only changes after `since` when given.
+async fn recent_updates(data: web::Data<AppState>, query: web::Query<RecentUpdatesQuery>) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, MAX_REPLAY);
+    match data.storage.recent_changes(None, limit).await {
+        // Newest first, so the limit keeps the latest and `since` only trims the tail
+        Ok(changes) => HttpResponse::Ok().json(changes.iter()
+            .filter(|change| query.since.is_none_or(|since| change.timestamp > since))
+            .map(UpdateMessage::from_change)
+            .collect::<Vec<_>>()),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
     }
 }
 
-// Emergency reset endpoint to help with site deletion issues
-async fn get_full_content(data: web::Data<AppState>, path: web::Path<(i64, String)>) -> impl Responder {
+#[derive(Deserialize)]
+struct PollQuery {
+    since_id: Option<i64>,
+    timeout: Option<u64>,
+}
+
+// Longest a poll request is held open, in seconds
+const MAX_POLL_TIMEOUT: u64 = 60;
+
+// GET /api/updates/poll: long-polling fallback for clients behind proxies that strip SSE.
+// Returns stored changes after since_id at once, otherwise waits up to `timeout` seconds
+// for the next matching update. Takes the same site_ids/tag filters as the stream.
+async fn poll_updates(data: web::Data<AppState>, filter: web::Query<StreamQuery>, query: web::Query<PollQuery>) -> impl Responder {
+    let mut filter = match StreamFilter::from_query(&filter) {
+        Ok(filter) => filter,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let timeout = std::time::Duration::from_secs(query.timeout.unwrap_or(30).clamp(1, MAX_POLL_TIMEOUT));
+    let storage = data.storage.as_ref();
+
+    // Subscribe before reading stored changes so nothing falls between the two
+    let mut rx = data.tx_updates.subscribe();
+    // Without since_id the client only wants what happens from now on
+    let since_id = match query.since_id {
+        Some(id) => id,
+        None => match storage.recent_changes(None, 1).await {
+            Ok(changes) => changes.first().map(|c| c.id).unwrap_or(0),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+        },
+    };
+
+    let missed = match storage.changes_since(since_id, MAX_REPLAY).await {
+        Ok(changes) => changes,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let mut updates = Vec::new();
+    let mut next_since_id = since_id;
+    for change in &missed {
+        next_since_id = change.id;
+        let event = StreamEvent::Update(UpdateMessage::from_change(change));
+        if filter.matches(storage, &event).await {
+            if let StreamEvent::Update(msg) = event {
+                updates.push(msg);
+            }
+        }
+    }
+
+    // Changes the filter skipped still advance the cursor; wait only if nothing matched
+    if updates.is_empty() {
+        let wait = async {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        let StreamEvent::Update(msg) = &event else { continue };
+                        if msg.update_id > next_since_id && filter.matches(storage, &event).await {
+                            return Some(event);
+                        }
+                    },
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        };
+        if let Ok(Some(StreamEvent::Update(msg))) = tokio::time::timeout(timeout, wait).await {
+            next_since_id = msg.update_id;
+            updates.push(msg);
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "updates": updates,
+        "next_since_id": next_since_id,
+    }))
+}
+
+#[derive(Deserialize)]
+struct ContentQuery {
+    // "json", "text", "markdown" or "html" instead of the Accept header
+    format: Option<String>,
+    // false serves the HTML exactly as it was fetched, for debugging
+    sanitized: Option<bool>,
+}
+
+// Stored page of a site at a timestamp: JSON with its HTML and text blocks, or the
+// extracted text, Markdown or HTML of the site's selected region, depending on ?format=
+// or else Accept. HTML is sanitized unless ?sanitized=false asks for the raw page.
+async fn get_full_content(data: web::Data<AppState>, req: actix_web::HttpRequest, path: web::Path<(i64, String)>, query: web::Query<ContentQuery>) -> impl Responder {
     let (site_id, timestamp) = path.into_inner();
+    let format = match query.format.as_deref() {
+        Some(format) => match formats::from_query(format) {
+            Some(format) => format,
+            None => return HttpResponse::BadRequest().body(format!("Invalid format '{}': expected json, text, markdown or html", format)),
+        },
+        None => match formats::negotiate(&req) {
+            Some(format) => format,
+            None => return formats::not_acceptable(),
+        },
+    };
     
     // Parse the timestamp
     let timestamp = DateTime::parse_from_rfc3339(&timestamp)
@@ -254,190 +1437,417 @@ async fn get_full_content(data: web::Data<AppState>, path: web::Path<(i64, Strin
         .unwrap_or_else(|_| Utc::now());
     
     // Fetch the content from the database
-    let content = sqlx::query!(
-        "SELECT content FROM updates WHERE site_id = ?1 AND timestamp = ?2 LIMIT 1",
-        site_id,
-        timestamp
-    )
-    .fetch_optional(&data.pool)
-    .await;
-    
-    match content {
-        Ok(Some(record)) => {
-            HttpResponse::Ok().json(serde_json::json!({
-                "content": record.content
-            }))
-        },
-        Ok(None) => {
-            HttpResponse::NotFound().body("Content not found")
-        },
-        Err(e) => {
-            HttpResponse::InternalServerError().body(format!("Database error: {}", e))
-        }
+    let content = match data.storage.content_at(site_id, timestamp).await {
+        Ok(Some(content)) => content,
+        Ok(None) => return HttpResponse::NotFound().body("Content not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let (selector, site_url) = match data.storage.get_site(site_id).await {
+        Ok(site) => site.map(|s| (s.selector, s.url)).unwrap_or_default(),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let selector = selector.as_deref();
+    let sanitized = query.sanitized.unwrap_or(true);
+
+    match format {
+        formats::Format::Text => formats::text(format, alerts::page_text(&content, selector)),
+        formats::Format::Markdown => formats::text(format, markdown::html_to_markdown(&content, selector)),
+        formats::Format::Html if !sanitized => formats::html(content),
+        formats::Format::Html => formats::html(sanitize::sanitized_html(&content, selector, &site_url)),
+        formats::Format::Json => formats::json(&serde_json::json!({
+            "site_id": site_id,
+            "timestamp": timestamp,
+            "selector": selector,
+            "blocks": diff::text_blocks(&content, selector),
+            "sanitized": sanitized,
+            "content": match sanitized {
+                true => sanitize::sanitized_html(&content, None, &site_url),
+                false => content,
+            },
+        })),
     }
 }
 
+// Emergency reset endpoint to help with site deletion issues
 async fn reset_db(data: web::Data<AppState>) -> impl Responder {
-    println!("Emergency database reset requested");
-    
-    // Make sure foreign keys are enabled
-    let _ = sqlx::query("PRAGMA foreign_keys = ON;").execute(&data.pool).await;
-    
-    // Complete reset by dropping and recreating tables
-    println!("Dropping all tables...");
-    let _ = sqlx::query("DROP TABLE IF EXISTS updates;").execute(&data.pool).await;
-    let _ = sqlx::query("DROP TABLE IF EXISTS sites;").execute(&data.pool).await;
-    
-    // Recreate the schema
-    println!("Recreating tables...");
-    let sites_table = sqlx::query(
-        "CREATE TABLE sites(
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            url TEXT NOT NULL UNIQUE,
-            interval_secs INTEGER NOT NULL,
-            style TEXT NOT NULL,
-            last_checked TEXT,
-            last_updated TEXT,
-            status TEXT
-        );"
-    ).execute(&data.pool).await;
-    
-    let updates_table = sqlx::query(
-        "CREATE TABLE updates(
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            site_id INTEGER,
-            timestamp TEXT,
-            diff_hash TEXT,
-            content TEXT,
-            FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
-        );"
-    ).execute(&data.pool).await;
+    warn!("Emergency database reset requested");
+
+    // Held data must be exported and released first
+    match data.storage.legal_holds().await {
+        Ok(holds) if !holds.is_empty() => {
+            warn!("Refusing database reset: {}", holds.summary());
+            return HttpResponse::Conflict().body(format!(
+                "Database reset refused: {}. Export them via GET /api/holds and release the holds first.", holds.summary()
+            ));
+        },
+        Ok(_) => {},
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
     
-    match (sites_table, updates_table) {
-        (Ok(_), Ok(_)) => {
-            // Ensure foreign keys are enabled
-            let _ = sqlx::query("PRAGMA foreign_keys = ON;").execute(&data.pool).await;
-            
+    // Complete reset: every site and its history go, global webhooks and alert rules stay
+    match data.storage.reset().await {
+        Ok(()) => {
             // Re-add default sites
-            add_default_sites(&data.pool).await;
-            println!("Database has been reset successfully and default sites added");
-            HttpResponse::Ok().body("Database has been completely reset. All tables were recreated and default sites were added.")
+            if data.config.demo {
+                demo::seed(data.storage.as_ref()).await;
+            } else if let Err(e) = seeds::seed(data.storage.as_ref(), &data.config).await {
+                error!("Seeding after reset failed: {}", e);
+            }
+            let _ = data.scheduler.send(scraper::SchedulerEvent::ReloadAll);
+            info!("Database has been reset successfully and default sites added");
+            HttpResponse::Ok().body("Database has been completely reset. All sites and their history were removed and default sites were added.")
         },
-        (Err(e), _) | (_, Err(e)) => {
-            println!("Error resetting database: {}", e);
+        Err(e) => {
+            error!("Error resetting database: {}", e);
             HttpResponse::InternalServerError().body(format!("Error resetting database: {}", e))
         }
     }
 }
 
+// Values missing from config.yaml take their defaults
+fn app_config(cfg: &serde_yaml::Value, network_guard: netguard::NetworkGuard, demo: bool) -> AppConfig {
+    AppConfig {
+        update_cache_size: cfg["update_cache_size"].as_i64().unwrap_or(5),
+        update_retention_days: cfg["update_retention_days"].as_i64().unwrap_or(0),
+        retention_interval_secs: cfg["retention_interval_secs"].as_i64().unwrap_or(300),
+        default_interval_secs: cfg["default_interval_secs"].as_i64().unwrap_or(1),
+        sitemap_interval_secs: cfg["sitemap_interval_secs"].as_i64().unwrap_or(3600),
+        interval_jitter_max_ms: cfg["interval_jitter_max_ms"].as_i64().unwrap_or(1500),
+        max_concurrent_fetches: cfg["max_concurrent_fetches"].as_i64().unwrap_or(10),
+        per_domain_min_delay_ms: cfg["per_domain_min_delay_ms"].as_i64().unwrap_or(1000),
+        coalesce_window_ms: cfg["coalesce_window_ms"].as_i64().unwrap_or(5000),
+        hash_fold_case: cfg["hash_fold_case"].as_bool().unwrap_or(false),
+        feed_lookback_days: cfg["feed_lookback_days"].as_i64().unwrap_or(0),
+        static_export_dir: cfg["static_export_dir"].as_str().map(String::from),
+        static_export_interval_secs: cfg["static_export_interval_secs"].as_i64().unwrap_or(300),
+        static_export_limit: cfg["static_export_limit"].as_i64().unwrap_or(100),
+        static_export_anonymize: cfg["static_export_anonymize"].as_bool().unwrap_or(false),
+        export_alias_salt: cfg["export_alias_salt"].as_str().unwrap_or("").to_string(),
+        db_size_warn_mb: cfg["db_size_warn_mb"].as_i64().unwrap_or(0),
+        disk_free_warn_mb: cfg["disk_free_warn_mb"].as_i64().unwrap_or(0),
+        storage_check_interval_secs: cfg["storage_check_interval_secs"].as_i64().unwrap_or(60),
+        emergency_retention_size: cfg["emergency_retention_size"].as_i64().unwrap_or(0),
+        watchdog_interval_secs: cfg["watchdog_interval_secs"].as_i64().unwrap_or(60),
+        watchdog_max_age_multiple: cfg["watchdog_max_age_multiple"].as_i64().unwrap_or(5),
+        watchdog_min_age_secs: cfg["watchdog_min_age_secs"].as_i64().unwrap_or(600),
+        health: health::Thresholds::new(cfg["health_degraded_after"].as_i64().unwrap_or(3), cfg["health_down_after"].as_i64().unwrap_or(10)),
+        self_test_canary_url: cfg["self_test_canary_url"].as_str().map(String::from),
+        self_test_exit_on_failure: cfg["self_test_exit_on_failure"].as_bool().unwrap_or(false),
+        readiness_max_heartbeat_age_secs: cfg["readiness_max_heartbeat_age_secs"].as_i64().unwrap_or(60),
+        webhook_max_attempts: cfg["webhook_max_attempts"].as_i64().unwrap_or(4),
+        webhook_retry_base_ms: cfg["webhook_retry_base_ms"].as_i64().unwrap_or(1000),
+        notify_burst_limit: cfg["notify_burst_limit"].as_i64().unwrap_or(3),
+        notify_window_secs: cfg["notify_window_secs"].as_i64().unwrap_or(0),
+        max_content_age_hours: cfg["max_content_age_hours"].as_i64().unwrap_or(0),
+        telegram_api_url: cfg["telegram_api_url"].as_str().unwrap_or("https://api.telegram.org").to_string(),
+        smtp_url: cfg["smtp_url"].as_str().map(|url| email::SmtpUrl(url.to_string())),
+        email_from: cfg["email_from"].as_str().unwrap_or("Website Watcher <watcher@localhost>").to_string(),
+        fetch_log_size: cfg["fetch_log_size"].as_i64().unwrap_or(100),
+        max_body_bytes: cfg["max_body_bytes"].as_i64().unwrap_or(50 * 1024 * 1024),
+        event_log_size: cfg["event_log_size"].as_i64().unwrap_or(10_000),
+        tuning: recommendations::Tuning {
+            samples_per_change: cfg["tune_samples_per_change"].as_i64().unwrap_or(4),
+            min_interval_secs: cfg["tune_min_interval_secs"].as_i64().unwrap_or(30),
+            max_interval_secs: cfg["tune_max_interval_secs"].as_i64().unwrap_or(86400),
+        },
+        adaptive: recommendations::AdaptiveBounds {
+            min_interval_secs: cfg["adaptive_min_interval_secs"].as_i64().unwrap_or(60),
+            max_interval_secs: cfg["adaptive_max_interval_secs"].as_i64().unwrap_or(86400),
+        },
+        active_window: active_hours::from_yaml(&cfg["active_window"]).expect("active_window"),
+        tune_auto_apply_interval_secs: cfg["tune_auto_apply_interval_secs"].as_i64().unwrap_or(0),
+        webhook_canary_interval_secs: cfg["webhook_canary_interval_secs"].as_i64().unwrap_or(0),
+        proxies: Arc::new(proxies::Proxies::new(proxies::ProxySettings::from_yaml(cfg).expect("proxies"), network_guard.clone())),
+        network_guard,
+        plugins: Arc::new(plugins::Plugins::new(cfg["plugin_dir"].as_str().unwrap_or("plugins"))),
+        browser: cfg["browser_path"].as_str().map(|path| screenshots::Browser {
+            path: path.to_string(),
+            timeout: std::time::Duration::from_secs(cfg["screenshot_timeout_secs"].as_u64().unwrap_or(30).max(1)),
+        }),
+        challenge_solver: cfg["challenge_solver_url"].as_str().filter(|url| !url.trim().is_empty()).map(|url| challenge::Solver::new(
+            url.to_string(),
+            std::time::Duration::from_secs(cfg["challenge_solver_timeout_secs"].as_u64().unwrap_or(60).max(1)),
+        )),
+        edgar: edgar::Edgar {
+            user_agent: cfg["edgar_user_agent"].as_str().map(String::from).filter(|agent| !agent.trim().is_empty()),
+            submissions_url: cfg["edgar_submissions_url"].as_str().unwrap_or("https://data.sec.gov/submissions").to_string(),
+            tickers_url: cfg["edgar_tickers_url"].as_str().unwrap_or("https://www.sec.gov/files/company_tickers.json").to_string(),
+        },
+        reddit: reddit::Reddit::new(
+            cfg["reddit_user_agent"].as_str().unwrap_or(concat!("linux:scraper_backend:", env!("CARGO_PKG_VERSION"))).to_string(),
+            match (cfg["reddit_client_id"].as_str(), cfg["reddit_client_secret"].as_str()) {
+                (Some(client_id), Some(client_secret)) => Some(reddit::Credentials {
+                    client_id: client_id.to_string(),
+                    client_secret: client_secret.to_string(),
+                    username: cfg["reddit_username"].as_str().map(String::from),
+                    password: cfg["reddit_password"].as_str().map(String::from),
+                }),
+                _ => None,
+            },
+            cfg["reddit_token_url"].as_str().unwrap_or("https://www.reddit.com/api/v1/access_token").to_string(),
+            cfg["reddit_oauth_url"].as_str().unwrap_or("https://oauth.reddit.com").to_string(),
+        ),
+        social: social::Social {
+            bluesky_api_url: cfg["bluesky_api_url"].as_str().unwrap_or("https://public.api.bsky.app").to_string(),
+            x_api_url: cfg["x_api_url"].as_str().unwrap_or("https://api.x.com/2").to_string(),
+            x_bearer_token: cfg["x_bearer_token"].as_str().map(String::from).filter(|token| !token.trim().is_empty()),
+        },
+        news_search: news_search::NewsSearch {
+            locale: cfg["news_search_locale"].as_str().unwrap_or("en-US").to_string(),
+            google_news_url: cfg["google_news_url"].as_str().unwrap_or("https://news.google.com/rss/search").to_string(),
+            bing_news_url: cfg["bing_news_url"].as_str().unwrap_or("https://api.bing.microsoft.com/v7.0/news/search").to_string(),
+            bing_key: cfg["bing_news_key"].as_str().map(String::from).filter(|key| !key.trim().is_empty()),
+        },
+        api_keys: auth::ConfigKeys::from_yaml(&cfg["api_keys"]).expect("api_keys"),
+        protect_reads: cfg["protect_reads"].as_bool().unwrap_or(false),
+        allow_open_writes: cfg["allow_open_writes"].as_bool().unwrap_or(false),
+        seed_file: cfg["seed_file"].as_str().map(String::from),
+        demo,
+        demo_change_interval_secs: cfg["demo_change_interval_secs"].as_i64().unwrap_or(20),
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    env_logger::init();
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .init();
 
     // load config
     let cfg: serde_yaml::Value =
         serde_yaml::from_str(&std::fs::read_to_string("config.yaml").unwrap()).unwrap();
-    let db_url = cfg["database_url"].as_str().unwrap();
+    let demo = std::env::args().skip(1).any(|arg| arg == demo::FLAG);
+    let reseed = std::env::args().skip(1).any(|arg| arg == seeds::FLAG);
+    let db_url = if demo {
+        // Start every demo from the same state
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", demo::DATABASE_FILE, suffix));
+        }
+        demo::DATABASE_URL
+    } else {
+        cfg["database_url"].as_str().unwrap()
+    };
     
+    let network_guard = netguard::NetworkGuard::new(
+        cfg["block_private_networks"].as_bool().unwrap_or(true),
+        &cfg["private_network_allowlist"].as_sequence().into_iter().flatten()
+            .filter_map(|v| v.as_str().map(String::from))
+            .chain(demo.then(|| demo::LOOPBACK.to_string()))
+            .collect::<Vec<_>>(),
+    ).expect("private_network_allowlist");
+
     // Parse other config values
-    let app_config = AppConfig {
-        update_cache_size: cfg["update_cache_size"].as_i64().unwrap_or(5),
-        default_interval_secs: cfg["default_interval_secs"].as_i64().unwrap_or(1),
-        interval_jitter_max_ms: cfg["interval_jitter_max_ms"].as_i64().unwrap_or(1500),
-    };
+    let app_config = app_config(&cfg, network_guard, demo);
     
-    println!("Config loaded: {:?}", app_config);
+    info!("Config loaded: {:?}", app_config);
     
-    let pool = SqlitePool::connect(db_url).await.expect("DB connect");
-
-    // ensure schema
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS sites(
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            url TEXT NOT NULL UNIQUE,
-            interval_secs INTEGER NOT NULL,
-            style TEXT NOT NULL,
-            last_checked TEXT,
-            last_updated TEXT,
-            status TEXT
-         );"
-    ).execute(&pool).await.unwrap();
+    // SQLite file or postgres:// URL; pending migrations are applied before anything else runs
+    let storage = sql_storage::connect(db_url).await.expect("DB connect");
 
     // Reset tables if requested via environment variable (for testing/development)
     let should_add_default_sites = if std::env::var("RESET_DB").is_ok() {
-        println!("RESET_DB environment variable detected. Dropping all tables...");
-        sqlx::query("DROP TABLE IF EXISTS updates;").execute(&pool).await.unwrap();
-        sqlx::query("DROP TABLE IF EXISTS sites;").execute(&pool).await.unwrap();
-        println!("Tables dropped. Will recreate them now.");
-        true
+        match storage.legal_holds().await {
+            Ok(holds) if holds.is_empty() => {
+                warn!("RESET_DB environment variable detected. Removing all sites...");
+                storage.reset().await.unwrap();
+                info!("All sites removed.");
+                true
+            },
+            Ok(holds) => {
+                error!("RESET_DB ignored: {}", holds.summary());
+                false
+            },
+            Err(e) => {
+                error!("RESET_DB ignored: legal holds could not be checked: {}", e);
+                false
+            },
+        }
     } else {
-        // Check if there are any sites - if not, consider this a fresh install
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM sites")
-            .fetch_optional(&pool)
-            .await
-            .unwrap_or_else(|_| Some((0,)))
-            .unwrap_or((0,));
-        
-        count.0 == 0
+        // If there are no sites, consider this a fresh install
+        storage.list_sites().await.map(|sites| sites.is_empty()).unwrap_or(true)
     };
-    
-    // Enable foreign key constraints in SQLite - MUST be set for each connection
-    sqlx::query("PRAGMA foreign_keys = ON;").execute(&pool).await.unwrap();
-    
-    // Make sure we recreate the tables with proper constraints
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS updates(
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            site_id INTEGER,
-            timestamp TEXT,
-            diff_hash TEXT,
-            content TEXT,
-            FOREIGN KEY(site_id) REFERENCES sites(id) ON DELETE CASCADE
-        );"
-    ).execute(&pool).await.unwrap();
-    
-    // Double-check that foreign keys are enabled
-    sqlx::query("PRAGMA foreign_keys = ON;").execute(&pool).await.unwrap();
-    
-    // Check if foreign keys are actually enforced
-    let fk_check: (i64,) = sqlx::query_as("PRAGMA foreign_keys;")
-        .fetch_one(&pool)
-        .await
-        .unwrap();
-        
-    println!("Foreign key constraints enabled: {}", if fk_check.0 == 1 { "yes" } else { "no" });
 
     // Add default sites if needed
-    if should_add_default_sites {
-        println!("Adding default sites to the database...");
-        add_default_sites(&pool).await;
-        println!("Default sites added successfully");
+    if demo {
+        warn!("Demo mode: using {} with fixture sites {:?}", demo::DATABASE_FILE, demo::fixture_urls());
+        demo::seed(storage.as_ref()).await;
+    } else if should_add_default_sites || reseed {
+        if let Err(e) = seeds::seed(storage.as_ref(), &app_config).await {
+            error!("Seeding failed: {}", e);
+        }
+    }
+
+    if app_config.allow_open_writes {
+        warn!("allow_open_writes is set: write routes are open to anyone who can reach port 8080; see api_keys in config.yaml");
+    } else if app_config.api_keys.is_empty() && storage.list_api_keys().await.map(|keys| keys.is_empty()).unwrap_or(false) {
+        warn!("No API keys configured: write and admin routes are refused; see api_keys in config.yaml");
     }
 
     let (tx, _rx) = broadcast::channel(1000);
+    let (scheduler_tx, scheduler_rx) = mpsc::unbounded_channel();
+
+    // Check DB, network and notifications before starting any background work
+    let self_test = selftest::run_self_test(storage.as_ref(), &tx, &app_config).await;
+    if !self_test.ok && app_config.self_test_exit_on_failure {
+        error!("Exiting because self_test_exit_on_failure is set");
+        std::process::exit(1);
+    }
+
+    let metrics = Arc::new(metrics::Metrics::default());
+    let timeline = Arc::new(timeline::Timeline::new(app_config.event_log_size.max(1) as usize));
     let state = Arc::new(AppState { 
-        pool: pool.clone(), 
+        storage: storage.clone(),
         tx_updates: tx.clone(),
-        config: app_config.clone()
+        scheduler: scheduler_tx.clone(),
+        config: app_config.clone(),
+        self_test: Arc::new(self_test),
+        metrics: metrics.clone(),
+        timeline: timeline.clone(),
     });
 
     // spawn scraper background task
-    tokio::spawn(scraper::run_scraper(pool.clone(), tx.clone(), app_config.clone()));
+    tokio::spawn(scraper::run_scraper(storage.clone(), tx.clone(), app_config.clone(), scheduler_tx.clone(), scheduler_rx, metrics.clone(), timeline));
+
+    // spawn static snapshot exporter (no-op unless static_export_dir is configured)
+    tokio::spawn(export::run_static_export(storage.clone(), app_config.clone()));
+
+    // spawn watchdog for sites without a recent successful fetch
+    tokio::spawn(watchdog::run_watchdog(storage.clone(), tx.clone(), app_config.clone()));
+
+    // spawn the dedup and throttle layer; notification channels listen to its output
+    // instead of the live stream
+    let (notify_tx, _) = broadcast::channel(1000);
+    tokio::spawn(notify::run_notification_gate(storage.clone(), tx.subscribe(), notify_tx.clone(), app_config.clone()));
+
+    // spawn webhook delivery for detected changes
+    tokio::spawn(webhooks::run_webhook_dispatcher(storage.clone(), notify_tx.subscribe(), app_config.clone()));
+
+    // spawn Telegram notifications for sites opted in to a chat
+    tokio::spawn(telegram::run_telegram_dispatcher(storage.clone(), notify_tx.subscribe(), app_config.clone()));
+
+    // spawn alert emails and scheduled digests (no-op unless smtp_url is configured)
+    tokio::spawn(email::run_alert_mailer(storage.clone(), notify_tx.subscribe(), app_config.clone()));
+    tokio::spawn(email::run_digests(storage.clone(), app_config.clone()));
+
+    // spawn periodic test deliveries to every webhook (no-op unless webhook_canary_interval_secs is set)
+    tokio::spawn(webhooks::run_webhook_canary(storage.clone(), tx.clone(), app_config.clone()));
+
+    // spawn interval auto-tuning (no-op unless tune_auto_apply_interval_secs is set)
+    tokio::spawn(recommendations::run_auto_tune(storage.clone(), scheduler_tx, app_config.clone()));
+
+    // spawn the move of updates stored inline, before bodies were content-addressed
+    tokio::spawn(contents::move_inline_backlog(storage.clone()));
+
+    // spawn search indexing of updates stored before the index existed
+    tokio::spawn(search::index_backlog(storage.clone()));
+
+    // spawn synthetic changes to the demo fixtures (demo mode only)
+    let demo_pages = Arc::new(demo::DemoPages::default());
+    if demo {
+        tokio::spawn(demo::run_changes(demo_pages.clone(), app_config.demo_change_interval_secs));
+    }
+
+    // spawn periodic pruning of stored history by count and age
+    tokio::spawn(retention::run_retention(storage.clone(), app_config.clone()));
+
+    // spawn DB size / free disk space monitor (no-op unless thresholds are configured)
+    tokio::spawn(disk_monitor::run_disk_monitor(storage, tx.clone(), db_url.to_string(), app_config.clone()));
 
     // start HTTP server
-    println!("Starting HTTP server at http://0.0.0.0:8080");
-    println!("Open your browser at http://localhost:8080");
-    println!("Press Ctrl+C to stop the server");
+    info!("Starting HTTP server at http://0.0.0.0:8080 (open http://localhost:8080, Ctrl+C to stop)");
     
     HttpServer::new(move || {
+        let metrics = metrics.clone();
         App::new()
+            // API keys for mutating and admin routes; inside the metrics wrapper so
+            // rejected requests are counted too
+            .wrap(from_fn(auth::require_api_key))
+            // Count and time every request by its route pattern
+            .wrap_fn(move |req, srv| {
+                let started = std::time::Instant::now();
+                let method = req.method().to_string();
+                let path = req.match_pattern().unwrap_or_else(|| "static".into());
+                let metrics = metrics.clone();
+                let response = srv.call(req);
+                async move {
+                    let response = response.await?;
+                    metrics.observe_http(&method, &path, response.status().as_u16(), started.elapsed());
+                    Ok(response)
+                }
+            })
             .wrap(Logger::default())
             .app_data(web::Data::from(state.clone()))
-            .service(web::resource("/api/sites").route(web::get().to(list_sites)).route(web::post().to(add_site)))
-            .service(web::resource("/api/sites/{id}").route(web::delete().to(delete_site)))
+            .service(web::resource("/api/sites").route(web::get().to(list_sites)).route(web::post().to(add_site)).route(web::delete().to(delete_sites)))
+            .service(web::resource("/api/sites/bulk").route(web::post().to(add_sites)))
+            .service(web::resource("/api/sites/import").route(web::post().to(opml::import_opml)))
+            .service(web::resource("/api/sites/export.opml").route(web::get().to(opml::export_opml)))
+            .service(web::resource("/api/sites/{id}").route(web::patch().to(update_site)).route(web::delete().to(delete_site)))
+            .service(web::resource("/api/tags").route(web::get().to(tags::list_tags)).route(web::post().to(tags::add_tag)))
+            .service(web::resource("/api/tags/{tag}").route(web::get().to(tags::get_tag)).route(web::patch().to(tags::update_tag)).route(web::delete().to(tags::delete_tag)))
+            .service(web::resource("/api/tags/{tag}/settings").route(web::post().to(tags::update_tag_settings)))
+            .service(web::resource("/api/sites/{id}/check").route(web::post().to(check_site_now)))
+            .service(web::resource("/api/sites/{id}/script").route(web::put().to(scripts::put_script)).route(web::delete().to(scripts::delete_script)))
+            .service(web::resource("/api/sites/{id}/script/test").route(web::post().to(scripts::test_script)))
+            .service(web::resource("/api/preview").route(web::post().to(dry_run::preview)))
+            .service(web::resource("/api/sites/{id}/burst").route(web::post().to(start_burst)).route(web::delete().to(stop_burst)))
+            .service(web::resource("/api/sites/{id}/log").route(web::get().to(site_fetch_log)))
+            .service(web::resource("/api/sites/{id}/stats").route(web::get().to(site_fetch_stats)))
+            .service(web::resource("/api/sites/{id}/documents").route(web::get().to(documents::list_documents)))
+            .service(web::resource("/api/stats").route(web::get().to(dashboard::get_dashboard)))
+            .service(web::resource("/api/stats/latency").route(web::get().to(latency_stats)))
+            .service(web::resource("/api/updates/{id}/fingerprint").route(web::get().to(fingerprint::get_fingerprint)))
+            .service(web::resource("/api/updates/{id}/diff").route(web::get().to(diff::get_diff)))
+            .service(web::resource("/api/updates/{id}/pdf").route(web::get().to(pdf::get_pdf)))
+            .service(web::resource("/api/updates/{id}/screenshot").route(web::get().to(screenshots::get_screenshot)))
+            .service(web::resource("/api/documents/{id}").route(web::get().to(documents::get_document)))
+            .service(web::resource("/api/updates/{id}/hold").route(web::put().to(holds::hold_update)).route(web::delete().to(holds::release_update)))
+            .service(web::resource("/api/holds").route(web::get().to(holds::list_holds)))
+            .service(web::resource("/api/updates").route(web::get().to(recent_updates)))
+            .service(web::resource("/api/items").route(web::get().to(items::list_items)))
+            .service(web::resource("/api/halts").route(web::get().to(halts::list_halts)))
+            .service(web::resource("/api/federal-documents").route(web::get().to(federal_register::list_documents)))
             .service(web::resource("/api/updates/stream").route(web::get().to(sse_updates)))
+            .service(web::resource("/api/updates/poll").route(web::get().to(poll_updates)))
+            .service(web::resource("/api/export/digest").route(web::get().to(export::export_digest)))
+            .service(web::resource("/api/webhooks").route(web::get().to(webhooks::list_webhooks)).route(web::post().to(webhooks::add_webhook)))
+            .service(web::resource("/api/webhooks/{id}").route(web::delete().to(webhooks::delete_webhook)))
+            .service(web::resource("/api/webhooks/{id}/test").route(web::post().to(webhooks::test_webhook)))
+            .service(web::resource("/api/email/recipients").route(web::get().to(email::list_recipients)).route(web::post().to(email::add_recipient)))
+            .service(web::resource("/api/email/recipients/{id}").route(web::patch().to(email::update_recipient)).route(web::delete().to(email::delete_recipient)))
+            .service(web::resource("/api/email/recipients/{id}/test").route(web::post().to(email::test_recipient)))
+            .service(web::resource("/api/email/templates").route(web::get().to(email::list_templates)))
+            .service(web::resource("/api/email/templates/{kind}").route(web::put().to(email::set_template)).route(web::delete().to(email::reset_template)))
+            .service(web::resource("/api/telegram/chats").route(web::get().to(telegram::list_chats)).route(web::post().to(telegram::add_chat)))
+            .service(web::resource("/api/telegram/chats/{id}").route(web::delete().to(telegram::delete_chat)))
+            .service(web::resource("/api/telegram/chats/{id}/sites").route(web::put().to(telegram::set_chat_sites)))
+            .service(web::resource("/api/telegram/chats/{id}/test").route(web::post().to(telegram::test_chat)))
+            .service(web::resource("/api/alert-rules").route(web::get().to(alerts::list_rules)).route(web::post().to(alerts::add_rule)))
+            .service(web::resource("/api/alert-rules/{id}").route(web::delete().to(alerts::delete_rule)))
+            .service(web::resource("/api/alerts").route(web::get().to(alerts::list_alerts)))
+            .service(web::resource("/api/alerts/{id}/ack").route(web::post().to(alerts::acknowledge)))
+            .service(web::resource("/api/command").route(web::post().to(bot::handle_command)))
+            .service(web::resource("/api/metrics").route(web::get().to(metrics::get_metrics)))
+            .service(web::resource("/metrics").route(web::get().to(metrics::prometheus_metrics)))
+            .service(web::resource("/healthz").route(web::get().to(selftest::healthz)))
+            .service(web::resource("/readyz").route(web::get().to(selftest::readyz)))
+            .service(web::resource("/api/search").route(web::get().to(search::search)))
+            .service(web::resource("/api/recommendations").route(web::get().to(recommendations::get_recommendations)))
+            .service(web::resource("/api/recommendations/apply").route(web::post().to(recommendations::apply)))
+            .service(web::resource("/api/admin/seed").route(web::post().to(seeds::reseed)))
+            .service(web::resource("/api/admin/reprocess").route(web::post().to(reprocess::reprocess)))
+            .service(web::resource("/api/admin/proxies").route(web::get().to(proxies::list_proxies)))
+            .service(web::resource("/api/admin/plugins").route(web::get().to(plugins::list_plugins)))
+            .service(web::resource("/api/admin/events").route(web::get().to(timeline::list_events)))
+            .service(web::resource("/api/admin/keys").route(web::get().to(auth::list_keys)).route(web::post().to(auth::add_key)))
+            .service(web::resource("/api/admin/keys/{id}").route(web::delete().to(auth::delete_key)))
+            .service(web::resource("/api/admin/tokens").route(web::get().to(auth::list_tokens)).route(web::post().to(auth::add_token)))
+            .service(web::resource("/api/admin/tokens/{id}").route(web::delete().to(auth::delete_token)))
             .service(web::resource("/api/reset-db").route(web::get().to(reset_db)))
             .service(web::resource("/api/content/{site_id}/{timestamp}").route(web::get().to(get_full_content)))
+            .configure(|cfg| if demo {
+                cfg.app_data(web::Data::from(demo_pages.clone()))
+                    .service(web::resource("/demo/{name}").route(web::get().to(demo::serve_fixture)));
+            })
             .service(Files::new("/", "./static").index_file("index.html"))
     })
     .bind(("0.0.0.0", 8080))?
@@ -0,0 +1,249 @@
+use super::scraper::{normalize_unicode, select_region};
+use scraper::{ElementRef, Html};
+
+// Never rendered; <head> only carries metadata
+const SKIPPED_TAGS: &[&str] = &["head", "script", "style", "noscript", "template", "svg", "iframe"];
+// Start a block of their own; anything not listed here is rendered inline
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "dd", "details", "div", "dl", "dt", "figcaption",
+    "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "html", "li", "main",
+    "nav", "ol", "p", "pre", "section", "summary", "table", "ul",
+];
+
+// Markdown for the readable part of a page (or its selected region): headings, paragraphs,
+// lists, links, emphasis, code, quotes and tables. Layout and styling are dropped.
+pub fn html_to_markdown(html: &str, selector: Option<&str>) -> String {
+    let region = selector.and_then(|sel| select_region(html, sel));
+    let document = Html::parse_document(region.as_deref().unwrap_or(html));
+    let mut blocks = Vec::new();
+    render_blocks(document.root_element(), &mut blocks);
+    normalize_unicode(&blocks.join("\n\n"))
+}
+
+fn is_block(name: &str) -> bool {
+    BLOCK_TAGS.contains(&name)
+}
+
+// Inline text collected so far becomes a paragraph
+fn flush(inline: &mut String, blocks: &mut Vec<String>) {
+    let lines: Vec<String> = inline.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect();
+    inline.clear();
+    if !lines.is_empty() {
+        blocks.push(lines.join("\n"));
+    }
+}
+
+fn render_blocks(element: ElementRef, blocks: &mut Vec<String>) {
+    let mut inline = String::new();
+    for child in element.children() {
+        if let Some(text) = child.value().as_text() {
+            inline.push_str(&text.replace('\n', " "));
+            continue;
+        }
+        let Some(child) = ElementRef::wrap(child) else {
+            continue;
+        };
+        let name = child.value().name();
+        if SKIPPED_TAGS.contains(&name) {
+            continue;
+        }
+        if is_block(name) {
+            flush(&mut inline, blocks);
+            render_block(child, name, blocks);
+        } else {
+            inline.push_str(&render_inline(child));
+        }
+    }
+    flush(&mut inline, blocks);
+}
+
+fn render_block(element: ElementRef, name: &str, blocks: &mut Vec<String>) {
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let text = inline_text(element);
+            if !text.is_empty() {
+                let level = name[1..].parse().unwrap_or(1);
+                blocks.push(format!("{} {}", "#".repeat(level), text));
+            }
+        },
+        "ul" | "ol" => {
+            let list = render_list(element, name == "ol", 0);
+            if !list.is_empty() {
+                blocks.push(list);
+            }
+        },
+        "pre" => {
+            let code: String = element.text().collect();
+            if !code.trim().is_empty() {
+                blocks.push(format!("```\n{}\n```", code.trim_matches('\n').trim_end()));
+            }
+        },
+        "blockquote" => {
+            let mut inner = Vec::new();
+            render_blocks(element, &mut inner);
+            if !inner.is_empty() {
+                let quoted: Vec<String> = inner.join("\n\n").lines().map(|l| format!("> {}", l).trim_end().to_string()).collect();
+                blocks.push(quoted.join("\n"));
+            }
+        },
+        "table" => {
+            let table = render_table(element);
+            if !table.is_empty() {
+                blocks.push(table);
+            }
+        },
+        "hr" => blocks.push("---".to_string()),
+        _ => render_blocks(element, blocks),
+    }
+}
+
+fn render_inline(element: ElementRef) -> String {
+    let name = element.value().name();
+    if SKIPPED_TAGS.contains(&name) {
+        return String::new();
+    }
+    let wrap = |marker: &str| {
+        let inner = inline_children(element);
+        match inner.trim() {
+            "" => inner,
+            text => padded(&inner, &format!("{}{}{}", marker, text, marker)),
+        }
+    };
+    match name {
+        "br" => "\n".to_string(),
+        "strong" | "b" => wrap("**"),
+        "em" | "i" => wrap("*"),
+        "code" | "kbd" => {
+            let code: String = element.text().collect();
+            match code.trim() {
+                "" => String::new(),
+                code => format!("`{}`", code),
+            }
+        },
+        "a" => {
+            let text = inline_children(element);
+            match element.value().attr("href").map(str::trim) {
+                Some(href) if !href.is_empty() && !href.starts_with('#') && !href.starts_with("javascript:") && !text.trim().is_empty() => {
+                    padded(&text, &format!("[{}]({})", text.trim(), href))
+                },
+                _ => text,
+            }
+        },
+        "img" => match (element.value().attr("alt"), element.value().attr("src")) {
+            (alt, Some(src)) => format!("![{}]({})", alt.unwrap_or_default().trim(), src.trim()),
+            _ => String::new(),
+        },
+        _ => inline_children(element),
+    }
+}
+
+// Markup can't start or end with a space, so whitespace inside the element moves outside
+fn padded(inner: &str, rendered: &str) -> String {
+    let lead = if inner.starts_with(char::is_whitespace) { " " } else { "" };
+    let trail = if inner.ends_with(char::is_whitespace) { " " } else { "" };
+    format!("{}{}{}", lead, rendered, trail)
+}
+
+// Everything inside `element` as one line of inline Markdown; nested blocks are flattened
+fn inline_children(element: ElementRef) -> String {
+    let mut text = String::new();
+    for child in element.children() {
+        if let Some(t) = child.value().as_text() {
+            text.push_str(&t.replace('\n', " "));
+        } else if let Some(child) = ElementRef::wrap(child) {
+            if is_block(child.value().name()) {
+                text.push(' ');
+            }
+            text.push_str(&render_inline(child));
+        }
+    }
+    text
+}
+
+fn inline_text(element: ElementRef) -> String {
+    inline_children(element).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn render_list(element: ElementRef, ordered: bool, depth: usize) -> String {
+    let mut lines = Vec::new();
+    let items = element.children().filter_map(ElementRef::wrap).filter(|c| c.value().name() == "li");
+    for (i, item) in items.enumerate() {
+        let mut text = String::new();
+        let mut nested = Vec::new();
+        for child in item.children() {
+            if let Some(t) = child.value().as_text() {
+                text.push_str(&t.replace('\n', " "));
+            } else if let Some(child) = ElementRef::wrap(child) {
+                match child.value().name() {
+                    "ul" | "ol" => nested.push(render_list(child, child.value().name() == "ol", depth + 1)),
+                    _ => text.push_str(&render_inline(child)),
+                }
+            }
+        }
+        let marker = if ordered { format!("{}.", i + 1) } else { "-".to_string() };
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        lines.push(format!("{}{} {}", "  ".repeat(depth), marker, text));
+        lines.extend(nested.into_iter().filter(|n| !n.is_empty()));
+    }
+    lines.join("\n")
+}
+
+// Pipe table; the first row is the header. Cells are flattened to one line.
+fn render_table(element: ElementRef) -> String {
+    let rows: Vec<Vec<String>> = element.descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|e| e.value().name() == "tr")
+        .map(|row| {
+            row.children()
+                .filter_map(ElementRef::wrap)
+                .filter(|c| matches!(c.value().name(), "td" | "th"))
+                .map(|cell| inline_text(cell).replace('|', "\\|"))
+                .collect()
+        })
+        .filter(|cells: &Vec<String>| !cells.is_empty())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let line = |cells: &[String]| {
+        let padded: Vec<&str> = (0..columns).map(|i| cells.get(i).map_or("", String::as_str)).collect();
+        format!("| {} |", padded.join(" | "))
+    };
+    let mut lines = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(rows[1..].iter().map(|row| line(row)));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_readable_structure() {
+        let html = r#"<html><head><title>T</title><style>p{}</style></head><body>
+            <nav><a href="/">Home</a></nav>
+            <h1>FDA  approves <em>drug</em></h1>
+            <p>Shares of <a href="https://x.example/acme">Acme</a> rose <b>12%</b>.<br>Trading resumes.</p>
+            <ul><li>One</li><li>Two<ol><li>Nested</li></ol></li></ul>
+            <blockquote><p>Quote</p></blockquote>
+            <pre>let a = 1;
+let b = 2;</pre>
+            <table><tr><th>Ticker</th><th>Move</th></tr><tr><td>ACME</td><td>+12|%</td></tr></table>
+            <script>track()</script>
+        </body></html>"#;
+        let expected = "[Home](/)\n\n\
+            # FDA approves *drug*\n\n\
+            Shares of [Acme](https://x.example/acme) rose **12%**.\nTrading resumes.\n\n\
+            - One\n- Two\n  1. Nested\n\n\
+            > Quote\n\n\
+            ```\nlet a = 1;\nlet b = 2;\n```\n\n\
+            | Ticker | Move |\n| --- | --- |\n| ACME | +12\\|% |";
+        assert_eq!(html_to_markdown(html, None), expected);
+        assert_eq!(html_to_markdown(html, Some("h1")), "# FDA approves *drug*");
+        assert_eq!(html_to_markdown("<p>  </p>", None), "");
+    }
+}
@@ -0,0 +1,136 @@
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::time::Duration;
+
+// Scheduler counters and fetch/HTTP instruments shared between the scraper task
+// and the HTTP handlers; exported as JSON on /api/metrics and in Prometheus
+// text format on /metrics
+pub struct Metrics {
+    registry: Registry,
+    pub checks_started: IntCounter,
+    pub checks_in_flight: IntGauge,
+    // Due checks waiting for their domain's turn or a fetch permit
+    pub checks_waiting: IntGauge,
+    // Unix time the scheduler loop last came round
+    pub scheduler_heartbeat: IntGauge,
+    // Due checks skipped because the previous fetch of the same site was still running
+    pub overlap_skips: IntCounter,
+    // Manual requests served by a fetch that was already running or due
+    pub coalesced_requests: IntCounter,
+    pub check_panics: IntCounter,
+    // status is the HTTP status code, "error" when no response arrived, or
+    // "probe" when a large-file probe showed nothing changed
+    fetches: IntCounterVec,
+    fetch_duration: HistogramVec,
+    changes: IntCounterVec,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str| {
+            let counter = IntCounter::new(name, help).unwrap();
+            registry.register(Box::new(counter.clone())).unwrap();
+            counter
+        };
+        let counter_vec = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels).unwrap();
+            registry.register(Box::new(counter.clone())).unwrap();
+            counter
+        };
+        let histogram_vec = |name: &str, help: &str, labels: &[&str]| {
+            let histogram = HistogramVec::new(HistogramOpts::new(name, help), labels).unwrap();
+            registry.register(Box::new(histogram.clone())).unwrap();
+            histogram
+        };
+        let checks_in_flight = IntGauge::new("scraper_checks_in_flight", "Site checks currently running").unwrap();
+        registry.register(Box::new(checks_in_flight.clone())).unwrap();
+        let checks_waiting = IntGauge::new("scraper_checks_waiting", "Due site checks waiting to fetch").unwrap();
+        registry.register(Box::new(checks_waiting.clone())).unwrap();
+        let scheduler_heartbeat = IntGauge::new("scraper_scheduler_heartbeat_timestamp_seconds", "Last pass of the scheduler loop").unwrap();
+        registry.register(Box::new(scheduler_heartbeat.clone())).unwrap();
+
+        Metrics {
+            checks_started: counter("scraper_checks_started_total", "Site checks started"),
+            checks_in_flight,
+            checks_waiting,
+            scheduler_heartbeat,
+            overlap_skips: counter("scraper_overlap_skips_total", "Due checks skipped while the previous check was running"),
+            coalesced_requests: counter("scraper_coalesced_requests_total", "Manual checks served by a running or due fetch"),
+            check_panics: counter("scraper_check_panics_total", "Site checks that aborted"),
+            fetches: counter_vec("scraper_fetches_total", "Fetches by domain and status", &["domain", "status"]),
+            fetch_duration: histogram_vec("scraper_fetch_duration_seconds", "Fetch latency by domain", &["domain"]),
+            changes: counter_vec("scraper_changes_total", "Detected changes by site", &["site_id", "domain"]),
+            http_requests: counter_vec("http_requests_total", "API requests by route and status", &["method", "path", "status"]),
+            http_duration: histogram_vec("http_request_duration_seconds", "API response latency by route", &["method", "path"]),
+            registry,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn observe_fetch(&self, site_id: i64, domain: &str, status: &str, duration: Duration, changed: bool) {
+        self.fetches.with_label_values(&[domain, status]).inc();
+        self.fetch_duration.with_label_values(&[domain]).observe(duration.as_secs_f64());
+        if changed {
+            self.changes.with_label_values(&[&site_id.to_string(), domain]).inc();
+        }
+    }
+
+    pub fn observe_http(&self, method: &str, path: &str, status: u16, duration: Duration) {
+        self.http_requests.with_label_values(&[method, path, &status.to_string()]).inc();
+        self.http_duration.with_label_values(&[method, path]).observe(duration.as_secs_f64());
+    }
+
+    fn snapshot(&self) -> serde_json::Value {
+        serde_json::json!({
+            "checks_started": self.checks_started.get(),
+            "checks_in_flight": self.checks_in_flight.get(),
+            "checks_waiting": self.checks_waiting.get(),
+            "overlap_skips": self.overlap_skips.get(),
+            "coalesced_requests": self.coalesced_requests.get(),
+            "check_panics": self.check_panics.get(),
+        })
+    }
+
+    fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+pub async fn get_metrics(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(data.metrics.snapshot())
+}
+
+// GET /metrics: Prometheus scrape endpoint
+pub async fn prometheus_metrics(data: web::Data<AppState>) -> impl Responder {
+    match data.metrics.render() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(prometheus::TEXT_FORMAT)
+            .body(body),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Metrics error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_labeled_fetch_metrics() {
+        let metrics = Metrics::default();
+        metrics.observe_fetch(7, "sec.gov", "200", Duration::from_millis(120), true);
+        metrics.observe_fetch(7, "sec.gov", "error", Duration::from_millis(10), false);
+        let text = metrics.render().unwrap();
+        assert!(text.contains(r#"scraper_fetches_total{domain="sec.gov",status="200"} 1"#));
+        assert!(text.contains(r#"scraper_changes_total{domain="sec.gov",site_id="7"} 1"#));
+        assert!(text.contains(r#"scraper_fetch_duration_seconds_count{domain="sec.gov"} 2"#));
+    }
+}
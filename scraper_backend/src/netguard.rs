@@ -0,0 +1,226 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::{Attempt, Policy};
+use reqwest::{ClientBuilder, Url};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::lookup_host;
+
+// Same limit as reqwest's default redirect policy; sites can set their own
+pub const MAX_REDIRECTS: usize = 10;
+
+// Keeps site fetches away from this machine and its network: loopback, private
+// (RFC 1918, IPv6 unique local), link-local and unspecified addresses are refused
+// unless the host or address is on private_network_allowlist.
+#[derive(Clone, Debug)]
+pub struct NetworkGuard {
+    // block_private_networks; false lets every address through
+    enabled: bool,
+    // Lowercase host names allowed whatever they resolve to
+    hosts: Vec<String>,
+    // Allowed addresses and networks, as (network, prefix length)
+    networks: Vec<(IpAddr, u32)>,
+}
+
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_internal_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_internal_v4(v4),
+            None => is_internal_v6(ip),
+        },
+    }
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast()
+}
+
+fn is_internal_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    // fc00::/7 unique local, fe80::/10 link-local
+    ip.is_loopback() || ip.is_unspecified() || (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80
+}
+
+fn in_network(ip: IpAddr, network: IpAddr, prefix: u32) -> bool {
+    let (ip, network, bits) = match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => (u32::from(ip) as u128, u32::from(network) as u128, 32),
+        (IpAddr::V6(ip), IpAddr::V6(network)) => (u128::from(ip), u128::from(network), 128),
+        _ => return false,
+    };
+    (ip ^ network).checked_shr(bits - prefix.min(bits)).unwrap_or(0) == 0
+}
+
+// "[::1]" and "127.0.0.1" are addresses; anything else is a name to resolve
+fn host_address(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+fn blocked(host: &str, ip: IpAddr) -> String {
+    let address = match host_address(host) {
+        Some(_) => format!("{} is a private network address", ip),
+        None => format!("{} resolves to private network address {}", host, ip),
+    };
+    format!("{}; add it to private_network_allowlist to fetch it", address)
+}
+
+impl NetworkGuard {
+    // Allowlist entries are host names, addresses or CIDR networks ("10.1.0.0/16")
+    pub fn new(enabled: bool, allowlist: &[String]) -> Result<Self, String> {
+        let mut guard = NetworkGuard { enabled, hosts: Vec::new(), networks: Vec::new() };
+        for entry in allowlist.iter().map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            match entry.split_once('/') {
+                Some((network, prefix)) => {
+                    let invalid = || format!("Invalid private_network_allowlist network '{}'", entry);
+                    let network = host_address(network).ok_or_else(invalid)?;
+                    let prefix: u32 = prefix.parse().map_err(|_| invalid())?;
+                    if prefix > if network.is_ipv4() { 32 } else { 128 } {
+                        return Err(invalid());
+                    }
+                    guard.networks.push((network, prefix));
+                },
+                None => match host_address(entry) {
+                    Some(ip) => guard.networks.push((ip, 128)),
+                    None => guard.hosts.push(entry.to_ascii_lowercase()),
+                },
+            }
+        }
+        Ok(guard)
+    }
+
+    fn allows(&self, host: &str, ip: IpAddr) -> bool {
+        !self.enabled
+            || !is_internal(ip)
+            || self.hosts.iter().any(|h| h.eq_ignore_ascii_case(host))
+            || self.networks.iter().any(|(network, prefix)| in_network(ip, *network, *prefix))
+    }
+
+    // URLs naming an address are connected to without a DNS lookup, so the resolver
+    // never sees them; they are checked here before every fetch and redirect
+    fn check_address(&self, url: &Url) -> Result<(), String> {
+        let host = url.host_str().unwrap_or_default();
+        match host_address(host) {
+            Some(ip) if !self.allows(host, ip) => Err(blocked(host, ip)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_url(&self, url: &str) -> Result<(), String> {
+        let url = Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+        self.check_address(&url)
+    }
+
+    // Rejects a new site whose host only resolves to blocked addresses, and returns its
+    // URL normalized. A host that doesn't resolve yet is accepted; the resolver checks it
+    // again on every fetch.
+    pub async fn validate_site_url(&self, url: &str) -> Result<String, String> {
+        let parsed = normalize_site_url(url)?;
+        self.check_address(&parsed)?;
+        let host = parsed.host_str().unwrap_or_default();
+        if !self.enabled || host_address(host).is_some() {
+            return Ok(parsed.into());
+        }
+        let Ok(addrs) = lookup_host((host, parsed.port_or_known_default().unwrap_or(80))).await else {
+            return Ok(parsed.into());
+        };
+        let addrs: Vec<IpAddr> = addrs.map(|a| a.ip()).collect();
+        match addrs.iter().any(|ip| self.allows(host, *ip)) {
+            false if !addrs.is_empty() => Err(blocked(host, addrs[0])),
+            _ => Ok(parsed.into()),
+        }
+    }
+
+    // Resolves site hosts through the guard and checks every redirect target, following
+    // at most max_redirects of them
+    pub fn apply(&self, builder: ClientBuilder, max_redirects: usize) -> ClientBuilder {
+        if !self.enabled {
+            return builder.redirect(Policy::limited(max_redirects));
+        }
+        let guard = self.clone();
+        builder
+            .dns_resolver(Arc::new(GuardedResolver(self.clone())))
+            .redirect(Policy::custom(move |attempt: Attempt| {
+                // previous() includes the original request
+                if attempt.previous().len() > max_redirects {
+                    return attempt.error("too many redirects");
+                }
+                match guard.check_address(attempt.url()) {
+                    Ok(()) => attempt.follow(),
+                    Err(e) => attempt.error(e),
+                }
+            }))
+    }
+}
+
+// The form site URLs are stored and compared in. Parsing already lowercases the scheme
+// and host, drops a default port, turns numeric hosts like 2852039166 or 0x7f.1 into
+// dotted addresses and gives an empty path "/"; on top of that the fragment, which never
+// reaches the server, and a trailing dot on the host are removed.
+pub fn normalize_site_url(url: &str) -> Result<Url, String> {
+    let mut parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Invalid URL '{}': only http and https are supported", url));
+    }
+    let host = match parsed.host_str() {
+        Some(host) if !host.is_empty() => host.to_string(),
+        _ => return Err(format!("Invalid URL '{}': no host", url)),
+    };
+    if host.len() > 1 && host.ends_with('.') {
+        parsed.set_host(Some(host.trim_end_matches('.'))).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    }
+    parsed.set_fragment(None);
+    Ok(parsed)
+}
+
+// Drops blocked addresses from every lookup, so a public name that resolves (or is
+// rebound) to an internal address can't be used to reach it
+struct GuardedResolver(NetworkGuard);
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.0.clone();
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = lookup_host((host, 0)).await?.collect();
+            let allowed: Vec<SocketAddr> = addrs.iter().copied().filter(|a| guard.allows(host, a.ip())).collect();
+            match addrs.first() {
+                Some(addr) if allowed.is_empty() => Err(blocked(host, addr.ip()).into()),
+                _ => Ok(Box::new(allowed.into_iter()) as Addrs),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn blocks_internal_addresses_unless_allowlisted() {
+        let guard = NetworkGuard::new(true, &[]).unwrap();
+        for url in ["http://127.0.0.1:8080/api/sites", "http://10.0.0.5/", "http://169.254.169.254/latest", "http://[::1]/", "http://[fd00::1]/", "http://[::ffff:192.168.1.1]/", "http://0.0.0.0/"] {
+            assert!(guard.check_url(url).is_err(), "{}", url);
+        }
+        assert!(guard.check_url("http://93.184.216.34/").is_ok());
+        assert!(guard.validate_site_url("http://localhost:8080/").await.is_err());
+        assert!(guard.validate_site_url("file:///etc/passwd").await.is_err());
+        // Numeric spellings of the metadata and loopback addresses
+        assert!(guard.validate_site_url("http://2852039166/latest").await.is_err());
+        assert!(guard.validate_site_url("http://0x7f.1/").await.is_err());
+        assert_eq!(
+            guard.validate_site_url(" HTTPS://Example.COM.:443/News?page=2#latest").await.as_deref(),
+            Ok("https://example.com/News?page=2")
+        );
+        assert_eq!(normalize_site_url("http://93.184.216.34").unwrap().as_str(), "http://93.184.216.34/");
+        assert!(normalize_site_url("http://user@").is_err());
+
+        let allowlist = ["Dashboard.internal".to_string(), "10.1.0.0/16".into(), "::1".into()];
+        let guard = NetworkGuard::new(true, &allowlist).unwrap();
+        assert!(guard.check_url("http://10.1.200.3/").is_ok());
+        assert!(guard.check_url("http://10.2.0.1/").is_err());
+        assert!(guard.check_url("http://[::1]:9000/").is_ok());
+        assert!(guard.allows("dashboard.internal", "192.168.0.10".parse().unwrap()));
+
+        assert!(NetworkGuard::new(false, &[]).unwrap().check_url("http://127.0.0.1/").is_ok());
+        assert!(NetworkGuard::new(true, &["10.0.0.0/33".into()]).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use super::feeds::{self, FeedEntry};
+use super::plugins::FetchRequest;
+use super::truncate::{self, WORD_BREAKS};
+use super::Site;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+// Sites with this extraction_mode watch a topic instead of a page: a news search for their
+// query, polled as Google News RSS, or through Bing's News Search API when a key is set
+pub const EXTRACTION_MODE: &str = "search";
+
+// Bing returns 100 articles at most
+const BING_COUNT: &str = "100";
+const BING_KEY_HEADER: &str = "Ocp-Apim-Subscription-Key";
+
+// The news search engines search sites poll and the locale results are in. Debug output
+// hides the key, since the loaded config is logged at startup.
+#[derive(Clone)]
+pub struct NewsSearch {
+    // Language and country such as "en-US"
+    pub locale: String,
+    pub google_news_url: String,
+    pub bing_news_url: String,
+    pub bing_key: Option<String>,
+}
+
+impl fmt::Debug for NewsSearch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NewsSearch")
+            .field("locale", &self.locale)
+            .field("google_news_url", &self.google_news_url)
+            .field("bing_news_url", &self.bing_news_url)
+            .field("bing_key", &self.bing_key.as_ref().map(|_| "<set>"))
+            .finish()
+    }
+}
+
+impl NewsSearch {
+    fn is_bing(&self, url: &str) -> bool {
+        url.starts_with(self.bing_news_url.trim_end_matches('/'))
+    }
+
+    // The URL a search site polls: Bing's API for the query when a key is set, else Google
+    // News RSS. Without a query the URL is kept as given, such as a Google News search feed.
+    pub fn resolve(&self, query: Option<&str>, url: &str) -> Result<String, String> {
+        let Some(query) = query.map(str::trim) else {
+            return match url.trim() {
+                "" => Err("A search site needs a query or a news search URL".to_string()),
+                url => Ok(url.to_string()),
+            };
+        };
+        if query.is_empty() {
+            return Err("query must not be empty".to_string());
+        }
+        let (language, country) = self.locale.split_once('-').unwrap_or((&self.locale, ""));
+        let mut search = match &self.bing_key {
+            Some(_) => Url::parse(&self.bing_news_url).map_err(|e| format!("Invalid bing_news_url '{}': {}", self.bing_news_url, e))?,
+            None => Url::parse(&self.google_news_url).map_err(|e| format!("Invalid google_news_url '{}': {}", self.google_news_url, e))?,
+        };
+        match &self.bing_key {
+            Some(_) => search.query_pairs_mut().append_pair("q", query).append_pair("count", BING_COUNT).append_pair("sortBy", "Date").append_pair("mkt", &self.locale),
+            None => search.query_pairs_mut()
+                .append_pair("q", query)
+                .append_pair("hl", &self.locale)
+                .append_pair("gl", country)
+                .append_pair("ceid", &format!("{}:{}", country, language)),
+        };
+        Ok(search.to_string())
+    }
+
+    // Bing searches send the key; Google News RSS needs nothing
+    pub fn request(&self, site: &Site) -> Result<Option<FetchRequest>, String> {
+        if !self.is_bing(&site.url) {
+            return Ok(None);
+        }
+        let key = self.bing_key.as_deref().ok_or("bing_news_key is not set; Bing's News Search API needs a key")?;
+        let headers = BTreeMap::from([(BING_KEY_HEADER.to_string(), key.to_string())]);
+        Ok(Some(FetchRequest { url: site.url.clone(), headers }))
+    }
+}
+
+fn is_bing_news(json: &Value) -> bool {
+    json["_type"] == "News" && json["value"].is_array()
+}
+
+// The article URL a hit is known by. Google News links carry a tracking "oc" parameter
+// that comes and goes.
+fn article_url(link: &str) -> String {
+    match Url::parse(link) {
+        Ok(mut url) if url.host_str() == Some("news.google.com") => {
+            url.set_query(None);
+            url.to_string()
+        },
+        _ => link.to_string(),
+    }
+}
+
+// Google News titles hits "Headline - Publisher"; the publisher becomes the summary, since
+// the description only repeats the headline
+fn google_hit(entry: FeedEntry) -> Option<FeedEntry> {
+    let link = entry.link.as_deref().map(article_url)?;
+    let (title, publisher) = match entry.title.as_deref().and_then(|title| title.rsplit_once(" - ")) {
+        Some((headline, publisher)) => (headline.to_string(), Some(publisher.to_string())),
+        None => (entry.title.clone()?, entry.summary.clone()),
+    };
+    Some(FeedEntry { guid: link.clone(), title: Some(title), link: Some(link), summary: publisher, published: entry.published })
+}
+
+// news/search: {"_type": "News", "value": [{"name", "url", "description", "datePublished", "provider"}]}
+fn bing_hit(article: &Value) -> Option<FeedEntry> {
+    let url = article["url"].as_str()?.to_string();
+    let publisher = article["provider"][0]["name"].as_str();
+    let description = article["description"].as_str();
+    let summary = match (publisher, description) {
+        (Some(publisher), Some(description)) => Some(format!("{}: {}", publisher, description)),
+        (publisher, description) => publisher.or(description).map(String::from),
+    };
+    Some(FeedEntry {
+        guid: url.clone(),
+        title: article["name"].as_str().map(String::from),
+        link: Some(url),
+        summary,
+        published: article["datePublished"].as_str().and_then(|date| DateTime::parse_from_rfc3339(date).ok()).map(|date| date.with_timezone(&Utc)),
+    })
+}
+
+// The hits of a news search, one per article URL. Bing responses are recognized without
+// the mode; Google News RSS needs it, being a feed otherwise.
+pub fn read(body: &str, extraction_mode: Option<&str>) -> Option<Vec<FeedEntry>> {
+    let named = extraction_mode == Some(EXTRACTION_MODE);
+    let hits: Vec<FeedEntry> = match serde_json::from_str::<Value>(body) {
+        Ok(json) if is_bing_news(&json) => json["value"].as_array()?.iter().filter_map(bing_hit).collect(),
+        Ok(_) => return None,
+        Err(_) if named => feeds::parse_feed(body)?.into_iter().filter_map(google_hit).collect(),
+        Err(_) => return None,
+    };
+    let mut seen = HashSet::new();
+    Some(hits.into_iter().filter(|hit| seen.insert(hit.guid.clone())).collect())
+}
+
+// Headed "🔎 4 news hits", one headline per line
+pub fn preview(hits: &[FeedEntry], max_length: usize) -> String {
+    let lines = hits.iter().map(|hit| hit.title.as_deref().unwrap_or(&hit.guid)).collect::<Vec<_>>().join("\n");
+    let heading = format!("🔎 {} news hit{}", hits.len(), if hits.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&lines, max_length, &[WORD_BREAKS]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_queries_and_reads_google_and_bing_hits() {
+        let mut search = NewsSearch {
+            locale: "en-US".to_string(),
+            google_news_url: "https://news.google.com/rss/search".to_string(),
+            bing_news_url: "https://api.bing.microsoft.com/v7.0/news/search".to_string(),
+            bing_key: None,
+        };
+        assert_eq!(search.resolve(Some("\"short report\" GME"), "").unwrap(), "https://news.google.com/rss/search?q=%22short+report%22+GME&hl=en-US&gl=US&ceid=US%3Aen");
+        assert!(search.resolve(None, " ").is_err());
+        search.bing_key = Some("key".to_string());
+        let bing = search.resolve(Some("GME"), "").unwrap();
+        assert_eq!(bing, "https://api.bing.microsoft.com/v7.0/news/search?q=GME&count=100&sortBy=Date&mkt=en-US");
+        assert!(format!("{:?}", search).contains("<set>"));
+
+        let google = r#"<?xml version="1.0"?><rss version="2.0"><channel><title>"GME" - Google News</title>
+            <item><title>GameStop shares jump - Reuters</title><link>https://news.google.com/rss/articles/CBMiabc?oc=5</link>
+              <guid isPermaLink="false">CBMiabc</guid><pubDate>Tue, 14 Oct 2026 12:00:00 GMT</pubDate><description>&lt;a href="x"&gt;GameStop shares jump&lt;/a&gt;</description></item>
+            <item><title>GameStop shares jump - Reuters</title><link>https://news.google.com/rss/articles/CBMiabc?oc=6</link><guid>CBMiabc2</guid></item>
+        </channel></rss>"#;
+        let hits = read(google, Some(EXTRACTION_MODE)).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].guid, "https://news.google.com/rss/articles/CBMiabc");
+        assert_eq!((hits[0].title.as_deref(), hits[0].summary.as_deref()), (Some("GameStop shares jump"), Some("Reuters")));
+        assert!(read(google, None).is_none());
+
+        let results = r#"{"_type": "News", "value": [{"name": "GameStop rallies", "url": "https://example.com/gme",
+            "description": "Shares rose.", "datePublished": "2026-10-14T12:00:00.0000000Z", "provider": [{"name": "Example News"}]}]}"#;
+        let hits = read(results, None).unwrap();
+        assert_eq!(hits[0].guid, "https://example.com/gme");
+        assert_eq!(hits[0].summary.as_deref(), Some("Example News: Shares rose."));
+        assert!(hits[0].published.is_some());
+    }
+}
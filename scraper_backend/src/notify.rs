@@ -0,0 +1,254 @@
+use super::storage::SharedStorage;
+use super::{AppConfig, Site, StreamEvent, UpdateMessage};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use tokio::sync::broadcast::{error::RecvError, Receiver, Sender};
+use tracing::{debug, warn};
+
+// How often held notifications are checked for the end of their window
+const FLUSH_CHECK_MS: u64 = 1000;
+
+// A site's throttle: at most `burst` notifications per `window_secs`; either one 0 turns it off
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    pub burst: i64,
+    pub window_secs: i64,
+}
+
+impl Limits {
+    pub fn for_site(site: Option<&Site>, config: &AppConfig) -> Limits {
+        Limits {
+            burst: site.and_then(|s| s.notify_burst_limit).unwrap_or(config.notify_burst_limit),
+            window_secs: site.and_then(|s| s.notify_window_secs).unwrap_or(config.notify_window_secs),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.burst > 0 && self.window_secs > 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Decision {
+    Send,
+    Hold,
+    // Already notified for this content
+    Drop,
+}
+
+struct Window {
+    ends: DateTime<Utc>,
+    // Length it was opened with, kept when it starts over
+    secs: i64,
+    sent: i64,
+    // Latest change held back, standing for `held_count` changes
+    held: Option<UpdateMessage>,
+    held_count: i64,
+}
+
+impl Window {
+    fn open(secs: i64, now: DateTime<Utc>, sent: i64) -> Window {
+        Window { ends: now + Duration::seconds(secs), secs, sent, held: None, held_count: 0 }
+    }
+}
+
+#[derive(Default)]
+struct Throttle {
+    windows: HashMap<i64, Window>,
+    // Last update decided on per site. A feed update sends one message per new entry,
+    // and they all go the way the first one went.
+    last: HashMap<i64, (i64, Decision)>,
+}
+
+impl Throttle {
+    fn repeat_of_last(&self, msg: &UpdateMessage) -> Option<Decision> {
+        self.last.get(&msg.site_id).filter(|(update_id, _)| *update_id == msg.update_id).map(|(_, d)| *d)
+    }
+
+    fn decide(&mut self, msg: &UpdateMessage, decision: Decision) {
+        self.last.insert(msg.site_id, (msg.update_id, decision));
+    }
+
+    // Sends while the site's window has room, otherwise holds the change back
+    fn admit(&mut self, msg: &UpdateMessage, limits: Limits, now: DateTime<Utc>) -> Decision {
+        if !limits.enabled() {
+            return Decision::Send;
+        }
+        let window = self.windows.entry(msg.site_id).or_insert_with(|| Window::open(limits.window_secs, now, 0));
+        // Ended quietly since the last flush check
+        if window.ends <= now && window.held.is_none() {
+            *window = Window::open(limits.window_secs, now, 0);
+        }
+        if window.sent < limits.burst {
+            window.sent += 1;
+            return Decision::Send;
+        }
+        window.held_count += 1;
+        hold(window, msg);
+        Decision::Hold
+    }
+
+    // Another message of an update that is already held
+    fn hold_sibling(&mut self, msg: &UpdateMessage) {
+        if let Some(window) = self.windows.get_mut(&msg.site_id) {
+            hold(window, msg);
+        }
+    }
+
+    // Held changes of windows that have ended, one collapsed message per site. A window
+    // that ends without held changes is dropped; one that flushed starts over, counting
+    // the flushed message.
+    fn flush(&mut self, now: DateTime<Utc>) -> Vec<UpdateMessage> {
+        let mut flushed = Vec::new();
+        self.windows.retain(|_, window| {
+            if window.ends > now {
+                return true;
+            }
+            let Some(mut msg) = window.held.take() else {
+                return false;
+            };
+            msg.collapsed_updates = Some(window.held_count);
+            flushed.push(msg);
+            *window = Window::open(window.secs, now, 1);
+            true
+        });
+        flushed
+    }
+}
+
+// The held message becomes the latest change, keeping the alert rules matched by every
+// change it stands for
+fn hold(window: &mut Window, msg: &UpdateMessage) {
+    let mut rules = window.held.take().map(|held| held.matched_rules).unwrap_or_default();
+    for rule in &msg.matched_rules {
+        if !rules.contains(rule) {
+            rules.push(rule.clone());
+        }
+    }
+    window.held = Some(UpdateMessage { matched_rules: rules, ..msg.clone() });
+}
+
+fn forward(out: &Sender<StreamEvent>, msg: UpdateMessage) {
+    if !msg.matched_rules.is_empty() {
+        let _ = out.send(StreamEvent::Alert(msg.clone()));
+    }
+    let _ = out.send(StreamEvent::Update(msg));
+}
+
+// Sits between detected changes and the notification channels (webhooks, Telegram, email).
+// A site is notified once per content hash, and a burst of changes beyond its limit within
+// the window is collapsed into one notification when the window ends. The live stream
+// gets every change regardless.
+pub async fn run_notification_gate(storage: SharedStorage, mut rx: Receiver<StreamEvent>, out: Sender<StreamEvent>, config: AppConfig) {
+    let mut throttle = Throttle::default();
+    let mut ticks = tokio::time::interval(std::time::Duration::from_millis(FLUSH_CHECK_MS));
+
+    loop {
+        let msg = tokio::select! {
+            event = rx.recv() => match event {
+                Ok(StreamEvent::Update(msg)) => msg,
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Notification gate fell behind, {} update(s) not notified", skipped);
+                    continue;
+                },
+                Err(RecvError::Closed) => return,
+            },
+            _ = ticks.tick() => {
+                for msg in throttle.flush(Utc::now()) {
+                    debug!(site_id = msg.site_id, collapsed = msg.collapsed_updates, "Sending collapsed notification");
+                    forward(&out, msg);
+                }
+                continue;
+            },
+        };
+
+        if let Some(decision) = throttle.repeat_of_last(&msg) {
+            match decision {
+                Decision::Send => forward(&out, msg),
+                Decision::Hold => throttle.hold_sibling(&msg),
+                Decision::Drop => {},
+            }
+            continue;
+        }
+
+        let now = Utc::now();
+        let first = match storage.claim_notification(msg.site_id, &msg.diff_hash, now).await {
+            Ok(first) => first,
+            Err(e) => {
+                warn!("Could not record notification for update {}: {}", msg.update_id, e);
+                true
+            }
+        };
+        let decision = if first {
+            let site = storage.get_site(msg.site_id).await.ok().flatten();
+            throttle.admit(&msg, Limits::for_site(site.as_ref(), &config), now)
+        } else {
+            debug!(site_id = msg.site_id, update_id = msg.update_id, "Content already notified, skipping");
+            Decision::Drop
+        };
+        throttle.decide(&msg, decision);
+        if decision == Decision::Send {
+            forward(&out, msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::MatchedRule;
+
+    fn msg(site_id: i64, update_id: i64, rule: Option<&str>) -> UpdateMessage {
+        UpdateMessage {
+            update_id,
+            site_id,
+            url: format!("https://{}.example", site_id),
+            timestamp: Utc::now(),
+            diff_hash: format!("hash-{}", update_id),
+            content_preview: String::new(),
+            has_full_content: true,
+            title: None,
+            link: None,
+            matched_rules: rule.map(|name| MatchedRule { id: name.len() as i64, name: name.into() }).into_iter().collect(),
+            published: None,
+            detection_latency_secs: None,
+            collapsed_updates: None,
+            metadata: None,
+            wire_source: None,
+            has_screenshot: false,
+            documents: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn collapses_bursts_beyond_the_limit() {
+        let limits = Limits { burst: 2, window_secs: 60 };
+        let start: DateTime<Utc> = "2024-05-01T12:00:00Z".parse().unwrap();
+        let mut throttle = Throttle::default();
+
+        assert_eq!(throttle.admit(&msg(1, 1, None), limits, start), Decision::Send);
+        assert_eq!(throttle.admit(&msg(1, 2, None), limits, start), Decision::Send);
+        assert_eq!(throttle.admit(&msg(1, 3, Some("FDA")), limits, start), Decision::Hold);
+        assert_eq!(throttle.admit(&msg(1, 4, None), limits, start + Duration::seconds(10)), Decision::Hold);
+        throttle.hold_sibling(&msg(1, 4, Some("SEC")));
+        // Other sites have windows of their own
+        assert_eq!(throttle.admit(&msg(2, 5, None), limits, start), Decision::Send);
+        // Throttling off
+        assert_eq!(throttle.admit(&msg(3, 6, None), Limits { burst: 0, window_secs: 60 }, start), Decision::Send);
+
+        assert!(throttle.flush(start + Duration::seconds(59)).is_empty());
+        let flushed = throttle.flush(start + Duration::seconds(60));
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(flushed[0].update_id, 4);
+        assert_eq!(flushed[0].collapsed_updates, Some(2));
+        let rules: Vec<&str> = flushed[0].matched_rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(rules, ["FDA", "SEC"]);
+
+        // The flushed message counts towards the next window; site 2's quiet window is gone
+        let later = start + Duration::seconds(61);
+        assert_eq!(throttle.admit(&msg(1, 7, None), limits, later), Decision::Send);
+        assert_eq!(throttle.admit(&msg(1, 8, None), limits, later), Decision::Hold);
+        assert!(!throttle.windows.contains_key(&2));
+    }
+}
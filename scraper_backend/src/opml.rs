@@ -0,0 +1,232 @@
+use super::{netguard, scraper::SchedulerEvent, tags, AppState, Site};
+use actix_web::{web, HttpResponse, Responder};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, XmlVersion};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use tracing::info;
+
+// A feed listed in an OPML file; tags come from the folders it sits in and its category
+#[derive(Debug, PartialEq)]
+pub struct OpmlFeed {
+    pub url: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ImportedSite {
+    id: i64,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct SkippedFeed {
+    url: String,
+    reason: String,
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref().eq_ignore_ascii_case(name))
+        .and_then(|a| a.normalized_value(XmlVersion::default()).ok().map(|v| v.trim().to_string()))
+        .filter(|v| !v.is_empty())
+}
+
+// OPML 2.0 categories are comma-separated, each possibly a /-separated path
+fn category_tags(category: Option<String>) -> Vec<String> {
+    category.unwrap_or_default()
+        .split([',', '/'])
+        .map(str::to_string)
+        .collect()
+}
+
+// Outlines with an xmlUrl are feeds; any other outline is a folder around them. A feed
+// listed twice keeps the tags of both places.
+pub fn parse(xml: &str) -> Result<Vec<OpmlFeed>, String> {
+    let mut reader = Reader::from_str(xml);
+    // Folder name per open outline (None for feeds opened with a closing tag)
+    let mut folders: Vec<Option<String>> = Vec::new();
+    let mut feeds: Vec<OpmlFeed> = Vec::new();
+    let mut seen_opml = false;
+    loop {
+        let event = reader.read_event().map_err(|e| format!("Invalid OPML at byte {}: {}", reader.error_position(), e))?;
+        let (element, empty) = match &event {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(e) => {
+                if e.local_name().as_ref() == "outline" {
+                    folders.pop();
+                }
+                continue;
+            },
+            Event::Eof => break,
+            _ => continue,
+        };
+        match element.local_name().as_ref() {
+            "opml" => seen_opml = true,
+            "outline" => {
+                let Some(url) = attribute(element, "xmlUrl").or_else(|| attribute(element, "url")) else {
+                    if !empty {
+                        folders.push(attribute(element, "text").or_else(|| attribute(element, "title")));
+                    }
+                    continue;
+                };
+                let mut tags: Vec<String> = folders.iter().flatten().cloned().collect();
+                tags.extend(category_tags(attribute(element, "category")));
+                match feeds.iter_mut().find(|f| f.url == url) {
+                    Some(feed) => feed.tags.extend(tags),
+                    None => feeds.push(OpmlFeed { url, tags }),
+                }
+                if !empty {
+                    folders.push(None);
+                }
+            },
+            _ => {},
+        }
+    }
+    if !seen_opml {
+        return Err("Not an OPML document: no <opml> element".into());
+    }
+    for feed in &mut feeds {
+        feed.tags = tags::normalize_tags(&feed.tags);
+    }
+    Ok(feeds)
+}
+
+// One folder per site's first tag, untagged sites at the top level; every tag also goes in
+// `category` so the import restores them all
+pub fn render(sites: &[Site]) -> String {
+    let mut folders: BTreeMap<&str, Vec<&Site>> = BTreeMap::new();
+    for site in sites {
+        folders.entry(site.tags.first().map(String::as_str).unwrap_or_default()).or_default().push(site);
+    }
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    out.push_str("  <head>\n    <title>Watched sites</title>\n  </head>\n  <body>\n");
+    for (folder, sites) in folders {
+        let indent = if folder.is_empty() { "    " } else { "      " };
+        if !folder.is_empty() {
+            out.push_str(&format!("    <outline text=\"{0}\" title=\"{0}\">\n", escape(folder)));
+        }
+        for site in sites {
+            let url = escape(site.url.as_str());
+            let category = match site.tags.is_empty() {
+                true => String::new(),
+                false => format!(" category=\"{}\"", escape(site.tags.join(","))),
+            };
+            out.push_str(&format!("{}<outline type=\"rss\" text=\"{1}\" title=\"{1}\" xmlUrl=\"{1}\"{2}/>\n", indent, url, category));
+        }
+        if !folder.is_empty() {
+            out.push_str("    </outline>\n");
+        }
+    }
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+// GET /api/sites/export.opml: the watchlist as OPML for feed readers
+pub async fn export_opml(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.list_sites().await {
+        Ok(sites) => HttpResponse::Ok()
+            .insert_header(("Content-Type", "text/x-opml; charset=utf-8"))
+            .insert_header(("Content-Disposition", "attachment; filename=\"watchlist.opml\""))
+            .body(render(&sites)),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// POST /api/sites/import: body is an OPML file. Feeds already watched, listed twice or
+// refused by the URL checks are skipped and reported; the rest are added with the
+// interval and style their tags give, or the global defaults.
+pub async fn import_opml(data: web::Data<AppState>, body: web::Bytes) -> impl Responder {
+    let Ok(xml) = std::str::from_utf8(&body) else {
+        return HttpResponse::BadRequest().body("OPML must be UTF-8");
+    };
+    let feeds = match parse(xml) {
+        Ok(feeds) => feeds,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let existing = match data.storage.list_sites().await {
+        Ok(sites) => sites,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let defined = match data.storage.list_tags().await {
+        Ok(defined) => defined,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let mut watched: HashSet<String> = existing.iter()
+        .filter_map(|s| netguard::normalize_site_url(&s.url).ok().map(String::from))
+        .collect();
+
+    let mut added = Vec::new();
+    let mut skipped = Vec::new();
+    for feed in feeds {
+        let url = match data.config.network_guard.validate_site_url(&feed.url).await {
+            Ok(url) => url,
+            Err(e) => {
+                skipped.push(SkippedFeed { url: feed.url, reason: e });
+                continue;
+            },
+        };
+        if !watched.insert(url.clone()) {
+            skipped.push(SkippedFeed { url, reason: "already watched".into() });
+            continue;
+        }
+        let (interval, style) = tags::defaults_for(&feed.tags, &defined);
+        let site = Site {
+            tags: feed.tags,
+            ..Site::new(&url, interval.unwrap_or(data.config.default_interval_secs), style.as_deref().unwrap_or("random"))
+        };
+        match data.storage.insert_site(&site).await {
+            Ok(id) => {
+                let _ = data.scheduler.send(SchedulerEvent::SiteAdded(id));
+                added.push(ImportedSite { id, url });
+            },
+            Err(e) => skipped.push(SkippedFeed { url, reason: e.to_string() }),
+        }
+    }
+
+    info!("OPML import added {} site(s), skipped {}", added.len(), skipped.len());
+    HttpResponse::Ok().json(serde_json::json!({
+        "added": added,
+        "skipped": skipped,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_folders_and_categories() {
+        let xml = r#"<?xml version="1.0"?>
+            <opml version="2.0"><head><title>Reader</title></head><body>
+              <outline text="FDA">
+                <outline text="Press" type="rss" xmlUrl="https://fda.example/rss?a=1&amp;b=2"/>
+                <outline text="Nested"><outline xmlUrl="https://deep.example/feed" category="gov/us"></outline></outline>
+              </outline>
+              <outline text="Top" xmlUrl="https://top.example/feed"/>
+              <outline text="SEC"><outline xmlUrl="https://fda.example/rss?a=1&amp;b=2"/></outline>
+            </body></opml>"#;
+        let feeds = parse(xml).unwrap();
+        assert_eq!(feeds, vec![
+            OpmlFeed { url: "https://fda.example/rss?a=1&b=2".into(), tags: vec!["fda".into(), "sec".into()] },
+            OpmlFeed { url: "https://deep.example/feed".into(), tags: vec!["fda".into(), "gov".into(), "nested".into(), "us".into()] },
+            OpmlFeed { url: "https://top.example/feed".into(), tags: vec![] },
+        ]);
+
+        let sites: Vec<Site> = feeds.into_iter()
+            .map(|f| Site { tags: f.tags, ..Site::new(&f.url, 60, "random") })
+            .collect();
+        let mut exported: Vec<(String, Vec<String>)> = parse(&render(&sites)).unwrap().into_iter().map(|f| (f.url, f.tags)).collect();
+        exported.sort();
+        let mut expected: Vec<(String, Vec<String>)> = sites.into_iter().map(|s| (s.url, s.tags)).collect();
+        expected.sort();
+        assert_eq!(exported, expected);
+
+        assert!(parse("<rss><channel/></rss>").is_err());
+        assert!(parse("<opml><body><outline").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use super::ignore::strip_ignored;
+use super::readability::Article;
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+
+pub const MIME_TYPE: &str = "application/pdf";
+// Sites whose URL serves a PDF without saying so set this as their extraction_mode
+pub const EXTRACTION_MODE: &str = "pdf";
+const SIGNATURE: &[u8] = b"%PDF-";
+
+// A fetched PDF: the text checks hash, diff and store, and the file it came from
+pub struct Pdf {
+    pub text: String,
+    pub data: Vec<u8>,
+}
+
+// By Content-Type, the site's extraction_mode or the file's own signature, since many
+// servers send PDFs as application/octet-stream
+pub fn is_pdf(content_type: Option<&str>, extraction_mode: Option<&str>, body: &[u8]) -> bool {
+    let mime = content_type.and_then(|t| t.split(';').next()).map(str::trim);
+    mime.is_some_and(|mime| mime.eq_ignore_ascii_case(MIME_TYPE))
+        || extraction_mode == Some(EXTRACTION_MODE)
+        || body.starts_with(SIGNATURE)
+}
+
+// Parsing runs on the blocking pool; a malformed file the parser panics on only fails
+// this fetch
+pub async fn extract(data: Vec<u8>) -> Result<Pdf, String> {
+    let extracted = tokio::task::spawn_blocking(move || {
+        pdf_extract::extract_text_from_mem_by_pages(&data).map(|pages| Pdf { text: page_text(&pages), data })
+    }).await;
+    match extracted {
+        Ok(Ok(pdf)) if pdf.text.is_empty() => Err("the PDF has no extractable text".into()),
+        Ok(Ok(pdf)) => Ok(pdf),
+        Ok(Err(e)) => Err(format!("PDF text extraction failed: {}", e)),
+        Err(_) => Err("PDF text extraction failed: the file could not be parsed".into()),
+    }
+}
+
+// Pages one after the other, lines trimmed and runs of blank lines collapsed, so a
+// repaginated document hashes like the original
+fn page_text(pages: &[String]) -> String {
+    let lines: Vec<&str> = pages.iter().flat_map(|page| page.lines()).map(str::trim).collect();
+    let mut text = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        match line.is_empty() {
+            true if i > 0 && !lines[i - 1].is_empty() => text.push('\n'),
+            true => {},
+            false => {
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(line);
+            },
+        }
+    }
+    text.trim_end().to_string()
+}
+
+// PDF text goes through hashing and previews like a readability article. Only regex
+// ignore patterns apply; a document has no elements for `css:` ones to match.
+pub fn article(text: &str, ignore: &[String]) -> Article {
+    let regexes: Vec<String> = ignore.iter().filter(|p| !p.starts_with("css:")).cloned().collect();
+    Article { title: None, text: strip_ignored(text, &regexes) }
+}
+
+// GET /api/updates/{id}/pdf: the PDF an update's text was extracted from
+pub async fn get_pdf(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let update_id = path.into_inner();
+    match data.storage.update_pdf(update_id).await {
+        Ok(Some(pdf)) => HttpResponse::Ok().content_type(MIME_TYPE).body(pdf),
+        Ok(None) => HttpResponse::NotFound().body(format!("Update with ID {} has no PDF", update_id)),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_pdfs_and_normalizes_their_text() {
+        assert!(is_pdf(Some("application/PDF; name=ruling.pdf"), None, b""));
+        assert!(is_pdf(Some("application/octet-stream"), None, b"%PDF-1.7\n"));
+        assert!(is_pdf(None, Some("pdf"), b"<html>"));
+        assert!(!is_pdf(Some("text/html"), None, b"<html>"));
+
+        let pages = ["  IN THE SUPREME COURT  \n\n\n\nNo. 23-411\n".to_string(), "\nOPINION\n  It is so ordered. \n\n".to_string()];
+        assert_eq!(page_text(&pages), "IN THE SUPREME COURT\n\nNo. 23-411\n\nOPINION\nIt is so ordered.");
+    }
+}
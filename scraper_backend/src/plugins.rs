@@ -0,0 +1,283 @@
+use super::scripts::{self, ScriptOutput, MAX_ITEMS};
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::info;
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+// Instructions one plugin call may execute, a few seconds of work
+const FUEL: u64 = 2_000_000_000;
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+// Largest JSON a plugin may hand back
+const MAX_OUTPUT_BYTES: usize = 16 * 1024 * 1024;
+
+// The request a plugin's fetch export asks for instead of a plain GET of the site's URL
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct FetchRequest {
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct PluginOutput {
+    title: Option<String>,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    items: Vec<PluginItem>,
+}
+
+#[derive(Deserialize)]
+struct PluginItem {
+    guid: Option<String>,
+    title: Option<String>,
+    link: Option<String>,
+    summary: Option<String>,
+    published: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PluginInfo {
+    name: String,
+    size_bytes: u64,
+    modified: Option<DateTime<Utc>>,
+    // Whether the module compiled and has the exports plugins need, and why not
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    // Whether it also rewrites the site's request
+    fetch: bool,
+}
+
+struct Loaded {
+    module: Module,
+    modified: SystemTime,
+}
+
+// WASM source adapters in plugin_dir, one `<name>.wasm` each. A module is compiled on first
+// use and again whenever its file changes, so plugins can be replaced without a restart.
+pub struct Plugins {
+    dir: PathBuf,
+    engine: Engine,
+    loaded: Mutex<HashMap<String, Loaded>>,
+}
+
+impl fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Plugins").field("dir", &self.dir).finish()
+    }
+}
+
+// Letters, digits, `-` and `_`, so a name can't point outside plugin_dir
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid plugin name '{}': use letters, digits, '-' and '_'", name));
+    }
+    Ok(())
+}
+
+fn trap(e: wasmtime::Error) -> String {
+    match e.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => "the plugin ran out of fuel".into(),
+        _ => format!("the plugin failed: {:#}", e),
+    }
+}
+
+impl Plugins {
+    pub fn new(dir: &str) -> Self {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        Plugins { dir: PathBuf::from(dir), engine: Engine::new(&config).expect("wasm engine"), loaded: Mutex::new(HashMap::new()) }
+    }
+
+    // The plugin's module, compiled again if its file changed since it was last loaded.
+    // Compiling is CPU-bound: callers stay off the async workers.
+    fn module(&self, name: &str) -> Result<Module, String> {
+        validate_name(name)?;
+        let path = self.dir.join(format!("{}.wasm", name));
+        let modified = std::fs::metadata(&path).and_then(|meta| meta.modified())
+            .map_err(|e| format!("Plugin '{}' not found in {}: {}", name, self.dir.display(), e))?;
+        if let Some(loaded) = self.loaded.lock().unwrap().get(name).filter(|loaded| loaded.modified == modified) {
+            return Ok(loaded.module.clone());
+        }
+        let module = Module::from_file(&self.engine, &path).map_err(|e| format!("Plugin '{}' doesn't compile: {:#}", name, e))?;
+        // Nothing is linked in: no WASI, no host functions, so no files, network or clock
+        if let Some(import) = module.imports().next() {
+            return Err(format!("Plugin '{}' imports {}::{}, but plugins get no imports", name, import.module(), import.name()));
+        }
+        for export in ["memory", "alloc", "extract"] {
+            if module.get_export(export).is_none() {
+                return Err(format!("Plugin '{}' doesn't export {}", name, export));
+            }
+        }
+        info!(plugin = name, "Plugin loaded from {}", path.display());
+        self.loaded.lock().unwrap().insert(name.to_string(), Loaded { module: module.clone(), modified });
+        Ok(module)
+    }
+
+    // Refuses a plugin that can't be loaded
+    pub fn check(&self, name: &str) -> Result<(), String> {
+        self.module(name).map(|_| ())
+    }
+
+    // Calls `export(ptr, len) -> i64` with the input copied into memory from the plugin's
+    // `alloc(len) -> ptr`; the result packs the output's pointer (high half) and length
+    fn call(&self, module: &Module, export: &str, input: &[u8]) -> Result<Vec<u8>, String> {
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY_BYTES).instances(1).build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        store.set_fuel(FUEL).map_err(trap)?;
+        let instance = Instance::new(&mut store, module, &[]).map_err(trap)?;
+        let memory = instance.get_memory(&mut store, "memory").ok_or("the plugin exports no memory")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc").map_err(trap)?;
+        let func = instance.get_typed_func::<(i32, i32), i64>(&mut store, export).map_err(trap)?;
+        let len = i32::try_from(input.len()).map_err(|_| "the input is too large for the plugin")?;
+        let ptr = alloc.call(&mut store, len).map_err(trap)?;
+        memory.write(&mut store, ptr as u32 as usize, input).map_err(|e| format!("the plugin allocated outside its memory: {}", e))?;
+        let packed = func.call(&mut store, (ptr, len)).map_err(trap)? as u64;
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        if out_len > MAX_OUTPUT_BYTES {
+            return Err(format!("the plugin returned more than {} bytes", MAX_OUTPUT_BYTES));
+        }
+        let mut output = vec![0; out_len];
+        memory.read(&store, out_ptr, &mut output).map_err(|e| format!("the plugin returned memory it doesn't have: {}", e))?;
+        Ok(output)
+    }
+
+    // The request the plugin's `fetch` export makes of {"url"}; None without the export or
+    // when it returns nothing, for a plain GET
+    pub fn fetch_request_blocking(&self, name: &str, url: &str) -> Result<Option<FetchRequest>, String> {
+        let module = self.module(name)?;
+        if module.get_export("fetch").is_none() {
+            return Ok(None);
+        }
+        let output = self.call(&module, "fetch", &serde_json::to_vec(&serde_json::json!({ "url": url })).unwrap())?;
+        if output.is_empty() {
+            return Ok(None);
+        }
+        serde_json::from_slice(&output).map(Some).map_err(|e| format!("the plugin's fetch returned invalid JSON: {}", e))
+    }
+
+    // What the plugin's `extract` export makes of {"url", "body"}: JSON with the title, text
+    // and items an extraction script would return
+    pub fn extract_blocking(&self, name: &str, body: &str, url: &str) -> Result<ScriptOutput, String> {
+        let module = self.module(name)?;
+        let input = serde_json::to_vec(&serde_json::json!({ "url": url, "body": body })).unwrap();
+        let output: PluginOutput = serde_json::from_slice(&self.call(&module, "extract", &input)?)
+            .map_err(|e| format!("the plugin's extract returned invalid JSON: {}", e))?;
+        let base = Url::parse(url).ok();
+        let items: Vec<_> = output.items.into_iter()
+            .filter_map(|item| scripts::entry([item.guid, item.title, item.link, item.summary, item.published], base.as_ref()))
+            .take(MAX_ITEMS)
+            .collect();
+        let title = output.title.filter(|t| !t.trim().is_empty());
+        if title.is_none() && output.text.trim().is_empty() && items.is_empty() {
+            return Err("the plugin extracted nothing from the page".into());
+        }
+        Ok(ScriptOutput { title, text: output.text.trim().to_string(), items })
+    }
+
+    pub async fn fetch_request(self: &Arc<Self>, name: &str, url: &str) -> Result<Option<FetchRequest>, String> {
+        let (plugins, name, url) = (self.clone(), name.to_string(), url.to_string());
+        tokio::task::spawn_blocking(move || plugins.fetch_request_blocking(&name, &url)).await
+            .unwrap_or_else(|e| Err(format!("the plugin crashed: {}", e)))
+    }
+
+    pub async fn extract(self: &Arc<Self>, name: &str, body: &str, url: &str) -> Result<ScriptOutput, String> {
+        let (plugins, name, body, url) = (self.clone(), name.to_string(), body.to_string(), url.to_string());
+        tokio::task::spawn_blocking(move || plugins.extract_blocking(&name, &body, &url)).await
+            .unwrap_or_else(|e| Err(format!("the plugin crashed: {}", e)))
+    }
+
+    fn list(&self) -> Vec<PluginInfo> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut plugins: Vec<PluginInfo> = entries.flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let name = path.file_stem()?.to_str()?.to_string();
+                if path.extension()? != "wasm" || validate_name(&name).is_err() {
+                    return None;
+                }
+                let meta = entry.metadata().ok()?;
+                let module = self.module(&name);
+                Some(PluginInfo {
+                    size_bytes: meta.len(),
+                    modified: meta.modified().ok().map(DateTime::<Utc>::from),
+                    valid: module.is_ok(),
+                    fetch: module.as_ref().is_ok_and(|module| module.get_export("fetch").is_some()),
+                    error: module.err(),
+                    name,
+                })
+            })
+            .collect();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        plugins
+    }
+}
+
+// GET /api/admin/plugins: the plugins in plugin_dir, loading any that changed
+pub async fn list_plugins(data: web::Data<AppState>) -> impl Responder {
+    let plugins = data.config.plugins.clone();
+    match web::block(move || plugins.list()).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Listing plugins failed: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Echoes a fixed request and a fixed page extraction, or spins forever on "loop"
+    const PLUGIN: &str = r#"(module
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 1024))
+        (data (i32.const 0) "{\"url\":\"https://api.example/halts.json\",\"headers\":{\"Accept\":\"application/json\"}}")
+        (data (i32.const 256) "{\"title\":\"Halts\",\"items\":[{\"title\":\"XYZ halted\",\"link\":\"/halts/XYZ\",\"published\":\"2024-05-01\"},{}]}")
+        (func (export "alloc") (param $len i32) (result i32)
+            (global.get $next)
+            (global.set $next (i32.add (global.get $next) (local.get $len))))
+        (func (export "fetch") (param i32 i32) (result i64)
+            (i64.const 80))
+        (func (export "extract") (param $ptr i32) (param $len i32) (result i64)
+            ;; {"body":"loop",...} has an l at offset 9
+            (if (i32.eq (i32.load8_u (i32.add (local.get $ptr) (i32.const 9))) (i32.const 108))
+                (then (loop $spin (br $spin))))
+            (i64.or (i64.shl (i64.const 256) (i64.const 32)) (i64.const 98))))"#;
+
+    #[test]
+    fn runs_plugins_isolated_and_reloads_them() {
+        let dir = std::env::temp_dir().join(format!("plugins-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("halts.wasm"), PLUGIN).unwrap();
+        let plugins = Plugins::new(dir.to_str().unwrap());
+
+        let request = plugins.fetch_request_blocking("halts", "https://nasdaq.example/halts").unwrap().unwrap();
+        assert_eq!(request, FetchRequest {
+            url: "https://api.example/halts.json".into(),
+            headers: BTreeMap::from([("Accept".to_string(), "application/json".to_string())]),
+        });
+        let output = plugins.extract_blocking("halts", "{}", "https://nasdaq.example/market/").unwrap();
+        assert_eq!(output.title.as_deref(), Some("Halts"));
+        assert_eq!(output.items.len(), 1);
+        assert_eq!(output.items[0].guid, "https://nasdaq.example/halts/XYZ");
+        assert_eq!(plugins.extract_blocking("halts", "loop", "https://nasdaq.example/").unwrap_err(), "the plugin ran out of fuel");
+
+        // Replaced on disk: picked up by the next call
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(dir.join("halts.wasm"), r#"(module (import "wasi" "fd_write" (func)))"#).unwrap();
+        assert!(plugins.check("halts").unwrap_err().contains("plugins get no imports"));
+        assert!(plugins.check("../halts").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
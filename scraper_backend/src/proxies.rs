@@ -0,0 +1,346 @@
+use super::netguard::{self, NetworkGuard};
+use super::scraper;
+use super::{AppState, Site};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use tracing::warn;
+
+// A site proxy list of just this sends the site's fetches out directly, bypassing the pool
+pub const DIRECT: &str = "direct";
+const SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+// Failed fetches in a row before health-based rotation benches a proxy
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    // Every proxy in turn
+    RoundRobin,
+    // In turn, skipping proxies benched after repeated failures until their cooldown ends
+    Health,
+}
+
+// The proxies, proxy_rotation and proxy_cooldown_secs settings
+#[derive(Clone)]
+pub struct ProxySettings {
+    pub pool: Vec<String>,
+    pub rotation: Rotation,
+    pub cooldown_secs: i64,
+}
+
+impl ProxySettings {
+    pub fn from_yaml(cfg: &serde_yaml::Value) -> Result<Self, String> {
+        let pool: Vec<String> = cfg["proxies"].as_sequence().into_iter().flatten()
+            .filter_map(|v| v.as_str().map(|s| s.trim().to_string()))
+            .filter(|s| !s.is_empty())
+            .collect();
+        for url in &pool {
+            validate(url)?;
+        }
+        let rotation = match cfg["proxy_rotation"].as_str().unwrap_or("round_robin") {
+            "round_robin" => Rotation::RoundRobin,
+            "health" => Rotation::Health,
+            other => return Err(format!("Invalid proxy_rotation '{}': expected round_robin or health", other)),
+        };
+        Ok(ProxySettings { pool, rotation, cooldown_secs: cfg["proxy_cooldown_secs"].as_i64().unwrap_or(300) })
+    }
+}
+
+// Debug output hides proxy passwords, since the loaded config is logged at startup
+impl fmt::Debug for ProxySettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxySettings")
+            .field("pool", &self.pool.iter().map(|url| redact(url)).collect::<Vec<_>>())
+            .field("rotation", &self.rotation)
+            .field("cooldown_secs", &self.cooldown_secs)
+            .finish()
+    }
+}
+
+// The URL with its password masked, as shown in the API and the fetch log
+pub fn redact(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("***"));
+            // Url adds a path of "/" to bare proxy addresses
+            let redacted = parsed.to_string();
+            match url.ends_with('/') {
+                true => redacted,
+                false => redacted.trim_end_matches('/').to_string(),
+            }
+        },
+        _ => url.to_string(),
+    }
+}
+
+pub fn serialize_redacted<S: Serializer>(urls: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(urls.iter().map(|url| redact(url)))
+}
+
+fn validate(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid proxy '{}': {}", redact(url), e))?;
+    if !SCHEMES.contains(&parsed.scheme()) || parsed.host_str().is_none() {
+        return Err(format!("Invalid proxy '{}': expected http://, https://, socks5:// or socks5h:// with a host", redact(url)));
+    }
+    Ok(())
+}
+
+// A site's proxy list: empty uses the global pool, ["direct"] none at all
+pub fn validate_site_proxies(proxies: &[String]) -> Result<Vec<String>, String> {
+    let proxies: Vec<String> = proxies.iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+    if proxies.iter().any(|p| p == DIRECT) {
+        if proxies.len() > 1 {
+            return Err(format!("'{}' can't be combined with other proxies", DIRECT));
+        }
+        return Ok(proxies);
+    }
+    for url in &proxies {
+        validate(url)?;
+    }
+    Ok(proxies)
+}
+
+#[derive(Default)]
+struct Health {
+    requests: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    benched_until: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_used: Option<DateTime<Utc>>,
+}
+
+impl Health {
+    fn benched(&self, now: DateTime<Utc>) -> bool {
+        self.benched_until.is_some_and(|until| until > now)
+    }
+}
+
+// Entry of GET /api/admin/proxies
+#[derive(Serialize)]
+pub struct ProxyStatus {
+    pub url: String,
+    // In the global pool, as opposed to only in site proxy lists
+    pub global: bool,
+    pub requests: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub benched_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    // One client per proxy (None for direct fetches) and redirect limit, built on first
+    // use; clones share the connection pool
+    clients: HashMap<(Option<String>, usize), reqwest::Client>,
+    health: HashMap<String, Health>,
+    // Next position per pool, keyed by the pool's proxies
+    cursors: HashMap<Vec<String>, usize>,
+}
+
+// The client a fetch goes out through, and the proxy behind it (None for direct)
+pub struct Route {
+    pub client: reqwest::Client,
+    pub proxy: Option<String>,
+}
+
+// Hands site fetches a client for the next proxy of the site's pool and keeps track of
+// how each proxy is doing. Behind a proxy, host names are resolved by the proxy, so the
+// network guard only sees literal addresses and redirects there; proxy hosts themselves
+// go through the guard like any other host.
+pub struct Proxies {
+    settings: ProxySettings,
+    guard: NetworkGuard,
+    state: Mutex<PoolState>,
+}
+
+impl Proxies {
+    pub fn new(settings: ProxySettings, guard: NetworkGuard) -> Self {
+        Proxies { settings, guard, state: Mutex::new(PoolState::default()) }
+    }
+
+    fn client(&self, state: &mut PoolState, proxy: Option<&str>, max_redirects: usize) -> Result<reqwest::Client, String> {
+        let key = (proxy.map(String::from), max_redirects);
+        if let Some(client) = state.clients.get(&key) {
+            return Ok(client.clone());
+        }
+        let builder = self.guard.apply(scraper::http_client_builder(), max_redirects);
+        let client = match proxy {
+            Some(url) => reqwest::Proxy::all(url)
+                .and_then(|proxy| builder.proxy(proxy).build())
+                .map_err(|e| format!("proxy {} unusable: {}", redact(url), e))?,
+            None => builder.build().map_err(|e| format!("HTTP client unusable: {}", e))?,
+        };
+        state.clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    fn pool_for<'a>(&'a self, site: &'a Site) -> &'a [String] {
+        match site.proxies.as_slice() {
+            [only] if only == DIRECT => &[],
+            [] => &self.settings.pool,
+            own => own,
+        }
+    }
+
+    // The next proxy in turn. Health-based rotation passes over benched proxies; when
+    // all of them are benched, the one coming back soonest is used.
+    fn pick(&self, state: &mut PoolState, pool: &[String], now: DateTime<Utc>) -> String {
+        let start = state.cursors.get(pool).copied().unwrap_or(0);
+        let usable = |url: &String| {
+            self.settings.rotation == Rotation::RoundRobin || !state.health.get(url).is_some_and(|h| h.benched(now))
+        };
+        let index = (0..pool.len()).map(|i| (start + i) % pool.len()).find(|i| usable(&pool[*i]))
+            .unwrap_or_else(|| {
+                (0..pool.len()).min_by_key(|i| state.health.get(&pool[*i]).and_then(|h| h.benched_until)).unwrap_or(0)
+            });
+        state.cursors.insert(pool.to_vec(), index + 1);
+        pool[index].clone()
+    }
+
+    pub fn route(&self, site: &Site, now: DateTime<Utc>) -> Result<Route, String> {
+        let max_redirects = site.max_redirects.map_or(netguard::MAX_REDIRECTS, |n| n.max(0) as usize);
+        self.route_through(self.pool_for(site), max_redirects, now)
+    }
+
+    // Requests made while a site is added, such as a ticker, account or robots.txt lookup,
+    // go out like a fetch of a site without proxies of its own
+    pub fn lookup(&self, now: DateTime<Utc>) -> Result<Route, String> {
+        self.route_through(&self.settings.pool, netguard::MAX_REDIRECTS, now)
+    }
+
+    fn route_through(&self, pool: &[String], max_redirects: usize, now: DateTime<Utc>) -> Result<Route, String> {
+        let mut state = self.state.lock().unwrap();
+        if pool.is_empty() {
+            return Ok(Route { client: self.client(&mut state, None, max_redirects)?, proxy: None });
+        }
+        let url = self.pick(&mut state, pool, now);
+        let client = self.client(&mut state, Some(&url), max_redirects)?;
+        let health = state.health.entry(url.clone()).or_default();
+        health.requests += 1;
+        health.last_used = Some(now);
+        Ok(Route { client, proxy: Some(url) })
+    }
+
+    // How a fetch through the proxy went; Err for failures the proxy may be to blame for
+    pub fn report(&self, proxy: &str, result: Result<(), String>, now: DateTime<Utc>) {
+        let mut state = self.state.lock().unwrap();
+        let health = state.health.entry(proxy.to_string()).or_default();
+        match result {
+            Ok(()) => {
+                health.consecutive_failures = 0;
+                health.benched_until = None;
+            },
+            Err(e) => {
+                health.failures += 1;
+                health.consecutive_failures += 1;
+                health.last_error = Some(e);
+                if self.settings.rotation == Rotation::Health && health.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                    // Fetches sent before it was benched keep reporting back
+                    let already_benched = health.benched(now);
+                    health.benched_until = Some(now + Duration::seconds(self.settings.cooldown_secs.max(0)));
+                    if already_benched {
+                        return;
+                    }
+                    warn!(proxy = %redact(proxy), "Proxy failed {} times in a row, benched for {}s", MAX_CONSECUTIVE_FAILURES, self.settings.cooldown_secs);
+                }
+            },
+        }
+    }
+
+    // Global pool first, then proxies only used by sites
+    pub fn status(&self) -> Vec<ProxyStatus> {
+        let state = self.state.lock().unwrap();
+        let mut urls = self.settings.pool.clone();
+        let mut others: Vec<&String> = state.health.keys().filter(|url| !urls.contains(url)).collect();
+        others.sort();
+        urls.extend(others.into_iter().cloned());
+        urls.iter()
+            .map(|url| {
+                let health = state.health.get(url);
+                ProxyStatus {
+                    url: redact(url),
+                    global: self.settings.pool.contains(url),
+                    requests: health.map_or(0, |h| h.requests),
+                    failures: health.map_or(0, |h| h.failures),
+                    consecutive_failures: health.map_or(0, |h| h.consecutive_failures),
+                    benched_until: health.and_then(|h| h.benched_until),
+                    last_error: health.and_then(|h| h.last_error.clone()),
+                    last_used: health.and_then(|h| h.last_used),
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for Proxies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.settings.fmt(f)
+    }
+}
+
+// GET /api/admin/proxies
+pub async fn list_proxies(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(data.config.proxies.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(rotation: Rotation) -> Proxies {
+        let settings = ProxySettings {
+            pool: vec!["http://a:8080".into(), "socks5://user:secret@b:1080".into(), "http://c:8080".into()],
+            rotation,
+            cooldown_secs: 60,
+        };
+        Proxies::new(settings, NetworkGuard::new(true, &[]).unwrap())
+    }
+
+    fn next(proxies: &Proxies, site: &Site, now: DateTime<Utc>) -> Option<String> {
+        proxies.route(site, now).unwrap().proxy
+    }
+
+    #[test]
+    fn rotates_and_benches_failing_proxies() {
+        let now: DateTime<Utc> = "2024-05-01T12:00:00Z".parse().unwrap();
+        let site = Site::new("https://example.com", 60, "none");
+
+        let round_robin = pool(Rotation::RoundRobin);
+        let picked: Vec<_> = (0..4).filter_map(|_| next(&round_robin, &site, now)).collect();
+        assert_eq!(picked, ["http://a:8080", "socks5://user:secret@b:1080", "http://c:8080", "http://a:8080"]);
+
+        // Sites can bypass the pool or bring their own
+        let direct = Site { proxies: vec![DIRECT.into()], ..site.clone() };
+        assert_eq!(next(&round_robin, &direct, now), None);
+        let own = Site { proxies: vec!["http://d:3128".into()], ..site.clone() };
+        assert_eq!(next(&round_robin, &own, now).as_deref(), Some("http://d:3128"));
+
+        let health = pool(Rotation::Health);
+        for _ in 0..MAX_CONSECUTIVE_FAILURES {
+            health.report("socks5://user:secret@b:1080", Err("connection refused".into()), now);
+        }
+        let picked: Vec<_> = (0..3).filter_map(|_| next(&health, &site, now)).collect();
+        assert_eq!(picked, ["http://a:8080", "http://c:8080", "http://a:8080"]);
+        // Back in rotation once the cooldown is over; one more failure benches it again
+        let later = now + Duration::seconds(61);
+        assert_eq!(next(&health, &site, later).as_deref(), Some("socks5://user:secret@b:1080"));
+        health.report("socks5://user:secret@b:1080", Err("connection refused".into()), later);
+        assert_eq!(next(&health, &site, later).as_deref(), Some("http://c:8080"));
+        assert_eq!(next(&health, &site, later).as_deref(), Some("http://a:8080"));
+        assert_eq!(next(&health, &site, later).as_deref(), Some("http://c:8080"));
+
+        let status = health.status();
+        assert_eq!(status[1].url, "socks5://user:***@b:1080");
+        assert_eq!((status[1].failures, status[1].requests), (4, 1));
+        assert_eq!(format!("{:?}", health.settings).matches("secret").count(), 0);
+
+        assert!(validate_site_proxies(&["direct".into(), "http://a:1".into()]).is_err());
+        assert!(validate_site_proxies(&["ftp://a:1".into()]).is_err());
+    }
+}
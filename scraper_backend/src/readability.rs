@@ -0,0 +1,255 @@
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// Shorter paragraphs are captions, bylines and buttons rather than article text
+const MIN_PARAGRAPH_CHARS: usize = 25;
+// Less text than this and the page has no article worth the name
+const MIN_ARTICLE_CHARS: usize = 200;
+
+// Never part of an article, whatever their content
+const SKIPPED_TAGS: [&str; 12] = ["script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form", "iframe", "button", "svg"];
+// Tags starting a new paragraph of the extracted text
+const BLOCK_TAGS: [&str; 22] = [
+    "p", "div", "section", "article", "main", "h1", "h2", "h3", "h4", "h5", "h6", "li", "ul", "ol",
+    "pre", "blockquote", "table", "tr", "td", "th", "figure", "br",
+];
+
+// The main article of a page: its headline and its paragraphs, separated by blank lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Article {
+    pub title: Option<String>,
+    pub text: String,
+}
+
+struct Patterns {
+    unlikely: Regex,
+    maybe: Regex,
+    positive: Regex,
+    negative: Regex,
+}
+
+// Class and id patterns of arc90's readability
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        unlikely: Regex::new(r"(?i)banner|breadcrumbs|combx|comment|community|cookie|disqus|extra|foot|header|legends|menu|modal|newsletter|pager|popup|related|remark|replies|rss|share|shoutbox|sidebar|skyscraper|social|sponsor|subscribe|tweet|twitter|ad-break|agegate").unwrap(),
+        maybe: Regex::new(r"(?i)and|article|body|column|content|main|shadow").unwrap(),
+        positive: Regex::new(r"(?i)article|body|content|entry|hentry|h-entry|main|page|post|text|blog|story").unwrap(),
+        negative: Regex::new(r"(?i)hidden|banner|combx|comment|com-|contact|foot|footnote|masthead|media|meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|tags|tool|widget").unwrap(),
+    })
+}
+
+// Finds the article the way arc90's readability does: every paragraph scores its parent
+// and, at half weight, its grandparent by length and comma count. Containers start from a
+// weight for their tag and class/id names, and the final score is cut by the share of their
+// text that is links. The best container is kept with those of its siblings that score
+// well or read like paragraphs. None when there is no article of MIN_ARTICLE_CHARS.
+pub fn extract(html: &str) -> Option<Article> {
+    let document = Html::parse_document(html);
+    let paragraphs = Selector::parse("p, pre, td").unwrap();
+
+    let mut scores = HashMap::new();
+    let mut candidates = Vec::new();
+    for paragraph in document.select(&paragraphs) {
+        if paragraph.ancestors().filter_map(ElementRef::wrap).chain([paragraph]).any(is_unlikely) {
+            continue;
+        }
+        let text = collapse(paragraph.text());
+        let length = text.chars().count();
+        if length < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (length / 100).min(3) as f64;
+        for (level, ancestor) in paragraph.ancestors().filter_map(ElementRef::wrap).take(2).enumerate() {
+            let entry = scores.entry(ancestor.id()).or_insert_with(|| {
+                candidates.push(ancestor);
+                initial_score(ancestor)
+            });
+            *entry += score / (level + 1) as f64;
+        }
+    }
+
+    let final_score = |el: ElementRef| scores.get(&el.id()).map(|score| score * (1.0 - link_density(el)));
+    let (top, top_score) = candidates.iter()
+        .filter_map(|c| Some((*c, final_score(*c)?)))
+        .fold(None, |best: Option<(ElementRef, f64)>, (c, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((c, score)),
+        })?;
+
+    let threshold = (top_score * 0.2).max(10.0);
+    let blocks: Vec<ElementRef> = match top.parent().and_then(ElementRef::wrap) {
+        Some(parent) => parent.children().filter_map(ElementRef::wrap)
+            .filter(|sibling| *sibling == top || (!is_unlikely(*sibling) && keep_sibling(*sibling, final_score(*sibling), threshold)))
+            .collect(),
+        None => vec![top],
+    };
+
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for block in blocks {
+        push_text(block, &mut lines, &mut current);
+        flush(&mut lines, &mut current);
+    }
+    let text = lines.join("\n\n");
+    if text.chars().count() < MIN_ARTICLE_CHARS {
+        return None;
+    }
+    Some(Article { title: title(&document), text })
+}
+
+fn class_and_id(el: ElementRef) -> String {
+    format!("{} {}", el.value().attr("class").unwrap_or(""), el.value().id().unwrap_or(""))
+}
+
+fn is_unlikely(el: ElementRef) -> bool {
+    if SKIPPED_TAGS.contains(&el.value().name()) {
+        return true;
+    }
+    let names = class_and_id(el);
+    let patterns = patterns();
+    !matches!(el.value().name(), "body" | "article" | "main")
+        && patterns.unlikely.is_match(&names)
+        && !patterns.maybe.is_match(&names)
+}
+
+fn class_weight(el: ElementRef) -> f64 {
+    let patterns = patterns();
+    [el.value().attr("class"), el.value().id()].into_iter().flatten()
+        .map(|name| match (patterns.negative.is_match(name), patterns.positive.is_match(name)) {
+            (true, _) => -25.0,
+            (false, true) => 25.0,
+            (false, false) => 0.0,
+        })
+        .sum()
+}
+
+fn initial_score(el: ElementRef) -> f64 {
+    let tag = match el.value().name() {
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    tag + class_weight(el)
+}
+
+// Share of an element's text inside links
+fn link_density(el: ElementRef) -> f64 {
+    let links = Selector::parse("a").unwrap();
+    let length = collapse(el.text()).chars().count();
+    if length == 0 {
+        return 0.0;
+    }
+    let linked: usize = el.select(&links).map(|a| collapse(a.text()).chars().count()).sum();
+    linked as f64 / length as f64
+}
+
+fn keep_sibling(sibling: ElementRef, score: Option<f64>, threshold: f64) -> bool {
+    if score.is_some_and(|score| score >= threshold) {
+        return true;
+    }
+    if sibling.value().name() != "p" {
+        return false;
+    }
+    let text = collapse(sibling.text());
+    let length = text.chars().count();
+    let density = link_density(sibling);
+    (length > 80 && density < 0.25) || (length > 0 && density == 0.0 && text.ends_with(['.', '!', '?']))
+}
+
+fn push_text(el: ElementRef, lines: &mut Vec<String>, current: &mut String) {
+    for child in el.children() {
+        match child.value() {
+            Node::Text(text) => current.push_str(text),
+            Node::Element(_) => {
+                let Some(child) = ElementRef::wrap(child) else { continue };
+                if is_unlikely(child) {
+                    continue;
+                }
+                let block = BLOCK_TAGS.contains(&child.value().name());
+                if block {
+                    flush(lines, current);
+                }
+                push_text(child, lines, current);
+                if block {
+                    flush(lines, current);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+fn flush(lines: &mut Vec<String>, current: &mut String) {
+    let line = collapse(std::iter::once(current.as_str()));
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    current.clear();
+}
+
+fn collapse<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// A page's title as an article would have it, for pages too short to be one
+pub fn page_title(html: &str) -> Option<String> {
+    title(&Html::parse_document(html))
+}
+
+// og:title or <title> without the site name after a separator ("Rates held | Reuters"),
+// else the first <h1>
+fn title(document: &Html) -> Option<String> {
+    let og_title = Selector::parse(r#"meta[property="og:title"]"#).unwrap();
+    let title = Selector::parse("title").unwrap();
+    let h1 = Selector::parse("h1").unwrap();
+    let page_title = document.select(&og_title).next()
+        .and_then(|meta| meta.value().attr("content"))
+        .map(|content| collapse(std::iter::once(content)))
+        .or_else(|| document.select(&title).next().map(|t| collapse(t.text())))
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            let cut = [" | ", " - ", " – ", " — ", " :: "].iter().filter_map(|sep| t.rfind(sep)).max();
+            match cut.map(|at| t[..at].trim()) {
+                Some(head) if head.split_whitespace().count() >= 3 => head.to_string(),
+                _ => t,
+            }
+        });
+    page_title.or_else(|| document.select(&h1).next().map(|h| collapse(h.text())).filter(|h| !h.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_the_article_without_page_furniture() {
+        let html = r#"<html><head><title>Central bank holds rates steady - Daily Ledger</title></head><body>
+            <header><a href="/">Daily Ledger</a> <a href="/markets">Markets</a></header>
+            <div class="sidebar"><p>Most read: ten stocks to watch, and why analysts like them this year</p></div>
+            <div id="story" class="article-body">
+              <h1>Central bank holds rates steady</h1>
+              <p>The central bank left its benchmark rate unchanged on Wednesday, citing slowing inflation, a cooling labour market and uncertainty abroad.</p>
+              <p>Officials said further cuts would depend on incoming data, and that the committee was prepared to act if conditions changed.</p>
+              <script>track("article")</script>
+              <div class="share-tools"><a href="/share">Share this article on social media</a></div>
+            </div>
+            <p>Markets rose modestly after the announcement, with bond yields slipping slightly.</p>
+            <div class="comments"><p>Great article, thanks for writing it, I learned a lot from this one.</p></div>
+            <footer><p>Copyright Daily Ledger, all rights reserved, since the year 1901.</p></footer>
+            </body></html>"#;
+        let article = extract(html).unwrap();
+        assert_eq!(article.title.as_deref(), Some("Central bank holds rates steady"));
+        assert!(article.text.starts_with("Central bank holds rates steady\n\nThe central bank left its benchmark rate unchanged"));
+        assert!(article.text.contains("prepared to act if conditions changed.\n\nMarkets rose modestly"));
+        for furniture in ["Most read", "track(", "Share this", "Great article", "Copyright", "Daily Ledger"] {
+            assert!(!article.text.contains(furniture), "{}", furniture);
+        }
+
+        assert_eq!(extract("<html><body><p>Just a short note, nothing more.</p></body></html>"), None);
+    }
+}
@@ -0,0 +1,276 @@
+use super::scraper::{SchedulerEvent, SchedulerHandle};
+use super::storage::{SharedStorage, Storage, StorageResult};
+use super::{AppConfig, AppState, Site};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::time::{sleep, Duration};
+use tracing::{info, warn};
+
+// Change times remembered per site, far more than retention keeps snapshots
+pub const CHANGE_HISTORY_SIZE: i64 = 100;
+// Fewer changes than this say nothing about how often a site changes
+const MIN_CHANGES: usize = 3;
+
+// Bounds for recommended intervals, from the tune_* settings
+#[derive(Clone, Debug)]
+pub struct Tuning {
+    // Checks wanted between two changes at the site's fastest observed pace
+    pub samples_per_change: i64,
+    pub min_interval_secs: i64,
+    pub max_interval_secs: i64,
+}
+
+// Range the adaptive style keeps its learned intervals in
+#[derive(Clone, Debug)]
+pub struct AdaptiveBounds {
+    pub min_interval_secs: i64,
+    pub max_interval_secs: i64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Oversampled,
+    Undersampled,
+    Ok,
+    InsufficientHistory,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Recommendation {
+    site_id: i64,
+    url: String,
+    interval_secs: i64,
+    changes_observed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shortest_gap_secs: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    median_gap_secs: Option<i64>,
+    verdict: Verdict,
+    // Only set when the verdict asks for a different interval
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recommended_interval_secs: Option<i64>,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct AppliedInterval {
+    site_id: i64,
+    url: String,
+    before: i64,
+    after: i64,
+}
+
+// "day", "3 hours", "90 seconds": the largest whole unit
+fn describe_period(secs: i64) -> String {
+    let (count, unit) = [(86400, "day"), (3600, "hour"), (60, "minute")]
+        .into_iter()
+        .find(|(unit_secs, _)| secs >= *unit_secs)
+        .map(|(unit_secs, unit)| (secs / unit_secs, unit))
+        .unwrap_or((secs, "second"));
+    match count {
+        1 => unit.to_string(),
+        _ => format!("{} {}s", count, unit),
+    }
+}
+
+// Gaps between consecutive changes (oldest first), shortest first
+fn sorted_gaps(changes: &[DateTime<Utc>]) -> Vec<i64> {
+    let mut gaps: Vec<i64> = changes.windows(2).map(|w| (w[1] - w[0]).num_seconds().max(0)).collect();
+    gaps.sort_unstable();
+    gaps
+}
+
+// The adaptive style's next interval after a check: halved when it saw a change and a
+// quarter longer when it didn't. Once enough changes are recorded, it also stays within
+// a factor of two of the median gap between them over samples_per_change, so one quiet
+// or busy spell doesn't undo what the history says.
+pub fn adapt_interval(current: i64, changed: bool, changes: &[DateTime<Utc>], samples_per_change: i64, bounds: &AdaptiveBounds) -> i64 {
+    let current = current.max(1);
+    let mut next = match changed {
+        true => current / 2,
+        false => current + (current / 4).max(1),
+    };
+    let gaps = sorted_gaps(changes);
+    if changes.len() >= MIN_CHANGES {
+        let target = (gaps[gaps.len() / 2] / samples_per_change.max(1)).max(1);
+        next = next.clamp(target / 2, target * 2);
+    }
+    let min = bounds.min_interval_secs.max(1);
+    next.clamp(min, bounds.max_interval_secs.max(min))
+}
+
+// Compares the gaps between a site's recorded changes (oldest first) with its interval.
+// The shortest gap is how fast the site can change; checking much more often than
+// samples_per_change times within it is wasted work. A median gap near the interval
+// means most checks see a change, so changes in between are probably missed.
+pub fn recommend(site: &Site, changes: &[DateTime<Utc>], tuning: &Tuning) -> Recommendation {
+    let interval = site.interval_secs.max(1);
+    let gaps = sorted_gaps(changes);
+    let shortest = gaps.first().copied();
+    let median = gaps.get(gaps.len() / 2).copied();
+
+    let mut recommendation = Recommendation {
+        site_id: site.id,
+        url: site.url.clone(),
+        interval_secs: interval,
+        changes_observed: changes.len(),
+        shortest_gap_secs: shortest,
+        median_gap_secs: median,
+        verdict: Verdict::Ok,
+        recommended_interval_secs: None,
+        message: String::new(),
+    };
+    if site.style == "adaptive" {
+        let learned = site.adaptive_interval_secs.unwrap_or(interval);
+        recommendation.message = format!("adaptive style tunes its own interval, currently {}s", learned);
+        return recommendation;
+    }
+    let (shortest, median) = match (shortest, median) {
+        (Some(shortest), Some(median)) if changes.len() >= MIN_CHANGES => (shortest, median),
+        _ => {
+            recommendation.verdict = Verdict::InsufficientHistory;
+            recommendation.message = format!("{} change(s) recorded; at least {} are needed", changes.len(), MIN_CHANGES);
+            return recommendation;
+        },
+    };
+
+    let bounded = |secs: i64| secs.clamp(tuning.min_interval_secs.max(1), tuning.max_interval_secs.max(1));
+    let faster = bounded(interval / 2);
+    let slower = bounded(shortest / tuning.samples_per_change.max(1));
+    if median * 2 <= interval * 3 && faster < interval {
+        recommendation.verdict = Verdict::Undersampled;
+        recommendation.recommended_interval_secs = Some(faster);
+        recommendation.message = format!(
+            "site changes on most checks (median gap {}s); interval {}s probably misses changes",
+            median, interval
+        );
+    } else if slower >= interval * 2 {
+        recommendation.verdict = Verdict::Oversampled;
+        recommendation.recommended_interval_secs = Some(slower);
+        recommendation.message = format!(
+            "site changes at most every {}; interval {}s is {}x oversampled",
+            describe_period(shortest), interval, shortest / interval
+        );
+    } else {
+        recommendation.message = format!("interval {}s suits changes at most every {}", interval, describe_period(shortest));
+    }
+    recommendation
+}
+
+async fn recommendations(storage: &dyn Storage, tuning: &Tuning) -> StorageResult<Vec<Recommendation>> {
+    let mut recommendations = Vec::new();
+    for site in storage.list_sites().await? {
+        let changes = storage.change_times(site.id).await?;
+        recommendations.push(recommend(&site, &changes, tuning));
+    }
+    Ok(recommendations)
+}
+
+// Sets every recommended interval and reschedules the affected sites
+async fn apply_recommendations(storage: &dyn Storage, scheduler: &SchedulerHandle, tuning: &Tuning) -> StorageResult<Vec<AppliedInterval>> {
+    let mut applied = Vec::new();
+    for recommendation in recommendations(storage, tuning).await? {
+        let Some(after) = recommendation.recommended_interval_secs else {
+            continue;
+        };
+        storage.set_interval(recommendation.site_id, after).await?;
+        let _ = scheduler.send(SchedulerEvent::SiteUpdated(recommendation.site_id));
+        info!("Interval of {} tuned from {}s to {}s: {}", recommendation.url, recommendation.interval_secs, after, recommendation.message);
+        applied.push(AppliedInterval {
+            site_id: recommendation.site_id,
+            url: recommendation.url,
+            before: recommendation.interval_secs,
+            after,
+        });
+    }
+    Ok(applied)
+}
+
+// GET /api/recommendations: per-site interval advice from recorded change times
+pub async fn get_recommendations(data: web::Data<AppState>) -> impl Responder {
+    match recommendations(data.storage.as_ref(), &data.config.tuning).await {
+        Ok(recommendations) => HttpResponse::Ok().json(recommendations),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// POST /api/recommendations/apply: set every recommended interval now
+pub async fn apply(data: web::Data<AppState>) -> impl Responder {
+    match apply_recommendations(data.storage.as_ref(), &data.scheduler, &data.config.tuning).await {
+        Ok(applied) => HttpResponse::Ok().json(applied),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// Applies recommendations every tune_auto_apply_interval_secs (0 disables)
+pub async fn run_auto_tune(storage: SharedStorage, scheduler: SchedulerHandle, config: AppConfig) {
+    if config.tune_auto_apply_interval_secs <= 0 {
+        return;
+    }
+    let interval = Duration::from_secs(config.tune_auto_apply_interval_secs as u64);
+    loop {
+        sleep(interval).await;
+        if let Err(e) = apply_recommendations(storage.as_ref(), &scheduler, &config.tuning).await {
+            warn!("Interval auto-tuning failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(gaps: &[i64]) -> Vec<DateTime<Utc>> {
+        let start = Utc::now();
+        let mut at = start;
+        std::iter::once(start)
+            .chain(gaps.iter().map(|gap| {
+                at += chrono::Duration::seconds(*gap);
+                at
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn recommends_intervals_from_change_gaps() {
+        let tuning = Tuning { samples_per_change: 4, min_interval_secs: 30, max_interval_secs: 86400 };
+        let site = Site::new("https://a.example", 1100, "random");
+
+        let daily = recommend(&site, &changes(&[86400, 90000, 172800]), &tuning);
+        assert_eq!(daily.verdict, Verdict::Oversampled);
+        assert_eq!(daily.recommended_interval_secs, Some(21600));
+        assert_eq!(daily.message, "site changes at most every day; interval 1100s is 78x oversampled");
+
+        let busy = recommend(&site, &changes(&[1100, 1100, 2200, 1100]), &tuning);
+        assert_eq!(busy.verdict, Verdict::Undersampled);
+        assert_eq!(busy.recommended_interval_secs, Some(550));
+
+        let fine = recommend(&site, &changes(&[4000, 5000]), &tuning);
+        assert_eq!(fine.verdict, Verdict::Ok);
+        assert_eq!(fine.recommended_interval_secs, None);
+
+        assert_eq!(recommend(&site, &changes(&[600]), &tuning).verdict, Verdict::InsufficientHistory);
+        // Already at the lower bound
+        let fast = Site::new("https://b.example", 30, "random");
+        assert_eq!(recommend(&fast, &changes(&[30, 30, 30]), &tuning).verdict, Verdict::Ok);
+        let adaptive = Site::new("https://c.example", 1100, "adaptive");
+        assert_eq!(recommend(&adaptive, &changes(&[86400, 90000, 172800]), &tuning).recommended_interval_secs, None);
+    }
+
+    #[test]
+    fn adaptive_intervals_follow_changes_within_bounds() {
+        let bounds = AdaptiveBounds { min_interval_secs: 60, max_interval_secs: 7200 };
+        assert_eq!(adapt_interval(1100, true, &[], 4, &bounds), 550);
+        assert_eq!(adapt_interval(1100, false, &[], 4, &bounds), 1375);
+        assert_eq!(adapt_interval(100, true, &[], 4, &bounds), 60);
+        assert_eq!(adapt_interval(7000, false, &[], 4, &bounds), 7200);
+
+        // Twice a day: the median gap of 43200s over 4 samples anchors it near 10800s
+        let twice_daily = changes(&[43200, 43200, 43200]);
+        assert_eq!(adapt_interval(300, false, &twice_daily, 4, &bounds), 5400);
+        let wide = AdaptiveBounds { max_interval_secs: 86400, ..bounds };
+        assert_eq!(adapt_interval(30000, false, &twice_daily, 4, &wide), 21600);
+    }
+}
@@ -0,0 +1,267 @@
+use super::feeds::FeedEntry;
+use super::plugins::FetchRequest;
+use super::truncate::{self, WORD_BREAKS};
+use super::Site;
+use chrono::DateTime;
+use reqwest::Url;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+// Sites with this extraction_mode poll a Reddit listing's JSON and report each new post or
+// comment. reddit.com user and subreddit pages and feeds are switched to it when added.
+pub const EXTRACTION_MODE: &str = "reddit";
+
+const PUBLIC_URL: &str = "https://www.reddit.com";
+// Listings return 25 children by default and 100 at most
+const LISTING_LIMIT: &str = "100";
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(10);
+// Tokens are renewed this long before Reddit says they expire
+const TOKEN_MARGIN: Duration = Duration::from_secs(60);
+const MAX_SUMMARY_CHARS: usize = 500;
+
+// A Reddit app's credentials: a script app logs in as its user, other apps get an app-only
+// token. Debug output hides the secrets, since the loaded config is logged at startup.
+#[derive(Clone)]
+pub struct Credentials {
+    pub client_id: String,
+    pub client_secret: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials").field("client_id", &self.client_id).field("username", &self.username).finish_non_exhaustive()
+    }
+}
+
+// Where Reddit is reached and how its sites identify themselves. Without credentials
+// listings are read from the public JSON endpoints; with them from the OAuth API, which
+// allows more requests.
+#[derive(Clone)]
+pub struct Reddit {
+    // Reddit asks for "<platform>:<app ID>:<version> (by /u/<username>)" and throttles
+    // generic agents
+    pub user_agent: String,
+    pub credentials: Option<Credentials>,
+    pub token_url: String,
+    pub oauth_url: String,
+    // The current OAuth token and when to renew it, shared by every clone
+    token: Arc<Mutex<Option<(String, Instant)>>>,
+}
+
+impl fmt::Debug for Reddit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reddit")
+            .field("user_agent", &self.user_agent)
+            .field("credentials", &self.credentials)
+            .field("token_url", &self.token_url)
+            .field("oauth_url", &self.oauth_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Reddit {
+    pub fn new(user_agent: String, credentials: Option<Credentials>, token_url: String, oauth_url: String) -> Self {
+        Reddit { user_agent, credentials, token_url, oauth_url, token: Arc::default() }
+    }
+
+    async fn token(&self, client: &reqwest::Client, credentials: &Credentials) -> Result<String, String> {
+        let mut token = self.token.lock().await;
+        if let Some((access_token, renew_at)) = token.as_ref() {
+            if Instant::now() < *renew_at {
+                return Ok(access_token.clone());
+            }
+        }
+        let form = match (&credentials.username, &credentials.password) {
+            (Some(username), Some(password)) => vec![("grant_type", "password"), ("username", username.as_str()), ("password", password.as_str())],
+            _ => vec![("grant_type", "client_credentials")],
+        };
+        let response: Value = client
+            .post(&self.token_url)
+            .basic_auth(&credentials.client_id, Some(&credentials.client_secret))
+            .header(reqwest::header::USER_AGENT, &self.user_agent)
+            .form(&form)
+            .timeout(TOKEN_TIMEOUT)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| format!("Getting a Reddit OAuth token failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Getting a Reddit OAuth token failed: {}", e))?;
+        let access_token = response["access_token"].as_str()
+            .ok_or_else(|| format!("Reddit refused an OAuth token: {}", response["error"].as_str().unwrap_or("no access_token")))?
+            .to_string();
+        let lifetime = Duration::from_secs(response["expires_in"].as_u64().unwrap_or(3600));
+        *token = Some((access_token.clone(), Instant::now() + lifetime.saturating_sub(TOKEN_MARGIN)));
+        Ok(access_token)
+    }
+
+    // Reddit sites are fetched with the configured User-Agent, and through the OAuth API
+    // with a bearer token when credentials are configured, asked for through the site's
+    // route
+    pub async fn request(&self, site: &Site, client: &reqwest::Client) -> Result<Option<FetchRequest>, String> {
+        if site.extraction_mode.as_deref() != Some(EXTRACTION_MODE) && !is_api_url(&site.url) {
+            return Ok(None);
+        }
+        let mut headers = BTreeMap::from([("User-Agent".to_string(), self.user_agent.clone())]);
+        let Some(credentials) = &self.credentials else {
+            return Ok(Some(FetchRequest { url: site.url.clone(), headers }));
+        };
+        let url = Url::parse(&site.url).map_err(|e| format!("Invalid URL '{}': {}", site.url, e))?;
+        let mut oauth = Url::parse(&self.oauth_url).map_err(|e| format!("Invalid reddit_oauth_url '{}': {}", self.oauth_url, e))?;
+        oauth.set_path(&format!("{}{}", oauth.path().trim_end_matches('/'), url.path()));
+        oauth.set_query(url.query());
+        headers.insert("Authorization".to_string(), format!("Bearer {}", self.token(client, credentials).await?));
+        Ok(Some(FetchRequest { url: oauth.to_string(), headers }))
+    }
+}
+
+fn is_reddit(url: &Url) -> bool {
+    url.host_str().is_some_and(|host| host == "reddit.com" || host.ends_with(".reddit.com"))
+}
+
+// A listing's JSON endpoint on reddit.com, as resolve gives them
+fn is_api_url(source: &str) -> bool {
+    Url::parse(source).is_ok_and(|url| is_reddit(&url) && url.path().ends_with(".json"))
+}
+
+// The listing a reddit.com page, feed or JSON endpoint shows, as ("user", name, kind) or
+// ("r", name, kind)
+fn listing(path: &str) -> Option<(&'static str, String, &'static str)> {
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".json").or_else(|| path.strip_suffix(".rss")).unwrap_or(path);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let valid = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    match segments.as_slice() {
+        ["user" | "u", name, rest @ ..] if valid(name) => {
+            let kind = match rest {
+                [] | ["overview"] => "overview",
+                ["submitted"] => "submitted",
+                ["comments"] => "comments",
+                _ => return None,
+            };
+            Some(("user", name.to_string(), kind))
+        },
+        ["r", name] | ["r", name, "new"] if valid(name) => Some(("r", name.to_string(), "new")),
+        _ => None,
+    }
+}
+
+// reddit.com user and subreddit pages and feeds, which are better read through the API
+pub fn is_listing(source: &str) -> bool {
+    Url::parse(source.trim()).is_ok_and(|url| is_reddit(&url) && listing(url.path()).is_some())
+}
+
+// The JSON endpoint a Reddit site polls, from a reddit.com listing URL or a short name:
+// "u/name" (posts and comments), "u/name/submitted", "u/name/comments" or "r/name" (new
+// posts)
+pub fn resolve(source: &str) -> Result<String, String> {
+    let source = source.trim();
+    let path = match Url::parse(source) {
+        Ok(url) if is_reddit(&url) => url.path().to_string(),
+        Ok(_) => return Err(format!("'{}' is not a reddit.com URL", source)),
+        Err(_) => source.to_string(),
+    };
+    let (prefix, name, kind) = listing(&path).ok_or_else(|| format!("'{}' is neither a Reddit user nor a subreddit listing", source))?;
+    let mut url = Url::parse(&format!("{}/{}/{}/{}.json", PUBLIC_URL, prefix, name, kind)).unwrap();
+    url.query_pairs_mut().append_pair("limit", LISTING_LIMIT).append_pair("raw_json", "1");
+    Ok(url.to_string())
+}
+
+fn text(value: &Value) -> Option<String> {
+    value.as_str().map(str::trim).filter(|s| !s.is_empty()).map(String::from)
+}
+
+// Whether a body is a listing from Reddit's JSON API
+pub fn is_listing_response(body: &str) -> bool {
+    body.trim_start().starts_with('{') && serde_json::from_str::<Value>(body).is_ok_and(|json| json["kind"] == "Listing")
+}
+
+// The posts and comments of a listing, newest first as Reddit lists them; None if the body
+// is not one
+pub fn parse(body: &str) -> Option<Vec<FeedEntry>> {
+    let json: Value = serde_json::from_str(body).ok()?;
+    if json["kind"] != "Listing" {
+        return None;
+    }
+    Some(json["data"]["children"].as_array()?.iter().filter_map(child).collect())
+}
+
+// A post (t3) or comment (t1): its fullname is the GUID; its score, comment count,
+// subreddit and author lead the summary
+fn child(child: &Value) -> Option<FeedEntry> {
+    let data = &child["data"];
+    let guid = text(&data["name"])?;
+    let (title, body) = match child["kind"].as_str()? {
+        "t3" => (text(&data["title"])?, text(&data["selftext"])),
+        "t1" => (format!("Comment on {}", text(&data["link_title"]).unwrap_or_else(|| "a post".to_string())), text(&data["body"])),
+        _ => return None,
+    };
+    let score = data["score"].as_i64().unwrap_or(0);
+    let mut details = vec![format!("{} point{}", score, if score.abs() == 1 { "" } else { "s" })];
+    if let Some(comments) = data["num_comments"].as_i64().filter(|_| child["kind"] == "t3") {
+        details.push(format!("{} comment{}", comments, if comments == 1 { "" } else { "s" }));
+    }
+    details.extend(text(&data["subreddit_name_prefixed"]).map(|subreddit| format!("in {}", subreddit)));
+    details.extend(text(&data["author"]).map(|author| format!("by u/{}", author)));
+    let mut summary = details.join(", ");
+    if let Some(body) = body {
+        summary.push_str("\n\n");
+        summary.push_str(&truncate::preview(&body, MAX_SUMMARY_CHARS, &[WORD_BREAKS]));
+    }
+    Some(FeedEntry {
+        guid,
+        title: Some(title),
+        link: text(&data["permalink"]).map(|permalink| format!("{}{}", PUBLIC_URL, permalink)),
+        summary: Some(summary),
+        published: data["created_utc"].as_f64().and_then(|created| DateTime::from_timestamp(created as i64, 0)),
+    })
+}
+
+// Headed "👽 2 Reddit posts", one post or comment per line
+pub fn preview(entries: &[FeedEntry], max_length: usize) -> String {
+    let lines = entries.iter().map(|entry| entry.title.as_deref().unwrap_or(&entry.guid)).collect::<Vec<_>>().join("\n");
+    let heading = format!("👽 {} Reddit post{}", entries.len(), if entries.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&lines, max_length, &[WORD_BREAKS]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_listings_and_reads_posts_and_comments() {
+        assert_eq!(resolve("u/DeepFuckingValue/submitted").unwrap(), "https://www.reddit.com/user/DeepFuckingValue/submitted.json?limit=100&raw_json=1");
+        assert_eq!(resolve("https://www.reddit.com/user/DeepFuckingValue/comments.rss").unwrap(), "https://www.reddit.com/user/DeepFuckingValue/comments.json?limit=100&raw_json=1");
+        assert_eq!(resolve("https://old.reddit.com/r/wallstreetbets/").unwrap(), "https://www.reddit.com/r/wallstreetbets/new.json?limit=100&raw_json=1");
+        assert_eq!(resolve("https://www.reddit.com/user/someone/.rss").unwrap(), "https://www.reddit.com/user/someone/overview.json?limit=100&raw_json=1");
+        assert!(resolve("https://www.reddit.com/r/wallstreetbets/comments/abc/title/").is_err());
+        assert!(resolve("https://example.com/r/stocks").is_err());
+        assert!(is_listing("https://www.reddit.com/r/stocks/new/"));
+        assert!(is_api_url(&resolve("r/stocks").unwrap()));
+
+        let listing = r#"{"kind": "Listing", "data": {"after": null, "children": [
+            {"kind": "t3", "data": {"name": "t3_1g3abc", "title": "GME YOLO update", "score": 1, "num_comments": 4200,
+             "subreddit_name_prefixed": "r/wallstreetbets", "author": "DeepFuckingValue", "selftext": "",
+             "permalink": "/r/wallstreetbets/comments/1g3abc/gme_yolo_update/", "created_utc": 1760450400.0}},
+            {"kind": "t1", "data": {"name": "t1_lqz9x", "link_title": "GME YOLO update", "body": "Still holding.", "score": 812,
+             "subreddit_name_prefixed": "r/wallstreetbets", "author": "DeepFuckingValue",
+             "permalink": "/r/wallstreetbets/comments/1g3abc/gme_yolo_update/lqz9x/", "created_utc": 1760450460}}
+        ]}}"#;
+        assert!(is_listing_response(listing));
+        let entries = parse(listing).unwrap();
+        assert_eq!(entries[0].guid, "t3_1g3abc");
+        assert_eq!(entries[0].summary.as_deref(), Some("1 point, 4200 comments, in r/wallstreetbets, by u/DeepFuckingValue"));
+        assert_eq!(entries[0].link.as_deref(), Some("https://www.reddit.com/r/wallstreetbets/comments/1g3abc/gme_yolo_update/"));
+        assert_eq!(entries[0].published.map(|d| d.to_rfc3339()).as_deref(), Some("2025-10-14T14:00:00+00:00"));
+        assert_eq!(entries[1].title.as_deref(), Some("Comment on GME YOLO update"));
+        assert_eq!(entries[1].summary.as_deref(), Some("812 points, in r/wallstreetbets, by u/DeepFuckingValue\n\nStill holding."));
+        assert!(parse(r#"{"kind": "t2", "data": {}}"#).is_none());
+    }
+}
@@ -0,0 +1,154 @@
+use super::plugins::Plugins;
+use super::scraper::{article_hash, content_hash, parse_entries, readable_article, PIPELINE_VERSION};
+use super::storage::{Storage, StorageError, StorageResult};
+use super::{links, pdf, scripts, AppState, Site};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+#[derive(Deserialize)]
+pub struct ReprocessQuery {
+    site_id: Option<i64>,
+    // Only snapshots fetched at or after this time
+    since: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct ReprocessSummary {
+    pipeline_version: i64,
+    sites: usize,
+    snapshots: usize,
+    // Snapshots whose hash differs under the current pipeline
+    rehashed: usize,
+    // Feed hashes follow the entry history rather than the cleaning pipeline
+    skipped_feeds: usize,
+}
+
+// POST /api/admin/reprocess: recompute change-detection hashes of stored snapshots with
+// the current pipeline. Nothing is broadcast, so no alerts or webhooks fire. Previews are
+// built from the stored content when served and need no rebuild.
+pub async fn reprocess(data: web::Data<AppState>, query: web::Query<ReprocessQuery>) -> impl Responder {
+    let sites = match query.site_id {
+        Some(id) => match data.storage.get_site(id).await {
+            Ok(Some(site)) => vec![site],
+            Ok(None) => return HttpResponse::NotFound().body(format!("Site with ID {} not found", id)),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+        },
+        None => match data.storage.list_sites().await {
+            Ok(sites) => sites,
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+        },
+    };
+
+    let mut summary = ReprocessSummary { pipeline_version: PIPELINE_VERSION, ..Default::default() };
+    for site in &sites {
+        if let Err(e) = reprocess_site(data.storage.as_ref(), &data.config.plugins, site, query.since, data.config.hash_fold_case, &mut summary).await {
+            return HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    }
+    info!(
+        "Reprocessed {} snapshot(s) of {} site(s) with pipeline v{}: {} rehashed",
+        summary.snapshots, summary.sites, PIPELINE_VERSION, summary.rehashed
+    );
+    HttpResponse::Ok().json(summary)
+}
+
+async fn reprocess_site(
+    storage: &dyn Storage,
+    plugins: &Arc<Plugins>,
+    site: &Site,
+    since: Option<DateTime<Utc>>,
+    fold_case: bool,
+    summary: &mut ReprocessSummary,
+) -> StorageResult<()> {
+    let snapshots = storage.snapshots(site.id, since).await?;
+    summary.sites += 1;
+    summary.snapshots += snapshots.len();
+
+    // Parsing and cleaning every stored page is CPU-bound
+    let selector = site.selector.clone();
+    let ignore = site.ignore_patterns.clone();
+    let mode = site.extraction_mode.clone();
+    let (plugin, script, url) = (site.plugin.clone(), site.extraction_script.clone(), site.url.clone());
+    let plugins = plugins.clone();
+    let hashes = web::block(move || {
+        snapshots.into_iter()
+            .map(|snapshot| {
+                let custom = match (&plugin, &script) {
+                    (Some(name), _) => Some(plugins.extract_blocking(name, &snapshot.content, &url)),
+                    (None, Some(script)) => Some(scripts::extract(script, &snapshot.content, &url)),
+                    (None, None) => None,
+                };
+                let hash = match (custom, parse_entries(&snapshot.content, mode.as_deref())) {
+                    // A page the plugin or script fails on keeps the hash it was stored with
+                    (Some(output), _) => output.ok().map(|output| article_hash(&output.article(), fold_case)),
+                    (None, _) if snapshot.pdf => Some(article_hash(&pdf::article(&snapshot.content, &ignore), fold_case)),
+                    // Feeds and link sets keep the hash of the fetch that last changed them
+                    (None, Some(_)) => None,
+                    (None, None) if mode.as_deref() == Some(links::EXTRACTION_MODE) => None,
+                    (None, None) => Some(match readable_article(&snapshot.content, mode.as_deref(), &ignore) {
+                        Some(article) => article_hash(&article, fold_case),
+                        None => content_hash(&snapshot.content, selector.as_deref(), &ignore, fold_case),
+                    }),
+                };
+                (snapshot, hash)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| StorageError::new(e.to_string()))?;
+
+    for (snapshot, hash) in hashes {
+        let Some(hash) = hash else {
+            summary.skipped_feeds += 1;
+            continue;
+        };
+        if hash != snapshot.diff_hash {
+            summary.rehashed += 1;
+        } else if snapshot.pipeline_version == Some(PIPELINE_VERSION) {
+            continue;
+        }
+        storage.set_snapshot_hash(snapshot.id, &hash, PIPELINE_VERSION).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn rehashes_outdated_snapshots_only() {
+        let storage = MemoryStorage::new();
+        let plugins = Arc::new(Plugins::new("plugins"));
+        let site = Site::new("https://a.example", 60, "random");
+        let site_id = storage.insert_site(&site).await.unwrap();
+        let site = Site { id: site_id, ..site };
+        let now = Utc::now();
+        let page = "<html><body><p>Hello</p></body></html>";
+        let feed = r#"<?xml version="1.0"?><rss version="2.0"><channel><title>T</title>
+            <item><guid>1</guid><title>One</title></item></channel></rss>"#;
+
+        let stale = storage.insert_update(site_id, now - chrono::Duration::hours(2), "old-pipeline-hash", page).await.unwrap();
+        let current = content_hash(page, None, &[], false);
+        storage.insert_update(site_id, now - chrono::Duration::hours(1), &current, page).await.unwrap();
+        storage.insert_update(site_id, now, "feed-hash", feed).await.unwrap();
+
+        let mut summary = ReprocessSummary::default();
+        reprocess_site(&storage, &plugins, &site, None, false, &mut summary).await.unwrap();
+        assert_eq!(summary, ReprocessSummary { pipeline_version: 0, sites: 1, snapshots: 3, rehashed: 1, skipped_feeds: 1 });
+
+        let snapshots = storage.snapshots(site_id, None).await.unwrap();
+        assert_eq!(snapshots.iter().find(|s| s.id == stale).unwrap().diff_hash, current);
+        assert_eq!(snapshots[2].diff_hash, "feed-hash");
+
+        // `since` limits the run to newer snapshots
+        let mut summary = ReprocessSummary::default();
+        reprocess_site(&storage, &plugins, &site, Some(now - chrono::Duration::minutes(90)), false, &mut summary).await.unwrap();
+        assert_eq!(summary.snapshots, 2);
+        assert_eq!(summary.rehashed, 0);
+    }
+}
@@ -0,0 +1,81 @@
+use super::storage::{SharedStorage, Storage, StorageResult};
+use super::{AppConfig, Site};
+use chrono::{DateTime, Duration, Utc};
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+// Trims stored history on a timer rather than on every fetch. Each site keeps its newest
+// retention_count updates (default update_cache_size) and drops those older than
+// retention_days (default update_retention_days); its latest update always survives
+// because the next check compares against it.
+pub async fn run_retention(storage: SharedStorage, config: AppConfig) {
+    let interval = std::time::Duration::from_secs(config.retention_interval_secs.max(1) as u64);
+
+    loop {
+        sleep(interval).await;
+
+        let sites = match storage.list_sites().await {
+            Ok(sites) => sites,
+            Err(e) => {
+                warn!("Retention could not load sites: {}", e);
+                continue;
+            }
+        };
+        let now = Utc::now();
+        let mut deleted = 0;
+        for site in &sites {
+            match prune_site(storage.as_ref(), site, now, config.update_cache_size, config.update_retention_days).await {
+                Ok(count) => deleted += count,
+                Err(e) => warn!("Retention failed for {}: {}", site.url, e),
+            }
+        }
+        if deleted > 0 {
+            info!("Retention removed {} stored update(s) across {} site(s)", deleted, sites.len());
+        }
+
+        // Bodies are shared between updates, so they go once nothing references them,
+        // whether pruned here or deleted with their site
+        match storage.collect_orphaned_contents().await {
+            Ok(0) => {},
+            Ok(count) => info!("Retention removed {} stored content blob(s) no update references", count),
+            Err(e) => warn!("Retention could not remove unreferenced contents: {}", e),
+        }
+    }
+}
+
+async fn prune_site(storage: &dyn Storage, site: &Site, now: DateTime<Utc>, default_count: i64, default_days: i64) -> StorageResult<u64> {
+    let keep = site.retention_count.unwrap_or(default_count);
+    let mut deleted = storage.prune_updates(site.id, keep.max(1)).await?;
+
+    let days = site.retention_days.unwrap_or(default_days);
+    if days > 0 {
+        deleted += storage.prune_updates_before(site.id, now - Duration::days(days)).await?;
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn prunes_by_count_and_age_but_keeps_latest() {
+        let storage = MemoryStorage::new();
+        let site = Site { retention_count: Some(3), retention_days: Some(7), ..Site::new("https://a.example", 60, "random") };
+        let site_id = storage.insert_site(&site).await.unwrap();
+        let site = Site { id: site_id, ..site };
+        let now = Utc::now();
+
+        for days_ago in [30, 20, 10, 5, 1] {
+            storage.insert_update(site_id, now - Duration::days(days_ago), &format!("h{}", days_ago), "page").await.unwrap();
+        }
+        // Three newest survive the count limit, then the 10-day-old one is past 7 days
+        assert_eq!(prune_site(&storage, &site, now, 5, 0).await.unwrap(), 3);
+        assert_eq!(storage.snapshots(site_id, None).await.unwrap().len(), 2);
+
+        // Only the latest update is left once everything has expired
+        assert_eq!(prune_site(&storage, &site, now + Duration::days(30), 5, 0).await.unwrap(), 1);
+        assert_eq!(storage.latest_hash(site_id).await.unwrap().as_deref(), Some("h1"));
+    }
+}
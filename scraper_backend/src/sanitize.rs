@@ -0,0 +1,119 @@
+use super::scraper::select_region;
+use ammonia::{Url, UrlRelative, UrlRelativeEvaluate};
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+// Dropped with everything inside them
+const DROPPED_TAGS: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "math", "iframe", "frame", "frameset",
+    "object", "embed", "applet", "form", "input", "button", "select", "textarea", "link", "meta", "base", "title",
+];
+// Kept as they are; any other element is replaced by its children
+const ALLOWED_TAGS: &[&str] = &[
+    "a", "abbr", "article", "b", "blockquote", "br", "caption", "cite", "code", "dd", "del", "div", "dl",
+    "dt", "em", "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "kbd", "li",
+    "main", "mark", "ol", "p", "pre", "q", "s", "section", "small", "span", "strong", "sub", "sup", "table",
+    "tbody", "td", "tfoot", "th", "thead", "time", "tr", "u", "ul",
+];
+// Attributes kept on each tag; any other attribute goes
+const TAG_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("a", &["href", "title"]),
+    ("img", &["src", "alt", "title", "width", "height"]),
+    ("td", &["colspan", "rowspan"]),
+    ("th", &["colspan", "rowspan"]),
+    ("time", &["datetime"]),
+    ("abbr", &["title"]),
+    ("ol", &["start"]),
+];
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+const LINK_REL: &str = "nofollow noopener noreferrer";
+
+// The stored page (or its selected region) reduced to markup that is safe to insert into
+// the frontend: no scripts, styles, frames, forms, event handlers or classes, only a short
+// list of tags and attributes, and links and images resolved against the page's URL with
+// anything but http(s) and mailto removed
+pub fn sanitized_html(html: &str, selector: Option<&str>, page_url: &str) -> String {
+    let region = selector.and_then(|sel| select_region(html, sel));
+    let base = Url::parse(page_url).ok();
+    let cleaned = ammonia::Builder::empty()
+        .tags(ALLOWED_TAGS.iter().copied().collect())
+        .clean_content_tags(DROPPED_TAGS.iter().copied().collect())
+        .generic_attributes(HashSet::new())
+        .tag_attributes(TAG_ATTRIBUTES.iter().map(|(tag, attrs)| (*tag, attrs.iter().copied().collect())).collect())
+        .url_schemes(URL_SCHEMES.iter().copied().collect())
+        .url_relative(UrlRelative::Custom(Box::new(PageUrl(base))))
+        .attribute_filter(image_source)
+        .link_rel(Some(LINK_REL))
+        .strip_comments(true)
+        .clean(region.as_deref().unwrap_or(html))
+        .to_string();
+    without_empty_images(&cleaned).trim().to_string()
+}
+
+// Relative links and images resolved against the page; in-page anchors stay as they are
+struct PageUrl(Option<Url>);
+
+impl UrlRelativeEvaluate<'static> for PageUrl {
+    fn evaluate<'u>(&self, url: &'u str) -> Option<Cow<'u, str>> {
+        if url.starts_with('#') {
+            return Some(url.into());
+        }
+        self.0.as_ref()?.join(url).ok().map(|url| String::from(url).into())
+    }
+}
+
+// mailto: is for links only. Runs before relative URLs are resolved, which keeps them.
+fn image_source<'v>(tag: &str, attr: &str, value: &'v str) -> Option<Cow<'v, str>> {
+    match (tag, attr, Url::parse(value)) {
+        ("img", "src", Ok(url)) if !matches!(url.scheme(), "http" | "https") => None,
+        _ => Some(value.into()),
+    }
+}
+
+// An image without a usable source has nothing left to show. Cleaned markup quotes every
+// attribute value and escapes quotes inside it, so the tag's attributes can be matched.
+fn without_empty_images(html: &str) -> Cow<'_, str> {
+    static IMAGE: OnceLock<Regex> = OnceLock::new();
+    let image = IMAGE.get_or_init(|| Regex::new(r#"<img((?:\s+[a-z-]+="[^"]*")*)\s*/?>"#).unwrap());
+    image.replace_all(html, |caps: &Captures| match caps[1].contains(" src=\"") {
+        true => caps[0].to_string(),
+        false => String::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_content_markup_and_drops_everything_active() {
+        let html = r#"<html><head><title>T</title><script>alert(1)</script></head><body>
+            <div class="post" onclick="steal()" style="color:red"><h2 id="x">Rates <em>held</em></h2>
+            <p>See <a href="/report.pdf" onmouseover="x()">the report</a>, <a href="javascript:alert(1)">this</a>
+               or <a href="mailto:press@bank.example">mail us</a> &amp; <font>more</font>.</p>
+            <img src="data:image/png;base64,AAAA"><img src="chart.png" alt="Chart" onerror="x()">
+            <iframe src="https://ads.example"></iframe><form><input name="q"></form>
+            <p>&lt;script&gt; stays text</p></div></body></html>"#;
+        assert_eq!(
+            sanitized_html(html, None, "https://bank.example/news/"),
+            "<div><h2>Rates <em>held</em></h2>\n            \
+             <p>See <a href=\"https://bank.example/report.pdf\" rel=\"nofollow noopener noreferrer\">the report</a>, \
+             <a rel=\"nofollow noopener noreferrer\">this</a>\n               \
+             or <a href=\"mailto:press@bank.example\" rel=\"nofollow noopener noreferrer\">mail us</a> &amp; more.</p>\n            \
+             <img src=\"https://bank.example/news/chart.png\" alt=\"Chart\">\n            \n            \
+             <p>&lt;script&gt; stays text</p></div>"
+        );
+        assert_eq!(sanitized_html(html, Some("h2"), "https://bank.example/"), "<h2>Rates <em>held</em></h2>");
+    }
+
+    #[test]
+    fn keeps_anchors_and_drops_images_without_a_web_source() {
+        let html = r##"<p><a href="#top">Top</a><img src="mailto:a@b.example" alt="x"><img alt="y"><img src="//cdn.example/a.png"></p>"##;
+        assert_eq!(
+            sanitized_html(html, None, "https://bank.example/"),
+            r##"<p><a href="#top" rel="nofollow noopener noreferrer">Top</a><img src="https://cdn.example/a.png"></p>"##
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use sqlx::migrate::Migrator;
+
+// Versioned schema per storage backend, embedded at build time and applied on startup.
+// Never edit a migration that has shipped: sqlx checks the recorded checksums, so schema
+// changes go into a new numbered file under migrations/<backend>/.
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("migrations/sqlite");
+pub static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("migrations/postgres");
+
+// Columns added to SQLite databases by the ad-hoc upgrades that predate migrations.
+// They only run against a database that has never been migrated, before it is baselined,
+// and fail harmlessly once the column exists.
+pub const LEGACY_SQLITE_UPGRADES: &[&str] = &[
+    "ALTER TABLE sites ADD COLUMN snoozed_until TEXT;",
+    "ALTER TABLE sites ADD COLUMN last_success TEXT;",
+    "ALTER TABLE sites ADD COLUMN selector TEXT;",
+    "ALTER TABLE sites ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE sites ADD COLUMN retention_count INTEGER;",
+    "ALTER TABLE sites ADD COLUMN probe_mode TEXT;",
+    "ALTER TABLE sites ADD COLUMN probe_tail_kb INTEGER;",
+    "ALTER TABLE sites ADD COLUMN probe_signature TEXT;",
+    "ALTER TABLE webhooks ADD COLUMN canary_checked_at TEXT;",
+    "ALTER TABLE webhooks ADD COLUMN canary_ok INTEGER;",
+    "ALTER TABLE webhooks ADD COLUMN canary_error TEXT;",
+];
+
+// Tables holding per-site data and the bodies of their updates, children first. A reset empties these; webhooks and alert
+// rules only lose their per-site rows, and Telegram chats stay with no sites opted in.
+pub const SITE_TABLES: &[&str] = &[
+    "alerts", "notified_changes", "seen_items", "items", "telegram_sites", "site_tags", "fetch_log", "detection_latency", "site_changes", "documents", "halts", "federal_documents", "screenshots", "updates", "contents", "pdfs", "sites",
+];
@@ -1,184 +1,1324 @@
-use super::{Site, UpdateMessage, AppConfig};
+use super::alerts::Alert;
+use super::ignore::strip_ignored;
+use super::metrics::Metrics;
+use super::proxies::{self, Proxies};
+use super::storage::{CheckRecord, FetchLogEntry, LatencySample, RecordedChange, SharedStorage};
+use super::plugins::FetchRequest;
+use super::readability::{self, Article};
+use super::scripts::ScriptOutput;
+use super::timeline::{EventKind, Timeline};
+use super::{alerts, assertions, challenge, charset, crawl, diff, documents, edgar, extractors, fda, federal_register, feeds, halts, health, items, links, news_search, pdf, recommendations, reddit, screenshots, scripts, search, sitemap, social, structured, substack, wire, Site, StreamEvent, UpdateMessage, AppConfig};
 use chrono::{Utc, DateTime};
 use rand::{seq::SliceRandom, thread_rng, Rng};
-use reqwest::header::{HeaderMap, USER_AGENT};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, LAST_MODIFIED, RANGE, RETRY_AFTER, USER_AGENT};
+use reqwest::StatusCode;
+use serde::Serialize;
 use sha2::{Sha256, Digest};
-use sqlx::{Pool, Sqlite};
 use tokio::{time::{sleep, Duration}, sync::broadcast::Sender};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::{oneshot, Mutex, Semaphore};
+use tokio::time::Instant;
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 // HTML tag and processing dependencies
 use regex::Regex;
 use scraper::{Html, Selector};
+use unicode_normalization::UnicodeNormalization;
 
 // Track site check intervals and backoff state
-type SiteState = Arc<RwLock<HashMap<i64, SiteCheckState>>>;
-
 #[derive(Debug, Clone)]
-struct SiteCheckState {
+pub struct SiteCheckState {
     next_check: DateTime<Utc>,
     backoff_count: u32,
+    // Set when the adaptive style learned a new interval from this check
+    learned_interval_secs: Option<i64>,
+}
+
+// Events pushed into the scheduler instead of having it poll the sites table
+#[derive(Debug)]
+pub enum SchedulerEvent {
+    SiteAdded(i64),
+    SiteUpdated(i64),
+    SiteRemoved(i64),
+    ReloadAll,
+    // Manual "check now"; the reply carries the outcome of whichever fetch serves it
+    CheckNow { site_id: i64, reply: oneshot::Sender<CheckOutcome> },
+    CheckFinished { site_id: i64, state: SiteCheckState, outcome: Box<CheckOutcome> },
+}
+
+pub type SchedulerHandle = UnboundedSender<SchedulerEvent>;
+
+// Why a fetch ran; a coalesced fetch lists every trigger it served
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckTrigger {
+    Scheduled,
+    Manual,
+}
+
+impl CheckTrigger {
+    fn label(self) -> &'static str {
+        match self {
+            CheckTrigger::Scheduled => "scheduled",
+            CheckTrigger::Manual => "manual",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckOutcome {
+    pub site_id: i64,
+    pub checked_at: DateTime<Utc>,
+    pub success: bool,
+    pub changed: bool,
+    pub update_id: Option<i64>,
+    pub triggers: Vec<CheckTrigger>,
+    // Status of the full GET; None when the request failed or a probe made it unnecessary
+    pub http_status: Option<u16>,
+    pub duration_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    // Proxy the fetch went out through, password masked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    // What the full GET's response said about itself
+    #[serde(flatten)]
+    pub response: ResponseMeta,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResponseMeta {
+    // Where redirects ended up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    // As sent by the server, which is the compressed size for compressed responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_length: Option<i64>,
+}
+
+impl ResponseMeta {
+    fn of(resp: &reqwest::Response) -> Self {
+        let header = |name| resp.headers().get(name).and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok());
+        ResponseMeta {
+            final_url: Some(resp.url().to_string()),
+            content_type: header(CONTENT_TYPE).map(String::from),
+            content_length: header(CONTENT_LENGTH).and_then(|v| v.trim().parse().ok()),
+        }
+    }
+}
+
+impl CheckOutcome {
+    // Detail of the check_finished timeline event
+    fn summary(&self) -> String {
+        let status = match self.http_status {
+            Some(code) => format!("HTTP {}", code),
+            None => self.status_label(),
+        };
+        let result = match (&self.error, self.success, self.changed) {
+            (Some(error), _, _) => format!("failed: {}", error),
+            (None, false, _) => "failed".to_string(),
+            (None, true, true) => "changed".to_string(),
+            (None, true, false) => "unchanged".to_string(),
+        };
+        format!("{} ({}, {}ms)", result, status, self.duration_ms)
+    }
+
+    // `status` label of scraper_fetches_total
+    fn status_label(&self) -> String {
+        match self.http_status {
+            Some(code) => code.to_string(),
+            None if self.success => "probe".into(),
+            None => "error".into(),
+        }
+    }
+
+    fn log_entry(&self) -> FetchLogEntry {
+        FetchLogEntry {
+            site_id: self.site_id,
+            fetched_at: self.checked_at,
+            status_code: self.http_status.map(i64::from),
+            duration_ms: self.duration_ms,
+            error: self.error.clone(),
+            proxy: self.proxy.clone(),
+            final_url: self.response.final_url.clone(),
+            content_type: self.response.content_type.clone(),
+            content_length: self.response.content_length,
+            changed: self.changed,
+            update_id: self.update_id,
+        }
+    }
+}
+
+// Requests waiting on the next (or the running) fetch of a site
+#[derive(Default)]
+struct PendingCheck {
+    triggers: Vec<CheckTrigger>,
+    waiters: Vec<oneshot::Sender<CheckOutcome>>,
+    // The regular check was pulled forward, so it no longer counts as a trigger
+    replaced_schedule: bool,
+}
+
+// Delay queue of (next_check, site_id). Entries are invalidated lazily: a popped entry
+// is only dispatched if it still matches the site's recorded next_check.
+// A site in `in_flight` is never dispatched again until its CheckFinished arrives,
+// so a slow fetch can't overlap with the next check of the same site.
+struct Scheduler {
+    queue: BinaryHeap<Reverse<(DateTime<Utc>, i64)>>,
+    sites: HashMap<i64, Site>,
+    states: HashMap<i64, SiteCheckState>,
+    pending: HashMap<i64, PendingCheck>,
+    in_flight: HashMap<i64, PendingCheck>,
+    // Manual requests this close to the next regular check wait for it instead
+    coalesce_window: chrono::Duration,
+    metrics: Arc<Metrics>,
+    timeline: Arc<Timeline>,
+}
+
+impl Scheduler {
+    fn new(coalesce_window: chrono::Duration, metrics: Arc<Metrics>, timeline: Arc<Timeline>) -> Self {
+        Scheduler {
+            queue: BinaryHeap::new(),
+            sites: HashMap::new(),
+            states: HashMap::new(),
+            pending: HashMap::new(),
+            in_flight: HashMap::new(),
+            coalesce_window,
+            metrics,
+            timeline,
+        }
+    }
+
+    fn schedule(&mut self, site_id: i64, at: DateTime<Utc>, backoff_count: u32) {
+        self.states.insert(site_id, SiteCheckState { next_check: at, backoff_count, learned_interval_secs: None });
+        self.queue.push(Reverse((at, site_id)));
+    }
+
+    // New to the scheduler (at startup, say), a site picks up the backoff stored with it
+    fn insert_site(&mut self, site: Site) {
+        let site_id = site.id;
+        let now = Utc::now();
+        let (at, backoff_count) = match self.states.get(&site_id) {
+            Some(state) => (now, state.backoff_count),
+            None => (site.backoff_until.filter(|until| *until > now).unwrap_or(now), site.backoff_count.max(0) as u32),
+        };
+        self.sites.insert(site_id, site);
+        self.schedule(site_id, at, backoff_count);
+    }
+
+    // Refresh a site's settings without touching its place in the queue,
+    // except that lifting a snooze or starting a burst makes the site due immediately
+    fn update_site(&mut self, site: Site) {
+        let Some(existing) = self.sites.get_mut(&site.id) else {
+            self.insert_site(site);
+            return;
+        };
+        let unsnoozed = existing.snoozed_until.is_some() && site.snoozed_until.is_none();
+        let burst = match (site.burst_interval_secs, site.burst_until) {
+            (Some(secs), Some(until)) if site.burst_until != existing.burst_until => Some((secs, until)),
+            _ => None,
+        };
+        *existing = site;
+        if let Some((secs, until)) = burst {
+            let detail = format!("every {}s until {}", secs, until.to_rfc3339());
+            self.timeline.record(Utc::now(), existing.id, EventKind::BurstStarted, detail);
+        }
+        if unsnoozed || burst.is_some() {
+            let site_id = existing.id;
+            let backoff_count = self.states.get(&site_id).map_or(0, |s| s.backoff_count);
+            self.schedule(site_id, Utc::now(), backoff_count);
+        }
+    }
+
+    fn remove_site(&mut self, site_id: i64) {
+        self.sites.remove(&site_id);
+        self.states.remove(&site_id);
+        self.pending.remove(&site_id);
+    }
+
+    fn clear(&mut self) {
+        self.queue.clear();
+        self.sites.clear();
+        self.states.clear();
+        self.pending.clear();
+    }
+
+    // Attach a manual request to a fetch that is already running or about to run,
+    // otherwise pull the site's next check forward to now
+    fn check_now(&mut self, site_id: i64, reply: oneshot::Sender<CheckOutcome>, now: DateTime<Utc>) {
+        if let Some(running) = self.in_flight.get_mut(&site_id) {
+            running.triggers.push(CheckTrigger::Manual);
+            running.waiters.push(reply);
+            self.metrics.coalesced_requests.inc();
+            return;
+        }
+        let Some(state) = self.states.get(&site_id).cloned() else {
+            return; // Unknown site; dropping the reply tells the caller
+        };
+        let pending = self.pending.entry(site_id).or_default();
+        pending.triggers.push(CheckTrigger::Manual);
+        pending.waiters.push(reply);
+        if state.next_check > now + self.coalesce_window {
+            pending.replaced_schedule = true;
+            self.schedule(site_id, now, state.backoff_count);
+        } else {
+            self.metrics.coalesced_requests.inc();
+        }
+    }
+
+    // Hand the result of a finished fetch to everyone it was attributed to
+    fn finish(&mut self, site_id: i64, state: SiteCheckState, mut outcome: CheckOutcome) {
+        let running = match self.in_flight.remove(&site_id) {
+            Some(running) => {
+                self.metrics.checks_in_flight.dec();
+                running
+            },
+            None => PendingCheck::default(),
+        };
+        let now = Utc::now();
+        self.timeline.record(now, site_id, EventKind::CheckFinished, outcome.summary());
+        let previous_backoff = self.states.get(&site_id).map_or(0, |s| s.backoff_count);
+        if state.backoff_count > previous_backoff {
+            let wait = (state.next_check - now).num_seconds().max(0);
+            let detail = format!("failure {} in a row, next check in {}s", state.backoff_count, wait);
+            self.timeline.record(now, site_id, EventKind::BackoffApplied, detail);
+        }
+        // Ignore scheduling for sites deleted while the check was running
+        if let Some(site) = self.sites.get_mut(&site_id) {
+            if state.learned_interval_secs.is_some() {
+                site.adaptive_interval_secs = state.learned_interval_secs;
+            }
+            self.schedule(site_id, state.next_check, state.backoff_count);
+        }
+        if running.triggers.len() > 1 {
+            debug!(site_id, triggers = running.triggers.len(), "Coalesced triggers into one fetch");
+        }
+        outcome.triggers = running.triggers;
+        for waiter in running.waiters {
+            let _ = waiter.send(outcome.clone());
+        }
+    }
+
+    fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.queue.peek().map(|Reverse((at, _))| *at)
+    }
+
+    // Pop every entry that is due now and still valid
+    fn pop_due(&mut self, now: DateTime<Utc>) -> Vec<(Site, u32)> {
+        let mut due = Vec::new();
+        while let Some(Reverse((at, site_id))) = self.queue.peek().cloned() {
+            if at > now {
+                break;
+            }
+            self.queue.pop();
+            let (Some(site), Some(state)) = (self.sites.get(&site_id), self.states.get(&site_id)) else {
+                continue; // Site was removed
+            };
+            if state.next_check != at {
+                continue;
+            }
+            let backoff_count = state.backoff_count;
+            // A fetch is already running; it reschedules the site when it finishes
+            if let Some(running) = self.in_flight.get_mut(&site_id) {
+                running.triggers.push(CheckTrigger::Scheduled);
+                self.metrics.overlap_skips.inc();
+                self.timeline.record(now, site_id, EventKind::CheckSkipped, "previous fetch still running");
+                debug!(site_id = site.id, url = %site.url, "Skipping check: previous fetch still running");
+                continue;
+            }
+            // Snoozed sites are pushed back until the snooze expires, unless someone asked
+            match site.snoozed_until {
+                Some(until) if until > now && !self.pending.contains_key(&site_id) => {
+                    self.timeline.record(now, site_id, EventKind::CheckSkipped, format!("snoozed until {}", until.to_rfc3339()));
+                    self.schedule(site_id, until, backoff_count)
+                },
+                _ => {
+                    let mut check = self.pending.remove(&site_id).unwrap_or_default();
+                    if !check.replaced_schedule {
+                        check.triggers.insert(0, CheckTrigger::Scheduled);
+                    }
+                    let triggers: Vec<&str> = check.triggers.iter().map(|t| t.label()).collect();
+                    self.timeline.record(now, site_id, EventKind::CheckStarted, triggers.join(", "));
+                    due.push((site.clone(), backoff_count));
+                    self.in_flight.insert(site_id, check);
+                    self.metrics.checks_started.inc();
+                    self.metrics.checks_in_flight.inc();
+                },
+            }
+        }
+        due.sort_by_key(|(site, _)| Reverse(site.priority));
+        due
+    }
+}
+
+// Spaces out requests to the same domain regardless of how many sites point at it.
+// Each caller reserves the next free slot for its domain, then sleeps until it.
+struct DomainLimiter {
+    min_delay: Duration,
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl DomainLimiter {
+    fn new(min_delay: Duration) -> Self {
+        DomainLimiter {
+            min_delay,
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn wait_turn(&self, url: &str) {
+        let Some(domain) = domain_key(url) else {
+            return;
+        };
+        // SEC's fair access limit holds whatever the configured delay
+        let min_delay = match domain == edgar::DOMAIN {
+            true => self.min_delay.max(edgar::MIN_REQUEST_INTERVAL),
+            false => self.min_delay,
+        };
+        if min_delay.is_zero() {
+            return;
+        }
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let now = Instant::now();
+            let slot = next_slot.get(&domain).copied().filter(|t| *t > now).unwrap_or(now);
+            next_slot.insert(domain, slot + min_delay);
+            // Forget domains that have been idle for a while
+            next_slot.retain(|_, t| *t > now);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
 }
 
-pub async fn run_scraper(pool: Pool<Sqlite>, tx: Sender<UpdateMessage>, config: AppConfig) {
-    println!("---------------------------------------------");
-    println!("Scraper background task started successfully");
-    println!("Will check for site updates in the background");
+// Group hosts by registrable domain so www.sec.gov and efts.sec.gov share a limit.
+// Handles the common two-level country suffixes (co.uk, gov.cn, ...) by keeping three labels.
+fn domain_key(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return Some(host);
+    }
+    let labels: Vec<&str> = host.split('.').filter(|l| !l.is_empty()).collect();
+    let second_level = ["co", "com", "gov", "org", "net", "ac", "edu", "ne", "or", "go"];
+    let keep = match labels.as_slice() {
+        [.., sld, tld] if tld.len() == 2 && second_level.contains(sld) => 3,
+        _ => 2,
+    };
+    Some(labels[labels.len().saturating_sub(keep)..].join("."))
+}
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+pub async fn run_scraper(
+    storage: SharedStorage,
+    tx: Sender<StreamEvent>,
+    config: AppConfig,
+    events_tx: SchedulerHandle,
+    mut events_rx: UnboundedReceiver<SchedulerEvent>,
+    metrics: Arc<Metrics>,
+    timeline: Arc<Timeline>,
+) {
+    info!("Scraper background task started, checking for site updates in the background");
     
-    // Create shared state for tracking site check schedules
-    let site_states: SiteState = Arc::new(RwLock::new(HashMap::new()));
+    let mut scheduler = Scheduler::new(chrono::Duration::milliseconds(config.coalesce_window_ms.max(0)), metrics.clone(), timeline);
+    
+    // Due checks wait for a permit so a cold start doesn't fire every fetch at once
+    let fetch_permits = Arc::new(Semaphore::new(config.max_concurrent_fetches.max(1) as usize));
+    
+    // Minimum spacing between requests to the same domain
+    let domain_limiter = Arc::new(DomainLimiter::new(Duration::from_millis(config.per_domain_min_delay_ms.max(0) as u64)));
     
     // Convert config to Arc to share across tasks
     let config = Arc::new(config);
     
+    // Seed the queue with every known site
+    let _ = events_tx.send(SchedulerEvent::ReloadAll);
+    
     loop {
-        let sites: Vec<Site> = sqlx::query_as::<_, Site>("SELECT * FROM sites")
-            .fetch_all(&pool)
-            .await
-            .unwrap_or_default();
-
-        let now = Utc::now();
+        metrics.scheduler_heartbeat.set(Utc::now().timestamp());
+        // Sleep until the earliest scheduled check, waking early for incoming events and at
+        // least every HEARTBEAT_INTERVAL so /readyz sees the loop is alive
+        let wait = scheduler.next_due()
+            .map(|at| (at - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+            .unwrap_or(HEARTBEAT_INTERVAL)
+            .min(HEARTBEAT_INTERVAL);
         
-        for site in sites {
-            let site_id = site.id;
-            let should_check = {
-                let states = site_states.read().await;
-                if let Some(state) = states.get(&site_id) {
-                    now >= state.next_check
-                } else {
-                    true // First time seeing this site, check it now
+        tokio::select! {
+            event = events_rx.recv() => {
+                let Some(event) = event else {
+                    warn!("Scheduler event channel closed, stopping scraper");
+                    return;
+                };
+                match event {
+                    SchedulerEvent::SiteAdded(site_id) => {
+                        if let Ok(Some(site)) = storage.get_site(site_id).await {
+                            scheduler.insert_site(site);
+                        }
+                    },
+                    SchedulerEvent::SiteUpdated(site_id) => {
+                        if let Ok(Some(site)) = storage.get_site(site_id).await {
+                            scheduler.update_site(site);
+                        }
+                    },
+                    SchedulerEvent::SiteRemoved(site_id) => scheduler.remove_site(site_id),
+                    SchedulerEvent::ReloadAll => {
+                        let sites = storage.list_sites().await.unwrap_or_default();
+                        scheduler.clear();
+                        for site in sites {
+                            scheduler.insert_site(site);
+                        }
+                    },
+                    SchedulerEvent::CheckNow { site_id, reply } => scheduler.check_now(site_id, reply, Utc::now()),
+                    SchedulerEvent::CheckFinished { site_id, state, outcome } => scheduler.finish(site_id, state, *outcome),
                 }
-            };
-            
-            if should_check {
-                // spawn per site
-                let pool_clone = pool.clone();
-                let tx_clone = tx.clone();
-                let site_states_clone = site_states.clone();
-                let config_clone = config.clone();
-                
-                tokio::spawn(async move {
-                    check_site(site, pool_clone, tx_clone, site_states_clone, &config_clone).await;
-                });
+            },
+            _ = sleep(wait) => {
+                for (site, backoff_count) in scheduler.pop_due(Utc::now()) {
+                    // spawn per site
+                    let storage_clone = storage.clone();
+                    let tx_clone = tx.clone();
+                    let events_clone = events_tx.clone();
+                    let config_clone = config.clone();
+                    let permits = fetch_permits.clone();
+                    let limiter = domain_limiter.clone();
+                    let proxies = config.proxies.clone();
+                    let metrics_clone = metrics.clone();
+                    let status_tx = tx.clone();
+                    let thresholds = config.health;
+                    
+                    tokio::spawn(async move {
+                        // Wait for the domain slot before taking a global permit so
+                        // queued requests for a busy host don't starve other sites
+                        metrics_clone.checks_waiting.inc();
+                        limiter.wait_turn(&site.url).await;
+                        let _permit = permits.acquire_owned().await;
+                        metrics_clone.checks_waiting.dec();
+                        let site_id = site.id;
+                        let interval_secs = site.interval_secs;
+                        let url = site.url.clone();
+                        let domain = domain_key(&site.url).unwrap_or_else(|| "unknown".into());
+                        let span = info_span!("check", site_id, url = %site.url);
+                        let fetch_log = storage_clone.clone();
+                        let fetch_log_size = config_clone.fetch_log_size;
+                        // Run the check in its own task so a panic still reports back;
+                        // otherwise the site would stay in flight and never be checked again
+                        let check = tokio::spawn(async move {
+                            check_site(site, &proxies, storage_clone, tx_clone, backoff_count, &config_clone).await
+                        }.instrument(span.clone()));
+                        let (state, outcome) = match check.await {
+                            Ok(result) => result,
+                            Err(e) => {
+                                span.in_scope(|| error!("Check aborted: {}", e));
+                                metrics_clone.check_panics.inc();
+                                failed_check(site_id, interval_secs, backoff_count, format!("check aborted: {}", e))
+                            },
+                        };
+                        let _ = fetch_log.record_fetch(&outcome.log_entry(), fetch_log_size).await;
+                        if state.backoff_count != backoff_count {
+                            let _ = fetch_log.set_backoff(site_id, state.backoff_count as i64, Some(state.next_check), outcome.error.as_deref()).await;
+                        }
+                        health::record(&fetch_log, &status_tx, site_id, &url, outcome.success, &thresholds).await;
+                        metrics_clone.observe_fetch(
+                            site_id,
+                            &domain,
+                            &outcome.status_label(),
+                            Duration::from_millis(outcome.duration_ms.max(0) as u64),
+                            outcome.changed,
+                        );
+                        let _ = events_clone.send(SchedulerEvent::CheckFinished { site_id, state, outcome: Box::new(outcome) });
+                    });
+                }
+            },
+        }
+    }
+}
+
+// Result reported for a check that never completed; retried after one interval
+fn failed_check(site_id: i64, interval_secs: i64, backoff_count: u32, error: String) -> (SiteCheckState, CheckOutcome) {
+    let now = Utc::now();
+    let state = SiteCheckState {
+        next_check: now + chrono::Duration::seconds(interval_secs),
+        backoff_count,
+        learned_interval_secs: None,
+    };
+    let outcome = CheckOutcome {
+        site_id,
+        checked_at: now,
+        success: false,
+        changed: false,
+        update_id: None,
+        triggers: Vec::new(),
+        http_status: None,
+        duration_ms: 0,
+        error: Some(error),
+        proxy: None,
+        response: ResponseMeta::default(),
+    };
+    (state, outcome)
+}
+
+// User agents rotated per request on the shared client
+const USER_AGENTS: [&str; 4] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64)",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7)",
+    "Mozilla/5.0 (X11; Linux x86_64)",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 14_0 like Mac OS X)",
+];
+
+// One client for all fetches so connections and TLS sessions are reused across checks.
+// Site fetches use the clients of the proxy pool, which also go through the private
+// network guard.
+pub fn build_http_client() -> reqwest::Client {
+    http_client_builder().build().unwrap()
+}
+
+// Request timeout of sites without their own timeout_secs
+pub const DEFAULT_TIMEOUT_SECS: i64 = 10;
+
+pub fn http_client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS as u64))
+        .pool_idle_timeout(Duration::from_secs(90))
+}
+
+// reqwest's own message ("error sending request") hides the cause, such as a refused
+// connection or an address blocked by the network guard
+pub fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+// A fetched body as text; a PDF is replaced by its text and kept alongside
+struct Body {
+    text: String,
+    pdf: Option<Vec<u8>>,
+}
+
+// Reads the body up to max_bytes and decodes it in the charset of its Content-Type or the
+// one the page declares, or extracts its text if it is a PDF
+async fn read_body(resp: reqwest::Response, max_bytes: u64, extraction_mode: Option<&str>) -> Result<Body, String> {
+    let content_type = resp.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(String::from);
+    let body = read_bytes(resp, max_bytes).await?;
+    if pdf::is_pdf(content_type.as_deref(), extraction_mode, &body) {
+        let pdf = pdf::extract(body).await?;
+        return Ok(Body { text: pdf.text, pdf: Some(pdf.data) });
+    }
+    Ok(Body { text: charset::decode(&body, content_type.as_deref()), pdf: None })
+}
+
+// The raw body up to max_bytes. A body announced as larger is refused before any of it
+// downloads.
+pub async fn read_bytes(mut resp: reqwest::Response, max_bytes: u64) -> Result<Vec<u8>, String> {
+    if let Some(length) = resp.content_length().filter(|length| *length > max_bytes) {
+        return Err(format!("body of {} bytes exceeds max_body_bytes ({})", length, max_bytes));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|e| error_chain(&e))? {
+        if (body.len() + chunk.len()) as u64 > max_bytes {
+            return Err(format!("body exceeds max_body_bytes ({})", max_bytes));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+// A page the site's check leads to, such as a release's canonical copy or a page new to a
+// sitemap, fetched with the site's client and limits and decoded to text
+pub async fn fetch_text(client: &reqwest::Client, config: &AppConfig, site: &Site, url: &str) -> Result<String, String> {
+    config.network_guard.check_url(url)?;
+    let timeout = Duration::from_secs(site.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(1) as u64);
+    let resp = site_get(client, url, None, timeout).send().await.map_err(|e| error_chain(&e))?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status().as_u16()));
+    }
+    let content_type = resp.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(String::from);
+    let data = read_bytes(resp, site.max_body_bytes.unwrap_or(config.max_body_bytes).max(1) as u64).await?;
+    Ok(charset::decode(&data, content_type.as_deref()))
+}
+
+fn random_user_agent() -> &'static str {
+    USER_AGENTS.choose(&mut thread_rng()).unwrap()
+}
+
+// Blocked or throttled responses are held against the proxy, not the site
+fn proxy_result(response: &Result<reqwest::Response, reqwest::Error>) -> Result<(), String> {
+    match response {
+        Ok(resp) if matches!(resp.status().as_u16(), 403 | 407 | 429) => Err(format!("HTTP {}", resp.status().as_u16())),
+        Ok(_) => Ok(()),
+        Err(e) => Err(error_chain(e)),
+    }
+}
+
+// What a check of the site would extract from its page right now, for POST /api/preview
+#[derive(Debug, Serialize)]
+pub struct DryRun {
+    pub http_status: u16,
+    #[serde(flatten)]
+    pub response: ResponseMeta,
+    // How the page was read: "plugin", "script", "pdf", "links", "feed", "article" or "page"
+    pub extraction: &'static str,
+    // Why a check would mark the site EXTRACTION_FAILED or CHALLENGE and store nothing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extraction_failed: Option<String>,
+    // The text the hash is taken of; for feeds and links, the entry IDs or URLs
+    pub comparison_text: String,
+    pub hash: String,
+    pub preview: String,
+    pub title: Option<String>,
+    // What the page declares about itself in JSON-LD, microdata and OpenGraph tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<structured::Metadata>,
+    pub items: Vec<feeds::FeedEntry>,
+}
+
+// Fetches the site once and runs it through the check's extraction without storing,
+// broadcasting or reporting anything
+pub async fn dry_run(site: &Site, config: &AppConfig) -> Result<DryRun, String> {
+    config.network_guard.check_url(&site.url)?;
+    let route = config.proxies.route(site, Utc::now())?;
+    let request = plugin_request(site, config, &route.client).await?;
+    let fetch_url = request.as_ref().map_or(site.url.as_str(), |request| request.url.as_str());
+    let timeout = Duration::from_secs(site.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(1) as u64);
+    let resp = site_get(&route.client, fetch_url, request.as_ref(), timeout).send().await.map_err(|e| error_chain(&e))?;
+    let mut http_status = resp.status().as_u16();
+    let response = ResponseMeta::of(&resp);
+    let mitigated = challenge::is_mitigated(resp.headers());
+    let Body { text: body, pdf } = read_body(resp, site.max_body_bytes.unwrap_or(config.max_body_bytes).max(1) as u64, site.extraction_mode.as_deref()).await?;
+    let mut challenged = None;
+    let body = match &pdf {
+        None => {
+            let page = challenge::pass(config.challenge_solver.as_ref(), fetch_url, route.proxy.as_deref(), http_status, mitigated, body).await;
+            http_status = page.status;
+            challenged = page.challenge.map(|challenge| format!("{}: {}", challenge::CHALLENGE, challenge));
+            match challenged {
+                Some(_) => page.body,
+                None => crawl::crawl(&route.client, config, site, page.body).await?,
             }
+        },
+        Some(_) => body,
+    };
+
+    let custom = custom_extraction(site, config, &body).await;
+    let extraction_failed = match &custom {
+        _ if challenged.is_some() => challenged,
+        Some(Err(e)) => Some(e.clone()),
+        _ => assertions::check(site, &body).err(),
+    };
+    let custom = custom.and_then(Result::ok);
+    let links = match (&custom, &pdf, site.extraction_mode.as_deref()) {
+        (None, None, Some(links::EXTRACTION_MODE)) => Some(links::extract(&body, site.selector.as_deref(), reqwest::Url::parse(&site.url).ok().as_ref(), site.link_pattern.as_deref())),
+        _ => None,
+    };
+    let feed = match (&custom, &pdf, &links) {
+        (None, None, None) => parse_entries(&body, site.extraction_mode.as_deref()),
+        _ => None,
+    };
+    let article = match (&custom, &pdf) {
+        (Some(output), _) => Some(output.article()),
+        (None, Some(_)) => Some(pdf::article(&body, &site.ignore_patterns)),
+        (None, None) => feed.is_none().then(|| readable_article(&body, site.extraction_mode.as_deref(), &site.ignore_patterns)).flatten(),
+    };
+    let extraction = match (&custom, &feed, &article) {
+        (Some(_), _, _) if site.plugin.is_some() => "plugin",
+        (Some(_), _, _) => "script",
+        _ if pdf.is_some() => "pdf",
+        _ if links.is_some() => "links",
+        (None, Some(_), _) if site.extraction_mode.as_deref() == Some(edgar::EXTRACTION_MODE) => "edgar",
+        (None, Some(_), _) if halts::read(&body, site.extraction_mode.as_deref()).is_some() => "halts",
+        (None, Some(_), _) if federal_register::read(&body, site.extraction_mode.as_deref()).is_some() => "federal_register",
+        (None, Some(_), _) if site.extraction_mode.as_deref() == Some(fda::EXTRACTION_MODE) || fda::is_openfda(&body) => "fda",
+        (None, Some(_), _) if site.extraction_mode.as_deref() == Some(reddit::EXTRACTION_MODE) || reddit::is_listing_response(&body) => "reddit",
+        (None, Some(_), _) if social::read(&body, site.extraction_mode.as_deref()).is_some() => "social",
+        (None, Some(_), _) if substack::read(&body, site.extraction_mode.as_deref()).is_some() => "substack",
+        (None, Some(_), _) if news_search::read(&body, site.extraction_mode.as_deref()).is_some() => "search",
+        (None, Some(_), _) if sitemap::read(&body, site.extraction_mode.as_deref()).is_some() => "sitemap",
+        (None, Some(_), _) => "feed",
+        (None, None, Some(_)) => "article",
+        (None, None, None) => "page",
+    };
+    let selector = site.selector.as_deref();
+    let (comparison_text, hash) = match (feed.as_ref().or(links.as_ref()), &article) {
+        (Some(entries), _) => (entries.iter().map(|e| e.guid.as_str()).collect::<Vec<_>>().join("\n"), feeds::entries_hash(entries)),
+        (None, Some(article)) => (article_comparison_text(article), article_hash(article, config.hash_fold_case)),
+        (None, None) => {
+            let text = clean_content_for_comparison(&body, selector, &site.ignore_patterns);
+            (text.clone(), sha256_hex(text, config.hash_fold_case))
+        },
+    };
+    let preview_length = site.preview_length.map_or(extractors::DEFAULT_PREVIEW_LENGTH, |n| n as usize);
+    let preview = match (&links, &article) {
+        (Some(links), _) => links::preview(links, false, preview_length),
+        (None, Some(article)) => extractors::article_preview(article, preview_length),
+        (None, None) => extractors::preview(&body, preview_length, selector, site.extraction_mode.as_deref()),
+    };
+    let title = article.and_then(|article| article.title);
+    let metadata = (pdf.is_none() && feed.is_none()).then(|| structured::extract(&body)).flatten();
+    let items = match (custom, feed.or(links)) {
+        (Some(output), _) => output.items,
+        (None, Some(entries)) => entries,
+        (None, None) => items::html_items(&body, selector, &site.url),
+    };
+    Ok(DryRun { http_status, response, extraction, extraction_failed, comparison_text, hash, preview, title, metadata, items })
+}
+
+// A plugin can send the request elsewhere, such as to the JSON API behind a page; the
+// network guard checks where. EDGAR sites without one send SEC's required User-Agent,
+// Reddit sites the configured one and their OAuth token, X sites their bearer token and
+// Bing searches their key.
+async fn plugin_request(site: &Site, config: &AppConfig, client: &reqwest::Client) -> Result<Option<FetchRequest>, String> {
+    let Some(name) = &site.plugin else {
+        if let Some(request) = edgar::request(site, &config.edgar)? {
+            return Ok(Some(request));
+        }
+        if let Some(request) = config.social.request(site)? {
+            return Ok(Some(request));
+        }
+        if let Some(request) = config.news_search.request(site)? {
+            return Ok(Some(request));
         }
-        sleep(Duration::from_millis(100)).await;
+        return config.reddit.request(site, client).await;
+    };
+    let request = config.plugins.fetch_request(name, &site.url).await?;
+    if let Some(request) = &request {
+        config.network_guard.check_url(&request.url)?;
     }
+    Ok(request)
 }
 
-async fn check_site(site: Site, pool: Pool<Sqlite>, tx: Sender<UpdateMessage>, site_states: SiteState, config: &Arc<AppConfig>) {
-    let mut headers = HeaderMap::new();
-    let agents = vec![
-        "Mozilla/5.0 (Windows NT 10.0; Win64; x64)",
-        "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7)",
-        "Mozilla/5.0 (X11; Linux x86_64)",
-        "Mozilla/5.0 (iPhone; CPU iPhone OS 14_0 like Mac OS X)",
-    ];
-    headers.insert(USER_AGENT, agents.choose(&mut thread_rng()).unwrap().parse().unwrap());
+// GET of the site's page, with the plugin's headers; they can replace the user agent
+pub fn site_get(client: &reqwest::Client, url: &str, request: Option<&FetchRequest>, timeout: Duration) -> reqwest::RequestBuilder {
+    let mut get = client.get(url).timeout(timeout);
+    let headers = request.map(|request| &request.headers);
+    if !headers.is_some_and(|headers| headers.keys().any(|name| name.eq_ignore_ascii_case("user-agent"))) {
+        get = get.header(USER_AGENT, random_user_agent());
+    }
+    for (name, value) in headers.into_iter().flatten() {
+        get = get.header(name, value);
+    }
+    get
+}
 
-    let client = reqwest::Client::builder()
-        .default_headers(headers)
-        .timeout(Duration::from_secs(10))
-        .build()
-        .unwrap();
+// What the site's plugin, else its extraction script, makes of a page; None with neither
+async fn custom_extraction(site: &Site, config: &AppConfig, body: &str) -> Option<Result<ScriptOutput, String>> {
+    match (&site.plugin, &site.extraction_script) {
+        (Some(name), _) => Some(config.plugins.extract(name, body, &site.url).await),
+        (None, Some(script)) => Some(scripts::run(script, body, &site.url).await),
+        (None, None) => None,
+    }
+}
+
+async fn check_site(mut site: Site, proxies: &Proxies, storage: SharedStorage, tx: Sender<StreamEvent>, backoff_count: u32, config: &Arc<AppConfig>) -> (SiteCheckState, CheckOutcome) {
+    let started = Instant::now();
+    // Sites stored before the guard existed may point straight at an internal address
+    if let Err(e) = config.network_guard.check_url(&site.url) {
+        warn!("Fetch blocked: {}", e);
+        let _ = storage.mark_checked(site.id, Utc::now(), false).await;
+        return failed_check(site.id, site.interval_secs, backoff_count, e);
+    }
+    let route = match proxies.route(&site, Utc::now()) {
+        Ok(route) => route,
+        Err(e) => {
+            warn!("Fetch not sent: {}", e);
+            let _ = storage.mark_checked(site.id, Utc::now(), false).await;
+            return failed_check(site.id, site.interval_secs, backoff_count, e);
+        },
+    };
+    let client = &route.client;
+    let proxy_label = route.proxy.as_deref().map(proxies::redact);
+    let request = match plugin_request(&site, config, &route.client).await {
+        Ok(request) => request,
+        Err(e) => {
+            warn!("Fetch not sent: {}", e);
+            let _ = storage.mark_checked(site.id, Utc::now(), false).await;
+            return failed_check(site.id, site.interval_secs, backoff_count, e);
+        },
+    };
+    let fetch_url = request.as_ref().map_or(site.url.as_str(), |request| request.url.as_str());
+    let timeout = Duration::from_secs(site.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(1) as u64);
+    // Large files: a cheap probe decides whether the full download is needed
+    let probe = match site.probe_mode.as_deref() {
+        Some(mode) => probe_signature(client, fetch_url, mode, site.probe_tail_kb.unwrap_or(DEFAULT_PROBE_TAIL_KB), timeout).await,
+        None => None,
+    };
+    // The scheduler's copy of the site is not refreshed after each check, so read
+    // the signature stored by the previous fetch
+    let previous_probe = match &probe {
+        Some(_) => storage.probe_signature(site.id).await.ok().flatten(),
+        None => None,
+    };
+    if probe.is_some() && probe == previous_probe {
+        let checked_at = Utc::now();
+        let _ = storage.mark_checked(site.id, checked_at, true).await;
+        let outcome = CheckOutcome {
+            site_id: site.id,
+            checked_at,
+            success: true,
+            changed: false,
+            update_id: None,
+            triggers: Vec::new(),
+            http_status: None,
+            duration_ms: started.elapsed().as_millis() as i64,
+            error: None,
+            proxy: proxy_label,
+            response: ResponseMeta::default(),
+        };
+        debug!("Probe unchanged, skipped full fetch");
+        adapt_interval(&mut site, false, &storage, config).await;
+        return (next_check_state(&site, true, checked_at, backoff_count, config), outcome);
+    }
 
     // fetch
-    let body_res = client.get(&site.url).send().await;
+    let body_res = site_get(client, fetch_url, request.as_ref(), timeout).send().await;
     let fetched_at = Utc::now();
+    if let Some(proxy) = &route.proxy {
+        proxies.report(proxy, proxy_result(&body_res), fetched_at);
+    }
+    // Rate limited or overloaded: back off instead of storing the error page
+    if let Some(limit) = body_res.as_ref().ok().and_then(|resp| RateLimit::from_response(resp, fetched_at)) {
+        let state = rate_limited_state(&site, &limit, fetched_at, backoff_count);
+        warn!(status = limit.status, retry_after = limit.retry_after, backoff_count = state.backoff_count, "Backing off until {}", state.next_check.to_rfc3339());
+        let _ = storage.mark_checked(site.id, fetched_at, false).await;
+        let outcome = CheckOutcome {
+            site_id: site.id,
+            checked_at: fetched_at,
+            success: false,
+            changed: false,
+            update_id: None,
+            triggers: Vec::new(),
+            http_status: Some(limit.status),
+            duration_ms: started.elapsed().as_millis() as i64,
+            error: Some(limit.describe()),
+            proxy: proxy_label,
+            response: body_res.as_ref().map(ResponseMeta::of).unwrap_or_default(),
+        };
+        return (state, outcome);
+    }
     let mut success = true;
+    let mut changed = false;
+    let mut update_id = None;
+    let mut http_status = None;
+    let mut error = None;
+    let mut response = ResponseMeta::default();
+    if let Err(e) = &body_res {
+        error = Some(error_chain(e));
+    }
     
     if let Ok(resp) = body_res {
-        if let Ok(body) = resp.text().await {
-            // Pre-process content to remove volatile elements before hashing
-            let cleaned_content = clean_content_for_comparison(&body);
-            
-            // Hash the cleaned content
-            let mut hasher = Sha256::new();
-            hasher.update(cleaned_content.as_bytes());
-            let hash = format!("{:x}", hasher.finalize());
+        http_status = Some(resp.status().as_u16());
+        response = ResponseMeta::of(&resp);
+        let last_modified = resp.headers()
+            .get(LAST_MODIFIED)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+            .map(|t| t.with_timezone(&Utc));
+        let mitigated = challenge::is_mitigated(resp.headers());
+        let (body, pdf) = match read_body(resp, site.max_body_bytes.unwrap_or(config.max_body_bytes).max(1) as u64, site.extraction_mode.as_deref()).await {
+            Ok(Body { text, pdf }) => (Ok(text), pdf),
+            Err(e) => (Err(format!("reading body failed: {}", e)), None),
+        };
+        // An anti-bot challenge is not the site's content and mustn't be hashed as a change
+        let mut challenged = false;
+        let body = match (body, &pdf) {
+            (Ok(body), None) => {
+                let page = challenge::pass(config.challenge_solver.as_ref(), fetch_url, route.proxy.as_deref(), http_status.unwrap_or_default(), mitigated, body).await;
+                http_status = Some(page.status);
+                match page.challenge {
+                    Some(challenge) => {
+                        challenged = true;
+                        Err(format!("{}: {}", challenge::CHALLENGE, challenge))
+                    },
+                    None => Ok(page.body),
+                }
+            },
+            (body, _) => body,
+        };
+        // A paginated listing is checked across the pages its crawl reaches
+        let body = match (body, &pdf) {
+            (Ok(body), None) if site.crawl_pattern.is_some() => crawl::crawl(client, config, &site, body).await,
+            (body, _) => body,
+        };
+        if let Err(e) = &body {
+            error = Some(e.clone());
+        }
+        // A site's own plugin or extraction script replaces feed parsing and readability; a
+        // page it fails on is treated like a failed assertion
+        let script_output = match &body {
+            Ok(body) => custom_extraction(&site, config, body).await,
+            Err(_) => None,
+        };
+        // A block page or broken layout must not be hashed and recorded as a change
+        let failed_assertion = match (&body, &script_output) {
+            (Ok(_), Some(Err(e))) => Some(e.clone()),
+            (Ok(body), _) => assertions::check(&site, body).err(),
+            (Err(_), _) => None,
+        };
+        if let Some(failure) = failed_assertion {
+            warn!("Extraction failed: {}", failure);
+            success = false;
+            error = Some(format!("{}: {}", assertions::EXTRACTION_FAILED, failure));
+            let _ = storage.mark_extraction_failed(site.id, fetched_at).await;
+        } else if let Ok(body) = body {
+            let last_hash = storage.latest_hash(site.id).await.ok().flatten();
 
-            let last_hash: Option<(String,)> = sqlx::query_as("SELECT diff_hash FROM updates WHERE site_id = ?1 ORDER BY id DESC LIMIT 1")
-                .bind(site.id)
-                .fetch_optional(&pool)
-                .await
-                .ok()
-                .flatten();
+            // Feeds are compared entry by entry, so reordered items or a new
+            // lastBuildDate don't count as changes
+            let script_output = script_output.and_then(Result::ok);
+            let custom = script_output.is_some();
+            let links_mode = !custom && pdf.is_none() && site.extraction_mode.as_deref() == Some(links::EXTRACTION_MODE);
+            // Halt lists are read like feeds and also stored halt by halt, so resumptions
+            // are noticed too
+            let halt_list = match (&script_output, &pdf) {
+                (None, None) if !links_mode => halts::read(&body, site.extraction_mode.as_deref()),
+                _ => None,
+            };
+            // Federal Register documents are kept with their metadata the same way
+            let federal_documents = match (&script_output, &pdf, &halt_list) {
+                (None, None, None) if !links_mode => federal_register::read(&body, site.extraction_mode.as_deref()),
+                _ => None,
+            };
+            let feed = match (&script_output, &pdf, &halt_list, &federal_documents) {
+                (None, None, Some(list), _) => Some(list.iter().map(halts::entry).collect()),
+                (None, None, None, Some(documents)) => Some(documents.iter().map(federal_register::entry).collect()),
+                (None, None, None, None) if !links_mode => parse_entries(&body, site.extraction_mode.as_deref()),
+                _ => None,
+            };
+            let seen_since = (config.feed_lookback_days > 0).then(|| fetched_at - chrono::Duration::days(config.feed_lookback_days));
+            let mut feed_diff = match &feed {
+                Some(entries) => match storage.feed_diff(site.id, entries, seen_since).await {
+                    Ok(diff) => Some(diff),
+                    Err(e) => {
+                        warn!("Failed to compare feed entries: {}", e);
+                        None
+                    }
+                },
+                None => None,
+            };
+            // Pages new to a sitemap are fetched for what they are about
+            if let Some(diff) = feed_diff.as_mut().filter(|_| sitemap::read(&body, site.extraction_mode.as_deref()).is_some()) {
+                sitemap::follow(client, config, &site, diff).await;
+            }
 
-            let changed = last_hash.map_or(true, |h| h.0 != hash);
+            // A PDF's text is hashed and previewed like an article
+            let article = match (&script_output, &pdf) {
+                (Some(output), _) => Some(output.article()),
+                (None, Some(_)) => Some(pdf::article(&body, &site.ignore_patterns)),
+                (None, None) => feed.is_none().then(|| readable_article(&body, site.extraction_mode.as_deref(), &site.ignore_patterns)).flatten(),
+            };
+            // Link sets are compared with the previous stored fetch, which is kept for the diff
+            let mut previous = None;
+            let link_diff = match links_mode {
+                true => {
+                    previous = storage.previous_content(site.id, i64::MAX).await.ok().flatten();
+                    Some(links::diff(&body, previous.as_deref(), site.selector.as_deref(), &site.url, site.link_pattern.as_deref()))
+                },
+                false => None,
+            };
+            let hash;
+            (hash, changed) = match (&feed, &feed_diff, &link_diff) {
+                (Some(entries), Some(diff), _) => {
+                    let changed = diff.first_fetch || !diff.new_entries.is_empty();
+                    // Reuse the previous hash when nothing new appeared so history only
+                    // shows a change where an entry was actually added
+                    let hash = match (&last_hash, changed) {
+                        (Some(previous), false) => previous.clone(),
+                        _ => feeds::entries_hash(entries),
+                    };
+                    (hash, changed)
+                },
+                // Likewise only links that weren't there before make a change
+                (_, _, Some(diff)) => {
+                    let changed = diff.first_fetch || !diff.new_links.is_empty();
+                    let hash = match (&last_hash, changed) {
+                        (Some(previous), false) => previous.clone(),
+                        _ => diff.hash.clone(),
+                    };
+                    (hash, changed)
+                },
+                _ => {
+                    let hash = match &article {
+                        Some(article) => article_hash(article, config.hash_fold_case),
+                        None => content_hash(&body, site.selector.as_deref(), &site.ignore_patterns, config.hash_fold_case),
+                    };
+                    let changed = last_hash.as_ref().is_none_or(|h| *h != hash);
+                    (hash, changed)
+                },
+            };
 
-            // Update last_checked
-            sqlx::query!("UPDATE sites SET last_checked = ?1, status = 'OK' WHERE id = ?2", fetched_at, site.id)
-                .execute(&pool)
-                .await
-                .unwrap();
+            let selector = site.selector.as_deref();
+            let previous = match changed {
+                true if links_mode => previous,
+                true => storage.previous_content(site.id, i64::MAX).await.ok().flatten(),
+                false => None,
+            };
+            let indexed_text = search::indexed_text(&body);
+            // Feeds and PDFs carry no page markup to declare anything in
+            let metadata = (feed.is_none() && pdf.is_none()).then(|| structured::extract(&body)).flatten();
+            // How old the content says it is: the page's declared date, or the newest of the
+            // feed's new entries
+            let published_at = match (&feed_diff, &metadata) {
+                (Some(diff), _) => diff.new_entries.iter().filter_map(|entry| entry.published).max(),
+                (None, Some(metadata)) => metadata.updated_at(),
+                (None, None) => None,
+            };
+            // Press releases, on a wire's own site or syndicated from one, are tagged with the
+            // wire so alert rules can single them out
+            let syndication = match (&feed, &pdf) {
+                (None, None) if !custom && !links_mode => wire::detect(&body, &site.url),
+                _ => None,
+            };
+            let site_wire = wire::from_url(&site.url);
+            let wire_source = match (&syndication, &feed_diff) {
+                (Some(syndication), _) => Some(syndication.wire.key),
+                (None, Some(diff)) => site_wire.or_else(|| diff.new_entries.iter().find_map(|e| e.link.as_deref().and_then(wire::from_url))).map(|w| w.key),
+                (None, None) => None,
+            };
+            // New feed entries and links become items as they are; a changed page is searched
+            // for headlines
+            let page_items = match (&feed, script_output, &link_diff) {
+                (None, Some(output), _) if changed => output.items,
+                (None, None, Some(diff)) if changed => diff.new_links.clone(),
+                (None, None, None) if changed => items::html_items(&body, selector, &site.url),
+                _ => Vec::new(),
+            };
+            let new_items = match &feed_diff {
+                Some(diff) => diff.new_entries.as_slice(),
+                None => page_items.as_slice(),
+            };
+            // Every fetch is stored regardless of change, together with everything
+            // derived from it, so a failure halfway leaves nothing behind
+            let record = CheckRecord {
+                site_id: site.id,
+                fetched_at,
+                diff_hash: &hash,
+                content: &body,
+                pdf: pdf.as_deref(),
+                metadata: metadata.as_ref(),
+                published_at,
+                wire_source,
+                indexed_text: &indexed_text,
+                probe_signature: probe.as_deref(),
+                feed_entries: feed_diff.as_ref().and(feed.as_deref()),
+                seen_since,
+                items: new_items,
+                change: changed.then(|| RecordedChange {
+                    diff: previous.as_ref().map(|previous| diff::text_diff(previous, &body, selector)),
+                    in_history: last_hash.is_some(),
+                    history_size: recommendations::CHANGE_HISTORY_SIZE,
+                }),
+            };
+            update_id = match storage.record_check(&record).await {
+                Ok(inserted_id) => Some(inserted_id),
+                Err(e) => {
+                    warn!("Failed to store fetch: {}", e);
+                    success = false;
+                    changed = false;
+                    error = Some(format!("storing the fetch failed: {}", e));
+                    let _ = storage.mark_checked(site.id, fetched_at, false).await;
+                    None
+                },
+            };
 
-            // Store every fetch in the database regardless of change
-            sqlx::query!("INSERT INTO updates(site_id, timestamp, diff_hash, content) VALUES (?1, ?2, ?3, ?4)",
-                site.id, fetched_at, hash, body)
-                .execute(&pool)
-                .await
-                .unwrap();
+            if let (Some(inserted_id), Some(list)) = (update_id, &halt_list) {
+                let announce = feed_diff.as_ref().is_some_and(|diff| !diff.first_fetch);
+                halts::record(&storage, &tx, site.id, inserted_id, list, announce, fetched_at).await;
+            }
+            if let (Some(inserted_id), Some(documents)) = (update_id, &federal_documents) {
+                federal_register::record(&storage, site.id, inserted_id, documents, fetched_at).await;
+            }
 
             // Only notify UI if content meaningfully changed
-            if changed {
-                // Extract and format a better content preview
-                let content_preview = extract_formatted_preview(&body, 400);
-                
-                // Notify about the update
-                let _ = tx.send(UpdateMessage{
-                    site_id: site.id,
-                    url: site.url.clone(),
-                    timestamp: fetched_at,
-                    diff_hash: hash,
-                    content_preview,
-                    has_full_content: true,
-                });
-                
-                // Update last_updated timestamp
-                sqlx::query!("UPDATE sites SET last_updated = ?1 WHERE id = ?2", fetched_at, site.id)
-                    .execute(&pool)
-                    .await
-                    .unwrap();
+            if let Some(inserted_id) = update_id.filter(|_| changed) {
+                let rules = alerts::rules_for_site(storage.as_ref(), site.id).await;
+                let preview_length = site.preview_length.map_or(extractors::DEFAULT_PREVIEW_LENGTH, |n| n as usize);
+                match feed_diff {
+                    // One message per genuinely new feed entry
+                    Some(diff) if !diff.first_fetch => {
+                        for entry in diff.new_entries {
+                            // Old entries coming back, such as after a feed was rebuilt
+                            if is_stale(&site, config, entry.published, fetched_at) {
+                                info!(guid = %entry.guid, "Not notifying about a stale feed entry");
+                                continue;
+                            }
+                            let entry_text = format!("{}\n{}", entry.title.as_deref().unwrap_or(""), entry.summary.as_deref().unwrap_or(""));
+                            let entry_wire = entry.link.as_deref().and_then(wire::from_url).or(site_wire);
+                            let matched_rules = alerts::match_rules(&rules, &entry_text, entry_wire.is_some());
+                            let detection_latency_secs = record_latency(&storage, site.id, inserted_id, entry.published, fetched_at).await;
+                            broadcast_update(&storage, &tx, UpdateMessage{
+                                update_id: inserted_id,
+                                site_id: site.id,
+                                url: site.url.clone(),
+                                timestamp: fetched_at,
+                                diff_hash: hash.clone(),
+                                content_preview: feeds::entry_preview(&entry, preview_length),
+                                has_full_content: true,
+                                title: entry.title,
+                                link: entry.link,
+                                matched_rules,
+                                published: entry.published,
+                                detection_latency_secs,
+                                collapsed_updates: None,
+                                metadata: None,
+                                wire_source: entry_wire.map(|wire| wire.key.to_string()),
+                                has_screenshot: false,
+                                documents: Vec::new(),
+                            }).await;
+                        }
+                    },
+                    // A sidebar or comment count changed around content that is old by its own date
+                    _ if is_stale(&site, config, published_at, fetched_at) => {
+                        info!(published = ?published_at, "Not notifying about a change to stale content");
+                    },
+                    _ => {
+                        // A press release is read from the wire's own copy where the page names one
+                        let release = match &syndication {
+                            Some(syndication) => wire::follow(client, config, &site, syndication, &body).await,
+                            None => None,
+                        };
+                        // Extract and format a better content preview
+                        let content_preview = match (&link_diff, &release, &article) {
+                            (Some(diff), _, _) => links::preview(&diff.new_links, true, preview_length),
+                            (None, Some(release), _) => extractors::article_preview(&release.article(), preview_length),
+                            (None, None, Some(article)) => extractors::article_preview(article, preview_length),
+                            (None, None, None) => extractors::preview(&body, preview_length, selector, site.extraction_mode.as_deref()),
+                        };
+                        
+                        // Only text added since the previous stored version can trigger alerts
+                        let mut matched_rules = Vec::new();
+                        if !rules.is_empty() {
+                            let (old_text, new_text) = match (custom, &article, &link_diff) {
+                                (_, _, Some(diff)) => (String::new(), links::describe(&diff.new_links)),
+                                // The previous page is read with the same plugin or script
+                                (true, Some(article), _) => {
+                                    let old_text = match previous {
+                                        Some(content) => custom_extraction(&site, config, &content).await.and_then(Result::ok),
+                                        None => None,
+                                    };
+                                    (old_text.map(|output| output.article().text).unwrap_or_default(), article.text.clone())
+                                },
+                                _ => (previous.map(|content| alerts::page_text(&content, selector)).unwrap_or_default(), alerts::page_text(&body, selector)),
+                            };
+                            let added = alerts::added_text(&old_text, &new_text);
+                            matched_rules = alerts::match_rules(&rules, &added, syndication.is_some());
+                        }
+                        
+                        // A first fetch says nothing about how quickly a change was noticed. The
+                        // page's own date beats the server's Last-Modified.
+                        let published = published_at.or(last_modified).filter(|_| last_hash.is_some());
+                        let detection_latency_secs = record_latency(&storage, site.id, inserted_id, published, fetched_at).await;
+
+                        // In browser mode the page is pictured as it looked when the change was
+                        // found; PDFs and feeds have nothing visual to add
+                        let has_screenshot = match &config.browser {
+                            Some(browser) if pdf.is_none() => screenshot(&storage, browser, &site.url, inserted_id).await,
+                            _ => false,
+                        };
+
+                        // New documents are saved before the source can take them down again
+                        let documents = match &link_diff {
+                            Some(diff) if site.archive_links && !diff.first_fetch => documents::archive(&storage, client, config, &site, inserted_id, &diff.new_links).await,
+                            _ => Vec::new(),
+                        };
+                        
+                        // Notify about the update
+                        broadcast_update(&storage, &tx, UpdateMessage{
+                            update_id: inserted_id,
+                            site_id: site.id,
+                            url: site.url.clone(),
+                            timestamp: fetched_at,
+                            diff_hash: hash,
+                            content_preview,
+                            has_full_content: true,
+                            title: release.and_then(|release| release.headline),
+                            link: syndication.and_then(|syndication| syndication.canonical),
+                            matched_rules,
+                            published,
+                            detection_latency_secs,
+                            collapsed_updates: None,
+                            metadata,
+                            wire_source: wire_source.map(String::from),
+                            has_screenshot,
+                            documents,
+                        }).await;
+                    },
+                }
             }
-            
-            // Limit the number of updates stored per site based on config
-            let update_cache_size = config.update_cache_size;
-            sqlx::query!(
-                "DELETE FROM updates WHERE id IN (
-                    SELECT id FROM updates 
-                    WHERE site_id = ?1 
-                    ORDER BY id DESC 
-                    LIMIT -1 OFFSET ?2
-                )",
-                site.id,
-                update_cache_size
-            )
-            .execute(&pool)
-            .await
-            .unwrap();
+
         } else {
             success = false;
+            if challenged {
+                warn!("Fetch challenged: {}", error.as_deref().unwrap_or_default());
+                let _ = storage.mark_challenged(site.id, fetched_at).await;
+            // A body over the cap or a crawl page that failed is a failed fetch like a
+            // transport error
+            } else if let Err(e) = storage.mark_checked(site.id, fetched_at, false).await {
+                warn!("Failed to record failed fetch: {}", e);
+            }
         }
     } else {
         success = false;
-        sqlx::query!("UPDATE sites SET last_checked = ?1, status = 'ERROR' WHERE id = ?2",
-            fetched_at, site.id)
-            .execute(&pool)
-            .await
-            .unwrap();
+        // Keep the watchdog's OVERDUE flag until a fetch actually succeeds
+        if let Err(e) = storage.mark_checked(site.id, fetched_at, false).await {
+            warn!("Failed to record failed fetch: {}", e);
+        }
     }
     
-    // Calculate next check time based on style and interval
-    let mut backoff_count = 0;
-    
-    // Get current state if it exists
-    if let Some(current_state) = site_states.read().await.get(&site.id) {
-        backoff_count = current_state.backoff_count;
+    if success {
+        adapt_interval(&mut site, changed, &storage, config).await;
+    }
+    let state = next_check_state(&site, success, fetched_at, backoff_count, config);
+    let outcome = CheckOutcome {
+        site_id: site.id,
+        checked_at: fetched_at,
+        success,
+        changed,
+        update_id,
+        triggers: Vec::new(),
+        http_status,
+        duration_ms: started.elapsed().as_millis() as i64,
+        error,
+        proxy: proxy_label,
+        response,
+    };
+    debug!(status = ?outcome.http_status, duration_ms = outcome.duration_ms, proxy = outcome.proxy, changed, "Fetch finished");
+    (state, outcome)
+}
+
+// An adaptive site learns its next interval from each successful check and its recorded
+// change times. The result is stored so a restart keeps it.
+async fn adapt_interval(site: &mut Site, changed: bool, storage: &SharedStorage, config: &AppConfig) {
+    if site.style != "adaptive" {
+        return;
+    }
+    let changes = storage.change_times(site.id).await.unwrap_or_default();
+    let current = site.adaptive_interval_secs.unwrap_or(site.interval_secs);
+    let learned = recommendations::adapt_interval(current, changed, &changes, config.tuning.samples_per_change, &config.adaptive);
+    if site.adaptive_interval_secs != Some(learned) {
+        if let Err(e) = storage.set_adaptive_interval(site.id, learned).await {
+            warn!("Failed to store adaptive interval: {}", e);
+        }
+        debug!(from = current, to = learned, "Adaptive interval changed");
+        site.adaptive_interval_secs = Some(learned);
+    }
+}
+
+// Calculate next check time based on style and interval
+fn next_check_state(site: &Site, success: bool, fetched_at: DateTime<Utc>, mut backoff_count: u32, config: &AppConfig) -> SiteCheckState {
+    // A successful fetch ends any backoff, including one for rate limiting
+    if success {
+        backoff_count = 0;
     }
-    
     // Determine next check time based on style
     let next_check_time = match site.style.as_str() {
         "random" => {
@@ -189,8 +1329,6 @@ async fn check_site(site: Site, pool: Pool<Sqlite>, tx: Sender<UpdateMessage>, s
         },
         "exponential" => {
             if success {
-                // Reset backoff on success
-                backoff_count = 0;
                 fetched_at + chrono::Duration::seconds(site.interval_secs)
             } else {
                 // Double wait time on failure, up to a reasonable maximum
@@ -199,248 +1337,140 @@ async fn check_site(site: Site, pool: Pool<Sqlite>, tx: Sender<UpdateMessage>, s
                 fetched_at + chrono::Duration::seconds(backoff_interval)
             }
         },
+        "adaptive" => fetched_at + chrono::Duration::seconds(site.adaptive_interval_secs.unwrap_or(site.interval_secs)),
         _ => {
             // "none" style or any unrecognized style - fixed interval only
             fetched_at + chrono::Duration::seconds(site.interval_secs)
         }
     };
-    
-    // Update the site state
-    let mut states = site_states.write().await;
-    states.insert(site.id, SiteCheckState {
+    // Outside its active hours the site is checked less often; windows were validated
+    // when they were set
+    let next_check_time = match site.active_window.as_ref().or(config.active_window.as_ref()).map(|w| w.compile()) {
+        Some(Ok(window)) => window.apply(fetched_at, next_check_time),
+        _ => next_check_time,
+    };
+    // A running burst overrides all of the above
+    let next_check_time = match (site.burst_interval_secs, site.burst_until) {
+        (Some(secs), Some(until)) if until > fetched_at => fetched_at + chrono::Duration::seconds(secs),
+        _ => next_check_time,
+    };
+
+    SiteCheckState {
         next_check: next_check_time,
         backoff_count,
-    });
+        learned_interval_secs: site.adaptive_interval_secs,
+    }
 }
 
-// Extract and format a preview of the content
-fn extract_formatted_preview(content: &str, max_length: usize) -> String {
-    // First check if it's RSS or XML content
-    if content.contains("<?xml") || content.contains("<rss") || content.contains("<feed") || 
-       content.contains("<item>") || content.contains("<entry>") {
-        return extract_rss_preview(content, max_length);
-    }
-    
-    // Special handler for Reddit content
-    if content.contains("/u/DeepFuckingValue") || content.contains("r/wallstreetbets") || 
-       content.contains("reddit.com") {
-        return extract_reddit_preview(content, max_length);
-    }
-    
-    // Check if it's JSON content
-    if (content.starts_with('{') && content.ends_with('}')) || 
-       (content.starts_with('[') && content.ends_with(']')) {
-        return extract_json_preview(content, max_length);
-    }
-    
-    // Check for JavaScript code
-    if content.contains("function(") || content.contains("var ") || content.contains("const ") {
-        // This is likely JavaScript or has a lot of JavaScript, clean it first
-        let cleaned = clean_script_content(content);
-        return format!("📰 Content Preview\n\n{}", if cleaned.len() > max_length {
-            format!("{}...", &cleaned[..find_word_boundary(&cleaned, max_length)])
-        } else {
-            cleaned
-        });
-    }
+// Longest backoff after rate-limited fetches (unless the interval is longer), and the
+// most of a Retry-After header that is honored
+const MAX_BACKOFF_SECS: i64 = 6 * 3600;
+const MAX_RETRY_AFTER_SECS: i64 = 86400;
 
-    // Process HTML content using a more robust approach
-    extract_html_preview(content, max_length)
+// A 429 or 5xx response, or SEC's 403: the source is limiting us or struggling
+#[derive(Debug, PartialEq)]
+struct RateLimit {
+    status: u16,
+    // Retry-After in seconds; the header may also be an HTTP date
+    retry_after: Option<i64>,
 }
 
-// Extract preview from HTML content using the HTML parser
-fn extract_html_preview(html: &str, max_length: usize) -> String {
-    // Create a new HTML document for parsing
-    let document = Html::parse_document(html);
-    
-    // Try to find the title
-    let mut title = String::new();
-    
-    // First check for title tag
-    if let Ok(title_selector) = Selector::parse("title") {
-        if let Some(title_element) = document.select(&title_selector).next() {
-            title = title_element.text().collect::<Vec<_>>().join(" ").trim().to_string();
-        }
-    }
-    
-    // If no title, try h1
-    if title.is_empty() {
-        if let Ok(h1_selector) = Selector::parse("h1") {
-            if let Some(h1_element) = document.select(&h1_selector).next() {
-                title = h1_element.text().collect::<Vec<_>>().join(" ").trim().to_string();
-            }
+impl RateLimit {
+    fn from_response(resp: &reqwest::Response, now: DateTime<Utc>) -> Option<Self> {
+        let status = resp.status();
+        // SEC refuses clients over its request rate with a 403 for ten minutes
+        let edgar_block = status == StatusCode::FORBIDDEN && domain_key(resp.url().as_str()).as_deref() == Some(edgar::DOMAIN);
+        if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() && !edgar_block {
+            return None;
         }
-    }
-    
-    // Start building the preview
-    let mut preview = String::new();
-    
-    // Add the title with formatting if found
-    if !title.is_empty() {
-        preview.push_str(&format!("📰 {}\n\n", title));
-    }
-    
-    // Try to extract meaningful content
-    let mut content_text = String::new();
-    
-    // Try various content selectors by priority
-    let content_selectors = [
-        "article", "main", ".content", "#content", ".post-content", 
-        ".entry-content", ".article-content", ".post", "p",
-        ".news-article", ".article__content", ".story-body", ".story__content"
-    ];
-    
-    for selector_str in content_selectors {
-        if let Ok(selector) = Selector::parse(selector_str) {
-            let elements: Vec<_> = document.select(&selector).collect();
-            if !elements.is_empty() {
-                // Join text from all matching elements
-                content_text = elements.iter()
-                    .flat_map(|el| el.text())
-                    .collect::<Vec<_>>()
-                    .join(" ")
-                    .trim()
-                    .to_string();
-                
-                // Remove excessive whitespace
-                content_text = normalize_whitespace(&content_text);
-                
-                if !content_text.is_empty() {
-                    break;
-                }
-            }
-        }
-    }
-    
-    // If we couldn't extract content with selectors, fall back to general text extraction
-    if content_text.is_empty() {
-        // Extract all text from body
-        if let Ok(body_selector) = Selector::parse("body") {
-            if let Some(body) = document.select(&body_selector).next() {
-                content_text = body.text()
-                    .collect::<Vec<_>>()
-                    .join(" ")
-                    .trim()
-                    .to_string();
-                
-                content_text = normalize_whitespace(&content_text);
-                content_text = clean_script_content(&content_text);
-            }
-        }
-    }
-    
-    // Add content preview with length limit
-    if !content_text.is_empty() {
-        let content_preview = if content_text.len() > max_length {
-            // Try to cut at a sentence boundary if possible
-            let cutoff = find_sentence_boundary(&content_text, max_length);
-            format!("{}...", &content_text[..cutoff])
-        } else {
-            content_text
+        let retry_after = resp.headers().get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, now));
+        let retry_after = match edgar_block {
+            true => Some(retry_after.unwrap_or(0).max(edgar::BLOCK_SECS)),
+            false => retry_after,
         };
-        
-        preview.push_str(&content_preview);
-    } else if !preview.is_empty() {
-        // If we only have a title, add a placeholder for content
-        preview.push_str("[Content not available]");
-    } else {
-        // Complete fallback if we couldn't extract anything meaningful
-        preview = "Unable to extract readable content from this page.".to_string();
+        Some(RateLimit { status: status.as_u16(), retry_after })
     }
-    
-    preview
-}
 
-// Extract preview from RSS/XML content
-fn extract_rss_preview(xml: &str, max_length: usize) -> String {
-    let mut preview = String::new();
-    
-    // Very basic XML tag extraction
-    // Look for common RSS/feed elements
-    let title_pattern = Regex::new(r"<title[^>]*>(.*?)</title>").unwrap_or_else(|_| Regex::new(r"").unwrap());
-    let desc_pattern = Regex::new(r"<description[^>]*>(.*?)</description>").unwrap_or_else(|_| Regex::new(r"").unwrap());
-    let content_pattern = Regex::new(r"<content[^>]*>(.*?)</content>").unwrap_or_else(|_| Regex::new(r"").unwrap());
-    
-    // Extract title 
-    if let Some(captures) = title_pattern.captures(xml) {
-        if let Some(title_match) = captures.get(1) {
-            let title = clean_xml_entities(title_match.as_str());
-            if !title.is_empty() {
-                preview.push_str(&format!("📰 {}\n\n", title));
-            }
+    fn describe(&self) -> String {
+        match self.retry_after {
+            Some(secs) => format!("HTTP {} (Retry-After {}s)", self.status, secs),
+            None => format!("HTTP {}", self.status),
         }
     }
-    
-    // Try to extract content (prioritize content over description)
-    let mut content_text = String::new();
-    
-    if let Some(captures) = content_pattern.captures(xml) {
-        if let Some(content_match) = captures.get(1) {
-            content_text = clean_xml_entities(content_match.as_str());
-        }
+}
+
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<i64> {
+    let value = value.trim();
+    let secs = match value.parse::<i64>() {
+        Ok(secs) => secs,
+        Err(_) => (DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc) - now).num_seconds(),
+    };
+    Some(secs.clamp(0, MAX_RETRY_AFTER_SECS))
+}
+
+// Whatever the site's style, the interval doubles with every rate-limited fetch in a row,
+// plus up to a quarter more so sites limited together don't all come back together.
+// A longer Retry-After wins.
+fn rate_limited_state(site: &Site, limit: &RateLimit, fetched_at: DateTime<Utc>, backoff_count: u32) -> SiteCheckState {
+    let backoff_count = backoff_count + 1;
+    let interval = site.interval_secs.max(1);
+    let delay = (interval * 2i64.pow(backoff_count.min(10))).min(MAX_BACKOFF_SECS.max(interval));
+    let delay = (delay + thread_rng().gen_range(0..=delay / 4)).max(limit.retry_after.unwrap_or(0));
+    SiteCheckState {
+        next_check: fetched_at + chrono::Duration::seconds(delay),
+        backoff_count,
+        learned_interval_secs: None,
     }
-    
-    // If no content, try description
-    if content_text.is_empty() {
-        if let Some(captures) = desc_pattern.captures(xml) {
-            if let Some(desc_match) = captures.get(1) {
-                content_text = clean_xml_entities(desc_match.as_str());
+}
+
+const DEFAULT_PROBE_TAIL_KB: i64 = 64;
+
+// Cheap stand-in for the full body of a large file. None when the server gives us
+// nothing to compare (no validators, or Range unsupported), which forces a full fetch.
+async fn probe_signature(client: &reqwest::Client, url: &str, mode: &str, tail_kb: i64, timeout: Duration) -> Option<String> {
+    match mode {
+        "head" => {
+            let resp = client.head(url).header(USER_AGENT, random_user_agent()).timeout(timeout).send().await.ok()?;
+            if !resp.status().is_success() {
+                return None;
             }
-        }
-    }
-    
-    // If still no content, try extracting from CDATA sections
-    if content_text.is_empty() {
-        let cdata_pattern = Regex::new(r"<!\[CDATA\[(.*?)\]\]>").unwrap_or_else(|_| Regex::new(r"").unwrap());
-        if let Some(captures) = cdata_pattern.captures(xml) {
-            if let Some(cdata_match) = captures.get(1) {
-                content_text = clean_html_content(cdata_match.as_str());
+            let headers = resp.headers();
+            let validators: Vec<String> = [CONTENT_LENGTH, ETAG, LAST_MODIFIED].iter()
+                .filter_map(|name| headers.get(name).and_then(|v| v.to_str().ok()).map(|v| format!("{}={}", name, v)))
+                .collect();
+            (!validators.is_empty()).then(|| validators.join(";"))
+        },
+        "tail" => {
+            let resp = client.get(url)
+                .header(USER_AGENT, random_user_agent())
+                .header(RANGE, format!("bytes=-{}", tail_kb.max(1) * 1024))
+                .timeout(timeout)
+                .send()
+                .await
+                .ok()?;
+            // A 200 means the server ignored the range; drop it before the body downloads
+            if resp.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                return None;
             }
-        }
-    }
-    
-    // Add content with length limit
-    if !content_text.is_empty() {
-        let cutoff = find_word_boundary(&content_text, max_length);
-        let content_preview = if content_text.len() > max_length {
-            format!("{}...", &content_text[..cutoff])
-        } else {
-            content_text
-        };
-        
-        preview.push_str(&content_preview);
-    } else if !preview.is_empty() {
-        // If we only have a title, add a placeholder
-        preview.push_str("[RSS feed detected - content not available]");
-    } else {
-        // Complete fallback
-        preview = "RSS/XML content detected, but couldn't extract readable content.".to_string();
+            // "bytes 1000-2023/2024": the total catches appends that leave the tail intact
+            let total = resp.headers().get(CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.rsplit('/').next())
+                .unwrap_or("*")
+                .to_string();
+            let tail = resp.bytes().await.ok()?;
+            let mut hasher = Sha256::new();
+            hasher.update(&tail);
+            Some(format!("total={};tail={:x}", total, hasher.finalize()))
+        },
+        _ => None,
     }
-    
-    preview
-}
-
-// Extract preview from JSON content
-fn extract_json_preview(json: &str, max_length: usize) -> String {
-    // Simple JSON preview - for now just indicate it's JSON and show a sample
-    let mut preview = String::new();
-    
-    preview.push_str("📊 JSON Data\n\n");
-    
-    // Get a short sample of the JSON
-    let content_preview = if json.len() > max_length {
-        format!("{}...", &json[..find_word_boundary(json, max_length)])
-    } else {
-        json.to_string()
-    };
-    
-    preview.push_str(&content_preview);
-    
-    preview
 }
 
 // Clean HTML content for better readability
-fn clean_html_content(html: &str) -> String {
+pub fn clean_html_content(html: &str) -> String {
     // Initialize regex only once if performance becomes an issue
     let tag_pattern = Regex::new(r"<[^>]*>").unwrap_or_else(|_| Regex::new(r"").unwrap());
     
@@ -460,7 +1490,7 @@ fn clean_html_content(html: &str) -> String {
 }
 
 // Clean XML/HTML entities
-fn clean_xml_entities(text: &str) -> String {
+pub fn clean_xml_entities(text: &str) -> String {
     text.replace("&nbsp;", " ")
         .replace("&amp;", "&")
         .replace("&lt;", "<")
@@ -477,14 +1507,14 @@ fn clean_xml_entities(text: &str) -> String {
 }
 
 // Normalize whitespace
-fn normalize_whitespace(text: &str) -> String {
+pub fn normalize_whitespace(text: &str) -> String {
     // Replace multiple spaces, tabs, newlines with single space
     let ws_pattern = Regex::new(r"\s+").unwrap_or_else(|_| Regex::new(r" ").unwrap());
     ws_pattern.replace_all(text, " ").to_string()
 }
 
 // Clean out JavaScript content that often gets mixed into scraped content
-fn clean_script_content(text: &str) -> String {
+pub fn clean_script_content(text: &str) -> String {
     // Common patterns found in JavaScript that leak into content
     let js_patterns = [
         r"function\s*\([^)]*\)\s*\{[^}]*\}",
@@ -520,90 +1550,115 @@ fn clean_script_content(text: &str) -> String {
     normalize_whitespace(&cleaned)
 }
 
-// Extract preview from Reddit content
-fn extract_reddit_preview(content: &str, max_length: usize) -> String {
-    // Reddit content often has a specific format we can parse
-    let mut preview = String::new();
-    
-    // Try to find post titles with a simple regex
-    let post_pattern = Regex::new(r"GME YOLO [^\n\r]+ r/[^\n\r]+").unwrap_or_else(|_| Regex::new(r"").unwrap());
-    if let Some(post_match) = post_pattern.find(content) {
-        preview.push_str(&format!("📈 {}\n\n", post_match.as_str()));
-    } else {
-        preview.push_str("📈 Reddit Updates\n\n");
+// Bump whenever cleaning or hashing changes what content_hash returns for the same page,
+// then run POST /api/admin/reprocess so stored history is compared like new fetches
+pub const PIPELINE_VERSION: i64 = 1;
+
+// The entries of a feed, the filings of an EDGAR site's response, the records of an FDA
+// query, the posts of a Reddit listing, a social account or a Substack publication, the
+// hits of a news search, the pages of a sitemap, the documents of a Federal Register query
+// or the halts of a halts list, one entry each
+pub fn parse_entries(body: &str, extraction_mode: Option<&str>) -> Option<Vec<feeds::FeedEntry>> {
+    if let Some(halts) = halts::read(body, extraction_mode) {
+        return Some(halts.iter().map(halts::entry).collect());
     }
-    
-    // Try to extract reddit username and post info
-    let username_pattern = Regex::new(r"/u/([A-Za-z0-9_-]+)").unwrap_or_else(|_| Regex::new(r"").unwrap());
-    if let Some(captures) = username_pattern.captures(content) {
-        if let Some(username) = captures.get(1) {
-            preview.push_str(&format!("User: u/{}\n", username.as_str()));
-        }
+    if let Some(documents) = federal_register::read(body, extraction_mode) {
+        return Some(documents.iter().map(federal_register::entry).collect());
     }
-    
-    // Extract subreddit if present
-    let subreddit_pattern = Regex::new(r"r/([A-Za-z0-9_-]+)").unwrap_or_else(|_| Regex::new(r"").unwrap());
-    if let Some(captures) = subreddit_pattern.captures(content) {
-        if let Some(subreddit) = captures.get(1) {
-            preview.push_str(&format!("Subreddit: r/{}\n\n", subreddit.as_str()));
-        }
+    if let Some(posts) = social::read(body, extraction_mode) {
+        return Some(posts);
     }
-    
-    // Clean the overall content
-    let cleaned = content
-        .lines()
-        .filter(|line| {
-            !line.contains("function") && 
-            !line.contains("var ") && 
-            !line.contains(".js") && 
-            !line.trim().is_empty() && 
-            line.len() > 5
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
-    
-    // Add limited content
-    if !cleaned.is_empty() {
-        let cutoff = find_word_boundary(&cleaned, max_length);
-        let content_preview = if cleaned.len() > max_length {
-            format!("{}...", &cleaned[..cutoff])
-        } else {
-            cleaned
-        };
-        
-        preview.push_str(&content_preview);
+    if let Some(posts) = substack::read(body, extraction_mode) {
+        return Some(posts);
+    }
+    if let Some(hits) = news_search::read(body, extraction_mode) {
+        return Some(hits);
+    }
+    if let Some(pages) = sitemap::read(body, extraction_mode) {
+        return Some(pages);
+    }
+    match extraction_mode {
+        Some(edgar::EXTRACTION_MODE) => edgar::parse(body),
+        Some(fda::EXTRACTION_MODE) => fda::parse(body),
+        None if fda::is_openfda(body) => fda::parse(body),
+        Some(reddit::EXTRACTION_MODE) => reddit::parse(body).or_else(|| feeds::parse_feed(body)),
+        None if reddit::is_listing_response(body) => reddit::parse(body),
+        _ => feeds::parse_feed(body),
     }
-    
-    preview
 }
 
-// Find a reasonable word boundary to cut text at
-fn find_word_boundary(text: &str, max_length: usize) -> usize {
-    if text.len() <= max_length {
-        return text.len();
+// Hash used for change detection: the entry set for feeds, otherwise the cleaned content
+pub fn content_hash(body: &str, selector: Option<&str>, ignore: &[String], fold_case: bool) -> String {
+    match feeds::parse_feed(body) {
+        Some(entries) => feeds::entries_hash(&entries),
+        None => cleaned_content_hash(body, selector, ignore, fold_case),
     }
-    
-    // Try to find a space, period, comma, or other natural break
-    let break_chars = [' ', '.', ',', ';', ':', '!', '?', '\n', '\r'];
-    
-    // Start from max_length and go backwards
-    for i in (0..max_length).rev() {
-        if i < text.len() {
-            let c = text.chars().nth(i).unwrap();
-            if break_chars.contains(&c) {
-                return i + 1; // Include the break character
-            }
-        }
+}
+
+// SHA-256 of the content after removing volatile elements
+fn cleaned_content_hash(body: &str, selector: Option<&str>, ignore: &[String], fold_case: bool) -> String {
+    sha256_hex(clean_content_for_comparison(body, selector, ignore), fold_case)
+}
+
+fn sha256_hex(mut cleaned_content: String, fold_case: bool) -> String {
+    if fold_case {
+        cleaned_content = cleaned_content.to_lowercase();
     }
-    
-    // If no good break found, just use the max_length
-    max_length
+    let mut hasher = Sha256::new();
+    hasher.update(cleaned_content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+// The main article of a page for sites in readability extraction mode, found after the
+// site's ignore patterns are stripped. None for other sites and for pages without an
+// article, which are hashed and previewed as usual.
+pub fn readable_article(body: &str, extraction_mode: Option<&str>, ignore: &[String]) -> Option<Article> {
+    match extraction_mode {
+        Some("readability") if ignore.is_empty() => readability::extract(body),
+        Some("readability") => readability::extract(&strip_ignored(body, ignore)),
+        _ => None,
+    }
+}
+
+// Hash of an article's title and text, so nothing around the article makes a change
+pub fn article_hash(article: &Article, fold_case: bool) -> String {
+    sha256_hex(article_comparison_text(article), fold_case)
+}
+
+fn article_comparison_text(article: &Article) -> String {
+    let text = format!("{}\n\n{}", article.title.as_deref().unwrap_or(""), article.text);
+    normalize_whitespace(&normalize_unicode(&text))
+}
+
+// CDN nodes serving the same page can differ in Unicode composition (é vs e + ́)
+// and sprinkle invisible formatting characters; neither is a real change
+pub fn normalize_unicode(text: &str) -> String {
+    text.nfc().filter(|c| !is_invisible_format_char(*c)).collect()
+}
+
+fn is_invisible_format_char(c: char) -> bool {
+    matches!(c,
+        '\u{00AD}'                  // Soft hyphen
+        | '\u{061C}'                // Arabic letter mark
+        | '\u{200B}'..='\u{200F}'   // Zero-width space/joiners, LRM, RLM
+        | '\u{202A}'..='\u{202E}'   // Bidi embeddings and overrides
+        | '\u{2060}'..='\u{2064}'   // Word joiner, invisible operators
+        | '\u{2066}'..='\u{2069}'   // Bidi isolates
+        | '\u{FEFF}'                // Zero-width no-break space / BOM
+    )
 }
 
 // Add this new function to clean content before comparing (for better delta detection)
-fn clean_content_for_comparison(content: &str) -> String {
-    // Step 1: Remove common dynamic elements
-    let mut cleaned = content.to_string();
+pub fn clean_content_for_comparison(content: &str, selector: Option<&str>, ignore: &[String]) -> String {
+    // A configured site selector replaces the generic content heuristics in step 2
+    let region = selector.and_then(|sel| select_region(content, sel));
+    let use_heuristics = region.is_none();
+    
+    // Step 1: Remove the site's own ignore patterns, then common dynamic elements
+    let mut cleaned = normalize_unicode(&region.unwrap_or_else(|| content.to_string()));
+    if !ignore.is_empty() {
+        cleaned = strip_ignored(&cleaned, ignore);
+    }
     
     // Remove timestamps, dates, and common dynamic patterns
     let patterns_to_remove = [
@@ -612,9 +1667,9 @@ fn clean_content_for_comparison(content: &str) -> String {
         r"\d{1,2}/\d{1,2}/\d{2,4}",                 // Date (MM/DD/YYYY)
         r"\d{4}-\d{2}-\d{2}",                       // ISO date (YYYY-MM-DD)
         r"[A-Za-z]{3},\s\d{1,2}\s[A-Za-z]{3}\s\d{4}", // Day, DD Mon YYYY
-        r"viewcount[\"']?\s*:\s*[\"']?\d+",         // View counts
-        r"[\"']timestamp[\"']\s*:\s*\d+",           // Timestamps in JSON
-        r"data-timestamp=[\"']\d+[\"']",            // Data timestamps
+        r#"viewcount["']?\s*:\s*["']?\d+"#,          // View counts
+        r#"["']timestamp["']\s*:\s*\d+"#,              // Timestamps in JSON
+        r#"data-timestamp=["']\d+["']"#,              // Data timestamps
         r"<script\b[^<]*(?:(?!<\/script>)<[^<]*)*<\/script>", // Scripts
         r"<iframe\b[^<]*(?:(?!<\/iframe>)<[^<]*)*<\/iframe>", // iframes
         r"<!--.*?-->",                               // HTML comments
@@ -631,13 +1686,17 @@ fn clean_content_for_comparison(content: &str) -> String {
     // Step 2: Optional - extract only the relevant content
     // This depends on the website structure, but we can add a generic implementation
     // For example, focus on main content areas and ignore headers, footers, sidebars
-    let content_selectors = [
-        r"<article.*?>(.*?)</article>",
-        r"<main.*?>(.*?)</main>",
-        r"<div.*?class=[\"\']content[\"\'].*?>(.*?)</div>",
-        r"<div.*?class=[\"\']post-content[\"\'].*?>(.*?)</div>",
-        r"<div.*?id=[\"\']content[\"\'].*?>(.*?)</div>",
-    ];
+    let content_selectors: &[&str] = if use_heuristics {
+        &[
+            r"<article.*?>(.*?)</article>",
+            r"<main.*?>(.*?)</main>",
+            r#"<div.*?class=["']content["'].*?>(.*?)</div>"#,
+            r#"<div.*?class=["']post-content["'].*?>(.*?)</div>"#,
+            r#"<div.*?id=["']content["'].*?>(.*?)</div>"#,
+        ]
+    } else {
+        &[]
+    };
     
     let mut extracted_content = String::new();
     for selector in content_selectors {
@@ -660,25 +1719,360 @@ fn clean_content_for_comparison(content: &str) -> String {
     normalize_whitespace(&cleaned)
 }
 
-// Add function to find sentence boundaries for better excerpt cutting
-fn find_sentence_boundary(text: &str, max_length: usize) -> usize {
-    if text.len() <= max_length {
-        return text.len();
+pub fn parse_selector(selector: &str) -> Result<Selector, String> {
+    Selector::parse(selector).map_err(|e| e.to_string())
+}
+
+// Content published more than the site's max_content_age_hours before it was fetched.
+// Content without a date is never stale.
+fn is_stale(site: &Site, config: &AppConfig, published: Option<DateTime<Utc>>, fetched_at: DateTime<Utc>) -> bool {
+    let hours = site.max_content_age_hours.unwrap_or(config.max_content_age_hours);
+    hours > 0 && published.is_some_and(|published| fetched_at - published > chrono::Duration::hours(hours))
+}
+
+// Store a latency sample when the source gave a published time
+async fn record_latency(storage: &SharedStorage, site_id: i64, update_id: i64, published: Option<DateTime<Utc>>, detected_at: DateTime<Utc>) -> Option<i64> {
+    let sample = LatencySample { site_id, update_id, published: published?, detected_at };
+    let _ = storage.record_latency(&sample).await;
+    Some(sample.latency_secs())
+}
+
+// Captures and stores a screenshot of a change; failures are logged and only leave the
+// update without one
+async fn screenshot(storage: &SharedStorage, browser: &screenshots::Browser, url: &str, update_id: i64) -> bool {
+    let png = match browser.screenshot(url, update_id).await {
+        Ok(png) => png,
+        Err(e) => {
+            warn!("Screenshot failed: {}", e);
+            return false;
+        },
+    };
+    match storage.insert_screenshot(update_id, Utc::now(), &png).await {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("Storing the screenshot failed: {}", e);
+            false
+        },
     }
-    
-    // Look for sentence-ending punctuation
-    let sentence_breaks = ['.', '!', '?', '\n', '\r'];
-    
-    // Start from max_length and go backwards
-    for i in (0..max_length).rev() {
-        if i < text.len() {
-            let c = text.chars().nth(i).unwrap();
-            if sentence_breaks.contains(&c) {
-                return i + 1; // Include the punctuation
-            }
+}
+
+// Send an update to stream subscribers. One that matched rules is also stored as an
+// alert awaiting acknowledgement and sent as a separate alert event.
+async fn broadcast_update(storage: &SharedStorage, tx: &Sender<StreamEvent>, msg: UpdateMessage) {
+    if !msg.matched_rules.is_empty() {
+        info!(update_id = msg.update_id, rules = msg.matched_rules.len(), "Update matched alert rules");
+        let alert = Alert {
+            id: 0,
+            update_id: msg.update_id,
+            site_id: msg.site_id,
+            rules: msg.matched_rules.iter().map(|r| r.name.as_str()).collect::<Vec<_>>().join(", "),
+            created_at: msg.timestamp,
+            acknowledged_at: None,
+            acknowledged_by: None,
+        };
+        if let Err(e) = storage.insert_alert(&alert).await {
+            warn!("Failed to store alert: {}", e);
         }
+        let _ = tx.send(StreamEvent::Alert(msg.clone()));
     }
-    
-    // Fall back to word boundary if no sentence break found
-    find_word_boundary(text, max_length)
-}
\ No newline at end of file
+    let _ = tx.send(StreamEvent::Update(msg));
+}
+
+// Outer HTML of every element matching a site's selector, or None if the selector
+// is invalid or matches nothing (callers then fall back to the whole page)
+pub fn select_region(html: &str, selector: &str) -> Option<String> {
+    let selector = parse_selector(selector).ok()?;
+    let document = Html::parse_document(html);
+    let parts: Vec<String> = document.select(&selector).map(|el| el.html()).collect();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(id: i64) -> Site {
+        Site {
+            id,
+            url: format!("https://example.com/{}", id),
+            interval_secs: 60,
+            style: "none".into(),
+            last_checked: None,
+            last_updated: None,
+            status: None,
+            snoozed_until: None,
+            last_success: None,
+            selector: None,
+            priority: 0,
+            retention_count: None,
+            retention_days: None,
+            probe_mode: None,
+            probe_tail_kb: None,
+            probe_signature: None,
+            assert_selector: None,
+            assert_pattern: None,
+            assert_min_length: None,
+            ignore_patterns: Vec::new(),
+            legal_hold: false,
+            notify_burst_limit: None,
+            notify_window_secs: None,
+            proxies: Vec::new(),
+            backoff_count: 0,
+            backoff_until: None,
+            backoff_reason: None,
+            consecutive_failures: 0,
+            health: None,
+            adaptive_interval_secs: None,
+            active_window: None,
+            burst_interval_secs: None,
+            burst_until: None,
+            burst_remaining_secs: None,
+            timeout_secs: None,
+            max_redirects: None,
+            max_body_bytes: None,
+            extraction_mode: None,
+            preview_length: None,
+            extraction_script: None,
+            plugin: None,
+            link_pattern: None,
+            archive_links: false,
+            max_content_age_hours: None,
+            crawl_pattern: None,
+            crawl_depth: None,
+            crawl_max_pages: None,
+            max_quiet_hours: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn normalizes_cosmetic_unicode_differences() {
+        let composed = "<p>Caf\u{00E9} news</p>";
+        let decomposed = "<p>Cafe\u{0301} n\u{200B}ews\u{FEFF}</p>";
+        assert_eq!(normalize_unicode(composed), normalize_unicode(decomposed));
+        assert_eq!(content_hash(composed, None, &[], false), content_hash(decomposed, None, &[], false));
+        assert_ne!(content_hash("<p>NEWS</p>", None, &[], false), content_hash("<p>news</p>", None, &[], false));
+        assert_eq!(content_hash("<p>NEWS</p>", None, &[], true), content_hash("<p>news</p>", None, &[], true));
+    }
+
+    #[test]
+    fn domain_key_groups_subdomains() {
+        assert_eq!(domain_key("https://www.sec.gov/news").as_deref(), Some("sec.gov"));
+        assert_eq!(domain_key("https://efts.sec.gov/LATEST").as_deref(), Some("sec.gov"));
+        assert_eq!(domain_key("https://www.betaville.co.uk/").as_deref(), Some("betaville.co.uk"));
+        assert_eq!(domain_key("https://www.mofcom.gov.cn/xwfb/").as_deref(), Some("mofcom.gov.cn"));
+        assert_eq!(domain_key("http://127.0.0.1:8080/").as_deref(), Some("127.0.0.1"));
+    }
+
+    #[test]
+    fn scheduler_skips_stale_and_removed_entries() {
+        let mut scheduler = Scheduler::new(chrono::Duration::seconds(5), Arc::default(), Arc::default());
+        let now = Utc::now();
+        scheduler.insert_site(site(1));
+        scheduler.insert_site(site(2));
+
+        // Rescheduling site 1 into the future leaves a stale entry at the old time
+        scheduler.schedule(1, now + chrono::Duration::seconds(60), 0);
+        scheduler.remove_site(2);
+
+        assert!(scheduler.pop_due(now + chrono::Duration::seconds(1)).is_empty());
+        let due = scheduler.pop_due(now + chrono::Duration::seconds(61));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0.id, 1);
+    }
+
+    fn outcome(site_id: i64) -> CheckOutcome {
+        CheckOutcome {
+            site_id,
+            checked_at: Utc::now(),
+            success: true,
+            changed: false,
+            update_id: None,
+            triggers: Vec::new(),
+            http_status: None,
+            duration_ms: 0,
+            error: None,
+            proxy: None,
+            response: ResponseMeta::default(),
+        }
+    }
+
+    #[test]
+    fn scheduler_coalesces_manual_and_scheduled_checks() {
+        let mut scheduler = Scheduler::new(chrono::Duration::seconds(5), Arc::default(), Arc::default());
+        let now = Utc::now();
+        scheduler.insert_site(site(1));
+        scheduler.schedule(1, now + chrono::Duration::seconds(3), 0);
+
+        // Next regular check is inside the window, so the manual request waits for it
+        let (reply, mut first) = oneshot::channel();
+        scheduler.check_now(1, reply, now);
+        assert!(scheduler.pop_due(now).is_empty());
+        let due = scheduler.pop_due(now + chrono::Duration::seconds(3));
+        assert_eq!(due.len(), 1);
+
+        // A second request while the fetch is running joins it
+        let (reply, mut second) = oneshot::channel();
+        scheduler.check_now(1, reply, now);
+        assert!(scheduler.pop_due(now + chrono::Duration::seconds(4)).is_empty());
+
+        let state = SiteCheckState { next_check: now + chrono::Duration::seconds(60), backoff_count: 0, learned_interval_secs: None };
+        scheduler.finish(1, state, outcome(1));
+        let expected = vec![CheckTrigger::Scheduled, CheckTrigger::Manual, CheckTrigger::Manual];
+        assert_eq!(first.try_recv().unwrap().triggers, expected);
+        assert_eq!(second.try_recv().unwrap().triggers, expected);
+    }
+
+    #[test]
+    fn scheduler_never_overlaps_checks_of_one_site() {
+        let mut scheduler = Scheduler::new(chrono::Duration::seconds(5), Arc::default(), Arc::default());
+        scheduler.insert_site(site(1));
+        let now = Utc::now();
+        assert_eq!(scheduler.pop_due(now).len(), 1);
+
+        // Unsnoozing while the fetch runs queues another check; it must wait
+        scheduler.schedule(1, now, 0);
+        assert!(scheduler.pop_due(now).is_empty());
+        assert_eq!(scheduler.metrics.overlap_skips.get(), 1);
+
+        let state = SiteCheckState { next_check: now, backoff_count: 0, learned_interval_secs: None };
+        scheduler.finish(1, state, outcome(1));
+        assert_eq!(scheduler.pop_due(now).len(), 1);
+    }
+
+    #[test]
+    fn reloading_sites_keeps_running_checks_in_flight() {
+        let mut scheduler = Scheduler::new(chrono::Duration::seconds(5), Arc::default(), Arc::default());
+        scheduler.insert_site(site(1));
+        let now = Utc::now();
+        assert_eq!(scheduler.pop_due(now).len(), 1);
+
+        // ReloadAll re-adds every site as due now; the running fetch still blocks a second one
+        scheduler.clear();
+        scheduler.insert_site(site(1));
+        assert!(scheduler.pop_due(Utc::now()).is_empty());
+
+        let state = SiteCheckState { next_check: now, backoff_count: 0, learned_interval_secs: None };
+        scheduler.finish(1, state, outcome(1));
+        assert_eq!(scheduler.pop_due(Utc::now()).len(), 1);
+    }
+
+    #[test]
+    fn manual_check_pulls_distant_schedule_forward() {
+        let mut scheduler = Scheduler::new(chrono::Duration::seconds(5), Arc::default(), Arc::default());
+        let now = Utc::now();
+        scheduler.insert_site(site(1));
+        scheduler.schedule(1, now + chrono::Duration::seconds(60), 0);
+
+        let (reply, mut rx) = oneshot::channel();
+        scheduler.check_now(1, reply, now);
+        assert_eq!(scheduler.pop_due(now).len(), 1);
+        // The replaced regular entry is stale and doesn't fire a second fetch
+        assert!(scheduler.pop_due(now + chrono::Duration::seconds(61)).is_empty());
+
+        let state = SiteCheckState { next_check: now + chrono::Duration::seconds(60), backoff_count: 0, learned_interval_secs: None };
+        scheduler.finish(1, state, outcome(1));
+        assert_eq!(rx.try_recv().unwrap().triggers, vec![CheckTrigger::Manual]);
+    }
+
+    #[test]
+    fn starting_a_burst_makes_the_site_due_now() {
+        let mut scheduler = Scheduler::new(chrono::Duration::seconds(5), Arc::default(), Arc::default());
+        let now = Utc::now();
+        scheduler.insert_site(site(1));
+        scheduler.schedule(1, now + chrono::Duration::seconds(600), 0);
+
+        let burst = Site { burst_interval_secs: Some(10), burst_until: Some(now + chrono::Duration::hours(1)), ..site(1) };
+        scheduler.update_site(burst.clone());
+        assert_eq!(scheduler.pop_due(Utc::now()).len(), 1);
+        // Settings refreshed again during the same burst leave the queue alone
+        scheduler.finish(1, SiteCheckState { next_check: now + chrono::Duration::seconds(10), backoff_count: 0, learned_interval_secs: None }, outcome(1));
+        scheduler.update_site(burst);
+        assert!(scheduler.pop_due(Utc::now()).is_empty());
+    }
+
+    // Answers one request with the raw response, whatever was asked
+    async fn serve_once(response: &'static [u8]) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.read(&mut [0; 1024]).await;
+            let _ = socket.write_all(response).await;
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn reads_bodies_in_their_charset_up_to_the_cap() {
+        let fetch = |response| async move { reqwest::get(serve_once(response).await).await.unwrap() };
+        let latin1 = b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=\"ISO-8859-1\"\r\nContent-Length: 4\r\nConnection: close\r\n\r\ncaf\xe9";
+        assert_eq!(read_body(fetch(latin1).await, 100, None).await.map(|body| body.text).as_deref(), Ok("caf\u{e9}"));
+
+        let announced = b"HTTP/1.1 200 OK\r\nContent-Length: 5000\r\nConnection: close\r\n\r\n";
+        assert!(read_body(fetch(announced).await, 100, None).await.err().unwrap().contains("5000 bytes"));
+        // Without a length the cap applies while reading
+        let unannounced = b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nwell over ten bytes of body";
+        assert!(read_body(fetch(unannounced).await, 10, None).await.is_err());
+    }
+
+    #[test]
+    fn rate_limits_back_off_exponentially_and_honor_retry_after() {
+        let now: DateTime<Utc> = "2024-05-01T12:00:00Z".parse().unwrap();
+        assert_eq!(parse_retry_after("120", now), Some(120));
+        assert_eq!(parse_retry_after("Wed, 01 May 2024 12:05:00 GMT", now), Some(300));
+        assert_eq!(parse_retry_after("Wed, 01 May 2024 11:00:00 GMT", now), Some(0));
+        assert_eq!(parse_retry_after("soon", now), None);
+
+        let site = Site::new("https://example.com", 60, "none");
+        let limit = RateLimit { status: 429, retry_after: None };
+        for (count, base) in [(0, 120), (1, 240), (4, 1920), (20, MAX_BACKOFF_SECS)] {
+            let state = rate_limited_state(&site, &limit, now, count);
+            let delay = (state.next_check - now).num_seconds();
+            assert_eq!(state.backoff_count, count + 1);
+            assert!(delay >= base && delay <= base + base / 4, "{} after {} failures", delay, count);
+        }
+        let limit = RateLimit { status: 503, retry_after: Some(3600) };
+        assert_eq!(rate_limited_state(&site, &limit, now, 0).next_check, now + chrono::Duration::seconds(3600));
+        assert_eq!(limit.describe(), "HTTP 503 (Retry-After 3600s)");
+    }
+
+    // The defaults plus `yaml`, with the guard off for local test servers
+    fn local_config(yaml: &str) -> Arc<AppConfig> {
+        let guard = super::super::netguard::NetworkGuard::new(false, &[]).unwrap();
+        Arc::new(super::super::app_config(&serde_yaml::from_str(yaml).unwrap(), guard, false))
+    }
+
+    // Checks a site that was OK against one canned response and returns its status after
+    async fn status_after_check(site: Site, config: &Arc<AppConfig>) -> Option<String> {
+        let storage: SharedStorage = Arc::new(super::super::storage::MemoryStorage::new());
+        let id = storage.insert_site(&site).await.unwrap();
+        storage.mark_checked(id, Utc::now(), true).await.unwrap();
+        let site = storage.get_site(id).await.unwrap().unwrap();
+        let (_, outcome) = check_site(site, &config.proxies, storage.clone(), tokio::sync::broadcast::channel(16).0, 0, config).await;
+        assert!(!outcome.success);
+        storage.get_site(id).await.unwrap().unwrap().status
+    }
+
+    #[tokio::test]
+    async fn bodies_over_the_cap_fail_the_site() {
+        let config = local_config("{}");
+        let url = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 5000\r\nConnection: close\r\n\r\n").await;
+        let site = Site { max_body_bytes: Some(100), ..Site::new(&url, 60, "none") };
+        assert_eq!(status_after_check(site, &config).await.as_deref(), Some("ERROR"));
+    }
+
+    #[tokio::test]
+    async fn failed_crawl_pages_fail_the_site() {
+        let config = local_config("{}");
+        // Nothing listens on port 1, so page 2 can't be fetched
+        let url = serve_once(b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n<a href=\"http://127.0.0.1:1/?page=2\">2</a>").await;
+        let site = Site { crawl_pattern: Some("page=".into()), ..Site::new(&url, 60, "none") };
+        assert_eq!(status_after_check(site, &config).await.as_deref(), Some("ERROR"));
+    }
+}
@@ -0,0 +1,88 @@
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::process::Command;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const WINDOW_SIZE: &str = "--window-size=1280,2000";
+
+// Headless Chrome or Chromium at browser_path; browser mode is off without one
+#[derive(Clone, Debug)]
+pub struct Browser {
+    pub path: String,
+    pub timeout: Duration,
+}
+
+impl Browser {
+    // The page as the browser renders it, as a PNG. The browser writes the file itself and
+    // is killed once the timeout passes.
+    pub async fn screenshot(&self, url: &str, update_id: i64) -> Result<Vec<u8>, String> {
+        let file = screenshot_path(update_id);
+        let mut command = Command::new(&self.path);
+        command
+            .args(["--headless", "--disable-gpu", "--hide-scrollbars", WINDOW_SIZE])
+            .arg(format!("--screenshot={}", file.display()))
+            .arg(url)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true);
+        let status = match tokio::time::timeout(self.timeout, command.status()).await {
+            Ok(Ok(status)) => status,
+            Ok(Err(e)) => return Err(format!("starting {} failed: {}", self.path, e)),
+            Err(_) => return Err(format!("no screenshot after {} seconds", self.timeout.as_secs())),
+        };
+        let data = tokio::fs::read(&file).await;
+        let _ = tokio::fs::remove_file(&file).await;
+        match data {
+            Ok(data) if data.starts_with(PNG_SIGNATURE) => Ok(data),
+            Ok(_) => Err("the browser wrote something other than a PNG".into()),
+            Err(_) => Err(format!("the browser exited with {} without a screenshot", status)),
+        }
+    }
+}
+
+// One file per capture, so concurrent checks never share one
+fn screenshot_path(update_id: i64) -> PathBuf {
+    std::env::temp_dir().join(format!("scraper-screenshot-{}-{}.png", std::process::id(), update_id))
+}
+
+// GET /api/updates/{id}/screenshot: the page as it looked when the change was detected
+pub async fn get_screenshot(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let update_id = path.into_inner();
+    match data.storage.update_screenshot(update_id).await {
+        Ok(Some(png)) => HttpResponse::Ok().content_type("image/png").body(png),
+        Ok(None) => HttpResponse::NotFound().body(format!("Update with ID {} has no screenshot", update_id)),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    // Stands in for Chrome: writes a PNG to the --screenshot path, or sleeps past the timeout
+    fn fake_browser(name: &str, script: &str) -> Browser {
+        let path = std::env::temp_dir().join(format!("scraper-{}-{}.sh", name, std::process::id()));
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Browser { path: path.display().to_string(), timeout: Duration::from_secs(2) }
+    }
+
+    #[tokio::test]
+    async fn reads_the_png_the_browser_writes() {
+        let browser = fake_browser("browser", r#"for arg; do case "$arg" in --screenshot=*) printf '\211PNG\r\n\032\npixels' > "${arg#--screenshot=}";; esac; done"#);
+        let png = browser.screenshot("https://a.example", 1).await.unwrap();
+        assert!(png.starts_with(PNG_SIGNATURE) && png.ends_with(b"pixels"));
+        assert!(!screenshot_path(1).exists());
+
+        let silent = fake_browser("silent", "exit 1");
+        assert!(silent.screenshot("https://a.example", 2).await.unwrap_err().contains("without a screenshot"));
+        let hung = Browser { timeout: Duration::from_millis(200), ..fake_browser("hung", "sleep 5") };
+        assert!(hung.screenshot("https://a.example", 3).await.unwrap_err().starts_with("no screenshot"));
+        for name in ["browser", "silent", "hung"] {
+            let _ = std::fs::remove_file(std::env::temp_dir().join(format!("scraper-{}-{}.sh", name, std::process::id())));
+        }
+    }
+}
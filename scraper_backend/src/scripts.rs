@@ -0,0 +1,294 @@
+use super::feeds::FeedEntry;
+use super::items::parse_date;
+use super::readability::Article;
+use super::scraper::{parse_selector, SchedulerEvent};
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use ego_tree::NodeId;
+use regex::Regex;
+use reqwest::Url;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope};
+use scraper::{ElementRef, Html};
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+// A page taking longer than this to extract is treated like one the script failed on
+const TIME_LIMIT: Duration = Duration::from_secs(2);
+pub const MAX_ITEMS: usize = 100;
+const MAX_MATCHES: usize = 10_000;
+
+// What a site's script got out of a page; used for the hash, the preview, alerts and items
+#[derive(Serialize, Debug, Clone)]
+pub struct ScriptOutput {
+    pub title: Option<String>,
+    pub text: String,
+    pub items: Vec<FeedEntry>,
+}
+
+impl ScriptOutput {
+    // Title and text followed by a line per item, so a new item is a change like new text
+    pub fn article(&self) -> Article {
+        let items = self.items.iter()
+            .map(|item| [item.title.as_deref(), item.link.as_deref()].into_iter().flatten().collect::<Vec<_>>().join(" "));
+        let text = std::iter::once(self.text.clone()).chain(items).filter(|t| !t.is_empty()).collect::<Vec<_>>();
+        Article { title: self.title.clone(), text: text.join("\n\n") }
+    }
+}
+
+// An element of the parsed page, as scripts see it
+#[derive(Clone)]
+struct Element {
+    document: Rc<Html>,
+    id: NodeId,
+}
+
+impl Element {
+    fn get(&self) -> ElementRef<'_> {
+        self.document.tree.get(self.id).and_then(ElementRef::wrap).expect("element of its own document")
+    }
+
+    fn select(&mut self, selector: &str) -> Result<Array, Box<EvalAltResult>> {
+        let selector = parse_selector(selector)?;
+        Ok(self.get().select(&selector).take(MAX_MATCHES)
+            .map(|el| Dynamic::from(Element { document: self.document.clone(), id: el.id() }))
+            .collect())
+    }
+
+    fn first(&mut self, selector: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let selector = parse_selector(selector)?;
+        Ok(self.get().select(&selector).next()
+            .map_or(Dynamic::UNIT, |el| Dynamic::from(Element { document: self.document.clone(), id: el.id() })))
+    }
+
+    fn text(&mut self) -> String {
+        collapse(self.get().text())
+    }
+
+    fn attr(&mut self, name: &str) -> Dynamic {
+        self.get().value().attr(name).map_or(Dynamic::UNIT, |value| value.into())
+    }
+}
+
+fn collapse<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Only what the script API below registers: rhai itself has no access to files, the
+// network or processes, and eval is switched off
+fn engine(deadline: Instant) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_array_size(MAX_MATCHES)
+        .set_max_map_size(1_000)
+        .disable_symbol("eval")
+        .on_progress(move |_| (Instant::now() > deadline).then_some(Dynamic::UNIT))
+        .on_print(|text| debug!("Extraction script: {}", text))
+        .on_debug(|text, _, _| debug!("Extraction script: {}", text));
+    engine.register_type_with_name::<Element>("Element")
+        .register_fn("parse", |html: &str| {
+            let document = Rc::new(Html::parse_document(html));
+            let id = document.root_element().id();
+            Element { document, id }
+        })
+        .register_fn("select", Element::select)
+        .register_fn("first", Element::first)
+        .register_fn("attr", Element::attr)
+        .register_get("text", Element::text)
+        .register_get("html", |el: &mut Element| el.get().html())
+        .register_fn("captures", |text: &str, pattern: &str| -> Result<Array, Box<EvalAltResult>> {
+            let pattern = Regex::new(pattern).map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?;
+            Ok(pattern.captures_iter(text).take(MAX_MATCHES)
+                .filter_map(|c| c.get(1).or_else(|| c.get(0)).map(|m| m.as_str().into()))
+                .collect())
+        });
+    engine
+}
+
+// Refuses a script that doesn't parse
+pub fn validate(script: &str) -> Result<(), String> {
+    if script.trim().is_empty() {
+        return Err("The extraction script is empty".into());
+    }
+    engine(Instant::now()).compile(script).map(|_| ()).map_err(|e| format!("Invalid script: {}", e))
+}
+
+// Runs a site's script on a page, without leaving the current thread. The script gets
+// the page as `body` and its address as `url` and returns a map of title, text (a string
+// or an array of paragraphs) and items (maps of title, link, summary and published).
+pub fn extract(script: &str, body: &str, page_url: &str) -> Result<ScriptOutput, String> {
+    let engine = engine(Instant::now() + TIME_LIMIT);
+    let mut scope = Scope::new();
+    scope.push_constant("body", body.to_string());
+    scope.push_constant("url", page_url.to_string());
+    let value = engine.eval_with_scope::<Dynamic>(&mut scope, script).map_err(|e| match *e {
+        EvalAltResult::ErrorTerminated(..) => format!("the script took longer than {}s", TIME_LIMIT.as_secs()),
+        e => format!("the script failed: {}", e),
+    })?;
+    let output = output(value, page_url)?;
+    // A page the script finds nothing on has most likely changed its layout
+    if output.title.is_none() && output.text.is_empty() && output.items.is_empty() {
+        return Err("the script extracted nothing from the page".into());
+    }
+    Ok(output)
+}
+
+// Runs a site's script on a page off the async workers
+pub async fn run(script: &str, body: &str, page_url: &str) -> Result<ScriptOutput, String> {
+    let (script, body, page_url) = (script.to_string(), body.to_string(), page_url.to_string());
+    tokio::task::spawn_blocking(move || extract(&script, &body, &page_url)).await
+        .unwrap_or_else(|e| Err(format!("the script crashed: {}", e)))
+}
+
+// Strings as they are, elements as their text, anything else as missing
+fn string(value: Option<&Dynamic>) -> Option<String> {
+    let value = value?;
+    let text = match value.clone().try_cast::<Element>() {
+        Some(mut el) => el.text(),
+        None => value.clone().into_string().ok()?,
+    };
+    Some(text.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+fn output(value: Dynamic, page_url: &str) -> Result<ScriptOutput, String> {
+    if value.is_string() {
+        return Ok(ScriptOutput { title: None, text: string(Some(&value)).unwrap_or_default(), items: Vec::new() });
+    }
+    let map = value.try_cast::<Map>().ok_or("the script must return a map of title, text and items")?;
+    let text = match map.get("text") {
+        Some(text) if text.is_array() => text.clone().cast::<Array>().iter()
+            .filter_map(|paragraph| string(Some(paragraph)))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        text => string(text).unwrap_or_default(),
+    };
+    let base = Url::parse(page_url).ok();
+    let mut items = Vec::new();
+    for item in map.get("items").and_then(|items| items.clone().try_cast::<Array>()).unwrap_or_default() {
+        let Some(item) = item.try_cast::<Map>() else {
+            return Err("items must be maps of title, link, summary and published".into());
+        };
+        let field = |name: &str| string(item.get(name));
+        items.extend(entry([field("guid"), field("title"), field("link"), field("summary"), field("published")], base.as_ref()));
+        if items.len() == MAX_ITEMS {
+            break;
+        }
+    }
+    Ok(ScriptOutput { title: string(map.get("title")), text, items })
+}
+
+// An item from its guid, title, link, summary and published date; the link is resolved
+// against the page, and it is identified by its guid, else its link, else its title
+pub fn entry([guid, title, link, summary, published]: [Option<String>; 5], base: Option<&Url>) -> Option<FeedEntry> {
+    let link = link.map(|href| match base.and_then(|base| base.join(&href).ok()) {
+        Some(url) => url.to_string(),
+        None => href,
+    });
+    let guid = guid.or_else(|| link.clone()).or_else(|| title.clone())?;
+    Some(FeedEntry { guid, title, link, summary, published: published.as_deref().and_then(parse_date) })
+}
+
+#[derive(Deserialize)]
+pub struct ScriptPayload {
+    script: String,
+}
+
+#[derive(Deserialize)]
+pub struct TestPayload {
+    // None tests the site's stored script
+    script: Option<String>,
+}
+
+// PUT /api/sites/{id}/script: store the site's extraction script, replacing any other
+pub async fn put_script(data: web::Data<AppState>, path: web::Path<i64>, payload: web::Json<ScriptPayload>) -> impl Responder {
+    let id = path.into_inner();
+    if let Err(e) = validate(&payload.script) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    set_script(&data, id, Some(&payload.script)).await
+}
+
+// DELETE /api/sites/{id}/script: back to the built-in extraction
+pub async fn delete_script(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    set_script(&data, path.into_inner(), None).await
+}
+
+async fn set_script(data: &AppState, id: i64, script: Option<&str>) -> HttpResponse {
+    match data.storage.get_site(id).await {
+        Ok(Some(_)) => {},
+        Ok(None) => return HttpResponse::NotFound().body(format!("Site with ID {} not found", id)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+    if let Err(e) = data.storage.set_extraction_script(id, script).await {
+        return HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+    }
+    info!(site_id = id, "Extraction script {}", if script.is_some() { "stored" } else { "removed" });
+    let _ = data.scheduler.send(SchedulerEvent::SiteUpdated(id));
+    HttpResponse::Ok().finish()
+}
+
+// POST /api/sites/{id}/script/test: run a script on the site's latest stored page without
+// storing anything, answering with what it extracts or 422 with why it failed
+pub async fn test_script(data: web::Data<AppState>, path: web::Path<i64>, payload: web::Json<TestPayload>) -> impl Responder {
+    let id = path.into_inner();
+    let site = match data.storage.get_site(id).await {
+        Ok(Some(site)) => site,
+        Ok(None) => return HttpResponse::NotFound().body(format!("Site with ID {} not found", id)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let Some(script) = payload.into_inner().script.or(site.extraction_script) else {
+        return HttpResponse::BadRequest().body(format!("Site {} has no extraction script", id));
+    };
+    if let Err(e) = validate(&script) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    let body = match data.storage.previous_content(id, i64::MAX).await {
+        Ok(Some(body)) => body,
+        Ok(None) => return HttpResponse::NotFound().body(format!("Site {} has no stored page to test against yet", id)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    match run(&script, &body, &site.url).await {
+        Ok(output) => HttpResponse::Ok().json(output),
+        Err(e) => HttpResponse::UnprocessableEntity().body(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_scripts_in_a_sandbox() {
+        let html = r#"<html><body><h1> Trading halts </h1><p>Next review in 5 minutes</p>
+            <table><tr class="halt"><td><a href="/halts/XYZ">XYZ Corp halted</a></td><td>2024-05-01</td><td>News pending</td></tr>
+                   <tr class="halt"><td><a href="https://other.example/ABC">ABC Inc halted</a></td><td>bad date</td><td></td></tr>
+                   <tr class="halt"><td></td></tr></table></body></html>"#;
+        let script = r#"
+            let page = parse(body);
+            let items = [];
+            for row in page.select("tr.halt") {
+                let cells = row.select("td");
+                let link = row.first("a");
+                if link == () { continue; }
+                items.push(#{ title: link, link: link.attr("href"), published: cells[1].text, summary: cells[2].text });
+            }
+            #{ title: page.first("h1"), text: captures(page.text, "review in (\\d+) minutes"), items: items }
+        "#;
+        let output = extract(script, html, "https://nasdaq.example/market/").unwrap();
+        assert_eq!(output.title.as_deref(), Some("Trading halts"));
+        assert_eq!(output.text, "5");
+        assert_eq!(output.items.iter().map(|i| i.guid.as_str()).collect::<Vec<_>>(), ["https://nasdaq.example/halts/XYZ", "https://other.example/ABC"]);
+        assert_eq!(output.items[0].summary.as_deref(), Some("News pending"));
+        assert_eq!(output.items[0].published, Some("2024-05-01T00:00:00Z".parse().unwrap()));
+        assert_eq!((output.items[1].summary.as_deref(), output.items[1].published), (None, None));
+        assert_eq!(output.article().text, "5\n\nXYZ Corp halted https://nasdaq.example/halts/XYZ\n\nABC Inc halted https://other.example/ABC");
+
+        assert_eq!(extract(r#"parse(body).first("h2")"#, html, "https://nasdaq.example/").unwrap_err(), "the script must return a map of title, text and items");
+        assert_eq!(extract(r#"#{ title: "" }"#, html, "https://nasdaq.example/").unwrap_err(), "the script extracted nothing from the page");
+        assert_eq!(extract("loop {}", html, "https://nasdaq.example/").unwrap_err(), "the script took longer than 2s");
+        assert!(extract(r#"eval("1")"#, html, "https://nasdaq.example/").is_err());
+        assert!(validate("let x = ;").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use super::alerts::page_text;
+use super::export::escape_html;
+use super::storage::SharedStorage;
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+// Storage backends wrap matched terms in these before the snippet is escaped
+pub const MATCH_START: char = '\u{2}';
+pub const MATCH_END: char = '\u{3}';
+
+const MAX_RESULTS: i64 = 100;
+// Stored updates indexed per batch when catching up on startup
+const BACKLOG_BATCH: i64 = 200;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    q: String,
+    site_id: Option<i64>,
+    limit: Option<i64>,
+}
+
+// What the index holds for a stored fetch: the visible text of the whole page
+pub fn indexed_text(content: &str) -> String {
+    page_text(content, None)
+}
+
+// Turns user input into an FTS5 query that can't be a syntax error: "quoted phrases"
+// stay phrases, every other word is quoted on its own, and all of them must match
+pub fn fts5_query(query: &str) -> String {
+    let quote = |term: &str| format!("\"{}\"", term.replace('"', "\"\""));
+    query.split('"')
+        .enumerate()
+        .flat_map(|(i, part)| match i % 2 {
+            1 => vec![part.trim()],
+            _ => part.split_whitespace().collect(),
+        })
+        .filter(|term| !term.is_empty())
+        .map(quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// HTML-escapes a snippet and turns the match markers into <mark> tags
+fn highlight(snippet: &str) -> String {
+    escape_html(&snippet.split_whitespace().collect::<Vec<_>>().join(" "))
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+// GET /api/search?q=...: stored updates whose text matches, best matches first
+pub async fn search(data: web::Data<AppState>, query: web::Query<SearchQuery>) -> impl Responder {
+    let q = query.q.trim();
+    if q.is_empty() {
+        return HttpResponse::BadRequest().body("Query parameter q must not be empty");
+    }
+    let limit = query.limit.unwrap_or(20).clamp(1, MAX_RESULTS);
+
+    match data.storage.search_updates(q, query.site_id, limit).await {
+        Ok(mut hits) => {
+            for hit in &mut hits {
+                hit.snippet = highlight(&hit.snippet);
+            }
+            HttpResponse::Ok().json(serde_json::json!({ "query": q, "hits": hits }))
+        },
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// Indexes updates stored before search existed, or whose indexing failed
+pub async fn index_backlog(storage: SharedStorage) {
+    let mut indexed = 0;
+    loop {
+        let batch = match storage.unindexed_updates(BACKLOG_BATCH).await {
+            Ok(batch) if batch.is_empty() => break,
+            Ok(batch) => batch,
+            Err(e) => {
+                warn!("Search index backlog could not be loaded: {}", e);
+                return;
+            },
+        };
+        let texts = tokio::task::spawn_blocking(move || {
+            batch.into_iter().map(|(id, content)| (id, indexed_text(&content))).collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+        for (id, text) in texts {
+            if let Err(e) = storage.index_update_text(id, &text).await {
+                warn!("Search indexing of update {} failed: {}", id, e);
+                return;
+            }
+            indexed += 1;
+        }
+    }
+    if indexed > 0 {
+        info!("Indexed {} stored update(s) for search", indexed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_and_snippets_are_escaped() {
+        assert_eq!(fts5_query(r#"fda  "phase 3" AND-"#), r#""fda" "phase 3" "AND-""#);
+        assert_eq!(fts5_query(r#"say "hi"#), r#""say" "hi""#);
+        assert_eq!(fts5_query("  "), "");
+
+        let snippet = format!("<b>\n{}FDA{} approves", MATCH_START, MATCH_END);
+        assert_eq!(highlight(&snippet), "&lt;b&gt; <mark>FDA</mark> approves");
+    }
+}
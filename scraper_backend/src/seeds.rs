@@ -0,0 +1,189 @@
+use super::{netguard, scraper::SchedulerEvent, storage::Storage, tags, AppConfig, AppState, Site};
+use actix_web::{web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+// Re-applies the seed file on startup even when the database already has sites
+pub const FLAG: &str = "--seed";
+
+// One entry of the seed file; what it leaves out comes from its tags, then the global defaults
+#[derive(Deserialize, Debug, Default, PartialEq)]
+pub struct SeedSite {
+    pub url: String,
+    #[serde(default)]
+    pub interval_secs: Option<i64>,
+    #[serde(default)]
+    pub style: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct SeededSite {
+    id: i64,
+    url: String,
+}
+
+#[derive(Serialize)]
+pub struct SkippedSeed {
+    url: String,
+    reason: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct SeedReport {
+    pub added: Vec<SeededSite>,
+    pub skipped: Vec<SkippedSeed>,
+}
+
+// A YAML list of entries, or CSV rows of url,interval_secs,style,tags with the tags
+// separated by spaces. Empty CSV cells are left unset; a header row and # lines are skipped.
+pub fn parse(text: &str, csv: bool) -> Result<Vec<SeedSite>, String> {
+    if !csv {
+        return serde_yaml::from_str::<Option<Vec<SeedSite>>>(text)
+            .map(Option::unwrap_or_default)
+            .map_err(|e| format!("Invalid seed file: {}", e));
+    }
+    let mut seeds = Vec::new();
+    for (line, row) in text.lines().enumerate() {
+        let row = row.trim();
+        if row.is_empty() || row.starts_with('#') || (line == 0 && row.starts_with("url")) {
+            continue;
+        }
+        let cells: Vec<&str> = row.split(',').map(str::trim).collect();
+        if cells.len() > 4 {
+            return Err(format!("Invalid seed file: line {} has more than 4 columns", line + 1));
+        }
+        let cell = |i: usize| cells.get(i).copied().filter(|c| !c.is_empty());
+        let interval_secs = match cell(1).map(str::parse::<i64>).transpose() {
+            Ok(interval) => interval,
+            Err(e) => return Err(format!("Invalid seed file: line {}: interval_secs: {}", line + 1, e)),
+        };
+        seeds.push(SeedSite {
+            url: cells[0].to_string(),
+            interval_secs,
+            style: cell(2).map(String::from),
+            tags: cell(3).map(|t| t.split_whitespace().map(String::from).collect()).unwrap_or_default(),
+        });
+    }
+    Ok(seeds)
+}
+
+pub fn load(path: &str) -> Result<Vec<SeedSite>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read seed file {}: {}", path, e))?;
+    let csv = std::path::Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    parse(&text, csv)
+}
+
+// Adds the entries whose URL isn't watched yet, so applying the same file again changes nothing
+pub async fn apply(storage: &dyn Storage, seeds: Vec<SeedSite>, default_interval_secs: i64) -> Result<SeedReport, String> {
+    let existing = storage.list_sites().await.map_err(|e| format!("Database error: {}", e))?;
+    let defined = storage.list_tags().await.map_err(|e| format!("Database error: {}", e))?;
+    let mut watched: HashSet<String> = existing.iter()
+        .filter_map(|s| netguard::normalize_site_url(&s.url).ok().map(String::from))
+        .collect();
+
+    let mut report = SeedReport::default();
+    for seed in seeds {
+        let url = match netguard::normalize_site_url(&seed.url) {
+            Ok(url) => String::from(url),
+            Err(e) => {
+                report.skipped.push(SkippedSeed { url: seed.url, reason: e });
+                continue;
+            },
+        };
+        if let Some(style) = seed.style.as_deref().filter(|s| !tags::STYLES.contains(s)) {
+            report.skipped.push(SkippedSeed { url, reason: format!("Unknown style '{}'", style) });
+            continue;
+        }
+        if seed.interval_secs.is_some_and(|i| i <= 0) {
+            report.skipped.push(SkippedSeed { url, reason: "interval_secs must be positive".into() });
+            continue;
+        }
+        if !watched.insert(url.clone()) {
+            continue;
+        }
+        let tags = tags::normalize_tags(&seed.tags);
+        let (tag_interval, tag_style) = tags::defaults_for(&tags, &defined);
+        let site = Site {
+            tags,
+            ..Site::new(
+                &url,
+                seed.interval_secs.or(tag_interval).unwrap_or(default_interval_secs),
+                seed.style.or(tag_style).as_deref().unwrap_or("random"),
+            )
+        };
+        match storage.insert_site(&site).await {
+            Ok(id) => report.added.push(SeededSite { id, url }),
+            Err(e) => report.skipped.push(SkippedSeed { url, reason: e.to_string() }),
+        }
+    }
+    Ok(report)
+}
+
+// Applies config.yaml's seed_file; used on a fresh install, after a reset and for --seed
+pub async fn seed(storage: &dyn Storage, config: &AppConfig) -> Result<SeedReport, String> {
+    let Some(path) = config.seed_file.as_deref() else {
+        return Ok(SeedReport::default());
+    };
+    let report = apply(storage, load(path)?, config.default_interval_secs).await?;
+    info!("Seeded {} site(s) from {}", report.added.len(), path);
+    for skipped in &report.skipped {
+        warn!("Seed entry {} skipped: {}", skipped.url, skipped.reason);
+    }
+    Ok(report)
+}
+
+// POST /api/admin/seed: re-applies the seed file without restarting
+pub async fn reseed(data: web::Data<AppState>) -> impl Responder {
+    if data.config.demo {
+        return HttpResponse::BadRequest().body("Demo mode doesn't use the seed file");
+    }
+    if data.config.seed_file.is_none() {
+        return HttpResponse::NotFound().body("No seed_file configured");
+    }
+    match seed(data.storage.as_ref(), &data.config).await {
+        Ok(report) => {
+            for site in &report.added {
+                let _ = data.scheduler.send(SchedulerEvent::SiteAdded(site.id));
+            }
+            HttpResponse::Ok().json(report)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn applies_yaml_and_csv_once() {
+        let yaml = "- url: https://a.example/feed\n  interval_secs: 900\n  style: none\n  tags: [Gov]\n- url: https://b.example/\n";
+        let csv = "url,interval_secs,style,tags\n# mirrors\nhttps://a.example/feed#top,,,\nhttps://c.example/,60,exponential,sec fda\nhttps://d.example/,,fast,\n";
+        assert_eq!(parse(yaml, false).unwrap()[0], SeedSite {
+            url: "https://a.example/feed".into(),
+            interval_secs: Some(900),
+            style: Some("none".into()),
+            tags: vec!["Gov".into()],
+        });
+        assert!(parse("url,interval\nhttps://x.example/,soon\n", true).is_err());
+        assert!(parse("", false).unwrap().is_empty());
+
+        let storage = MemoryStorage::new();
+        let report = apply(&storage, parse(yaml, false).unwrap(), 30).await.unwrap();
+        assert_eq!(report.added.len(), 2);
+        let report = apply(&storage, parse(csv, true).unwrap(), 30).await.unwrap();
+        assert_eq!(report.added.iter().map(|s| s.url.as_str()).collect::<Vec<_>>(), ["https://c.example/"]);
+        assert_eq!(report.skipped.len(), 1);
+        assert!(apply(&storage, parse(yaml, false).unwrap(), 30).await.unwrap().added.is_empty());
+
+        let sites = storage.list_sites().await.unwrap();
+        let a = sites.iter().find(|s| s.url == "https://a.example/feed").unwrap();
+        assert_eq!((a.interval_secs, a.style.as_str(), a.tags.clone()), (900, "none", vec!["gov".to_string()]));
+        let b = sites.iter().find(|s| s.url == "https://b.example/").unwrap();
+        assert_eq!((b.interval_secs, b.style.as_str()), (30, "random"));
+    }
+}
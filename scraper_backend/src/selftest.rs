@@ -0,0 +1,156 @@
+use super::storage::Storage;
+use super::{scraper, AppConfig, AppState, StatusEvent, StreamEvent};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast::Sender;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+const READINESS_DB_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestCheck {
+    name: &'static str,
+    ok: bool,
+    // Skipped checks (e.g. no canary URL configured) count as passing
+    skipped: bool,
+    detail: String,
+    duration_ms: u128,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub ok: bool,
+    ran_at: DateTime<Utc>,
+    checks: Vec<SelfTestCheck>,
+}
+
+// Boot-time checks of everything the watcher needs to do useful work, so a broken
+// deployment shows up in the log and /healthz instead of quietly detecting nothing
+pub async fn run_self_test(storage: &dyn Storage, tx: &Sender<StreamEvent>, config: &AppConfig) -> SelfTestReport {
+    let ran_at = Utc::now();
+    let mut checks = Vec::new();
+
+    let started = Instant::now();
+    let result = storage.round_trip()
+        .await
+        .map(|_| "write/read round trip ok".to_string())
+        .map_err(|e| e.to_string());
+    checks.push(finish_check("database", started, result));
+
+    let started = Instant::now();
+    match &config.self_test_canary_url {
+        Some(url) => {
+            let result = canary_fetch(url).await;
+            checks.push(finish_check("canary_fetch", started, result));
+        },
+        None => checks.push(SelfTestCheck {
+            name: "canary_fetch",
+            ok: true,
+            skipped: true,
+            detail: "self_test_canary_url not set".into(),
+            duration_ms: 0,
+        }),
+    }
+
+    // Nobody is subscribed yet at boot, so only a closed channel counts as a failure
+    let started = Instant::now();
+    let sent = tx.send(StreamEvent::Status(StatusEvent {
+        kind: "self_test".into(),
+        site_id: None,
+        level: "info".into(),
+        message: "Startup self-test dry-send".into(),
+        timestamp: ran_at,
+    }));
+    let detail = match sent {
+        Ok(receivers) => format!("delivered to {} subscriber(s)", receivers),
+        Err(_) => "no subscribers yet; channel open".into(),
+    };
+    checks.push(finish_check("notifications", started, Ok(detail)));
+
+    let ok = checks.iter().all(|c| c.ok);
+    for check in &checks {
+        let label = if check.skipped { "SKIP" } else if check.ok { "PASS" } else { "FAIL" };
+        if check.ok {
+            info!("Self-test {} {}: {} ({}ms)", label, check.name, check.detail, check.duration_ms);
+        } else {
+            error!("Self-test {} {}: {} ({}ms)", label, check.name, check.detail, check.duration_ms);
+        }
+    }
+    if ok {
+        info!("Self-test passed");
+    } else {
+        error!("SELF-TEST FAILED - the watcher may not detect any changes");
+    }
+
+    SelfTestReport { ok, ran_at, checks }
+}
+
+fn finish_check(name: &'static str, started: Instant, result: Result<String, String>) -> SelfTestCheck {
+    let duration_ms = started.elapsed().as_millis();
+    match result {
+        Ok(detail) => SelfTestCheck { name, ok: true, skipped: false, detail, duration_ms },
+        Err(detail) => SelfTestCheck { name, ok: false, skipped: false, detail, duration_ms },
+    }
+}
+
+async fn canary_fetch(url: &str) -> Result<String, String> {
+    let client = scraper::build_http_client();
+    let resp = client.get(url).send().await.map_err(|e| format!("{}: {}", url, e))?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(format!("{} returned {}", url, status));
+    }
+    let body = resp.text().await.map_err(|e| format!("{}: {}", url, e))?;
+    Ok(format!("{} returned {} ({} bytes)", url, status, body.len()))
+}
+
+// Liveness: 200 while the process answers, with the boot self-test report. A failed
+// self-test shows as "ok": false; restarting wouldn't fix a bad canary URL.
+pub async fn healthz(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(data.self_test.as_ref())
+}
+
+// Readiness: the database answers a read (probes come often, so unlike the self-test
+// nothing is written), the scheduler loop came round within
+// readiness_max_heartbeat_age_secs and the notification task still listens on the
+// broadcast channel. 503 with the failing checks otherwise, such as after the scraper
+// task died while HTTP kept answering.
+pub async fn readyz(data: web::Data<AppState>) -> impl Responder {
+    let ran_at = Utc::now();
+    let mut checks = Vec::new();
+
+    let started = Instant::now();
+    let result = match tokio::time::timeout(READINESS_DB_TIMEOUT, data.storage.ping()).await {
+        Ok(Ok(())) => Ok("sites table readable".to_string()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("no answer within {}s", READINESS_DB_TIMEOUT.as_secs())),
+    };
+    checks.push(finish_check("database", started, result));
+
+    let started = Instant::now();
+    let max_age = data.config.readiness_max_heartbeat_age_secs.max(1);
+    let result = match data.metrics.scheduler_heartbeat.get() {
+        0 => Err("scheduler loop hasn't started".to_string()),
+        heartbeat => match ran_at.timestamp() - heartbeat {
+            age if age <= max_age => Ok(format!("last pass {}s ago", age)),
+            age => Err(format!("last pass {}s ago, over the {}s limit", age, max_age)),
+        },
+    };
+    checks.push(finish_check("scheduler", started, result));
+
+    let started = Instant::now();
+    let result = match data.tx_updates.receiver_count() {
+        0 => Err("no subscribers; the notification task has stopped".to_string()),
+        receivers => Ok(format!("{} subscriber(s)", receivers)),
+    };
+    checks.push(finish_check("notifications", started, result));
+
+    let report = SelfTestReport { ok: checks.iter().all(|c| c.ok), ran_at, checks };
+    if report.ok {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
@@ -0,0 +1,186 @@
+use super::feeds::{FeedDiff, FeedEntry};
+use super::netguard::NetworkGuard;
+use super::readability;
+use super::scraper::{self, clean_xml_entities};
+use super::truncate::{self, SENTENCE_BREAKS, WORD_BREAKS};
+use super::{AppConfig, Site};
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::Regex;
+use reqwest::Url;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::warn;
+
+// Sites with this extraction_mode watch a site's sitemap: pages newly listed in it, or
+// listed with a new lastmod, are fetched and reported like feed entries. Sitemap URLs are
+// switched to it when added.
+pub const EXTRACTION_MODE: &str = "sitemap";
+
+// Marks sitemaps and sitemap indexes, in their xmlns
+const NAMESPACE: &str = "sitemaps.org/schemas/sitemap";
+// URLs read from a sitemap, the most recently modified, which keeps their item keys within
+// what a site's seen_items holds
+const MAX_URLS: usize = 1000;
+// Pages fetched per check; the rest are reported by their URL alone
+const MAX_FOLLOWED: usize = 20;
+const MAX_SUMMARY_CHARS: usize = 300;
+const ROBOTS_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn url_block() -> &'static Regex {
+    static URL_BLOCK: OnceLock<Regex> = OnceLock::new();
+    URL_BLOCK.get_or_init(|| Regex::new(r"(?s)<(?:\w+:)?(?:url|sitemap)>(.*?)</(?:\w+:)?(?:url|sitemap)>").unwrap())
+}
+
+fn tags() -> &'static (Regex, Regex) {
+    static TAGS: OnceLock<(Regex, Regex)> = OnceLock::new();
+    TAGS.get_or_init(|| {
+        let tag = |name: &str| Regex::new(&format!(r"(?s)<(?:\w+:)?{0}>(.*?)</(?:\w+:)?{0}>", name)).unwrap();
+        (tag("loc"), tag("lastmod"))
+    })
+}
+
+fn tag(block: &str, pattern: &Regex) -> Option<String> {
+    let value = pattern.captures(block)?[1].trim().trim_start_matches("<![CDATA[").trim_end_matches("]]>").trim().to_string();
+    Some(clean_xml_entities(&value)).filter(|value| !value.is_empty())
+}
+
+// W3C datetimes, from a bare date to a time with seconds and a zone
+fn lastmod(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|date| date.with_timezone(&Utc))
+        .or_else(|| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M%:z").ok().map(|date| date.with_timezone(&Utc)))
+        .or_else(|| NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok().map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc()))
+}
+
+pub fn is_sitemap(body: &str) -> bool {
+    body.contains(NAMESPACE) && (body.contains("<urlset") || body.contains("<sitemapindex"))
+}
+
+// sitemap.xml, sitemap_index.xml, news-sitemap.xml and the like
+pub fn is_sitemap_url(source: &str) -> bool {
+    Url::parse(source.trim()).is_ok_and(|url| {
+        let file = url.path().rsplit('/').next().unwrap_or_default().to_lowercase();
+        file.contains("sitemap") && file.ends_with(".xml")
+    })
+}
+
+// The pages a sitemap lists, or the sitemaps an index lists, each keyed by its URL and
+// lastmod so a page listed with a new lastmod is a new entry. They carry no link, which
+// would make the page the same entry whatever its lastmod; follow adds it.
+pub fn read(body: &str, extraction_mode: Option<&str>) -> Option<Vec<FeedEntry>> {
+    let listing = body.contains("<urlset") || body.contains("<sitemapindex");
+    if !listing || (extraction_mode != Some(EXTRACTION_MODE) && !body.contains(NAMESPACE)) {
+        return None;
+    }
+    let (loc, modified) = tags();
+    let mut entries: Vec<FeedEntry> = url_block().captures_iter(body).filter_map(|block| {
+        let loc = tag(&block[1], loc)?;
+        let modified = tag(&block[1], modified);
+        Some(FeedEntry {
+            guid: match &modified {
+                Some(modified) => format!("{} {}", loc, modified),
+                None => loc,
+            },
+            title: None,
+            link: None,
+            summary: None,
+            published: modified.as_deref().and_then(lastmod),
+        })
+    }).collect();
+    if entries.len() > MAX_URLS {
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.published));
+        entries.truncate(MAX_URLS);
+    }
+    Some(entries)
+}
+
+fn location(entry: &FeedEntry) -> &str {
+    entry.guid.split(' ').next().unwrap_or(&entry.guid)
+}
+
+// Links the new entries of a check to their pages. Once the sitemap has been read before,
+// the first MAX_FOLLOWED pages are fetched for their title and opening text; pages that
+// fail, and those past the limit, are titled with their URL.
+pub async fn follow(client: &reqwest::Client, config: &AppConfig, site: &Site, diff: &mut FeedDiff) {
+    for (i, entry) in diff.new_entries.iter_mut().enumerate() {
+        let url = location(entry).to_string();
+        entry.link = Some(url.clone());
+        if diff.first_fetch || i >= MAX_FOLLOWED || url.ends_with(".xml") {
+            entry.title = Some(url);
+            continue;
+        }
+        match scraper::fetch_text(client, config, site, &url).await {
+            Ok(html) => {
+                let article = readability::extract(&html);
+                entry.title = article.as_ref().and_then(|article| article.title.clone()).or_else(|| readability::page_title(&html)).or(Some(url));
+                entry.summary = article.map(|article| truncate::preview(&article.text, MAX_SUMMARY_CHARS, &[SENTENCE_BREAKS, WORD_BREAKS]));
+            },
+            Err(e) => {
+                warn!(url, "Fetching a page new to the sitemap failed: {}", e);
+                entry.title = Some(url);
+            },
+        }
+    }
+}
+
+// The sitemap a site publishes: the URL itself when it names one, else the first Sitemap
+// line of its robots.txt, else /sitemap.xml. robots.txt is read through a guarded client.
+pub async fn resolve(source: &str, guard: &NetworkGuard, client: &reqwest::Client) -> Result<String, String> {
+    let source = source.trim();
+    if is_sitemap_url(source) {
+        return Ok(source.to_string());
+    }
+    let url = Url::parse(source).map_err(|e| format!("Invalid URL '{}': {}", source, e))?;
+    let origin = url.origin().ascii_serialization();
+    let robots = format!("{}/robots.txt", origin);
+    guard.check_url(&robots)?;
+    let listed = match client.get(&robots).timeout(ROBOTS_TIMEOUT).send().await.and_then(reqwest::Response::error_for_status) {
+        Ok(resp) => resp.text().await.ok().and_then(|text| {
+            text.lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("sitemap"))
+                .map(|(_, value)| value.trim().to_string())
+        }),
+        Err(_) => None,
+    };
+    Ok(listed.filter(|sitemap| Url::parse(sitemap).is_ok()).unwrap_or_else(|| format!("{}/sitemap.xml", origin)))
+}
+
+// Headed "🗺️ 120 sitemap URLs", the most recently modified first
+pub fn preview(entries: &[FeedEntry], max_length: usize) -> String {
+    let mut sorted: Vec<&FeedEntry> = entries.iter().collect();
+    sorted.sort_by_key(|entry| std::cmp::Reverse(entry.published));
+    let lines = sorted.iter().map(|entry| location(entry)).collect::<Vec<_>>().join("\n");
+    let heading = format!("🗺️ {} sitemap URL{}", entries.len(), if entries.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&lines, max_length, &[WORD_BREAKS]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_urls_and_lastmods() {
+        let sitemap = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://acme.example/news/q3-results</loc><lastmod>2026-10-14T09:30:00+00:00</lastmod></url>
+              <url><loc>https://acme.example/about?a=1&amp;b=2</loc><lastmod>2026-01-02</lastmod></url>
+              <url><loc><![CDATA[https://acme.example/team]]></loc></url>
+            </urlset>"#;
+        assert!(is_sitemap(sitemap));
+        let entries = read(sitemap, None).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].guid, "https://acme.example/news/q3-results 2026-10-14T09:30:00+00:00");
+        assert_eq!(entries[0].published.map(|d| d.to_rfc3339()).as_deref(), Some("2026-10-14T09:30:00+00:00"));
+        assert_eq!(location(&entries[1]), "https://acme.example/about?a=1&b=2");
+        assert_eq!(entries[1].published.map(|d| d.date_naive()), NaiveDate::from_ymd_opt(2026, 1, 2));
+        assert_eq!(entries[2].guid, "https://acme.example/team");
+        assert!(entries[0].link.is_none());
+
+        // A new lastmod makes a new entry
+        let updated = sitemap.replace("2026-10-14T09:30:00+00:00", "2026-10-15T08:00:00+00:00");
+        assert_ne!(read(&updated, None).unwrap()[0].guid, entries[0].guid);
+        assert!(read("<html><body>urlset</body></html>", None).is_none());
+        assert!(is_sitemap_url("https://acme.example/sitemap_index.xml"));
+        assert!(!is_sitemap_url("https://acme.example/news/"));
+    }
+}
@@ -0,0 +1,317 @@
+use super::feeds::FeedEntry;
+use super::netguard::NetworkGuard;
+use super::plugins::FetchRequest;
+use super::truncate::{self, WORD_BREAKS};
+use super::Site;
+use chrono::{DateTime, Utc};
+use reqwest::header::{AUTHORIZATION, USER_AGENT};
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+// Sites with this extraction_mode follow one account's posts through its platform's API:
+// Mastodon-compatible servers such as Truth Social, Bluesky, or X with an API token.
+// Profile URLs on truthsocial.com, bsky.app and x.com are switched to it when added.
+pub const EXTRACTION_MODE: &str = "social";
+
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+// Characters of a post kept as its title
+const TITLE_CHARS: usize = 100;
+// Mastodon returns 40 statuses at most, X 100 posts
+const MASTODON_LIMIT: &str = "40";
+const X_LIMIT: &str = "100";
+// Mastodon servers behind bot protection, Truth Social's among them, turn away lookups
+// that don't look like a browser's
+const BROWSER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0 Safari/537.36";
+
+// Servers known to speak Mastodon's API, besides any "/@name" profile given with the mode
+const MASTODON_HOSTS: &[&str] = &["truthsocial.com", "mastodon.social", "mastodon.online", "mstdn.social"];
+const X_HOSTS: &[&str] = &["x.com", "twitter.com"];
+// Paths under x.com that are not accounts
+const X_RESERVED: &[&str] = &["home", "explore", "search", "i", "intent", "settings", "notifications", "messages", "hashtag", "share"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Platform {
+    Mastodon,
+    Bluesky,
+    X,
+}
+
+// Where the Bluesky and X APIs are and the token X sites are read with. Debug output hides
+// the token, since the loaded config is logged at startup.
+#[derive(Clone)]
+pub struct Social {
+    pub bluesky_api_url: String,
+    pub x_api_url: String,
+    // An X app's bearer token; X sites can't be added, and their checks fail, without one
+    pub x_bearer_token: Option<String>,
+}
+
+impl fmt::Debug for Social {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Social")
+            .field("bluesky_api_url", &self.bluesky_api_url)
+            .field("x_api_url", &self.x_api_url)
+            .field("x_bearer_token", &self.x_bearer_token.as_ref().map(|_| "<set>"))
+            .finish()
+    }
+}
+
+// An account on a platform: a Mastodon server and username, a Bluesky handle or an X
+// username
+fn account(url: &Url, any_mastodon: bool) -> Option<(Platform, String)> {
+    let host = url.host_str()?.trim_start_matches("www.").to_lowercase();
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    let valid = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    match segments.as_slice() {
+        [handle, ..] if handle.starts_with('@') && (any_mastodon || MASTODON_HOSTS.contains(&host.as_str())) => {
+            Some((Platform::Mastodon, handle[1..].to_string())).filter(|(_, name)| valid(name))
+        },
+        ["profile", handle, ..] if host == "bsky.app" && valid(handle) => Some((Platform::Bluesky, handle.to_string())),
+        [name, ..] if X_HOSTS.contains(&host.as_str()) && valid(name) && !X_RESERVED.contains(name) => Some((Platform::X, name.to_string())),
+        _ => None,
+    }
+}
+
+// Profile pages on the platforms social sites know, which never render without JavaScript
+pub fn is_profile(source: &str) -> bool {
+    Url::parse(source.trim()).is_ok_and(|url| account(&url, false).is_some())
+}
+
+impl Social {
+    fn is_x_api(&self, url: &str) -> bool {
+        url.starts_with(self.x_api_url.trim_end_matches('/'))
+    }
+
+    fn x_token(&self) -> Result<&str, String> {
+        self.x_bearer_token.as_deref().filter(|token| !token.trim().is_empty())
+            .ok_or_else(|| "x_bearer_token is not set; X's API needs an app's bearer token".to_string())
+    }
+
+    async fn lookup(&self, client: &reqwest::Client, url: &str, headers: &[(reqwest::header::HeaderName, String)], what: &str) -> Result<Value, String> {
+        let mut request = client.get(url).timeout(LOOKUP_TIMEOUT);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        request.send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| format!("Looking up {} failed: {}", what, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Looking up {} failed: {}", what, e))
+    }
+
+    // The API URL a social site polls for an account's posts, from its profile URL. Mastodon
+    // and X accounts are looked up for their ID; replies are left out. Mastodon servers are
+    // whatever the profile names, so the guard checks them first and `client` should be
+    // guarded too, against names and redirects leading to internal addresses.
+    pub async fn resolve(&self, source: &str, guard: &NetworkGuard, client: &reqwest::Client) -> Result<String, String> {
+        let source = source.trim();
+        let url = Url::parse(source).map_err(|_| format!("'{}' is not a profile URL", source))?;
+        let (platform, name) = account(&url, true).ok_or_else(|| format!("'{}' is not a Mastodon, Bluesky or X profile", source))?;
+        let api = match platform {
+            Platform::Mastodon => {
+                let server = url.origin().ascii_serialization();
+                let mut lookup = Url::parse(&format!("{}/api/v1/accounts/lookup", server)).unwrap();
+                lookup.query_pairs_mut().append_pair("acct", &name);
+                guard.check_url(lookup.as_str())?;
+                let account = self.lookup(client, lookup.as_str(), &[(USER_AGENT, BROWSER_AGENT.to_string())], &format!("@{}", name)).await?;
+                let id = account["id"].as_str().ok_or_else(|| format!("{} has no account @{}", server, name))?;
+                let mut api = Url::parse(&format!("{}/api/v1/accounts/{}/statuses", server, id)).unwrap();
+                api.query_pairs_mut().append_pair("exclude_replies", "true").append_pair("limit", MASTODON_LIMIT);
+                api
+            },
+            Platform::Bluesky => {
+                let mut api = Url::parse(&format!("{}/xrpc/app.bsky.feed.getAuthorFeed", self.bluesky_api_url.trim_end_matches('/')))
+                    .map_err(|e| format!("Invalid bluesky_api_url '{}': {}", self.bluesky_api_url, e))?;
+                api.query_pairs_mut().append_pair("actor", &name).append_pair("filter", "posts_no_replies").append_pair("limit", "100");
+                api
+            },
+            Platform::X => {
+                let base = self.x_api_url.trim_end_matches('/');
+                let token = format!("Bearer {}", self.x_token()?);
+                let user = self.lookup(client, &format!("{}/users/by/username/{}", base, name), &[(AUTHORIZATION, token)], &format!("@{}", name)).await?;
+                let id = user["data"]["id"].as_str().ok_or_else(|| format!("X has no account @{}", name))?;
+                let mut api = Url::parse(&format!("{}/users/{}/tweets", base, id)).map_err(|e| format!("Invalid x_api_url '{}': {}", self.x_api_url, e))?;
+                api.query_pairs_mut().append_pair("exclude", "replies").append_pair("max_results", X_LIMIT).append_pair("tweet.fields", "created_at,public_metrics");
+                api
+            },
+        };
+        Ok(api.to_string())
+    }
+
+    // X sites send the bearer token. Mastodon servers get a browser's User-Agent like any
+    // site, and Bluesky's public API needs nothing.
+    pub fn request(&self, site: &Site) -> Result<Option<FetchRequest>, String> {
+        if !self.is_x_api(&site.url) {
+            return Ok(None);
+        }
+        let headers = BTreeMap::from([("Authorization".to_string(), format!("Bearer {}", self.x_token()?))]);
+        Ok(Some(FetchRequest { url: site.url.clone(), headers }))
+    }
+}
+
+fn text(value: &Value) -> Option<String> {
+    value.as_str().map(str::trim).filter(|s| !s.is_empty()).map(String::from)
+}
+
+fn timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.as_str()?).ok().map(|d| d.with_timezone(&Utc))
+}
+
+// A Mastodon status's HTML as text, a paragraph a line
+fn status_text(html: &str) -> String {
+    let fragment = Html::parse_fragment(&html.replace("<br>", "\n").replace("<br />", "\n"));
+    let paragraphs = Selector::parse("p").unwrap();
+    let lines: Vec<String> = match fragment.select(&paragraphs).next() {
+        Some(_) => fragment.select(&paragraphs).map(|p| p.text().collect::<String>()).collect(),
+        None => vec![fragment.root_element().text().collect()],
+    };
+    lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n")
+}
+
+// A post as a feed entry: its text, cut short, is the title and all of it the summary. Posts
+// of only media are titled with what they carry.
+fn post(guid: String, author: &str, text: String, media: usize, link: Option<String>, published: Option<DateTime<Utc>>) -> FeedEntry {
+    let shown = match (text.is_empty(), media) {
+        (false, _) => text.lines().next().unwrap_or_default().to_string(),
+        (true, 0) => "(no text)".to_string(),
+        (true, n) => format!("({} attachment{})", n, if n == 1 { "" } else { "s" }),
+    };
+    FeedEntry {
+        guid,
+        title: Some(format!("{}: {}", author, truncate::preview(&shown, TITLE_CHARS, &[WORD_BREAKS]))),
+        link,
+        summary: Some(text).filter(|s| !s.is_empty()),
+        published,
+    }
+}
+
+// GET /api/v1/accounts/:id/statuses: an array of statuses, boosts carrying the original
+fn mastodon(statuses: &[Value]) -> Vec<FeedEntry> {
+    statuses.iter().filter_map(|status| {
+        let shown = if status["reblog"].is_object() { &status["reblog"] } else { status };
+        let author = format!("@{}", text(&status["account"]["acct"])?);
+        let author = match text(&status["reblog"]["account"]["acct"]) {
+            Some(original) => format!("{} boosted @{}", author, original),
+            None => author,
+        };
+        let body = status_text(shown["content"].as_str().unwrap_or_default());
+        let media = shown["media_attachments"].as_array().map_or(0, Vec::len);
+        Some(post(text(&status["uri"]).or_else(|| text(&status["id"]))?, &author, body, media, text(&shown["url"]), timestamp(&status["created_at"])))
+    }).collect()
+}
+
+// app.bsky.feed.getAuthorFeed: {"feed": [{"post": ..., "reason": ...}]}, reposts with a reason
+fn bluesky(feed: &[Value]) -> Vec<FeedEntry> {
+    feed.iter().filter_map(|item| {
+        let post_view = &item["post"];
+        let uri = text(&post_view["uri"])?;
+        let handle = text(&post_view["author"]["handle"])?;
+        let author = match text(&item["reason"]["by"]["handle"]) {
+            Some(reposter) => format!("@{} reposted @{}", reposter, handle),
+            None => format!("@{}", handle),
+        };
+        let record_key = uri.rsplit('/').next().unwrap_or_default();
+        let link = format!("https://bsky.app/profile/{}/post/{}", handle, record_key);
+        let media = post_view["embed"]["images"].as_array().map_or(0, Vec::len);
+        // A repost is a new entry even though the post isn't
+        let guid = match item["reason"]["$type"].as_str() {
+            Some(reason) if reason.ends_with("reasonRepost") => format!("{}#repost", uri),
+            _ => uri,
+        };
+        let published = timestamp(&item["reason"]["indexedAt"]).or_else(|| timestamp(&post_view["record"]["createdAt"]));
+        Some(post(guid, &author, text(&post_view["record"]["text"]).unwrap_or_default(), media, Some(link), published))
+    }).collect()
+}
+
+// GET /2/users/:id/tweets: {"data": [{"id", "text", "created_at"}], "includes": ...}
+fn x(posts: &[Value]) -> Vec<FeedEntry> {
+    posts.iter().filter_map(|tweet| {
+        let id = text(&tweet["id"])?;
+        let link = format!("https://x.com/i/status/{}", id);
+        Some(post(format!("x:{}", id), "X", text(&tweet["text"]).unwrap_or_default(), 0, Some(link), timestamp(&tweet["created_at"])))
+    }).collect()
+}
+
+// The posts of a Mastodon, Bluesky or X API response, newest first as the APIs list them.
+// Recognized without the mode by what only each API returns; None if the body is none of
+// them.
+pub fn read(body: &str, extraction_mode: Option<&str>) -> Option<Vec<FeedEntry>> {
+    let trimmed = body.trim_start();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return None;
+    }
+    let json: Value = serde_json::from_str(body).ok()?;
+    let named = extraction_mode == Some(EXTRACTION_MODE);
+    if let Some(statuses) = json.as_array() {
+        let mastodon_like = statuses.first().is_some_and(|s| s["account"].is_object() && s.get("content").is_some() && s.get("uri").is_some());
+        return (mastodon_like || (named && statuses.is_empty())).then(|| mastodon(statuses));
+    }
+    if let Some(feed) = json["feed"].as_array() {
+        let bluesky_like = feed.first().is_some_and(|item| item["post"]["uri"].as_str().is_some_and(|uri| uri.starts_with("at://")));
+        return (bluesky_like || (named && feed.is_empty())).then(|| bluesky(feed));
+    }
+    let posts = json["data"].as_array();
+    let x_like = posts.and_then(|posts| posts.first()).is_some_and(|tweet| tweet.get("edit_history_tweet_ids").is_some());
+    match posts {
+        Some(posts) if x_like || named => Some(x(posts)),
+        // X leaves out "data" when an account has nothing to list
+        None if named && json["meta"]["result_count"] == 0 => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+// Headed "💬 3 posts", one post per line
+pub fn preview(posts: &[FeedEntry], max_length: usize) -> String {
+    let lines = posts.iter().map(|post| post.title.as_deref().unwrap_or(&post.guid)).collect::<Vec<_>>().join("\n");
+    let heading = format!("💬 {} post{}", posts.len(), if posts.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&lines, max_length, &[WORD_BREAKS]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_profiles_and_reads_each_platform() {
+        assert!(is_profile("https://truthsocial.com/@realDonaldTrump"));
+        assert!(is_profile("https://bsky.app/profile/jay.bsky.team"));
+        assert!(is_profile("https://x.com/SECGov"));
+        assert!(!is_profile("https://x.com/search?q=gme"));
+        assert!(!is_profile("https://medium.com/@writer"));
+        assert_eq!(account(&Url::parse("https://fosstodon.org/@rust").unwrap(), true), Some((Platform::Mastodon, "rust".to_string())));
+
+        let statuses = r#"[
+            {"id": "113", "uri": "https://truthsocial.com/users/realDonaldTrump/statuses/113", "url": "https://truthsocial.com/@realDonaldTrump/113",
+             "created_at": "2026-10-14T12:00:00.000Z", "account": {"acct": "realDonaldTrump"}, "reblog": null,
+             "content": "<p>First line &amp; more</p><p>Second line</p>", "media_attachments": []},
+            {"id": "112", "uri": "https://truthsocial.com/users/realDonaldTrump/statuses/112", "url": null,
+             "created_at": "2026-10-14T11:00:00.000Z", "account": {"acct": "realDonaldTrump"}, "content": "",
+             "reblog": {"account": {"acct": "someone"}, "content": "", "url": "https://truthsocial.com/@someone/99", "media_attachments": [{"type": "video"}]}}
+        ]"#;
+        let posts = read(statuses, None).unwrap();
+        assert_eq!(posts[0].title.as_deref(), Some("@realDonaldTrump: First line & more"));
+        assert_eq!(posts[0].summary.as_deref(), Some("First line & more\nSecond line"));
+        assert_eq!(posts[0].link.as_deref(), Some("https://truthsocial.com/@realDonaldTrump/113"));
+        assert_eq!(posts[1].title.as_deref(), Some("@realDonaldTrump boosted @someone: (1 attachment)"));
+        assert_eq!(posts[1].link.as_deref(), Some("https://truthsocial.com/@someone/99"));
+
+        let feed = r#"{"feed": [{"post": {"uri": "at://did:plc:abc/app.bsky.feed.post/3kxyz", "author": {"handle": "jay.bsky.team"},
+            "record": {"text": "Hello Bluesky", "createdAt": "2026-10-14T10:00:00Z"}}}], "cursor": "x"}"#;
+        let posts = read(feed, None).unwrap();
+        assert_eq!(posts[0].guid, "at://did:plc:abc/app.bsky.feed.post/3kxyz");
+        assert_eq!(posts[0].link.as_deref(), Some("https://bsky.app/profile/jay.bsky.team/post/3kxyz"));
+
+        let tweets = r#"{"data": [{"id": "184", "text": "Investor alert", "created_at": "2026-10-14T09:00:00.000Z", "edit_history_tweet_ids": ["184"]}]}"#;
+        let posts = read(tweets, None).unwrap();
+        assert_eq!((posts[0].guid.as_str(), posts[0].link.as_deref()), ("x:184", Some("https://x.com/i/status/184")));
+        assert_eq!(read(r#"{"meta": {"result_count": 0}}"#, Some(EXTRACTION_MODE)).unwrap().len(), 0);
+        assert!(read(r#"{"data": [{"id": 1}]}"#, None).is_none());
+        assert!(read("[1, 2]", None).is_none());
+    }
+}
@@ -0,0 +1,2125 @@
+use super::alerts::{Alert, AlertRule};
+use super::{assertions, challenge, health};
+use super::auth::{ApiKey, ApiToken};
+use super::compression::{self, StoredContent};
+use super::contents::{content_address, pdf_address};
+use super::documents::{Document, NewDocument};
+use super::federal_register::{FederalDocument, FederalDocumentQuery, StoredFederalDocument};
+use super::halts::{Halt, HaltChange, HaltQuery, StoredHalt};
+use super::email::{EmailRecipient, EmailTemplate};
+use super::feeds::{self, FeedDiff, FeedEntry, MAX_SEEN_ITEMS};
+use super::items::{Item, ItemQuery};
+use super::schema;
+use super::scraper;
+use super::search;
+use super::telegram::TelegramChat;
+use super::storage::{
+    sort_by_median, summarize_latency, Activity, ChangeCount, ChangeEntry, CheckRecord, FetchLogEntry, LatencySample,
+    LegalHolds, SearchHit, SharedStorage, SiteLatency, Snapshot, Storage, StorageError, StorageResult, UpdateContent,
+    LATENCY_SAMPLES_PER_SITE,
+};
+use super::tags::{self, AffectedSite, Tag, TagSettings, TaggedSite};
+use super::webhooks::Webhook;
+use super::{Assertions, Site, SiteUpdate};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use sqlx::{Connection, Pool, Postgres, Sqlite, SqliteConnection};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+// Stored fetches that changed the site's hash.
+// A row without a predecessor only counts when it is the site's recorded last update,
+// otherwise it is just the oldest unchanged snapshot left over after pruning.
+const CHANGE_ROWS: &str =
+    "SELECT u.id, u.site_id, s.url, u.timestamp, u.diff_hash, COALESCE(c.body, u.content) AS content, s.selector, u.metadata, u.published_at, u.wire_source
+     FROM (
+        SELECT *, LAG(diff_hash) OVER (PARTITION BY site_id ORDER BY id) AS prev_hash
+        FROM updates
+     ) u
+     JOIN sites s ON s.id = u.site_id
+     LEFT JOIN contents c ON c.hash = u.content_hash
+     WHERE ((u.prev_hash IS NULL AND u.timestamp = s.last_updated)
+        OR u.prev_hash != u.diff_hash)";
+
+// Updates retention may delete: neither the update nor its site is under legal hold.
+// Held updates don't count towards a site's retention_count either.
+const NOT_HELD: &str = "NOT legal_hold AND site_id NOT IN (SELECT id FROM sites WHERE legal_hold)";
+
+// Bodies are stored once in contents and referenced by content_hash. Updates written
+// before that keep the body inline in content until move_inline_content gets to them,
+// so reads take whichever is set.
+const BODY: &str = "COALESCE(c.body, u.content)";
+const BODY_JOIN: &str = "LEFT JOIN contents c ON c.hash = u.content_hash";
+const INSERT_CONTENT: &str = "INSERT INTO contents (hash, body) VALUES ($1, $2) ON CONFLICT (hash) DO NOTHING";
+// Fetched PDFs, stored once like bodies and referenced by pdf_hash
+const INSERT_PDF: &str = "INSERT INTO pdfs (hash, data) VALUES ($1, $2) ON CONFLICT (hash) DO NOTHING";
+// Tables with rows belonging to a site, removed before the site itself
+const SITE_CHILD_TABLES: [&str; 15] = [
+    "alerts", "notified_changes", "documents", "halts", "federal_documents", "updates", "seen_items", "items", "webhooks", "telegram_sites", "site_tags", "alert_rules",
+    "fetch_log", "detection_latency", "site_changes",
+];
+
+// A document without its body, which only GET /api/documents/{id} reads
+const DOCUMENT_COLUMNS: &str = "id, site_id, update_id, url, content_type, size, fetched_at";
+
+// Every tag a site carries has a row in tags
+const ADD_TAG: &str = "INSERT INTO tags (name) VALUES ($1) ON CONFLICT (name) DO NOTHING";
+
+// A check's writes are retried when another writer holds the database lock (SQLite) or
+// the transaction lost a serialization conflict or deadlock (Postgres)
+const BUSY_ATTEMPTS: u32 = 4;
+const BUSY_RETRY_MS: u64 = 250;
+
+fn is_busy(e: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db) = e else {
+        return false;
+    };
+    match db.code().as_deref() {
+        // SQLITE_BUSY and SQLITE_LOCKED, including their extended codes
+        Some(code) if code.parse::<i64>().is_ok_and(|code| matches!(code & 0xff, 5 | 6)) => true,
+        Some("40001") | Some("40P01") => true,
+        _ => false,
+    }
+}
+
+// Pick the backend from the URL scheme; anything that isn't Postgres is handed to SQLite
+pub async fn connect(db_url: &str) -> StorageResult<SharedStorage> {
+    if db_url.starts_with("postgres://") || db_url.starts_with("postgresql://") {
+        Ok(Arc::new(PostgresStorage::connect(db_url).await?))
+    } else {
+        Ok(Arc::new(SqliteStorage::connect(db_url).await?))
+    }
+}
+
+fn attach_tags(sites: &mut [Site], rows: Vec<(i64, String)>) {
+    let mut by_site: HashMap<i64, Vec<String>> = HashMap::new();
+    for (site_id, tag) in rows {
+        by_site.entry(site_id).or_default().push(tag);
+    }
+    for site in sites.iter_mut() {
+        site.tags = by_site.remove(&site.id).unwrap_or_default();
+    }
+}
+
+pub struct SqliteStorage {
+    pool: Pool<Sqlite>,
+}
+
+impl SqliteStorage {
+    pub async fn connect(db_url: &str) -> StorageResult<Self> {
+        // Upgrade over a single connection before the pool opens. A pooled connection that
+        // loaded the schema earlier would prepare `SELECT *` against the old column list.
+        let mut conn = SqliteConnection::connect(db_url).await?;
+        let (migrated,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+        )
+        .fetch_one(&mut conn)
+        .await?;
+        if migrated == 0 {
+            for statement in schema::LEGACY_SQLITE_UPGRADES {
+                let _ = sqlx::query(statement).execute(&mut conn).await;
+            }
+        }
+        schema::SQLITE_MIGRATOR.run(&mut conn).await?;
+        conn.close().await?;
+
+        let pool = Pool::<Sqlite>::connect(db_url).await?;
+        let (foreign_keys,): (i64,) = sqlx::query_as("PRAGMA foreign_keys;").fetch_one(&pool).await?;
+        info!("Foreign key constraints enabled: {}", if foreign_keys == 1 { "yes" } else { "no" });
+        Ok(SqliteStorage { pool })
+    }
+
+    // Search rows share the update's rowid; FTS5 marks matches in snippet()
+    const INSERT_UPDATE_TEXT: &'static str = "INSERT OR REPLACE INTO update_text (rowid, text) VALUES ($1, $2)";
+    const UNINDEXED_UPDATES: &'static str =
+        "SELECT u.id, COALESCE(c.body, u.content) FROM updates u LEFT JOIN contents c ON c.hash = u.content_hash
+         WHERE u.id NOT IN (SELECT rowid FROM update_text) ORDER BY u.id LIMIT $1";
+    const SEARCH_UPDATES: &'static str =
+        "SELECT u.id AS update_id, u.site_id, s.url, u.timestamp,
+            snippet(update_text, 0, char(2), char(3), '…', 16) AS snippet
+         FROM update_text
+         JOIN updates u ON u.id = update_text.rowid
+         JOIN sites s ON s.id = u.site_id
+         WHERE update_text MATCH $1 AND ($2 IS NULL OR u.site_id = $2)
+         ORDER BY update_text.rank, u.id DESC
+         LIMIT $3";
+
+    fn match_query(query: &str) -> String {
+        search::fts5_query(query)
+    }
+
+    const TEXT_MATCHES: &'static str = "u.id IN (SELECT rowid FROM update_text WHERE LOWER(text) LIKE $2)";
+
+    // Pages in use, free pages included; the WAL isn't counted
+    const DATABASE_SIZE: &'static str = "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()";
+}
+
+pub struct PostgresStorage {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresStorage {
+    pub async fn connect(db_url: &str) -> StorageResult<Self> {
+        let pool = Pool::<Postgres>::connect(db_url).await?;
+        schema::POSTGRES_MIGRATOR.run(&pool).await?;
+        info!("Connected to Postgres");
+        Ok(PostgresStorage { pool })
+    }
+
+    const INSERT_UPDATE_TEXT: &'static str =
+        "INSERT INTO update_text (update_id, text) VALUES ($1, $2) ON CONFLICT (update_id) DO UPDATE SET text = excluded.text";
+    const UNINDEXED_UPDATES: &'static str =
+        "SELECT u.id, COALESCE(c.body, u.content) FROM updates u LEFT JOIN contents c ON c.hash = u.content_hash
+         WHERE NOT EXISTS (SELECT 1 FROM update_text t WHERE t.update_id = u.id) ORDER BY u.id LIMIT $1";
+    const SEARCH_UPDATES: &'static str =
+        "SELECT u.id AS update_id, u.site_id, s.url, u.timestamp,
+            ts_headline('simple', t.text, q, 'StartSel=' || chr(2) || ', StopSel=' || chr(3) || ', MaxFragments=2, MaxWords=20, MinWords=8') AS snippet
+         FROM update_text t
+         CROSS JOIN websearch_to_tsquery('simple', $1) q
+         JOIN updates u ON u.id = t.update_id
+         JOIN sites s ON s.id = u.site_id
+         WHERE t.document @@ q AND ($2::BIGINT IS NULL OR u.site_id = $2)
+         ORDER BY ts_rank(t.document, q) DESC, u.id DESC
+         LIMIT $3";
+
+    // websearch_to_tsquery accepts any input, quotes included
+    fn match_query(query: &str) -> String {
+        query.to_string()
+    }
+
+    const TEXT_MATCHES: &'static str = "u.id IN (SELECT update_id FROM update_text WHERE LOWER(text) LIKE $2)";
+
+    const DATABASE_SIZE: &'static str = "SELECT pg_database_size(current_database())";
+}
+
+// Both backends run the same statements: `$N` placeholders, RETURNING and ON CONFLICT
+// are understood by SQLite and Postgres alike. Full-text search is the exception; each
+// backend provides those statements as associated consts.
+macro_rules! impl_sql_storage {
+    ($backend:ty) => {
+        #[async_trait]
+        impl Storage for $backend {
+            async fn list_sites(&self) -> StorageResult<Vec<Site>> {
+                let mut sites: Vec<Site> = sqlx::query_as("SELECT * FROM sites ORDER BY id")
+                    .fetch_all(&self.pool)
+                    .await?;
+                let tags: Vec<(i64, String)> = sqlx::query_as("SELECT site_id, tag FROM site_tags ORDER BY tag")
+                    .fetch_all(&self.pool)
+                    .await?;
+                attach_tags(&mut sites, tags);
+                Ok(sites)
+            }
+
+            async fn get_site(&self, id: i64) -> StorageResult<Option<Site>> {
+                let site: Option<Site> = sqlx::query_as("SELECT * FROM sites WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                let Some(mut site) = site else {
+                    return Ok(None);
+                };
+                let tags: Vec<(String,)> = sqlx::query_as("SELECT tag FROM site_tags WHERE site_id = $1 ORDER BY tag")
+                    .bind(id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                site.tags = tags.into_iter().map(|(tag,)| tag).collect();
+                Ok(Some(site))
+            }
+
+            // Site row and its tags are written together
+            async fn insert_site(&self, site: &Site) -> StorageResult<i64> {
+                let mut tx = self.pool.begin().await?;
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO sites (url, interval_secs, style, selector, priority, retention_count, probe_mode, probe_tail_kb,
+                        assert_selector, assert_pattern, assert_min_length, ignore_patterns, retention_days, legal_hold,
+                        notify_burst_limit, notify_window_secs, proxies, active_window, timeout_secs, max_redirects, max_body_bytes,
+                        extraction_mode, preview_length, extraction_script, plugin, link_pattern,
+                        archive_links, max_content_age_hours, crawl_pattern, crawl_depth, crawl_max_pages, max_quiet_hours)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28,
+                        $29, $30, $31, $32)
+                     RETURNING id"
+                )
+                .bind(&site.url)
+                .bind(site.interval_secs)
+                .bind(&site.style)
+                .bind(&site.selector)
+                .bind(site.priority)
+                .bind(site.retention_count)
+                .bind(&site.probe_mode)
+                .bind(site.probe_tail_kb)
+                .bind(&site.assert_selector)
+                .bind(&site.assert_pattern)
+                .bind(site.assert_min_length)
+                .bind(Json(&site.ignore_patterns))
+                .bind(site.retention_days)
+                .bind(site.legal_hold)
+                .bind(site.notify_burst_limit)
+                .bind(site.notify_window_secs)
+                .bind(Json(&site.proxies))
+                .bind(Json(&site.active_window))
+                .bind(site.timeout_secs)
+                .bind(site.max_redirects)
+                .bind(site.max_body_bytes)
+                .bind(&site.extraction_mode)
+                .bind(site.preview_length)
+                .bind(&site.extraction_script)
+                .bind(&site.plugin)
+                .bind(&site.link_pattern)
+                .bind(site.archive_links)
+                .bind(site.max_content_age_hours)
+                .bind(&site.crawl_pattern)
+                .bind(site.crawl_depth)
+                .bind(site.crawl_max_pages)
+                .bind(site.max_quiet_hours)
+                .fetch_one(&mut *tx)
+                .await?;
+                for tag in tags::normalize_tags(&site.tags) {
+                    sqlx::query(ADD_TAG).bind(&tag).execute(&mut *tx).await?;
+                    sqlx::query("INSERT INTO site_tags (site_id, tag) VALUES ($1, $2)")
+                        .bind(id)
+                        .bind(tag)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                tx.commit().await?;
+                Ok(id)
+            }
+
+            async fn update_site(&self, id: i64, update: &SiteUpdate, selector: Option<String>, probe_mode: Option<String>, asserts: Assertions) -> StorageResult<bool> {
+                let mut tx = self.pool.begin().await?;
+                // Only touch the nullable columns when the field was sent. Changing the probe
+                // settings drops the stored signature so the next check does a full fetch.
+                let rows = sqlx::query(
+                    "UPDATE sites SET
+                        interval_secs = COALESCE($1, interval_secs),
+                        adaptive_interval_secs = CASE WHEN $1 IS NULL THEN adaptive_interval_secs END,
+                        style = COALESCE($2, style),
+                        selector = CASE WHEN $3 THEN $4 ELSE selector END,
+                        priority = COALESCE($5, priority),
+                        retention_count = CASE WHEN $6 THEN $7 ELSE retention_count END,
+                        probe_mode = CASE WHEN $9 THEN $10 ELSE probe_mode END,
+                        probe_tail_kb = CASE WHEN $11 THEN $12 ELSE probe_tail_kb END,
+                        probe_signature = CASE WHEN $9 OR $11 THEN NULL ELSE probe_signature END,
+                        assert_selector = CASE WHEN $13 THEN $14 ELSE assert_selector END,
+                        assert_pattern = CASE WHEN $15 THEN $16 ELSE assert_pattern END,
+                        assert_min_length = CASE WHEN $17 THEN $18 ELSE assert_min_length END,
+                        ignore_patterns = COALESCE($19, ignore_patterns),
+                        retention_days = CASE WHEN $20 THEN $21 ELSE retention_days END,
+                        legal_hold = COALESCE($22, legal_hold),
+                        notify_burst_limit = CASE WHEN $23 THEN $24 ELSE notify_burst_limit END,
+                        notify_window_secs = CASE WHEN $25 THEN $26 ELSE notify_window_secs END,
+                        proxies = COALESCE($27, proxies),
+                        active_window = CASE WHEN $28 THEN $29 ELSE active_window END,
+                        timeout_secs = CASE WHEN $30 THEN $31 ELSE timeout_secs END,
+                        max_redirects = CASE WHEN $32 THEN $33 ELSE max_redirects END,
+                        max_body_bytes = CASE WHEN $34 THEN $35 ELSE max_body_bytes END,
+                        extraction_mode = CASE WHEN $36 THEN $37 ELSE extraction_mode END,
+                        preview_length = CASE WHEN $38 THEN $39 ELSE preview_length END,
+                        plugin = CASE WHEN $40 THEN $41 ELSE plugin END,
+                        link_pattern = CASE WHEN $42 THEN $43 ELSE link_pattern END,
+                        archive_links = COALESCE($44, archive_links),
+                        max_content_age_hours = CASE WHEN $45 THEN $46 ELSE max_content_age_hours END,
+                        crawl_pattern = CASE WHEN $47 THEN $48 ELSE crawl_pattern END,
+                        crawl_depth = CASE WHEN $49 THEN $50 ELSE crawl_depth END,
+                        crawl_max_pages = CASE WHEN $51 THEN $52 ELSE crawl_max_pages END,
+                        max_quiet_hours = CASE WHEN $53 THEN $54 ELSE max_quiet_hours END
+                     WHERE id = $8"
+                )
+                .bind(update.interval_secs)
+                .bind(&update.style)
+                .bind(update.selector.is_some())
+                .bind(selector)
+                .bind(update.priority)
+                .bind(update.retention_count.is_some())
+                .bind(update.retention_count.filter(|n| *n > 0))
+                .bind(id)
+                .bind(update.probe_mode.is_some())
+                .bind(probe_mode)
+                .bind(update.probe_tail_kb.is_some())
+                .bind(update.probe_tail_kb.filter(|kb| *kb > 0))
+                .bind(update.assert_selector.is_some())
+                .bind(asserts.selector)
+                .bind(update.assert_pattern.is_some())
+                .bind(asserts.pattern)
+                .bind(update.assert_min_length.is_some())
+                .bind(update.assert_min_length.filter(|n| *n > 0))
+                .bind(update.ignore_patterns.as_ref().map(Json))
+                .bind(update.retention_days.is_some())
+                .bind(update.retention_days.filter(|n| *n > 0))
+                .bind(update.legal_hold)
+                .bind(update.notify_burst_limit.is_some())
+                .bind(update.notify_burst_limit.filter(|n| *n > 0))
+                .bind(update.notify_window_secs.is_some())
+                .bind(update.notify_window_secs.filter(|n| *n > 0))
+                .bind(update.proxies.as_ref().map(Json))
+                .bind(update.active_window.is_some())
+                .bind(Json(update.active_window.clone().flatten()))
+                .bind(update.timeout_secs.is_some())
+                .bind(update.timeout_secs.filter(|n| *n > 0))
+                .bind(update.max_redirects.is_some())
+                .bind(update.max_redirects.filter(|n| *n > 0))
+                .bind(update.max_body_bytes.is_some())
+                .bind(update.max_body_bytes.filter(|n| *n > 0))
+                .bind(update.extraction_mode.is_some())
+                .bind(update.extraction_mode.as_deref().filter(|mode| !mode.is_empty()))
+                .bind(update.preview_length.is_some())
+                .bind(update.preview_length.filter(|n| *n > 0))
+                .bind(update.plugin.is_some())
+                .bind(update.plugin.as_deref().filter(|name| !name.is_empty()))
+                .bind(update.link_pattern.is_some())
+                .bind(update.link_pattern.as_deref().filter(|pattern| !pattern.is_empty()))
+                .bind(update.archive_links)
+                .bind(update.max_content_age_hours.is_some())
+                .bind(update.max_content_age_hours.filter(|n| *n > 0))
+                .bind(update.crawl_pattern.is_some())
+                .bind(update.crawl_pattern.as_deref().filter(|pattern| !pattern.is_empty()))
+                .bind(update.crawl_depth.is_some())
+                .bind(update.crawl_depth.filter(|n| *n > 0))
+                .bind(update.crawl_max_pages.is_some())
+                .bind(update.crawl_max_pages.filter(|n| *n > 0))
+                .bind(update.max_quiet_hours.is_some())
+                .bind(update.max_quiet_hours.filter(|n| *n > 0))
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+                if rows > 0 {
+                    if let Some(new_tags) = &update.tags {
+                        sqlx::query("DELETE FROM site_tags WHERE site_id = $1")
+                            .bind(id)
+                            .execute(&mut *tx)
+                            .await?;
+                        for tag in tags::normalize_tags(new_tags) {
+                            sqlx::query(ADD_TAG).bind(&tag).execute(&mut *tx).await?;
+                            sqlx::query("INSERT INTO site_tags (site_id, tag) VALUES ($1, $2)")
+                                .bind(id)
+                                .bind(tag)
+                                .execute(&mut *tx)
+                                .await?;
+                        }
+                    }
+                }
+                tx.commit().await?;
+                Ok(rows > 0)
+            }
+
+            async fn delete_site(&self, id: i64) -> StorageResult<bool> {
+                Ok(!self.delete_sites(&[id]).await?.is_empty())
+            }
+
+            // Child rows are removed by hand: SQLite databases created by older versions
+            // have no ON DELETE CASCADE on their foreign keys
+            async fn delete_sites(&self, ids: &[i64]) -> StorageResult<Vec<i64>> {
+                let mut tx = self.pool.begin().await?;
+                let mut deleted = Vec::new();
+                for &id in ids {
+                    debug!(site_id = id, "Deleting stored updates");
+                    for table in SITE_CHILD_TABLES {
+                        sqlx::query(&format!("DELETE FROM {} WHERE site_id = $1", table))
+                            .bind(id)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                    let result = sqlx::query("DELETE FROM sites WHERE id = $1")
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+                    if result.rows_affected() > 0 {
+                        deleted.push(id);
+                    }
+                }
+                tx.commit().await?;
+                Ok(deleted)
+            }
+
+            async fn site_ids_with_tag(&self, tag: &str) -> StorageResult<Vec<i64>> {
+                let rows: Vec<(i64,)> = sqlx::query_as("SELECT site_id FROM site_tags WHERE tag = $1")
+                    .bind(tag)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().map(|(id,)| id).collect())
+            }
+
+            async fn retune_tag(&self, tag: &str, settings: &TagSettings) -> StorageResult<Vec<AffectedSite>> {
+                let mut tx = self.pool.begin().await?;
+
+                let sites: Vec<TaggedSite> = sqlx::query_as(
+                    "SELECT s.id, s.url, s.interval_secs, s.style, s.priority, s.retention_count
+                     FROM sites s JOIN site_tags t ON t.site_id = s.id
+                     WHERE t.tag = $1
+                     ORDER BY s.id"
+                )
+                .bind(tag)
+                .fetch_all(&mut *tx)
+                .await?;
+
+                let mut affected = Vec::with_capacity(sites.len());
+                for site in sites {
+                    let after = settings.apply(&site.settings);
+                    if !settings.dry_run {
+                        sqlx::query("UPDATE sites SET interval_secs = $1, style = $2, priority = $3, retention_count = $4 WHERE id = $5")
+                            .bind(after.interval_secs)
+                            .bind(&after.style)
+                            .bind(after.priority)
+                            .bind(after.retention_count)
+                            .bind(site.id)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                    affected.push(AffectedSite {
+                        id: site.id,
+                        url: site.url,
+                        before: site.settings,
+                        after,
+                    });
+                }
+
+                if settings.dry_run {
+                    tx.rollback().await?;
+                } else {
+                    tx.commit().await?;
+                }
+                Ok(affected)
+            }
+
+            async fn list_tags(&self) -> StorageResult<Vec<Tag>> {
+                let tags = sqlx::query_as(
+                    "SELECT t.name, t.description, t.interval_secs, t.style, COUNT(s.site_id) AS site_count
+                     FROM tags t LEFT JOIN site_tags s ON s.tag = t.name
+                     GROUP BY t.name, t.description, t.interval_secs, t.style
+                     ORDER BY t.name"
+                )
+                .fetch_all(&self.pool)
+                .await?;
+                Ok(tags)
+            }
+
+            async fn insert_tag(&self, tag: &Tag) -> StorageResult<bool> {
+                let result = sqlx::query(
+                    "INSERT INTO tags (name, description, interval_secs, style) VALUES ($1, $2, $3, $4)
+                     ON CONFLICT (name) DO NOTHING"
+                )
+                .bind(&tag.name)
+                .bind(&tag.description)
+                .bind(tag.interval_secs)
+                .bind(&tag.style)
+                .execute(&self.pool)
+                .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn update_tag(&self, tag: &Tag) -> StorageResult<bool> {
+                let result = sqlx::query("UPDATE tags SET description = $1, interval_secs = $2, style = $3 WHERE name = $4")
+                    .bind(&tag.description)
+                    .bind(tag.interval_secs)
+                    .bind(&tag.style)
+                    .bind(&tag.name)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn delete_tag(&self, name: &str) -> StorageResult<bool> {
+                let mut tx = self.pool.begin().await?;
+                sqlx::query("DELETE FROM site_tags WHERE tag = $1").bind(name).execute(&mut *tx).await?;
+                let result = sqlx::query("DELETE FROM tags WHERE name = $1").bind(name).execute(&mut *tx).await?;
+                tx.commit().await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn mark_checked(&self, site_id: i64, checked_at: DateTime<Utc>, success: bool) -> StorageResult<()> {
+                let sql = if success {
+                    "UPDATE sites SET last_checked = $1, last_success = $1, status = CASE WHEN status = 'STALE' THEN status ELSE 'OK' END WHERE id = $2"
+                } else {
+                    "UPDATE sites SET last_checked = $1, status = CASE WHEN status = 'OVERDUE' THEN status ELSE 'ERROR' END WHERE id = $2"
+                };
+                sqlx::query(sql)
+                    .bind(checked_at)
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn mark_extraction_failed(&self, site_id: i64, checked_at: DateTime<Utc>) -> StorageResult<()> {
+                sqlx::query("UPDATE sites SET last_checked = $1, status = CASE WHEN status = 'OVERDUE' THEN status ELSE $2 END WHERE id = $3")
+                    .bind(checked_at)
+                    .bind(assertions::EXTRACTION_FAILED)
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn mark_challenged(&self, site_id: i64, checked_at: DateTime<Utc>) -> StorageResult<()> {
+                sqlx::query("UPDATE sites SET last_checked = $1, status = CASE WHEN status = 'OVERDUE' THEN status ELSE $2 END WHERE id = $3")
+                    .bind(checked_at)
+                    .bind(challenge::CHALLENGE)
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn mark_stale(&self, site_id: i64) -> StorageResult<bool> {
+                let result = sqlx::query("UPDATE sites SET status = 'STALE' WHERE id = $1 AND status = 'OK'")
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn mark_overdue(&self, site_id: i64) -> StorageResult<()> {
+                sqlx::query("UPDATE sites SET status = 'OVERDUE' WHERE id = $1")
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn set_snoozed_until(&self, site_id: i64, until: Option<DateTime<Utc>>) -> StorageResult<()> {
+                sqlx::query("UPDATE sites SET snoozed_until = $1 WHERE id = $2")
+                    .bind(until)
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn set_burst(&self, site_id: i64, interval_secs: Option<i64>, until: Option<DateTime<Utc>>) -> StorageResult<()> {
+                sqlx::query("UPDATE sites SET burst_interval_secs = $1, burst_until = $2 WHERE id = $3")
+                    .bind(interval_secs)
+                    .bind(until)
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn set_extraction_script(&self, site_id: i64, script: Option<&str>) -> StorageResult<()> {
+                sqlx::query("UPDATE sites SET extraction_script = $1 WHERE id = $2")
+                    .bind(script)
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn probe_signature(&self, site_id: i64) -> StorageResult<Option<String>> {
+                let row: Option<(Option<String>,)> = sqlx::query_as("SELECT probe_signature FROM sites WHERE id = $1")
+                    .bind(site_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.and_then(|(signature,)| signature))
+            }
+
+            async fn set_backoff(&self, site_id: i64, backoff_count: i64, until: Option<DateTime<Utc>>, reason: Option<&str>) -> StorageResult<()> {
+                let active = backoff_count > 0;
+                sqlx::query("UPDATE sites SET backoff_count = $1, backoff_until = $2, backoff_reason = $3 WHERE id = $4")
+                    .bind(backoff_count)
+                    .bind(until.filter(|_| active))
+                    .bind(reason.filter(|_| active))
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn record_health(&self, site_id: i64, success: bool, thresholds: &health::Thresholds) -> StorageResult<Option<health::Transition>> {
+                let mut tx = self.pool.begin().await?;
+                let row: Option<(i64, Option<String>)> = sqlx::query_as(
+                    "UPDATE sites SET consecutive_failures = CASE WHEN $1 THEN 0 ELSE consecutive_failures + 1 END WHERE id = $2
+                     RETURNING consecutive_failures, health"
+                )
+                .bind(success)
+                .bind(site_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+                let Some((failures, from)) = row else {
+                    return Ok(None);
+                };
+                let to = thresholds.state(failures);
+                let changed = from.as_deref() != Some(to);
+                if changed {
+                    sqlx::query("UPDATE sites SET health = $1 WHERE id = $2")
+                        .bind(to)
+                        .bind(site_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                // Committed either way, or the streak wouldn't grow between transitions
+                tx.commit().await?;
+                Ok(changed.then_some(health::Transition { from, to, failures }))
+            }
+
+            async fn record_check(&self, record: &CheckRecord<'_>) -> StorageResult<i64> {
+                let mut attempt = 1;
+                loop {
+                    match self.write_check(record).await {
+                        Err(e) if attempt < BUSY_ATTEMPTS && is_busy(&e) => {
+                            debug!(site_id = record.site_id, attempt, "Database busy, retrying check write: {}", e);
+                            tokio::time::sleep(std::time::Duration::from_millis(BUSY_RETRY_MS * attempt as u64)).await;
+                            attempt += 1;
+                        },
+                        result => return Ok(result?),
+                    }
+                }
+            }
+
+            async fn latest_hash(&self, site_id: i64) -> StorageResult<Option<String>> {
+                let row: Option<(String,)> = sqlx::query_as("SELECT diff_hash FROM updates WHERE site_id = $1 ORDER BY id DESC LIMIT 1")
+                    .bind(site_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(|(hash,)| hash))
+            }
+
+            #[cfg(test)]
+            async fn insert_update(&self, site_id: i64, timestamp: DateTime<Utc>, diff_hash: &str, content: &str) -> StorageResult<i64> {
+                let address = content_address(content);
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(INSERT_CONTENT)
+                    .bind(&address)
+                    .bind(compression::compress(content))
+                    .execute(&mut *tx)
+                    .await?;
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO updates (site_id, timestamp, diff_hash, content_hash, pipeline_version)
+                     VALUES ($1, $2, $3, $4, $5) RETURNING id"
+                )
+                .bind(site_id)
+                .bind(timestamp)
+                .bind(diff_hash)
+                .bind(&address)
+                .bind(scraper::PIPELINE_VERSION)
+                .fetch_one(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+
+            async fn previous_content(&self, site_id: i64, before_id: i64) -> StorageResult<Option<String>> {
+                let sql = format!(
+                    "SELECT {} FROM updates u {} WHERE u.site_id = $1 AND u.id < $2 ORDER BY u.id DESC LIMIT 1",
+                    BODY, BODY_JOIN
+                );
+                let row: Option<(StoredContent,)> = sqlx::query_as(&sql)
+                    .bind(site_id)
+                    .bind(before_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(|(content,)| content.into()))
+            }
+
+            async fn update_pdf(&self, update_id: i64) -> StorageResult<Option<Vec<u8>>> {
+                let row: Option<(Vec<u8>,)> = sqlx::query_as("SELECT p.data FROM updates u JOIN pdfs p ON p.hash = u.pdf_hash WHERE u.id = $1")
+                    .bind(update_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(|(data,)| data))
+            }
+
+            async fn insert_screenshot(&self, update_id: i64, captured_at: DateTime<Utc>, png: &[u8]) -> StorageResult<()> {
+                sqlx::query("INSERT INTO screenshots (update_id, captured_at, data) VALUES ($1, $2, $3)")
+                    .bind(update_id)
+                    .bind(captured_at)
+                    .bind(png)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn update_screenshot(&self, update_id: i64) -> StorageResult<Option<Vec<u8>>> {
+                let row: Option<(Vec<u8>,)> = sqlx::query_as("SELECT data FROM screenshots WHERE update_id = $1")
+                    .bind(update_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(|(data,)| data))
+            }
+
+            async fn update_content(&self, update_id: i64) -> StorageResult<Option<UpdateContent>> {
+                let sql = format!(
+                    "SELECT u.site_id, u.timestamp, {} AS content, s.selector, COALESCE(s.ignore_patterns, '[]') AS ignore_patterns, u.diff
+                     FROM updates u LEFT JOIN sites s ON s.id = u.site_id {}
+                     WHERE u.id = $1",
+                    BODY, BODY_JOIN
+                );
+                let record = sqlx::query_as(&sql)
+                    .bind(update_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(record)
+            }
+
+            async fn content_at(&self, site_id: i64, timestamp: DateTime<Utc>) -> StorageResult<Option<String>> {
+                let sql = format!("SELECT {} FROM updates u {} WHERE u.site_id = $1 AND u.timestamp = $2 LIMIT 1", BODY, BODY_JOIN);
+                let row: Option<(StoredContent,)> = sqlx::query_as(&sql)
+                    .bind(site_id)
+                    .bind(timestamp)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(|(content,)| content.into()))
+            }
+
+            async fn snapshots(&self, site_id: i64, since: Option<DateTime<Utc>>) -> StorageResult<Vec<Snapshot>> {
+                let sql = format!(
+                    "SELECT u.id, u.diff_hash, {} AS content, u.pipeline_version, u.pdf_hash IS NOT NULL AS pdf FROM updates u {}
+                     WHERE u.site_id = $1 AND ($2 IS NULL OR u.timestamp >= $2)
+                     ORDER BY u.id",
+                    BODY, BODY_JOIN
+                );
+                let snapshots = sqlx::query_as(&sql)
+                    .bind(site_id)
+                    .bind(since)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(snapshots)
+            }
+
+            async fn set_snapshot_hash(&self, update_id: i64, diff_hash: &str, pipeline_version: i64) -> StorageResult<()> {
+                sqlx::query("UPDATE updates SET diff_hash = $1, pipeline_version = $2 WHERE id = $3")
+                    .bind(diff_hash)
+                    .bind(pipeline_version)
+                    .bind(update_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn index_update_text(&self, update_id: i64, text: &str) -> StorageResult<()> {
+                sqlx::query(Self::INSERT_UPDATE_TEXT)
+                    .bind(update_id)
+                    .bind(text)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn unindexed_updates(&self, limit: i64) -> StorageResult<Vec<(i64, String)>> {
+                let rows: Vec<(i64, StoredContent)> = sqlx::query_as(Self::UNINDEXED_UPDATES)
+                    .bind(limit)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().map(|(id, content)| (id, content.into())).collect())
+            }
+
+            async fn search_updates(&self, query: &str, site_id: Option<i64>, limit: i64) -> StorageResult<Vec<SearchHit>> {
+                let query = Self::match_query(query);
+                if query.trim().is_empty() {
+                    return Ok(Vec::new());
+                }
+                let hits = sqlx::query_as(Self::SEARCH_UPDATES)
+                    .bind(query)
+                    .bind(site_id)
+                    .bind(limit)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(hits)
+            }
+
+            async fn move_inline_content(&self, limit: i64) -> StorageResult<u64> {
+                let rows: Vec<(i64, StoredContent)> = sqlx::query_as("SELECT id, content FROM updates WHERE content IS NOT NULL ORDER BY id LIMIT $1")
+                    .bind(limit)
+                    .fetch_all(&self.pool)
+                    .await?;
+                let count = rows.len() as u64;
+                let mut tx = self.pool.begin().await?;
+                for (id, content) in rows {
+                    let content = String::from(content);
+                    let address = content_address(&content);
+                    sqlx::query(INSERT_CONTENT)
+                        .bind(&address)
+                        .bind(compression::compress(&content))
+                        .execute(&mut *tx)
+                        .await?;
+                    sqlx::query("UPDATE updates SET content_hash = $1, content = NULL WHERE id = $2")
+                        .bind(&address)
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                tx.commit().await?;
+                Ok(count)
+            }
+
+            async fn collect_orphaned_contents(&self) -> StorageResult<u64> {
+                let bodies = sqlx::query("DELETE FROM contents WHERE NOT EXISTS (SELECT 1 FROM updates u WHERE u.content_hash = contents.hash)")
+                    .execute(&self.pool)
+                    .await?;
+                let pdfs = sqlx::query("DELETE FROM pdfs WHERE NOT EXISTS (SELECT 1 FROM updates u WHERE u.pdf_hash = pdfs.hash)")
+                    .execute(&self.pool)
+                    .await?;
+                Ok(bodies.rows_affected() + pdfs.rows_affected())
+            }
+
+            async fn set_update_hold(&self, update_id: i64, held: bool) -> StorageResult<bool> {
+                let result = sqlx::query("UPDATE updates SET legal_hold = $1 WHERE id = $2")
+                    .bind(held)
+                    .bind(update_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn legal_holds(&self) -> StorageResult<LegalHolds> {
+                let sites = sqlx::query_as("SELECT id AS site_id, url FROM sites WHERE legal_hold ORDER BY id")
+                    .fetch_all(&self.pool)
+                    .await?;
+                let updates = sqlx::query_as(
+                    "SELECT u.id AS update_id, u.site_id, s.url, u.timestamp
+                     FROM updates u JOIN sites s ON s.id = u.site_id
+                     WHERE u.legal_hold ORDER BY u.id"
+                )
+                .fetch_all(&self.pool)
+                .await?;
+                Ok(LegalHolds { sites, updates })
+            }
+
+            async fn prune_updates(&self, site_id: i64, keep: i64) -> StorageResult<u64> {
+                let sql = format!(
+                    "DELETE FROM updates WHERE site_id = $1 AND {} AND id NOT IN (
+                        SELECT id FROM updates WHERE site_id = $1 AND NOT legal_hold ORDER BY id DESC LIMIT $2
+                    )",
+                    NOT_HELD
+                );
+                let result = sqlx::query(&sql)
+                    .bind(site_id)
+                    .bind(keep)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn prune_all_updates(&self, keep: i64) -> StorageResult<u64> {
+                let sql = format!(
+                    "DELETE FROM updates WHERE id IN (
+                        SELECT id FROM (
+                            SELECT id, ROW_NUMBER() OVER (PARTITION BY site_id ORDER BY id DESC) AS rn
+                            FROM updates WHERE {}
+                        ) ranked WHERE rn > $1
+                    )",
+                    NOT_HELD
+                );
+                let result = sqlx::query(&sql)
+                    .bind(keep)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn prune_updates_before(&self, site_id: i64, cutoff: DateTime<Utc>) -> StorageResult<u64> {
+                let sql = format!(
+                    "DELETE FROM updates WHERE site_id = $1 AND timestamp < $2 AND {}
+                        AND id <> (SELECT MAX(id) FROM updates WHERE site_id = $1)",
+                    NOT_HELD
+                );
+                let result = sqlx::query(&sql)
+                    .bind(site_id)
+                    .bind(cutoff)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn recent_changes(&self, term: Option<&str>, limit: i64) -> StorageResult<Vec<ChangeEntry>> {
+                // LOWER on both sides since LIKE is case-sensitive in Postgres. Content is
+                // compressed, so the term is matched against the search index's page text.
+                let pattern = term.map(|t| format!("%{}%", t.to_lowercase()));
+                let sql = format!(
+                    "{} AND ($2 IS NULL OR LOWER(s.url) LIKE $2 OR {}) ORDER BY u.id DESC LIMIT $1",
+                    CHANGE_ROWS, Self::TEXT_MATCHES
+                );
+                let changes = sqlx::query_as(&sql)
+                    .bind(limit)
+                    .bind(pattern)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(changes)
+            }
+
+            async fn changes_since(&self, after_id: i64, limit: i64) -> StorageResult<Vec<ChangeEntry>> {
+                let sql = format!("{} AND u.id > $2 ORDER BY u.id LIMIT $1", CHANGE_ROWS);
+                let changes = sqlx::query_as(&sql)
+                    .bind(limit)
+                    .bind(after_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(changes)
+            }
+
+            async fn changes_between(&self, from: DateTime<Utc>, until: DateTime<Utc>, limit: i64) -> StorageResult<Vec<ChangeEntry>> {
+                let sql = format!("{} AND u.timestamp >= $2 AND u.timestamp < $3 ORDER BY u.id LIMIT $1", CHANGE_ROWS);
+                let changes = sqlx::query_as(&sql)
+                    .bind(limit)
+                    .bind(from)
+                    .bind(until)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(changes)
+            }
+
+            async fn feed_diff(&self, site_id: i64, entries: &[FeedEntry], seen_since: Option<DateTime<Utc>>) -> StorageResult<FeedDiff> {
+                let seen: Vec<(String,)> = sqlx::query_as(
+                    "SELECT item_key FROM seen_items WHERE site_id = $1 AND ($2 IS NULL OR last_seen >= $2)"
+                )
+                    .bind(site_id)
+                    .bind(seen_since)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(feeds::diff_against(seen.into_iter().map(|(key,)| key).collect(), entries))
+            }
+
+            async fn list_items(&self, query: &ItemQuery) -> StorageResult<Vec<Item>> {
+                let keyword = query.keyword().map(|k| format!("%{}%", k.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")));
+                let items = sqlx::query_as(
+                    "SELECT i.id, i.site_id, s.url AS site_url, i.guid, i.title, i.link, i.summary, i.published_at, i.first_seen
+                     FROM items i JOIN sites s ON s.id = i.site_id
+                     WHERE ($1 IS NULL OR i.site_id = $1)
+                       AND ($2 IS NULL OR i.site_id IN (SELECT site_id FROM site_tags WHERE tag = $2))
+                       AND ($3 IS NULL OR LOWER(COALESCE(i.title, '') || ' ' || COALESCE(i.summary, '')) LIKE $3 ESCAPE '\\')
+                       AND ($4 IS NULL OR COALESCE(i.published_at, i.first_seen) >= $4)
+                       AND ($5 IS NULL OR COALESCE(i.published_at, i.first_seen) < $5)
+                     ORDER BY COALESCE(i.published_at, i.first_seen) DESC, i.id DESC
+                     LIMIT $6"
+                )
+                    .bind(query.site_id)
+                    .bind(query.tag())
+                    .bind(keyword)
+                    .bind(query.since)
+                    .bind(query.until)
+                    .bind(query.limit())
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(items)
+            }
+
+            async fn insert_document(&self, document: &NewDocument<'_>) -> StorageResult<i64> {
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO documents (site_id, update_id, url, content_type, size, fetched_at, data) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id"
+                )
+                .bind(document.site_id)
+                .bind(document.update_id)
+                .bind(document.url)
+                .bind(document.content_type)
+                .bind(document.data.len() as i64)
+                .bind(document.fetched_at)
+                .bind(document.data)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(id)
+            }
+
+            async fn document(&self, id: i64) -> StorageResult<Option<(Document, Vec<u8>)>> {
+                let document: Option<Document> = sqlx::query_as(&format!("SELECT {} FROM documents WHERE id = $1", DOCUMENT_COLUMNS))
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                let Some(document) = document else {
+                    return Ok(None);
+                };
+                let (body,): (Vec<u8>,) = sqlx::query_as("SELECT data FROM documents WHERE id = $1")
+                    .bind(id)
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok(Some((document, body)))
+            }
+
+            async fn list_documents(&self, site_id: i64) -> StorageResult<Vec<Document>> {
+                let documents = sqlx::query_as(&format!("SELECT {} FROM documents WHERE site_id = $1 ORDER BY id DESC", DOCUMENT_COLUMNS))
+                    .bind(site_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(documents)
+            }
+
+            async fn record_halt(&self, site_id: i64, update_id: i64, halt: &Halt, seen_at: DateTime<Utc>) -> StorageResult<HaltChange> {
+                let inserted = sqlx::query(
+                    "INSERT INTO halts (site_id, update_id, symbol, name, market, reason_code, halted_at, resumed_at, first_seen)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT (site_id, symbol, halted_at) DO NOTHING"
+                )
+                .bind(site_id)
+                .bind(update_id)
+                .bind(&halt.symbol)
+                .bind(&halt.name)
+                .bind(&halt.market)
+                .bind(&halt.reason_code)
+                .bind(halt.halted_at)
+                .bind(halt.resumed_at)
+                .bind(seen_at)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+                if inserted > 0 {
+                    return Ok(HaltChange::New);
+                }
+                let Some(resumed_at) = halt.resumed_at else {
+                    return Ok(HaltChange::Unchanged);
+                };
+                let resumed = sqlx::query("UPDATE halts SET resumed_at = $1 WHERE site_id = $2 AND symbol = $3 AND halted_at = $4 AND resumed_at IS NULL")
+                    .bind(resumed_at)
+                    .bind(site_id)
+                    .bind(&halt.symbol)
+                    .bind(halt.halted_at)
+                    .execute(&self.pool)
+                    .await?
+                    .rows_affected();
+                Ok(if resumed > 0 { HaltChange::Resumed } else { HaltChange::Unchanged })
+            }
+
+            async fn list_halts(&self, query: &HaltQuery) -> StorageResult<Vec<StoredHalt>> {
+                let halts = sqlx::query_as(
+                    "SELECT id, site_id, update_id, symbol, name, market, reason_code, halted_at, resumed_at, first_seen FROM halts
+                     WHERE ($1 IS NULL OR site_id = $1) AND ($2 IS NULL OR symbol = $2)
+                     ORDER BY halted_at DESC, id DESC
+                     LIMIT $3"
+                )
+                    .bind(query.site_id)
+                    .bind(query.symbol())
+                    .bind(query.limit())
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(halts)
+            }
+
+            async fn record_federal_document(&self, site_id: i64, update_id: i64, document: &FederalDocument, seen_at: DateTime<Utc>) -> StorageResult<bool> {
+                let inserted = sqlx::query(
+                    "INSERT INTO federal_documents (site_id, update_id, document_number, title, document_type, agencies, publication_date, html_url, pdf_url, summary, first_seen)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) ON CONFLICT (site_id, document_number) DO NOTHING"
+                )
+                .bind(site_id)
+                .bind(update_id)
+                .bind(&document.document_number)
+                .bind(&document.title)
+                .bind(&document.document_type)
+                .bind(Json(&document.agencies))
+                .bind(document.publication_date)
+                .bind(&document.html_url)
+                .bind(&document.pdf_url)
+                .bind(&document.summary)
+                .bind(seen_at)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+                Ok(inserted > 0)
+            }
+
+            async fn list_federal_documents(&self, query: &FederalDocumentQuery) -> StorageResult<Vec<StoredFederalDocument>> {
+                let documents = sqlx::query_as(
+                    "SELECT id, site_id, update_id, document_number, title, document_type, agencies, publication_date, html_url, pdf_url, summary, first_seen
+                     FROM federal_documents
+                     WHERE ($1 IS NULL OR site_id = $1) AND ($2 IS NULL OR document_type = $2)
+                     ORDER BY publication_date DESC, id DESC
+                     LIMIT $3"
+                )
+                    .bind(query.site_id)
+                    .bind(query.document_type())
+                    .bind(query.limit())
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(documents)
+            }
+
+            async fn list_alert_rules(&self) -> StorageResult<Vec<AlertRule>> {
+                let rules = sqlx::query_as("SELECT * FROM alert_rules ORDER BY id")
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rules)
+            }
+
+            async fn site_alert_rules(&self, site_id: i64) -> StorageResult<Vec<AlertRule>> {
+                let rules = sqlx::query_as("SELECT * FROM alert_rules WHERE site_id IS NULL OR site_id = $1 ORDER BY id")
+                    .bind(site_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rules)
+            }
+
+            async fn insert_alert_rule(&self, rule: &AlertRule) -> StorageResult<i64> {
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO alert_rules (name, pattern, is_regex, site_id, created_at, high_priority, press_release) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id"
+                )
+                .bind(&rule.name)
+                .bind(&rule.pattern)
+                .bind(rule.is_regex)
+                .bind(rule.site_id)
+                .bind(rule.created_at)
+                .bind(rule.high_priority)
+                .bind(rule.press_release)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(id)
+            }
+
+            async fn delete_alert_rule(&self, id: i64) -> StorageResult<bool> {
+                let result = sqlx::query("DELETE FROM alert_rules WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn insert_alert(&self, alert: &Alert) -> StorageResult<i64> {
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO alerts (update_id, site_id, rules, created_at) VALUES ($1, $2, $3, $4) RETURNING id"
+                )
+                .bind(alert.update_id)
+                .bind(alert.site_id)
+                .bind(&alert.rules)
+                .bind(alert.created_at)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(id)
+            }
+
+            async fn list_alerts(&self, unacknowledged_only: bool, limit: i64) -> StorageResult<Vec<Alert>> {
+                let alerts = sqlx::query_as("SELECT * FROM alerts WHERE ($1 OR acknowledged_at IS NULL) ORDER BY id DESC LIMIT $2")
+                    .bind(!unacknowledged_only)
+                    .bind(limit)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(alerts)
+            }
+
+            async fn acknowledge_alert(&self, id: i64, at: DateTime<Utc>, by: Option<&str>) -> StorageResult<bool> {
+                let result = sqlx::query(
+                    "UPDATE alerts SET acknowledged_at = COALESCE(acknowledged_at, $1),
+                        acknowledged_by = CASE WHEN acknowledged_at IS NULL THEN $2 ELSE acknowledged_by END
+                     WHERE id = $3"
+                )
+                .bind(at)
+                .bind(by)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn list_webhooks(&self) -> StorageResult<Vec<Webhook>> {
+                let hooks = sqlx::query_as("SELECT * FROM webhooks ORDER BY id")
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(hooks)
+            }
+
+            async fn site_webhooks(&self, site_id: i64) -> StorageResult<Vec<Webhook>> {
+                let hooks = sqlx::query_as("SELECT * FROM webhooks WHERE site_id IS NULL OR site_id = $1 ORDER BY id")
+                    .bind(site_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(hooks)
+            }
+
+            async fn get_webhook(&self, id: i64) -> StorageResult<Option<Webhook>> {
+                let hook = sqlx::query_as("SELECT * FROM webhooks WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(hook)
+            }
+
+            async fn insert_webhook(&self, hook: &Webhook) -> StorageResult<i64> {
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO webhooks (url, secret, site_id, created_at) VALUES ($1, $2, $3, $4) RETURNING id"
+                )
+                .bind(&hook.url)
+                .bind(&hook.secret)
+                .bind(hook.site_id)
+                .bind(hook.created_at)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(id)
+            }
+
+            async fn delete_webhook(&self, id: i64) -> StorageResult<bool> {
+                let result = sqlx::query("DELETE FROM webhooks WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn record_canary(&self, id: i64, checked_at: DateTime<Utc>, error: Option<&str>) -> StorageResult<()> {
+                sqlx::query("UPDATE webhooks SET canary_checked_at = $1, canary_ok = $2, canary_error = $3 WHERE id = $4")
+                    .bind(checked_at)
+                    .bind(error.is_none())
+                    .bind(error)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn list_telegram_chats(&self) -> StorageResult<Vec<TelegramChat>> {
+                let mut chats: Vec<TelegramChat> = sqlx::query_as("SELECT * FROM telegram_chats ORDER BY id")
+                    .fetch_all(&self.pool)
+                    .await?;
+                let sites: Vec<(i64, i64)> = sqlx::query_as("SELECT telegram_chat_id, site_id FROM telegram_sites ORDER BY site_id")
+                    .fetch_all(&self.pool)
+                    .await?;
+                for chat in &mut chats {
+                    chat.site_ids = sites.iter().filter(|(chat_id, _)| *chat_id == chat.id).map(|(_, site_id)| *site_id).collect();
+                }
+                Ok(chats)
+            }
+
+            async fn site_telegram_chats(&self, site_id: i64) -> StorageResult<Vec<TelegramChat>> {
+                let chats = sqlx::query_as(
+                    "SELECT c.* FROM telegram_chats c JOIN telegram_sites s ON s.telegram_chat_id = c.id
+                     WHERE s.site_id = $1 ORDER BY c.id"
+                )
+                .bind(site_id)
+                .fetch_all(&self.pool)
+                .await?;
+                Ok(chats)
+            }
+
+            async fn insert_telegram_chat(&self, chat: &TelegramChat) -> StorageResult<i64> {
+                let mut tx = self.pool.begin().await?;
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO telegram_chats (name, bot_token, chat_id, created_at) VALUES ($1, $2, $3, $4) RETURNING id"
+                )
+                .bind(&chat.name)
+                .bind(&chat.bot_token)
+                .bind(&chat.chat_id)
+                .bind(chat.created_at)
+                .fetch_one(&mut *tx)
+                .await?;
+                for site_id in &chat.site_ids {
+                    sqlx::query("INSERT INTO telegram_sites (telegram_chat_id, site_id) VALUES ($1, $2)")
+                        .bind(id)
+                        .bind(site_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                tx.commit().await?;
+                Ok(id)
+            }
+
+            async fn set_telegram_sites(&self, id: i64, site_ids: &[i64]) -> StorageResult<bool> {
+                let mut tx = self.pool.begin().await?;
+                let exists: Option<(i64,)> = sqlx::query_as("SELECT id FROM telegram_chats WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                if exists.is_none() {
+                    return Ok(false);
+                }
+                sqlx::query("DELETE FROM telegram_sites WHERE telegram_chat_id = $1")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                for site_id in site_ids {
+                    sqlx::query("INSERT INTO telegram_sites (telegram_chat_id, site_id) VALUES ($1, $2)")
+                        .bind(id)
+                        .bind(site_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn delete_telegram_chat(&self, id: i64) -> StorageResult<bool> {
+                let mut tx = self.pool.begin().await?;
+                sqlx::query("DELETE FROM telegram_sites WHERE telegram_chat_id = $1")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                let result = sqlx::query("DELETE FROM telegram_chats WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn list_email_recipients(&self) -> StorageResult<Vec<EmailRecipient>> {
+                let recipients = sqlx::query_as("SELECT * FROM email_recipients ORDER BY id")
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(recipients)
+            }
+
+            async fn insert_email_recipient(&self, recipient: &EmailRecipient) -> StorageResult<i64> {
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO email_recipients (address, alerts, digest, digest_hour, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING id"
+                )
+                .bind(&recipient.address)
+                .bind(recipient.alerts)
+                .bind(&recipient.digest)
+                .bind(recipient.digest_hour)
+                .bind(recipient.created_at)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(id)
+            }
+
+            async fn update_email_recipient(&self, recipient: &EmailRecipient) -> StorageResult<bool> {
+                let result = sqlx::query("UPDATE email_recipients SET address = $1, alerts = $2, digest = $3, digest_hour = $4 WHERE id = $5")
+                    .bind(&recipient.address)
+                    .bind(recipient.alerts)
+                    .bind(&recipient.digest)
+                    .bind(recipient.digest_hour)
+                    .bind(recipient.id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn delete_email_recipient(&self, id: i64) -> StorageResult<bool> {
+                let result = sqlx::query("DELETE FROM email_recipients WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn mark_digest_sent(&self, id: i64, at: DateTime<Utc>) -> StorageResult<()> {
+                sqlx::query("UPDATE email_recipients SET last_digest_at = $1 WHERE id = $2")
+                    .bind(at)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn list_email_templates(&self) -> StorageResult<Vec<EmailTemplate>> {
+                let templates = sqlx::query_as("SELECT * FROM email_templates ORDER BY kind")
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(templates)
+            }
+
+            async fn set_email_template(&self, template: &EmailTemplate) -> StorageResult<()> {
+                sqlx::query(
+                    "INSERT INTO email_templates (kind, subject, body) VALUES ($1, $2, $3)
+                     ON CONFLICT (kind) DO UPDATE SET subject = excluded.subject, body = excluded.body"
+                )
+                .bind(&template.kind)
+                .bind(&template.subject)
+                .bind(&template.body)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn delete_email_template(&self, kind: &str) -> StorageResult<bool> {
+                let result = sqlx::query("DELETE FROM email_templates WHERE kind = $1")
+                    .bind(kind)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn claim_notification(&self, site_id: i64, diff_hash: &str, at: DateTime<Utc>) -> StorageResult<bool> {
+                let result = sqlx::query(
+                    "INSERT INTO notified_changes (site_id, diff_hash, notified_at) VALUES ($1, $2, $3)
+                     ON CONFLICT (site_id, diff_hash) DO NOTHING"
+                )
+                .bind(site_id)
+                .bind(diff_hash)
+                .bind(at)
+                .execute(&self.pool)
+                .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn list_api_keys(&self) -> StorageResult<Vec<ApiKey>> {
+                let keys = sqlx::query_as("SELECT * FROM api_keys ORDER BY id")
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(keys)
+            }
+
+            async fn insert_api_key(&self, key: &ApiKey) -> StorageResult<i64> {
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO api_keys (name, key_hash, role, created_at) VALUES ($1, $2, $3, $4) RETURNING id"
+                )
+                .bind(&key.name)
+                .bind(&key.key_hash)
+                .bind(&key.role)
+                .bind(key.created_at)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(id)
+            }
+
+            async fn delete_api_key(&self, id: i64) -> StorageResult<bool> {
+                let result = sqlx::query("DELETE FROM api_keys WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn list_api_tokens(&self) -> StorageResult<Vec<ApiToken>> {
+                let tokens = sqlx::query_as("SELECT * FROM api_tokens ORDER BY id")
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(tokens)
+            }
+
+            async fn insert_api_token(&self, token: &ApiToken) -> StorageResult<i64> {
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO api_tokens (name, token_hash, scope, created_at, expires_at) VALUES ($1, $2, $3, $4, $5) RETURNING id"
+                )
+                .bind(&token.name)
+                .bind(&token.token_hash)
+                .bind(&token.scope)
+                .bind(token.created_at)
+                .bind(token.expires_at)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(id)
+            }
+
+            async fn delete_api_token(&self, id: i64) -> StorageResult<bool> {
+                let result = sqlx::query("DELETE FROM api_tokens WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn record_fetch(&self, entry: &FetchLogEntry, keep: i64) -> StorageResult<()> {
+                sqlx::query(
+                    "INSERT INTO fetch_log (site_id, fetched_at, status_code, duration_ms, error, proxy, final_url, content_type,
+                        content_length, changed, update_id)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"
+                )
+                    .bind(entry.site_id)
+                    .bind(entry.fetched_at)
+                    .bind(entry.status_code)
+                    .bind(entry.duration_ms)
+                    .bind(&entry.error)
+                    .bind(&entry.proxy)
+                    .bind(&entry.final_url)
+                    .bind(&entry.content_type)
+                    .bind(entry.content_length)
+                    .bind(entry.changed)
+                    .bind(entry.update_id)
+                    .execute(&self.pool)
+                    .await?;
+                sqlx::query(
+                    "DELETE FROM fetch_log WHERE site_id = $1 AND id NOT IN (
+                        SELECT id FROM fetch_log WHERE site_id = $1 ORDER BY id DESC LIMIT $2
+                    )"
+                )
+                .bind(entry.site_id)
+                .bind(keep.max(0))
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn fetch_log(&self, site_id: i64, limit: i64) -> StorageResult<Vec<FetchLogEntry>> {
+                let entries = sqlx::query_as(
+                    "SELECT site_id, fetched_at, status_code, duration_ms, error, proxy, final_url, content_type, content_length,
+                        changed, update_id
+                     FROM fetch_log WHERE site_id = $1 ORDER BY id DESC LIMIT $2"
+                )
+                .bind(site_id)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+                Ok(entries)
+            }
+
+            async fn change_times(&self, site_id: i64) -> StorageResult<Vec<DateTime<Utc>>> {
+                let rows: Vec<(DateTime<Utc>,)> = sqlx::query_as("SELECT changed_at FROM site_changes WHERE site_id = $1 ORDER BY changed_at, id")
+                    .bind(site_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().map(|(at,)| at).collect())
+            }
+
+            async fn set_interval(&self, site_id: i64, interval_secs: i64) -> StorageResult<()> {
+                sqlx::query("UPDATE sites SET interval_secs = $1 WHERE id = $2")
+                    .bind(interval_secs)
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+            async fn set_adaptive_interval(&self, site_id: i64, interval_secs: i64) -> StorageResult<()> {
+                sqlx::query("UPDATE sites SET adaptive_interval_secs = $1 WHERE id = $2")
+                    .bind(interval_secs)
+                    .bind(site_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn record_latency(&self, sample: &LatencySample) -> StorageResult<()> {
+                sqlx::query(
+                    "INSERT INTO detection_latency (site_id, update_id, published, detected_at, latency_secs)
+                     VALUES ($1, $2, $3, $4, $5)"
+                )
+                .bind(sample.site_id)
+                .bind(sample.update_id)
+                .bind(sample.published)
+                .bind(sample.detected_at)
+                .bind(sample.latency_secs())
+                .execute(&self.pool)
+                .await?;
+                sqlx::query(
+                    "DELETE FROM detection_latency WHERE site_id = $1 AND id NOT IN (
+                        SELECT id FROM detection_latency WHERE site_id = $1 ORDER BY id DESC LIMIT $2
+                    )"
+                )
+                .bind(sample.site_id)
+                .bind(LATENCY_SAMPLES_PER_SITE)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn latency_stats(&self) -> StorageResult<Vec<SiteLatency>> {
+                let rows: Vec<(i64, String, i64)> = sqlx::query_as(
+                    "SELECT l.site_id, s.url, l.latency_secs FROM detection_latency l
+                     JOIN sites s ON s.id = l.site_id
+                     ORDER BY l.site_id, l.id"
+                )
+                .fetch_all(&self.pool)
+                .await?;
+
+                let mut stats = Vec::new();
+                for group in rows.chunk_by(|a, b| a.0 == b.0) {
+                    let latencies: Vec<i64> = group.iter().map(|r| r.2).collect();
+                    stats.extend(summarize_latency(group[0].0, group[0].1.clone(), &latencies));
+                }
+                Ok(sort_by_median(stats))
+            }
+
+            async fn activity(&self, since: DateTime<Utc>) -> StorageResult<Activity> {
+                let activity = sqlx::query_as(
+                    "SELECT COUNT(*) AS fetches,
+                        COALESCE(SUM(CASE WHEN error IS NOT NULL THEN 1 ELSE 0 END), 0) AS failed_fetches,
+                        (SELECT COUNT(*) FROM updates WHERE timestamp >= $1) AS changes
+                     FROM fetch_log WHERE fetched_at >= $1"
+                )
+                .bind(since)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(activity)
+            }
+
+            async fn top_changing_sites(&self, since: DateTime<Utc>, limit: i64) -> StorageResult<Vec<ChangeCount>> {
+                let sites = sqlx::query_as(
+                    "SELECT u.site_id, s.url, COUNT(*) AS changes FROM updates u
+                     JOIN sites s ON s.id = u.site_id
+                     WHERE u.timestamp >= $1
+                     GROUP BY u.site_id, s.url
+                     ORDER BY changes DESC, u.site_id
+                     LIMIT $2"
+                )
+                .bind(since)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+                Ok(sites)
+            }
+
+            async fn database_size(&self) -> StorageResult<Option<i64>> {
+                let (size,): (i64,) = sqlx::query_as(Self::DATABASE_SIZE).fetch_one(&self.pool).await?;
+                Ok(Some(size))
+            }
+
+            // Reads the sites table, so a missing schema fails too, without writing anything
+            async fn ping(&self) -> StorageResult<()> {
+                sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM sites").fetch_one(&self.pool).await?;
+                Ok(())
+            }
+
+            // Write and read back a canary row inside a transaction that is always rolled back
+            async fn round_trip(&self) -> StorageResult<()> {
+                let mut tx = self.pool.begin().await?;
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO sites (url, interval_secs, style) VALUES ('selftest://canary', 0, 'none') RETURNING id"
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+                let row: Option<(String,)> = sqlx::query_as("SELECT url FROM sites WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                let _ = tx.rollback().await;
+
+                match row {
+                    Some((url,)) if url == "selftest://canary" => Ok(()),
+                    _ => Err(StorageError::new("canary row not read back")),
+                }
+            }
+
+            // Other tables reference sites, so rows are deleted rather than truncated
+            async fn reset(&self) -> StorageResult<()> {
+                warn!("Dropping all sites and their stored data");
+                let mut tx = self.pool.begin().await?;
+                sqlx::query("DELETE FROM webhooks WHERE site_id IS NOT NULL").execute(&mut *tx).await?;
+                sqlx::query("DELETE FROM alert_rules WHERE site_id IS NOT NULL").execute(&mut *tx).await?;
+                for table in schema::SITE_TABLES {
+                    sqlx::query(&format!("DELETE FROM {}", table)).execute(&mut *tx).await?;
+                }
+                tx.commit().await?;
+                Ok(())
+            }
+        }
+
+        impl $backend {
+            // Rolled back as a whole when any statement fails (dropping tx rolls it back)
+            async fn write_check(&self, record: &CheckRecord<'_>) -> Result<i64, sqlx::Error> {
+                let site_id = record.site_id;
+                let mut tx = self.pool.begin().await?;
+                if let Some(entries) = record.feed_entries {
+                    for key in entries.iter().flat_map(feeds::item_keys) {
+                        sqlx::query(
+                            "INSERT INTO seen_items (site_id, item_key, first_seen, last_seen)
+                             VALUES ($1, $2, $3, $3)
+                             ON CONFLICT (site_id, item_key) DO UPDATE SET last_seen = excluded.last_seen"
+                        )
+                        .bind(site_id)
+                        .bind(key)
+                        .bind(record.fetched_at)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    sqlx::query(
+                        "DELETE FROM seen_items WHERE site_id = $1 AND (
+                            ($2 IS NOT NULL AND last_seen < $2) OR item_key NOT IN (
+                                SELECT item_key FROM seen_items WHERE site_id = $1 ORDER BY last_seen DESC LIMIT $3
+                            )
+                        )"
+                    )
+                    .bind(site_id)
+                    .bind(record.seen_since)
+                    .bind(MAX_SEEN_ITEMS)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                for item in record.items {
+                    sqlx::query(
+                        "INSERT INTO items (site_id, guid, title, link, summary, published_at, first_seen)
+                         VALUES ($1, $2, $3, $4, $5, $6, $7)
+                         ON CONFLICT (site_id, guid) DO NOTHING"
+                    )
+                    .bind(site_id)
+                    .bind(&item.guid)
+                    .bind(&item.title)
+                    .bind(&item.link)
+                    .bind(&item.summary)
+                    .bind(item.published)
+                    .bind(record.fetched_at)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                sqlx::query("UPDATE sites SET last_checked = $1, last_success = $1, status = CASE WHEN status = 'STALE' THEN status ELSE 'OK' END, probe_signature = COALESCE($2, probe_signature) WHERE id = $3")
+                    .bind(record.fetched_at)
+                    .bind(record.probe_signature)
+                    .bind(site_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                // A body that is already stored, such as an unchanged fetch, only adds the update row
+                let address = content_address(record.content);
+                sqlx::query(INSERT_CONTENT)
+                    .bind(&address)
+                    .bind(compression::compress(record.content))
+                    .execute(&mut *tx)
+                    .await?;
+                let pdf_address = record.pdf.map(pdf_address);
+                if let (Some(pdf), Some(pdf_address)) = (record.pdf, &pdf_address) {
+                    sqlx::query(INSERT_PDF)
+                        .bind(pdf_address)
+                        .bind(pdf)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                let diff = record.change.as_ref().and_then(|change| change.diff.as_deref());
+                let (update_id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO updates (site_id, timestamp, diff_hash, content_hash, pipeline_version, diff, pdf_hash, metadata, published_at, wire_source)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING id"
+                )
+                .bind(site_id)
+                .bind(record.fetched_at)
+                .bind(record.diff_hash)
+                .bind(&address)
+                .bind(scraper::PIPELINE_VERSION)
+                .bind(diff)
+                .bind(&pdf_address)
+                .bind(Json(record.metadata))
+                .bind(record.published_at)
+                .bind(record.wire_source)
+                .fetch_one(&mut *tx)
+                .await?;
+                sqlx::query(Self::INSERT_UPDATE_TEXT)
+                    .bind(update_id)
+                    .bind(record.indexed_text)
+                    .execute(&mut *tx)
+                    .await?;
+
+                if let Some(change) = &record.change {
+                    if change.in_history {
+                        sqlx::query("INSERT INTO site_changes (site_id, changed_at) VALUES ($1, $2)")
+                            .bind(site_id)
+                            .bind(record.fetched_at)
+                            .execute(&mut *tx)
+                            .await?;
+                        sqlx::query(
+                            "DELETE FROM site_changes WHERE site_id = $1 AND id NOT IN (
+                                SELECT id FROM site_changes WHERE site_id = $1 ORDER BY id DESC LIMIT $2
+                            )"
+                        )
+                        .bind(site_id)
+                        .bind(change.history_size.max(0))
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    sqlx::query("UPDATE sites SET last_updated = $1, status = CASE WHEN status = 'STALE' THEN 'OK' ELSE status END WHERE id = $2")
+                        .bind(record.fetched_at)
+                        .bind(site_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                tx.commit().await?;
+                Ok(update_id)
+            }
+        }
+    };
+}
+
+impl_sql_storage!(SqliteStorage);
+impl_sql_storage!(PostgresStorage);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::RecordedChange;
+
+    fn entry(guid: &str) -> FeedEntry {
+        FeedEntry { guid: guid.into(), title: None, link: None, summary: None, published: None }
+    }
+
+    #[tokio::test]
+    async fn sqlite_storage_round_trips_sites_and_changes() {
+        let path = std::env::temp_dir().join(format!("scraper-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let storage = SqliteStorage::connect(&format!("sqlite:{}?mode=rwc", path.display())).await.unwrap();
+
+        let site = Site { tags: vec!["FDA".into(), "gov".into()], ..Site::new("https://a.example", 60, "random") };
+        let id = storage.insert_site(&site).await.unwrap();
+        assert!(storage.insert_site(&site).await.is_err());
+        assert_eq!(storage.site_ids_with_tag("fda").await.unwrap(), vec![id]);
+
+        let now = Utc::now();
+        let check = |diff_hash, content, feed_entries, change| CheckRecord {
+            site_id: id,
+            fetched_at: now,
+            diff_hash,
+            content,
+            pdf: None,
+            metadata: None,
+            published_at: None,
+            wire_source: None,
+            indexed_text: content,
+            probe_signature: Some("etag"),
+            feed_entries,
+            seen_since: None,
+            items: &[],
+            change,
+        };
+        let first_change = RecordedChange { diff: None, in_history: false, history_size: 10 };
+        storage.record_check(&check("h1", "one", None, Some(first_change))).await.unwrap();
+        storage.insert_update(id, now, "h1", "one again").await.unwrap();
+        storage.insert_update(id, now, "h2", "two").await.unwrap();
+        let changes = storage.recent_changes(None, 10).await.unwrap();
+        assert_eq!(changes.iter().map(|c| c.diff_hash.as_str()).collect::<Vec<_>>(), vec!["h2", "h1"]);
+        assert_eq!(storage.probe_signature(id).await.unwrap().as_deref(), Some("etag"));
+        assert!(storage.change_times(id).await.unwrap().is_empty());
+
+        let entries = [entry("a")];
+        let first = storage.feed_diff(id, &entries, None).await.unwrap();
+        assert!(first.first_fetch);
+        let change = RecordedChange { diff: Some("-two\n+feed".into()), in_history: true, history_size: 10 };
+        let update_id = storage.record_check(&check("h3", "feed", Some(&entries), Some(change))).await.unwrap();
+        assert_eq!(storage.update_content(update_id).await.unwrap().unwrap().diff.as_deref(), Some("-two\n+feed"));
+        assert_eq!(storage.change_times(id).await.unwrap(), vec![now]);
+        let next = storage.feed_diff(id, &[entry("a"), entry("b"), entry("b")], None).await.unwrap();
+        assert!(!next.first_fetch);
+        assert_eq!(next.new_entries.iter().map(|e| e.guid.as_str()).collect::<Vec<_>>(), vec!["b"]);
+        // Items not seen again within the lookback window are forgotten
+        let later = now + chrono::Duration::days(2);
+        let window = Some(now + chrono::Duration::days(1));
+        assert!(storage.feed_diff(id, &entries, window).await.unwrap().first_fetch);
+        let entries_b = [entry("b")];
+        let refresh = CheckRecord { fetched_at: later, seen_since: window, ..check("h3", "feed", Some(&entries_b), None) };
+        storage.record_check(&refresh).await.unwrap();
+        let seen: Vec<(String,)> = sqlx::query_as("SELECT item_key FROM seen_items").fetch_all(&storage.pool).await.unwrap();
+        assert_eq!(seen, vec![("guid:b".to_string(),)]);
+
+        // Items are stored once; keyword wildcards are matched literally
+        let item = FeedEntry { title: Some("FDA approves 50% of filings".into()), ..entry("i1") };
+        let with_items = CheckRecord { items: std::slice::from_ref(&item), ..check("h3", "feed", None, None) };
+        storage.record_check(&with_items).await.unwrap();
+        storage.record_check(&with_items).await.unwrap();
+        let query = |q: &str| ItemQuery { q: Some(q.into()), tag: Some("FDA".into()), ..ItemQuery::default() };
+        let items = storage.list_items(&query("50%")).await.unwrap();
+        assert_eq!(items.iter().map(|i| (i.guid.as_str(), i.site_url.as_str())).collect::<Vec<_>>(), [("i1", "https://a.example")]);
+        assert!(storage.list_items(&query("5_%")).await.unwrap().is_empty());
+
+        // Nothing of a check is kept when part of it fails
+        let orphan = CheckRecord { site_id: id + 100, ..check("h4", "orphan", Some(&entries), None) };
+        assert!(storage.record_check(&orphan).await.is_err());
+        assert!(storage.feed_diff(id + 100, &entries, None).await.unwrap().first_fetch);
+
+        let other = storage.insert_site(&Site::new("https://b.example", 60, "random")).await.unwrap();
+        assert_eq!(storage.delete_sites(&[id, other + 1]).await.unwrap(), vec![id]);
+        assert!(storage.delete_site(other).await.unwrap());
+        assert!(!storage.delete_site(other).await.unwrap());
+        assert!(storage.recent_changes(None, 10).await.unwrap().is_empty());
+        assert!(storage.list_tags().await.unwrap().iter().all(|t| t.site_count == 0));
+
+        storage.ping().await.unwrap();
+        storage.round_trip().await.unwrap();
+        storage.reset().await.unwrap();
+        assert!(storage.list_sites().await.unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn sqlite_search_finds_phrases_until_updates_are_pruned() {
+        let path = std::env::temp_dir().join(format!("scraper-search-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let storage = SqliteStorage::connect(&format!("sqlite:{}?mode=rwc", path.display())).await.unwrap();
+        let id = storage.insert_site(&Site::new("https://a.example", 60, "random")).await.unwrap();
+
+        let now = Utc::now();
+        let old = storage.insert_update(id, now, "h1", "<p>FDA approves <b>phase 3</b> trial</p>").await.unwrap();
+        storage.index_update_text(old, &search::indexed_text("<p>FDA approves <b>phase 3</b> trial</p>")).await.unwrap();
+        let new = storage.insert_update(id, now, "h2", "<p>Trial halted</p>").await.unwrap();
+        assert_eq!(storage.unindexed_updates(10).await.unwrap(), vec![(new, "<p>Trial halted</p>".to_string())]);
+        storage.index_update_text(new, "Trial halted").await.unwrap();
+
+        let hits = storage.search_updates(r#"approves "phase 3""#, None, 10).await.unwrap();
+        assert_eq!(hits.iter().map(|h| h.update_id).collect::<Vec<_>>(), vec![old]);
+        assert_eq!(hits[0].snippet, "FDA \u{2}approves\u{3}\n\u{2}phase 3\u{3}\ntrial");
+        assert_eq!(storage.search_updates("trial", Some(id), 10).await.unwrap().len(), 2);
+        assert!(storage.search_updates("\"", None, 10).await.unwrap().is_empty());
+
+        storage.prune_updates(id, 1).await.unwrap();
+        assert!(storage.search_updates("approves", None, 10).await.unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn sqlite_health_follows_the_failure_streak() {
+        let path = std::env::temp_dir().join(format!("scraper-health-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let storage = SqliteStorage::connect(&format!("sqlite:{}?mode=rwc", path.display())).await.unwrap();
+        let id = storage.insert_site(&Site::new("https://a.example", 60, "random")).await.unwrap();
+        let thresholds = health::Thresholds::new(2, 4);
+        let mut states = Vec::new();
+        for success in [true, false, false, false, false, false, true] {
+            let transition = storage.record_health(id, success, &thresholds).await.unwrap();
+            states.push(transition.map(|t| (t.to, t.failures)));
+        }
+        assert_eq!(states, [
+            Some((health::OK, 0)), None, Some((health::DEGRADED, 2)), None, Some((health::DOWN, 4)), None, Some((health::OK, 0)),
+        ]);
+        let site = storage.get_site(id).await.unwrap().unwrap();
+        assert_eq!((site.consecutive_failures, site.health.as_deref()), (0, Some(health::OK)));
+        assert!(storage.record_health(999, false, &thresholds).await.unwrap().is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn sqlite_retention_skips_held_updates_and_sites() {
+        let path = std::env::temp_dir().join(format!("scraper-hold-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let storage = SqliteStorage::connect(&format!("sqlite:{}?mode=rwc", path.display())).await.unwrap();
+        let id = storage.insert_site(&Site::new("https://a.example", 60, "random")).await.unwrap();
+        let held_site = storage.insert_site(&Site { legal_hold: true, ..Site::new("https://b.example", 60, "random") }).await.unwrap();
+
+        let now = Utc::now();
+        let mut ids = Vec::new();
+        for n in 0..4 {
+            ids.push(storage.insert_update(id, now, &format!("h{}", n), "page").await.unwrap());
+            storage.insert_update(held_site, now, &format!("h{}", n), "page").await.unwrap();
+        }
+        assert!(storage.set_update_hold(ids[0], true).await.unwrap());
+        assert!(!storage.set_update_hold(999, true).await.unwrap());
+
+        // The held update doesn't count towards the two kept
+        assert_eq!(storage.prune_updates(id, 2).await.unwrap(), 1);
+        assert_eq!(storage.prune_all_updates(1).await.unwrap(), 1);
+        assert_eq!(storage.prune_updates_before(id, now + chrono::Duration::days(1)).await.unwrap(), 0);
+        let kept: Vec<i64> = storage.snapshots(id, None).await.unwrap().iter().map(|s| s.id).collect();
+        assert_eq!(kept, vec![ids[0], ids[3]]);
+        assert_eq!(storage.snapshots(held_site, None).await.unwrap().len(), 4);
+
+        let holds = storage.legal_holds().await.unwrap();
+        assert_eq!(holds.sites.iter().map(|s| s.site_id).collect::<Vec<_>>(), vec![held_site]);
+        assert_eq!(holds.updates.iter().map(|u| u.update_id).collect::<Vec<_>>(), vec![ids[0]]);
+        assert!(holds.covers_site(id));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn sqlite_stores_each_body_once_and_collects_orphans() {
+        let path = std::env::temp_dir().join(format!("scraper-contents-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let storage = SqliteStorage::connect(&format!("sqlite:{}?mode=rwc", path.display())).await.unwrap();
+        let id = storage.insert_site(&Site::new("https://a.example", 60, "random")).await.unwrap();
+        let bodies = || sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM contents").fetch_one(&storage.pool);
+
+        // A row written before the contents table, uncompressed and inline
+        let now = Utc::now();
+        sqlx::query("INSERT INTO updates (site_id, timestamp, diff_hash, content) VALUES ($1, $2, 'h1', '<p>old</p>')")
+            .bind(id)
+            .bind(now)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        let at = |secs| now + chrono::Duration::seconds(secs);
+        let new = storage.insert_update(id, at(1), "h2", "<p>new</p>").await.unwrap();
+        storage.insert_update(id, at(2), "h2", "<p>new</p>").await.unwrap();
+        assert_eq!(bodies().await.unwrap(), (1,));
+        assert_eq!(storage.previous_content(id, new).await.unwrap().as_deref(), Some("<p>old</p>"));
+
+        // Moving the inline row stores it once too, even when the body is already there
+        storage.insert_update(id, at(3), "h1", "<p>old</p>").await.unwrap();
+        assert_eq!(storage.move_inline_content(10).await.unwrap(), 1);
+        assert_eq!(storage.move_inline_content(10).await.unwrap(), 0);
+        assert_eq!(bodies().await.unwrap(), (2,));
+        assert_eq!(storage.content_at(id, now).await.unwrap().as_deref(), Some("<p>old</p>"));
+        assert_eq!(storage.update_content(new).await.unwrap().unwrap().content, "<p>new</p>");
+        let contents: Vec<String> = storage.snapshots(id, None).await.unwrap().into_iter().map(|s| s.content).collect();
+        assert_eq!(contents, ["<p>old</p>", "<p>new</p>", "<p>new</p>", "<p>old</p>"]);
+
+        // A body goes once the last update using it is pruned
+        assert_eq!(storage.collect_orphaned_contents().await.unwrap(), 0);
+        assert_eq!(storage.prune_updates(id, 1).await.unwrap(), 3);
+        assert_eq!(storage.collect_orphaned_contents().await.unwrap(), 1);
+        assert_eq!(bodies().await.unwrap(), (1,));
+        assert_eq!(storage.content_at(id, at(3)).await.unwrap().as_deref(), Some("<p>old</p>"));
+
+        // PDFs are kept the same way, next to the text extracted from them
+        let opinion = CheckRecord {
+            site_id: id,
+            fetched_at: at(4),
+            diff_hash: "h3",
+            content: "Opinion",
+            pdf: Some(b"%PDF-1.7 opinion"),
+            metadata: None,
+            published_at: None,
+            wire_source: None,
+            indexed_text: "Opinion",
+            probe_signature: None,
+            feed_entries: None,
+            seen_since: None,
+            items: &[],
+            change: None,
+        };
+        let first = storage.record_check(&opinion).await.unwrap();
+        storage.record_check(&CheckRecord { fetched_at: at(5), change: None, ..opinion }).await.unwrap();
+        let pdfs = || sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM pdfs").fetch_one(&storage.pool);
+        assert_eq!(pdfs().await.unwrap(), (1,));
+        assert_eq!(storage.update_pdf(first).await.unwrap().as_deref(), Some(&b"%PDF-1.7 opinion"[..]));
+        assert_eq!(storage.update_pdf(new).await.unwrap(), None);
+        let revised = CheckRecord { fetched_at: at(6), content: "Revised", pdf: Some(b"%PDF-1.7 revised"), ..opinion };
+        storage.record_check(&revised).await.unwrap();
+        assert_eq!(storage.prune_updates(id, 1).await.unwrap(), 3);
+        assert_eq!(storage.collect_orphaned_contents().await.unwrap(), 3);
+        assert_eq!(pdfs().await.unwrap(), (1,));
+
+        // Archived documents outlive the update that found them, but not their site
+        let document = NewDocument {
+            site_id: id,
+            update_id: first,
+            url: "https://a.example/23-411.pdf",
+            content_type: Some("application/pdf"),
+            data: b"%PDF-1.7 opinion",
+            fetched_at: at(4),
+        };
+        let document_id = storage.insert_document(&document).await.unwrap();
+        let (stored, data) = storage.document(document_id).await.unwrap().unwrap();
+        assert_eq!((stored.update_id, stored.size, data.as_slice()), (Some(first), 16, &b"%PDF-1.7 opinion"[..]));
+        assert_eq!(storage.list_documents(id).await.unwrap().len(), 1);
+
+        // A halt is new once, then resumed once it gets a resumption time
+        let halt = Halt { symbol: "ABCD".into(), name: None, market: None, reason_code: Some("T1".into()), halted_at: at(4), resumed_at: None };
+        assert_eq!(storage.record_halt(id, first, &halt, at(4)).await.unwrap(), HaltChange::New);
+        assert_eq!(storage.record_halt(id, new, &halt, at(5)).await.unwrap(), HaltChange::Unchanged);
+        let resumed = Halt { resumed_at: Some(at(6)), ..halt.clone() };
+        assert_eq!(storage.record_halt(id, new, &resumed, at(6)).await.unwrap(), HaltChange::Resumed);
+        assert_eq!(storage.record_halt(id, new, &resumed, at(7)).await.unwrap(), HaltChange::Unchanged);
+        let listed = storage.list_halts(&HaltQuery { symbol: Some("abcd".into()), ..HaltQuery::default() }).await.unwrap();
+        assert_eq!((listed.len(), listed[0].update_id, listed[0].halt.resumed_at), (1, Some(first), Some(at(6))));
+
+        // Federal Register documents are stored once, with their agencies and date
+        let rule = FederalDocument {
+            document_number: "2026-20555".into(),
+            title: Some("Air Plan Approval; Ohio".into()),
+            document_type: Some("Rule".into()),
+            agencies: vec!["Environmental Protection Agency".into()],
+            publication_date: chrono::NaiveDate::from_ymd_opt(2026, 10, 14),
+            html_url: None,
+            pdf_url: Some("https://www.govinfo.gov/2026-20555.pdf".into()),
+            summary: None,
+        };
+        assert!(storage.record_federal_document(id, first, &rule, at(4)).await.unwrap());
+        assert!(!storage.record_federal_document(id, new, &rule, at(5)).await.unwrap());
+        let notice = FederalDocument { document_number: "2026-20501".into(), document_type: Some("Notice".into()), ..rule.clone() };
+        assert!(storage.record_federal_document(id, new, &notice, at(5)).await.unwrap());
+        let rules = storage.list_federal_documents(&FederalDocumentQuery { document_type: Some("rule".into()), ..FederalDocumentQuery::default() }).await.unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!((rules[0].update_id, &rules[0].document), (Some(first), &rule));
+
+        assert_eq!(storage.delete_sites(&[id]).await.unwrap(), vec![id]);
+        assert!(storage.document(document_id).await.unwrap().is_none());
+        assert!(storage.list_halts(&HaltQuery::default()).await.unwrap().is_empty());
+        assert!(storage.list_federal_documents(&FederalDocumentQuery::default()).await.unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn sqlite_storage_upgrades_databases_that_predate_migrations() {
+        let path = std::env::temp_dir().join(format!("scraper-legacy-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let db_url = format!("sqlite:{}?mode=rwc", path.display());
+        let pool = Pool::<Sqlite>::connect(&db_url).await.unwrap();
+        sqlx::query(
+            "CREATE TABLE sites(
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL UNIQUE,
+                interval_secs INTEGER NOT NULL,
+                style TEXT NOT NULL,
+                last_checked TEXT,
+                last_updated TEXT,
+                status TEXT
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO sites(url, interval_secs, style) VALUES('https://old.example', 60, 'random')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let storage = SqliteStorage::connect(&db_url).await.unwrap();
+        let sites = storage.list_sites().await.unwrap();
+        assert_eq!(sites.iter().map(|s| s.url.as_str()).collect::<Vec<_>>(), vec!["https://old.example"]);
+        assert_eq!(sites[0].priority, 0);
+        storage.insert_site(&Site::new("https://new.example", 60, "random")).await.unwrap();
+        drop(storage);
+
+        // A second start finds nothing left to apply
+        let storage = SqliteStorage::connect(&db_url).await.unwrap();
+        assert_eq!(storage.list_sites().await.unwrap().len(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+}
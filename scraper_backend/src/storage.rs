@@ -0,0 +1,1872 @@
+use super::alerts::{Alert, AlertRule};
+use super::auth::{ApiKey, ApiToken};
+use super::{assertions, challenge, health};
+use super::compression::StoredContent;
+use super::documents::{Document, NewDocument};
+use super::federal_register::{FederalDocument, FederalDocumentQuery, StoredFederalDocument};
+use super::halts::{Halt, HaltChange, HaltQuery, StoredHalt};
+use super::email::{EmailRecipient, EmailTemplate};
+use super::feeds::{FeedDiff, FeedEntry};
+use super::items::{Item, ItemQuery};
+use super::structured::Metadata;
+use super::tags::{AffectedSite, Tag, TagSettings};
+use super::telegram::TelegramChat;
+use super::webhooks::Webhook;
+use super::{Assertions, Site, SiteUpdate};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use std::sync::Arc;
+#[cfg(test)]
+use super::{feeds, scraper, search, tags::{self, SiteSettings}};
+#[cfg(test)]
+use std::{collections::{BTreeMap, HashMap, HashSet}, sync::Mutex};
+
+pub type SharedStorage = Arc<dyn Storage>;
+
+// Backend-neutral error so handlers don't depend on sqlx
+#[derive(Debug)]
+pub struct StorageError(String);
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl StorageError {
+    pub fn new(message: impl Into<String>) -> Self {
+        StorageError(message.into())
+    }
+}
+
+impl From<sqlx::Error> for StorageError {
+    fn from(e: sqlx::Error) -> Self {
+        StorageError(e.to_string())
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for StorageError {
+    fn from(e: sqlx::migrate::MigrateError) -> Self {
+        StorageError(format!("migration failed: {}", e))
+    }
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+// Site counts for status reports
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct SiteStats {
+    pub total: i64,
+    pub ok: i64,
+    pub errors: i64,
+    pub pending: i64,
+    pub snoozed: i64,
+    pub last_change: Option<DateTime<Utc>>,
+}
+
+impl SiteStats {
+    fn from_sites(sites: &[Site], now: DateTime<Utc>) -> Self {
+        let count = |f: &dyn Fn(&Site) -> bool| sites.iter().filter(|s| f(s)).count() as i64;
+        SiteStats {
+            total: sites.len() as i64,
+            ok: count(&|s| s.status.as_deref() == Some("OK")),
+            errors: count(&|s| matches!(s.status.as_deref(), Some("ERROR" | assertions::EXTRACTION_FAILED | challenge::CHALLENGE))),
+            pending: count(&|s| s.status.is_none()),
+            snoozed: count(&|s| s.snoozed_until.is_some_and(|t| t > now)),
+            last_change: sites.iter().filter_map(|s| s.last_updated).max(),
+        }
+    }
+}
+
+// One fetch attempt, kept in a rolling per-site log
+#[derive(Serialize, FromRow, Clone, Debug, PartialEq)]
+pub struct FetchLogEntry {
+    pub site_id: i64,
+    pub fetched_at: DateTime<Utc>,
+    pub status_code: Option<i64>,
+    pub duration_ms: i64,
+    pub error: Option<String>,
+    // Proxy that served the fetch, password masked; None when it went out directly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    // Where redirects ended up, and the Content-Type and Content-Length the server sent
+    pub final_url: Option<String>,
+    pub content_type: Option<String>,
+    pub content_length: Option<i64>,
+    // Whether the fetch was stored as a change, and the update it was stored as
+    pub changed: bool,
+    pub update_id: Option<i64>,
+}
+
+// A stored fetch whose hash differs from the previous fetch of the same site
+#[derive(FromRow, Clone, Debug)]
+pub struct ChangeEntry {
+    pub id: i64,
+    pub site_id: i64,
+    pub url: String,
+    pub timestamp: DateTime<Utc>,
+    pub diff_hash: String,
+    #[sqlx(try_from = "StoredContent")]
+    pub content: String,
+    pub selector: Option<String>,
+    // Structured data the page declared
+    #[sqlx(json)]
+    pub metadata: Option<Metadata>,
+    // When the content was published, by its own account
+    pub published_at: Option<DateTime<Utc>>,
+    // Press-release wire the content came from
+    pub wire_source: Option<String>,
+}
+
+// A stored update matching a search; the snippet marks matches with search::MATCH_START
+// and MATCH_END until the handler escapes it
+#[derive(Serialize, FromRow, Clone, Debug)]
+pub struct SearchHit {
+    pub update_id: i64,
+    pub site_id: i64,
+    pub url: String,
+    pub timestamp: DateTime<Utc>,
+    pub snippet: String,
+}
+
+// A site held as a whole
+#[derive(Serialize, FromRow, Clone, Debug)]
+pub struct HeldSite {
+    pub site_id: i64,
+    pub url: String,
+}
+
+// A single update held on its own
+#[derive(Serialize, FromRow, Clone, Debug)]
+pub struct HeldUpdate {
+    pub update_id: i64,
+    pub site_id: i64,
+    pub url: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+// Everything under legal hold. Held data survives retention and blocks site deletion
+// and database resets.
+#[derive(Serialize, Default, Debug)]
+pub struct LegalHolds {
+    pub sites: Vec<HeldSite>,
+    pub updates: Vec<HeldUpdate>,
+}
+
+impl LegalHolds {
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty() && self.updates.is_empty()
+    }
+
+    pub fn covers_site(&self, site_id: i64) -> bool {
+        self.sites.iter().any(|s| s.site_id == site_id) || self.updates.iter().any(|u| u.site_id == site_id)
+    }
+
+    pub fn summary(&self) -> String {
+        format!("{} site(s) and {} update(s) are under legal hold", self.sites.len(), self.updates.len())
+    }
+}
+
+// A stored fetch with the site settings needed to re-hash it
+#[derive(FromRow)]
+pub struct UpdateContent {
+    pub site_id: i64,
+    pub timestamp: DateTime<Utc>,
+    #[sqlx(try_from = "StoredContent")]
+    pub content: String,
+    pub selector: Option<String>,
+    #[sqlx(json)]
+    pub ignore_patterns: Vec<String>,
+    // Stored when the fetch was a change and had a predecessor
+    pub diff: Option<String>,
+}
+
+// A stored fetch as input to reprocessing
+#[derive(FromRow, Clone, Debug)]
+pub struct Snapshot {
+    pub id: i64,
+    pub diff_hash: String,
+    #[sqlx(try_from = "StoredContent")]
+    pub content: String,
+    pub pipeline_version: Option<i64>,
+    // Whether content is the text of a fetched PDF
+    pub pdf: bool,
+}
+
+// Time from a source's published timestamp to the fetch that detected it
+#[derive(Clone, Debug)]
+pub struct LatencySample {
+    pub site_id: i64,
+    pub update_id: i64,
+    pub published: DateTime<Utc>,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl LatencySample {
+    pub fn latency_secs(&self) -> i64 {
+        (self.detected_at - self.published).num_seconds().max(0)
+    }
+}
+
+// Per-site summary of detection latency, slowest sources first
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SiteLatency {
+    pub site_id: i64,
+    pub url: String,
+    pub samples: i64,
+    pub avg_secs: i64,
+    pub median_secs: i64,
+    pub max_secs: i64,
+    pub last_secs: i64,
+}
+
+// Fetches and stored changes across all sites since some time. Fetches are read from the
+// fetch log, which keeps fetch_log_size entries per site.
+#[derive(Serialize, FromRow, Debug, Default, PartialEq)]
+pub struct Activity {
+    pub fetches: i64,
+    pub failed_fetches: i64,
+    pub changes: i64,
+}
+
+// A site by the changes it stored since some time
+#[derive(Serialize, FromRow, Debug, PartialEq)]
+pub struct ChangeCount {
+    pub site_id: i64,
+    pub url: String,
+    pub changes: i64,
+}
+
+// Detection latency samples kept per site
+pub const LATENCY_SAMPLES_PER_SITE: i64 = 200;
+
+// `latencies` oldest first
+pub fn summarize_latency(site_id: i64, url: String, latencies: &[i64]) -> Option<SiteLatency> {
+    let last_secs = *latencies.last()?;
+    let mut sorted = latencies.to_vec();
+    sorted.sort_unstable();
+    Some(SiteLatency {
+        site_id,
+        url,
+        samples: sorted.len() as i64,
+        avg_secs: sorted.iter().sum::<i64>() / sorted.len() as i64,
+        median_secs: sorted[sorted.len() / 2],
+        max_secs: *sorted.last()?,
+        last_secs,
+    })
+}
+
+pub fn sort_by_median(mut stats: Vec<SiteLatency>) -> Vec<SiteLatency> {
+    stats.sort_by(|a, b| b.median_secs.cmp(&a.median_secs).then(a.site_id.cmp(&b.site_id)));
+    stats
+}
+
+// Everything a successful fetch stores, written by record_check as one unit
+pub struct CheckRecord<'a> {
+    pub site_id: i64,
+    pub fetched_at: DateTime<Utc>,
+    pub diff_hash: &'a str,
+    pub content: &'a str,
+    // The PDF `content` was extracted from, kept as fetched
+    pub pdf: Option<&'a [u8]>,
+    // Structured data the page declared
+    pub metadata: Option<&'a Metadata>,
+    // Publication date of the content, from its structured data or feed entries
+    pub published_at: Option<DateTime<Utc>>,
+    // Press-release wire the content came from, such as "businesswire"
+    pub wire_source: Option<&'a str>,
+    // Visible text for the search index
+    pub indexed_text: &'a str,
+    pub probe_signature: Option<&'a str>,
+    pub feed_entries: Option<&'a [FeedEntry]>,
+    // Seen feed items not seen again since then are forgotten; None keeps them
+    pub seen_since: Option<DateTime<Utc>>,
+    // News items found by the fetch; those the site already has are left alone
+    pub items: &'a [FeedEntry],
+    // Set when the fetch changed the site
+    pub change: Option<RecordedChange>,
+}
+
+pub struct RecordedChange {
+    // Against the previous stored fetch, if there is one
+    pub diff: Option<String>,
+    // A first fetch is no change in the site's own history
+    pub in_history: bool,
+    // Change history entries kept per site
+    pub history_size: i64,
+}
+
+// Everything the app persists. SqliteStorage and PostgresStorage (sql_storage.rs) run
+// the same SQL; the backend is picked from the database URL at startup.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    // Sites, with their tags
+    async fn list_sites(&self) -> StorageResult<Vec<Site>>;
+    async fn get_site(&self, id: i64) -> StorageResult<Option<Site>>;
+    // `id` and check state are ignored; returns the new site's id
+    async fn insert_site(&self, site: &Site) -> StorageResult<i64>;
+    // PATCH semantics of SiteUpdate; false if the site doesn't exist
+    async fn update_site(&self, id: i64, update: &SiteUpdate, selector: Option<String>, probe_mode: Option<String>, asserts: Assertions) -> StorageResult<bool>;
+    // Removes the site and everything stored for it; false if it didn't exist
+    async fn delete_site(&self, id: i64) -> StorageResult<bool>;
+    // Like delete_site for each id, in one transaction; returns the ids that existed
+    async fn delete_sites(&self, ids: &[i64]) -> StorageResult<Vec<i64>>;
+    async fn site_ids_with_tag(&self, tag: &str) -> StorageResult<Vec<i64>>;
+    // Applies the settings to every site carrying `tag` at once (nothing is written on a dry run)
+    async fn retune_tag(&self, tag: &str, settings: &TagSettings) -> StorageResult<Vec<AffectedSite>>;
+    // Tag definitions, including every tag in use; `site_count` is ignored on insert and update
+    async fn list_tags(&self) -> StorageResult<Vec<Tag>>;
+    // False if the tag already exists
+    async fn insert_tag(&self, tag: &Tag) -> StorageResult<bool>;
+    async fn update_tag(&self, tag: &Tag) -> StorageResult<bool>;
+    // Also takes the tag off every site carrying it
+    async fn delete_tag(&self, name: &str) -> StorageResult<bool>;
+
+    // Schedules
+    async fn mark_checked(&self, site_id: i64, checked_at: DateTime<Utc>, success: bool) -> StorageResult<()>;
+    // The page was fetched but failed the site's assertions; keeps OVERDUE like a failed fetch
+    async fn mark_extraction_failed(&self, site_id: i64, checked_at: DateTime<Utc>) -> StorageResult<()>;
+    // The fetch got an anti-bot challenge instead of the page; keeps OVERDUE the same way
+    async fn mark_challenged(&self, site_id: i64, checked_at: DateTime<Utc>) -> StorageResult<()>;
+    async fn mark_overdue(&self, site_id: i64) -> StorageResult<()>;
+    // Only a site whose fetches are OK goes STALE; false if it wasn't. Unchanged fetches keep
+    // the flag and the next change clears it.
+    async fn mark_stale(&self, site_id: i64) -> StorageResult<bool>;
+    async fn set_snoozed_until(&self, site_id: i64, until: Option<DateTime<Utc>>) -> StorageResult<()>;
+    // None for both ends the burst
+    async fn set_burst(&self, site_id: i64, interval_secs: Option<i64>, until: Option<DateTime<Utc>>) -> StorageResult<()>;
+    // None goes back to the built-in extraction
+    async fn set_extraction_script(&self, site_id: i64, script: Option<&str>) -> StorageResult<()>;
+    async fn probe_signature(&self, site_id: i64) -> StorageResult<Option<String>>;
+    // A backoff_count of 0 clears the backoff
+    async fn set_backoff(&self, site_id: i64, backoff_count: i64, until: Option<DateTime<Utc>>, reason: Option<&str>) -> StorageResult<()>;
+    // Ends or extends the site's failure streak; Some when that changes its health state
+    async fn record_health(&self, site_id: i64, success: bool, thresholds: &health::Thresholds) -> StorageResult<Option<health::Transition>>;
+
+    // Stores a fetch with everything derived from it (status, feed entries, search text,
+    // diff, change history) all at once or not at all; returns the new update's id
+    async fn record_check(&self, record: &CheckRecord<'_>) -> StorageResult<i64>;
+
+    // Updates
+    async fn latest_hash(&self, site_id: i64) -> StorageResult<Option<String>>;
+    // Seeds stored fetches in tests; checks store theirs through record_check
+    #[cfg(test)]
+    async fn insert_update(&self, site_id: i64, timestamp: DateTime<Utc>, diff_hash: &str, content: &str) -> StorageResult<i64>;
+    // Content of the newest update stored before `before_id`
+    async fn previous_content(&self, site_id: i64, before_id: i64) -> StorageResult<Option<String>>;
+    async fn update_content(&self, update_id: i64) -> StorageResult<Option<UpdateContent>>;
+    async fn content_at(&self, site_id: i64, timestamp: DateTime<Utc>) -> StorageResult<Option<String>>;
+    // The PDF an update's content was extracted from, if it was fetched as one
+    async fn update_pdf(&self, update_id: i64) -> StorageResult<Option<Vec<u8>>>;
+    // PNG of the page captured in browser mode when the update was a change; it goes
+    // with its update
+    async fn insert_screenshot(&self, update_id: i64, captured_at: DateTime<Utc>, png: &[u8]) -> StorageResult<()>;
+    async fn update_screenshot(&self, update_id: i64) -> StorageResult<Option<Vec<u8>>>;
+    // Stored fetches of a site, oldest first, optionally only those from `since` on
+    async fn snapshots(&self, site_id: i64, since: Option<DateTime<Utc>>) -> StorageResult<Vec<Snapshot>>;
+    async fn set_snapshot_hash(&self, update_id: i64, diff_hash: &str, pipeline_version: i64) -> StorageResult<()>;
+    // Full-text index over the visible text of stored updates. Index rows go away with
+    // their update, so pruning needs no extra step.
+    async fn index_update_text(&self, update_id: i64, text: &str) -> StorageResult<()>;
+    // (id, content) of stored updates missing from the index, oldest first
+    async fn unindexed_updates(&self, limit: i64) -> StorageResult<Vec<(i64, String)>>;
+    // Best matches first; words must all appear and "quoted phrases" appear as written
+    async fn search_updates(&self, query: &str, site_id: Option<i64>, limit: i64) -> StorageResult<Vec<SearchHit>>;
+    // Moves up to `limit` updates stored inline, from before the contents table, to a
+    // content-addressed body; returns how many
+    async fn move_inline_content(&self, limit: i64) -> StorageResult<u64>;
+    // Deletes stored bodies and PDFs no update references any more; returns how many
+    async fn collect_orphaned_contents(&self) -> StorageResult<u64>;
+    // Legal holds on single updates; whole sites are held through their legal_hold setting
+    async fn set_update_hold(&self, update_id: i64, held: bool) -> StorageResult<bool>;
+    async fn legal_holds(&self) -> StorageResult<LegalHolds>;
+    // Keep only the newest `keep` updates of a site. Pruning never deletes held updates
+    // or updates of held sites.
+    async fn prune_updates(&self, site_id: i64, keep: i64) -> StorageResult<u64>;
+    // Same, for every site at once
+    async fn prune_all_updates(&self, keep: i64) -> StorageResult<u64>;
+    // Drop a site's updates stored before `cutoff`, except its latest
+    async fn prune_updates_before(&self, site_id: i64, cutoff: DateTime<Utc>) -> StorageResult<u64>;
+    // Newest first, optionally only those whose URL or content contains `term`
+    async fn recent_changes(&self, term: Option<&str>, limit: i64) -> StorageResult<Vec<ChangeEntry>>;
+    // Changes stored after update `after_id`, oldest first (for stream replay)
+    async fn changes_since(&self, after_id: i64, limit: i64) -> StorageResult<Vec<ChangeEntry>>;
+    // Changes detected in [from, until), oldest first (for digests)
+    async fn changes_between(&self, from: DateTime<Utc>, until: DateTime<Utc>, limit: i64) -> StorageResult<Vec<ChangeEntry>>;
+
+    // The entries none of whose keys were seen for this site since seen_since (ever if
+    // None); record_check stores their keys
+    async fn feed_diff(&self, site_id: i64, entries: &[FeedEntry], seen_since: Option<DateTime<Utc>>) -> StorageResult<FeedDiff>;
+    // Stored news items matching the query, newest first
+    async fn list_items(&self, query: &ItemQuery) -> StorageResult<Vec<Item>>;
+
+    // Archived copies of newly linked documents; returns the new document's id
+    async fn insert_document(&self, document: &NewDocument<'_>) -> StorageResult<i64>;
+    // The document with its stored body
+    async fn document(&self, id: i64) -> StorageResult<Option<(Document, Vec<u8>)>>;
+    // Newest first
+    async fn list_documents(&self, site_id: i64) -> StorageResult<Vec<Document>>;
+
+    // Stores a halt a check listed: New the first time, Resumed when one stored without a
+    // resumption time now has one
+    async fn record_halt(&self, site_id: i64, update_id: i64, halt: &Halt, seen_at: DateTime<Utc>) -> StorageResult<HaltChange>;
+    // Latest halt time first
+    async fn list_halts(&self, query: &HaltQuery) -> StorageResult<Vec<StoredHalt>>;
+
+    // Stores a Federal Register document a check listed; false if the site had it already
+    async fn record_federal_document(&self, site_id: i64, update_id: i64, document: &FederalDocument, seen_at: DateTime<Utc>) -> StorageResult<bool>;
+    // Latest publication first
+    async fn list_federal_documents(&self, query: &FederalDocumentQuery) -> StorageResult<Vec<StoredFederalDocument>>;
+
+    // Alert rules; site_alert_rules includes the global ones
+    async fn list_alert_rules(&self) -> StorageResult<Vec<AlertRule>>;
+    async fn site_alert_rules(&self, site_id: i64) -> StorageResult<Vec<AlertRule>>;
+    // `id` is ignored; returns the new rule's id
+    async fn insert_alert_rule(&self, rule: &AlertRule) -> StorageResult<i64>;
+    async fn delete_alert_rule(&self, id: i64) -> StorageResult<bool>;
+
+    // Fired alerts, newest first; `id` and acknowledgement are ignored on insert
+    async fn insert_alert(&self, alert: &Alert) -> StorageResult<i64>;
+    async fn list_alerts(&self, unacknowledged_only: bool, limit: i64) -> StorageResult<Vec<Alert>>;
+    // False if there is no such alert; an acknowledged alert keeps its first acknowledgement
+    async fn acknowledge_alert(&self, id: i64, at: DateTime<Utc>, by: Option<&str>) -> StorageResult<bool>;
+
+    // Webhooks; site_webhooks includes the global ones
+    async fn list_webhooks(&self) -> StorageResult<Vec<Webhook>>;
+    async fn site_webhooks(&self, site_id: i64) -> StorageResult<Vec<Webhook>>;
+    async fn get_webhook(&self, id: i64) -> StorageResult<Option<Webhook>>;
+    // `id` and canary state are ignored; returns the new webhook's id
+    async fn insert_webhook(&self, hook: &Webhook) -> StorageResult<i64>;
+    async fn delete_webhook(&self, id: i64) -> StorageResult<bool>;
+    async fn record_canary(&self, id: i64, checked_at: DateTime<Utc>, error: Option<&str>) -> StorageResult<()>;
+
+    // Telegram chats with the sites opted in to each; site_telegram_chats only those a site is in
+    async fn list_telegram_chats(&self) -> StorageResult<Vec<TelegramChat>>;
+    async fn site_telegram_chats(&self, site_id: i64) -> StorageResult<Vec<TelegramChat>>;
+    // `id` is ignored; returns the new chat's id
+    async fn insert_telegram_chat(&self, chat: &TelegramChat) -> StorageResult<i64>;
+    // Replaces the chat's sites; false if there is no such chat
+    async fn set_telegram_sites(&self, id: i64, site_ids: &[i64]) -> StorageResult<bool>;
+    async fn delete_telegram_chat(&self, id: i64) -> StorageResult<bool>;
+
+    // Email recipients; `id` and last_digest_at are ignored on insert and update
+    async fn list_email_recipients(&self) -> StorageResult<Vec<EmailRecipient>>;
+    async fn insert_email_recipient(&self, recipient: &EmailRecipient) -> StorageResult<i64>;
+    // False if there is no such recipient
+    async fn update_email_recipient(&self, recipient: &EmailRecipient) -> StorageResult<bool>;
+    async fn delete_email_recipient(&self, id: i64) -> StorageResult<bool>;
+    async fn mark_digest_sent(&self, id: i64, at: DateTime<Utc>) -> StorageResult<()>;
+    // Stored template overrides; kinds without one use the built-in template
+    async fn list_email_templates(&self) -> StorageResult<Vec<EmailTemplate>>;
+    async fn set_email_template(&self, template: &EmailTemplate) -> StorageResult<()>;
+    async fn delete_email_template(&self, kind: &str) -> StorageResult<bool>;
+
+    // Records that a site was notified about content `diff_hash`; false if it already was
+    async fn claim_notification(&self, site_id: i64, diff_hash: &str, at: DateTime<Utc>) -> StorageResult<bool>;
+
+    // API keys created at runtime; keys from config aren't stored
+    async fn list_api_keys(&self) -> StorageResult<Vec<ApiKey>>;
+    // `id` is ignored; returns the new key's id
+    async fn insert_api_key(&self, key: &ApiKey) -> StorageResult<i64>;
+    async fn delete_api_key(&self, id: i64) -> StorageResult<bool>;
+    // Tokens minted at runtime, expired ones included until they are revoked
+    async fn list_api_tokens(&self) -> StorageResult<Vec<ApiToken>>;
+    // `id` is ignored; returns the new token's id
+    async fn insert_api_token(&self, token: &ApiToken) -> StorageResult<i64>;
+    async fn delete_api_token(&self, id: i64) -> StorageResult<bool>;
+
+    // Fetch log; record_fetch keeps the newest `keep` entries of the site
+    async fn record_fetch(&self, entry: &FetchLogEntry, keep: i64) -> StorageResult<()>;
+    // Newest first
+    async fn fetch_log(&self, site_id: i64, limit: i64) -> StorageResult<Vec<FetchLogEntry>>;
+
+    // Change history for interval recommendations, oldest first; record_check keeps the
+    // newest RecordedChange::history_size entries of the site
+    async fn change_times(&self, site_id: i64) -> StorageResult<Vec<DateTime<Utc>>>;
+    async fn set_interval(&self, site_id: i64, interval_secs: i64) -> StorageResult<()>;
+    async fn set_adaptive_interval(&self, site_id: i64, interval_secs: i64) -> StorageResult<()>;
+
+    // Stats
+    async fn site_stats(&self, now: DateTime<Utc>) -> StorageResult<SiteStats> {
+        Ok(SiteStats::from_sites(&self.list_sites().await?, now))
+    }
+    async fn record_latency(&self, sample: &LatencySample) -> StorageResult<()>;
+    async fn latency_stats(&self) -> StorageResult<Vec<SiteLatency>>;
+    async fn activity(&self, since: DateTime<Utc>) -> StorageResult<Activity>;
+    // Most changes first
+    async fn top_changing_sites(&self, since: DateTime<Utc>, limit: i64) -> StorageResult<Vec<ChangeCount>>;
+    // Bytes on disk; None where the backend can't tell
+    async fn database_size(&self) -> StorageResult<Option<i64>>;
+
+    // Maintenance: a read that touches no rows (readiness probes), a write/read that is
+    // rolled back (boot self-test), and dropping every site with its data. Global webhooks
+    // and alert rules survive a reset.
+    async fn ping(&self) -> StorageResult<()>;
+    async fn round_trip(&self) -> StorageResult<()>;
+    async fn reset(&self) -> StorageResult<()>;
+}
+
+#[cfg(test)]
+struct StoredUpdate {
+    id: i64,
+    site_id: i64,
+    timestamp: DateTime<Utc>,
+    diff_hash: String,
+    content: String,
+    pipeline_version: Option<i64>,
+    diff: Option<String>,
+    pdf: Option<Vec<u8>>,
+    screenshot: Option<Vec<u8>>,
+    metadata: Option<Metadata>,
+    published_at: Option<DateTime<Utc>>,
+    wire_source: Option<String>,
+    // Search index entry
+    text: Option<String>,
+    legal_hold: bool,
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct MemoryData {
+    sites: BTreeMap<i64, Site>,
+    // Ascending by id
+    updates: Vec<StoredUpdate>,
+    // When each feed item key was last seen, per site
+    seen_items: HashMap<i64, HashMap<String, DateTime<Utc>>>,
+    // Ascending by id
+    items: Vec<Item>,
+    alert_rules: Vec<AlertRule>,
+    // Oldest first
+    alerts: Vec<Alert>,
+    webhooks: Vec<Webhook>,
+    telegram_chats: Vec<TelegramChat>,
+    email_recipients: Vec<EmailRecipient>,
+    email_templates: Vec<EmailTemplate>,
+    notified_changes: HashSet<(i64, String)>,
+    api_keys: Vec<ApiKey>,
+    api_tokens: Vec<ApiToken>,
+    // Oldest first
+    fetch_log: Vec<FetchLogEntry>,
+    // (site_id, changed_at), oldest first
+    site_changes: Vec<(i64, DateTime<Utc>)>,
+    latencies: BTreeMap<i64, Vec<i64>>,
+    // By name; site_count is worked out on listing
+    tags: BTreeMap<String, Tag>,
+    // With their bodies, ascending by id
+    documents: Vec<(Document, Vec<u8>)>,
+    halts: Vec<StoredHalt>,
+    federal_documents: Vec<StoredFederalDocument>,
+    next_id: i64,
+}
+
+#[cfg(test)]
+impl MemoryData {
+    // One sequence for every table is enough for tests
+    fn next_id(&mut self) -> i64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn remove_site(&mut self, id: i64) -> bool {
+        self.updates.retain(|u| u.site_id != id);
+        self.seen_items.remove(&id);
+        self.items.retain(|i| i.site_id != id);
+        self.alert_rules.retain(|r| r.site_id != Some(id));
+        self.alerts.retain(|a| a.site_id != id);
+        self.webhooks.retain(|h| h.site_id != Some(id));
+        for chat in &mut self.telegram_chats {
+            chat.site_ids.retain(|&site_id| site_id != id);
+        }
+        self.fetch_log.retain(|e| e.site_id != id);
+        self.site_changes.retain(|(site_id, _)| *site_id != id);
+        self.notified_changes.retain(|(site_id, _)| *site_id != id);
+        self.latencies.remove(&id);
+        self.documents.retain(|(d, _)| d.site_id != id);
+        self.halts.retain(|h| h.site_id != id);
+        self.federal_documents.retain(|d| d.site_id != id);
+        self.sites.remove(&id).is_some()
+    }
+
+    fn add_tags(&mut self, names: &[String]) {
+        for name in tags::normalize_tags(names) {
+            self.tags.entry(name.clone()).or_insert_with(|| Tag { name, ..Tag::default() });
+        }
+    }
+
+    // Mirrors the CHANGE_ROWS query in sql_storage.rs, oldest first
+    fn changes(&self) -> Vec<ChangeEntry> {
+        let mut previous: HashMap<i64, &str> = HashMap::new();
+        let mut changes = Vec::new();
+        for update in &self.updates {
+            let prev_hash = previous.insert(update.site_id, &update.diff_hash);
+            let Some(site) = self.sites.get(&update.site_id) else {
+                continue;
+            };
+            let changed = match prev_hash {
+                None => site.last_updated == Some(update.timestamp),
+                Some(prev) => prev != update.diff_hash,
+            };
+            if changed {
+                changes.push(ChangeEntry {
+                    id: update.id,
+                    site_id: site.id,
+                    url: site.url.clone(),
+                    timestamp: update.timestamp,
+                    diff_hash: update.diff_hash.clone(),
+                    content: update.content.clone(),
+                    selector: site.selector.clone(),
+                    metadata: update.metadata.clone(),
+                    published_at: update.published_at,
+                    wire_source: update.wire_source.clone(),
+                });
+            }
+        }
+        changes
+    }
+}
+
+// Process-local backend so tests don't need a database file
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStorage {
+    data: Mutex<MemoryData>,
+}
+
+#[cfg(test)]
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_site<T>(&self, site_id: i64, f: impl FnOnce(&mut Site) -> T) -> Option<T> {
+        self.data.lock().unwrap().sites.get_mut(&site_id).map(f)
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn list_sites(&self) -> StorageResult<Vec<Site>> {
+        Ok(self.data.lock().unwrap().sites.values().cloned().collect())
+    }
+
+    async fn get_site(&self, id: i64) -> StorageResult<Option<Site>> {
+        Ok(self.data.lock().unwrap().sites.get(&id).cloned())
+    }
+
+    async fn insert_site(&self, site: &Site) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        if data.sites.values().any(|s| s.url == site.url) {
+            return Err(StorageError(format!("UNIQUE constraint failed: sites.url ({})", site.url)));
+        }
+        let id = data.next_id();
+        data.add_tags(&site.tags);
+        data.sites.insert(id, Site {
+            id,
+            last_checked: None,
+            last_updated: None,
+            status: None,
+            snoozed_until: None,
+            last_success: None,
+            probe_signature: None,
+            tags: tags::normalize_tags(&site.tags),
+            ..site.clone()
+        });
+        Ok(id)
+    }
+
+    async fn update_site(&self, id: i64, update: &SiteUpdate, selector: Option<String>, probe_mode: Option<String>, asserts: Assertions) -> StorageResult<bool> {
+        let updated = self.with_site(id, |site| {
+            if let Some(interval_secs) = update.interval_secs {
+                site.interval_secs = interval_secs;
+                site.adaptive_interval_secs = None;
+            }
+            site.style = update.style.clone().unwrap_or_else(|| site.style.clone());
+            site.priority = update.priority.unwrap_or(site.priority);
+            if update.selector.is_some() {
+                site.selector = selector;
+            }
+            if let Some(count) = update.retention_count {
+                site.retention_count = Some(count).filter(|n| *n > 0);
+            }
+            if let Some(days) = update.retention_days {
+                site.retention_days = Some(days).filter(|n| *n > 0);
+            }
+            if let Some(limit) = update.notify_burst_limit {
+                site.notify_burst_limit = Some(limit).filter(|n| *n > 0);
+            }
+            if let Some(secs) = update.notify_window_secs {
+                site.notify_window_secs = Some(secs).filter(|n| *n > 0);
+            }
+            if update.probe_mode.is_some() {
+                site.probe_mode = probe_mode;
+            }
+            if let Some(kb) = update.probe_tail_kb {
+                site.probe_tail_kb = Some(kb).filter(|kb| *kb > 0);
+            }
+            if update.probe_mode.is_some() || update.probe_tail_kb.is_some() {
+                site.probe_signature = None;
+            }
+            if update.assert_selector.is_some() {
+                site.assert_selector = asserts.selector;
+            }
+            if update.assert_pattern.is_some() {
+                site.assert_pattern = asserts.pattern;
+            }
+            if let Some(min_length) = update.assert_min_length {
+                site.assert_min_length = Some(min_length).filter(|n| *n > 0);
+            }
+            site.legal_hold = update.legal_hold.unwrap_or(site.legal_hold);
+            if let Some(patterns) = &update.ignore_patterns {
+                site.ignore_patterns = patterns.clone();
+            }
+            if let Some(window) = &update.active_window {
+                site.active_window = window.clone();
+            }
+            if let Some(secs) = update.timeout_secs {
+                site.timeout_secs = Some(secs).filter(|n| *n > 0);
+            }
+            if let Some(n) = update.max_redirects {
+                site.max_redirects = Some(n).filter(|n| *n > 0);
+            }
+            if let Some(bytes) = update.max_body_bytes {
+                site.max_body_bytes = Some(bytes).filter(|n| *n > 0);
+            }
+            if let Some(mode) = &update.extraction_mode {
+                site.extraction_mode = Some(mode.clone()).filter(|mode| !mode.is_empty());
+            }
+            if let Some(length) = update.preview_length {
+                site.preview_length = Some(length).filter(|n| *n > 0);
+            }
+            if let Some(name) = &update.plugin {
+                site.plugin = Some(name.clone()).filter(|name| !name.is_empty());
+            }
+            if let Some(pattern) = &update.link_pattern {
+                site.link_pattern = Some(pattern.clone()).filter(|pattern| !pattern.is_empty());
+            }
+            site.archive_links = update.archive_links.unwrap_or(site.archive_links);
+            if let Some(hours) = update.max_content_age_hours {
+                site.max_content_age_hours = Some(hours).filter(|n| *n > 0);
+            }
+            if let Some(pattern) = &update.crawl_pattern {
+                site.crawl_pattern = Some(pattern.clone()).filter(|pattern| !pattern.is_empty());
+            }
+            if let Some(depth) = update.crawl_depth {
+                site.crawl_depth = Some(depth).filter(|n| *n > 0);
+            }
+            if let Some(pages) = update.crawl_max_pages {
+                site.crawl_max_pages = Some(pages).filter(|n| *n > 0);
+            }
+            if let Some(hours) = update.max_quiet_hours {
+                site.max_quiet_hours = Some(hours).filter(|n| *n > 0);
+            }
+            if let Some(list) = &update.proxies {
+                site.proxies = list.clone();
+            }
+            if let Some(new_tags) = &update.tags {
+                site.tags = tags::normalize_tags(new_tags);
+            }
+        }).is_some();
+        if let Some(new_tags) = update.tags.as_ref().filter(|_| updated) {
+            self.data.lock().unwrap().add_tags(new_tags);
+        }
+        Ok(updated)
+    }
+
+    async fn delete_site(&self, id: i64) -> StorageResult<bool> {
+        Ok(self.data.lock().unwrap().remove_site(id))
+    }
+
+    async fn delete_sites(&self, ids: &[i64]) -> StorageResult<Vec<i64>> {
+        let mut data = self.data.lock().unwrap();
+        Ok(ids.iter().copied().filter(|&id| data.remove_site(id)).collect())
+    }
+
+    async fn site_ids_with_tag(&self, tag: &str) -> StorageResult<Vec<i64>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.sites.values().filter(|s| s.tags.iter().any(|t| t == tag)).map(|s| s.id).collect())
+    }
+
+    async fn retune_tag(&self, tag: &str, settings: &TagSettings) -> StorageResult<Vec<AffectedSite>> {
+        let mut data = self.data.lock().unwrap();
+        let mut affected = Vec::new();
+        for site in data.sites.values_mut().filter(|s| s.tags.iter().any(|t| t == tag)) {
+            let before = SiteSettings {
+                interval_secs: site.interval_secs,
+                style: site.style.clone(),
+                priority: site.priority,
+                retention_count: site.retention_count,
+            };
+            let after = settings.apply(&before);
+            if !settings.dry_run {
+                site.interval_secs = after.interval_secs;
+                site.style = after.style.clone();
+                site.priority = after.priority;
+                site.retention_count = after.retention_count;
+            }
+            affected.push(AffectedSite { id: site.id, url: site.url.clone(), before, after });
+        }
+        Ok(affected)
+    }
+
+    async fn list_tags(&self) -> StorageResult<Vec<Tag>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.tags.values()
+            .map(|tag| Tag {
+                site_count: data.sites.values().filter(|s| s.tags.contains(&tag.name)).count() as i64,
+                ..tag.clone()
+            })
+            .collect())
+    }
+
+    async fn insert_tag(&self, tag: &Tag) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        if data.tags.contains_key(&tag.name) {
+            return Ok(false);
+        }
+        data.tags.insert(tag.name.clone(), Tag { site_count: 0, ..tag.clone() });
+        Ok(true)
+    }
+
+    async fn update_tag(&self, tag: &Tag) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let Some(stored) = data.tags.get_mut(&tag.name) else {
+            return Ok(false);
+        };
+        *stored = Tag { site_count: 0, ..tag.clone() };
+        Ok(true)
+    }
+
+    async fn delete_tag(&self, name: &str) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        for site in data.sites.values_mut() {
+            site.tags.retain(|t| t != name);
+        }
+        Ok(data.tags.remove(name).is_some())
+    }
+
+    async fn mark_checked(&self, site_id: i64, checked_at: DateTime<Utc>, success: bool) -> StorageResult<()> {
+        self.with_site(site_id, |site| {
+            site.last_checked = Some(checked_at);
+            if success {
+                site.last_success = Some(checked_at);
+                if site.status.as_deref() != Some("STALE") {
+                    site.status = Some("OK".into());
+                }
+            } else if site.status.as_deref() != Some("OVERDUE") {
+                site.status = Some("ERROR".into());
+            }
+        });
+        Ok(())
+    }
+
+    async fn mark_extraction_failed(&self, site_id: i64, checked_at: DateTime<Utc>) -> StorageResult<()> {
+        self.with_site(site_id, |site| {
+            site.last_checked = Some(checked_at);
+            if site.status.as_deref() != Some("OVERDUE") {
+                site.status = Some(assertions::EXTRACTION_FAILED.into());
+            }
+        });
+        Ok(())
+    }
+
+    async fn mark_challenged(&self, site_id: i64, checked_at: DateTime<Utc>) -> StorageResult<()> {
+        self.with_site(site_id, |site| {
+            site.last_checked = Some(checked_at);
+            if site.status.as_deref() != Some("OVERDUE") {
+                site.status = Some(challenge::CHALLENGE.into());
+            }
+        });
+        Ok(())
+    }
+
+    async fn mark_overdue(&self, site_id: i64) -> StorageResult<()> {
+        self.with_site(site_id, |site| site.status = Some("OVERDUE".into()));
+        Ok(())
+    }
+
+    async fn mark_stale(&self, site_id: i64) -> StorageResult<bool> {
+        Ok(self.with_site(site_id, |site| {
+            let ok = site.status.as_deref() == Some("OK");
+            if ok {
+                site.status = Some("STALE".into());
+            }
+            ok
+        }).unwrap_or(false))
+    }
+
+    async fn set_snoozed_until(&self, site_id: i64, until: Option<DateTime<Utc>>) -> StorageResult<()> {
+        self.with_site(site_id, |site| site.snoozed_until = until);
+        Ok(())
+    }
+
+    async fn set_burst(&self, site_id: i64, interval_secs: Option<i64>, until: Option<DateTime<Utc>>) -> StorageResult<()> {
+        self.with_site(site_id, |site| {
+            site.burst_interval_secs = interval_secs;
+            site.burst_until = until;
+        });
+        Ok(())
+    }
+
+    async fn set_extraction_script(&self, site_id: i64, script: Option<&str>) -> StorageResult<()> {
+        self.with_site(site_id, |site| site.extraction_script = script.map(str::to_string));
+        Ok(())
+    }
+
+    async fn probe_signature(&self, site_id: i64) -> StorageResult<Option<String>> {
+        Ok(self.with_site(site_id, |site| site.probe_signature.clone()).flatten())
+    }
+
+    async fn set_backoff(&self, site_id: i64, backoff_count: i64, until: Option<DateTime<Utc>>, reason: Option<&str>) -> StorageResult<()> {
+        self.with_site(site_id, |site| {
+            site.backoff_count = backoff_count;
+            site.backoff_until = until.filter(|_| backoff_count > 0);
+            site.backoff_reason = reason.filter(|_| backoff_count > 0).map(String::from);
+        });
+        Ok(())
+    }
+
+    async fn record_health(&self, site_id: i64, success: bool, thresholds: &health::Thresholds) -> StorageResult<Option<health::Transition>> {
+        Ok(self.with_site(site_id, |site| {
+            site.consecutive_failures = if success { 0 } else { site.consecutive_failures + 1 };
+            let to = thresholds.state(site.consecutive_failures);
+            let from = site.health.replace(to.to_string());
+            (from.as_deref() != Some(to)).then_some(health::Transition { from, to, failures: site.consecutive_failures })
+        }).flatten())
+    }
+
+    async fn record_check(&self, record: &CheckRecord<'_>) -> StorageResult<i64> {
+        let site_id = record.site_id;
+        if let Some(entries) = record.feed_entries {
+            let mut data = self.data.lock().unwrap();
+            let seen = data.seen_items.entry(site_id).or_default();
+            for key in entries.iter().flat_map(feeds::item_keys) {
+                seen.insert(key, record.fetched_at);
+            }
+            seen.retain(|_, last_seen| record.seen_since.is_none_or(|since| *last_seen >= since));
+            let mut by_age: Vec<DateTime<Utc>> = seen.values().copied().collect();
+            by_age.sort_unstable_by(|a, b| b.cmp(a));
+            if let Some(&oldest_kept) = by_age.get(feeds::MAX_SEEN_ITEMS as usize - 1) {
+                seen.retain(|_, last_seen| *last_seen >= oldest_kept);
+            }
+        }
+        {
+            let mut data = self.data.lock().unwrap();
+            let site_url = data.sites.get(&site_id).map(|s| s.url.clone()).unwrap_or_default();
+            for entry in record.items {
+                if data.items.iter().any(|i| i.site_id == site_id && i.guid == entry.guid) {
+                    continue;
+                }
+                let id = data.next_id();
+                data.items.push(Item {
+                    id,
+                    site_id,
+                    site_url: site_url.clone(),
+                    guid: entry.guid.clone(),
+                    title: entry.title.clone(),
+                    link: entry.link.clone(),
+                    summary: entry.summary.clone(),
+                    published_at: entry.published,
+                    first_seen: record.fetched_at,
+                });
+            }
+        }
+        self.mark_checked(site_id, record.fetched_at, true).await?;
+        if let Some(signature) = record.probe_signature {
+            self.with_site(site_id, |site| site.probe_signature = Some(signature.to_string()));
+        }
+        let update_id = self.insert_update(site_id, record.fetched_at, record.diff_hash, record.content).await?;
+        self.index_update_text(update_id, record.indexed_text).await?;
+        {
+            let mut data = self.data.lock().unwrap();
+            if let Some(update) = data.updates.iter_mut().find(|u| u.id == update_id) {
+                update.pdf = record.pdf.map(<[u8]>::to_vec);
+                update.metadata = record.metadata.cloned();
+                update.published_at = record.published_at;
+                update.wire_source = record.wire_source.map(String::from);
+            }
+        }
+        if let Some(change) = &record.change {
+            let mut data = self.data.lock().unwrap();
+            if let Some(update) = data.updates.iter_mut().find(|u| u.id == update_id) {
+                update.diff = change.diff.clone();
+            }
+            if change.in_history {
+                data.site_changes.push((site_id, record.fetched_at));
+                let count = data.site_changes.iter().filter(|(id, _)| *id == site_id).count() as i64;
+                let mut excess = (count - change.history_size.max(0)).max(0);
+                data.site_changes.retain(|(id, _)| {
+                    if *id == site_id && excess > 0 {
+                        excess -= 1;
+                        return false;
+                    }
+                    true
+                });
+            }
+            if let Some(site) = data.sites.get_mut(&site_id) {
+                site.last_updated = Some(record.fetched_at);
+                if site.status.as_deref() == Some("STALE") {
+                    site.status = Some("OK".into());
+                }
+            }
+        }
+        Ok(update_id)
+    }
+
+    async fn latest_hash(&self, site_id: i64) -> StorageResult<Option<String>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.updates.iter().rev().find(|u| u.site_id == site_id).map(|u| u.diff_hash.clone()))
+    }
+
+    async fn insert_update(&self, site_id: i64, timestamp: DateTime<Utc>, diff_hash: &str, content: &str) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        let id = data.next_id();
+        data.updates.push(StoredUpdate {
+            id,
+            site_id,
+            timestamp,
+            diff_hash: diff_hash.to_string(),
+            content: content.to_string(),
+            pipeline_version: Some(scraper::PIPELINE_VERSION),
+            diff: None,
+            pdf: None,
+            screenshot: None,
+            metadata: None,
+            published_at: None,
+            wire_source: None,
+            text: None,
+            legal_hold: false,
+        });
+        Ok(id)
+    }
+
+    async fn previous_content(&self, site_id: i64, before_id: i64) -> StorageResult<Option<String>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.updates.iter()
+            .rev()
+            .find(|u| u.site_id == site_id && u.id < before_id)
+            .map(|u| u.content.clone()))
+    }
+
+    async fn update_content(&self, update_id: i64) -> StorageResult<Option<UpdateContent>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.updates.iter().find(|u| u.id == update_id).map(|u| UpdateContent {
+            site_id: u.site_id,
+            timestamp: u.timestamp,
+            content: u.content.clone(),
+            selector: data.sites.get(&u.site_id).and_then(|s| s.selector.clone()),
+            ignore_patterns: data.sites.get(&u.site_id).map(|s| s.ignore_patterns.clone()).unwrap_or_default(),
+            diff: u.diff.clone(),
+        }))
+    }
+
+    async fn content_at(&self, site_id: i64, timestamp: DateTime<Utc>) -> StorageResult<Option<String>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.updates.iter()
+            .find(|u| u.site_id == site_id && u.timestamp == timestamp)
+            .map(|u| u.content.clone()))
+    }
+
+    async fn update_pdf(&self, update_id: i64) -> StorageResult<Option<Vec<u8>>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.updates.iter().find(|u| u.id == update_id).and_then(|u| u.pdf.clone()))
+    }
+
+    async fn insert_screenshot(&self, update_id: i64, _captured_at: DateTime<Utc>, png: &[u8]) -> StorageResult<()> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(update) = data.updates.iter_mut().find(|u| u.id == update_id) {
+            update.screenshot = Some(png.to_vec());
+        }
+        Ok(())
+    }
+
+    async fn update_screenshot(&self, update_id: i64) -> StorageResult<Option<Vec<u8>>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.updates.iter().find(|u| u.id == update_id).and_then(|u| u.screenshot.clone()))
+    }
+
+    async fn snapshots(&self, site_id: i64, since: Option<DateTime<Utc>>) -> StorageResult<Vec<Snapshot>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.updates.iter()
+            .filter(|u| u.site_id == site_id && since.is_none_or(|since| u.timestamp >= since))
+            .map(|u| Snapshot {
+                id: u.id,
+                diff_hash: u.diff_hash.clone(),
+                content: u.content.clone(),
+                pipeline_version: u.pipeline_version,
+                pdf: u.pdf.is_some(),
+            })
+            .collect())
+    }
+
+    async fn set_snapshot_hash(&self, update_id: i64, diff_hash: &str, pipeline_version: i64) -> StorageResult<()> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(update) = data.updates.iter_mut().find(|u| u.id == update_id) {
+            update.diff_hash = diff_hash.to_string();
+            update.pipeline_version = Some(pipeline_version);
+        }
+        Ok(())
+    }
+
+    async fn index_update_text(&self, update_id: i64, text: &str) -> StorageResult<()> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(update) = data.updates.iter_mut().find(|u| u.id == update_id) {
+            update.text = Some(text.to_string());
+        }
+        Ok(())
+    }
+
+    async fn unindexed_updates(&self, limit: i64) -> StorageResult<Vec<(i64, String)>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.updates.iter()
+            .filter(|u| u.text.is_none())
+            .take(limit.max(0) as usize)
+            .map(|u| (u.id, u.content.clone()))
+            .collect())
+    }
+
+    // ASCII case-insensitive substring match on every word, newest first; the snippet is the
+    // whole text with the first word marked
+    async fn search_updates(&self, query: &str, site_id: Option<i64>, limit: i64) -> StorageResult<Vec<SearchHit>> {
+        let terms: Vec<String> = query.split_whitespace().map(|t| t.trim_matches('"').to_ascii_lowercase()).filter(|t| !t.is_empty()).collect();
+        let Some(first) = terms.first() else {
+            return Ok(Vec::new());
+        };
+        let data = self.data.lock().unwrap();
+        Ok(data.updates.iter()
+            .rev()
+            .filter(|u| site_id.is_none_or(|id| u.site_id == id))
+            .filter_map(|u| {
+                let text = u.text.as_deref()?;
+                let lower = text.to_ascii_lowercase();
+                if !terms.iter().all(|t| lower.contains(t.as_str())) {
+                    return None;
+                }
+                let at = lower.find(first.as_str())?;
+                let end = at + first.len();
+                Some(SearchHit {
+                    update_id: u.id,
+                    site_id: u.site_id,
+                    url: data.sites.get(&u.site_id).map(|s| s.url.clone()).unwrap_or_default(),
+                    timestamp: u.timestamp,
+                    snippet: format!("{}{}{}{}{}", &text[..at], search::MATCH_START, &text[at..end], search::MATCH_END, &text[end..]),
+                })
+            })
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    // Each update holds its own content string, so there is nothing to move or collect
+    async fn move_inline_content(&self, _limit: i64) -> StorageResult<u64> {
+        Ok(0)
+    }
+
+    async fn collect_orphaned_contents(&self) -> StorageResult<u64> {
+        Ok(0)
+    }
+
+    async fn set_update_hold(&self, update_id: i64, held: bool) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        Ok(data.updates.iter_mut().find(|u| u.id == update_id).map(|u| u.legal_hold = held).is_some())
+    }
+
+    async fn legal_holds(&self) -> StorageResult<LegalHolds> {
+        let data = self.data.lock().unwrap();
+        let url = |site_id: i64| data.sites.get(&site_id).map(|s| s.url.clone()).unwrap_or_default();
+        Ok(LegalHolds {
+            sites: data.sites.values()
+                .filter(|s| s.legal_hold)
+                .map(|s| HeldSite { site_id: s.id, url: s.url.clone() })
+                .collect(),
+            updates: data.updates.iter()
+                .filter(|u| u.legal_hold)
+                .map(|u| HeldUpdate { update_id: u.id, site_id: u.site_id, url: url(u.site_id), timestamp: u.timestamp })
+                .collect(),
+        })
+    }
+
+    async fn prune_updates(&self, site_id: i64, keep: i64) -> StorageResult<u64> {
+        let mut data = self.data.lock().unwrap();
+        if data.sites.get(&site_id).is_some_and(|s| s.legal_hold) {
+            return Ok(0);
+        }
+        let mut kept = 0;
+        let before = data.updates.len();
+        // Walk newest first so the most recent `keep` survive
+        let mut survivors: Vec<StoredUpdate> = data.updates.drain(..)
+            .rev()
+            .filter(|u| {
+                if u.site_id != site_id || u.legal_hold {
+                    return true;
+                }
+                kept += 1;
+                kept <= keep
+            })
+            .collect();
+        survivors.reverse();
+        data.updates = survivors;
+        Ok((before - data.updates.len()) as u64)
+    }
+
+    async fn prune_all_updates(&self, keep: i64) -> StorageResult<u64> {
+        let site_ids: Vec<i64> = self.data.lock().unwrap().sites.keys().copied().collect();
+        let mut deleted = 0;
+        for site_id in site_ids {
+            deleted += self.prune_updates(site_id, keep).await?;
+        }
+        Ok(deleted)
+    }
+
+    async fn prune_updates_before(&self, site_id: i64, cutoff: DateTime<Utc>) -> StorageResult<u64> {
+        let mut data = self.data.lock().unwrap();
+        if data.sites.get(&site_id).is_some_and(|s| s.legal_hold) {
+            return Ok(0);
+        }
+        let latest = data.updates.iter().filter(|u| u.site_id == site_id).map(|u| u.id).max();
+        let before = data.updates.len();
+        data.updates.retain(|u| u.site_id != site_id || u.legal_hold || u.timestamp >= cutoff || Some(u.id) == latest);
+        Ok((before - data.updates.len()) as u64)
+    }
+
+    async fn recent_changes(&self, term: Option<&str>, limit: i64) -> StorageResult<Vec<ChangeEntry>> {
+        let term = term.map(str::to_lowercase);
+        let changes = self.data.lock().unwrap().changes();
+        Ok(changes.into_iter()
+            .rev()
+            .filter(|c| term.as_ref().is_none_or(|t| c.url.to_lowercase().contains(t) || c.content.to_lowercase().contains(t)))
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn changes_since(&self, after_id: i64, limit: i64) -> StorageResult<Vec<ChangeEntry>> {
+        let changes = self.data.lock().unwrap().changes();
+        Ok(changes.into_iter()
+            .filter(|c| c.id > after_id)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn changes_between(&self, from: DateTime<Utc>, until: DateTime<Utc>, limit: i64) -> StorageResult<Vec<ChangeEntry>> {
+        let changes = self.data.lock().unwrap().changes();
+        Ok(changes.into_iter()
+            .filter(|c| c.timestamp >= from && c.timestamp < until)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn feed_diff(&self, site_id: i64, entries: &[FeedEntry], seen_since: Option<DateTime<Utc>>) -> StorageResult<FeedDiff> {
+        let data = self.data.lock().unwrap();
+        let seen = data.seen_items.get(&site_id).into_iter().flatten()
+            .filter(|(_, last_seen)| seen_since.is_none_or(|since| **last_seen >= since))
+            .map(|(key, _)| key.clone())
+            .collect();
+        Ok(feeds::diff_against(seen, entries))
+    }
+
+    async fn list_items(&self, query: &ItemQuery) -> StorageResult<Vec<Item>> {
+        let data = self.data.lock().unwrap();
+        let (tag, keyword) = (query.tag(), query.keyword());
+        let mut items: Vec<Item> = data.items.iter()
+            .filter(|i| query.site_id.is_none_or(|id| i.site_id == id))
+            .filter(|i| tag.as_ref().is_none_or(|tag| data.sites.get(&i.site_id).is_some_and(|s| s.tags.contains(tag))))
+            .filter(|i| keyword.as_ref().is_none_or(|k| {
+                format!("{} {}", i.title.as_deref().unwrap_or(""), i.summary.as_deref().unwrap_or("")).to_lowercase().contains(k)
+            }))
+            .filter(|i| {
+                let at = i.published_at.unwrap_or(i.first_seen);
+                query.since.is_none_or(|since| at >= since) && query.until.is_none_or(|until| at < until)
+            })
+            .cloned()
+            .collect();
+        items.sort_by_key(|i| std::cmp::Reverse((i.published_at.unwrap_or(i.first_seen), i.id)));
+        items.truncate(query.limit() as usize);
+        Ok(items)
+    }
+
+    async fn insert_document(&self, document: &NewDocument<'_>) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        let id = data.next_id();
+        data.documents.push((Document {
+            id,
+            site_id: document.site_id,
+            update_id: Some(document.update_id),
+            url: document.url.to_string(),
+            content_type: document.content_type.map(String::from),
+            size: document.data.len() as i64,
+            fetched_at: document.fetched_at,
+        }, document.data.to_vec()));
+        Ok(id)
+    }
+
+    async fn document(&self, id: i64) -> StorageResult<Option<(Document, Vec<u8>)>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.documents.iter().find(|(d, _)| d.id == id).map(|(d, body)| (d.clone(), body.clone())))
+    }
+
+    async fn list_documents(&self, site_id: i64) -> StorageResult<Vec<Document>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.documents.iter().rev().filter(|(d, _)| d.site_id == site_id).map(|(d, _)| d.clone()).collect())
+    }
+
+    async fn record_halt(&self, site_id: i64, update_id: i64, halt: &Halt, seen_at: DateTime<Utc>) -> StorageResult<HaltChange> {
+        let mut data = self.data.lock().unwrap();
+        let stored = data.halts.iter_mut().find(|h| h.site_id == site_id && h.halt.symbol == halt.symbol && h.halt.halted_at == halt.halted_at);
+        match stored {
+            Some(stored) if stored.halt.resumed_at.is_none() && halt.resumed_at.is_some() => {
+                stored.halt.resumed_at = halt.resumed_at;
+                Ok(HaltChange::Resumed)
+            },
+            Some(_) => Ok(HaltChange::Unchanged),
+            None => {
+                let id = data.next_id();
+                data.halts.push(StoredHalt { id, site_id, update_id: Some(update_id), halt: halt.clone(), first_seen: seen_at });
+                Ok(HaltChange::New)
+            },
+        }
+    }
+
+    async fn list_halts(&self, query: &HaltQuery) -> StorageResult<Vec<StoredHalt>> {
+        let data = self.data.lock().unwrap();
+        let symbol = query.symbol();
+        let mut halts: Vec<StoredHalt> = data.halts.iter()
+            .filter(|h| query.site_id.is_none_or(|id| h.site_id == id) && symbol.as_ref().is_none_or(|s| *s == h.halt.symbol))
+            .cloned()
+            .collect();
+        halts.sort_by(|a, b| b.halt.halted_at.cmp(&a.halt.halted_at).then(b.id.cmp(&a.id)));
+        halts.truncate(query.limit() as usize);
+        Ok(halts)
+    }
+
+    async fn record_federal_document(&self, site_id: i64, update_id: i64, document: &FederalDocument, seen_at: DateTime<Utc>) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        if data.federal_documents.iter().any(|d| d.site_id == site_id && d.document.document_number == document.document_number) {
+            return Ok(false);
+        }
+        let id = data.next_id();
+        data.federal_documents.push(StoredFederalDocument { id, site_id, update_id: Some(update_id), document: document.clone(), first_seen: seen_at });
+        Ok(true)
+    }
+
+    async fn list_federal_documents(&self, query: &FederalDocumentQuery) -> StorageResult<Vec<StoredFederalDocument>> {
+        let data = self.data.lock().unwrap();
+        let document_type = query.document_type();
+        let mut documents: Vec<StoredFederalDocument> = data.federal_documents.iter()
+            .filter(|d| query.site_id.is_none_or(|id| d.site_id == id) && document_type.as_ref().is_none_or(|t| d.document.document_type.as_ref() == Some(t)))
+            .cloned()
+            .collect();
+        documents.sort_by(|a, b| b.document.publication_date.cmp(&a.document.publication_date).then(b.id.cmp(&a.id)));
+        documents.truncate(query.limit() as usize);
+        Ok(documents)
+    }
+
+    async fn list_alert_rules(&self) -> StorageResult<Vec<AlertRule>> {
+        Ok(self.data.lock().unwrap().alert_rules.clone())
+    }
+
+    async fn site_alert_rules(&self, site_id: i64) -> StorageResult<Vec<AlertRule>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.alert_rules.iter().filter(|r| r.site_id.is_none_or(|id| id == site_id)).cloned().collect())
+    }
+
+    async fn insert_alert_rule(&self, rule: &AlertRule) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        let id = data.next_id();
+        data.alert_rules.push(AlertRule { id, ..rule.clone() });
+        Ok(id)
+    }
+
+    async fn delete_alert_rule(&self, id: i64) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let before = data.alert_rules.len();
+        data.alert_rules.retain(|r| r.id != id);
+        Ok(data.alert_rules.len() < before)
+    }
+
+    async fn insert_alert(&self, alert: &Alert) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        let id = data.next_id();
+        data.alerts.push(Alert { id, acknowledged_at: None, acknowledged_by: None, ..alert.clone() });
+        Ok(id)
+    }
+
+    async fn list_alerts(&self, unacknowledged_only: bool, limit: i64) -> StorageResult<Vec<Alert>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.alerts.iter().rev()
+            .filter(|a| !unacknowledged_only || a.acknowledged_at.is_none())
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn acknowledge_alert(&self, id: i64, at: DateTime<Utc>, by: Option<&str>) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let Some(alert) = data.alerts.iter_mut().find(|a| a.id == id) else {
+            return Ok(false);
+        };
+        if alert.acknowledged_at.is_none() {
+            alert.acknowledged_at = Some(at);
+            alert.acknowledged_by = by.map(String::from);
+        }
+        Ok(true)
+    }
+
+    async fn list_webhooks(&self) -> StorageResult<Vec<Webhook>> {
+        Ok(self.data.lock().unwrap().webhooks.clone())
+    }
+
+    async fn site_webhooks(&self, site_id: i64) -> StorageResult<Vec<Webhook>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.webhooks.iter().filter(|h| h.site_id.is_none_or(|id| id == site_id)).cloned().collect())
+    }
+
+    async fn get_webhook(&self, id: i64) -> StorageResult<Option<Webhook>> {
+        Ok(self.data.lock().unwrap().webhooks.iter().find(|h| h.id == id).cloned())
+    }
+
+    async fn insert_webhook(&self, hook: &Webhook) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        let id = data.next_id();
+        data.webhooks.push(Webhook {
+            id,
+            canary_checked_at: None,
+            canary_ok: None,
+            canary_error: None,
+            ..hook.clone()
+        });
+        Ok(id)
+    }
+
+    async fn delete_webhook(&self, id: i64) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let before = data.webhooks.len();
+        data.webhooks.retain(|h| h.id != id);
+        Ok(data.webhooks.len() < before)
+    }
+
+    async fn list_telegram_chats(&self) -> StorageResult<Vec<TelegramChat>> {
+        Ok(self.data.lock().unwrap().telegram_chats.clone())
+    }
+
+    async fn site_telegram_chats(&self, site_id: i64) -> StorageResult<Vec<TelegramChat>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.telegram_chats.iter().filter(|c| c.site_ids.contains(&site_id)).cloned().collect())
+    }
+
+    async fn insert_telegram_chat(&self, chat: &TelegramChat) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        let id = data.next_id();
+        data.telegram_chats.push(TelegramChat { id, ..chat.clone() });
+        Ok(id)
+    }
+
+    async fn set_telegram_sites(&self, id: i64, site_ids: &[i64]) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        Ok(data.telegram_chats.iter_mut().find(|c| c.id == id).map(|c| c.site_ids = site_ids.to_vec()).is_some())
+    }
+
+    async fn delete_telegram_chat(&self, id: i64) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let before = data.telegram_chats.len();
+        data.telegram_chats.retain(|c| c.id != id);
+        Ok(data.telegram_chats.len() < before)
+    }
+
+    async fn list_email_recipients(&self) -> StorageResult<Vec<EmailRecipient>> {
+        Ok(self.data.lock().unwrap().email_recipients.clone())
+    }
+
+    async fn insert_email_recipient(&self, recipient: &EmailRecipient) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        let id = data.next_id();
+        data.email_recipients.push(EmailRecipient { id, last_digest_at: None, ..recipient.clone() });
+        Ok(id)
+    }
+
+    async fn update_email_recipient(&self, recipient: &EmailRecipient) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let Some(stored) = data.email_recipients.iter_mut().find(|r| r.id == recipient.id) else {
+            return Ok(false);
+        };
+        *stored = EmailRecipient { last_digest_at: stored.last_digest_at, ..recipient.clone() };
+        Ok(true)
+    }
+
+    async fn delete_email_recipient(&self, id: i64) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let before = data.email_recipients.len();
+        data.email_recipients.retain(|r| r.id != id);
+        Ok(data.email_recipients.len() < before)
+    }
+
+    async fn mark_digest_sent(&self, id: i64, at: DateTime<Utc>) -> StorageResult<()> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(recipient) = data.email_recipients.iter_mut().find(|r| r.id == id) {
+            recipient.last_digest_at = Some(at);
+        }
+        Ok(())
+    }
+
+    async fn list_email_templates(&self) -> StorageResult<Vec<EmailTemplate>> {
+        Ok(self.data.lock().unwrap().email_templates.clone())
+    }
+
+    async fn set_email_template(&self, template: &EmailTemplate) -> StorageResult<()> {
+        let mut data = self.data.lock().unwrap();
+        data.email_templates.retain(|t| t.kind != template.kind);
+        data.email_templates.push(template.clone());
+        Ok(())
+    }
+
+    async fn delete_email_template(&self, kind: &str) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let before = data.email_templates.len();
+        data.email_templates.retain(|t| t.kind != kind);
+        Ok(data.email_templates.len() < before)
+    }
+
+    async fn claim_notification(&self, site_id: i64, diff_hash: &str, _at: DateTime<Utc>) -> StorageResult<bool> {
+        Ok(self.data.lock().unwrap().notified_changes.insert((site_id, diff_hash.to_string())))
+    }
+
+    async fn list_api_keys(&self) -> StorageResult<Vec<ApiKey>> {
+        Ok(self.data.lock().unwrap().api_keys.clone())
+    }
+
+    async fn insert_api_key(&self, key: &ApiKey) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        let id = data.next_id();
+        data.api_keys.push(ApiKey { id, ..key.clone() });
+        Ok(id)
+    }
+
+    async fn delete_api_key(&self, id: i64) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let before = data.api_keys.len();
+        data.api_keys.retain(|k| k.id != id);
+        Ok(data.api_keys.len() < before)
+    }
+
+    async fn list_api_tokens(&self) -> StorageResult<Vec<ApiToken>> {
+        Ok(self.data.lock().unwrap().api_tokens.clone())
+    }
+
+    async fn insert_api_token(&self, token: &ApiToken) -> StorageResult<i64> {
+        let mut data = self.data.lock().unwrap();
+        let id = data.next_id();
+        data.api_tokens.push(ApiToken { id, ..token.clone() });
+        Ok(id)
+    }
+
+    async fn delete_api_token(&self, id: i64) -> StorageResult<bool> {
+        let mut data = self.data.lock().unwrap();
+        let before = data.api_tokens.len();
+        data.api_tokens.retain(|t| t.id != id);
+        Ok(data.api_tokens.len() < before)
+    }
+
+    async fn record_canary(&self, id: i64, checked_at: DateTime<Utc>, error: Option<&str>) -> StorageResult<()> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(hook) = data.webhooks.iter_mut().find(|h| h.id == id) {
+            hook.canary_checked_at = Some(checked_at);
+            hook.canary_ok = Some(error.is_none());
+            hook.canary_error = error.map(String::from);
+        }
+        Ok(())
+    }
+
+    async fn record_fetch(&self, entry: &FetchLogEntry, keep: i64) -> StorageResult<()> {
+        let mut data = self.data.lock().unwrap();
+        data.fetch_log.push(entry.clone());
+        let count = data.fetch_log.iter().filter(|e| e.site_id == entry.site_id).count() as i64;
+        let mut excess = (count - keep.max(0)).max(0);
+        data.fetch_log.retain(|e| {
+            if e.site_id == entry.site_id && excess > 0 {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+        Ok(())
+    }
+
+    async fn change_times(&self, site_id: i64) -> StorageResult<Vec<DateTime<Utc>>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.site_changes.iter().filter(|(id, _)| *id == site_id).map(|(_, at)| *at).collect())
+    }
+
+    async fn set_interval(&self, site_id: i64, interval_secs: i64) -> StorageResult<()> {
+        self.with_site(site_id, |site| site.interval_secs = interval_secs);
+        Ok(())
+    }
+
+    async fn set_adaptive_interval(&self, site_id: i64, interval_secs: i64) -> StorageResult<()> {
+        self.with_site(site_id, |site| site.adaptive_interval_secs = Some(interval_secs));
+        Ok(())
+    }
+
+    async fn fetch_log(&self, site_id: i64, limit: i64) -> StorageResult<Vec<FetchLogEntry>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.fetch_log.iter()
+            .rev()
+            .filter(|e| e.site_id == site_id)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn record_latency(&self, sample: &LatencySample) -> StorageResult<()> {
+        let mut data = self.data.lock().unwrap();
+        let samples = data.latencies.entry(sample.site_id).or_default();
+        samples.push(sample.latency_secs());
+        let excess = samples.len().saturating_sub(LATENCY_SAMPLES_PER_SITE as usize);
+        samples.drain(..excess);
+        Ok(())
+    }
+
+    async fn latency_stats(&self) -> StorageResult<Vec<SiteLatency>> {
+        let data = self.data.lock().unwrap();
+        let stats = data.latencies.iter()
+            .filter_map(|(site_id, latencies)| {
+                let url = data.sites.get(site_id)?.url.clone();
+                summarize_latency(*site_id, url, latencies)
+            })
+            .collect();
+        Ok(sort_by_median(stats))
+    }
+
+    async fn activity(&self, since: DateTime<Utc>) -> StorageResult<Activity> {
+        let data = self.data.lock().unwrap();
+        let fetches: Vec<&FetchLogEntry> = data.fetch_log.iter().filter(|e| e.fetched_at >= since).collect();
+        Ok(Activity {
+            fetches: fetches.len() as i64,
+            failed_fetches: fetches.iter().filter(|e| e.error.is_some()).count() as i64,
+            changes: data.updates.iter().filter(|u| u.timestamp >= since).count() as i64,
+        })
+    }
+
+    async fn top_changing_sites(&self, since: DateTime<Utc>, limit: i64) -> StorageResult<Vec<ChangeCount>> {
+        let data = self.data.lock().unwrap();
+        let mut counts: BTreeMap<i64, i64> = BTreeMap::new();
+        for update in data.updates.iter().filter(|u| u.timestamp >= since) {
+            *counts.entry(update.site_id).or_default() += 1;
+        }
+        let mut sites: Vec<ChangeCount> = counts.into_iter()
+            .filter_map(|(site_id, changes)| Some(ChangeCount { site_id, url: data.sites.get(&site_id)?.url.clone(), changes }))
+            .collect();
+        sites.sort_by_key(|site| std::cmp::Reverse(site.changes));
+        sites.truncate(limit.max(0) as usize);
+        Ok(sites)
+    }
+
+    async fn database_size(&self) -> StorageResult<Option<i64>> {
+        Ok(None)
+    }
+
+    async fn ping(&self) -> StorageResult<()> {
+        Ok(())
+    }
+
+    async fn round_trip(&self) -> StorageResult<()> {
+        Ok(())
+    }
+
+    async fn reset(&self) -> StorageResult<()> {
+        let mut data = self.data.lock().unwrap();
+        data.sites.clear();
+        data.updates.clear();
+        data.seen_items.clear();
+        data.items.clear();
+        data.alert_rules.retain(|r| r.site_id.is_none());
+        data.alerts.clear();
+        data.webhooks.retain(|h| h.site_id.is_none());
+        for chat in &mut data.telegram_chats {
+            chat.site_ids.clear();
+        }
+        data.fetch_log.clear();
+        data.notified_changes.clear();
+        data.latencies.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(url: &str) -> Site {
+        Site {
+            id: 0,
+            url: url.to_string(),
+            interval_secs: 60,
+            style: "random".into(),
+            last_checked: None,
+            last_updated: None,
+            status: None,
+            snoozed_until: None,
+            last_success: None,
+            selector: None,
+            priority: 0,
+            retention_count: None,
+            retention_days: None,
+            probe_mode: None,
+            probe_tail_kb: None,
+            probe_signature: None,
+            assert_selector: None,
+            assert_pattern: None,
+            assert_min_length: None,
+            ignore_patterns: Vec::new(),
+            legal_hold: false,
+            notify_burst_limit: None,
+            notify_window_secs: None,
+            proxies: Vec::new(),
+            backoff_count: 0,
+            backoff_until: None,
+            backoff_reason: None,
+            consecutive_failures: 0,
+            health: None,
+            adaptive_interval_secs: None,
+            active_window: None,
+            burst_interval_secs: None,
+            burst_until: None,
+            burst_remaining_secs: None,
+            timeout_secs: None,
+            max_redirects: None,
+            max_body_bytes: None,
+            extraction_mode: None,
+            preview_length: None,
+            extraction_script: None,
+            plugin: None,
+            link_pattern: None,
+            archive_links: false,
+            max_content_age_hours: None,
+            crawl_pattern: None,
+            crawl_depth: None,
+            crawl_max_pages: None,
+            max_quiet_hours: None,
+            tags: vec!["FDA".into()],
+        }
+    }
+
+    #[tokio::test]
+    async fn memory_storage_keeps_newest_updates() {
+        let storage = MemoryStorage::new();
+        let a = storage.insert_site(&site("https://a.example")).await.unwrap();
+        let b = storage.insert_site(&site("https://b.example")).await.unwrap();
+        let now = Utc::now();
+
+        let first = storage.insert_update(a, now, "h1", "one").await.unwrap();
+        storage.insert_update(b, now, "other", "b").await.unwrap();
+        let second = storage.insert_update(a, now, "h2", "two").await.unwrap();
+        storage.insert_update(a, now, "h3", "three").await.unwrap();
+
+        assert_eq!(storage.latest_hash(a).await.unwrap().as_deref(), Some("h3"));
+        assert_eq!(storage.previous_content(a, second).await.unwrap().as_deref(), Some("one"));
+        assert_eq!(storage.prune_updates(a, 2).await.unwrap(), 1);
+        assert_eq!(storage.previous_content(a, second).await.unwrap(), None);
+        assert_eq!(storage.latest_hash(b).await.unwrap().as_deref(), Some("other"));
+        assert!(first < second);
+
+        assert!(storage.delete_site(a).await.unwrap());
+        assert!(!storage.delete_site(a).await.unwrap());
+        assert_eq!(storage.latest_hash(a).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn memory_storage_tracks_check_state() {
+        let storage = MemoryStorage::new();
+        let a = storage.insert_site(&site("https://a.example")).await.unwrap();
+        let b = storage.insert_site(&site("https://b.example")).await.unwrap();
+        storage.insert_site(&site("https://c.example")).await.unwrap();
+        let now = Utc::now();
+
+        storage.record_check(&CheckRecord {
+            site_id: a,
+            fetched_at: now,
+            diff_hash: "h1",
+            content: "<p>one</p>",
+            pdf: None,
+            metadata: None,
+            published_at: None,
+            wire_source: None,
+            indexed_text: "one",
+            probe_signature: Some("etag"),
+            feed_entries: None,
+            seen_since: None,
+            items: &[],
+            change: Some(RecordedChange { diff: None, in_history: false, history_size: 10 }),
+        }).await.unwrap();
+        storage.mark_checked(b, now, false).await.unwrap();
+        storage.set_snoozed_until(b, Some(now + chrono::Duration::hours(1))).await.unwrap();
+
+        let stats = storage.site_stats(now).await.unwrap();
+        assert_eq!(stats, SiteStats { total: 3, ok: 1, errors: 1, pending: 1, snoozed: 1, last_change: Some(now) });
+        assert_eq!(storage.get_site(a).await.unwrap().unwrap().tags, vec!["fda".to_string()]);
+
+        // A quiet site stays STALE through unchanged fetches; failing sites aren't flagged
+        assert!(storage.mark_stale(a).await.unwrap());
+        assert!(!storage.mark_stale(b).await.unwrap());
+        storage.mark_checked(a, now, true).await.unwrap();
+        assert_eq!(storage.get_site(a).await.unwrap().unwrap().status.as_deref(), Some("STALE"));
+    }
+
+    #[tokio::test]
+    async fn summarizes_detection_latency_per_site() {
+        let storage = MemoryStorage::new();
+        let fast = storage.insert_site(&site("https://fast.example")).await.unwrap();
+        let slow = storage.insert_site(&site("https://slow.example")).await.unwrap();
+        let now = Utc::now();
+        let sample = |site_id, secs| LatencySample {
+            site_id,
+            update_id: 1,
+            published: now - chrono::Duration::seconds(secs),
+            detected_at: now,
+        };
+        for secs in [30, 10, 20] {
+            storage.record_latency(&sample(fast, secs)).await.unwrap();
+        }
+        storage.record_latency(&sample(slow, 600)).await.unwrap();
+        // Published "in the future" (clock skew) counts as zero
+        assert_eq!(sample(fast, -5).latency_secs(), 0);
+
+        let stats = storage.latency_stats().await.unwrap();
+        assert_eq!(stats[0].site_id, slow);
+        assert_eq!(stats[1], SiteLatency {
+            site_id: fast,
+            url: "https://fast.example".into(),
+            samples: 3,
+            avg_secs: 20,
+            median_secs: 20,
+            max_secs: 30,
+            last_secs: 20,
+        });
+    }
+
+    #[tokio::test]
+    async fn memory_storage_rolls_fetch_log() {
+        let storage = MemoryStorage::new();
+        let now = Utc::now();
+        for duration_ms in 1..=4 {
+            let entry = FetchLogEntry {
+                site_id: 1,
+                fetched_at: now,
+                status_code: Some(200),
+                duration_ms,
+                error: None,
+                proxy: None,
+                final_url: Some("https://example.com/".into()),
+                content_type: Some("text/html".into()),
+                content_length: None,
+                changed: false,
+                update_id: None,
+            };
+            storage.record_fetch(&entry, 3).await.unwrap();
+        }
+        let log = storage.fetch_log(1, 10).await.unwrap();
+        assert_eq!(log.iter().map(|e| e.duration_ms).collect::<Vec<_>>(), vec![4, 3, 2]);
+        assert!(storage.fetch_log(2, 10).await.unwrap().is_empty());
+    }
+}
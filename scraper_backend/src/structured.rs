@@ -0,0 +1,245 @@
+use super::items::parse_date;
+use super::truncate::{self, SENTENCE_BREAKS, WORD_BREAKS};
+use chrono::{DateTime, Utc};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Article bodies standing in for a missing description are cut to this
+const MAX_SUMMARY_CHARS: usize = 500;
+
+// schema.org types describing the page's own content; other items (the publisher, a
+// breadcrumb list) only fill in what these leave out
+const ARTICLE_TYPES: [&str; 9] = [
+    "Article", "NewsArticle", "ReportageNews", "AnalysisNewsArticle", "BlogPosting", "Report", "ScholarlyArticle", "TechArticle", "PressRelease",
+];
+
+// What a page says about itself in JSON-LD, microdata and OpenGraph tags, in that order of
+// preference field by field. Stored with each update.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    // schema.org @type or og:type
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    // The description, or the start of the article body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+}
+
+impl Metadata {
+    fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    // Fields this one lacks are taken from a less preferred source
+    fn or(self, other: Metadata) -> Metadata {
+        Metadata {
+            kind: self.kind.or(other.kind),
+            title: self.title.or(other.title),
+            summary: self.summary.or(other.summary),
+            published: self.published.or(other.published),
+            modified: self.modified.or(other.modified),
+            author: self.author.or(other.author),
+            image: self.image.or(other.image),
+            url: self.url.or(other.url),
+            site_name: self.site_name.or(other.site_name),
+        }
+    }
+
+    // When the content last changed by the page's own account
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.published.max(self.modified)
+    }
+}
+
+// None when the page carries none of it
+pub fn extract(html: &str) -> Option<Metadata> {
+    let document = Html::parse_document(html);
+    let metadata = json_ld(&document).or(microdata(&document)).or(open_graph(&document));
+    (!metadata.is_empty()).then_some(metadata)
+}
+
+fn text(value: &str) -> Option<String> {
+    Some(value.split_whitespace().collect::<Vec<_>>().join(" ")).filter(|v| !v.is_empty())
+}
+
+fn summary(description: Option<String>, body: Option<String>) -> Option<String> {
+    description.or_else(|| body.map(|body| truncate::preview(&body, MAX_SUMMARY_CHARS, &[SENTENCE_BREAKS, WORD_BREAKS])))
+}
+
+fn is_article_type(kind: &str) -> bool {
+    let kind = kind.rsplit('/').next().unwrap_or(kind);
+    ARTICLE_TYPES.contains(&kind)
+}
+
+// Every item in the page's JSON-LD blocks, through arrays and @graph
+fn json_ld_items(document: &Html) -> Vec<Value> {
+    let scripts = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    let mut items = Vec::new();
+    let mut pending: Vec<Value> = document.select(&scripts)
+        .filter_map(|script| serde_json::from_str(&script.text().collect::<String>()).ok())
+        .collect();
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::Array(values) => pending.extend(values.into_iter().rev()),
+            Value::Object(mut object) => {
+                if let Some(graph) = object.remove("@graph") {
+                    pending.push(graph);
+                }
+                items.push(Value::Object(object));
+            },
+            _ => {},
+        }
+    }
+    items
+}
+
+// A string, the first of an array, or an object's name (url for images)
+fn ld_text(value: Option<&Value>, key: &str) -> Option<String> {
+    match value? {
+        Value::String(s) => text(s),
+        Value::Array(values) => values.iter().find_map(|v| ld_text(Some(v), key)),
+        Value::Object(object) => ld_text(object.get(key), key),
+        _ => None,
+    }
+}
+
+fn ld_type(item: &Value) -> Option<String> {
+    ld_text(item.get("@type"), "@type")
+}
+
+fn json_ld(document: &Html) -> Metadata {
+    let items = json_ld_items(document);
+    let main = items.iter()
+        .find(|item| ld_type(item).is_some_and(|kind| is_article_type(&kind)))
+        .or_else(|| items.iter().find(|item| item.get("headline").is_some()));
+    let Some(item) = main else {
+        return Metadata::default();
+    };
+    let field = |key: &str| ld_text(item.get(key), "name");
+    Metadata {
+        kind: ld_type(item),
+        title: field("headline").or_else(|| field("name")),
+        summary: summary(field("description"), field("articleBody")),
+        published: field("datePublished").as_deref().and_then(parse_date),
+        modified: field("dateModified").as_deref().and_then(parse_date),
+        author: field("author"),
+        image: ld_text(item.get("image"), "url"),
+        url: field("url"),
+        site_name: field("publisher"),
+    }
+}
+
+// Value of an itemprop: the content, datetime or href attribute when it has one, else its text
+fn microdata_value(element: ElementRef) -> Option<String> {
+    let value = element.value();
+    match value.attr("content").or_else(|| value.attr("datetime")).or_else(|| value.attr("href")) {
+        Some(attr) => text(attr),
+        None => text(&element.text().collect::<String>()),
+    }
+}
+
+fn microdata(document: &Html) -> Metadata {
+    let scopes = Selector::parse("[itemscope][itemtype]").unwrap();
+    let Some(scope) = document.select(&scopes).find(|s| s.value().attr("itemtype").is_some_and(is_article_type)) else {
+        return Metadata::default();
+    };
+    let prop = |name: &str| {
+        let selector = Selector::parse(&format!(r#"[itemprop="{}"]"#, name)).unwrap();
+        scope.select(&selector).next().and_then(microdata_value)
+    };
+    let author = Selector::parse(r#"[itemprop="author"] [itemprop="name"]"#).unwrap();
+    Metadata {
+        kind: scope.value().attr("itemtype").and_then(|t| t.rsplit('/').next()).and_then(text),
+        title: prop("headline").or_else(|| prop("name")),
+        summary: summary(prop("description"), prop("articleBody")),
+        published: prop("datePublished").as_deref().and_then(parse_date),
+        modified: prop("dateModified").as_deref().and_then(parse_date),
+        author: scope.select(&author).next().and_then(microdata_value).or_else(|| prop("author")),
+        image: prop("image"),
+        url: prop("url"),
+        site_name: None,
+    }
+}
+
+// OpenGraph tags, and the other <meta> names CMSs commonly put a publication date in
+fn open_graph(document: &Html) -> Metadata {
+    let metas = Selector::parse("meta[content]").unwrap();
+    let meta = |names: &[&str]| {
+        document.select(&metas)
+            .find(|m| {
+                let name = m.value().attr("property").or_else(|| m.value().attr("name")).unwrap_or_default();
+                names.iter().any(|n| n.eq_ignore_ascii_case(name))
+            })
+            .and_then(|m| m.value().attr("content"))
+            .and_then(text)
+    };
+    Metadata {
+        kind: meta(&["og:type"]),
+        title: meta(&["og:title"]),
+        summary: meta(&["og:description", "description"]),
+        published: meta(&["article:published_time", "date", "pubdate", "publish-date", "dc.date.issued", "dc.date", "parsely-pub-date", "sailthru.date"])
+            .as_deref()
+            .and_then(parse_date),
+        modified: meta(&["article:modified_time", "og:updated_time"]).as_deref().and_then(parse_date),
+        author: meta(&["article:author", "author"]),
+        image: meta(&["og:image"]),
+        url: meta(&["og:url"]),
+        site_name: meta(&["og:site_name"]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_json_ld_then_microdata_then_open_graph() {
+        let page = r#"<html><head><title>Press | Acme</title>
+            <meta property="og:title" content="OG title">
+            <meta property="og:site_name" content="Acme Corp">
+            <meta name="description" content="Acme announces results.">
+            <script type="application/ld+json">{"@context": "https://schema.org", "@graph": [
+                {"@type": "Organization", "name": "Acme"},
+                {"@type": "NewsArticle", "headline": " Acme reports   Q3 ", "datePublished": "2026-10-14T09:00:00-04:00",
+                 "author": [{"@type": "Person", "name": "Jane Roe"}], "image": {"url": "https://acme.example/q3.png"},
+                 "articleBody": "Revenue rose 12%. Margins widened."}
+            ]}</script></head>
+            <body><div itemscope itemtype="https://schema.org/Article">
+                <h1 itemprop="headline">Microdata title</h1>
+                <time itemprop="dateModified" datetime="2026-10-15">Oct 15</time>
+            </div></body></html>"#;
+        let metadata = extract(page).unwrap();
+        assert_eq!(metadata.kind.as_deref(), Some("NewsArticle"));
+        assert_eq!(metadata.title.as_deref(), Some("Acme reports Q3"));
+        // The article's own body wins over a page-wide meta description; what it lacks
+        // comes from further down
+        assert_eq!(metadata.summary.as_deref(), Some("Revenue rose 12%. Margins widened."));
+        assert_eq!(metadata.published.map(|d| d.to_rfc3339()).as_deref(), Some("2026-10-14T13:00:00+00:00"));
+        assert_eq!(metadata.updated_at(), parse_date("2026-10-15"));
+        assert_eq!(metadata.author.as_deref(), Some("Jane Roe"));
+        assert_eq!(metadata.image.as_deref(), Some("https://acme.example/q3.png"));
+        assert_eq!(metadata.site_name.as_deref(), Some("Acme Corp"));
+
+        let described = r#"<script type="application/ld+json">[{"@type": "BlogPosting", "headline": "H", "description": "Short.", "articleBody": "Long."}]</script>"#;
+        assert_eq!(extract(described).unwrap().summary.as_deref(), Some("Short."));
+        assert!(extract("<html><head><title>Plain</title></head><body><p>text</p></body></html>").is_none());
+        // CMS date tags, in whichever case and date format they come
+        let dated = r#"<meta name="DC.Date.Issued" content="Tue, 13 Oct 2026 08:00:00 GMT"><meta name="pubdate" content="2026-10-01T08:00:00">"#;
+        assert_eq!(extract(dated).unwrap().published, parse_date("2026-10-13T08:00:00Z"));
+    }
+}
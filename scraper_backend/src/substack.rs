@@ -0,0 +1,147 @@
+use super::feeds::FeedEntry;
+use super::truncate::{self, WORD_BREAKS};
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+
+// Sites with this extraction_mode poll a Substack publication's posts API instead of its
+// feed, which cuts posts short. *.substack.com URLs are switched to it when added; custom
+// domains need it set.
+pub const EXTRACTION_MODE: &str = "substack";
+
+const POSTS_PATH: &str = "/api/v1/posts";
+// The API's own maximum
+const POSTS_LIMIT: &str = "50";
+// Marks paid posts' titles, so they stand out among free ones
+const PAID_MARK: &str = "[Paid]";
+
+// substack.com itself hosts the reader and search, not a publication
+const NOT_PUBLICATIONS: &[&str] = &["www", "on", "open", "support", "api"];
+
+// A publication on Substack's own domain, such as thebearcave.substack.com
+pub fn is_publication(source: &str) -> bool {
+    Url::parse(source.trim()).is_ok_and(|url| {
+        url.host_str().and_then(|host| host.strip_suffix(".substack.com")).is_some_and(|name| !name.contains('.') && !NOT_PUBLICATIONS.contains(&name))
+    })
+}
+
+// The posts API of the publication a URL is on, whatever page or feed it names
+pub fn resolve(source: &str) -> Result<String, String> {
+    let url = Url::parse(source.trim()).map_err(|_| format!("'{}' is not a Substack URL", source.trim()))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(format!("'{}' is not a Substack URL", source.trim()));
+    }
+    let mut api = Url::parse(&format!("{}{}", url.origin().ascii_serialization(), POSTS_PATH)).unwrap();
+    api.query_pairs_mut().append_pair("limit", POSTS_LIMIT);
+    Ok(api.to_string())
+}
+
+fn text(value: &Value) -> Option<String> {
+    value.as_str().map(str::trim).filter(|s| !s.is_empty()).map(String::from)
+}
+
+fn block_text(element: ElementRef) -> String {
+    element.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// A post's HTML as text, a block a paragraph. List items holding paragraphs are read
+// through those.
+fn body_text(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let blocks = Selector::parse("p, h1, h2, h3, h4, h5, h6, pre, li").unwrap();
+    let paragraphs = Selector::parse("p").unwrap();
+    fragment.select(&blocks)
+        .filter(|block| block.value().name() != "li" || block.select(&paragraphs).next().is_none())
+        .map(block_text)
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// Paid posts are readable only by subscribers: the API gives their teaser instead of the
+// body
+fn is_paid(post: &Value) -> bool {
+    matches!(post["audience"].as_str(), Some("only_paid" | "founding"))
+}
+
+// A post as a feed entry, its ID the GUID: free posts carry their whole body, paid posts
+// their subtitle and teaser and a [Paid] title
+fn entry(post: &Value) -> Option<FeedEntry> {
+    let id = match &post["id"] {
+        Value::Number(id) => id.to_string(),
+        id => text(id)?,
+    };
+    let title = text(&post["title"])?;
+    let subtitle = text(&post["subtitle"]).or_else(|| text(&post["description"]));
+    let (title, summary) = match is_paid(post) {
+        true => {
+            let teaser = text(&post["truncated_body_text"]);
+            let summary = [subtitle, teaser].into_iter().flatten().collect::<Vec<_>>().join("\n\n");
+            (format!("{} {}", PAID_MARK, title), summary)
+        },
+        false => {
+            let body = post["body_html"].as_str().map(body_text).filter(|body| !body.is_empty());
+            (title, body.or(subtitle).or_else(|| text(&post["truncated_body_text"])).unwrap_or_default())
+        },
+    };
+    Some(FeedEntry {
+        guid: format!("substack:{}", id),
+        title: Some(title),
+        link: text(&post["canonical_url"]),
+        summary: Some(summary).filter(|s| !s.is_empty()),
+        published: post["post_date"].as_str().and_then(|date| DateTime::parse_from_rfc3339(date).ok()).map(|date| date.with_timezone(&Utc)),
+    })
+}
+
+// The posts of a posts API response, newest first as Substack lists them. Recognized
+// without the mode by the fields only Substack's posts carry; None if the body isn't one.
+pub fn read(body: &str, extraction_mode: Option<&str>) -> Option<Vec<FeedEntry>> {
+    if !body.trim_start().starts_with('[') {
+        return None;
+    }
+    let posts: Vec<Value> = serde_json::from_str(body).ok()?;
+    let substack_like = posts.first().is_some_and(|post| post.get("canonical_url").is_some() && post.get("audience").is_some() && post.get("post_date").is_some());
+    let named = extraction_mode == Some(EXTRACTION_MODE);
+    (substack_like || (named && posts.is_empty())).then(|| posts.iter().filter_map(entry).collect())
+}
+
+// Headed "✉️ 2 Substack posts", one post per line
+pub fn preview(posts: &[FeedEntry], max_length: usize) -> String {
+    let lines = posts.iter().map(|post| post.title.as_deref().unwrap_or(&post.guid)).collect::<Vec<_>>().join("\n");
+    let heading = format!("✉️ {} Substack post{}", posts.len(), if posts.len() == 1 { "" } else { "s" });
+    format!("{}\n\n{}", heading, truncate::preview(&lines, max_length, &[WORD_BREAKS]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_publications_and_reads_free_and_paid_posts() {
+        assert!(is_publication("https://thebearcave.substack.com/feed"));
+        assert!(!is_publication("https://www.substack.com/"));
+        assert!(!is_publication("https://example.com/feed"));
+        assert_eq!(resolve("https://thebearcave.substack.com/feed").unwrap(), "https://thebearcave.substack.com/api/v1/posts?limit=50");
+
+        let posts = r#"[
+            {"id": 151234567, "title": "Problems at Acme", "subtitle": "A short report", "audience": "everyone",
+             "canonical_url": "https://thebearcave.substack.com/p/problems-at-acme", "post_date": "2026-10-14T11:02:35.162Z",
+             "body_html": "<p>First paragraph.</p><ul><li><p>A point</p></li><li>Another</li></ul><h3>Heading</h3>",
+             "truncated_body_text": "First paragraph."},
+            {"id": 151234500, "title": "Weekly Bear Cave", "subtitle": "Paid edition", "audience": "only_paid",
+             "canonical_url": "https://thebearcave.substack.com/p/weekly", "post_date": "2026-10-13T11:00:00.000Z",
+             "body_html": null, "truncated_body_text": "This week we look at..."}
+        ]"#;
+        let entries = read(posts, None).unwrap();
+        assert_eq!(entries[0].guid, "substack:151234567");
+        assert_eq!(entries[0].title.as_deref(), Some("Problems at Acme"));
+        assert_eq!(entries[0].summary.as_deref(), Some("First paragraph.\n\nA point\n\nAnother\n\nHeading"));
+        assert_eq!(entries[1].title.as_deref(), Some("[Paid] Weekly Bear Cave"));
+        assert_eq!(entries[1].summary.as_deref(), Some("Paid edition\n\nThis week we look at..."));
+        assert_eq!(entries[1].published.map(|d| d.to_rfc3339()).as_deref(), Some("2026-10-13T11:00:00+00:00"));
+        assert!(read("[]", None).is_none());
+        assert_eq!(read("[]", Some(EXTRACTION_MODE)).unwrap().len(), 0);
+        assert!(read(r#"[{"id": 1, "title": "x"}]"#, None).is_none());
+    }
+}
@@ -0,0 +1,265 @@
+use super::{scraper::SchedulerEvent, AppState};
+use actix_web::{web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::info;
+
+pub const STYLES: &[&str] = &["random", "exponential", "adaptive", "none"];
+
+// Tags are case-insensitive labels like "gov" or "fda"
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = tags.iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+// A tag as a group: an optional description and defaults that sites added with the tag
+// start from when they don't set an interval or style of their own
+#[derive(Serialize, FromRow, Clone, Debug, Default, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub description: Option<String>,
+    pub interval_secs: Option<i64>,
+    pub style: Option<String>,
+    pub site_count: i64,
+}
+
+#[derive(Deserialize)]
+pub struct NewTag {
+    name: String,
+    description: Option<String>,
+    interval_secs: Option<i64>,
+    style: Option<String>,
+}
+
+// An empty description or style and an interval_secs of 0 clear them
+#[derive(Deserialize)]
+pub struct TagUpdate {
+    description: Option<String>,
+    interval_secs: Option<i64>,
+    style: Option<String>,
+}
+
+// Interval and style for a new site from the first of its tags (alphabetically) that sets each
+pub fn defaults_for(site_tags: &[String], defined: &[Tag]) -> (Option<i64>, Option<String>) {
+    let groups: Vec<&Tag> = normalize_tags(site_tags).iter()
+        .filter_map(|name| defined.iter().find(|t| t.name == *name))
+        .collect();
+    (
+        groups.iter().find_map(|t| t.interval_secs),
+        groups.iter().find_map(|t| t.style.clone()),
+    )
+}
+
+fn validate_tag(tag: &Tag) -> Result<(), String> {
+    if tag.name.is_empty() {
+        return Err("Tag name must not be empty".into());
+    }
+    if tag.interval_secs.is_some_and(|i| i < 1) {
+        return Err("interval_secs must be at least 1".into());
+    }
+    if let Some(style) = tag.style.as_deref().filter(|s| !STYLES.contains(s)) {
+        return Err(format!("Unknown style '{}', expected one of {}", style, STYLES.join(", ")));
+    }
+    Ok(())
+}
+
+async fn find_tag(data: &AppState, name: &str) -> Result<Option<Tag>, HttpResponse> {
+    match data.storage.list_tags().await {
+        Ok(tags) => Ok(tags.into_iter().find(|t| t.name == name)),
+        Err(e) => Err(HttpResponse::InternalServerError().body(format!("Database error: {}", e))),
+    }
+}
+
+// GET /api/tags: every tag with its site count and defaults
+pub async fn list_tags(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.list_tags().await {
+        Ok(tags) => HttpResponse::Ok().json(tags),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn get_tag(data: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
+    let name = path.into_inner().trim().to_lowercase();
+    match find_tag(&data, &name).await {
+        Ok(Some(tag)) => HttpResponse::Ok().json(tag),
+        Ok(None) => HttpResponse::NotFound().body(format!("Tag '{}' not found", name)),
+        Err(response) => response,
+    }
+}
+
+// POST /api/tags: define a tag before any site carries it, or with defaults
+pub async fn add_tag(data: web::Data<AppState>, payload: web::Json<NewTag>) -> impl Responder {
+    let tag = Tag {
+        name: payload.name.trim().to_lowercase(),
+        description: payload.description.clone().filter(|d| !d.trim().is_empty()),
+        interval_secs: payload.interval_secs,
+        style: payload.style.clone(),
+        site_count: 0,
+    };
+    if let Err(e) = validate_tag(&tag) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    match data.storage.insert_tag(&tag).await {
+        Ok(true) => HttpResponse::Ok().json(tag),
+        Ok(false) => HttpResponse::Conflict().body(format!("Tag '{}' already exists", tag.name)),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// PATCH /api/tags/{tag}: change the description or defaults. Sites already carrying the
+// tag keep their settings; POST /api/tags/{tag}/settings changes those.
+pub async fn update_tag(data: web::Data<AppState>, path: web::Path<String>, payload: web::Json<TagUpdate>) -> impl Responder {
+    let name = path.into_inner().trim().to_lowercase();
+    let mut tag = match find_tag(&data, &name).await {
+        Ok(Some(tag)) => tag,
+        Ok(None) => return HttpResponse::NotFound().body(format!("Tag '{}' not found", name)),
+        Err(response) => return response,
+    };
+    if let Some(description) = &payload.description {
+        tag.description = Some(description.clone()).filter(|d| !d.trim().is_empty());
+    }
+    if let Some(interval) = payload.interval_secs {
+        tag.interval_secs = Some(interval).filter(|i| *i != 0);
+    }
+    if let Some(style) = &payload.style {
+        tag.style = Some(style.clone()).filter(|s| !s.is_empty());
+    }
+    if let Err(e) = validate_tag(&tag) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    match data.storage.update_tag(&tag).await {
+        Ok(true) => HttpResponse::Ok().json(tag),
+        Ok(false) => HttpResponse::NotFound().body(format!("Tag '{}' not found", name)),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// DELETE /api/tags/{tag}: removes the tag from every site; the sites themselves stay
+pub async fn delete_tag(data: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
+    let name = path.into_inner().trim().to_lowercase();
+    match data.storage.delete_tag(&name).await {
+        Ok(true) => {
+            info!("Deleted tag '{}'", name);
+            HttpResponse::Ok().finish()
+        },
+        Ok(false) => HttpResponse::NotFound().body(format!("Tag '{}' not found", name)),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TagSettings {
+    interval_secs: Option<i64>,
+    style: Option<String>,
+    priority: Option<i64>,
+    // 0 falls back to the global update_cache_size
+    retention_count: Option<i64>,
+    // Only report which sites would change
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, FromRow, Clone)]
+pub struct SiteSettings {
+    pub interval_secs: i64,
+    pub style: String,
+    pub priority: i64,
+    pub retention_count: Option<i64>,
+}
+
+#[derive(FromRow)]
+pub struct TaggedSite {
+    pub id: i64,
+    pub url: String,
+    #[sqlx(flatten)]
+    pub settings: SiteSettings,
+}
+
+#[derive(Serialize)]
+pub struct AffectedSite {
+    pub id: i64,
+    pub url: String,
+    pub before: SiteSettings,
+    pub after: SiteSettings,
+}
+
+impl TagSettings {
+    pub fn apply(&self, current: &SiteSettings) -> SiteSettings {
+        SiteSettings {
+            interval_secs: self.interval_secs.unwrap_or(current.interval_secs),
+            style: self.style.clone().unwrap_or_else(|| current.style.clone()),
+            priority: self.priority.unwrap_or(current.priority),
+            retention_count: match self.retention_count {
+                Some(0) => None,
+                Some(n) => Some(n),
+                None => current.retention_count,
+            },
+        }
+    }
+}
+
+// POST /api/tags/{tag}/settings: retune every site carrying a tag in one transaction
+pub async fn update_tag_settings(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<TagSettings>,
+) -> impl Responder {
+    let tag = path.into_inner().trim().to_lowercase();
+    if payload.interval_secs.is_some_and(|i| i < 1) {
+        return HttpResponse::BadRequest().body("interval_secs must be at least 1");
+    }
+    if payload.retention_count.is_some_and(|n| n < 0) {
+        return HttpResponse::BadRequest().body("retention_count must not be negative");
+    }
+
+    let result = data.storage.retune_tag(&tag, &payload).await;
+    let affected = match result {
+        Ok(affected) => affected,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    if affected.is_empty() {
+        return HttpResponse::NotFound().body(format!("No sites tagged '{}'", tag));
+    }
+
+    if !payload.dry_run {
+        for site in &affected {
+            let _ = data.scheduler.send(SchedulerEvent::SiteUpdated(site.id));
+        }
+        info!("Updated settings of {} site(s) tagged '{}'", affected.len(), tag);
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "tag": tag,
+        "dry_run": payload.dry_run,
+        "affected": affected,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tags() {
+        let tags = vec![" FDA ".to_string(), "gov".into(), "fda".into(), "".into()];
+        assert_eq!(normalize_tags(&tags), vec!["fda".to_string(), "gov".into()]);
+    }
+
+    #[test]
+    fn new_sites_take_defaults_from_their_tags() {
+        let defined = vec![
+            Tag { name: "fda".into(), interval_secs: Some(300), ..Tag::default() },
+            Tag { name: "gov".into(), interval_secs: Some(900), style: Some("none".into()), ..Tag::default() },
+        ];
+        let tags = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(defaults_for(&tags(&["GOV", "fda"]), &defined), (Some(300), Some("none".into())));
+        assert_eq!(defaults_for(&tags(&["sec"]), &defined), (None, None));
+    }
+}
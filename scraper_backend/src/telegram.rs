@@ -0,0 +1,318 @@
+use super::storage::SharedStorage;
+use super::{scraper, AppConfig, AppState, StreamEvent, UpdateMessage};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio::time::{sleep, Duration};
+use tracing::{error, warn};
+
+// Longest preview sent; Telegram itself allows 4096 characters per message
+const PREVIEW_CHARS: usize = 500;
+
+#[derive(Serialize, FromRow, Clone)]
+pub struct TelegramChat {
+    pub id: i64,
+    pub name: String,
+    // Never returned: anyone holding it can post as the bot
+    #[serde(skip_serializing)]
+    pub bot_token: String,
+    // Numeric id, or @username for public channels
+    pub chat_id: String,
+    pub created_at: DateTime<Utc>,
+    // Sites opted in to this chat; kept in telegram_sites
+    #[sqlx(skip)]
+    pub site_ids: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct NewTelegramChat {
+    name: Option<String>,
+    bot_token: String,
+    chat_id: String,
+    #[serde(default)]
+    site_ids: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct TelegramSites {
+    site_ids: Vec<i64>,
+}
+
+// Characters MarkdownV2 reserves outside code and links
+const RESERVED: &[char] = &['_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!', '\\'];
+
+pub fn escape_markdown_v2(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if RESERVED.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Inside the (...) of a link only ')' and '\' are special
+fn escape_link_target(url: &str) -> String {
+    url.replace('\\', "\\\\").replace(')', "\\)")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    match super::truncate::cut(text, max_chars, &[]) {
+        Some(start) => format!("{}…", start.trim_end()),
+        None => text.to_string(),
+    }
+}
+
+// Title (or URL) linking to the page, the site URL for feed entries, timestamp, matched
+// alert rules and the preview
+fn format_message(msg: &UpdateMessage) -> String {
+    let link = msg.link.as_deref().unwrap_or(&msg.url);
+    let mut text = format!(
+        "*Change detected*\n[{}]({})",
+        escape_markdown_v2(msg.title.as_deref().unwrap_or(&msg.url)),
+        escape_link_target(link),
+    );
+    if msg.title.is_some() {
+        text.push_str(&format!("\n{}", escape_markdown_v2(&msg.url)));
+    }
+    text.push_str(&format!("\n_{}_", escape_markdown_v2(&msg.timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string())));
+    if let Some(count) = msg.collapsed_updates {
+        text.push_str(&format!("\n_{}_", escape_markdown_v2(&format!("Latest of {} changes in a burst", count))));
+    }
+    if !msg.matched_rules.is_empty() {
+        let names: Vec<&str> = msg.matched_rules.iter().map(|r| r.name.as_str()).collect();
+        text.push_str(&format!("\n*Alert:* {}", escape_markdown_v2(&names.join(", "))));
+    }
+    let preview = truncate(&msg.content_preview, PREVIEW_CHARS);
+    if !preview.is_empty() {
+        text.push_str(&format!("\n\n{}", escape_markdown_v2(&preview)));
+    }
+    text
+}
+
+// Push every detected change to the chats its site is opted in to
+pub async fn run_telegram_dispatcher(storage: SharedStorage, mut rx: Receiver<StreamEvent>, config: AppConfig) {
+    let client = scraper::build_http_client();
+
+    loop {
+        let msg = match rx.recv().await {
+            Ok(StreamEvent::Update(msg)) => msg,
+            Ok(_) => continue,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Telegram dispatcher fell behind, {} update(s) not sent", skipped);
+                continue;
+            },
+            Err(RecvError::Closed) => return,
+        };
+
+        let chats = match storage.site_telegram_chats(msg.site_id).await {
+            Ok(chats) => chats,
+            Err(e) => {
+                warn!("Could not load Telegram chats: {}", e);
+                continue;
+            }
+        };
+        if chats.is_empty() {
+            continue;
+        }
+
+        let text = format_message(&msg);
+        for chat in chats {
+            let client = client.clone();
+            let text = text.clone();
+            let config = config.clone();
+            let label = format!("update {}", msg.update_id);
+            tokio::spawn(async move {
+                let _ = send(&client, &chat, &text, &label, &config).await;
+            });
+        }
+    }
+}
+
+// sendMessage, retried like webhook deliveries; Telegram's retry_after wins when it rate-limits us
+async fn send(client: &reqwest::Client, chat: &TelegramChat, text: &str, label: &str, config: &AppConfig) -> Result<(), String> {
+    let url = format!("{}/bot{}/sendMessage", config.telegram_api_url.trim_end_matches('/'), chat.bot_token);
+    let body = serde_json::json!({
+        "chat_id": chat.chat_id,
+        "text": text,
+        "parse_mode": "MarkdownV2",
+        "disable_web_page_preview": true,
+    });
+    let attempts = config.webhook_max_attempts.max(1);
+
+    for attempt in 1..=attempts {
+        let mut delay_ms = config.webhook_retry_base_ms.max(0) * 2i64.pow((attempt - 1).min(10) as u32);
+        // The token is part of the URL, so errors are reported without it
+        let error = match client.post(&url).json(&body).send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) => {
+                let status = resp.status();
+                let reply: serde_json::Value = resp.json().await.unwrap_or_default();
+                if let Some(retry_after) = reply["parameters"]["retry_after"].as_i64() {
+                    delay_ms = retry_after * 1000;
+                }
+                let description = reply["description"].as_str().unwrap_or("no description");
+                // Bad tokens, chats and formatting won't fix themselves
+                if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    error!("Telegram chat {} rejected {}: HTTP {} {}", chat.id, label, status, description);
+                    return Err(format!("HTTP {}: {}", status, description));
+                }
+                format!("HTTP {}: {}", status, description)
+            },
+            Err(e) => e.without_url().to_string(),
+        };
+
+        if attempt == attempts {
+            error!("Telegram chat {} gave up on {} after {} attempt(s): {}", chat.id, label, attempts, error);
+            return Err(error);
+        }
+        warn!("Telegram chat {} delivery of {} failed ({}), retrying in {}ms", chat.id, label, error, delay_ms);
+        sleep(Duration::from_millis(delay_ms as u64)).await;
+    }
+    Ok(())
+}
+
+// Every listed site must exist
+async fn check_sites(data: &AppState, site_ids: &[i64]) -> Result<(), HttpResponse> {
+    for &site_id in site_ids {
+        match data.storage.get_site(site_id).await {
+            Ok(Some(_)) => {},
+            Ok(None) => return Err(HttpResponse::BadRequest().body(format!("Site with ID {} not found", site_id))),
+            Err(e) => return Err(HttpResponse::InternalServerError().body(format!("Database error: {}", e))),
+        }
+    }
+    Ok(())
+}
+
+pub async fn list_chats(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.list_telegram_chats().await {
+        Ok(chats) => HttpResponse::Ok().json(chats),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn add_chat(data: web::Data<AppState>, payload: web::Json<NewTelegramChat>) -> impl Responder {
+    let bot_token = payload.bot_token.trim();
+    // Tokens look like 123456:ABC-DEF...
+    if !bot_token.split_once(':').is_some_and(|(id, secret)| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) && !secret.is_empty()) {
+        return HttpResponse::BadRequest().body("bot_token must look like <bot id>:<secret>, as issued by @BotFather");
+    }
+    let chat_id = payload.chat_id.trim();
+    if chat_id.is_empty() {
+        return HttpResponse::BadRequest().body("chat_id must not be empty");
+    }
+    if let Err(response) = check_sites(&data, &payload.site_ids).await {
+        return response;
+    }
+
+    let mut site_ids = payload.site_ids.clone();
+    site_ids.sort_unstable();
+    site_ids.dedup();
+    let mut chat = TelegramChat {
+        id: 0,
+        name: payload.name.as_deref().map(str::trim).filter(|n| !n.is_empty()).unwrap_or(chat_id).to_string(),
+        bot_token: bot_token.to_string(),
+        chat_id: chat_id.to_string(),
+        created_at: Utc::now(),
+        site_ids,
+    };
+
+    match data.storage.insert_telegram_chat(&chat).await {
+        Ok(id) => {
+            chat.id = id;
+            HttpResponse::Ok().json(chat)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// PUT /api/telegram/chats/{id}/sites: replace the sites opted in to a chat
+pub async fn set_chat_sites(data: web::Data<AppState>, path: web::Path<i64>, payload: web::Json<TelegramSites>) -> impl Responder {
+    let id = path.into_inner();
+    if let Err(response) = check_sites(&data, &payload.site_ids).await {
+        return response;
+    }
+    let mut site_ids = payload.site_ids.clone();
+    site_ids.sort_unstable();
+    site_ids.dedup();
+    match data.storage.set_telegram_sites(id, &site_ids).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("Telegram chat with ID {} not found", id)),
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({ "id": id, "site_ids": site_ids })),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn delete_chat(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    match data.storage.delete_telegram_chat(id).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("Telegram chat with ID {} not found", id)),
+        Ok(true) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// POST /api/telegram/chats/{id}/test: send a test message now and report whether Telegram took it
+pub async fn test_chat(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    let chats = match data.storage.list_telegram_chats().await {
+        Ok(chats) => chats,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let Some(chat) = chats.into_iter().find(|c| c.id == id) else {
+        return HttpResponse::NotFound().body(format!("Telegram chat with ID {} not found", id));
+    };
+
+    let text = format!("*\\[TEST\\]* {}", escape_markdown_v2("Notifications for this chat are working. No action needed."));
+    let result = send(&scraper::build_http_client(), &chat, &text, "test message", &data.config).await;
+    HttpResponse::Ok().json(serde_json::json!({
+        "id": id,
+        "ok": result.is_ok(),
+        "error": result.err(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_changes_as_escaped_markdown_v2() {
+        assert_eq!(escape_markdown_v2("Q3 (est.) +5%_x"), "Q3 \\(est\\.\\) \\+5%\\_x");
+        assert_eq!(escape_link_target("https://x.example/a_(b)"), "https://x.example/a_(b\\)");
+        assert_eq!(truncate("  abcdef ", 3), "abc…");
+        assert_eq!(truncate("abc", 3), "abc");
+
+        let msg = UpdateMessage {
+            update_id: 7,
+            site_id: 1,
+            url: "https://www.sec.gov/news/pressreleases.rss".into(),
+            timestamp: "2024-05-01T12:30:00Z".parse().unwrap(),
+            diff_hash: String::new(),
+            content_preview: "SEC charges X-Corp. ".repeat(40),
+            has_full_content: true,
+            title: Some("SEC charges [X] Corp.".into()),
+            link: Some("https://www.sec.gov/x".into()),
+            matched_rules: vec![crate::alerts::MatchedRule { id: 1, name: "SEC".into() }],
+            published: None,
+            detection_latency_secs: None,
+            collapsed_updates: None,
+            metadata: None,
+            wire_source: None,
+            has_screenshot: false,
+            documents: Vec::new(),
+        };
+        let text = format_message(&msg);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "*Change detected*");
+        assert_eq!(lines[1], "[SEC charges \\[X\\] Corp\\.](https://www.sec.gov/x)");
+        assert_eq!(lines[2], "https://www\\.sec\\.gov/news/pressreleases\\.rss");
+        assert_eq!(lines[3], "_2024\\-05\\-01 12:30:00 UTC_");
+        assert_eq!(lines[4], "*Alert:* SEC");
+        assert!(lines[6].ends_with('…'));
+        assert!(lines[6].chars().count() < PREVIEW_CHARS * 2);
+    }
+}
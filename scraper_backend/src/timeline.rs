@@ -0,0 +1,114 @@
+use super::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+const DEFAULT_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    CheckStarted,
+    CheckFinished,
+    // A due check that didn't run: the previous fetch was still running, or the site is snoozed
+    CheckSkipped,
+    // A failed check of an exponential site pushed its next check back
+    BackoffApplied,
+    // POST /api/sites/{id}/burst started or replaced a burst
+    BurstStarted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEvent {
+    pub id: u64,
+    pub at: DateTime<Utc>,
+    pub site_id: i64,
+    pub kind: EventKind,
+    pub detail: String,
+}
+
+// What the scheduler did and why, newest last, in memory only. Once `capacity` events
+// are held the oldest are dropped, so the window covered depends on how busy it is.
+pub struct Timeline {
+    capacity: usize,
+    events: Mutex<VecDeque<TimelineEvent>>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Timeline {
+    pub fn new(capacity: usize) -> Self {
+        Timeline { capacity: capacity.max(1), events: Mutex::new(VecDeque::new()) }
+    }
+
+    pub fn record(&self, at: DateTime<Utc>, site_id: i64, kind: EventKind, detail: impl Into<String>) {
+        let mut events = self.events.lock().unwrap();
+        let id = events.back().map_or(1, |e| e.id + 1);
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(TimelineEvent { id, at, site_id, kind, detail: detail.into() });
+    }
+
+    // Oldest first, so a window reads in the order things happened
+    fn query(&self, filter: &EventQuery) -> Vec<TimelineEvent> {
+        let events = self.events.lock().unwrap();
+        let matching: Vec<&TimelineEvent> = events
+            .iter()
+            .filter(|e| filter.site_id.is_none_or(|id| e.site_id == id))
+            .filter(|e| filter.kind.is_none_or(|kind| e.kind == kind))
+            .filter(|e| filter.since.is_none_or(|since| e.at >= since))
+            .filter(|e| filter.until.is_none_or(|until| e.at < until))
+            .collect();
+        // Keep the newest `limit` of them
+        let limit = filter.limit.unwrap_or(500).max(1) as usize;
+        matching[matching.len().saturating_sub(limit)..].iter().map(|e| (*e).clone()).collect()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EventQuery {
+    site_id: Option<i64>,
+    kind: Option<EventKind>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    limit: Option<i64>,
+}
+
+// GET /api/admin/events?site_id=&kind=&since=&until=&limit=: recorded scheduler events
+pub async fn list_events(data: web::Data<AppState>, query: web::Query<EventQuery>) -> impl Responder {
+    HttpResponse::Ok().json(data.timeline.query(&query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_newest_events_and_filters_a_window() {
+        let timeline = Timeline::new(3);
+        let start = Utc::now();
+        let at = |secs| start + chrono::Duration::seconds(secs);
+        timeline.record(at(0), 1, EventKind::CheckStarted, "scheduled");
+        timeline.record(at(1), 1, EventKind::CheckFinished, "ok");
+        timeline.record(at(2), 2, EventKind::CheckSkipped, "snoozed");
+        timeline.record(at(3), 1, EventKind::BackoffApplied, "retry in 120s");
+
+        let all = EventQuery { site_id: None, kind: None, since: None, until: None, limit: None };
+        let ids: Vec<u64> = timeline.query(&all).iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![2, 3, 4]);
+
+        let site = EventQuery { site_id: Some(1), ..all };
+        assert_eq!(timeline.query(&site).len(), 2);
+        let window = EventQuery { since: Some(at(1)), until: Some(at(3)), site_id: None, ..site };
+        assert_eq!(timeline.query(&window).iter().map(|e| e.kind).collect::<Vec<_>>(), vec![EventKind::CheckFinished, EventKind::CheckSkipped]);
+        let newest = EventQuery { limit: Some(1), since: None, until: None, ..window };
+        assert_eq!(timeline.query(&newest)[0].id, 4);
+    }
+}
@@ -0,0 +1,51 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+// Characters a shortened text may end after
+pub const WORD_BREAKS: &[char] = &[' ', '.', ',', ';', ':', '!', '?', '\n', '\r'];
+pub const SENTENCE_BREAKS: &[char] = &['.', '!', '?', '\n', '\r'];
+
+// The start of `text` within max_length graphemes, so a flag, an emoji with a skin tone or
+// a letter with combining accents is never split. It ends after the last grapheme holding
+// one of the first set of `breaks` that occurs, trying the sets in order, or after all
+// max_length graphemes when none does. None when the text already fits.
+pub fn cut<'a>(text: &'a str, max_length: usize, breaks: &[&[char]]) -> Option<&'a str> {
+    let mut graphemes = text.grapheme_indices(true);
+    let mut end = 0;
+    let mut last_breaks = vec![None; breaks.len()];
+    for (at, grapheme) in graphemes.by_ref().take(max_length) {
+        end = at + grapheme.len();
+        for (last, chars) in last_breaks.iter_mut().zip(breaks) {
+            if grapheme.contains(*chars) {
+                *last = Some(end);
+            }
+        }
+    }
+    graphemes.next()?;
+    Some(&text[..last_breaks.into_iter().flatten().next().unwrap_or(end)])
+}
+
+// `text` cut as above with "..." after it, or the whole text when it fits
+pub fn preview(text: &str, max_length: usize, breaks: &[&[char]]) -> String {
+    match cut(text, max_length, breaks) {
+        Some(start) => format!("{}...", start),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cuts_on_grapheme_and_word_boundaries() {
+        assert_eq!(cut("short", 10, &[WORD_BREAKS]), None);
+        assert_eq!(cut("Prix à Zürich gelöscht", 16, &[WORD_BREAKS]), Some("Prix à Zürich "));
+        assert_eq!(cut("Aufträge über 1.000 €", 12, &[SENTENCE_BREAKS, WORD_BREAKS]), Some("Aufträge "));
+        assert_eq!(cut("First one. Then more text", 20, &[SENTENCE_BREAKS, WORD_BREAKS]), Some("First one."));
+        // Never inside a multi-byte character, a ZWJ sequence or a flag
+        assert_eq!(cut("日本経済新聞社の記事", 4, &[WORD_BREAKS]), Some("日本経済"));
+        assert_eq!(cut("👩‍👩‍👧‍👦👍🏽🇯🇵x", 3, &[]), Some("👩‍👩‍👧‍👦👍🏽🇯🇵"));
+        assert_eq!(cut("e\u{301}e\u{301}e\u{301}", 2, &[]), Some("e\u{301}e\u{301}"));
+        assert_eq!(preview("Große Überraschung", 5, &[WORD_BREAKS]), "Große...");
+    }
+}
@@ -0,0 +1,99 @@
+use super::storage::SharedStorage;
+use super::{AppConfig, Site, StatusEvent, StreamEvent};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use tokio::sync::broadcast::Sender;
+use tokio::time::{sleep, Duration};
+use tracing::{info, warn};
+
+// Flags sites whose last successful fetch is older than watchdog_max_age_multiple × their
+// interval. Catches scheduler bugs, permanent blocks and lost in-memory state, none of which
+// show up as a single failed check. Sites with max_quiet_hours are also flagged STALE once
+// they go that long without a change: a source that died while still answering.
+pub async fn run_watchdog(storage: SharedStorage, tx: Sender<StreamEvent>, config: AppConfig) {
+    let interval = Duration::from_secs(config.watchdog_interval_secs.max(1) as u64);
+
+    // Sites that have never succeeded or changed are measured from when the watchdog started
+    let started = Utc::now();
+    let mut flagged: HashSet<i64> = HashSet::new();
+    let mut quiet: HashSet<i64> = HashSet::new();
+
+    loop {
+        sleep(interval).await;
+
+        let sites = match storage.list_sites().await {
+            Ok(sites) => sites,
+            Err(e) => {
+                warn!("Watchdog could not load sites: {}", e);
+                continue;
+            }
+        };
+
+        let now = Utc::now();
+        flagged.retain(|id| sites.iter().any(|s| s.id == *id));
+        quiet.retain(|id| sites.iter().any(|s| s.id == *id));
+
+        for site in &sites {
+            if site.snoozed_until.is_some_and(|until| until > now) {
+                continue;
+            }
+
+            let overdue = config.watchdog_max_age_multiple > 0 && is_overdue(site, started, now, &config);
+            if overdue && flagged.insert(site.id) {
+                let _ = storage.mark_overdue(site.id).await;
+
+                let message = match site.last_success {
+                    Some(ts) => format!("No successful fetch of {} since {}", site.url, ts.format("%Y-%m-%d %H:%M:%S UTC")),
+                    None => format!("No successful fetch of {} since monitoring started", site.url),
+                };
+                warn!("Watchdog: {}", message);
+                let _ = tx.send(StreamEvent::Status(StatusEvent {
+                    kind: "watchdog".into(),
+                    site_id: Some(site.id),
+                    level: "warning".into(),
+                    message,
+                    timestamp: now,
+                }));
+            } else if !overdue && flagged.remove(&site.id) {
+                info!("Watchdog: {} is fetching successfully again", site.url);
+            }
+
+            if !is_quiet(site, started, now) {
+                if quiet.remove(&site.id) {
+                    info!("Watchdog: {} is changing again", site.url);
+                }
+                continue;
+            }
+            // Marked again after failed fetches have set another status in between, but
+            // reported once per quiet spell
+            if storage.mark_stale(site.id).await.unwrap_or(false) && quiet.insert(site.id) {
+                let message = match site.last_updated {
+                    Some(ts) => format!("No change to {} since {}, longer than its {} quiet hours", site.url, ts.format("%Y-%m-%d %H:%M:%S UTC"), site.max_quiet_hours.unwrap_or_default()),
+                    None => format!("No change to {} since monitoring started, longer than its {} quiet hours", site.url, site.max_quiet_hours.unwrap_or_default()),
+                };
+                warn!("Watchdog: {}", message);
+                let _ = tx.send(StreamEvent::Status(StatusEvent {
+                    kind: "stale".into(),
+                    site_id: Some(site.id),
+                    level: "warning".into(),
+                    message,
+                    timestamp: now,
+                }));
+            }
+        }
+    }
+}
+
+fn is_quiet(site: &Site, started: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    let Some(hours) = site.max_quiet_hours.filter(|hours| *hours > 0) else {
+        return false;
+    };
+    let since = site.last_updated.unwrap_or(started);
+    (now - since).num_seconds() > hours * 3600
+}
+
+fn is_overdue(site: &Site, started: DateTime<Utc>, now: DateTime<Utc>, config: &AppConfig) -> bool {
+    let max_age_secs = (site.interval_secs * config.watchdog_max_age_multiple).max(config.watchdog_min_age_secs);
+    let since = site.last_success.unwrap_or(started);
+    (now - since).num_seconds() > max_age_secs
+}
@@ -0,0 +1,284 @@
+use super::storage::{SharedStorage, Storage};
+use super::{scraper, AppConfig, AppState, StatusEvent, StreamEvent};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::FromRow;
+use tokio::sync::broadcast::{error::RecvError, Receiver, Sender};
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+// Set on synthetic test deliveries so receivers can drop them
+pub const CANARY_HEADER: &str = "X-Webhook-Canary";
+
+#[derive(Serialize, FromRow, Clone)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    // Only returned once, when the webhook is created
+    #[serde(skip_serializing)]
+    pub secret: String,
+    // None delivers changes from every site
+    pub site_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    // Result of the last test delivery; None until the first canary ran
+    pub canary_checked_at: Option<DateTime<Utc>>,
+    pub canary_ok: Option<bool>,
+    pub canary_error: Option<String>,
+}
+
+// Body of a test delivery
+#[derive(Serialize)]
+struct CanaryMessage {
+    canary: bool,
+    title: String,
+    content_preview: String,
+    timestamp: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct NewWebhook {
+    url: String,
+    site_id: Option<i64>,
+    secret: Option<String>,
+}
+
+// Deliver every detected change to the matching webhooks
+pub async fn run_webhook_dispatcher(storage: SharedStorage, mut rx: Receiver<StreamEvent>, config: AppConfig) {
+    let client = scraper::build_http_client();
+
+    loop {
+        let msg = match rx.recv().await {
+            Ok(StreamEvent::Update(msg)) => msg,
+            Ok(_) => continue,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Webhook dispatcher fell behind, {} update(s) not delivered", skipped);
+                continue;
+            },
+            Err(RecvError::Closed) => return,
+        };
+
+        let hooks = match storage.site_webhooks(msg.site_id).await {
+            Ok(hooks) => hooks,
+            Err(e) => {
+                warn!("Could not load webhooks: {}", e);
+                continue;
+            }
+        };
+
+        // One task per delivery so a slow endpoint doesn't hold up the others
+        for hook in hooks {
+            let client = client.clone();
+            let msg = msg.clone();
+            let config = config.clone();
+            let body = match serde_json::to_string(&msg) {
+                Ok(body) => body,
+                Err(_) => continue,
+            };
+            tokio::spawn(async move {
+                let label = format!("update {}", msg.update_id);
+                let _ = deliver(&client, &hook, body, &label, false, &config).await;
+            });
+        }
+    }
+}
+
+// POST a JSON body with exponential backoff between attempts; the error is the last failure
+async fn deliver(client: &reqwest::Client, hook: &Webhook, body: String, label: &str, canary: bool, config: &AppConfig) -> Result<(), String> {
+    let signature = format!("sha256={}", sign(&hook.secret, body.as_bytes()));
+    let attempts = config.webhook_max_attempts.max(1);
+
+    for attempt in 1..=attempts {
+        let mut request = client.post(&hook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, &signature);
+        if canary {
+            request = request.header(CANARY_HEADER, "1");
+        }
+        let result = request.body(body.clone()).send().await;
+
+        let error = match result {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) => format!("HTTP {}", resp.status()),
+            Err(e) => e.to_string(),
+        };
+
+        if attempt == attempts {
+            error!("Webhook {} gave up on {} after {} attempt(s): {}", hook.id, label, attempts, error);
+            return Err(error);
+        }
+        let delay_ms = config.webhook_retry_base_ms.max(0) * 2i64.pow((attempt - 1).min(10) as u32);
+        warn!("Webhook {} delivery of {} failed ({}), retrying in {}ms", hook.id, label, error, delay_ms);
+        sleep(Duration::from_millis(delay_ms as u64)).await;
+    }
+    Ok(())
+}
+
+// Send a clearly labeled test alert to every webhook on a fixed cadence, so a broken
+// endpoint or expired credential shows up before a real change fails to arrive
+pub async fn run_webhook_canary(storage: SharedStorage, tx: Sender<StreamEvent>, config: AppConfig) {
+    if config.webhook_canary_interval_secs <= 0 {
+        return;
+    }
+    let client = scraper::build_http_client();
+    let interval = Duration::from_secs(config.webhook_canary_interval_secs as u64);
+    info!("Webhook canary enabled, test delivery every {}s", interval.as_secs());
+
+    loop {
+        sleep(interval).await;
+        let hooks = match storage.list_webhooks().await {
+            Ok(hooks) => hooks,
+            Err(e) => {
+                warn!("Webhook canary could not load webhooks: {}", e);
+                continue;
+            }
+        };
+
+        for hook in hooks {
+            let client = client.clone();
+            let storage = storage.clone();
+            let tx = tx.clone();
+            let config = config.clone();
+            tokio::spawn(async move {
+                let result = send_canary(&client, &hook, &config).await;
+                record_canary(storage.as_ref(), &tx, &hook, result).await;
+            });
+        }
+    }
+}
+
+async fn send_canary(client: &reqwest::Client, hook: &Webhook, config: &AppConfig) -> Result<(), String> {
+    let msg = CanaryMessage {
+        canary: true,
+        title: "[TEST] Notification canary".into(),
+        content_preview: "Scheduled test alert to verify this webhook still receives notifications. No action needed.".into(),
+        timestamp: Utc::now(),
+    };
+    let body = serde_json::to_string(&msg).map_err(|e| e.to_string())?;
+    deliver(client, hook, body, "canary", true, config).await
+}
+
+// Store the result and announce failures (and the recovery) on the stream
+async fn record_canary(storage: &dyn Storage, tx: &Sender<StreamEvent>, hook: &Webhook, result: Result<(), String>) {
+    let now = Utc::now();
+    let ok = result.is_ok();
+    let _ = storage.record_canary(hook.id, now, result.as_ref().err().map(String::as_str)).await;
+
+    let message = match (&result, hook.canary_ok) {
+        (Err(e), Some(true) | None) => format!("Webhook {} ({}) failed its test delivery: {}", hook.id, hook.url, e),
+        (Ok(()), Some(false)) => format!("Webhook {} ({}) is receiving test deliveries again", hook.id, hook.url),
+        _ => return,
+    };
+    if ok { info!("{}", message) } else { warn!("{}", message) }
+    let _ = tx.send(StreamEvent::Status(StatusEvent {
+        kind: "webhook_canary".into(),
+        site_id: hook.site_id,
+        level: if ok { "info" } else { "warning" }.into(),
+        message,
+        timestamp: now,
+    }));
+}
+
+// Hex HMAC-SHA256 of the request body, keyed with the webhook secret
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+pub async fn list_webhooks(data: web::Data<AppState>) -> impl Responder {
+    match data.storage.list_webhooks().await {
+        Ok(hooks) => HttpResponse::Ok().json(hooks),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn add_webhook(data: web::Data<AppState>, payload: web::Json<NewWebhook>) -> impl Responder {
+    match reqwest::Url::parse(&payload.url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {},
+        _ => return HttpResponse::BadRequest().body(format!("Invalid webhook URL '{}'", payload.url)),
+    }
+
+    if let Some(site_id) = payload.site_id {
+        match data.storage.get_site(site_id).await {
+            Ok(Some(_)) => {},
+            Ok(None) => return HttpResponse::BadRequest().body(format!("Site with ID {} not found", site_id)),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+        }
+    }
+
+    let secret = match payload.secret.as_deref().map(str::trim) {
+        Some(secret) if !secret.is_empty() => secret.to_string(),
+        _ => thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect(),
+    };
+    let hook = Webhook {
+        id: 0,
+        url: payload.url.clone(),
+        secret,
+        site_id: payload.site_id,
+        created_at: Utc::now(),
+        canary_checked_at: None,
+        canary_ok: None,
+        canary_error: None,
+    };
+
+    match data.storage.insert_webhook(&hook).await {
+        Ok(id) => HttpResponse::Ok().json(serde_json::json!({
+            "id": id,
+            "url": hook.url,
+            "site_id": hook.site_id,
+            "created_at": hook.created_at,
+            "secret": hook.secret,
+        })),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+pub async fn delete_webhook(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    match data.storage.delete_webhook(id).await {
+        Ok(false) => HttpResponse::NotFound().body(format!("Webhook with ID {} not found", id)),
+        Ok(true) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+// POST /api/webhooks/{id}/test: send a canary now and report whether it arrived
+pub async fn test_webhook(data: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+    let hook = match data.storage.get_webhook(id).await {
+        Ok(hook) => hook,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    };
+    let Some(hook) = hook else {
+        return HttpResponse::NotFound().body(format!("Webhook with ID {} not found", id));
+    };
+
+    let client = scraper::build_http_client();
+    let result = send_canary(&client, &hook, &data.config).await;
+    let body = serde_json::json!({
+        "id": id,
+        "ok": result.is_ok(),
+        "error": result.as_ref().err(),
+    });
+    record_canary(data.storage.as_ref(), &data.tx_updates, &hook, result).await;
+    HttpResponse::Ok().json(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
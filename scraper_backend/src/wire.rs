@@ -0,0 +1,208 @@
+use super::readability::{self, Article};
+use super::scraper;
+use super::{AppConfig, Site};
+use ::scraper::{ElementRef, Html, Selector};
+use regex::Regex;
+use reqwest::Url;
+use std::sync::OnceLock;
+use tracing::warn;
+
+// PR Newswire's link back to the release, closing syndicated copies
+const SYNDICATION_NOTE: &str = "View original content";
+
+// A press-release wire: where its releases live, the mark its datelines carry and the
+// element its release pages keep the story in
+pub struct Wire {
+    // Stored as the update's wire_source
+    pub key: &'static str,
+    pub name: &'static str,
+    host: &'static str,
+    release_path: &'static str,
+    marker: &'static str,
+    body_selector: &'static str,
+}
+
+pub const WIRES: &[Wire] = &[
+    Wire {
+        key: "businesswire",
+        name: "Business Wire",
+        host: "businesswire.com",
+        release_path: "/news/home/",
+        marker: "(BUSINESS WIRE)",
+        body_selector: ".bw-release-story, [itemprop=articleBody]",
+    },
+    Wire {
+        key: "prnewswire",
+        name: "PR Newswire",
+        host: "prnewswire.com",
+        release_path: "/news-releases/",
+        marker: "/PRNewswire",
+        body_selector: ".release-body, [itemprop=articleBody]",
+    },
+    Wire {
+        key: "globenewswire",
+        name: "GlobeNewswire",
+        host: "globenewswire.com",
+        release_path: "/news-release/",
+        marker: "(GLOBE NEWSWIRE)",
+        body_selector: "#main-body-container, .main-body-container, [itemprop=articleBody]",
+    },
+];
+
+// "NEW YORK--(BUSINESS WIRE)--", "CHICAGO, Oct. 14, 2026 /PRNewswire/ --" and
+// "OSLO, Norway, Oct. 14, 2026 (GLOBE NEWSWIRE) --" open the first paragraph
+fn dateline() -> &'static Regex {
+    static DATELINE: OnceLock<Regex> = OnceLock::new();
+    DATELINE.get_or_init(|| Regex::new(r"^(.{2,160}?(?:\(BUSINESS WIRE\)|/PRNewswire(?:-[A-Za-z]+)?/|\(GLOBE NEWSWIRE\)))\s*(?:--|–|—)\s*").unwrap())
+}
+
+// The wire a URL is on
+pub fn from_url(url: &str) -> Option<&'static Wire> {
+    let host = Url::parse(url).ok()?.host_str()?.to_lowercase();
+    WIRES.iter().find(|wire| host == wire.host || host.ends_with(&format!(".{}", wire.host)))
+}
+
+fn is_release(wire: &Wire, url: &Url) -> bool {
+    from_url(url.as_str()).is_some_and(|w| w.key == wire.key) && url.path().starts_with(wire.release_path)
+}
+
+// A release a page carries: hosted on the wire itself, or syndicated from it, in which case
+// `canonical` is the wire's own copy when the page links to it
+pub struct Syndication {
+    pub wire: &'static Wire,
+    pub canonical: Option<String>,
+}
+
+fn text(element: ElementRef) -> String {
+    element.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn first_paragraphs(document: &Html) -> impl Iterator<Item = String> + '_ {
+    static PARAGRAPHS: OnceLock<Selector> = OnceLock::new();
+    document.select(PARAGRAPHS.get_or_init(|| Selector::parse("p").unwrap())).map(text)
+}
+
+// Whether a page is a wire release; IR pages syndicating one keep the wire's dateline and
+// usually name the wire's copy as canonical or link to it
+pub fn detect(body: &str, page_url: &str) -> Option<Syndication> {
+    if let Some(wire) = from_url(page_url) {
+        return Some(Syndication { wire, canonical: None });
+    }
+    let base = Url::parse(page_url).ok()?;
+    let document = Html::parse_document(body);
+    let links = Selector::parse(r#"link[rel~="canonical"][href], meta[property="og:url"][content]"#).unwrap();
+    let declared = document.select(&links)
+        .filter_map(|e| e.value().attr("href").or_else(|| e.value().attr("content")))
+        .filter_map(|href| base.join(href).ok())
+        .find_map(|url| WIRES.iter().find(|wire| is_release(wire, &url)).map(|wire| (wire, url)));
+    if let Some((wire, url)) = declared {
+        return Some(Syndication { wire, canonical: Some(url.to_string()) });
+    }
+    let opening = first_paragraphs(&document).take(20).find_map(|p| dateline().captures(&p).map(|c| c[1].to_string()))?;
+    let wire = WIRES.iter().find(|wire| opening.contains(wire.marker))?;
+    let anchors = Selector::parse("a[href]").unwrap();
+    let canonical = document.select(&anchors)
+        .filter_map(|a| base.join(a.value().attr("href")?).ok())
+        .find(|url| is_release(wire, url))
+        .map(String::from);
+    Some(Syndication { wire, canonical })
+}
+
+// A release read cleanly: its headline, its dateline and the story after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    pub headline: Option<String>,
+    pub dateline: Option<String>,
+    pub body: String,
+}
+
+impl Release {
+    pub fn article(&self) -> Article {
+        let text = match &self.dateline {
+            Some(dateline) => format!("{} — {}", dateline, self.body),
+            None => self.body.clone(),
+        };
+        Article { title: self.headline.clone(), text }
+    }
+}
+
+// The release in a wire's page or a page syndicating it. The story is the wire's release
+// element where there is one, else the page's main article.
+pub fn parse(html: &str, wire: &Wire) -> Option<Release> {
+    let document = Html::parse_document(html);
+    let headings = Selector::parse("h1").unwrap();
+    let title = Selector::parse(r#"meta[property="og:title"][content]"#).unwrap();
+    let headline = document.select(&headings).map(text).find(|h| !h.is_empty())
+        .or_else(|| document.select(&title).next().and_then(|m| m.value().attr("content")).map(str::to_string));
+    let container = Selector::parse(wire.body_selector).unwrap();
+    let blocks = Selector::parse("p, li").unwrap();
+    // Pages syndicating a release rarely keep the wire's markup, but the story still
+    // starts at its dateline
+    let paragraphs: Vec<String> = match document.select(&container).next() {
+        Some(story) => story.select(&blocks).map(text).collect(),
+        None => match first_paragraphs(&document).position(|p| dateline().is_match(&p)) {
+            Some(start) => first_paragraphs(&document).skip(start).collect(),
+            None => readability::extract(html)?.text.lines().map(str::to_string).collect(),
+        },
+    };
+    let mut paragraphs = paragraphs.into_iter().filter(|p| !p.is_empty() && !p.starts_with(SYNDICATION_NOTE));
+    let first = paragraphs.next()?;
+    let (dateline, opening) = match dateline().captures(&first) {
+        Some(captures) => (Some(captures[1].to_string()), first[captures[0].len()..].to_string()),
+        None => (None, first),
+    };
+    let body = std::iter::once(opening).chain(paragraphs).collect::<Vec<_>>().join("\n\n");
+    Some(Release { headline, dateline, body })
+}
+
+// The release a changed page carries, read from the wire's own copy when the page names one
+// and from the page otherwise
+pub async fn follow(client: &reqwest::Client, config: &AppConfig, site: &Site, syndication: &Syndication, body: &str) -> Option<Release> {
+    let canonical = syndication.canonical.as_deref().filter(|url| *url != site.url);
+    if let Some(url) = canonical {
+        match scraper::fetch_text(client, config, site, url).await {
+            Ok(html) => match parse(&html, syndication.wire) {
+                Some(release) => return Some(release),
+                None => warn!(url, "The {} copy of a release had no story", syndication.wire.name),
+            },
+            Err(e) => warn!(url, "Fetching the {} copy of a release failed: {}", syndication.wire.name, e),
+        }
+    }
+    parse(body, syndication.wire)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_wire_releases_and_reads_them() {
+        let release = r#"<html><head><title>Acme Reports Third Quarter Results | Business Wire</title></head><body>
+            <h1>Acme Reports Third Quarter Results</h1>
+            <div class="bw-release-story">
+              <p>NEW YORK--(BUSINESS WIRE)--Acme Corp. (NYSE: ACME) today reported revenue of $1.2 billion.</p>
+              <p>“We had a strong quarter,” said the CEO.</p>
+            </div><p>Contacts: press@acme.example</p></body></html>"#;
+        let syndication = detect(release, "https://www.businesswire.com/news/home/20261014005123/en/").unwrap();
+        assert_eq!((syndication.wire.key, syndication.canonical), ("businesswire", None));
+        let parsed = parse(release, syndication.wire).unwrap();
+        assert_eq!(parsed.headline.as_deref(), Some("Acme Reports Third Quarter Results"));
+        assert_eq!(parsed.dateline.as_deref(), Some("NEW YORK--(BUSINESS WIRE)"));
+        assert_eq!(parsed.body, "Acme Corp. (NYSE: ACME) today reported revenue of $1.2 billion.\n\n“We had a strong quarter,” said the CEO.");
+
+        // An IR page syndicating a release, linking to the wire's copy
+        let ir = r#"<html><body><h1>Acme Announces FDA Approval</h1>
+            <p>CHICAGO, Oct. 14, 2026 /PRNewswire/ -- Acme today announced approval.</p>
+            <p><a href="https://www.prnewswire.com/news-releases/acme-announces-fda-approval-302270001.html">View original content</a></p></body></html>"#;
+        let syndication = detect(ir, "https://investors.acme.example/news/2026-10-14").unwrap();
+        assert_eq!(syndication.wire.key, "prnewswire");
+        assert_eq!(syndication.canonical.as_deref(), Some("https://www.prnewswire.com/news-releases/acme-announces-fda-approval-302270001.html"));
+        let parsed = parse(ir, syndication.wire).unwrap();
+        assert_eq!(parsed.dateline.as_deref(), Some("CHICAGO, Oct. 14, 2026 /PRNewswire/"));
+        assert_eq!(parsed.body, "Acme today announced approval.");
+        let canonical = r#"<link rel="canonical" href="https://www.globenewswire.com/news-release/2026/10/14/3001/0/en/Acme.html">"#;
+        assert_eq!(detect(canonical, "https://ir.acme.example/release").unwrap().wire.key, "globenewswire");
+        assert!(detect("<p>Acme announced results today.</p>", "https://ir.acme.example/").is_none());
+        assert_eq!(from_url("https://www.globenewswire.com/search").map(|w| w.name), Some("GlobeNewswire"));
+    }
+}
@@ -1,6 +1,5 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
     #[test]
     fn dummy() {
         assert_eq!(2+2,4);